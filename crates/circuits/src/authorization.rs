@@ -0,0 +1,111 @@
+//! Spend authorization for actions proved outside a `TransactionProof`
+//!
+//! Swaps, veNFT locks, gauge votes and bribe claims each spend a note or
+//! act for a veNFT in their own proof. As with `SpendProof`, the proof shows
+//! knowledge of `ak` and `nk`, which the full viewing key also holds, and
+//! reveals `rk = ak + [alpha]G`. An `AuthorizedProof` adds the signature
+//! under `rk` over the action's sighash, which only the spending key can
+//! produce; verification rejects the action without it.
+
+use std::marker::PhantomData;
+
+use privl1_crypto::halo2;
+use privl1_crypto::hash::DomainSeparatedHasher;
+use privl1_crypto::keys::{self, Signature, SpendingKey};
+use privl1_crypto::proof::{Halo2Proof, VerificationKey};
+use privl1_crypto::{CryptoError, Point, Scalar};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// A circuit whose proof spends a note under a re-randomized key `rk`
+pub trait SpendAuthorizing {
+    /// Domain of the sighash the spend authorization signs
+    const SIGHASH_DOMAIN: &'static str;
+    /// Public-input row of `rk.x`
+    const RK_X: usize;
+    /// Public-input row of `rk.y`
+    const RK_Y: usize;
+}
+
+/// Proof of an action that spends a note, with its spend authorization
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthorizedProof<C> {
+    /// The action's proof
+    pub proof: Halo2Proof,
+    /// Re-randomized spend validating key, as bound in the proof's inputs
+    pub rk: Point,
+    /// Signature over `sighash()` under `rk`
+    #[serde(default)]
+    pub spend_auth_sig: Option<Signature>,
+    #[serde(skip)]
+    circuit: PhantomData<C>,
+}
+
+impl<C: SpendAuthorizing> AuthorizedProof<C> {
+    /// Wrap an unsigned proof revealing `rk`
+    pub fn new(proof: Halo2Proof, rk: Point) -> Self {
+        Self {
+            proof,
+            rk,
+            spend_auth_sig: None,
+            circuit: PhantomData,
+        }
+    }
+
+    /// Message the spend authorization signature signs
+    ///
+    /// Commits to the proof, its public inputs and `rk`, but not to the
+    /// signature itself.
+    pub fn sighash(&self) -> [u8; 32] {
+        let mut hasher = DomainSeparatedHasher::new(C::SIGHASH_DOMAIN);
+        hasher.update(&self.proof.vk_id);
+        hasher.update(&(self.proof.proof.len() as u64).to_le_bytes());
+        hasher.update(&self.proof.proof);
+        hasher.update(&(self.proof.public_inputs.len() as u64).to_le_bytes());
+        for input in &self.proof.public_inputs {
+            hasher.update(&(input.len() as u64).to_le_bytes());
+            hasher.update(input);
+        }
+        hasher.update(&self.rk.to_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Sign the action with the key `rk` re-randomizes by `alpha`
+    ///
+    /// Fails if `rk` is not `ak + [alpha]G` for this key.
+    pub fn authorize(&mut self, key: &SpendingKey, alpha: &Scalar) -> Result<()> {
+        if keys::randomize_validating_key(&key.validating_key(), alpha) != self.rk {
+            return Err(CryptoError::InvalidKey.into());
+        }
+
+        self.spend_auth_sig = Some(key.sign_randomized(alpha, &self.sighash()));
+        Ok(())
+    }
+
+    /// Check the spend authorization signature over `sighash()`
+    pub fn is_authorized(&self) -> bool {
+        self.spend_auth_sig
+            .as_ref()
+            .is_some_and(|sig| sig.verify(&self.rk, &self.sighash()))
+    }
+
+    /// Check that `rk` is the key the proof's public inputs reveal
+    pub fn binds_rk(&self) -> bool {
+        let (x, y) = self.rk.coordinates();
+        let inputs = &self.proof.public_inputs;
+        inputs.get(C::RK_X) == Some(&halo2::encode_instance(&x))
+            && inputs.get(C::RK_Y) == Some(&halo2::encode_instance(&y))
+    }
+
+    /// Verify the proof and its spend authorization
+    ///
+    /// An unsigned action, or one signed under another key or over another
+    /// proof, is rejected even if the proof itself verifies.
+    pub fn verify(&self, vk: &VerificationKey) -> Result<bool> {
+        if !self.binds_rk() || !self.is_authorized() {
+            return Ok(false);
+        }
+        Ok(self.proof.verify(vk)?)
+    }
+}
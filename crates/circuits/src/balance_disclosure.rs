@@ -0,0 +1,471 @@
+//! Balance disclosure circuit
+//!
+//! Proves "my shielded balance of asset A is at least X" for a
+//! `DisclosureStatement::BalanceAtLeast`. For public `(anchor, asset,
+//! threshold, context, nf × MAX_BALANCE_NOTES)`, the circuit checks, for each
+//! note slot:
+//! - `cm = Poseidon(value, asset, owner.x, owner.y, rcm)` with one
+//!   `owner = ak + [nk]N` for every slot
+//! - `cm` is a leaf of the tree with root `anchor`, unless `value = 0`
+//! - `nf = Poseidon(nk, cm, position)`
+//!
+//! and that the values sum to at least `threshold` without u64 overflow.
+//!
+//! The verifier checks the nullifiers are distinct and unspent, so the notes
+//! are still held. Revealing them links the notes to their later spends; the
+//! values and the owner stay private. Unused slots hold zero-value notes
+//! outside the tree, whose nullifiers are never spent.
+
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::disclosure::{DisclosureProof, DisclosureStatement, MAX_BALANCE_NOTES};
+use privl1_crypto::halo2;
+use privl1_crypto::keys::FullKeys;
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::Note;
+use privl1_crypto::nullifier::Nullifier;
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::gadgets::{assign_free_advice, MerklePathConfig, U64Config};
+use crate::note::NoteConfig;
+use crate::spend::{authentication_path, merkle_root};
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 14;
+
+/// Name the balance verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "disclose-balance-v1";
+
+/// Version of the balance circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows, in `DisclosureStatement::public_inputs` order
+pub const ANCHOR: usize = 0;
+pub const ASSET: usize = 1;
+pub const THRESHOLD: usize = 2;
+pub const CONTEXT: usize = 3;
+/// First nullifier; slot `i` uses row `NULLIFIERS + i`
+pub const NULLIFIERS: usize = 4;
+
+/// Configuration of the balance circuit
+#[derive(Clone, Debug)]
+pub struct BalanceConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+/// The balance circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct BalanceCircuit {
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    values: [Value<u64>; MAX_BALANCE_NOTES],
+    rcms: [Value<pallas::Base>; MAX_BALANCE_NOTES],
+    paths: [Value<[pallas::Base; TREE_DEPTH]>; MAX_BALANCE_NOTES],
+    positions: [Value<u64>; MAX_BALANCE_NOTES],
+}
+
+impl Circuit<pallas::Base> for BalanceCircuit {
+    type Config = BalanceConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> BalanceConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        BalanceConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: BalanceConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        let arith = &config.arith;
+        note.load(&mut layouter)?;
+
+        let anchor = note.public_base(layouter.namespace(|| "anchor"), ANCHOR)?;
+        let asset = note.public_base(layouter.namespace(|| "asset"), ASSET)?;
+        let threshold = note.public_amount(layouter.namespace(|| "threshold"), THRESHOLD)?;
+        note.public_base(layouter.namespace(|| "context"), CONTEXT)?;
+
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+
+        let mut total = arith.constant(layouter.namespace(|| "no balance"), 0)?;
+        for i in 0..MAX_BALANCE_NOTES {
+            let mut layouter = layouter.namespace(|| format!("note {}", i));
+
+            let value = arith.witness(layouter.namespace(|| "value"), self.values[i])?;
+            let rcm =
+                assign_free_advice(layouter.namespace(|| "rcm"), note.advices[2], self.rcms[i])?;
+            let cm = note.note_commitment(
+                layouter.namespace(|| "cm"),
+                value.clone(),
+                asset.clone(),
+                &owner,
+                rcm,
+            )?;
+
+            // Notes holding value are in the tree
+            let (root, position) = config.merkle.calculate_root(
+                layouter.namespace(|| "merkle path"),
+                cm.clone(),
+                self.paths[i],
+                self.positions[i],
+            )?;
+            arith.assert_equal_unless_zero(
+                layouter.namespace(|| "root = anchor"),
+                &value,
+                &root,
+                &anchor,
+            )?;
+
+            let nf =
+                note.nullifier(layouter.namespace(|| "nullifier"), nk.clone(), cm, position)?;
+            layouter.constrain_instance(nf.cell(), note.primary, NULLIFIERS + i)?;
+
+            total = arith.checked_add(layouter.namespace(|| "total"), &total, &value)?;
+        }
+        arith.assert_le(
+            layouter.namespace(|| "threshold <= total"),
+            &threshold,
+            &total,
+        )
+    }
+}
+
+/// One note slot of a balance disclosure
+#[derive(Clone, Debug)]
+struct Slot {
+    value: u64,
+    rcm: pallas::Base,
+    cm: pallas::Base,
+    path: [pallas::Base; TREE_DEPTH],
+    position: u64,
+    nullifier: Nullifier,
+}
+
+impl Slot {
+    fn new(note: &Note, keys: &FullKeys, path: [pallas::Base; TREE_DEPTH], position: u64) -> Self {
+        Self {
+            value: note.value(),
+            rcm: note.rcm(),
+            cm: note.commitment().to_field(),
+            path,
+            position,
+            nullifier: keys.nullifier.derive_nullifier(note, position),
+        }
+    }
+}
+
+/// Private inputs of a balance disclosure
+#[derive(Clone, Debug)]
+pub struct BalanceWitness {
+    ak: NativePoint,
+    nk: pallas::Base,
+    asset_id: [u8; 32],
+    threshold: u64,
+    anchor: MerkleRoot,
+    slots: [Slot; MAX_BALANCE_NOTES],
+    context: [u8; 32],
+}
+
+impl BalanceWitness {
+    /// Build the witness disclosing that `notes`, each with its Merkle proof
+    /// under one anchor, hold at least `threshold`
+    ///
+    /// Fails if there are no notes or more than `MAX_BALANCE_NOTES`, the keys
+    /// do not own them all, they differ in asset or anchor, a note repeats, or
+    /// they hold less than `threshold`.
+    pub fn new<R: RngCore>(
+        notes: &[(Note, MerkleProof)],
+        keys: &FullKeys,
+        threshold: u64,
+        context: [u8; 32],
+        rng: &mut R,
+    ) -> Result<Self> {
+        let Some((first, first_proof)) = notes.first() else {
+            return Err(CircuitError::InvalidParameters(
+                "No notes to disclose".into(),
+            ));
+        };
+        if notes.len() > MAX_BALANCE_NOTES {
+            return Err(CircuitError::InvalidParameters(format!(
+                "At most {} notes per disclosure",
+                MAX_BALANCE_NOTES
+            )));
+        }
+        let asset_id = *first.asset_id();
+        let anchor = merkle_root(
+            first.commitment().to_field(),
+            &authentication_path(first_proof)?,
+            first_proof.position,
+        );
+
+        let mut slots = Vec::with_capacity(MAX_BALANCE_NOTES);
+        for (note, merkle_proof) in notes {
+            if note.owner() != &keys.public {
+                return Err(CircuitError::InvalidParameters(
+                    "Note is not owned by these keys".into(),
+                ));
+            }
+            if note.asset_id() != &asset_id {
+                return Err(CircuitError::InvalidParameters(
+                    "Notes differ in asset".into(),
+                ));
+            }
+            let slot = Slot::new(
+                note,
+                keys,
+                authentication_path(merkle_proof)?,
+                merkle_proof.position,
+            );
+            if merkle_root(slot.cm, &slot.path, slot.position) != anchor {
+                return Err(CircuitError::InvalidParameters(
+                    "Notes differ in anchor".into(),
+                ));
+            }
+            if slots
+                .iter()
+                .any(|other: &Slot| other.nullifier == slot.nullifier)
+            {
+                return Err(CircuitError::InvalidParameters(
+                    "Note disclosed twice".into(),
+                ));
+            }
+            slots.push(slot);
+        }
+
+        slots
+            .iter()
+            .try_fold(0u64, |total, slot| total.checked_add(slot.value))
+            .filter(|total| *total >= threshold)
+            .ok_or_else(|| {
+                CircuitError::InvalidParameters("Notes hold less than the threshold".into())
+            })?;
+
+        // Pad with zero-value notes outside the tree
+        while slots.len() < MAX_BALANCE_NOTES {
+            let dummy = Note::with_randomness(0, keys.public, asset_id, Scalar::random(rng));
+            slots.push(Slot::new(
+                &dummy,
+                keys,
+                [pallas::Base::zero(); TREE_DEPTH],
+                0,
+            ));
+        }
+
+        Ok(Self {
+            ak: keys.spending.validating_key(),
+            nk: keys.nullifier.to_base(),
+            asset_id,
+            threshold,
+            anchor,
+            slots: slots.try_into().expect("padded to MAX_BALANCE_NOTES"),
+            context,
+        })
+    }
+
+    /// The disclosed statement
+    pub fn statement(&self) -> DisclosureStatement {
+        DisclosureStatement::BalanceAtLeast {
+            anchor: self.anchor,
+            asset_id: self.asset_id,
+            threshold: self.threshold,
+            nullifiers: self.slots.clone().map(|slot| slot.nullifier),
+        }
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        self.statement()
+            .public_inputs(&self.context)
+            .expect("Poseidon output is canonical")
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> BalanceCircuit {
+        BalanceCircuit {
+            ak: Value::known(self.ak.inner().to_affine()),
+            nk: Value::known(self.nk),
+            values: self.slots.clone().map(|slot| Value::known(slot.value)),
+            rcms: self.slots.clone().map(|slot| Value::known(slot.rcm)),
+            paths: self.slots.clone().map(|slot| Value::known(slot.path)),
+            positions: self.slots.clone().map(|slot| Value::known(slot.position)),
+        }
+    }
+}
+
+crate::keystore::circuit_keys!("balance", BalanceCircuit, BalanceProvingKey);
+
+/// Prove a balance
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &BalanceProvingKey,
+    witness: &BalanceWitness,
+    rng: R,
+) -> Result<DisclosureProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(DisclosureProof::new(
+        witness.statement(),
+        witness.context,
+        Halo2Proof::new(
+            proof,
+            instances.iter().map(halo2::encode_instance).collect(),
+            key.verification_key().id(),
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use rand::rngs::OsRng;
+
+    /// Notes of `values` owned by one key, all in one tree
+    fn holdings(values: &[u64]) -> (FullKeys, Vec<(Note, MerkleProof)>) {
+        let keys = FullKeys::random(&mut OsRng);
+        let notes: Vec<_> = values
+            .iter()
+            .map(|value| Note::new_with_owner(*value, keys.public, *AssetId::NATIVE.as_bytes()))
+            .collect();
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append([0u8; 32]).unwrap();
+        let positions: Vec<_> = notes
+            .iter()
+            .map(|note| {
+                tree.append(note.commitment().leaf()).unwrap();
+                tree.mark().unwrap()
+            })
+            .collect();
+        let notes = notes
+            .into_iter()
+            .zip(positions)
+            .map(|(note, position)| (note, tree.prove(position).unwrap()))
+            .collect();
+        (keys, notes)
+    }
+
+    #[test]
+    fn test_balance_circuit() {
+        let (keys, notes) = holdings(&[300, 500, 200, 1]);
+        for (count, threshold) in [(1, 300), (2, 800), (4, 1_001), (4, 0)] {
+            let witness =
+                BalanceWitness::new(&notes[..count], &keys, threshold, [7u8; 32], &mut OsRng)
+                    .unwrap();
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_invalid_holdings_rejected() {
+        let (keys, notes) = holdings(&[300, 500]);
+        let disclose = |notes: &[(Note, MerkleProof)], keys: &FullKeys, threshold| {
+            BalanceWitness::new(notes, keys, threshold, [7u8; 32], &mut OsRng)
+        };
+        assert!(disclose(&notes, &keys, 801).is_err());
+        assert!(disclose(&[], &keys, 0).is_err());
+        assert!(disclose(&[notes[0].clone(), notes[0].clone()], &keys, 0).is_err());
+        assert!(disclose(&notes, &FullKeys::random(&mut OsRng), 0).is_err());
+
+        // A note from another tree
+        let (_, other) = holdings(&[400]);
+        let stray = Note::new_with_owner(400, keys.public, *AssetId::NATIVE.as_bytes());
+        assert!(disclose(&[notes[0].clone(), (stray, other[0].1.clone())], &keys, 0).is_err());
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let (keys, notes) = holdings(&[300, 500]);
+        let witness = BalanceWitness::new(&notes, &keys, 800, [7u8; 32], &mut OsRng).unwrap();
+
+        let rows = [ANCHOR, ASSET, THRESHOLD]
+            .into_iter()
+            .chain((0..MAX_BALANCE_NOTES).map(|i| NULLIFIERS + i));
+        for row in rows {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_outside_tree_rejected() {
+        let (keys, notes) = holdings(&[300, 500]);
+        let mut witness = BalanceWitness::new(&notes, &keys, 800, [7u8; 32], &mut OsRng).unwrap();
+
+        // Fill a padding slot with a valuable note that was never appended
+        let fake = Note::new_with_owner(1_000, keys.public, *AssetId::NATIVE.as_bytes());
+        let (path, position) = (witness.slots[0].path, witness.slots[0].position);
+        witness.slots[2] = Slot::new(&fake, &keys, path, position);
+        witness.threshold = 1_800;
+        assert!(
+            !mock_failures(&witness.circuit(), vec![witness.public_inputs()], K)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (keys, notes) = holdings(&[300, 500]);
+        let witness = BalanceWitness::new(&notes, &keys, 750, [7u8; 32], &mut OsRng).unwrap();
+        let key = keygen().unwrap();
+
+        let disclosure = prove(&key, &witness, OsRng).unwrap();
+        assert!(disclosure.verify(key.verification_key()).unwrap());
+
+        // Claiming a higher threshold fails
+        let mut inflated = disclosure.clone();
+        if let DisclosureStatement::BalanceAtLeast { threshold, .. } = &mut inflated.statement {
+            *threshold = 900;
+        }
+        inflated.proof.public_inputs[THRESHOLD] = halo2::encode_instance(&pallas::Base::from(900));
+        assert!(!inflated.verify(key.verification_key()).unwrap());
+    }
+}
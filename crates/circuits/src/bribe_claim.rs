@@ -0,0 +1,681 @@
+//! Private bribe-claim circuit
+//!
+//! Ports `Bribe.sol`'s `claim` (see `ROADMAP.md`): a voter who gave a pool
+//! `weight` of the epoch's `total` votes claims `weight × bribe / total` into
+//! a fresh shielded note, without revealing which vote it was. For public
+//! `(anchor, epoch, pool, total, bribe, asset, cnf, rk, cm_out)`, the circuit
+//! checks:
+//! - `receipt = Poseidon(VOTE_RECEIPT_DOMAIN, vnf, pool, weight)` is a leaf of
+//!   the receipt tree with root `anchor`, as the gauge pallet appends it for
+//!   an accepted vote
+//! - `vnf = Poseidon(VOTE_NULLIFIER_DOMAIN, nk, ve_cm, epoch)` for a veNFT leaf
+//!   `ve_cm = Poseidon(nft_id, owner.x, owner.y, vp.x, vp.y)` with
+//!   `owner = ak + [nk]N`, so only the NFT's owner can claim
+//! - `cnf = Poseidon(CLAIM_NULLIFIER_DOMAIN, nk, receipt)`, as
+//!   `NullifierDerivingKey::derive_claim_nullifier` computes it
+//! - `weight <= total` and `cm_out` commits to `floor(weight × bribe / total)`
+//!   of `asset`, computed over the full 128-bit product
+//! - `rk = ak + [alpha]G`, the key the claim authorization is checked against
+//!
+//! The vote nullifier and weight stay private, so a claim cannot be linked to
+//! its vote. The bribe pallet rejects a second claim with the same `cnf`. A
+//! `BribeClaimProof` only verifies once signed under `rk` (see
+//! [`crate::authorization`]).
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::{NonIdentityPoint, Point};
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::halo2;
+use privl1_crypto::keys::{self, FullKeys, PublicKey};
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::{asset_base, Note};
+use privl1_crypto::nullifier::{Nullifier, CLAIM_NULLIFIER_DOMAIN, VOTE_NULLIFIER_DOMAIN};
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::authorization::{AuthorizedProof, SpendAuthorizing};
+use crate::gadgets::{self, assign_free_advice, MerklePathConfig, U64Config};
+use crate::gauge_vote::{vote_receipt, GaugeVote, VOTE_RECEIPT_DOMAIN};
+use crate::note::NoteConfig;
+use crate::spend::{authentication_path, merkle_root};
+use crate::ve_lock::VeNft;
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 13;
+
+/// Name the claim verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "bribe-claim-v1";
+
+/// Version of the claim circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows
+pub const ANCHOR: usize = 0;
+pub const EPOCH: usize = 1;
+pub const POOL: usize = 2;
+pub const TOTAL_WEIGHT: usize = 3;
+pub const BRIBE: usize = 4;
+pub const ASSET: usize = 5;
+pub const CLAIM_NULLIFIER: usize = 6;
+pub const RK_X: usize = 7;
+pub const RK_Y: usize = 8;
+pub const CM_OUT: usize = 9;
+
+/// A bribe deposited for a pool's voters in an epoch
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bribe {
+    /// Pool whose voters share the bribe
+    pub pool: u32,
+    /// Epoch of the votes
+    pub epoch: u64,
+    /// Asset of the bribe
+    pub asset_id: [u8; 32],
+    /// Deposited amount
+    pub amount: u64,
+    /// Total weight voted for the pool in the epoch
+    pub total_weight: u64,
+}
+
+/// Share of `bribe` owed to `weight` of its pool's votes, as `Bribe.sol`'s
+/// `claimable` computes it
+///
+/// `None` if no weight was voted or `weight` exceeds the total.
+pub fn claimable(weight: u64, bribe: &Bribe) -> Option<u64> {
+    if bribe.total_weight == 0 || weight > bribe.total_weight {
+        return None;
+    }
+    Some((weight as u128 * bribe.amount as u128 / bribe.total_weight as u128) as u64)
+}
+
+/// Configuration of the claim circuit
+#[derive(Clone, Debug)]
+pub struct BribeClaimConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+/// The claim circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct BribeClaimCircuit {
+    nft_id: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    vp_x: Value<pallas::Base>,
+    vp_y: Value<pallas::Base>,
+    weight: Value<u64>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+    alpha: Value<pallas::Scalar>,
+    recipient: Value<pallas::Affine>,
+    rcm_out: Value<pallas::Base>,
+}
+
+impl Circuit<pallas::Base> for BribeClaimCircuit {
+    type Config = BribeClaimConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> BribeClaimConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        BribeClaimConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: BribeClaimConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        let arith = &config.arith;
+        note.load(&mut layouter)?;
+
+        let epoch = note.public_amount(layouter.namespace(|| "epoch"), EPOCH)?;
+        let pool = note.public_base(layouter.namespace(|| "pool"), POOL)?;
+        let total = note.public_amount(layouter.namespace(|| "total weight"), TOTAL_WEIGHT)?;
+        let bribe = note.public_amount(layouter.namespace(|| "bribe"), BRIBE)?;
+        let asset = note.public_base(layouter.namespace(|| "asset"), ASSET)?;
+
+        // Recompute the vote nullifier from the owner's veNFT leaf
+        let nft_id = assign_free_advice(
+            layouter.namespace(|| "nft_id"),
+            note.advices[0],
+            self.nft_id,
+        )?;
+        let vp_x = assign_free_advice(layouter.namespace(|| "vp.x"), note.advices[1], self.vp_x)?;
+        let vp_y = assign_free_advice(layouter.namespace(|| "vp.y"), note.advices[2], self.vp_y)?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let ve_cm = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "ve_cm"),
+            [nft_id, owner.inner().x(), owner.inner().y(), vp_x, vp_y],
+        )?;
+        let vote_domain =
+            arith.constant(layouter.namespace(|| "vote domain"), VOTE_NULLIFIER_DOMAIN)?;
+        let vnf = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "vote nullifier"),
+            [vote_domain, nk.clone(), ve_cm, epoch],
+        )?;
+
+        // The vote's receipt is in the receipt tree
+        let weight = arith.witness(layouter.namespace(|| "weight"), self.weight)?;
+        let receipt_domain =
+            arith.constant(layouter.namespace(|| "receipt domain"), VOTE_RECEIPT_DOMAIN)?;
+        let receipt = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "receipt"),
+            [receipt_domain, vnf, pool, weight.clone()],
+        )?;
+        let (anchor, _) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            receipt.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+
+        // One claim per receipt
+        let claim_domain = arith.constant(
+            layouter.namespace(|| "claim domain"),
+            CLAIM_NULLIFIER_DOMAIN,
+        )?;
+        let cnf = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "claim nullifier"),
+            [claim_domain, nk, receipt],
+        )?;
+        layouter.constrain_instance(cnf.cell(), note.primary, CLAIM_NULLIFIER)?;
+
+        let rk = note.randomize_key(layouter.namespace(|| "rk"), &ak, self.alpha)?;
+        note.expose_point(&mut layouter, &rk, RK_X)?;
+
+        // Pay out weight * bribe / total
+        arith.assert_le(layouter.namespace(|| "weight <= total"), &weight, &total)?;
+        let (payout, _) =
+            arith.mul_div(layouter.namespace(|| "payout"), &weight, &bribe, &total)?;
+
+        let recipient = Point::new(
+            note.ecc_chip(),
+            layouter.namespace(|| "recipient"),
+            self.recipient,
+        )?;
+        let rcm_out = assign_free_advice(
+            layouter.namespace(|| "rcm_out"),
+            note.advices[2],
+            self.rcm_out,
+        )?;
+        let cm_out = note.note_commitment(
+            layouter.namespace(|| "cm_out"),
+            payout,
+            asset,
+            &recipient,
+            rcm_out,
+        )?;
+        layouter.constrain_instance(cm_out.cell(), note.primary, CM_OUT)
+    }
+}
+
+/// Private inputs of a claim
+#[derive(Clone, Debug)]
+pub struct BribeClaimWitness {
+    nft: VeNft,
+    ak: NativePoint,
+    nk: pallas::Base,
+    bribe: Bribe,
+    weight: u64,
+    path: [pallas::Base; TREE_DEPTH],
+    position: u64,
+    alpha: Scalar,
+    payout: Note,
+    receipt: pallas::Base,
+    claim_nullifier: Nullifier,
+}
+
+impl BribeClaimWitness {
+    /// Build the witness for claiming `bribe` for `nft`'s vote of `weight`,
+    /// paying `recipient`
+    ///
+    /// `receipt_proof` is the path of the vote's receipt in the receipt tree.
+    /// Fails if the keys do not own the NFT or nothing is claimable.
+    pub fn new<R: RngCore>(
+        nft: &VeNft,
+        keys: &FullKeys,
+        bribe: &Bribe,
+        weight: u64,
+        receipt_proof: &MerkleProof,
+        recipient: PublicKey,
+        rng: &mut R,
+    ) -> Result<Self> {
+        if nft.owner != keys.public {
+            return Err(CircuitError::InvalidParameters(
+                "veNFT is not owned by these keys".into(),
+            ));
+        }
+        let payout = claimable(weight, bribe).ok_or_else(|| {
+            CircuitError::InvalidParameters("Weight exceeds the pool's votes".into())
+        })?;
+
+        let vote_nullifier = keys
+            .nullifier
+            .derive_vote_nullifier(nft.commitment(), bribe.epoch);
+        let receipt = vote_receipt(
+            &vote_nullifier,
+            &GaugeVote {
+                pool: bribe.pool,
+                weight,
+            },
+        );
+
+        Ok(Self {
+            nft: nft.clone(),
+            ak: keys.spending.validating_key(),
+            nk: keys.nullifier.to_base(),
+            bribe: bribe.clone(),
+            weight,
+            path: authentication_path(receipt_proof)?,
+            position: receipt_proof.position,
+            alpha: Scalar::random(rng),
+            payout: Note::with_randomness(payout, recipient, bribe.asset_id, Scalar::random(rng)),
+            receipt,
+            claim_nullifier: keys.nullifier.derive_claim_nullifier(receipt),
+        })
+    }
+
+    /// Root of the receipt tree the receipt's authentication path hashes to
+    pub fn anchor(&self) -> MerkleRoot {
+        merkle_root(self.receipt, &self.path, self.position)
+    }
+
+    /// Receipt being claimed
+    pub fn receipt(&self) -> pallas::Base {
+        self.receipt
+    }
+
+    /// Nullifier of this receipt's claim
+    pub fn claim_nullifier(&self) -> Nullifier {
+        self.claim_nullifier
+    }
+
+    /// Note paying out the claimed share
+    pub fn payout(&self) -> &Note {
+        &self.payout
+    }
+
+    /// Re-randomized key the claim authorization is checked against
+    pub fn rk(&self) -> NativePoint {
+        keys::randomize_validating_key(&self.ak, &self.alpha)
+    }
+
+    /// Randomizer of the spend key, needed to sign the claim authorization
+    pub fn alpha(&self) -> &Scalar {
+        &self.alpha
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let to_base = |bytes: &[u8; 32]| {
+            Option::from(pallas::Base::from_repr(*bytes)).expect("Poseidon output is canonical")
+        };
+        let (rk_x, rk_y) = self.rk().coordinates();
+
+        vec![
+            to_base(self.anchor().as_bytes()),
+            pallas::Base::from(self.bribe.epoch),
+            pallas::Base::from(self.bribe.pool as u64),
+            pallas::Base::from(self.bribe.total_weight),
+            pallas::Base::from(self.bribe.amount),
+            asset_base(&self.bribe.asset_id),
+            to_base(self.claim_nullifier.as_bytes()),
+            rk_x,
+            rk_y,
+            self.payout.commitment().to_field(),
+        ]
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> BribeClaimCircuit {
+        let (vp_x, vp_y) = self.nft.voting_power_commit().as_point().coordinates();
+
+        BribeClaimCircuit {
+            nft_id: Value::known(pallas::Base::from(self.nft.nft_id)),
+            ak: Value::known(self.ak.inner().to_affine()),
+            nk: Value::known(self.nk),
+            vp_x: Value::known(vp_x),
+            vp_y: Value::known(vp_y),
+            weight: Value::known(self.weight),
+            path: Value::known(self.path),
+            position: Value::known(self.position),
+            alpha: Value::known(*self.alpha.inner()),
+            recipient: Value::known(self.payout.owner().as_point().inner().to_affine()),
+            rcm_out: Value::known(self.payout.rcm()),
+        }
+    }
+}
+
+impl SpendAuthorizing for BribeClaimCircuit {
+    const SIGHASH_DOMAIN: &'static str = "PRIVL1_BRIBE_CLAIM_SIGHASH";
+    const RK_X: usize = RK_X;
+    const RK_Y: usize = RK_Y;
+}
+
+/// Claim proof with the voter's spend authorization
+pub type BribeClaimProof = AuthorizedProof<BribeClaimCircuit>;
+
+crate::keystore::circuit_keys!("claim", BribeClaimCircuit, BribeClaimProvingKey);
+
+/// Prove a claim
+///
+/// The proof is unsigned; `BribeClaimProof::authorize` it with the veNFT
+/// owner's spending key and `witness.alpha()`.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &BribeClaimProvingKey,
+    witness: &BribeClaimWitness,
+    rng: R,
+) -> Result<BribeClaimProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    let proof = Halo2Proof::new(
+        proof,
+        instances.iter().map(halo2::encode_instance).collect(),
+        key.verification_key().id(),
+    );
+    Ok(BribeClaimProof::new(proof, witness.rk()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use crate::gauge_vote::GaugeVoteWitness;
+    use ff::Field;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use rand::rngs::OsRng;
+
+    const EPOCH_7: u64 = 7;
+
+    fn bribe(amount: u64, total_weight: u64) -> Bribe {
+        Bribe {
+            pool: 1,
+            epoch: EPOCH_7,
+            asset_id: *AssetId::NATIVE.as_bytes(),
+            amount,
+            total_weight,
+        }
+    }
+
+    /// A vote of `weight` for pool 1, with its receipt in a receipt tree
+    fn voted(weight: u64) -> (FullKeys, VeNft, MerkleProof) {
+        let keys = FullKeys::random(&mut OsRng);
+        let nft = VeNft {
+            nft_id: 42,
+            owner: keys.public,
+            voting_power: u64::MAX,
+            blinding: Scalar::random(&mut OsRng),
+        };
+
+        let mut ve_tree = IncrementalMerkleTree::new();
+        let position = ve_tree.append(nft.leaf()).unwrap();
+        let votes = [
+            GaugeVote { pool: 2, weight: 1 },
+            GaugeVote { pool: 1, weight },
+        ];
+        let vote = GaugeVoteWitness::new(
+            &nft,
+            &ve_tree.prove(position).unwrap(),
+            &keys,
+            EPOCH_7,
+            &votes,
+            &mut OsRng,
+        )
+        .unwrap();
+
+        let mut receipts = IncrementalMerkleTree::new();
+        let positions: Vec<_> = vote
+            .receipts()
+            .iter()
+            .map(|receipt| {
+                receipts.append(receipt.to_repr()).unwrap();
+                receipts.mark().unwrap()
+            })
+            .collect();
+        (keys, nft, receipts.prove(positions[1]).unwrap())
+    }
+
+    fn claim_witness(weight: u64, bribe: &Bribe) -> Result<BribeClaimWitness> {
+        let (keys, nft, receipt_proof) = voted(weight);
+        let recipient = FullKeys::random(&mut OsRng).public;
+        BribeClaimWitness::new(
+            &nft,
+            &keys,
+            bribe,
+            weight,
+            &receipt_proof,
+            recipient,
+            &mut OsRng,
+        )
+    }
+
+    #[test]
+    fn test_claimable() {
+        assert_eq!(claimable(250, &bribe(1_000, 1_000)), Some(250));
+        assert_eq!(claimable(1, &bribe(10, 3)), Some(3));
+        assert_eq!(
+            claimable(u64::MAX, &bribe(u64::MAX, u64::MAX)),
+            Some(u64::MAX)
+        );
+        assert_eq!(claimable(0, &bribe(1_000, 1_000)), Some(0));
+        assert_eq!(claimable(1, &bribe(1_000, 0)), None);
+        assert_eq!(claimable(2, &bribe(1_000, 1)), None);
+    }
+
+    #[test]
+    fn test_claim_circuit() {
+        for (weight, bribe) in [
+            (250, bribe(1_000, 1_000)),
+            (1, bribe(10, 3)),
+            (u64::MAX / 2, bribe(u64::MAX, u64::MAX)),
+        ] {
+            let witness = claim_witness(weight, &bribe).unwrap();
+            assert_eq!(witness.payout().value(), claimable(weight, &bribe).unwrap());
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        // With all of the weight the payout is the bribe, so no change rounds away
+        let witness = claim_witness(1_000, &bribe(1_000, 1_000)).unwrap();
+
+        for row in [
+            ANCHOR,
+            EPOCH,
+            POOL,
+            TOTAL_WEIGHT,
+            BRIBE,
+            ASSET,
+            CLAIM_NULLIFIER,
+            RK_X,
+            RK_Y,
+            CM_OUT,
+        ] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_one_claim_per_receipt() {
+        let (keys, nft, receipt_proof) = voted(250);
+        let bribe = bribe(1_000, 1_000);
+        let claim = |recipient| {
+            BribeClaimWitness::new(
+                &nft,
+                &keys,
+                &bribe,
+                250,
+                &receipt_proof,
+                recipient,
+                &mut OsRng,
+            )
+            .unwrap()
+        };
+
+        // Claiming again, even to another recipient, reveals the same nullifier
+        let first = claim(keys.public);
+        let second = claim(FullKeys::random(&mut OsRng).public);
+        assert_eq!(first.claim_nullifier(), second.claim_nullifier());
+        assert_eq!(first.anchor(), second.anchor());
+        assert_ne!(first.payout().commitment(), second.payout().commitment());
+
+        // The claim reveals neither the vote nullifier nor the receipt
+        let vnf = keys
+            .nullifier
+            .derive_vote_nullifier(nft.commitment(), EPOCH_7);
+        let instances = first.public_inputs();
+        let vnf = Option::from(pallas::Base::from_repr(*vnf.as_bytes())).unwrap();
+        assert!(!instances.contains(&vnf));
+        assert!(!instances.contains(&first.receipt()));
+    }
+
+    #[test]
+    fn test_inflated_claim_rejected() {
+        // More weight than was voted has no receipt under the real anchor
+        let (keys, nft, receipt_proof) = voted(250);
+        let claim = |weight| {
+            BribeClaimWitness::new(
+                &nft,
+                &keys,
+                &bribe(1_000, 1_000),
+                weight,
+                &receipt_proof,
+                keys.public,
+                &mut OsRng,
+            )
+            .unwrap()
+        };
+        let witness = claim(251);
+        let mut instances = witness.public_inputs();
+        instances[ANCHOR] = claim(250).public_inputs()[ANCHOR];
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // A larger payout than the share
+        let witness = claim_witness(250, &bribe(1_000, 1_000)).unwrap();
+        let mut circuit = witness.circuit();
+        let inflated = Note::with_randomness(
+            251,
+            *witness.payout().owner(),
+            *witness.payout().asset_id(),
+            Scalar::random(&mut OsRng),
+        );
+        circuit.rcm_out = Value::known(inflated.rcm());
+        let mut instances = witness.public_inputs();
+        instances[CM_OUT] = inflated.commitment().to_field();
+        assert!(!mock_failures(&circuit, vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // Another voter's receipt, or weight above the total
+        assert!(claim_witness(2, &bribe(1_000, 1)).is_err());
+        let other = FullKeys::random(&mut OsRng);
+        assert!(BribeClaimWitness::new(
+            &nft,
+            &other,
+            &bribe(1_000, 1_000),
+            250,
+            &receipt_proof,
+            other.public,
+            &mut OsRng
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let bribe = bribe(1_000, 1_000);
+        let (keys, nft, receipt_proof) = voted(250);
+        let recipient = FullKeys::random(&mut OsRng).public;
+        let witness = BribeClaimWitness::new(
+            &nft,
+            &keys,
+            &bribe,
+            250,
+            &receipt_proof,
+            recipient,
+            &mut OsRng,
+        )
+        .unwrap();
+        let key = keygen().unwrap();
+
+        let mut proof = prove(&key, &witness, OsRng).unwrap();
+        proof.authorize(&keys.spending, witness.alpha()).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Claiming a bribe of another pool fails
+        let mut tampered = proof.clone();
+        tampered.proof.public_inputs[POOL] = halo2::encode_instance(&pallas::Base::from(2));
+        assert!(!tampered.verify(key.verification_key()).unwrap());
+
+        // The proof alone does not authorize the claim: it is rejected with no
+        // signature or one under another key
+        let mut unsigned = proof.clone();
+        unsigned.spend_auth_sig = None;
+        let other = FullKeys::random(&mut OsRng);
+        let mut wrong_key = proof.clone();
+        wrong_key.spend_auth_sig = Some(
+            other
+                .spending
+                .sign_randomized(witness.alpha(), &proof.sighash()),
+        );
+        for rejected in [&unsigned, &wrong_key] {
+            assert!(rejected.proof.verify(key.verification_key()).unwrap());
+            assert!(!rejected.verify(key.verification_key()).unwrap());
+        }
+        assert!(unsigned
+            .authorize(&other.spending, witness.alpha())
+            .is_err());
+    }
+}
@@ -0,0 +1,552 @@
+//! Groth16 phase-2 trusted-setup ceremony (BGM17)
+//!
+//! Phase 2 specialises powers-of-tau parameters to one circuit. The ceremony
+//! only randomises δ: each participant multiplies δ by a secret δ' and divides
+//! the `L` and `H` queries by δ'. The final keys are sound as long as one
+//! participant destroyed their δ'.
+//!
+//! Workflow (all steps work offline on files exchanged by hand):
+//! 1. [`initialize`]: build δ = 1 parameters from a powers-of-tau file
+//! 2. [`contribute`]: each participant adds a contribution with a proof of
+//!    knowledge of δ' and records the returned hash
+//! 3. [`verify`]: anyone recomputes the initial parameters and checks every
+//!    contribution, getting back the list of contribution hashes
+//! 4. [`export`]: emit the final proving key and verification key
+//!
+//! Each contribution publishes `s`, `s·δ'` (G1) and `r·δ'` (G2), where `r` is
+//! hashed to G2 from the transcript so far. This binds the contribution to
+//! the history and proves δ' is known to the contributor.
+
+use std::io::{Read, Write};
+
+use ark_bls12_381::{g2, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::hashing::curve_maps::wb::WBMap;
+use ark_ec::hashing::map_to_curve_hasher::MapToCurveBasedHasher;
+use ark_ec::hashing::HashToCurve;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::field_hashers::DefaultFieldHasher;
+use ark_ff::{Field, UniformRand};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_relations::lc;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal, SynthesisError,
+    SynthesisMode, Variable,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use privl1_crypto::groth16::{self as crypto_groth16, Groth16ProvingKey};
+use privl1_crypto::hash::DomainSeparatedHasher;
+use privl1_crypto::proof::VerificationKey;
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+
+use crate::ptau::{merge_pairs, same_ratio, PowersOfTau};
+use crate::CircuitError;
+
+const MAGIC: &[u8; 13] = b"PRIVL1-PHASE2";
+const VERSION: u8 = 1;
+
+/// Domain for hashing the transcript to G2
+const HASH_TO_G2_DOMAIN: &[u8] = b"PRIVL1_GROTH16_PHASE2_R";
+
+/// One participant's contribution
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution {
+    /// `[δ]_1` after this contribution
+    pub delta_after: G1Affine,
+    /// Random `s` in G1
+    pub s: G1Affine,
+    /// `s·δ'`
+    pub s_delta: G1Affine,
+    /// `r·δ'`, where `r` is the transcript hashed to G2
+    pub r_delta: G2Affine,
+}
+
+/// Phase-2 parameters for one circuit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phase2Params {
+    /// Current proving key (includes the verifying key)
+    pub pk: Groth16ProvingKey,
+    /// Hash of the initial (δ = 1) parameters; identifies circuit and phase 1
+    pub initial_hash: [u8; 32],
+    /// Contributions so far, in order
+    pub contributions: Vec<Contribution>,
+}
+
+impl Phase2Params {
+    /// Hash of the latest contribution (or the initial hash if none)
+    pub fn transcript_hash(&self) -> [u8; 32] {
+        self.contributions
+            .iter()
+            .fold(self.initial_hash, |prev, c| contribution_hash(&prev, c))
+    }
+
+    /// Write in the phase-2 file format
+    pub fn write<W: Write>(&self, mut writer: W) -> crate::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&self.initial_hash)?;
+        self.pk
+            .serialize_compressed(&mut writer)
+            .and_then(|_| self.contributions.serialize_compressed(&mut writer))
+            .map_err(|e| CircuitError::SerializationError(e.to_string()))
+    }
+
+    /// Read a phase-2 file (points are validated)
+    pub fn read<R: Read>(mut reader: R) -> crate::Result<Self> {
+        let mut header = [0u8; 14];
+        reader.read_exact(&mut header)?;
+        if &header[..13] != MAGIC || header[13] != VERSION {
+            return Err(CircuitError::SerializationError(
+                "Not a PRIVL1 phase-2 file".into(),
+            ));
+        }
+
+        let mut initial_hash = [0u8; 32];
+        reader.read_exact(&mut initial_hash)?;
+
+        let pk = Groth16ProvingKey::deserialize_compressed(&mut reader)
+            .map_err(|e| CircuitError::SerializationError(e.to_string()))?;
+        let contributions = Vec::<Contribution>::deserialize_compressed(&mut reader)
+            .map_err(|e| CircuitError::SerializationError(e.to_string()))?;
+
+        Ok(Self {
+            pk,
+            initial_hash,
+            contributions,
+        })
+    }
+}
+
+/// Build the initial (δ = 1) parameters for a circuit
+///
+/// The QAP matches `ark-groth16` (libsnark reduction), so the exported keys
+/// work with its prover.
+pub fn initialize<C: ConstraintSynthesizer<Fr>>(
+    ptau: &PowersOfTau,
+    circuit: C,
+) -> crate::Result<Phase2Params> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit
+        .generate_constraints(cs.clone())
+        .map_err(|e| CircuitError::Synthesis(e.to_string()))?;
+    cs.finalize();
+
+    let matrices = cs
+        .to_matrices()
+        .ok_or_else(|| CircuitError::Synthesis("Constraint matrices unavailable".into()))?;
+    let num_inputs = matrices.num_instance_variables;
+    let num_variables = num_inputs + matrices.num_witness_variables;
+    let num_constraints = matrices.num_constraints;
+
+    let domain_size = (num_constraints + num_inputs).next_power_of_two();
+    let basis = ptau.lagrange(domain_size)?;
+
+    let mut a = vec![G1Projective::zero(); num_variables];
+    let mut b_g1 = vec![G1Projective::zero(); num_variables];
+    let mut b_g2 = vec![G2Projective::zero(); num_variables];
+    // β·A_j(τ) + α·B_j(τ) + C_j(τ) for every variable
+    let mut abc = vec![G1Projective::zero(); num_variables];
+
+    for (row, terms) in matrices.a.iter().enumerate() {
+        for (coeff, var) in terms {
+            a[*var] += basis.tau_g1[row] * coeff;
+            abc[*var] += basis.beta_tau_g1[row] * coeff;
+        }
+    }
+    // The reduction adds one row per input so the input polynomials are independent
+    for input in 0..num_inputs {
+        a[input] += basis.tau_g1[num_constraints + input];
+        abc[input] += basis.beta_tau_g1[num_constraints + input];
+    }
+    for (row, terms) in matrices.b.iter().enumerate() {
+        for (coeff, var) in terms {
+            b_g1[*var] += basis.tau_g1[row] * coeff;
+            b_g2[*var] += basis.tau_g2[row] * coeff;
+            abc[*var] += basis.alpha_tau_g1[row] * coeff;
+        }
+    }
+    for (row, terms) in matrices.c.iter().enumerate() {
+        for (coeff, var) in terms {
+            abc[*var] += basis.tau_g1[row] * coeff;
+        }
+    }
+
+    let abc = G1Projective::normalize_batch(&abc);
+    let vk = VerifyingKey {
+        alpha_g1: ptau.alpha_tau_g1[0],
+        beta_g2: ptau.beta_g2,
+        gamma_g2: G2Affine::generator(),
+        delta_g2: G2Affine::generator(),
+        gamma_abc_g1: abc[..num_inputs].to_vec(),
+    };
+    let pk = ProvingKey {
+        vk,
+        beta_g1: ptau.beta_tau_g1[0],
+        delta_g1: G1Affine::generator(),
+        a_query: G1Projective::normalize_batch(&a),
+        b_g1_query: G1Projective::normalize_batch(&b_g1),
+        b_g2_query: G2Projective::normalize_batch(&b_g2),
+        h_query: basis.h_query,
+        l_query: abc[num_inputs..].to_vec(),
+    };
+
+    let mut hasher = DomainSeparatedHasher::new("PRIVL1_GROTH16_PHASE2_INIT");
+    hasher.update(&serialize(&pk)?);
+
+    Ok(Phase2Params {
+        pk,
+        initial_hash: *hasher.finalize().as_bytes(),
+        contributions: Vec::new(),
+    })
+}
+
+/// Add a contribution, returning its hash for the participant to publish
+///
+/// The secret δ' is sampled from `rng` and dropped when this returns.
+pub fn contribute<R: RngCore + CryptoRng>(
+    params: &mut Phase2Params,
+    rng: &mut R,
+) -> crate::Result<[u8; 32]> {
+    let delta = loop {
+        let candidate = Fr::rand(rng);
+        if !candidate.is_zero() {
+            break candidate;
+        }
+    };
+    let delta_inv = delta.inverse().expect("nonzero");
+
+    let prev_hash = params.transcript_hash();
+    let s = G1Projective::rand(rng).into_affine();
+    let s_delta = (s * delta).into_affine();
+    let r = hash_to_g2(&prev_hash, &s, &s_delta)?;
+
+    let pk = &mut params.pk;
+    pk.delta_g1 = (pk.delta_g1 * delta).into_affine();
+    pk.vk.delta_g2 = (pk.vk.delta_g2 * delta).into_affine();
+
+    let l_query: Vec<G1Projective> = pk.l_query.iter().map(|p| *p * delta_inv).collect();
+    let h_query: Vec<G1Projective> = pk.h_query.iter().map(|p| *p * delta_inv).collect();
+    pk.l_query = G1Projective::normalize_batch(&l_query);
+    pk.h_query = G1Projective::normalize_batch(&h_query);
+
+    let contribution = Contribution {
+        delta_after: pk.delta_g1,
+        s,
+        s_delta,
+        r_delta: (r * delta).into_affine(),
+    };
+    let hash = contribution_hash(&prev_hash, &contribution);
+    params.contributions.push(contribution);
+
+    Ok(hash)
+}
+
+/// Verify a phase-2 transcript against the circuit and powers of tau
+///
+/// Returns the hash of every contribution, in order, so participants can
+/// check theirs is included.
+pub fn verify<C, R>(
+    ptau: &PowersOfTau,
+    circuit: C,
+    params: &Phase2Params,
+    rng: &mut R,
+) -> crate::Result<Vec<[u8; 32]>>
+where
+    C: ConstraintSynthesizer<Fr>,
+    R: RngCore + CryptoRng,
+{
+    let initial = initialize(ptau, circuit)?;
+    if params.initial_hash != initial.initial_hash {
+        return Err(CircuitError::VerificationFailed(
+            "Parameters are for a different circuit".into(),
+        ));
+    }
+
+    // Everything except δ, L and H is fixed by phase 1 and the circuit
+    let (pk, init) = (&params.pk, &initial.pk);
+    if pk.vk.alpha_g1 != init.vk.alpha_g1
+        || pk.vk.beta_g2 != init.vk.beta_g2
+        || pk.vk.gamma_g2 != init.vk.gamma_g2
+        || pk.vk.gamma_abc_g1 != init.vk.gamma_abc_g1
+        || pk.beta_g1 != init.beta_g1
+        || pk.a_query != init.a_query
+        || pk.b_g1_query != init.b_g1_query
+        || pk.b_g2_query != init.b_g2_query
+        || pk.l_query.len() != init.l_query.len()
+        || pk.h_query.len() != init.h_query.len()
+    {
+        return Err(CircuitError::VerificationFailed(
+            "Fixed key elements were modified".into(),
+        ));
+    }
+
+    let mut hashes = Vec::with_capacity(params.contributions.len());
+    let mut prev_hash = params.initial_hash;
+    let mut prev_delta = G1Affine::generator();
+    for (index, c) in params.contributions.iter().enumerate() {
+        let r = hash_to_g2(&prev_hash, &c.s, &c.s_delta)?;
+
+        // Proof of knowledge of δ', and δ_after = δ_before · δ'
+        if !same_ratio((c.s, c.s_delta), (r, c.r_delta))
+            || !same_ratio((prev_delta, c.delta_after), (r, c.r_delta))
+        {
+            return Err(CircuitError::VerificationFailed(format!(
+                "Invalid contribution {}",
+                index
+            )));
+        }
+
+        prev_hash = contribution_hash(&prev_hash, c);
+        prev_delta = c.delta_after;
+        hashes.push(prev_hash);
+    }
+
+    if pk.delta_g1 != prev_delta {
+        return Err(CircuitError::VerificationFailed(
+            "δ does not match the last contribution".into(),
+        ));
+    }
+    if !same_ratio(
+        (G1Affine::generator(), pk.delta_g1),
+        (G2Affine::generator(), pk.vk.delta_g2),
+    ) {
+        return Err(CircuitError::VerificationFailed(
+            "δ differs between G1 and G2".into(),
+        ));
+    }
+
+    // L and H were divided by the accumulated δ
+    let delta_ratio = (G2Affine::generator(), pk.vk.delta_g2);
+    let l_ok = pk.l_query.is_empty()
+        || same_ratio(merge_pairs(&pk.l_query, &init.l_query, rng), delta_ratio);
+    let h_ok = same_ratio(merge_pairs(&pk.h_query, &init.h_query, rng), delta_ratio);
+    if !l_ok || !h_ok {
+        return Err(CircuitError::VerificationFailed(
+            "L or H query not scaled by δ".into(),
+        ));
+    }
+
+    Ok(hashes)
+}
+
+/// Emit the final proving key and verification key
+///
+/// Refuses parameters without contributions, whose δ is publicly known.
+/// Run [`verify`] first; this does not re-check the transcript.
+pub fn export(params: &Phase2Params) -> crate::Result<(Groth16ProvingKey, VerificationKey)> {
+    if params.contributions.is_empty() {
+        return Err(CircuitError::InvalidParameters("No contributions".into()));
+    }
+
+    let vk = crypto_groth16::verification_key(&params.pk.vk)?;
+    Ok((params.pk.clone(), vk))
+}
+
+/// Groth16 circuits that can go through the ceremony
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CeremonyCircuit {
+    /// `x³ + x + 5 = y`, used for ceremony dry runs
+    Example,
+}
+
+impl CeremonyCircuit {
+    /// All circuits, for listing in the CLI
+    pub const ALL: &'static [CeremonyCircuit] = &[CeremonyCircuit::Example];
+
+    /// Look up a circuit by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.name() == name)
+    }
+
+    /// Circuit name
+    pub fn name(&self) -> &'static str {
+        match self {
+            CeremonyCircuit::Example => "example",
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for CeremonyCircuit {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<Fr>,
+    ) -> std::result::Result<(), SynthesisError> {
+        match self {
+            CeremonyCircuit::Example => ExampleCircuit::default().generate_constraints(cs),
+        }
+    }
+}
+
+/// Proves knowledge of `x` such that `x³ + x + 5 = y` for a public `y`
+#[derive(Clone, Debug, Default)]
+pub struct ExampleCircuit {
+    pub x: Option<Fr>,
+}
+
+impl ExampleCircuit {
+    /// The public output for a given `x`
+    pub fn output(x: Fr) -> Fr {
+        x.square() * x + x + Fr::from(5u64)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ExampleCircuit {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<Fr>,
+    ) -> std::result::Result<(), SynthesisError> {
+        let missing = || SynthesisError::AssignmentMissing;
+
+        let y = cs.new_input_variable(|| self.x.map(Self::output).ok_or_else(missing))?;
+        let x = cs.new_witness_variable(|| self.x.ok_or_else(missing))?;
+        let x2 = cs.new_witness_variable(|| self.x.map(|x| x.square()).ok_or_else(missing))?;
+        let x3 = cs.new_witness_variable(|| self.x.map(|x| x.square() * x).ok_or_else(missing))?;
+
+        cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x2)?;
+        cs.enforce_constraint(lc!() + x2, lc!() + x, lc!() + x3)?;
+        cs.enforce_constraint(
+            lc!() + x3 + x + (Fr::from(5u64), Variable::One),
+            lc!() + Variable::One,
+            lc!() + y,
+        )?;
+        Ok(())
+    }
+}
+
+/// Hash the transcript so far and a contribution's `(s, s·δ')` to G2
+fn hash_to_g2(prev_hash: &[u8; 32], s: &G1Affine, s_delta: &G1Affine) -> crate::Result<G2Affine> {
+    let mut message = prev_hash.to_vec();
+    message.extend_from_slice(&serialize(s)?);
+    message.extend_from_slice(&serialize(s_delta)?);
+
+    let hasher = MapToCurveBasedHasher::<
+        G2Projective,
+        DefaultFieldHasher<Sha256, 128>,
+        WBMap<g2::Config>,
+    >::new(HASH_TO_G2_DOMAIN)
+    .map_err(|e| CircuitError::InvalidParameters(format!("Hash to G2: {:?}", e)))?;
+
+    hasher
+        .hash(&message)
+        .map_err(|e| CircuitError::InvalidParameters(format!("Hash to G2: {:?}", e)))
+}
+
+/// Chain a contribution onto the transcript hash
+fn contribution_hash(prev_hash: &[u8; 32], contribution: &Contribution) -> [u8; 32] {
+    let mut hasher = DomainSeparatedHasher::new("PRIVL1_GROTH16_PHASE2_CONTRIBUTION");
+    hasher.update(prev_hash);
+    hasher.update(&serialize(contribution).expect("serializing into a Vec cannot fail"));
+    *hasher.finalize().as_bytes()
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T) -> crate::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value
+        .serialize_compressed(&mut bytes)
+        .map_err(|e| CircuitError::SerializationError(e.to_string()))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_381::Bls12_381;
+    use ark_groth16::Groth16;
+    use ark_snark::SNARK;
+    use privl1_crypto::proof::Groth16Proof;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    fn ceremony(contributors: usize) -> (PowersOfTau, Phase2Params) {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(3, &mut rng).unwrap();
+        let mut params = initialize(&ptau, CeremonyCircuit::Example).unwrap();
+        for _ in 0..contributors {
+            contribute(&mut params, &mut rng).unwrap();
+        }
+        (ptau, params)
+    }
+
+    #[test]
+    fn test_full_ceremony() {
+        let mut rng = test_rng();
+        let (ptau, params) = ceremony(3);
+
+        let hashes = verify(&ptau, CeremonyCircuit::Example, &params, &mut rng).unwrap();
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[2], params.transcript_hash());
+
+        // The exported keys prove and verify
+        let (pk, vk) = export(&params).unwrap();
+        let x = Fr::from(3u64);
+        let proof =
+            Groth16::<Bls12_381>::prove(&pk, ExampleCircuit { x: Some(x) }, &mut rng).unwrap();
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+        let input = crypto_groth16::encode_input(&ExampleCircuit::output(x));
+        let groth16_proof = Groth16Proof::new(proof_bytes, vec![input], vk.id());
+        assert!(groth16_proof.verify(&vk).unwrap());
+    }
+
+    #[test]
+    fn test_export_requires_contribution() {
+        let (ptau, params) = ceremony(0);
+        assert!(export(&params).is_err());
+        assert!(verify(&ptau, CeremonyCircuit::Example, &params, &mut test_rng()).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_transcript_rejected() {
+        let mut rng = test_rng();
+        let (ptau, params) = ceremony(2);
+
+        // Replacing δ without a matching proof of knowledge
+        let mut forged = params.clone();
+        forged.pk.delta_g1 = G1Affine::generator();
+        forged.pk.vk.delta_g2 = G2Affine::generator();
+        assert!(verify(&ptau, CeremonyCircuit::Example, &forged, &mut rng).is_err());
+
+        // Dropping a contribution breaks the δ chain
+        let mut dropped = params.clone();
+        dropped.contributions.remove(0);
+        assert!(verify(&ptau, CeremonyCircuit::Example, &dropped, &mut rng).is_err());
+
+        // Modifying the L query
+        let mut modified = params.clone();
+        modified.pk.l_query[0] = G1Affine::generator();
+        assert!(verify(&ptau, CeremonyCircuit::Example, &modified, &mut rng).is_err());
+
+        // Touching a fixed element
+        let mut fixed = params;
+        fixed.pk.a_query.swap(0, 1);
+        assert!(verify(&ptau, CeremonyCircuit::Example, &fixed, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_params_file_roundtrip() {
+        let (_, params) = ceremony(1);
+
+        let mut bytes = Vec::new();
+        params.write(&mut bytes).unwrap();
+        assert_eq!(Phase2Params::read(bytes.as_slice()).unwrap(), params);
+
+        assert!(Phase2Params::read(&bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn test_circuit_lookup() {
+        assert_eq!(
+            CeremonyCircuit::from_name("example"),
+            Some(CeremonyCircuit::Example)
+        );
+        assert_eq!(CeremonyCircuit::from_name("unknown"), None);
+    }
+}
@@ -0,0 +1,504 @@
+//! Circuit cost reports and MockProver harness
+//!
+//! [`measure`] lays a circuit out without proving it and reports the rows and
+//! columns it uses and an upper bound on the `k` it fits in; [`min_k`] finds
+//! the exact `k` with MockProver, and [`report`] adds keygen, prover and
+//! verifier times at that `k`. [`mock_failures`] runs MockProver and renders
+//! each unsatisfied constraint, lookup or copy as one line.
+//!
+//! halo2 keeps the constraint system's contents private, so columns and
+//! lookups are counted by allocating one more of each on a copy (reading
+//! column indices through `dev::metadata`), and selectors by the ones the
+//! layout enables.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use halo2_proofs::circuit::Value;
+use halo2_proofs::dev::{metadata, MockProver};
+use halo2_proofs::plonk::{
+    keygen_pk, keygen_vk, Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem,
+    Error, Fixed, FloorPlanner, Instance, Selector,
+};
+use pasta_curves::pallas;
+use privl1_crypto::halo2::{self, MAX_K};
+
+use crate::{CircuitError, Result};
+
+/// Structure and (optionally) proving cost of a circuit
+#[derive(Clone, Debug)]
+pub struct CostReport {
+    /// Rows used by any region, table or constant
+    pub rows: usize,
+    /// Advice columns
+    pub advice_columns: usize,
+    /// Fixed columns, excluding selectors
+    pub fixed_columns: usize,
+    /// Selectors the layout enables (compressed into fixed columns at keygen)
+    pub selectors: usize,
+    /// Instance columns
+    pub instance_columns: usize,
+    /// Lookup arguments
+    pub lookups: usize,
+    /// Maximum constraint degree
+    pub degree: usize,
+    /// Rows reserved for blinding at the end of every column
+    pub blinding_rows: usize,
+    /// Smallest `k` the measured layout fits in. This is an upper bound:
+    /// constants are counted as if they followed every region, so the
+    /// circuit may fit one `k` lower
+    pub k_bound: u32,
+    /// Smallest `k` MockProver accepts the circuit at, if searched with
+    /// [`min_k`]
+    pub min_k: Option<u32>,
+    /// Proving costs at `min_k`, if measured
+    pub timings: Option<Timings>,
+}
+
+/// Proving costs of a circuit at one `k`
+#[derive(Clone, Debug)]
+pub struct Timings {
+    /// Circuit size the costs were measured at
+    pub k: u32,
+    /// Verifying- and proving-key generation
+    pub keygen: Duration,
+    /// Proof creation
+    pub prove: Duration,
+    /// Proof verification
+    pub verify: Duration,
+    /// Proof size in bytes
+    pub proof_size: usize,
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rows:             {}", self.rows)?;
+        writeln!(f, "advice columns:   {}", self.advice_columns)?;
+        writeln!(
+            f,
+            "fixed columns:    {} (+{} selectors)",
+            self.fixed_columns, self.selectors
+        )?;
+        writeln!(f, "instance columns: {}", self.instance_columns)?;
+        writeln!(f, "lookups:          {}", self.lookups)?;
+        writeln!(f, "degree:           {}", self.degree)?;
+        write!(
+            f,
+            "k bound:          {} ({} usable rows)",
+            self.k_bound,
+            usable_rows(self.k_bound, self.blinding_rows)
+        )?;
+        if let Some(min_k) = self.min_k {
+            writeln!(f)?;
+            write!(
+                f,
+                "minimal k:        {} ({} usable rows)",
+                min_k,
+                usable_rows(min_k, self.blinding_rows)
+            )?;
+        }
+
+        if let Some(timings) = &self.timings {
+            writeln!(f)?;
+            writeln!(f, "keygen (k={}):    {:?}", timings.k, timings.keygen)?;
+            writeln!(f, "prove:            {:?}", timings.prove)?;
+            writeln!(f, "verify:           {:?}", timings.verify)?;
+            write!(f, "proof size:       {} bytes", timings.proof_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lay out `circuit` and report its structure; no proving
+pub fn measure<C: Circuit<pallas::Base>>(circuit: &C) -> Result<CostReport> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+
+    // Each new column's index is the number of columns before it
+    let mut probe = cs.clone();
+    let advice_columns = column_index(probe.advice_column());
+    let instance_columns = column_index(probe.instance_column());
+    let lookups = probe.lookup(|_| vec![]);
+    // The circuit's constant columns are not exposed either, so constants are
+    // laid out in a column of their own and counted as if they followed every
+    // region, which bounds the rows the real layout needs
+    let constants = probe.fixed_column();
+
+    let mut counter = RowCounter::new(constants);
+    C::FloorPlanner::synthesize(&mut counter, circuit, config, vec![constants])
+        .map_err(|e| CircuitError::Synthesis(format!("{:?}", e)))?;
+
+    let blinding_rows = cs.blinding_factors() + 1;
+    let needed = (counter.rows + counter.constants + blinding_rows).max(cs.minimum_rows());
+    let k_bound = (1..=MAX_K).find(|k| 1usize << k >= needed).ok_or_else(|| {
+        CircuitError::InvalidParameters(format!("Circuit needs {} rows, above 2^{}", needed, MAX_K))
+    })?;
+
+    Ok(CostReport {
+        rows: counter.rows,
+        advice_columns,
+        fixed_columns: column_index(constants),
+        selectors: counter.selectors.len(),
+        instance_columns,
+        lookups,
+        degree: cs.degree(),
+        blinding_rows,
+        k_bound,
+        min_k: None,
+        timings: None,
+    })
+}
+
+/// Generate keys for `circuit` at `k`, then prove and verify it once
+///
+/// `circuit` must carry a witness satisfying `instances`.
+pub fn benchmark<C: Circuit<pallas::Base> + Clone>(
+    circuit: &C,
+    instances: &[pallas::Base],
+    k: u32,
+) -> Result<Timings> {
+    let params = halo2::params(k)?;
+
+    let start = Instant::now();
+    let vk =
+        keygen_vk(&params, circuit).map_err(|e| CircuitError::Synthesis(format!("{:?}", e)))?;
+    let pk =
+        keygen_pk(&params, vk, circuit).map_err(|e| CircuitError::Synthesis(format!("{:?}", e)))?;
+    let keygen = start.elapsed();
+
+    let start = Instant::now();
+    let proof = halo2::create_proof(&params, &pk, circuit.clone(), instances, rand::rngs::OsRng)?;
+    let prove = start.elapsed();
+
+    let start = Instant::now();
+    let verified = halo2::verify_proof(&params, pk.get_vk(), &proof, instances);
+    let verify = start.elapsed();
+    if !verified {
+        return Err(CircuitError::VerificationFailed(
+            "Benchmark proof does not verify".into(),
+        ));
+    }
+
+    Ok(Timings {
+        k,
+        keygen,
+        prove,
+        verify,
+        proof_size: proof.len(),
+    })
+}
+
+/// [`measure`] `circuit`, find its [`min_k`], then [`benchmark`] it there
+pub fn report<C: Circuit<pallas::Base> + Clone>(
+    circuit: &C,
+    instances: &[pallas::Base],
+) -> Result<CostReport> {
+    let mut report = measure(circuit)?;
+    let bound = report
+        .k_bound
+        .max(min_k_for_instances(instances.len(), report.blinding_rows));
+    let k = min_k(circuit, instances, bound)?;
+    report.min_k = Some(k);
+    report.timings = Some(benchmark(circuit, instances, k)?);
+    Ok(report)
+}
+
+/// Smallest `k` at which MockProver accepts `circuit` with `instances`,
+/// searching down from `bound`, which it must accept
+///
+/// A circuit that fits in `2^k` rows fits in any larger domain, so the
+/// search stops at the first `k` that fails.
+pub fn min_k<C: Circuit<pallas::Base>>(
+    circuit: &C,
+    instances: &[pallas::Base],
+    bound: u32,
+) -> Result<u32> {
+    mock_prove(circuit, vec![instances.to_vec()], bound)?;
+    let fits = |k: u32| {
+        mock_failures(circuit, vec![instances.to_vec()], k)
+            .is_ok_and(|failures| failures.is_empty())
+    };
+    let mut k = bound;
+    while k > 1 && fits(k - 1) {
+        k -= 1;
+    }
+    Ok(k)
+}
+
+/// Run MockProver at `k`, returning one message per failure (empty if satisfied)
+pub fn mock_failures<C: Circuit<pallas::Base>>(
+    circuit: &C,
+    instances: Vec<Vec<pallas::Base>>,
+    k: u32,
+) -> Result<Vec<String>> {
+    let prover = MockProver::run(k, circuit, instances)
+        .map_err(|e| CircuitError::Synthesis(format!("{:?}", e)))?;
+
+    Ok(match prover.verify() {
+        Ok(()) => vec![],
+        Err(failures) => failures.iter().map(|failure| failure.to_string()).collect(),
+    })
+}
+
+/// Run MockProver at `k`, failing with every unsatisfied constraint listed
+pub fn mock_prove<C: Circuit<pallas::Base>>(
+    circuit: &C,
+    instances: Vec<Vec<pallas::Base>>,
+    k: u32,
+) -> Result<()> {
+    let failures = mock_failures(circuit, instances, k)?;
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CircuitError::VerificationFailed(failures.join("\n")))
+    }
+}
+
+/// Index of `column` among the columns of its type
+fn column_index<C: Into<Column<Any>>>(column: C) -> usize {
+    let column = column.into();
+    let target = metadata::Column::from(column);
+    (0..)
+        .find(|index| metadata::Column::from((*column.column_type(), *index)) == target)
+        .expect("columns are indexed from zero")
+}
+
+fn usable_rows(k: u32, blinding_rows: usize) -> usize {
+    (1usize << k).saturating_sub(blinding_rows)
+}
+
+fn min_k_for_instances(instances: usize, blinding_rows: usize) -> u32 {
+    (1..=MAX_K)
+        .find(|k| usable_rows(*k, blinding_rows) >= instances)
+        .unwrap_or(MAX_K)
+}
+
+/// Records the highest row a floor planner touches, the constants it assigns
+/// and the selectors it enables
+struct RowCounter {
+    rows: usize,
+    constant_column: Column<Fixed>,
+    constants: usize,
+    selectors: HashSet<Selector>,
+}
+
+impl RowCounter {
+    fn new(constant_column: Column<Fixed>) -> Self {
+        Self {
+            rows: 0,
+            constant_column,
+            constants: 0,
+            selectors: HashSet::new(),
+        }
+    }
+
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+    }
+}
+
+impl Assignment<pallas::Base> for RowCounter {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _: A,
+        selector: &Selector,
+        row: usize,
+    ) -> std::result::Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.selectors.insert(*selector);
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _: Column<Instance>,
+        _: usize,
+    ) -> std::result::Result<Value<pallas::Base>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if column == self.constant_column {
+            self.constants += 1;
+            return Ok(());
+        }
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _: Column<Any>,
+        _: usize,
+        _: Column<Any>,
+        _: usize,
+    ) -> std::result::Result<(), Error> {
+        Ok(())
+    }
+
+    // Tables are padded to the end of the column; that is not usage
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<pallas::Base>>,
+    ) -> std::result::Result<(), Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::note::{AssetId, Note};
+    use rand::rngs::OsRng;
+
+    use crate::output::{self, OutputWitness};
+    use crate::{spend, swap};
+
+    fn output_witness() -> OutputWitness {
+        let keys = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(10, keys.public, *AssetId::NATIVE.as_bytes());
+        OutputWitness::random(&note, &mut OsRng)
+    }
+
+    #[test]
+    fn test_note_circuits_fit_their_k() {
+        let output = measure(&output::OutputCircuit::default()).unwrap();
+        let spend = measure(&spend::SpendCircuit::default()).unwrap();
+        let swap = measure(&swap::SwapCircuit::default()).unwrap();
+
+        for (report, k) in [(&output, output::K), (&spend, spend::K), (&swap, swap::K)] {
+            assert!(
+                report.k_bound <= k,
+                "needs k={}, declared {}",
+                report.k_bound,
+                k
+            );
+            // The range-check table alone fills 2^10 rows
+            assert!(report.rows >= 1 << 10);
+            assert_eq!(report.instance_columns, 1);
+            assert_eq!(report.lookups, output.lookups);
+        }
+        assert!(swap.rows > spend.rows && spend.rows > output.rows);
+    }
+
+    #[test]
+    fn test_mock_failures_are_reported() {
+        let witness = output_witness();
+        let circuit = witness.circuit();
+
+        assert!(
+            mock_failures(&circuit, vec![witness.public_inputs()], output::K)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(mock_prove(&circuit, vec![witness.public_inputs()], output::K).is_ok());
+
+        let mut instances = witness.public_inputs();
+        instances[output::CM] += pallas::Base::ONE;
+        let failures = mock_failures(&circuit, vec![instances.clone()], output::K).unwrap();
+        assert!(!failures.is_empty());
+        match mock_prove(&circuit, vec![instances], output::K) {
+            Err(CircuitError::VerificationFailed(message)) => {
+                assert_eq!(message, failures.join("\n"))
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        // Too small a k is a synthesis error, not a failure list
+        assert!(mock_failures(&circuit, vec![witness.public_inputs()], 9).is_err());
+    }
+
+    #[test]
+    fn test_min_k_is_exact() {
+        let witness = output_witness();
+        let (circuit, instances) = (witness.circuit(), witness.public_inputs());
+        let bound = measure(&circuit).unwrap().k_bound;
+
+        let k = min_k(&circuit, &instances, bound).unwrap();
+        assert!(k <= bound);
+        assert!(mock_prove(&circuit, vec![instances.clone()], k).is_ok());
+        assert!(mock_prove(&circuit, vec![instances.clone()], k - 1).is_err());
+        assert_eq!(min_k(&circuit, &instances, bound + 1).unwrap(), k);
+
+        // A witness that does not satisfy the instances fits nowhere
+        let mut wrong = instances;
+        wrong[output::CM] += pallas::Base::ONE;
+        assert!(matches!(
+            min_k(&circuit, &wrong, bound),
+            Err(CircuitError::VerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_report_with_timings() {
+        let witness = output_witness();
+        let report = report(&witness.circuit(), &witness.public_inputs()).unwrap();
+
+        let timings = report.timings.as_ref().unwrap();
+        assert_eq!(report.min_k, Some(timings.k));
+        assert!(timings.k <= report.k_bound);
+        assert!(timings.proof_size > 0);
+        assert!(report.to_string().contains("proof size"));
+
+        // A witness that does not satisfy the instances cannot be benchmarked
+        let mut instances = witness.public_inputs();
+        instances[output::CV_X] += pallas::Base::ONE;
+        assert!(benchmark(&witness.circuit(), &instances, timings.k).is_err());
+    }
+}
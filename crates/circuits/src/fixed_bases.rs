@@ -0,0 +1,188 @@
+//! Fixed bases for the Halo2 ECC chip
+//!
+//! Each base matches a generator used natively by `privl1-crypto`:
+//! - `SpendAuthG`: the Pallas generator, for `rk = ak + [alpha]G`
+//! - `ValueCommitV`, `ValueCommitR`: the Pedersen value-commitment generators
+//! - `NullifierK`: `N` from [`keys::nullifier_key_base`], binding `nk` into
+//!   the owner's public key
+//!
+//! The window tables the chip needs (`z`, `u`) are searched for on first use
+//! and cached for the life of the process.
+
+use std::sync::OnceLock;
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::chip::constants::find_zs_and_us;
+use halo2_gadgets::ecc::chip::{
+    BaseFieldElem, FixedPoint, FullScalar, ShortScalar, H, NUM_WINDOWS, NUM_WINDOWS_SHORT,
+};
+use halo2_gadgets::ecc::FixedPoints;
+use pasta_curves::pallas;
+use privl1_crypto::commitment::PedersenCommitment;
+use privl1_crypto::keys;
+use privl1_crypto::Point;
+
+/// All fixed bases used by PRIVL1 circuits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FixedBases {
+    Full(FullWidth),
+    NullifierK,
+    ValueCommitV,
+}
+
+/// Bases multiplied by full-width scalars
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FullWidth {
+    SpendAuthG,
+    ValueCommitR,
+}
+
+/// Base multiplied by a base-field element (the nullifier deriving key)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NullifierK;
+
+/// Base multiplied by a signed 64-bit value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueCommitV;
+
+impl FixedPoints<pallas::Affine> for FixedBases {
+    type FullScalar = FullWidth;
+    type ShortScalar = ValueCommitV;
+    type Base = NullifierK;
+}
+
+/// A generator with its precomputed window table
+struct WindowTable {
+    generator: pallas::Affine,
+    z: Vec<u64>,
+    u: Vec<[[u8; 32]; H]>,
+}
+
+impl WindowTable {
+    fn new(generator: Point, num_windows: usize) -> Self {
+        let generator = generator.inner().to_affine();
+        let (z, u) = find_zs_and_us(generator, num_windows)
+            .expect("window table exists for every PRIVL1 base")
+            .into_iter()
+            .map(|(z, us)| (z, us.map(|u| u.to_repr())))
+            .unzip();
+
+        Self { generator, z, u }
+    }
+}
+
+fn spend_auth_g() -> &'static WindowTable {
+    static TABLE: OnceLock<WindowTable> = OnceLock::new();
+    TABLE.get_or_init(|| WindowTable::new(Point::generator(), NUM_WINDOWS))
+}
+
+fn value_commit_r() -> &'static WindowTable {
+    static TABLE: OnceLock<WindowTable> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        WindowTable::new(*PedersenCommitment::new().blinding_generator(), NUM_WINDOWS)
+    })
+}
+
+fn value_commit_v() -> &'static WindowTable {
+    static TABLE: OnceLock<WindowTable> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        WindowTable::new(
+            *PedersenCommitment::new().value_generator(),
+            NUM_WINDOWS_SHORT,
+        )
+    })
+}
+
+fn nullifier_k() -> &'static WindowTable {
+    static TABLE: OnceLock<WindowTable> = OnceLock::new();
+    TABLE.get_or_init(|| WindowTable::new(keys::nullifier_key_base(), NUM_WINDOWS))
+}
+
+impl FullWidth {
+    fn table(&self) -> &'static WindowTable {
+        match self {
+            FullWidth::SpendAuthG => spend_auth_g(),
+            FullWidth::ValueCommitR => value_commit_r(),
+        }
+    }
+}
+
+impl FixedPoint<pallas::Affine> for FullWidth {
+    type FixedScalarKind = FullScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        self.table().generator
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        self.table().u.clone()
+    }
+
+    fn z(&self) -> Vec<u64> {
+        self.table().z.clone()
+    }
+}
+
+impl FixedPoint<pallas::Affine> for NullifierK {
+    type FixedScalarKind = BaseFieldElem;
+
+    fn generator(&self) -> pallas::Affine {
+        nullifier_k().generator
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        nullifier_k().u.clone()
+    }
+
+    fn z(&self) -> Vec<u64> {
+        nullifier_k().z.clone()
+    }
+}
+
+impl FixedPoint<pallas::Affine> for ValueCommitV {
+    type FixedScalarKind = ShortScalar;
+
+    fn generator(&self) -> pallas::Affine {
+        value_commit_v().generator
+    }
+
+    fn u(&self) -> Vec<[[u8; 32]; H]> {
+        value_commit_v().u.clone()
+    }
+
+    fn z(&self) -> Vec<u64> {
+        value_commit_v().z.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_gadgets::ecc::chip::constants::{test_lagrange_coeffs, test_zs_and_us};
+
+    #[test]
+    fn test_full_width_tables() {
+        for base in [FullWidth::SpendAuthG, FullWidth::ValueCommitR] {
+            test_lagrange_coeffs(base.generator(), NUM_WINDOWS);
+            test_zs_and_us(base.generator(), &base.z(), &base.u(), NUM_WINDOWS);
+        }
+    }
+
+    #[test]
+    fn test_nullifier_k_table() {
+        let base = NullifierK;
+        assert_eq!(
+            base.generator(),
+            keys::nullifier_key_base().inner().to_affine()
+        );
+        test_zs_and_us(base.generator(), &base.z(), &base.u(), NUM_WINDOWS);
+    }
+
+    #[test]
+    fn test_value_commit_v_table() {
+        let base = ValueCommitV;
+        test_lagrange_coeffs(base.generator(), NUM_WINDOWS_SHORT);
+        test_zs_and_us(base.generator(), &base.z(), &base.u(), NUM_WINDOWS_SHORT);
+    }
+}
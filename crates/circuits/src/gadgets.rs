@@ -0,0 +1,1303 @@
+//! Halo2 gadget library
+//!
+//! Building blocks shared by the PRIVL1 circuits:
+//! - [`RangeCheckConfig`]: lookup range checks for u64 and arbitrary widths
+//! - [`MerklePathConfig`]: authentication paths of a Poseidon Merkle tree
+//! - [`ValueCommitConfig`]: `cv = [value]V + [rcv]R` by fixed-base scalar mul
+//! - [`U64Config`]: checked addition, subtraction, multiplication, floor
+//!   division and comparison of u64 amounts, plus bit splits and comparisons
+//!   of wider field elements
+//! - [`poseidon_hash`]: P128Pow5T3 over a fixed number of cells
+//! - [`assign_free_advice`]: witness an unconstrained private input
+//!
+//! Gadgets copy their inputs, so they can share advice columns; all columns
+//! passed in must be equality-enabled, and gadgets that assign constants need
+//! the circuit to have enabled a constant column.
+
+use ff::{Field, PrimeField};
+use halo2_gadgets::ecc::chip::{CircuitVersion, EccChip, EccConfig};
+use halo2_gadgets::ecc::{FixedPoint, FixedPointShort, Point, ScalarFixed, ScalarFixedShort};
+use halo2_gadgets::poseidon::primitives::{ConstantLength, P128Pow5T3};
+use halo2_gadgets::poseidon::{Hash as PoseidonGadget, Pow5Chip, Pow5Config};
+use halo2_gadgets::utilities::bool_check;
+use halo2_gadgets::utilities::lookup_range_check::{LookupRangeCheck, LookupRangeCheckConfig};
+use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
+use halo2_proofs::plonk::{
+    Advice, Column, ConstraintSystem, Constraints, Error, Expression, Selector, TableColumn,
+};
+use halo2_proofs::poly::Rotation;
+use pasta_curves::pallas;
+
+use crate::fixed_bases::{FixedBases, FullWidth, ValueCommitV};
+
+/// Word size of the lookup range check (the table holds `0..2^K`)
+pub const RANGE_CHECK_K: usize = 10;
+
+/// Poseidon chip configuration (width 3, rate 2)
+pub type PoseidonConfig = Pow5Config<pallas::Base, 3, 2>;
+
+/// An assigned `pallas::Base` cell
+pub type AssignedBase = AssignedCell<pallas::Base, pallas::Base>;
+
+/// A point assigned by the ECC chip
+pub type CircuitPoint = Point<pallas::Affine, EccChip<FixedBases>>;
+
+/// Load the lookup range-check table `0..2^RANGE_CHECK_K`
+pub fn load_range_table(
+    layouter: &mut impl Layouter<pallas::Base>,
+    table_idx: TableColumn,
+) -> Result<(), Error> {
+    layouter.assign_table(
+        || "range check table",
+        |mut table| {
+            for index in 0..(1 << RANGE_CHECK_K) {
+                table.assign_cell(
+                    || "table_idx",
+                    table_idx,
+                    index,
+                    || Value::known(pallas::Base::from(index as u64)),
+                )?;
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Witness a free `value` in `column`
+pub fn assign_free_advice(
+    mut layouter: impl Layouter<pallas::Base>,
+    column: Column<Advice>,
+    value: Value<pallas::Base>,
+) -> Result<AssignedBase, Error> {
+    layouter.assign_region(
+        || "load private",
+        |mut region| region.assign_advice(|| "private input", column, 0, || value),
+    )
+}
+
+/// Hash `L` cells with P128Pow5T3, matching `privl1_crypto::PoseidonHash::hash`
+pub fn poseidon_hash<const L: usize>(
+    config: &PoseidonConfig,
+    mut layouter: impl Layouter<pallas::Base>,
+    message: [AssignedBase; L],
+) -> Result<AssignedBase, Error> {
+    let chip = Pow5Chip::construct(config.clone());
+    let hasher = PoseidonGadget::<_, _, P128Pow5T3, ConstantLength<L>, 3, 2>::init(
+        chip,
+        layouter.namespace(|| "init"),
+    )?;
+    hasher.hash(layouter.namespace(|| "hash"), message)
+}
+
+/// Lookup range check over `RANGE_CHECK_K`-bit words
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    lookup: LookupRangeCheckConfig<pallas::Base, RANGE_CHECK_K>,
+    table_idx: TableColumn,
+}
+
+impl RangeCheckConfig {
+    /// Configure the lookup, with its running sum in `running_sum`
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        running_sum: Column<Advice>,
+        table_idx: TableColumn,
+    ) -> Self {
+        Self {
+            lookup: LookupRangeCheckConfig::configure(meta, running_sum, table_idx),
+            table_idx,
+        }
+    }
+
+    /// The underlying lookup configuration (the ECC chip needs it)
+    pub fn lookup(&self) -> &LookupRangeCheckConfig<pallas::Base, RANGE_CHECK_K> {
+        &self.lookup
+    }
+
+    /// Load the lookup table; call once per synthesis
+    pub fn load(&self, layouter: &mut impl Layouter<pallas::Base>) -> Result<(), Error> {
+        load_range_table(layouter, self.table_idx)
+    }
+
+    /// Constrain `element < 2^num_bits`
+    ///
+    /// Whole words are checked with a running sum; the remaining high bits
+    /// with a short lookup on what is left of it.
+    pub fn range_check(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        element: AssignedBase,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        assert!(
+            num_bits > 0 && num_bits < pallas::Base::NUM_BITS as usize,
+            "range check width out of bounds"
+        );
+
+        let words = num_bits / RANGE_CHECK_K;
+        let rem = num_bits % RANGE_CHECK_K;
+        let high = if words == 0 {
+            element
+        } else {
+            let zs =
+                self.lookup
+                    .copy_check(layouter.namespace(|| "words"), element, words, rem == 0)?;
+            if rem == 0 {
+                return Ok(());
+            }
+            zs[words].clone()
+        };
+
+        self.lookup
+            .copy_short_check(layouter.namespace(|| "high bits"), high, rem)
+    }
+
+    /// Constrain `element < 2^64`
+    pub fn range_check_u64(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        element: AssignedBase,
+    ) -> Result<(), Error> {
+        self.range_check(layouter, element, 64)
+    }
+}
+
+/// Merkle authentication path over Poseidon, for trees of depth `DEPTH`
+///
+/// Matches `privl1_crypto::merkle` for `DEPTH = merkle::TREE_DEPTH`: each
+/// level hashes `(node, sibling)` or `(sibling, node)` by the position bit.
+#[derive(Clone, Debug)]
+pub struct MerklePathConfig<const DEPTH: usize> {
+    advices: [Column<Advice>; 5],
+    q_swap: Selector,
+    q_position: Selector,
+    poseidon: PoseidonConfig,
+}
+
+impl<const DEPTH: usize> MerklePathConfig<DEPTH> {
+    /// Configure the swap and position gates over `advices`
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advices: [Column<Advice>; 5],
+        poseidon: PoseidonConfig,
+    ) -> Self {
+        // One Merkle level: order (cur, sibling) by the position bit
+        let q_swap = meta.selector();
+        meta.create_gate("merkle swap", |meta| {
+            let q = meta.query_selector(q_swap);
+            let cur = meta.query_advice(advices[0], Rotation::cur());
+            let sibling = meta.query_advice(advices[1], Rotation::cur());
+            let bit = meta.query_advice(advices[2], Rotation::cur());
+            let left = meta.query_advice(advices[3], Rotation::cur());
+            let right = meta.query_advice(advices[4], Rotation::cur());
+
+            Constraints::with_selector(
+                q,
+                [
+                    ("bit is boolean", bool_check(bit.clone())),
+                    (
+                        "left",
+                        left - (cur.clone() + bit.clone() * (sibling.clone() - cur.clone())),
+                    ),
+                    ("right", right - (sibling.clone() + bit * (cur - sibling))),
+                ],
+            )
+        });
+
+        // Position from its bits, most significant first: acc = 2 * acc_next + bit
+        let q_position = meta.selector();
+        meta.create_gate("merkle position", |meta| {
+            let q = meta.query_selector(q_position);
+            let bit = meta.query_advice(advices[0], Rotation::cur());
+            let acc = meta.query_advice(advices[1], Rotation::cur());
+            let acc_next = meta.query_advice(advices[1], Rotation::next());
+
+            Constraints::with_selector(q, Some(acc - (acc_next * pallas::Base::from(2) + bit)))
+        });
+
+        Self {
+            advices,
+            q_swap,
+            q_position,
+            poseidon,
+        }
+    }
+
+    /// Hash `leaf` up its authentication path, returning `(root, position)`
+    ///
+    /// `position` is recomposed from the path bits, so it is below `2^DEPTH`.
+    pub fn calculate_root(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        leaf: AssignedBase,
+        path: Value<[pallas::Base; DEPTH]>,
+        position: Value<u64>,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        let mut node = leaf;
+        let mut bits = Vec::with_capacity(DEPTH);
+        for level in 0..DEPTH {
+            let sibling = path.map(|path| path[level]);
+            let bit = position.map(|p| pallas::Base::from((p >> level) & 1));
+            let (left, right, bit) = self.assign_swap(
+                layouter.namespace(|| format!("swap {}", level)),
+                &node,
+                sibling,
+                bit,
+            )?;
+            bits.push(bit);
+
+            node = poseidon_hash(
+                &self.poseidon,
+                layouter.namespace(|| format!("merkle hash {}", level)),
+                [left, right],
+            )?;
+        }
+
+        let position = self.assign_position(layouter.namespace(|| "position"), &bits)?;
+        Ok((node, position))
+    }
+
+    /// Order a node and its sibling by the position bit, returning `(left, right, bit)`
+    fn assign_swap(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        node: &AssignedBase,
+        sibling: Value<pallas::Base>,
+        bit: Value<pallas::Base>,
+    ) -> Result<(AssignedBase, AssignedBase, AssignedBase), Error> {
+        layouter.assign_region(
+            || "merkle swap",
+            |mut region| {
+                self.q_swap.enable(&mut region, 0)?;
+
+                let cur = node.copy_advice(|| "cur", &mut region, self.advices[0], 0)?;
+                let sibling = region.assign_advice(|| "sibling", self.advices[1], 0, || sibling)?;
+                let bit = region.assign_advice(|| "bit", self.advices[2], 0, || bit)?;
+
+                let inputs = cur.value().zip(sibling.value()).zip(bit.value());
+                let left = inputs.map(|((c, s), b)| *c + *b * (*s - *c));
+                let right = inputs.map(|((c, s), b)| *s + *b * (*c - *s));
+                let left = region.assign_advice(|| "left", self.advices[3], 0, || left)?;
+                let right = region.assign_advice(|| "right", self.advices[4], 0, || right)?;
+
+                Ok((left, right, bit))
+            },
+        )
+    }
+
+    /// Recompose the leaf position from its bits (least significant first)
+    fn assign_position(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        bits: &[AssignedBase],
+    ) -> Result<AssignedBase, Error> {
+        layouter.assign_region(
+            || "merkle position",
+            |mut region| {
+                let mut acc = region.assign_advice_from_constant(
+                    || "acc",
+                    self.advices[1],
+                    bits.len(),
+                    pallas::Base::ZERO,
+                )?;
+                for (row, bit) in bits.iter().enumerate().rev() {
+                    self.q_position.enable(&mut region, row)?;
+                    let bit = bit.copy_advice(|| "bit", &mut region, self.advices[0], row)?;
+                    let value = acc
+                        .value()
+                        .zip(bit.value())
+                        .map(|(acc, bit)| acc.double() + bit);
+                    acc = region.assign_advice(|| "acc", self.advices[1], row, || value)?;
+                }
+                Ok(acc)
+            },
+        )
+    }
+}
+
+/// Value commitments `cv = [value]V + [rcv]R`, matching `PedersenCommitment`
+#[derive(Clone, Debug)]
+pub struct ValueCommitConfig {
+    ecc: EccConfig<FixedBases>,
+    sign: Column<Advice>,
+}
+
+impl ValueCommitConfig {
+    /// Use a configured ECC chip; `sign` holds the sign of unsigned commitments
+    pub fn construct(ecc: EccConfig<FixedBases>, sign: Column<Advice>) -> Self {
+        Self { ecc, sign }
+    }
+
+    /// Commit to a non-negative value
+    ///
+    /// The short-scalar decomposition of `value` constrains it to 64 bits.
+    pub fn commit(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: AssignedBase,
+        rcv: Value<pallas::Scalar>,
+    ) -> Result<CircuitPoint, Error> {
+        let one = layouter.assign_region(
+            || "sign",
+            |mut region| {
+                region.assign_advice_from_constant(|| "one", self.sign, 0, pallas::Base::ONE)
+            },
+        )?;
+        self.commit_signed(layouter, value, one, rcv)
+    }
+
+    /// Commit to `sign * magnitude`, with `magnitude < 2^64` and `sign = ±1`
+    pub fn commit_signed(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        magnitude: AssignedBase,
+        sign: AssignedBase,
+        rcv: Value<pallas::Scalar>,
+    ) -> Result<CircuitPoint, Error> {
+        let ecc_chip = EccChip::construct(self.ecc.clone(), CircuitVersion::AnchoredBase);
+
+        let value = ScalarFixedShort::new(
+            ecc_chip.clone(),
+            layouter.namespace(|| "value"),
+            (magnitude, sign),
+        )?;
+        let (value_v, _) = FixedPointShort::from_inner(ecc_chip.clone(), ValueCommitV)
+            .mul(layouter.namespace(|| "[value]V"), value)?;
+
+        let rcv = ScalarFixed::new(ecc_chip.clone(), layouter.namespace(|| "rcv"), rcv)?;
+        let (rcv_r, _) = FixedPoint::from_inner(ecc_chip, FullWidth::ValueCommitR)
+            .mul(layouter.namespace(|| "[rcv]R"), rcv)?;
+
+        value_v.add(layouter.namespace(|| "cv"), &rcv_r)
+    }
+}
+
+/// Arithmetic over u64 amounts
+///
+/// Inputs must already be constrained to 64 bits (by [`U64Config::witness`],
+/// a range check or a value commitment). Checked operations leave the circuit
+/// unsatisfiable on overflow or underflow; [`U64Config::mul_wide`] returns the
+/// high word instead. [`U64Config::low_bits`] and [`U64Config::assert_lt_bits`]
+/// work on any field element and take their own bit widths.
+#[derive(Clone, Debug)]
+pub struct U64Config {
+    advices: [Column<Advice>; 4],
+    q_add: Selector,
+    q_mul: Selector,
+    q_lt: Selector,
+    range: RangeCheckConfig,
+}
+
+impl U64Config {
+    /// Configure the arithmetic gates over `advices`
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        advices: [Column<Advice>; 4],
+        range: RangeCheckConfig,
+    ) -> Self {
+        let two_pow_64 = Expression::Constant(two_pow_64());
+
+        // a + b = c
+        let q_add = meta.selector();
+        meta.create_gate("u64 add", |meta| {
+            let q = meta.query_selector(q_add);
+            let a = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+            let c = meta.query_advice(advices[2], Rotation::cur());
+
+            Constraints::with_selector(q, Some(a + b - c))
+        });
+
+        // a * b = hi * 2^64 + lo
+        let q_mul = meta.selector();
+        meta.create_gate("u64 mul", |meta| {
+            let q = meta.query_selector(q_mul);
+            let a = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+            let hi = meta.query_advice(advices[2], Rotation::cur());
+            let lo = meta.query_advice(advices[3], Rotation::cur());
+
+            Constraints::with_selector(q, Some(a * b - (hi * two_pow_64.clone() + lo)))
+        });
+
+        // a - b + lt * 2^64 = r, with r < 2^64 checked by lookup
+        let q_lt = meta.selector();
+        meta.create_gate("u64 lt", |meta| {
+            let q = meta.query_selector(q_lt);
+            let a = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+            let lt = meta.query_advice(advices[2], Rotation::cur());
+            let r = meta.query_advice(advices[3], Rotation::cur());
+
+            Constraints::with_selector(
+                q,
+                [
+                    ("lt is boolean", bool_check(lt.clone())),
+                    ("difference", a - b + lt * two_pow_64.clone() - r),
+                ],
+            )
+        });
+
+        Self {
+            advices,
+            q_add,
+            q_mul,
+            q_lt,
+            range,
+        }
+    }
+
+    /// Witness a u64 amount
+    pub fn witness(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: Value<u64>,
+    ) -> Result<AssignedBase, Error> {
+        let cell = layouter.assign_region(
+            || "u64",
+            |mut region| {
+                region.assign_advice(
+                    || "value",
+                    self.advices[0],
+                    0,
+                    || value.map(pallas::Base::from),
+                )
+            },
+        )?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "range"), cell.clone())?;
+        Ok(cell)
+    }
+
+    /// `a + b`, failing on overflow
+    pub fn checked_add(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        let sum = a.value().zip(b.value()).map(|(a, b)| *a + *b);
+        let sum = self.assign_add(layouter.namespace(|| "add"), a, b, sum)?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "no overflow"), sum.clone())?;
+        Ok(sum)
+    }
+
+    /// `a - b`, failing on underflow
+    pub fn checked_sub(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        // Laid out as difference + b = a
+        let difference = a.value().zip(b.value()).map(|(a, b)| *a - *b);
+        let difference = layouter.assign_region(
+            || "difference",
+            |mut region| region.assign_advice(|| "difference", self.advices[0], 0, || difference),
+        )?;
+        self.assign_sum(layouter.namespace(|| "sub"), &difference, b, a)?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "no underflow"), difference.clone())?;
+        Ok(difference)
+    }
+
+    /// Constrain `a <= b`
+    pub fn assert_le(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<(), Error> {
+        self.checked_sub(layouter, b, a).map(|_| ())
+    }
+
+    /// `1` if `a < b`, else `0`
+    pub fn lt(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        let (lt, r) = layouter.assign_region(
+            || "u64 lt",
+            |mut region| {
+                self.q_lt.enable(&mut region, 0)?;
+                let a = a.copy_advice(|| "a", &mut region, self.advices[0], 0)?;
+                let b = b.copy_advice(|| "b", &mut region, self.advices[1], 0)?;
+
+                let lt = a
+                    .value()
+                    .zip(b.value())
+                    .map(|(a, b)| pallas::Base::from((to_u128(a) < to_u128(b)) as u64));
+                let r = a
+                    .value()
+                    .zip(b.value())
+                    .zip(lt)
+                    .map(|((a, b), lt)| *a - *b + lt * two_pow_64());
+                let lt = region.assign_advice(|| "lt", self.advices[2], 0, || lt)?;
+                let r = region.assign_advice(|| "r", self.advices[3], 0, || r)?;
+                Ok((lt, r))
+            },
+        )?;
+        self.range.range_check_u64(layouter.namespace(|| "r"), r)?;
+        Ok(lt)
+    }
+
+    /// `a * b` as `(hi, lo)` words; the product overflowed iff `hi != 0`
+    pub fn mul_wide(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        let (hi, lo) = self.assign_mul(layouter.namespace(|| "mul"), a, b)?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "hi"), hi.clone())?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "lo"), lo.clone())?;
+        Ok((hi, lo))
+    }
+
+    /// `a * b`, failing on overflow
+    pub fn checked_mul(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        let (hi, lo) = self.assign_mul(layouter.namespace(|| "mul"), a, b)?;
+        layouter.assign_region(
+            || "no overflow",
+            |mut region| region.constrain_constant(hi.cell(), pallas::Base::ZERO),
+        )?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "product"), lo.clone())?;
+        Ok(lo)
+    }
+
+    /// Floor division `a / b` as `(quotient, remainder)`, failing if `b = 0`
+    pub fn div_rem(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        let division = a.value().zip(b.value()).map(|(a, b)| {
+            let (a, b) = (to_u128(a) as u64, to_u128(b) as u64);
+            a.checked_div(b).zip(a.checked_rem(b)).unwrap_or_default()
+        });
+        let quotient = self.witness(layouter.namespace(|| "quotient"), division.map(|(q, _)| q))?;
+        let remainder =
+            self.witness(layouter.namespace(|| "remainder"), division.map(|(_, r)| r))?;
+
+        // a = quotient * b + remainder, without wrapping, and remainder < b
+        let product = self.checked_mul(layouter.namespace(|| "quotient * b"), &quotient, b)?;
+        let sum = self.checked_add(layouter.namespace(|| "+ remainder"), &product, &remainder)?;
+        let lt = self.lt(layouter.namespace(|| "remainder < b"), &remainder, b)?;
+        layouter.assign_region(
+            || "division",
+            |mut region| {
+                region.constrain_equal(sum.cell(), a.cell())?;
+                region.constrain_constant(lt.cell(), pallas::Base::ONE)
+            },
+        )?;
+
+        Ok((quotient, remainder))
+    }
+
+    /// `floor(a * b / c)` as `(quotient, remainder)`, over the full 128-bit
+    /// product; fails if `c = 0` or the quotient overflows
+    pub fn mul_div(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+        c: &AssignedBase,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        let division = a.value().zip(b.value()).zip(c.value()).map(|((a, b), c)| {
+            let product = to_u128(a) * to_u128(b);
+            let c = to_u128(c);
+            product
+                .checked_div(c)
+                .zip(product.checked_rem(c))
+                .unwrap_or_default()
+        });
+        let quotient = self.witness(
+            layouter.namespace(|| "quotient"),
+            division.map(|(q, _)| q as u64),
+        )?;
+        let remainder = self.witness(
+            layouter.namespace(|| "remainder"),
+            division.map(|(_, r)| r as u64),
+        )?;
+
+        // a * b = quotient * c + remainder; both sides are below 2^129, so
+        // equality in the field is equality of integers
+        let product = self.assign_exact_mul(layouter.namespace(|| "a * b"), a, b)?;
+        let scaled = self.assign_exact_mul(layouter.namespace(|| "quotient * c"), &quotient, c)?;
+        self.assign_sum(
+            layouter.namespace(|| "+ remainder"),
+            &scaled,
+            &remainder,
+            &product,
+        )?;
+        let lt = self.lt(layouter.namespace(|| "remainder < c"), &remainder, c)?;
+        layouter.assign_region(
+            || "division",
+            |mut region| region.constrain_constant(lt.cell(), pallas::Base::ONE),
+        )?;
+
+        Ok((quotient, remainder))
+    }
+
+    /// Constrain `a != 0`
+    pub fn assert_nonzero(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+    ) -> Result<(), Error> {
+        let one = self.constant(layouter.namespace(|| "one"), 1)?;
+        self.assert_le(layouter.namespace(|| "a >= 1"), &one, a)
+    }
+
+    /// Constrain `a = b` unless `flag = 0`, as `flag * (a - b) = 0`
+    ///
+    /// `flag` must be a u64; `a` and `b` may be any field elements.
+    pub fn assert_equal_unless_zero(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        flag: &AssignedBase,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<(), Error> {
+        // Laid out as difference + b = a
+        let difference = a.value().zip(b.value()).map(|(a, b)| *a - *b);
+        let difference = layouter.assign_region(
+            || "difference",
+            |mut region| region.assign_advice(|| "difference", self.advices[0], 0, || difference),
+        )?;
+        self.assign_sum(layouter.namespace(|| "a - b"), &difference, b, a)?;
+
+        let product =
+            self.assign_exact_mul(layouter.namespace(|| "flag * (a - b)"), flag, &difference)?;
+        layouter.assign_region(
+            || "flag * (a - b) = 0",
+            |mut region| region.constrain_constant(product.cell(), pallas::Base::ZERO),
+        )
+    }
+
+    /// The low `num_bits` bits of `x`, for `0 < num_bits < 254`
+    ///
+    /// `x` is split as `hi * 2^num_bits + lo` with `hi < 2^(254 - num_bits)`,
+    /// so the split cannot wrap mod p and is unique. Elements `x >= 2^254`
+    /// have no split and leave the circuit unsatisfiable.
+    pub fn low_bits(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        x: &AssignedBase,
+        num_bits: usize,
+    ) -> Result<AssignedBase, Error> {
+        assert!(num_bits > 0 && num_bits < 254, "split width out of bounds");
+        let shift = pallas::Base::from(2).pow_vartime([num_bits as u64]);
+
+        let lo = x.value().map(|x| truncate(x, num_bits));
+        let hi = x
+            .value()
+            .zip(lo)
+            .map(|(x, lo)| (*x - lo) * shift.invert().unwrap());
+        let (hi, lo, shift) = layouter.assign_region(
+            || "split",
+            |mut region| {
+                let hi = region.assign_advice(|| "hi", self.advices[0], 0, || hi)?;
+                let lo = region.assign_advice(|| "lo", self.advices[1], 0, || lo)?;
+                let shift = region.assign_advice_from_constant(
+                    || "2^num_bits",
+                    self.advices[2],
+                    0,
+                    shift,
+                )?;
+                Ok((hi, lo, shift))
+            },
+        )?;
+        self.range
+            .range_check(layouter.namespace(|| "lo"), lo.clone(), num_bits)?;
+        self.range
+            .range_check(layouter.namespace(|| "hi"), hi.clone(), 254 - num_bits)?;
+
+        // hi * 2^num_bits + lo = x
+        let scaled =
+            self.assign_exact_mul(layouter.namespace(|| "hi * 2^num_bits"), &hi, &shift)?;
+        self.assign_sum(layouter.namespace(|| "+ lo"), &scaled, &lo, x)?;
+        Ok(lo)
+    }
+
+    /// Constrain `a < b` for `a, b < 2^num_bits`, with `num_bits < 254`
+    ///
+    /// Checks `b - a - 1 < 2^num_bits`, which wraps to a larger element when
+    /// `a >= b`. The bounds on `a` and `b` are not checked here.
+    pub fn assert_lt_bits(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        // Laid out as (gap + 1) + a = b
+        let gap = a
+            .value()
+            .zip(b.value())
+            .map(|(a, b)| *b - *a - pallas::Base::ONE);
+        let gap = layouter.assign_region(
+            || "gap",
+            |mut region| region.assign_advice(|| "gap", self.advices[0], 0, || gap),
+        )?;
+        let one = self.constant(layouter.namespace(|| "one"), 1)?;
+        let step = gap.value().map(|gap| *gap + pallas::Base::ONE);
+        let step = self.assign_add(layouter.namespace(|| "gap + 1"), &gap, &one, step)?;
+        self.assign_sum(layouter.namespace(|| "+ a"), &step, a, b)?;
+
+        self.range
+            .range_check(layouter.namespace(|| "gap"), gap, num_bits)
+    }
+
+    /// A fixed amount
+    pub fn constant(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: u64,
+    ) -> Result<AssignedBase, Error> {
+        layouter.assign_region(
+            || "u64 constant",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "constant",
+                    self.advices[0],
+                    0,
+                    pallas::Base::from(value),
+                )
+            },
+        )
+    }
+
+    /// Assign `a + b = sum` with a fresh `sum` cell
+    fn assign_add(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+        sum: Value<pallas::Base>,
+    ) -> Result<AssignedBase, Error> {
+        layouter.assign_region(
+            || "u64 add",
+            |mut region| {
+                self.q_add.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.advices[0], 0)?;
+                b.copy_advice(|| "b", &mut region, self.advices[1], 0)?;
+                region.assign_advice(|| "sum", self.advices[2], 0, || sum)
+            },
+        )
+    }
+
+    /// Constrain `a + b = sum` over existing cells
+    fn assign_sum(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+        sum: &AssignedBase,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "u64 add",
+            |mut region| {
+                self.q_add.enable(&mut region, 0)?;
+                a.copy_advice(|| "a", &mut region, self.advices[0], 0)?;
+                b.copy_advice(|| "b", &mut region, self.advices[1], 0)?;
+                sum.copy_advice(|| "sum", &mut region, self.advices[2], 0)?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Assign `a * b = hi * 2^64 + lo`, without range checks
+    fn assign_mul(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        layouter.assign_region(
+            || "u64 mul",
+            |mut region| {
+                self.q_mul.enable(&mut region, 0)?;
+                let a = a.copy_advice(|| "a", &mut region, self.advices[0], 0)?;
+                let b = b.copy_advice(|| "b", &mut region, self.advices[1], 0)?;
+
+                let product = a.value().zip(b.value()).map(|(a, b)| *a * *b);
+                let lo = product.map(|p| pallas::Base::from(to_u128(&p) as u64));
+                let hi = product
+                    .zip(lo)
+                    .map(|(p, lo)| (p - lo) * two_pow_64().invert().unwrap());
+                let hi = region.assign_advice(|| "hi", self.advices[2], 0, || hi)?;
+                let lo = region.assign_advice(|| "lo", self.advices[3], 0, || lo)?;
+                Ok((hi, lo))
+            },
+        )
+    }
+
+    /// Assign `a * b` as one field element (`hi = 0`), exact for u64 inputs
+    fn assign_exact_mul(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedBase,
+        b: &AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        layouter.assign_region(
+            || "u64 exact mul",
+            |mut region| {
+                self.q_mul.enable(&mut region, 0)?;
+                let a = a.copy_advice(|| "a", &mut region, self.advices[0], 0)?;
+                let b = b.copy_advice(|| "b", &mut region, self.advices[1], 0)?;
+
+                let product = a.value().zip(b.value()).map(|(a, b)| *a * *b);
+                region.assign_advice_from_constant(
+                    || "hi",
+                    self.advices[2],
+                    0,
+                    pallas::Base::ZERO,
+                )?;
+                region.assign_advice(|| "product", self.advices[3], 0, || product)
+            },
+        )
+    }
+}
+
+fn two_pow_64() -> pallas::Base {
+    pallas::Base::from_u128(1 << 64)
+}
+
+/// Low 128 bits of a field element
+fn to_u128(value: &pallas::Base) -> u128 {
+    u128::from_le_bytes(value.to_repr()[..16].try_into().unwrap())
+}
+
+/// Low `num_bits` bits of a field element
+fn truncate(value: &pallas::Base, num_bits: usize) -> pallas::Base {
+    let mut repr = value.to_repr();
+    for (i, byte) in repr.iter_mut().enumerate() {
+        let kept = num_bits.saturating_sub(i * 8).min(8);
+        *byte &= ((1u16 << kept) - 1) as u8;
+    }
+    pallas::Base::from_repr(repr).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::circuit::floor_planner;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::Circuit;
+    use privl1_crypto::commitment::PedersenCommitment;
+    use privl1_crypto::{PoseidonHash, Scalar};
+    use rand::rngs::OsRng;
+
+    use crate::note::NoteConfig;
+
+    const K: u32 = 11;
+    const DEPTH: usize = 4;
+
+    #[derive(Clone, Debug)]
+    enum Case {
+        Range(pallas::Base, usize),
+        Add(u64, u64),
+        Sub(u64, u64),
+        Lt(u64, u64),
+        MulWide(u64, u64),
+        CheckedMul(u64, u64),
+        DivRem(u64, u64),
+        MulDiv(u64, u64, u64),
+        Nonzero(u64),
+        EqualUnlessZero(u64, pallas::Base, pallas::Base),
+        LowBits(pallas::Base, usize),
+        LtBits(pallas::Base, pallas::Base, usize),
+        Merkle(pallas::Base, [pallas::Base; DEPTH], u64),
+        ValueCommit(u64, pallas::Scalar),
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestConfig {
+        note: NoteConfig,
+        arith: U64Config,
+        merkle: MerklePathConfig<DEPTH>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestCircuit(Case);
+
+    impl Circuit<pallas::Base> for TestCircuit {
+        type Config = TestConfig;
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> TestConfig {
+            let note = NoteConfig::configure(meta);
+            let arith = U64Config::configure(
+                meta,
+                note.advices[..4].try_into().unwrap(),
+                note.range.clone(),
+            );
+            let merkle = MerklePathConfig::configure(
+                meta,
+                note.advices[..5].try_into().unwrap(),
+                note.poseidon.clone(),
+            );
+            TestConfig {
+                note,
+                arith,
+                merkle,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: TestConfig,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), Error> {
+            config.note.load(&mut layouter)?;
+            let arith = &config.arith;
+
+            let outputs = match self.0.clone() {
+                Case::Range(value, num_bits) => {
+                    let cell = assign_free_advice(
+                        layouter.namespace(|| "value"),
+                        config.note.advices[0],
+                        Value::known(value),
+                    )?;
+                    config.note.range.range_check(
+                        layouter.namespace(|| "range"),
+                        cell,
+                        num_bits,
+                    )?;
+                    vec![]
+                }
+                Case::Add(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    vec![arith.checked_add(layouter.namespace(|| "add"), &a, &b)?]
+                }
+                Case::Sub(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    vec![arith.checked_sub(layouter.namespace(|| "sub"), &a, &b)?]
+                }
+                Case::Lt(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    vec![arith.lt(layouter.namespace(|| "lt"), &a, &b)?]
+                }
+                Case::MulWide(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    let (hi, lo) = arith.mul_wide(layouter.namespace(|| "mul"), &a, &b)?;
+                    vec![hi, lo]
+                }
+                Case::CheckedMul(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    vec![arith.checked_mul(layouter.namespace(|| "mul"), &a, &b)?]
+                }
+                Case::DivRem(a, b) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    let (q, r) = arith.div_rem(layouter.namespace(|| "div"), &a, &b)?;
+                    vec![q, r]
+                }
+                Case::MulDiv(a, b, c) => {
+                    let (a, b) = witness_pair(arith, layouter.namespace(|| "inputs"), a, b)?;
+                    let c = arith.witness(layouter.namespace(|| "c"), Value::known(c))?;
+                    let (q, r) = arith.mul_div(layouter.namespace(|| "mul div"), &a, &b, &c)?;
+                    vec![q, r]
+                }
+                Case::Nonzero(a) => {
+                    let a = arith.witness(layouter.namespace(|| "a"), Value::known(a))?;
+                    arith.assert_nonzero(layouter.namespace(|| "nonzero"), &a)?;
+                    vec![]
+                }
+                Case::EqualUnlessZero(flag, a, b) => {
+                    let flag = arith.witness(layouter.namespace(|| "flag"), Value::known(flag))?;
+                    let a = assign_free_advice(
+                        layouter.namespace(|| "a"),
+                        config.note.advices[1],
+                        Value::known(a),
+                    )?;
+                    let b = assign_free_advice(
+                        layouter.namespace(|| "b"),
+                        config.note.advices[2],
+                        Value::known(b),
+                    )?;
+                    arith.assert_equal_unless_zero(
+                        layouter.namespace(|| "equal"),
+                        &flag,
+                        &a,
+                        &b,
+                    )?;
+                    vec![]
+                }
+                Case::LowBits(x, num_bits) => {
+                    let x = assign_free_advice(
+                        layouter.namespace(|| "x"),
+                        config.note.advices[3],
+                        Value::known(x),
+                    )?;
+                    vec![arith.low_bits(layouter.namespace(|| "low bits"), &x, num_bits)?]
+                }
+                Case::LtBits(a, b, num_bits) => {
+                    let a = assign_free_advice(
+                        layouter.namespace(|| "a"),
+                        config.note.advices[1],
+                        Value::known(a),
+                    )?;
+                    let b = assign_free_advice(
+                        layouter.namespace(|| "b"),
+                        config.note.advices[2],
+                        Value::known(b),
+                    )?;
+                    arith.assert_lt_bits(layouter.namespace(|| "lt"), &a, &b, num_bits)?;
+                    vec![]
+                }
+                Case::Merkle(leaf, path, position) => {
+                    let leaf = assign_free_advice(
+                        layouter.namespace(|| "leaf"),
+                        config.note.advices[0],
+                        Value::known(leaf),
+                    )?;
+                    let (root, position) = config.merkle.calculate_root(
+                        layouter.namespace(|| "path"),
+                        leaf,
+                        Value::known(path),
+                        Value::known(position),
+                    )?;
+                    vec![root, position]
+                }
+                Case::ValueCommit(value, rcv) => {
+                    let value = assign_free_advice(
+                        layouter.namespace(|| "value"),
+                        config.note.advices[0],
+                        Value::known(pallas::Base::from(value)),
+                    )?;
+                    let cv = config.note.value_commit.commit(
+                        layouter.namespace(|| "cv"),
+                        value,
+                        Value::known(rcv),
+                    )?;
+                    vec![cv.inner().x(), cv.inner().y()]
+                }
+            };
+
+            for (row, cell) in outputs.iter().enumerate() {
+                layouter.constrain_instance(cell.cell(), config.note.primary, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn witness_pair(
+        config: &U64Config,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: u64,
+        b: u64,
+    ) -> Result<(AssignedBase, AssignedBase), Error> {
+        let a = config.witness(layouter.namespace(|| "a"), Value::known(a))?;
+        let b = config.witness(layouter.namespace(|| "b"), Value::known(b))?;
+        Ok((a, b))
+    }
+
+    fn verify(case: Case, outputs: Vec<pallas::Base>) -> bool {
+        MockProver::run(K, &TestCircuit(case), vec![outputs])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn test_range_check() {
+        for num_bits in [3, 10, 23, 64, 100] {
+            let max = (1u128 << num_bits) - 1;
+            assert!(verify(
+                Case::Range(pallas::Base::from_u128(max), num_bits),
+                vec![]
+            ));
+            assert!(!verify(
+                Case::Range(pallas::Base::from_u128(max + 1), num_bits),
+                vec![]
+            ));
+        }
+        assert!(!verify(Case::Range(-pallas::Base::ONE, 64), vec![]));
+    }
+
+    #[test]
+    fn test_checked_add_sub() {
+        let base = pallas::Base::from;
+
+        assert!(verify(Case::Add(2, 3), vec![base(5)]));
+        assert!(!verify(Case::Add(2, 3), vec![base(6)]));
+        assert!(verify(Case::Add(u64::MAX - 1, 1), vec![base(u64::MAX)]));
+        assert!(!verify(
+            Case::Add(u64::MAX, 1),
+            vec![pallas::Base::from_u128(1 << 64)]
+        ));
+
+        assert!(verify(Case::Sub(5, 3), vec![base(2)]));
+        assert!(verify(Case::Sub(3, 3), vec![base(0)]));
+        assert!(!verify(Case::Sub(3, 5), vec![-base(2)]));
+    }
+
+    #[test]
+    fn test_lt() {
+        let (zero, one) = (pallas::Base::ZERO, pallas::Base::ONE);
+
+        assert!(verify(Case::Lt(1, 2), vec![one]));
+        assert!(verify(Case::Lt(2, 2), vec![zero]));
+        assert!(verify(Case::Lt(u64::MAX, 0), vec![zero]));
+        assert!(verify(Case::Lt(0, u64::MAX), vec![one]));
+        assert!(!verify(Case::Lt(1, 2), vec![zero]));
+        assert!(!verify(Case::Lt(2, 1), vec![one]));
+    }
+
+    #[test]
+    fn test_mul_overflow() {
+        let base = pallas::Base::from;
+        let wide = |a: u64, b: u64| {
+            let product = a as u128 * b as u128;
+            vec![base((product >> 64) as u64), base(product as u64)]
+        };
+
+        assert!(verify(
+            Case::MulWide(u64::MAX, u64::MAX),
+            wide(u64::MAX, u64::MAX)
+        ));
+        assert!(verify(Case::MulWide(6, 7), wide(6, 7)));
+        assert!(!verify(Case::MulWide(6, 7), vec![base(1), base(42)]));
+
+        assert!(verify(
+            Case::CheckedMul(1 << 32, (1 << 32) - 1),
+            vec![base((1 << 32) * ((1 << 32) - 1))]
+        ));
+        assert!(!verify(Case::CheckedMul(1 << 32, 1 << 32), vec![base(0)]));
+    }
+
+    #[test]
+    fn test_div_rem() {
+        let base = pallas::Base::from;
+
+        assert!(verify(Case::DivRem(17, 5), vec![base(3), base(2)]));
+        assert!(verify(Case::DivRem(4, 5), vec![base(0), base(4)]));
+        assert!(verify(
+            Case::DivRem(u64::MAX, 1),
+            vec![base(u64::MAX), base(0)]
+        ));
+        // Other results are rejected, and division by zero has no witness
+        assert!(!verify(Case::DivRem(17, 5), vec![base(2), base(7)]));
+        assert!(!verify(Case::DivRem(17, 5), vec![base(4), -base(3)]));
+        assert!(!verify(Case::DivRem(17, 0), vec![base(0), base(0)]));
+
+        // The product may exceed 64 bits as long as the quotient does not
+        assert!(verify(Case::MulDiv(17, 3, 5), vec![base(10), base(1)]));
+        assert!(verify(
+            Case::MulDiv(u64::MAX, u64::MAX - 1, u64::MAX),
+            vec![base(u64::MAX - 1), base(0)]
+        ));
+        let (n, d) = (1u128 << 80, (1u128 << 30) + 1);
+        assert!(verify(
+            Case::MulDiv(1 << 40, 1 << 40, d as u64),
+            vec![base((n / d) as u64), base((n % d) as u64)]
+        ));
+        assert!(!verify(Case::MulDiv(17, 3, 5), vec![base(9), base(6)]));
+        assert!(!verify(
+            Case::MulDiv(u64::MAX, 2, 1),
+            vec![base(0), base(0)]
+        ));
+        assert!(!verify(Case::MulDiv(17, 3, 0), vec![base(0), base(0)]));
+
+        assert!(verify(Case::Nonzero(1), vec![]));
+        assert!(!verify(Case::Nonzero(0), vec![]));
+
+        let (x, y) = (pallas::Base::from(7), -pallas::Base::from(7));
+        assert!(verify(Case::EqualUnlessZero(5, x, x), vec![]));
+        assert!(verify(Case::EqualUnlessZero(0, x, y), vec![]));
+        assert!(!verify(Case::EqualUnlessZero(5, x, y), vec![]));
+        assert!(!verify(Case::EqualUnlessZero(u64::MAX, y, x), vec![]));
+    }
+
+    #[test]
+    fn test_wide_bits() {
+        let two_pow = |bits: u64| pallas::Base::from(2).pow_vartime([bits]);
+        let low = two_pow(249) + pallas::Base::from(12_345);
+        let x = two_pow(253) + two_pow(251) + low;
+        assert_eq!(truncate(&x, 250), low);
+
+        assert!(verify(Case::LowBits(x, 250), vec![low]));
+        assert!(verify(
+            Case::LowBits(pallas::Base::from(0x1234), 8),
+            vec![pallas::Base::from(0x34)]
+        ));
+        // Other outputs are rejected, and x >= 2^254 has no split
+        assert!(!verify(
+            Case::LowBits(x, 250),
+            vec![low + pallas::Base::ONE]
+        ));
+        assert!(!verify(
+            Case::LowBits(two_pow(254), 250),
+            vec![pallas::Base::ZERO]
+        ));
+
+        let (a, b) = (two_pow(249), two_pow(250) - pallas::Base::ONE);
+        assert!(verify(Case::LtBits(a, b, 250), vec![]));
+        assert!(verify(
+            Case::LtBits(pallas::Base::ZERO, pallas::Base::ONE, 250),
+            vec![]
+        ));
+        assert!(!verify(Case::LtBits(b, a, 250), vec![]));
+        assert!(!verify(Case::LtBits(a, a, 250), vec![]));
+    }
+
+    #[test]
+    fn test_merkle_path() {
+        let leaf = pallas::Base::from(7);
+        let path = [1u64, 2, 3, 4].map(pallas::Base::from);
+
+        for position in [0u64, 5, 15] {
+            let root = path
+                .iter()
+                .enumerate()
+                .fold(leaf, |node, (level, sibling)| {
+                    if (position >> level) & 1 == 0 {
+                        PoseidonHash::hash_two(node, *sibling).to_field()
+                    } else {
+                        PoseidonHash::hash_two(*sibling, node).to_field()
+                    }
+                });
+            let position_base = pallas::Base::from(position);
+
+            assert!(verify(
+                Case::Merkle(leaf, path, position),
+                vec![root, position_base]
+            ));
+            assert!(!verify(
+                Case::Merkle(leaf, path, position ^ 1),
+                vec![root, position_base]
+            ));
+            assert!(!verify(
+                Case::Merkle(leaf + pallas::Base::ONE, path, position),
+                vec![root, position_base]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_value_commitment() {
+        let rcv = Scalar::random(&mut OsRng);
+        let cv = PedersenCommitment::new().commit_with_blinding(1_000, rcv);
+        let (x, y) = cv.as_point().coordinates();
+
+        assert!(verify(Case::ValueCommit(1_000, *rcv.inner()), vec![x, y]));
+        assert!(!verify(Case::ValueCommit(1_001, *rcv.inner()), vec![x, y]));
+    }
+}
@@ -0,0 +1,595 @@
+//! Anonymous gauge-vote circuit
+//!
+//! Proves a veNFT holder voted with at most the lock's voting power, without
+//! revealing which veNFT voted. For public `(anchor, epoch, vnf, rk, (pool,
+//! weight) × MAX_VOTES)`, the circuit checks:
+//! - `ve_cm = Poseidon(nft_id, owner.x, owner.y, vp.x, vp.y)` is a leaf of the
+//!   veNFT tree with root `anchor`, where `vp = [voting_power]V + [r]R`
+//! - `owner = ak + [nk]N`, so the prover holds the owner's nullifier key
+//! - `vnf = Poseidon(VOTE_NULLIFIER_DOMAIN, nk, ve_cm, epoch)`, as
+//!   `NullifierDerivingKey::derive_vote_nullifier` computes it
+//! - the weights sum to at most `voting_power`, without u64 overflow
+//! - `rk = ak + [alpha]G`, the key the vote authorization is checked against
+//!
+//! The vote nullifier is the same for every vote of an NFT in an epoch, so
+//! the gauge pallet rejects a second one. Unused vote slots have weight zero.
+//! For each weighted pool the pallet appends a [`vote_receipt`] to the receipt
+//! tree, which the bribe-claim circuit proves membership in. A
+//! `GaugeVoteProof` only verifies once signed under `rk` (see
+//! [`crate::authorization`]).
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::halo2;
+use privl1_crypto::keys::{self, FullKeys};
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::nullifier::{Nullifier, VOTE_NULLIFIER_DOMAIN};
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, PoseidonHash, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::authorization::{AuthorizedProof, SpendAuthorizing};
+use crate::gadgets::{self, assign_free_advice, MerklePathConfig, U64Config};
+use crate::note::NoteConfig;
+use crate::spend::{authentication_path, merkle_root};
+use crate::ve_lock::VeNft;
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 13;
+
+/// Name the vote verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "gauge-vote-v1";
+
+/// Version of the vote circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Pools a single vote can weight
+pub const MAX_VOTES: usize = 4;
+
+/// Domain tag of vote receipts ("rcpt")
+pub const VOTE_RECEIPT_DOMAIN: u64 = 0x7263_7074;
+
+/// Public input rows
+pub const ANCHOR: usize = 0;
+pub const EPOCH: usize = 1;
+pub const VOTE_NULLIFIER: usize = 2;
+pub const RK_X: usize = 3;
+pub const RK_Y: usize = 4;
+/// First `(pool, weight)` pair; vote `i` uses rows `VOTES + 2i` and `VOTES + 2i + 1`
+pub const VOTES: usize = 5;
+
+/// Row of the `i`th vote's pool id
+pub const fn pool_row(i: usize) -> usize {
+    VOTES + 2 * i
+}
+
+/// Row of the `i`th vote's weight
+pub const fn weight_row(i: usize) -> usize {
+    VOTES + 2 * i + 1
+}
+
+/// Configuration of the vote circuit
+#[derive(Clone, Debug)]
+pub struct GaugeVoteConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+/// The vote circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct GaugeVoteCircuit {
+    nft_id: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    voting_power: Value<u64>,
+    r_power: Value<pallas::Scalar>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+    alpha: Value<pallas::Scalar>,
+}
+
+impl Circuit<pallas::Base> for GaugeVoteCircuit {
+    type Config = GaugeVoteConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> GaugeVoteConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        GaugeVoteConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: GaugeVoteConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        let arith = &config.arith;
+        note.load(&mut layouter)?;
+
+        let epoch = note.public_amount(layouter.namespace(|| "epoch"), EPOCH)?;
+
+        // The weights sum to at most the committed voting power
+        let mut total = arith.constant(layouter.namespace(|| "no weight"), 0)?;
+        for i in 0..MAX_VOTES {
+            note.public_base(layouter.namespace(|| format!("pool {}", i)), pool_row(i))?;
+            let weight = note.public_amount(
+                layouter.namespace(|| format!("weight {}", i)),
+                weight_row(i),
+            )?;
+            total = arith.checked_add(
+                layouter.namespace(|| format!("total {}", i)),
+                &total,
+                &weight,
+            )?;
+        }
+        let voting_power =
+            arith.witness(layouter.namespace(|| "voting_power"), self.voting_power)?;
+        arith.assert_le(
+            layouter.namespace(|| "total <= voting_power"),
+            &total,
+            &voting_power,
+        )?;
+        let vp = note.value_commitment(layouter.namespace(|| "vp"), voting_power, self.r_power)?;
+
+        // Open the veNFT leaf
+        let nft_id = assign_free_advice(
+            layouter.namespace(|| "nft_id"),
+            note.advices[2],
+            self.nft_id,
+        )?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let ve_cm = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "ve_cm"),
+            [
+                nft_id,
+                owner.inner().x(),
+                owner.inner().y(),
+                vp.inner().x(),
+                vp.inner().y(),
+            ],
+        )?;
+        let (anchor, _) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            ve_cm.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+
+        // One nullifier per NFT and epoch
+        let domain = arith.constant(layouter.namespace(|| "domain"), VOTE_NULLIFIER_DOMAIN)?;
+        let vnf = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "vote nullifier"),
+            [domain, nk, ve_cm, epoch],
+        )?;
+        layouter.constrain_instance(vnf.cell(), note.primary, VOTE_NULLIFIER)?;
+
+        let rk = note.randomize_key(layouter.namespace(|| "rk"), &ak, self.alpha)?;
+        note.expose_point(&mut layouter, &rk, RK_X)
+    }
+}
+
+/// Weight given to one pool's gauge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GaugeVote {
+    /// Pool id
+    pub pool: u32,
+    /// Voting power assigned to the pool
+    pub weight: u64,
+}
+
+/// Receipt of `vote` under vote nullifier `vnf`
+///
+/// `receipt = Poseidon(VOTE_RECEIPT_DOMAIN, vnf, pool, weight)`, computed from
+/// public inputs of an accepted vote.
+pub fn vote_receipt(vnf: &Nullifier, vote: &GaugeVote) -> pallas::Base {
+    let vnf = Option::from(pallas::Base::from_repr(*vnf.as_bytes()))
+        .expect("Poseidon output is canonical");
+    PoseidonHash::hash([
+        pallas::Base::from(VOTE_RECEIPT_DOMAIN),
+        vnf,
+        pallas::Base::from(vote.pool as u64),
+        pallas::Base::from(vote.weight),
+    ])
+    .to_field()
+}
+
+/// Private inputs of a vote
+#[derive(Clone, Debug)]
+pub struct GaugeVoteWitness {
+    nft: VeNft,
+    ak: NativePoint,
+    nk: pallas::Base,
+    path: [pallas::Base; TREE_DEPTH],
+    position: u64,
+    alpha: Scalar,
+    epoch: u64,
+    votes: [GaugeVote; MAX_VOTES],
+    vote_nullifier: Nullifier,
+}
+
+impl GaugeVoteWitness {
+    /// Build the witness for voting `nft` (at `merkle_proof.position` in the
+    /// veNFT tree) in `epoch`
+    ///
+    /// Fails if the keys do not own the NFT, there are more than `MAX_VOTES`
+    /// votes, or their weights exceed the NFT's voting power.
+    pub fn new<R: RngCore>(
+        nft: &VeNft,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        epoch: u64,
+        votes: &[GaugeVote],
+        rng: &mut R,
+    ) -> Result<Self> {
+        if nft.owner != keys.public {
+            return Err(CircuitError::InvalidParameters(
+                "veNFT is not owned by these keys".into(),
+            ));
+        }
+        if votes.len() > MAX_VOTES {
+            return Err(CircuitError::InvalidParameters(format!(
+                "At most {} pools per vote",
+                MAX_VOTES
+            )));
+        }
+        votes
+            .iter()
+            .try_fold(0u64, |total, vote| total.checked_add(vote.weight))
+            .filter(|total| *total <= nft.voting_power)
+            .ok_or_else(|| {
+                CircuitError::InvalidParameters("Votes exceed the voting power".into())
+            })?;
+
+        let mut padded = [GaugeVote::default(); MAX_VOTES];
+        padded[..votes.len()].copy_from_slice(votes);
+
+        Ok(Self {
+            nft: nft.clone(),
+            ak: keys.spending.validating_key(),
+            nk: keys.nullifier.to_base(),
+            path: authentication_path(merkle_proof)?,
+            position: merkle_proof.position,
+            alpha: Scalar::random(rng),
+            epoch,
+            votes: padded,
+            vote_nullifier: keys
+                .nullifier
+                .derive_vote_nullifier(nft.commitment(), epoch),
+        })
+    }
+
+    /// Root of the veNFT tree the NFT's authentication path hashes to
+    pub fn anchor(&self) -> MerkleRoot {
+        merkle_root(self.nft.commitment(), &self.path, self.position)
+    }
+
+    /// Nullifier of this NFT's vote in the epoch
+    pub fn vote_nullifier(&self) -> Nullifier {
+        self.vote_nullifier
+    }
+
+    /// Re-randomized key the vote authorization is checked against
+    pub fn rk(&self) -> NativePoint {
+        keys::randomize_validating_key(&self.ak, &self.alpha)
+    }
+
+    /// Randomizer of the spend key, needed to sign the vote authorization
+    pub fn alpha(&self) -> &Scalar {
+        &self.alpha
+    }
+
+    /// Votes, padded with zero-weight slots to `MAX_VOTES`
+    pub fn votes(&self) -> &[GaugeVote; MAX_VOTES] {
+        &self.votes
+    }
+
+    /// Receipts of the weighted pools, in vote order
+    pub fn receipts(&self) -> Vec<pallas::Base> {
+        self.votes
+            .iter()
+            .filter(|vote| vote.weight > 0)
+            .map(|vote| vote_receipt(&self.vote_nullifier, vote))
+            .collect()
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let to_base = |bytes: &[u8; 32]| {
+            Option::from(pallas::Base::from_repr(*bytes)).expect("Poseidon output is canonical")
+        };
+        let (rk_x, rk_y) = self.rk().coordinates();
+
+        let mut instances = vec![
+            to_base(self.anchor().as_bytes()),
+            pallas::Base::from(self.epoch),
+            to_base(self.vote_nullifier.as_bytes()),
+            rk_x,
+            rk_y,
+        ];
+        for vote in &self.votes {
+            instances.push(pallas::Base::from(vote.pool as u64));
+            instances.push(pallas::Base::from(vote.weight));
+        }
+        instances
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> GaugeVoteCircuit {
+        GaugeVoteCircuit {
+            nft_id: Value::known(pallas::Base::from(self.nft.nft_id)),
+            ak: Value::known(self.ak.inner().to_affine()),
+            nk: Value::known(self.nk),
+            voting_power: Value::known(self.nft.voting_power),
+            r_power: Value::known(*self.nft.blinding.inner()),
+            path: Value::known(self.path),
+            position: Value::known(self.position),
+            alpha: Value::known(*self.alpha.inner()),
+        }
+    }
+}
+
+impl SpendAuthorizing for GaugeVoteCircuit {
+    const SIGHASH_DOMAIN: &'static str = "PRIVL1_GAUGE_VOTE_SIGHASH";
+    const RK_X: usize = RK_X;
+    const RK_Y: usize = RK_Y;
+}
+
+/// Vote proof with the veNFT owner's spend authorization
+pub type GaugeVoteProof = AuthorizedProof<GaugeVoteCircuit>;
+
+crate::keystore::circuit_keys!("vote", GaugeVoteCircuit, GaugeVoteProvingKey);
+
+/// Prove a vote
+///
+/// The proof is unsigned; `GaugeVoteProof::authorize` it with the veNFT
+/// owner's spending key and `witness.alpha()`.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &GaugeVoteProvingKey,
+    witness: &GaugeVoteWitness,
+    rng: R,
+) -> Result<GaugeVoteProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    let proof = Halo2Proof::new(
+        proof,
+        instances.iter().map(halo2::encode_instance).collect(),
+        key.verification_key().id(),
+    );
+    Ok(GaugeVoteProof::new(proof, witness.rk()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use rand::rngs::OsRng;
+
+    /// A veNFT with `voting_power` at position 1 of a two-leaf veNFT tree
+    fn ve_nft(voting_power: u64) -> (FullKeys, VeNft, MerkleProof) {
+        let keys = FullKeys::random(&mut OsRng);
+        let nft = VeNft {
+            nft_id: 42,
+            owner: keys.public,
+            voting_power,
+            blinding: Scalar::random(&mut OsRng),
+        };
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append([0u8; 32]).unwrap();
+        let position = tree.append(nft.leaf()).unwrap();
+        let merkle_proof = tree.prove(position).unwrap();
+        assert!(merkle_proof.verify(&nft.leaf(), &tree.root()));
+
+        (keys, nft, merkle_proof)
+    }
+
+    fn vote_witness(
+        voting_power: u64,
+        epoch: u64,
+        votes: &[GaugeVote],
+    ) -> Result<GaugeVoteWitness> {
+        let (keys, nft, merkle_proof) = ve_nft(voting_power);
+        GaugeVoteWitness::new(&nft, &merkle_proof, &keys, epoch, votes, &mut OsRng)
+    }
+
+    const VOTES_50_50: [GaugeVote; 2] = [
+        GaugeVote {
+            pool: 1,
+            weight: 500,
+        },
+        GaugeVote {
+            pool: 2,
+            weight: 500,
+        },
+    ];
+
+    #[test]
+    fn test_vote_circuit() {
+        // All of the voting power, part of it, or none
+        for votes in [&VOTES_50_50[..], &VOTES_50_50[..1], &[]] {
+            let witness = vote_witness(1_000, 7, votes).unwrap();
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_vote_nullifier_per_epoch() {
+        let (keys, nft, merkle_proof) = ve_nft(1_000);
+        let vote = |epoch| {
+            GaugeVoteWitness::new(&nft, &merkle_proof, &keys, epoch, &VOTES_50_50, &mut OsRng)
+                .unwrap()
+        };
+
+        // Voting twice in an epoch reveals the same nullifier; rk is fresh
+        let (first, second) = (vote(7), vote(7));
+        assert_eq!(first.vote_nullifier(), second.vote_nullifier());
+        assert_ne!(first.rk(), second.rk());
+        assert_eq!(first.anchor(), second.anchor());
+
+        // Another epoch gives an unrelated nullifier
+        assert_ne!(first.vote_nullifier(), vote(8).vote_nullifier());
+        assert_eq!(
+            first.vote_nullifier(),
+            keys.nullifier.derive_vote_nullifier(nft.commitment(), 7)
+        );
+
+        // One receipt per weighted pool
+        let receipts = first.receipts();
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(
+            receipts[0],
+            vote_receipt(&first.vote_nullifier(), &VOTES_50_50[0])
+        );
+        assert_ne!(receipts[0], receipts[1]);
+        assert_eq!(receipts, second.receipts());
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let witness = vote_witness(1_000, 7, &VOTES_50_50).unwrap();
+
+        // Pool ids are only copied into the circuit, so any pool satisfies it;
+        // the proof binds them, see `test_prove_and_verify`
+        let rows = [ANCHOR, EPOCH, VOTE_NULLIFIER, RK_X, RK_Y]
+            .into_iter()
+            .chain((0..MAX_VOTES).map(weight_row));
+        for row in rows {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_overvote_rejected() {
+        assert!(vote_witness(999, 7, &VOTES_50_50).is_err());
+        assert!(vote_witness(
+            u64::MAX,
+            7,
+            &[GaugeVote {
+                pool: 1,
+                weight: u64::MAX
+            }; 2]
+        )
+        .is_err());
+        assert!(vote_witness(1_000, 7, &[GaugeVote::default(); MAX_VOTES + 1]).is_err());
+
+        // Claiming more weight than the committed power
+        let witness = vote_witness(1_000, 7, &VOTES_50_50).unwrap();
+        let mut instances = witness.public_inputs();
+        instances[weight_row(2)] = pallas::Base::ONE;
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // Or weights wrapping around u64
+        let witness = vote_witness(1_000, 7, &[]).unwrap();
+        let mut instances = witness.public_inputs();
+        instances[weight_row(0)] = pallas::Base::from(u64::MAX);
+        instances[weight_row(1)] = pallas::Base::from(2);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_other_keys_cannot_vote() {
+        let (keys, nft, merkle_proof) = ve_nft(1_000);
+        let other = FullKeys::random(&mut OsRng);
+        assert!(
+            GaugeVoteWitness::new(&nft, &merkle_proof, &other, 7, &VOTES_50_50, &mut OsRng)
+                .is_err()
+        );
+
+        // The other keys' nullifier key does not derive the owner
+        let witness =
+            GaugeVoteWitness::new(&nft, &merkle_proof, &keys, 7, &VOTES_50_50, &mut OsRng).unwrap();
+        let mut circuit = witness.circuit();
+        circuit.nk = Value::known(other.nullifier.to_base());
+        assert!(!mock_failures(&circuit, vec![witness.public_inputs()], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (keys, nft, merkle_proof) = ve_nft(1_000);
+        let witness =
+            GaugeVoteWitness::new(&nft, &merkle_proof, &keys, 7, &VOTES_50_50, &mut OsRng).unwrap();
+        let key = keygen().unwrap();
+
+        let mut proof = prove(&key, &witness, OsRng).unwrap();
+        proof.authorize(&keys.spending, witness.alpha()).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Moving weight to another pool fails
+        let mut tampered = proof.clone();
+        tampered.proof.public_inputs[pool_row(1)] = halo2::encode_instance(&pallas::Base::from(3));
+        assert!(!tampered.verify(key.verification_key()).unwrap());
+
+        // The proof alone does not authorize the vote: it is rejected with no
+        // signature or one under another key
+        let mut unsigned = proof.clone();
+        unsigned.spend_auth_sig = None;
+        let other = FullKeys::random(&mut OsRng);
+        let mut wrong_key = proof.clone();
+        wrong_key.spend_auth_sig = Some(
+            other
+                .spending
+                .sign_randomized(witness.alpha(), &proof.sighash()),
+        );
+        for rejected in [&unsigned, &wrong_key] {
+            assert!(rejected.proof.verify(key.verification_key()).unwrap());
+            assert!(!rejected.verify(key.verification_key()).unwrap());
+        }
+        assert!(unsigned
+            .authorize(&other.spending, witness.alpha())
+            .is_err());
+    }
+}
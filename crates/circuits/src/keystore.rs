@@ -0,0 +1,652 @@
+//! Proving-key store
+//!
+//! Proving needs IPA parameters and a proving key per circuit, and both are
+//! slow to generate. A [`KeyStore`] builds each key once and shares it between
+//! threads. When opened on a directory it also persists what `halo2_proofs` can
+//! serialize:
+//! - `params-<k>.bin`: IPA parameters. `Params::new(k)` derives its generators
+//!   by hashing to the curve, so every node computes the same ones.
+//! - `<circuit>.vk`: the `VerificationKey` key data (circuit name, `k` and
+//!   pinned verifying-key hash), written as `ceremony export` writes its keys.
+//!
+//! halo2_proofs 0.3 cannot serialize proving keys, so they are rebuilt from the
+//! stored parameters on first use. The rebuilt verifying key must match the
+//! stored descriptor and the pinned `VerificationKey::id`. [`KeyStore::open`]
+//! and [`KeyStore::global`] pin the ids in [`PINNED_IDS`], and stored
+//! parameters are only used for pinned circuits. The id covers only the
+//! fixed-column commitments, not the rest of the generators, so a stored
+//! parameter file must also hash to its size's entry in [`PINNED_PARAMS`]. A
+//! mismatch fails closed rather than proving against a silently changed
+//! circuit or substituted parameters.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use halo2_proofs::plonk::{keygen_pk, Circuit};
+use pasta_curves::pallas;
+use privl1_crypto::halo2::{self, Halo2Params, Halo2ProvingKey, VkBuilder};
+use privl1_crypto::hash::Blake3Hash;
+use privl1_crypto::proof::VerificationKey;
+
+use crate::{CircuitError, Result};
+
+/// Proving key for one circuit with the parameters and verification key it
+/// was built from
+pub struct CircuitKey {
+    params: Arc<Halo2Params>,
+    pk: Halo2ProvingKey,
+    vk: VerificationKey,
+}
+
+impl CircuitKey {
+    /// IPA parameters the key was built from
+    pub fn params(&self) -> &Halo2Params {
+        &self.params
+    }
+
+    /// Halo2 proving key
+    pub fn proving_key(&self) -> &Halo2ProvingKey {
+        &self.pk
+    }
+
+    /// Verification key proofs from this key verify against
+    pub fn verification_key(&self) -> &VerificationKey {
+        &self.vk
+    }
+}
+
+/// Verification key ids of every circuit in this crate, as hex
+///
+/// The ids follow from the circuit and its `Params::new(k)` generators, so a
+/// changed circuit must update its entry here. The mismatch error names the
+/// new id.
+pub const PINNED_IDS: &[(&str, &str)] = &[
+    (
+        "bribe-claim-v1",
+        "f6d1224f3bc3542faa967870d1100fcb5cf13b41768a23ccfd7638b6cc630dcc",
+    ),
+    (
+        "disclose-balance-v1",
+        "93799fe062d0ac4be17cff8fc902ca1ba95e18e3aeaaacd64627936d5cb9fbdb",
+    ),
+    (
+        "disclose-origin-v1",
+        "8cf7f61a9440ee1484725c69323e56a9f7888db2c272dfa5338a40030561f6f5",
+    ),
+    (
+        "disclose-recipient-v1",
+        "8861122bf710fe31dcd570ee9a389c98722e379bfa07d3c5b1a75979def827e6",
+    ),
+    (
+        "gauge-vote-v1",
+        "b400ce8ca7ffa4c44dcc678195c3d8403607a5f014bfdab0880f87c6084099e9",
+    ),
+    (
+        "output-v1",
+        "8238811a87a901984907f67ecc518259b5463792f9843dd57f998815e177e2da",
+    ),
+    (
+        "reserves-v1",
+        "f34b0f455d94b03d1d8bc2c904304adea41b7da5becf9a55b654cd1d07c009b7",
+    ),
+    (
+        "spend-v1",
+        "a01c5097b09a36aee416a5489c72bdb302669c6c2780dafd82ec919281badd62",
+    ),
+    (
+        "swap-v1",
+        "f25323e6af073364e3c06016e7de6c129571ae9edd9de716f9b52bd60202bc91",
+    ),
+    (
+        "ve-lock-v1",
+        "7d56a8474c2c9fbb521cf46b8ca5a2af883c61e6b1f0959616945a2d78941afa",
+    ),
+];
+
+/// BLAKE3 hashes of the serialized `Params::new(k)` for every circuit size in
+/// this crate, as hex
+///
+/// A parameter file is read only if it hashes to its size's entry. The
+/// mismatch error names the file's hash.
+pub const PINNED_PARAMS: &[(u32, &str)] = &[
+    (
+        11,
+        "46d2cc8da807c681ba7301f789a7a388bf9f2ea74e37f1bd1defbb6b82c4d25e",
+    ),
+    (
+        12,
+        "657e0b22474bfe35b547cbaa17f19afad7bbaf3461bb214cd4739bfc1e42ff15",
+    ),
+    (
+        13,
+        "2c7eb351c10632d63279fb2342ac3a547bc93a9b4835a8027f5cb7ca5d09b890",
+    ),
+    (
+        14,
+        "f81c051458c5751ffed06b3d9295f62b1254db75632574b242265ab0c0f84869",
+    ),
+    (
+        15,
+        "4011c4b95d21a0a3dbe2492b667f496a12bfd6e288fc733e712287abd977ba8d",
+    ),
+];
+
+/// A key being built or already built; holding its lock builds it only once
+type KeySlot = Arc<Mutex<Option<Arc<CircuitKey>>>>;
+
+/// Lazily built proving keys, optionally persisted to a directory
+pub struct KeyStore {
+    dir: Option<PathBuf>,
+    pins: RwLock<HashMap<String, [u8; 32]>>,
+    keys: Mutex<HashMap<(String, u32), KeySlot>>,
+}
+
+impl KeyStore {
+    /// A store that keeps keys in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            pins: RwLock::new(HashMap::new()),
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A store persisting parameters and key descriptors under `dir`
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir: Some(dir),
+            ..Self::pinned()
+        })
+    }
+
+    /// The process-wide in-memory store used by `keygen()` in circuit modules
+    pub fn global() -> &'static KeyStore {
+        static STORE: OnceLock<KeyStore> = OnceLock::new();
+        STORE.get_or_init(KeyStore::pinned)
+    }
+
+    /// An in-memory store with [`PINNED_IDS`] applied
+    fn pinned() -> Self {
+        let store = Self::in_memory();
+        for (circuit, id) in PINNED_IDS {
+            let mut bytes = [0u8; 32];
+            hex::decode_to_slice(id, &mut bytes).expect("malformed pinned id");
+            store.pin(circuit, bytes);
+        }
+        store
+    }
+
+    /// Require `circuit`'s verification key to have id `id`
+    ///
+    /// Applies to keys loaded after the call.
+    pub fn pin(&self, circuit: &str, id: [u8; 32]) {
+        self.pins
+            .write()
+            .expect("key pins poisoned")
+            .insert(circuit.to_string(), id);
+    }
+
+    /// Get the proving key for `circuit` at size 2^k, building it on first use
+    ///
+    /// Concurrent callers for the same circuit wait for a single build.
+    pub fn load<C>(&self, circuit: &str, k: u32, build_vk: VkBuilder) -> Result<Arc<CircuitKey>>
+    where
+        C: Circuit<pallas::Base> + Default,
+    {
+        let slot = self
+            .keys
+            .lock()
+            .expect("key store poisoned")
+            .entry((circuit.to_string(), k))
+            .or_default()
+            .clone();
+
+        let mut slot = slot.lock().expect("key slot poisoned");
+        if let Some(key) = slot.as_ref() {
+            return Ok(key.clone());
+        }
+
+        let key = Arc::new(self.build::<C>(circuit, k, build_vk)?);
+        *slot = Some(key.clone());
+        Ok(key)
+    }
+
+    fn build<C>(&self, circuit: &str, k: u32, build_vk: VkBuilder) -> Result<CircuitKey>
+    where
+        C: Circuit<pallas::Base> + Default,
+    {
+        let pinned = self
+            .pins
+            .read()
+            .expect("key pins poisoned")
+            .get(circuit)
+            .copied();
+        let stored = self.read_params(k)?;
+        if stored.is_some() && pinned.is_none() {
+            return Err(CircuitError::KeyMismatch(format!(
+                "no pinned {} verification key to check stored parameters against",
+                circuit
+            )));
+        }
+        let generated;
+        let params: &Halo2Params = match &stored {
+            Some(params) => params,
+            None => {
+                generated = halo2::params(k)?;
+                &generated
+            }
+        };
+
+        let vk = build_vk(params)?;
+        let key = halo2::verification_key(circuit, k, &vk);
+        if let Some(pinned) = pinned {
+            if key.id() != pinned {
+                return Err(CircuitError::KeyMismatch(format!(
+                    "{} verification key {} is not the pinned {}",
+                    circuit,
+                    hex::encode(key.id()),
+                    hex::encode(pinned)
+                )));
+            }
+        }
+        self.check_descriptor(circuit, &key)?;
+
+        // Stored parameters are trusted only once they rebuild the expected key
+        let params = match stored {
+            Some(params) => halo2::install_params(k, params)?,
+            None => {
+                let params = halo2::params(k)?;
+                self.write_params(k, &params)?;
+                params
+            }
+        };
+
+        let pk = keygen_pk(&params, vk, &C::default())
+            .map_err(|e| CircuitError::Synthesis(format!("{:?}", e)))?;
+
+        Ok(CircuitKey {
+            params,
+            pk,
+            vk: key,
+        })
+    }
+
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(name))
+    }
+
+    fn read_params(&self, k: u32) -> Result<Option<Halo2Params>> {
+        let Some(path) = self.path(&format!("params-{}.bin", k)) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        // `Params::write` starts with k as a little-endian u32
+        let bytes = fs::read(&path)?;
+        if bytes.get(..4) != Some(&k.to_le_bytes()[..]) {
+            return Err(CircuitError::KeyMismatch(format!(
+                "{} is not for k={}",
+                path.display(),
+                k
+            )));
+        }
+        let Some((_, pinned)) = PINNED_PARAMS.iter().find(|(size, _)| *size == k) else {
+            return Err(CircuitError::KeyMismatch(format!(
+                "no pinned hash to check {} against",
+                path.display()
+            )));
+        };
+        let hash = hex::encode(Blake3Hash::hash(&bytes).as_bytes());
+        if hash != *pinned {
+            return Err(CircuitError::KeyMismatch(format!(
+                "{} hashes to {}, not the pinned {}",
+                path.display(),
+                hash,
+                pinned
+            )));
+        }
+
+        Halo2Params::read(&mut &bytes[..])
+            .map(Some)
+            .map_err(|e| CircuitError::SerializationError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn write_params(&self, k: u32, params: &Halo2Params) -> Result<()> {
+        let Some(path) = self.path(&format!("params-{}.bin", k)) else {
+            return Ok(());
+        };
+        if path.exists() {
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        params.write(&mut bytes)?;
+        write_atomic(&path, &bytes)
+    }
+
+    /// Compare against the stored descriptor, storing it on first use
+    fn check_descriptor(&self, circuit: &str, key: &VerificationKey) -> Result<()> {
+        let Some(path) = self.path(&format!("{}.vk", circuit)) else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return write_atomic(&path, &key.key_data);
+        }
+
+        if fs::read(&path)? != key.key_data {
+            return Err(CircuitError::KeyMismatch(format!(
+                "{} does not match the {} circuit",
+                path.display(),
+                circuit
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Write via a temporary file so readers never see a partial file
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Define a circuit module's proving key type and its `build_vk`,
+/// `register`, `load` and `keygen` functions
+///
+/// Expects `CIRCUIT_NAME` and `K` in the calling module, and the circuit's
+/// `Default` to be its keygen shape.
+macro_rules! circuit_keys {
+    ($name:literal, $circuit:ty, $key:ident) => {
+        #[doc = concat!("Proving key for the ", $name, " circuit, paired with its")]
+        /// verification key
+        #[derive(Clone)]
+        pub struct $key {
+            key: ::std::sync::Arc<$crate::keystore::CircuitKey>,
+        }
+
+        impl $key {
+            /// Verification key proofs from this key verify against
+            pub fn verification_key(&self) -> &::privl1_crypto::proof::VerificationKey {
+                self.key.verification_key()
+            }
+        }
+
+        #[doc = concat!("Build the ", $name, " verifying key (registered with")]
+        /// `privl1_crypto::halo2`)
+        pub fn build_vk(
+            params: &::privl1_crypto::halo2::Halo2Params,
+        ) -> ::privl1_crypto::Result<::privl1_crypto::halo2::Halo2VerifyingKey> {
+            ::halo2_proofs::plonk::keygen_vk(params, &<$circuit>::default())
+                .map_err(|e| ::privl1_crypto::CryptoError::OperationFailed(format!("{:?}", e)))
+        }
+
+        #[doc = concat!("Register the ", $name, " circuit so its verification keys can be loaded")]
+        pub fn register() {
+            ::privl1_crypto::halo2::register_circuit(CIRCUIT_NAME, build_vk);
+        }
+
+        #[doc = concat!("Load the ", $name, " proving key from `store`, building it on first use")]
+        pub fn load(store: &$crate::keystore::KeyStore) -> $crate::Result<$key> {
+            register();
+            let key = store.load::<$circuit>(CIRCUIT_NAME, K, build_vk)?;
+
+            Ok($key { key })
+        }
+
+        #[doc = concat!("Get the ", $name, " proving key (deterministic; no trusted")]
+        /// setup), built once per process
+        pub fn keygen() -> $crate::Result<$key> {
+            load($crate::keystore::KeyStore::global())
+        }
+    };
+}
+
+pub(crate) use circuit_keys;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{self, OutputCircuit, OutputWitness};
+    use crate::{
+        balance_disclosure, bribe_claim, gauge_vote, origin_disclosure, recipient_disclosure,
+        reserves, spend, swap, ve_lock,
+    };
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::note::{AssetId, Note};
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("privl1-keystore-{}", OsRng.next_u64()))
+    }
+
+    fn load_output(store: &KeyStore) -> Result<Arc<CircuitKey>> {
+        output::register();
+        store.load::<OutputCircuit>(output::CIRCUIT_NAME, output::K, output::build_vk)
+    }
+
+    #[test]
+    fn test_keys_built_once() {
+        let store = KeyStore::in_memory();
+        let first = load_output(&store).unwrap();
+        let second = load_output(&store).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Concurrent loads share the same build
+        let store = Arc::new(KeyStore::in_memory());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || load_output(&store).unwrap())
+            })
+            .collect();
+        let keys: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert!(keys.iter().all(|key| Arc::ptr_eq(key, &keys[0])));
+        assert_eq!(
+            keys[0].verification_key().id(),
+            first.verification_key().id()
+        );
+    }
+
+    #[test]
+    fn test_persisted_keys_reload() {
+        let dir = temp_dir();
+        let first = load_output(&KeyStore::open(&dir).unwrap()).unwrap();
+        assert!(dir.join(format!("params-{}.bin", output::K)).exists());
+        assert!(dir.join(format!("{}.vk", output::CIRCUIT_NAME)).exists());
+
+        // A fresh store proves from the stored parameters
+        let key = load_output(&KeyStore::open(&dir).unwrap()).unwrap();
+        assert_eq!(key.verification_key().id(), first.verification_key().id());
+
+        let keys = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(5, keys.public, *AssetId::NATIVE.as_bytes());
+        let witness = OutputWitness::random(&note, &mut OsRng);
+        let proof = output::prove(
+            &output::load(&KeyStore::open(&dir).unwrap()).unwrap(),
+            &witness,
+            OsRng,
+        )
+        .unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pinned_id_enforced() {
+        let id = load_output(&KeyStore::in_memory())
+            .unwrap()
+            .verification_key()
+            .id();
+
+        let store = KeyStore::in_memory();
+        store.pin(output::CIRCUIT_NAME, [7u8; 32]);
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        let store = KeyStore::in_memory();
+        store.pin(output::CIRCUIT_NAME, id);
+        assert_eq!(load_output(&store).unwrap().verification_key().id(), id);
+    }
+
+    #[test]
+    fn test_stale_descriptor_rejected() {
+        let dir = temp_dir();
+        let store = KeyStore::open(&dir).unwrap();
+        let stale = halo2::Halo2KeyData {
+            circuit: output::CIRCUIT_NAME.into(),
+            k: output::K,
+            pinned_hash: [1u8; 32],
+        };
+        fs::write(
+            dir.join(format!("{}.vk", output::CIRCUIT_NAME)),
+            stale.to_bytes(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_params_rejected() {
+        let dir = temp_dir();
+        let store = KeyStore::open(&dir).unwrap();
+
+        // Parameters for another size under this size's name
+        let mut bytes = Vec::new();
+        halo2::params(4).unwrap().write(&mut bytes).unwrap();
+        fs::write(dir.join(format!("params-{}.bin", output::K)), &bytes).unwrap();
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        // Truncated parameters
+        fs::write(
+            dir.join(format!("params-{}.bin", output::K)),
+            output::K.to_le_bytes(),
+        )
+        .unwrap();
+        assert!(load_output(&store).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pinned_params_match() {
+        for (k, pinned) in PINNED_PARAMS {
+            let mut bytes = Vec::new();
+            halo2::params(*k).unwrap().write(&mut bytes).unwrap();
+            assert_eq!(
+                hex::encode(Blake3Hash::hash(&bytes).as_bytes()),
+                *pinned,
+                "k={}",
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_circuit_pinned() {
+        let names = [
+            balance_disclosure::CIRCUIT_NAME,
+            bribe_claim::CIRCUIT_NAME,
+            gauge_vote::CIRCUIT_NAME,
+            origin_disclosure::CIRCUIT_NAME,
+            output::CIRCUIT_NAME,
+            recipient_disclosure::CIRCUIT_NAME,
+            reserves::CIRCUIT_NAME,
+            spend::CIRCUIT_NAME,
+            swap::CIRCUIT_NAME,
+            ve_lock::CIRCUIT_NAME,
+        ];
+        let pins = KeyStore::pinned().pins.into_inner().unwrap();
+        assert_eq!(pins.len(), names.len());
+        assert!(names.iter().all(|name| pins.contains_key(*name)));
+    }
+
+    #[test]
+    fn test_wrong_generators_rejected() {
+        let dir = temp_dir();
+        let store = KeyStore::open(&dir).unwrap();
+
+        // Parameters of the right size whose `g` and `g_lagrange` are swapped:
+        // the header still matches, but every commitment changes
+        let mut bytes = Vec::new();
+        halo2::params(output::K).unwrap().write(&mut bytes).unwrap();
+        let n = 32 << output::K;
+        let (g, g_lagrange) = bytes[4..4 + 2 * n].split_at(n);
+        let swapped = [&bytes[..4], g_lagrange, g, &bytes[4 + 2 * n..]].concat();
+        fs::write(dir.join(format!("params-{}.bin", output::K)), swapped).unwrap();
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_substituted_params_rejected() {
+        let dir = temp_dir();
+        let store = KeyStore::open(&dir).unwrap();
+
+        // `w` and `u` close the file and are not covered by the verification
+        // key id, so only the pinned hash catches a change to them
+        let mut bytes = Vec::new();
+        halo2::params(output::K).unwrap().write(&mut bytes).unwrap();
+        let (w, u) = bytes[bytes.len() - 64..].split_at(32);
+        let swapped = [&bytes[..bytes.len() - 64], u, w].concat();
+        fs::write(dir.join(format!("params-{}.bin", output::K)), swapped).unwrap();
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        // A size without a pinned hash is refused
+        let mut bytes = Vec::new();
+        halo2::params(4).unwrap().write(&mut bytes).unwrap();
+        fs::write(dir.join("params-4.bin"), &bytes).unwrap();
+        assert!(matches!(
+            store.read_params(4),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unpinned_params_refused() {
+        let dir = temp_dir();
+        load_output(&KeyStore::open(&dir).unwrap()).unwrap();
+
+        // Without a pin the stored parameters cannot be checked
+        let store = KeyStore {
+            dir: Some(dir.clone()),
+            ..KeyStore::in_memory()
+        };
+        assert!(matches!(
+            load_output(&store),
+            Err(CircuitError::KeyMismatch(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
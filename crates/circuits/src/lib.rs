@@ -1 +1,61 @@
 //! PRIVL1 circuits module
+//!
+//! - Halo2 spend, output, PrivateSwap, veNFT lock, gauge-vote and
+//!   bribe-claim circuits with witness builders (`spend`, `output`, `swap`,
+//!   `ve_lock`, `gauge_vote`, `bribe_claim`)
+//! - Spend authorization for actions that spend a note outside a
+//!   transaction (`authorization`)
+//! - Selective-disclosure circuits producing `DisclosureProof`s
+//!   (`origin_disclosure`, `balance_disclosure`, `recipient_disclosure`),
+//!   including proofs of reserves over unspent notes (`reserves`)
+//! - Gadget library (range checks, Merkle paths, value commitments, u64
+//!   arithmetic), fixed bases and note relations shared by the Halo2 circuits
+//! - Proving-key store with deterministic, disk-cached parameters (`keystore`)
+//! - Cost reports and a MockProver harness for any circuit (`cost`)
+//! - Groth16 phase-2 trusted-setup ceremony (`ceremony`, `ptau`)
+
+pub mod authorization;
+pub mod balance_disclosure;
+pub mod bribe_claim;
+pub mod ceremony;
+pub mod cost;
+pub mod fixed_bases;
+pub mod gadgets;
+pub mod gauge_vote;
+pub mod keystore;
+pub mod note;
+pub mod origin_disclosure;
+pub mod output;
+pub mod ptau;
+pub mod recipient_disclosure;
+pub mod reserves;
+pub mod spend;
+pub mod swap;
+pub mod ve_lock;
+
+/// Common error type for circuit operations
+#[derive(Debug, thiserror::Error)]
+pub enum CircuitError {
+    #[error("Synthesis error: {0}")]
+    Synthesis(String),
+
+    #[error("Invalid parameters: {0}")]
+    InvalidParameters(String),
+
+    #[error("Verification failed: {0}")]
+    VerificationFailed(String),
+
+    #[error("Serialization error: {0}")]
+    SerializationError(String),
+
+    #[error("Key mismatch: {0}")]
+    KeyMismatch(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Crypto(#[from] privl1_crypto::CryptoError),
+}
+
+pub type Result<T> = std::result::Result<T, CircuitError>;
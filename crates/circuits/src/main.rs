@@ -0,0 +1,640 @@
+//! PRIVL1 circuits command-line tool
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use ark_serialize::CanonicalSerialize;
+use clap::{Parser, Subcommand};
+use ff::PrimeField;
+use halo2_proofs::plonk::Circuit;
+use pasta_curves::pallas;
+use privl1_circuits::balance_disclosure::{self, BalanceWitness};
+use privl1_circuits::bribe_claim::{self, Bribe, BribeClaimWitness};
+use privl1_circuits::ceremony::{self, CeremonyCircuit, Phase2Params};
+use privl1_circuits::cost;
+use privl1_circuits::gauge_vote::{self, GaugeVote, GaugeVoteWitness};
+use privl1_circuits::origin_disclosure::{self, OriginWitness};
+use privl1_circuits::output::{self, OutputWitness};
+use privl1_circuits::ptau::PowersOfTau;
+use privl1_circuits::recipient_disclosure::{self, RecipientWitness};
+use privl1_circuits::reserves::{self, ReserveNote, ReservesWitness};
+use privl1_circuits::spend::{self, SpendWitness};
+use privl1_circuits::swap::{self, SwapOrder, SwapWitness};
+use privl1_circuits::ve_lock::{self, VeLock, VeLockWitness, VeNft};
+use privl1_crypto::disclosure::{DisclosureProof, DisclosureStatement};
+use privl1_crypto::hash::DomainSeparatedHasher;
+use privl1_crypto::keys::{FullKeys, FullViewingKey};
+use privl1_crypto::merkle::IncrementalMerkleTree;
+use privl1_crypto::note::{AssetId, Note};
+use privl1_crypto::nullifier_tree::NullifierTree;
+use privl1_crypto::Scalar;
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+
+#[derive(Parser)]
+#[command(name = "privl1-circuits", about = "PRIVL1 circuit tooling")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Groth16 phase-2 trusted-setup ceremony
+    #[command(subcommand)]
+    Ceremony(CeremonyCommand),
+    /// Report rows, columns, minimal k and proving cost of the Halo2 circuits
+    Cost {
+        /// Circuit to report on (e.g. `spend` or `disclose-balance`); all if omitted
+        circuit: Option<String>,
+        /// Skip keygen, proving and verification timings
+        #[arg(long)]
+        no_timings: bool,
+    },
+    /// Proof of reserves over shielded notes
+    #[command(subcommand)]
+    Reserves(ReservesCommand),
+}
+
+#[derive(Subcommand)]
+enum CeremonyCommand {
+    /// Generate an INSECURE powers-of-tau file for testing
+    DevPtau {
+        /// log2 of the maximum domain size
+        #[arg(long)]
+        power: u32,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Convert a BLS12-381 snarkjs `.ptau` file from a phase-1 ceremony
+    ImportPtau {
+        /// snarkjs powers-of-tau file
+        #[arg(long)]
+        snarkjs: PathBuf,
+        /// Keep powers for domains of up to 2^power; all if omitted
+        #[arg(long)]
+        power: Option<u32>,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Start a ceremony for a circuit from a powers-of-tau file
+    New {
+        #[arg(long)]
+        ptau: PathBuf,
+        /// Circuit name
+        #[arg(long)]
+        circuit: String,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Add a contribution to a ceremony file
+    Contribute {
+        #[arg(long = "in")]
+        input: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        /// Extra entropy mixed with the system RNG
+        #[arg(long)]
+        entropy: Option<String>,
+    },
+    /// Verify every contribution in a ceremony file
+    Verify {
+        #[arg(long)]
+        ptau: PathBuf,
+        #[arg(long)]
+        circuit: String,
+        #[arg(long)]
+        params: PathBuf,
+    },
+    /// Write the final proving and verification keys
+    Export {
+        #[arg(long)]
+        params: PathBuf,
+        /// Proving key output (compressed)
+        #[arg(long)]
+        pk: PathBuf,
+        /// Verification key output (`VerificationKey.key_data`)
+        #[arg(long)]
+        vk: PathBuf,
+    },
+    /// List circuits available for the ceremony
+    Circuits,
+}
+
+#[derive(Subcommand)]
+enum ReservesCommand {
+    /// Print the hex-encoded full viewing key `ak || nk` of a key seed
+    ///
+    /// The key reveals every note of its owner but cannot sign the spend
+    /// authorization that spends, swaps, locks, votes and claims require.
+    ViewingKey {
+        /// File holding the hex-encoded 32-byte key seed
+        #[arg(long)]
+        seed: PathBuf,
+    },
+    /// Prove that the notes of a holdings file hold at least a threshold
+    Prove {
+        /// File holding the hex-encoded 64-byte full viewing key
+        #[arg(long)]
+        key: PathBuf,
+        /// JSON list of notes with their commitment and nullifier tree proofs
+        #[arg(long)]
+        holdings: PathBuf,
+        #[arg(long)]
+        threshold: u64,
+        /// Hex-encoded 32-byte context, e.g. an auditor's challenge
+        #[arg(long)]
+        context: Option<String>,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Verify proof-of-reserves files and print what they prove together
+    Verify {
+        /// Proof file; repeat for proofs of distinct owners to add them up
+        #[arg(long, required = true)]
+        proof: Vec<PathBuf>,
+        /// Context the proof must be bound to
+        #[arg(long)]
+        context: Option<String>,
+    },
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Ceremony(command) => run_ceremony(command),
+        Command::Cost {
+            circuit,
+            no_timings,
+        } => run_cost(circuit, !no_timings),
+        Command::Reserves(command) => run_reserves(command),
+    }
+}
+
+fn run_ceremony(command: CeremonyCommand) -> Result<()> {
+    match command {
+        CeremonyCommand::DevPtau { power, out } => {
+            eprintln!("WARNING: the secrets of this file are known to this machine; never use it in production");
+            let ptau = PowersOfTau::generate_insecure(power, &mut OsRng)?;
+            let mut writer = create(&out)?;
+            ptau.write(&mut writer)?;
+            writer.flush()?;
+            println!("Wrote 2^{} powers of tau to {}", power, out.display());
+        }
+        CeremonyCommand::ImportPtau {
+            snarkjs,
+            power,
+            out,
+        } => {
+            let ptau = PowersOfTau::read_snarkjs(open(&snarkjs)?, power)?;
+            ptau.validate(&mut OsRng)?;
+            let mut writer = create(&out)?;
+            ptau.write(&mut writer)?;
+            writer.flush()?;
+            println!(
+                "Imported 2^{} powers of tau to {}",
+                ptau.power,
+                out.display()
+            );
+        }
+        CeremonyCommand::New { ptau, circuit, out } => {
+            let circuit = lookup(&circuit)?;
+            let ptau = read_ptau(&ptau)?;
+            let params = ceremony::initialize(&ptau, circuit)?;
+            write_params(&params, &out)?;
+            println!("Initial hash: {}", hex::encode(params.initial_hash));
+        }
+        CeremonyCommand::Contribute {
+            input,
+            out,
+            entropy,
+        } => {
+            let mut params = read_params(&input)?;
+
+            let mut seed = [0u8; 32];
+            OsRng.fill_bytes(&mut seed);
+            let mut hasher = DomainSeparatedHasher::new("PRIVL1_CEREMONY_ENTROPY");
+            hasher.update(&seed);
+            hasher.update(entropy.unwrap_or_default().as_bytes());
+            let mut rng = StdRng::from_seed(*hasher.finalize().as_bytes());
+
+            let hash = ceremony::contribute(&mut params, &mut rng)?;
+            write_params(&params, &out)?;
+            println!(
+                "Contribution #{} hash: {}",
+                params.contributions.len(),
+                hex::encode(hash)
+            );
+            println!("Publish this hash so others can check your contribution is included.");
+        }
+        CeremonyCommand::Verify {
+            ptau,
+            circuit,
+            params,
+        } => {
+            let circuit = lookup(&circuit)?;
+            let ptau = read_ptau(&ptau)?;
+            let params = read_params(&params)?;
+
+            let hashes = ceremony::verify(&ptau, circuit, &params, &mut OsRng)?;
+            println!("Initial hash: {}", hex::encode(params.initial_hash));
+            for (index, hash) in hashes.iter().enumerate() {
+                println!("Contribution #{}: {}", index + 1, hex::encode(hash));
+            }
+            println!("Transcript OK ({} contributions)", hashes.len());
+        }
+        CeremonyCommand::Export { params, pk, vk } => {
+            let params = read_params(&params)?;
+            let (proving_key, verification_key) = ceremony::export(&params)?;
+
+            let mut writer = create(&pk)?;
+            proving_key
+                .serialize_compressed(&mut writer)
+                .map_err(|e| anyhow!("Serializing proving key: {}", e))?;
+            writer.flush()?;
+            std::fs::write(&vk, &verification_key.key_data)
+                .with_context(|| format!("Writing {}", vk.display()))?;
+
+            println!(
+                "Verification key id: {}",
+                hex::encode(verification_key.id())
+            );
+        }
+        CeremonyCommand::Circuits => {
+            for circuit in CeremonyCircuit::ALL {
+                println!("{}", circuit.name());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_reserves(command: ReservesCommand) -> Result<()> {
+    match command {
+        ReservesCommand::ViewingKey { seed } => {
+            let seed = std::fs::read_to_string(&seed)
+                .with_context(|| format!("Reading {}", seed.display()))?;
+            let keys = FullKeys::from_seed(&parse_hex32(seed.trim())?);
+            println!("{}", hex::encode(keys.full_viewing_key().to_bytes()));
+        }
+        ReservesCommand::Prove {
+            key,
+            holdings,
+            threshold,
+            context,
+            out,
+        } => {
+            let fvk = std::fs::read_to_string(&key)
+                .with_context(|| format!("Reading {}", key.display()))?;
+            let fvk = parse_viewing_key(fvk.trim())?;
+            let notes: Vec<ReserveNote> = serde_json::from_reader(open(&holdings)?)
+                .with_context(|| format!("Parsing {}", holdings.display()))?;
+            let context = context
+                .as_deref()
+                .map(parse_hex32)
+                .transpose()?
+                .unwrap_or_default();
+
+            let witness = ReservesWitness::new(&notes, &fvk, threshold, context, &mut OsRng)?;
+            let proof = reserves::prove(&reserves::keygen()?, &witness, OsRng)?;
+            let mut writer = create(&out)?;
+            serde_json::to_writer_pretty(&mut writer, &proof)?;
+            writer.flush()?;
+            println!("Wrote proof of reserves to {}", out.display());
+        }
+        ReservesCommand::Verify { proof, context } => {
+            let disclosures = proof
+                .iter()
+                .map(|path| {
+                    serde_json::from_reader(open(path)?)
+                        .with_context(|| format!("Parsing {}", path.display()))
+                })
+                .collect::<Result<Vec<DisclosureProof>>>()?;
+            if let Some(context) = context {
+                if disclosures[0].context != parse_hex32(&context)? {
+                    bail!("Proof is bound to another context");
+                }
+            }
+            let total =
+                reserves::combined_reserves(&disclosures, reserves::keygen()?.verification_key())?;
+
+            // combined_reserves checked that all are proofs of reserves
+            // under the same roots and asset
+            for disclosure in &disclosures {
+                if let DisclosureStatement::ReservesAtLeast {
+                    owner, threshold, ..
+                } = &disclosure.statement
+                {
+                    println!("Owner:          {}", hex::encode(owner.to_bytes()));
+                    println!("At least:       {}", threshold);
+                }
+            }
+            let DisclosureStatement::ReservesAtLeast {
+                anchor,
+                nullifier_root,
+                asset_id,
+                ..
+            } = &disclosures[0].statement
+            else {
+                unreachable!("checked by combined_reserves");
+            };
+            println!("Asset:          {}", hex::encode(asset_id));
+            println!("Total:          {}", total);
+            println!("Anchor:         {}", anchor.to_hex());
+            println!("Nullifier root: {}", nullifier_root.to_hex());
+            println!("Proof OK; check that one block committed to both roots");
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_viewing_key(hex: &str) -> Result<FullViewingKey> {
+    let bytes = hex::decode(hex).with_context(|| format!("Invalid hex '{}'", hex))?;
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| anyhow!("Expected {} bytes of hex", FullViewingKey::LEN))?;
+    Ok(FullViewingKey::from_bytes(&bytes)?)
+}
+
+fn parse_hex32(hex: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hex).with_context(|| format!("Invalid hex '{}'", hex))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Expected 32 bytes of hex, got '{}'", hex))
+}
+
+/// Halo2 circuits the `cost` subcommand knows sample witnesses for
+const HALO2_CIRCUITS: [&str; 10] = [
+    "spend",
+    "output",
+    "swap",
+    "ve-lock",
+    "gauge-vote",
+    "bribe-claim",
+    "disclose-origin",
+    "disclose-balance",
+    "disclose-recipient",
+    "reserves",
+];
+
+fn run_cost(circuit: Option<String>, timings: bool) -> Result<()> {
+    let names = match circuit {
+        Some(name) => vec![name],
+        None => HALO2_CIRCUITS.iter().map(|name| name.to_string()).collect(),
+    };
+
+    for name in names {
+        // A sample note in a one-leaf tree, enough to satisfy every circuit
+        let mut rng = OsRng;
+        let keys = FullKeys::random(&mut rng);
+        let note = Note::new_with_owner(1_000, keys.public, *AssetId::NATIVE.as_bytes());
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.commitment().leaf())?;
+        let merkle_proof = tree.prove(0)?;
+
+        match name.as_str() {
+            "spend" => {
+                let witness = SpendWitness::random(&note, &merkle_proof, &keys, &mut rng)?;
+                print_cost(
+                    &name,
+                    spend::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "output" => {
+                let witness = OutputWitness::random(&note, &mut rng);
+                print_cost(
+                    &name,
+                    output::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "swap" => {
+                let order = SwapOrder {
+                    amount_in: 600,
+                    reserve_in: 50_000,
+                    reserve_out: 80_000,
+                    asset_out: [1u8; 32],
+                    recipient: keys.public,
+                };
+                let witness = SwapWitness::new(&note, &merkle_proof, &keys, order, &mut rng)?;
+                print_cost(
+                    &name,
+                    swap::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "ve-lock" => {
+                let lock = VeLock {
+                    amount: 600,
+                    tier: 4,
+                    lock_time: 1_700_000_000,
+                    nft_id: 1,
+                };
+                let witness = VeLockWitness::new(&note, &merkle_proof, &keys, lock, &mut rng)?;
+                print_cost(
+                    &name,
+                    ve_lock::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "gauge-vote" => {
+                let nft = VeNft {
+                    nft_id: 1,
+                    owner: keys.public,
+                    voting_power: 1_000,
+                    blinding: Scalar::random(&mut rng),
+                };
+                let mut ve_tree = IncrementalMerkleTree::new();
+                ve_tree.append(nft.leaf())?;
+                let votes = [
+                    GaugeVote {
+                        pool: 0,
+                        weight: 600,
+                    },
+                    GaugeVote {
+                        pool: 1,
+                        weight: 400,
+                    },
+                ];
+                let witness =
+                    GaugeVoteWitness::new(&nft, &ve_tree.prove(0)?, &keys, 1, &votes, &mut rng)?;
+                let (circuit, instances) = (witness.circuit(), witness.public_inputs());
+                print_cost(&name, gauge_vote::K, circuit, instances, timings)?;
+            }
+            "bribe-claim" => {
+                let nft = VeNft {
+                    nft_id: 1,
+                    owner: keys.public,
+                    voting_power: 1_000,
+                    blinding: Scalar::random(&mut rng),
+                };
+                let mut ve_tree = IncrementalMerkleTree::new();
+                ve_tree.append(nft.leaf())?;
+                let votes = [GaugeVote {
+                    pool: 0,
+                    weight: 600,
+                }];
+                let vote =
+                    GaugeVoteWitness::new(&nft, &ve_tree.prove(0)?, &keys, 1, &votes, &mut rng)?;
+                let mut receipts = IncrementalMerkleTree::new();
+                receipts.append(vote.receipts()[0].to_repr())?;
+
+                let bribe = Bribe {
+                    pool: 0,
+                    epoch: 1,
+                    asset_id: [1u8; 32],
+                    amount: 5_000,
+                    total_weight: 2_400,
+                };
+                let witness = BribeClaimWitness::new(
+                    &nft,
+                    &keys,
+                    &bribe,
+                    600,
+                    &receipts.prove(0)?,
+                    keys.public,
+                    &mut rng,
+                )?;
+                let (circuit, instances) = (witness.circuit(), witness.public_inputs());
+                print_cost(&name, bribe_claim::K, circuit, instances, timings)?;
+            }
+            "disclose-origin" => {
+                let witness = OriginWitness::new(&note, &merkle_proof, &keys, 1, [0u8; 32])?;
+                print_cost(
+                    &name,
+                    origin_disclosure::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "disclose-balance" => {
+                let notes = [(note.clone(), merkle_proof.clone())];
+                let witness = BalanceWitness::new(&notes, &keys, 1_000, [0u8; 32], &mut rng)?;
+                print_cost(
+                    &name,
+                    balance_disclosure::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "disclose-recipient" => {
+                let witness = RecipientWitness::new(&note, [0u8; 32]);
+                print_cost(
+                    &name,
+                    recipient_disclosure::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            "reserves" => {
+                let nullifier = keys.nullifier.derive_nullifier(&note, 0);
+                let notes = [ReserveNote {
+                    note: note.clone(),
+                    merkle_proof: merkle_proof.clone(),
+                    unspent: NullifierTree::new().prove_unspent(&nullifier)?,
+                }];
+                let witness = ReservesWitness::new(
+                    &notes,
+                    &keys.full_viewing_key(),
+                    1_000,
+                    [0u8; 32],
+                    &mut rng,
+                )?;
+                print_cost(
+                    &name,
+                    reserves::K,
+                    witness.circuit(),
+                    witness.public_inputs(),
+                    timings,
+                )?;
+            }
+            _ => bail!(
+                "Unknown circuit '{}' (expected one of {})",
+                name,
+                HALO2_CIRCUITS.join(", ")
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_cost<C: Circuit<pallas::Base> + Clone>(
+    name: &str,
+    k: u32,
+    circuit: C,
+    instances: Vec<pallas::Base>,
+    timings: bool,
+) -> Result<()> {
+    let failures = cost::mock_failures(&circuit, vec![instances.clone()], k)?;
+    let mut report = if timings {
+        cost::report(&circuit, &instances)?
+    } else {
+        cost::measure(&circuit)?
+    };
+    if failures.is_empty() && report.min_k.is_none() {
+        report.min_k = Some(cost::min_k(&circuit, &instances, k)?);
+    }
+    println!("== {} (declared k={})", name, k);
+    println!("{}", report);
+
+    for failure in &failures {
+        println!("MockProver:       {}", failure);
+    }
+    if !failures.is_empty() {
+        bail!("{} sample witness does not satisfy the circuit", name);
+    }
+    println!("MockProver:       OK");
+    println!();
+
+    Ok(())
+}
+
+fn lookup(name: &str) -> Result<CeremonyCircuit> {
+    match CeremonyCircuit::from_name(name) {
+        Some(circuit) => Ok(circuit),
+        None => bail!("Unknown circuit '{}' (see `ceremony circuits`)", name),
+    }
+}
+
+fn read_ptau(path: &Path) -> Result<PowersOfTau> {
+    let ptau = PowersOfTau::read(open(path)?)?;
+    ptau.validate(&mut OsRng)?;
+    Ok(ptau)
+}
+
+fn read_params(path: &Path) -> Result<Phase2Params> {
+    Ok(Phase2Params::read(open(path)?)?)
+}
+
+fn write_params(params: &Phase2Params, path: &Path) -> Result<()> {
+    let mut writer = create(path)?;
+    params.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("Creating {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
@@ -0,0 +1,194 @@
+//! Columns, chips and relations shared by the note circuits
+//!
+//! Spend, output and swap circuits open note commitments and derive owners,
+//! nullifiers and value commitments; this module configures the chips they
+//! need and synthesizes those relations exactly as `privl1-crypto` computes
+//! them natively.
+
+use halo2_gadgets::ecc::chip::{CircuitVersion, EccChip, EccConfig};
+use halo2_gadgets::ecc::{FixedPoint, FixedPointBaseField, NonIdentityPoint, ScalarFixed};
+use halo2_gadgets::poseidon::primitives::P128Pow5T3;
+use halo2_gadgets::poseidon::Pow5Chip;
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Instance};
+use pasta_curves::pallas;
+
+use crate::fixed_bases::{FixedBases, FullWidth, NullifierK};
+use crate::gadgets::{
+    self, AssignedBase, CircuitPoint, PoseidonConfig, RangeCheckConfig, ValueCommitConfig,
+};
+
+/// Columns and chip configurations shared by the note circuits
+#[derive(Clone, Debug)]
+pub struct NoteConfig {
+    /// Public inputs
+    pub primary: Column<Instance>,
+    /// Advice columns (all equality-enabled)
+    pub advices: [Column<Advice>; 10],
+    /// Lookup range check (shared with the ECC chip)
+    pub range: RangeCheckConfig,
+    /// ECC chip
+    pub ecc: EccConfig<FixedBases>,
+    /// Poseidon chip
+    pub poseidon: PoseidonConfig,
+    /// Value commitments over the ECC chip
+    pub value_commit: ValueCommitConfig,
+}
+
+impl NoteConfig {
+    /// Allocate columns and configure the ECC and Poseidon chips
+    pub fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self {
+        let advices = [(); 10].map(|_| meta.advice_column());
+        for advice in advices {
+            meta.enable_equality(advice);
+        }
+
+        let primary = meta.instance_column();
+        meta.enable_equality(primary);
+
+        let lagrange_coeffs = [(); 8].map(|_| meta.fixed_column());
+        meta.enable_constant(lagrange_coeffs[0]);
+        let rc_a = lagrange_coeffs[2..5].try_into().unwrap();
+        let rc_b = lagrange_coeffs[5..8].try_into().unwrap();
+
+        let table_idx = meta.lookup_table_column();
+        let range = RangeCheckConfig::configure(meta, advices[9], table_idx);
+        let ecc = EccChip::<FixedBases>::configure(
+            meta,
+            advices,
+            lagrange_coeffs,
+            *range.lookup(),
+        );
+        let poseidon = Pow5Chip::configure::<P128Pow5T3>(
+            meta,
+            advices[6..9].try_into().unwrap(),
+            advices[5],
+            rc_a,
+            rc_b,
+        );
+
+        Self {
+            primary,
+            advices,
+            range,
+            value_commit: ValueCommitConfig::construct(ecc.clone(), advices[4]),
+            ecc,
+            poseidon,
+        }
+    }
+
+    /// Load the lookup table; call once per synthesis
+    pub fn load(&self, layouter: &mut impl Layouter<pallas::Base>) -> Result<(), Error> {
+        self.range.load(layouter)
+    }
+
+    /// Construct the ECC chip
+    pub fn ecc_chip(&self) -> EccChip<FixedBases> {
+        EccChip::construct(self.ecc.clone(), CircuitVersion::AnchoredBase)
+    }
+
+    /// `cm = Poseidon(value, asset, owner.x, owner.y, rcm)`
+    pub fn note_commitment(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        value: AssignedBase,
+        asset: AssignedBase,
+        owner: &CircuitPoint,
+        rcm: AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        gadgets::poseidon_hash(
+            &self.poseidon,
+            layouter,
+            [value, asset, owner.inner().x(), owner.inner().y(), rcm],
+        )
+    }
+
+    /// `owner = ak + [nk]N`, the public key owning notes `nk` can spend
+    pub fn derive_owner(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        ak: &NonIdentityPoint<pallas::Affine, EccChip<FixedBases>>,
+        nk: AssignedBase,
+    ) -> Result<CircuitPoint, Error> {
+        let nk_n = FixedPointBaseField::from_inner(self.ecc_chip(), NullifierK)
+            .mul(layouter.namespace(|| "[nk]N"), nk)?;
+        nk_n.add(layouter.namespace(|| "owner"), ak)
+    }
+
+    /// `nf = Poseidon(nk, cm, position)`
+    pub fn nullifier(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        nk: AssignedBase,
+        cm: AssignedBase,
+        position: AssignedBase,
+    ) -> Result<AssignedBase, Error> {
+        gadgets::poseidon_hash(&self.poseidon, layouter, [nk, cm, position])
+    }
+
+    /// `rk = ak + [alpha]G`, the re-randomized spend validating key
+    pub fn randomize_key(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        ak: &NonIdentityPoint<pallas::Affine, EccChip<FixedBases>>,
+        alpha: Value<pallas::Scalar>,
+    ) -> Result<CircuitPoint, Error> {
+        let alpha = ScalarFixed::new(self.ecc_chip(), layouter.namespace(|| "alpha"), alpha)?;
+        let (alpha_g, _) = FixedPoint::from_inner(self.ecc_chip(), FullWidth::SpendAuthG)
+            .mul(layouter.namespace(|| "[alpha]G"), alpha)?;
+        alpha_g.add(layouter.namespace(|| "rk"), ak)
+    }
+
+    /// `cv = [value]V + [rcv]R`, with `value < 2^64`
+    pub fn value_commitment(
+        &self,
+        layouter: impl Layouter<pallas::Base>,
+        value: AssignedBase,
+        rcv: Value<pallas::Scalar>,
+    ) -> Result<CircuitPoint, Error> {
+        self.value_commit.commit(layouter, value, rcv)
+    }
+
+    /// Copy a public input into an advice cell
+    pub fn public_base(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        row: usize,
+    ) -> Result<AssignedBase, Error> {
+        layouter.assign_region(
+            || "public input",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "public input",
+                    self.primary,
+                    row,
+                    self.advices[0],
+                    0,
+                )
+            },
+        )
+    }
+
+    /// Copy a public amount into an advice cell and range-check it to 64 bits
+    pub fn public_amount(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        row: usize,
+    ) -> Result<AssignedBase, Error> {
+        let cell = self.public_base(layouter.namespace(|| "copy"), row)?;
+        self.range
+            .range_check_u64(layouter.namespace(|| "range"), cell.clone())?;
+        Ok(cell)
+    }
+
+    /// Expose a point's coordinates as public inputs at rows `x_row`, `x_row + 1`
+    pub fn expose_point(
+        &self,
+        layouter: &mut impl Layouter<pallas::Base>,
+        point: &CircuitPoint,
+        x_row: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(point.inner().x().cell(), self.primary, x_row)?;
+        layouter.constrain_instance(point.inner().y().cell(), self.primary, x_row + 1)
+    }
+}
@@ -0,0 +1,303 @@
+//! Note-origin disclosure circuit
+//!
+//! Proves "this payment came from a note I received at height H" for a
+//! `DisclosureStatement::NoteOrigin`. For public `(anchor, position, nf,
+//! context)`, the circuit checks:
+//! - `cm = Poseidon(value, asset, owner.x, owner.y, rcm)` is the leaf at
+//!   `position` of the note commitment tree with root `anchor`
+//! - `owner = ak + [nk]N`, so the prover holds the owner's nullifier key
+//! - `nf = Poseidon(nk, cm, position)`, the nullifier the payment revealed
+//!
+//! The verifier maps `position` to the height it was appended at. Value, asset
+//! and owner stay private. `context` is only bound to the proof.
+
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::disclosure::{DisclosureProof, DisclosureStatement};
+use privl1_crypto::halo2;
+use privl1_crypto::keys::FullKeys;
+use privl1_crypto::merkle::{MerkleProof, TREE_DEPTH};
+use privl1_crypto::note::Note;
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::Scalar;
+use rand::{CryptoRng, RngCore};
+
+use crate::gadgets::{assign_free_advice, MerklePathConfig};
+use crate::note::NoteConfig;
+use crate::spend::SpendWitness;
+use crate::Result;
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 12;
+
+/// Name the origin verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "disclose-origin-v1";
+
+/// Version of the origin circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows, in `DisclosureStatement::public_inputs` order
+pub const ANCHOR: usize = 0;
+pub const POSITION: usize = 1;
+pub const NULLIFIER: usize = 2;
+pub const CONTEXT: usize = 3;
+
+/// Configuration of the origin circuit
+#[derive(Clone, Debug)]
+pub struct OriginConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+}
+
+/// The origin circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct OriginCircuit {
+    value: Value<pallas::Base>,
+    asset: Value<pallas::Base>,
+    rcm: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+}
+
+impl Circuit<pallas::Base> for OriginCircuit {
+    type Config = OriginConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> OriginConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+
+        OriginConfig { note, merkle }
+    }
+
+    fn synthesize(
+        &self,
+        config: OriginConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        note.load(&mut layouter)?;
+        note.public_base(layouter.namespace(|| "context"), CONTEXT)?;
+
+        // Note opening
+        let value =
+            assign_free_advice(layouter.namespace(|| "value"), note.advices[0], self.value)?;
+        let asset =
+            assign_free_advice(layouter.namespace(|| "asset"), note.advices[1], self.asset)?;
+        let rcm = assign_free_advice(layouter.namespace(|| "rcm"), note.advices[2], self.rcm)?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let cm = note.note_commitment(layouter.namespace(|| "cm"), value, asset, &owner, rcm)?;
+
+        // The leaf at the disclosed position
+        let (anchor, position) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            cm.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+        layouter.constrain_instance(position.cell(), note.primary, POSITION)?;
+
+        let nf = note.nullifier(layouter.namespace(|| "nullifier"), nk, cm, position)?;
+        layouter.constrain_instance(nf.cell(), note.primary, NULLIFIER)
+    }
+}
+
+/// Private inputs of an origin disclosure
+#[derive(Clone, Debug)]
+pub struct OriginWitness {
+    note: SpendWitness,
+    height: u64,
+    context: [u8; 32],
+}
+
+impl OriginWitness {
+    /// Build the witness disclosing that `note`, appended at `height`, is
+    /// the one spent by its nullifier
+    ///
+    /// Fails if the keys do not own the note.
+    pub fn new(
+        note: &Note,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        height: u64,
+        context: [u8; 32],
+    ) -> Result<Self> {
+        // Only the note opening is used
+        let note = SpendWitness::new(note, merkle_proof, keys, Scalar::zero(), Scalar::zero())?;
+
+        Ok(Self {
+            note,
+            height,
+            context,
+        })
+    }
+
+    /// The disclosed statement
+    pub fn statement(&self) -> DisclosureStatement {
+        DisclosureStatement::NoteOrigin {
+            anchor: self.note.anchor(),
+            position: self.note.position,
+            height: self.height,
+            nullifier: self.note.nullifier(),
+        }
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        self.statement()
+            .public_inputs(&self.context)
+            .expect("Poseidon output is canonical")
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> OriginCircuit {
+        let note = &self.note;
+
+        OriginCircuit {
+            value: Value::known(pallas::Base::from(note.value)),
+            asset: Value::known(note.asset),
+            rcm: Value::known(note.rcm),
+            ak: Value::known(note.ak.inner().to_affine()),
+            nk: Value::known(note.nk),
+            path: Value::known(note.path),
+            position: Value::known(note.position),
+        }
+    }
+}
+
+crate::keystore::circuit_keys!("origin", OriginCircuit, OriginProvingKey);
+
+/// Prove a note's origin
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &OriginProvingKey,
+    witness: &OriginWitness,
+    rng: R,
+) -> Result<DisclosureProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(DisclosureProof::new(
+        witness.statement(),
+        witness.context,
+        Halo2Proof::new(
+            proof,
+            instances.iter().map(halo2::encode_instance).collect(),
+            key.verification_key().id(),
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::hash::bytes_to_base;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use rand::rngs::OsRng;
+
+    /// A note received at position 1, after another note
+    fn received() -> (FullKeys, Note, MerkleProof) {
+        let keys = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(500, keys.public, *AssetId::NATIVE.as_bytes());
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append([0u8; 32]).unwrap();
+        let position = tree.append(note.commitment().leaf()).unwrap();
+        let merkle_proof = tree.prove(position).unwrap();
+        (keys, note, merkle_proof)
+    }
+
+    #[test]
+    fn test_origin_circuit() {
+        let (keys, note, merkle_proof) = received();
+        let witness = OriginWitness::new(&note, &merkle_proof, &keys, 10, [7u8; 32]).unwrap();
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+
+        // The disclosed nullifier is the one a spend of the note reveals
+        match witness.statement() {
+            DisclosureStatement::NoteOrigin {
+                position,
+                nullifier,
+                ..
+            } => {
+                assert_eq!(position, 1);
+                assert_eq!(nullifier, keys.nullifier.derive_nullifier(&note, 1));
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let (keys, note, merkle_proof) = received();
+        let witness = OriginWitness::new(&note, &merkle_proof, &keys, 10, [7u8; 32]).unwrap();
+
+        for row in [ANCHOR, POSITION, NULLIFIER] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_other_keys_cannot_disclose() {
+        let (keys, note, merkle_proof) = received();
+        let other = FullKeys::random(&mut OsRng);
+        assert!(OriginWitness::new(&note, &merkle_proof, &other, 10, [7u8; 32]).is_err());
+
+        let witness = OriginWitness::new(&note, &merkle_proof, &keys, 10, [7u8; 32]).unwrap();
+        let mut circuit = witness.circuit();
+        circuit.nk = Value::known(other.nullifier.to_base());
+        assert!(!mock_failures(&circuit, vec![witness.public_inputs()], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (keys, note, merkle_proof) = received();
+        let witness = OriginWitness::new(&note, &merkle_proof, &keys, 10, [7u8; 32]).unwrap();
+        let key = keygen().unwrap();
+
+        let disclosure = prove(&key, &witness, OsRng).unwrap();
+        assert!(disclosure.verify(key.verification_key()).unwrap());
+
+        // The proof is bound to its context
+        let mut replayed = disclosure.clone();
+        replayed.context = [8u8; 32];
+        replayed.proof.public_inputs[CONTEXT] = halo2::encode_instance(&bytes_to_base(&[8u8; 32]));
+        assert!(replayed.binds_statement());
+        assert!(!replayed.verify(key.verification_key()).unwrap());
+    }
+}
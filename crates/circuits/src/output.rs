@@ -0,0 +1,393 @@
+//! Output circuit
+//!
+//! Proves, for public `(cm, cv, asset)`, knowledge of a note such that:
+//! - `cm = Poseidon(value, asset, owner.x, owner.y, rcm)` for the recipient
+//!   `owner`
+//! - `cv = [value]V + [rcv]R`, with `value < 2^64`
+//! - `asset` is the note's `asset_base(asset_id)`
+//!
+//! As in the spend circuit, `V` is shared by every asset and the asset is
+//! public, so the `asset_id` an `OutputProof` declares is bound to the proof.
+//!
+//! The relations mirror `Note::commitment` and `PedersenCommitment` in
+//! `privl1-crypto`. Unlike a spend, the owner is any point: dummy outputs are
+//! owned by the identity.
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::Point;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::commitment::{Commitment, PedersenCommitment};
+use privl1_crypto::halo2;
+use privl1_crypto::note::{asset_base, Note, NoteCommitment};
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::gadgets::assign_free_advice;
+use crate::note::NoteConfig;
+use crate::Result;
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 11;
+
+/// Name the output verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "output-v1";
+
+/// Version of the output circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows
+pub const CM: usize = 0;
+pub const CV_X: usize = 1;
+pub const CV_Y: usize = 2;
+pub const ASSET: usize = 3;
+
+/// The output circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct OutputCircuit {
+    value: Value<pallas::Base>,
+    asset: Value<pallas::Base>,
+    owner: Value<pallas::Affine>,
+    rcm: Value<pallas::Base>,
+    rcv: Value<pallas::Scalar>,
+}
+
+impl Circuit<pallas::Base> for OutputCircuit {
+    type Config = NoteConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> NoteConfig {
+        NoteConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: NoteConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        config.load(&mut layouter)?;
+
+        let value = assign_free_advice(
+            layouter.namespace(|| "value"),
+            config.advices[0],
+            self.value,
+        )?;
+        let asset = assign_free_advice(
+            layouter.namespace(|| "asset"),
+            config.advices[1],
+            self.asset,
+        )?;
+        layouter.constrain_instance(asset.cell(), config.primary, ASSET)?;
+        let rcm = assign_free_advice(layouter.namespace(|| "rcm"), config.advices[2], self.rcm)?;
+        let owner = Point::new(
+            config.ecc_chip(),
+            layouter.namespace(|| "owner"),
+            self.owner,
+        )?;
+
+        let cm = config.note_commitment(
+            layouter.namespace(|| "cm"),
+            value.clone(),
+            asset,
+            &owner,
+            rcm,
+        )?;
+        layouter.constrain_instance(cm.cell(), config.primary, CM)?;
+
+        let cv =
+            config.value_commitment(layouter.namespace(|| "value commitment"), value, self.rcv)?;
+        config.expose_point(&mut layouter, &cv, CV_X)
+    }
+}
+
+/// Private inputs of an output, built from the note being created
+#[derive(Clone, Debug)]
+pub struct OutputWitness {
+    value: u64,
+    asset: pallas::Base,
+    owner: NativePoint,
+    rcm: pallas::Base,
+    rcv: Scalar,
+    commitment: NoteCommitment,
+}
+
+impl OutputWitness {
+    /// Build the witness for creating `note`
+    ///
+    /// `rcv` is the value-commitment randomness, needed again for the binding
+    /// signature.
+    pub fn new(note: &Note, rcv: Scalar) -> Self {
+        Self {
+            value: note.value(),
+            asset: asset_base(note.asset_id()),
+            owner: *note.owner().as_point(),
+            rcm: note.rcm(),
+            rcv,
+            commitment: note.commitment(),
+        }
+    }
+
+    /// Build a witness with fresh `rcv`
+    pub fn random<R: RngCore>(note: &Note, rng: &mut R) -> Self {
+        Self::new(note, Scalar::random(rng))
+    }
+
+    /// Note commitment revealed by the output
+    pub fn commitment(&self) -> &NoteCommitment {
+        &self.commitment
+    }
+
+    /// Value commitment revealed by the output
+    pub fn value_commitment(&self) -> Commitment {
+        PedersenCommitment::new().commit_with_blinding(self.value, self.rcv)
+    }
+
+    /// Value-commitment randomness
+    pub fn rcv(&self) -> &Scalar {
+        &self.rcv
+    }
+
+    /// Public inputs: `[cm, cv.x, cv.y, asset]`
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let (cv_x, cv_y) = self.value_commitment().as_point().coordinates();
+        vec![self.commitment.to_field(), cv_x, cv_y, self.asset]
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> OutputCircuit {
+        OutputCircuit {
+            value: Value::known(pallas::Base::from(self.value)),
+            asset: Value::known(self.asset),
+            owner: Value::known(self.owner.inner().to_affine()),
+            rcm: Value::known(self.rcm),
+            rcv: Value::known(*self.rcv.inner()),
+        }
+    }
+}
+
+crate::keystore::circuit_keys!("output", OutputCircuit, OutputProvingKey);
+
+/// Prove an output
+///
+/// The proof's public inputs are `[cm, cv.x, cv.y, asset]`; `cm` is the
+/// canonical encoding of `witness.commitment().leaf()`.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &OutputProvingKey,
+    witness: &OutputWitness,
+    rng: R,
+) -> Result<Halo2Proof> {
+    let instances = witness.public_inputs();
+    debug_assert_eq!(instances[CM].to_repr(), witness.commitment().leaf());
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(Halo2Proof::new(
+        proof,
+        instances.iter().map(halo2::encode_instance).collect(),
+        key.verification_key().id(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::note::AssetId;
+    use privl1_crypto::proof::OutputProof;
+    use privl1_crypto::PoseidonHash;
+    use rand::rngs::OsRng;
+
+    fn output_witness(value: u64) -> OutputWitness {
+        let keys = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(value, keys.public, *AssetId::NATIVE.as_bytes());
+        OutputWitness::random(&note, &mut OsRng)
+    }
+
+    #[test]
+    fn test_output_circuit() {
+        let witness = output_witness(1_000);
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+
+        // Dummy notes are owned by the identity
+        let dummy = OutputWitness::random(&Note::dummy(), &mut OsRng);
+        mock_prove(&dummy.circuit(), vec![dummy.public_inputs()], K).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let witness = output_witness(42);
+
+        for row in [CM, CV_X, CV_Y, ASSET] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_proof_layout() {
+        let witness = output_witness(7);
+        let encoded: Vec<_> = witness
+            .public_inputs()
+            .iter()
+            .map(halo2::encode_instance)
+            .collect();
+        let output = OutputProof {
+            proof: Halo2Proof::new(vec![], encoded, [0u8; 32]),
+            commitment: *witness.commitment(),
+            value_commitment: witness.value_commitment(),
+            circuit_version: CIRCUIT_VERSION,
+        };
+        assert!(output.binds_public_inputs());
+
+        let other = output_witness(7);
+        let swapped = OutputProof {
+            commitment: *other.commitment(),
+            ..output
+        };
+        assert!(!swapped.binds_public_inputs());
+    }
+
+    #[test]
+    fn test_asset_is_bound() {
+        // Spending 100 of a worthless asset and creating 100 of the native
+        // one gives equal value commitments for equal randomness, so the
+        // assets must be told apart by the public inputs
+        let keys = FullKeys::random(&mut OsRng);
+        let worthless = Note::new_with_owner(100, keys.public, [9u8; 32]);
+        let native = Note::new_with_owner(100, keys.public, *AssetId::NATIVE.as_bytes());
+        let rcv = Scalar::random(&mut OsRng);
+        let (worthless, native) = (
+            OutputWitness::new(&worthless, rcv),
+            OutputWitness::new(&native, rcv),
+        );
+        assert_eq!(worthless.value_commitment(), native.value_commitment());
+        assert_ne!(
+            worthless.public_inputs()[ASSET],
+            native.public_inputs()[ASSET]
+        );
+
+        // The circuit rejects the native asset for a note of another
+        let mut instances = worthless.public_inputs();
+        instances[ASSET] = native.public_inputs()[ASSET];
+        assert!(!mock_failures(&worthless.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // and an output declaring the native asset no longer binds the proof
+        let output = OutputProof {
+            proof: Halo2Proof::new(
+                vec![],
+                worthless
+                    .public_inputs()
+                    .iter()
+                    .map(halo2::encode_instance)
+                    .collect(),
+                [0u8; 32],
+            ),
+            commitment: *worthless.commitment(),
+            value_commitment: worthless.value_commitment(),
+            circuit_version: CIRCUIT_VERSION,
+        };
+        assert!(output.binds_public_inputs());
+        let relabelled = OutputProof {
+            commitment: NoteCommitment::new(
+                worthless.commitment().to_field(),
+                *AssetId::NATIVE.as_bytes(),
+            ),
+            ..output
+        };
+        assert!(!relabelled.binds_public_inputs());
+    }
+
+    #[test]
+    fn test_wrong_witness_rejected() {
+        let witness = output_witness(42);
+        let instances = witness.public_inputs();
+        let other = FullKeys::random(&mut OsRng)
+            .public
+            .as_point()
+            .inner()
+            .to_affine();
+
+        let tampered: [fn(&mut OutputCircuit, pallas::Affine); 5] = [
+            |c, _| c.value = Value::known(pallas::Base::from(43)),
+            |c, _| c.asset = Value::known(asset_base(&[1u8; 32])),
+            |c, owner| c.owner = Value::known(owner),
+            |c, _| c.rcm = c.rcm.map(|rcm| rcm + pallas::Base::ONE),
+            |c, _| c.rcv = c.rcv.map(|rcv| rcv + pallas::Scalar::ONE),
+        ];
+        for (i, tamper) in tampered.iter().enumerate() {
+            let mut circuit = witness.circuit();
+            tamper(&mut circuit, other);
+            assert!(
+                !mock_failures(&circuit, vec![instances.clone()], K)
+                    .unwrap()
+                    .is_empty(),
+                "witness {} unconstrained",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_range_enforced() {
+        let witness = output_witness(0);
+        let value = pallas::Base::from(u64::MAX) + pallas::Base::ONE;
+
+        // Public inputs consistent with a 2^64 value, computed natively
+        let (owner_x, owner_y) = witness.owner.coordinates();
+        let cm =
+            PoseidonHash::hash([value, witness.asset, owner_x, owner_y, witness.rcm]).to_field();
+        let pedersen = PedersenCommitment::new();
+        let cv = pedersen.value_generator().mul(&Scalar::from_base(value))
+            + pedersen.blinding_generator().mul(&witness.rcv);
+        let (cv_x, cv_y) = cv.coordinates();
+
+        let mut circuit = witness.circuit();
+        circuit.value = Value::known(value);
+        let instances = vec![cm, cv_x, cv_y, witness.asset];
+        assert!(!mock_failures(&circuit, vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // The largest value is accepted
+        let witness = output_witness(u64::MAX);
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let witness = output_witness(7);
+        let key = keygen().unwrap();
+
+        let proof = prove(&key, &witness, OsRng).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Claiming another commitment fails
+        let mut tampered = proof.clone();
+        tampered.public_inputs[CM] =
+            halo2::encode_instance(&(witness.commitment().to_field() + pallas::Base::ONE));
+        assert!(!tampered.verify(key.verification_key()).unwrap());
+    }
+}
@@ -0,0 +1,598 @@
+//! Powers-of-tau (phase 1) parameters over BLS12-381
+//!
+//! A powers-of-tau file holds, for a maximum domain size N = 2^power:
+//! - `[τ^i]_1` for i < 2N - 1 and `[τ^i]_2` for i < N
+//! - `[α τ^i]_1` and `[β τ^i]_1` for i < N, and `[β]_2`
+//!
+//! File layout: magic `PRIVL1-PTAU` || version (u8) || the fields above in
+//! ark-serialize compressed encoding. Points are checked to be on the curve
+//! and in the prime-order subgroup when read.
+//!
+//! Circuit-specific phase 2 needs these powers in the Lagrange basis of its
+//! evaluation domain; [`PowersOfTau::lagrange`] converts them with an FFT over
+//! group elements.
+//!
+//! Transcripts of public phase-1 ceremonies are imported from the snarkjs
+//! `.ptau` format with [`PowersOfTau::read_snarkjs`]. Only BLS12-381 files
+//! are accepted; the perpetual powers of tau published for BN254 cannot back
+//! these keys.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use ark_bls12_381::{Bls12_381, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{BigInt, BigInteger, Field, PrimeField, UniformRand};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use rand::{CryptoRng, RngCore};
+
+use crate::CircuitError;
+
+const MAGIC: &[u8; 11] = b"PRIVL1-PTAU";
+const VERSION: u8 = 1;
+
+/// Largest supported power (domains of up to 2^28 constraints)
+pub const MAX_POWER: u32 = 28;
+
+const SNARKJS_MAGIC: &[u8; 4] = b"ptau";
+
+/// Sections of a snarkjs `.ptau` file
+const SNARKJS_HEADER: u32 = 1;
+const SNARKJS_TAU_G1: u32 = 2;
+const SNARKJS_TAU_G2: u32 = 3;
+const SNARKJS_ALPHA_TAU_G1: u32 = 4;
+const SNARKJS_BETA_TAU_G1: u32 = 5;
+const SNARKJS_BETA_G2: u32 = 6;
+
+/// Size of a base field element in snarkjs files
+const SNARKJS_N8: usize = 48;
+
+/// Phase-1 parameters
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PowersOfTau {
+    /// log2 of the maximum domain size
+    pub power: u32,
+    /// `[τ^i]_1` for i < 2^(power+1) - 1
+    pub tau_g1: Vec<G1Affine>,
+    /// `[τ^i]_2` for i < 2^power
+    pub tau_g2: Vec<G2Affine>,
+    /// `[α τ^i]_1` for i < 2^power
+    pub alpha_tau_g1: Vec<G1Affine>,
+    /// `[β τ^i]_1` for i < 2^power
+    pub beta_tau_g1: Vec<G1Affine>,
+    /// `[β]_2`
+    pub beta_g2: G2Affine,
+}
+
+/// Phase-1 parameters in the Lagrange basis of a size-n domain
+#[derive(Clone, Debug)]
+pub struct LagrangeBasis {
+    /// `[L_i(τ)]_1`
+    pub tau_g1: Vec<G1Affine>,
+    /// `[L_i(τ)]_2`
+    pub tau_g2: Vec<G2Affine>,
+    /// `[α L_i(τ)]_1`
+    pub alpha_tau_g1: Vec<G1Affine>,
+    /// `[β L_i(τ)]_1`
+    pub beta_tau_g1: Vec<G1Affine>,
+    /// `[τ^i Z(τ)]_1` for i < n - 1, with `Z(X) = X^n - 1`
+    pub h_query: Vec<G1Affine>,
+}
+
+impl PowersOfTau {
+    /// Generate parameters from locally sampled secrets
+    ///
+    /// Whoever runs this knows τ, α and β. Only for tests and development
+    /// networks; production parameters must come from a phase-1 ceremony.
+    pub fn generate_insecure<R: RngCore + CryptoRng>(
+        power: u32,
+        rng: &mut R,
+    ) -> crate::Result<Self> {
+        if power == 0 || power > MAX_POWER {
+            return Err(CircuitError::InvalidParameters(format!(
+                "Unsupported power {}",
+                power
+            )));
+        }
+
+        let tau = Fr::rand(rng);
+        let alpha = Fr::rand(rng);
+        let beta = Fr::rand(rng);
+
+        let n = 1usize << power;
+        let mut powers = Vec::with_capacity(2 * n - 1);
+        let mut current = Fr::ONE;
+        for _ in 0..2 * n - 1 {
+            powers.push(current);
+            current *= tau;
+        }
+
+        let g1 = G1Projective::generator();
+        let g2 = G2Projective::generator();
+        let scaled = |factor: Fr| -> Vec<G1Affine> {
+            let points: Vec<G1Projective> =
+                powers[..n].iter().map(|p| g1 * (*p * factor)).collect();
+            G1Projective::normalize_batch(&points)
+        };
+
+        let tau_g1: Vec<G1Projective> = powers.iter().map(|p| g1 * p).collect();
+        let tau_g2: Vec<G2Projective> = powers[..n].iter().map(|p| g2 * p).collect();
+
+        Ok(Self {
+            power,
+            tau_g1: G1Projective::normalize_batch(&tau_g1),
+            tau_g2: G2Projective::normalize_batch(&tau_g2),
+            alpha_tau_g1: scaled(alpha),
+            beta_tau_g1: scaled(beta),
+            beta_g2: (g2 * beta).into_affine(),
+        })
+    }
+
+    /// Maximum supported domain size
+    pub fn max_domain_size(&self) -> usize {
+        1 << self.power
+    }
+
+    /// Check lengths and that every vector uses the same τ, α and β
+    ///
+    /// Uses random linear combinations, so the cost is a handful of pairings
+    /// plus one MSM per vector.
+    pub fn validate<R: RngCore + CryptoRng>(&self, rng: &mut R) -> crate::Result<()> {
+        let n = self.max_domain_size();
+        if self.power == 0
+            || self.power > MAX_POWER
+            || self.tau_g1.len() != 2 * n - 1
+            || self.tau_g2.len() != n
+            || self.alpha_tau_g1.len() != n
+            || self.beta_tau_g1.len() != n
+        {
+            return Err(CircuitError::InvalidParameters(
+                "Wrong vector lengths".into(),
+            ));
+        }
+
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return Err(CircuitError::InvalidParameters(
+                "Powers must start at the generator".into(),
+            ));
+        }
+        if self.tau_g1[1].is_zero() || self.alpha_tau_g1[0].is_zero() || self.beta_g2.is_zero() {
+            return Err(CircuitError::InvalidParameters("Degenerate secrets".into()));
+        }
+
+        let tau_in_g2 = (g2, self.tau_g2[1]);
+        let checks = [
+            // Consecutive G1 powers differ by τ
+            same_ratio(
+                merge_pairs(&self.tau_g1[..2 * n - 2], &self.tau_g1[1..], rng),
+                tau_in_g2,
+            ),
+            // Consecutive G2 powers differ by τ
+            same_ratio(
+                (g1, self.tau_g1[1]),
+                merge_pairs(&self.tau_g2[..n - 1], &self.tau_g2[1..], rng),
+            ),
+            // α and β vectors are the τ powers scaled by α and β
+            same_ratio(
+                merge_pairs(&self.alpha_tau_g1[..n - 1], &self.alpha_tau_g1[1..], rng),
+                tau_in_g2,
+            ),
+            same_ratio(
+                merge_pairs(&self.beta_tau_g1[..n - 1], &self.beta_tau_g1[1..], rng),
+                tau_in_g2,
+            ),
+            same_ratio((g1, self.beta_tau_g1[0]), (g2, self.beta_g2)),
+        ];
+
+        if checks.iter().all(|ok| *ok) {
+            Ok(())
+        } else {
+            Err(CircuitError::InvalidParameters(
+                "Inconsistent powers of tau".into(),
+            ))
+        }
+    }
+
+    /// Convert to the Lagrange basis of the size-`n` domain (n a power of two)
+    pub fn lagrange(&self, n: usize) -> crate::Result<LagrangeBasis> {
+        if !n.is_power_of_two() || n < 2 || n > self.max_domain_size() {
+            return Err(CircuitError::InvalidParameters(format!(
+                "Domain size {} not supported by 2^{} powers of tau",
+                n, self.power
+            )));
+        }
+        let domain = Radix2EvaluationDomain::<Fr>::new(n)
+            .ok_or_else(|| CircuitError::InvalidParameters(format!("No domain of size {}", n)))?;
+
+        let ifft_g1 = |points: &[G1Affine]| -> Vec<G1Affine> {
+            let mut projective: Vec<G1Projective> = points.iter().map(|p| p.into_group()).collect();
+            domain.ifft_in_place(&mut projective);
+            G1Projective::normalize_batch(&projective)
+        };
+
+        let mut tau_g2: Vec<G2Projective> =
+            self.tau_g2[..n].iter().map(|p| p.into_group()).collect();
+        domain.ifft_in_place(&mut tau_g2);
+
+        // τ^i Z(τ) = τ^(i+n) - τ^i
+        let h_query: Vec<G1Projective> = (0..n - 1)
+            .map(|i| self.tau_g1[i + n].into_group() - self.tau_g1[i])
+            .collect();
+
+        Ok(LagrangeBasis {
+            tau_g1: ifft_g1(&self.tau_g1[..n]),
+            tau_g2: G2Projective::normalize_batch(&tau_g2),
+            alpha_tau_g1: ifft_g1(&self.alpha_tau_g1[..n]),
+            beta_tau_g1: ifft_g1(&self.beta_tau_g1[..n]),
+            h_query: G1Projective::normalize_batch(&h_query),
+        })
+    }
+
+    /// Write in the powers-of-tau file format
+    pub fn write<W: Write>(&self, mut writer: W) -> crate::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        self.serialize_compressed(&mut writer)
+            .map_err(|e| CircuitError::SerializationError(e.to_string()))
+    }
+
+    /// Read and check a powers-of-tau file
+    pub fn read<R: Read>(mut reader: R) -> crate::Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[..11] != MAGIC || header[11] != VERSION {
+            return Err(CircuitError::SerializationError(
+                "Not a PRIVL1 powers-of-tau file".into(),
+            ));
+        }
+
+        Self::deserialize_compressed(reader)
+            .map_err(|e| CircuitError::SerializationError(e.to_string()))
+    }
+
+    /// Import a snarkjs `.ptau` file over BLS12-381, such as the output of
+    /// `snarkjs powersoftau prepare phase2`
+    ///
+    /// Keeps the powers for domains of up to 2^`power`, or all of them if
+    /// `power` is `None`. Points are checked to be on the curve and in the
+    /// prime-order subgroup; run [`Self::validate`] before use.
+    pub fn read_snarkjs<R: Read + Seek>(mut reader: R, power: Option<u32>) -> crate::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNARKJS_MAGIC {
+            return Err(snarkjs_error("bad magic"));
+        }
+        let _version = read_u32(&mut reader)?;
+
+        // Section table: type -> (offset, size)
+        let mut sections = HashMap::new();
+        for _ in 0..read_u32(&mut reader)? {
+            let kind = read_u32(&mut reader)?;
+            let size = read_u64(&mut reader)?;
+            let offset = reader.stream_position()?;
+            sections.insert(kind, (offset, size));
+            reader.seek(SeekFrom::Start(offset + size))?;
+        }
+        let seek = |reader: &mut R, kind: u32, len: u64| -> crate::Result<()> {
+            let (offset, size) = *sections
+                .get(&kind)
+                .ok_or_else(|| snarkjs_error(&format!("missing section {}", kind)))?;
+            if size < len {
+                return Err(snarkjs_error(&format!("section {} too short", kind)));
+            }
+            reader.seek(SeekFrom::Start(offset))?;
+            Ok(())
+        };
+
+        seek(&mut reader, SNARKJS_HEADER, 4 + SNARKJS_N8 as u64 + 4)?;
+        let mut modulus = [0u8; SNARKJS_N8];
+        if read_u32(&mut reader)? as usize == SNARKJS_N8 {
+            reader.read_exact(&mut modulus)?;
+        }
+        if modulus[..] != Fq::MODULUS.to_bytes_le()[..] {
+            return Err(snarkjs_error("not a BLS12-381 file"));
+        }
+        let file_power = read_u32(&mut reader)?;
+        let power = power.unwrap_or(file_power);
+        if power == 0 || power > file_power || power > MAX_POWER {
+            return Err(CircuitError::InvalidParameters(format!(
+                "Cannot take 2^{} powers from a 2^{} file",
+                power, file_power
+            )));
+        }
+
+        let n = 1u64 << power;
+        let g1_size = |count: u64| count * 2 * SNARKJS_N8 as u64;
+        let g2_size = |count: u64| count * 4 * SNARKJS_N8 as u64;
+        seek(&mut reader, SNARKJS_TAU_G1, g1_size(2 * n - 1))?;
+        let tau_g1 = read_points(&mut reader, 2 * n - 1, read_g1)?;
+        seek(&mut reader, SNARKJS_TAU_G2, g2_size(n))?;
+        let tau_g2 = read_points(&mut reader, n, read_g2)?;
+        seek(&mut reader, SNARKJS_ALPHA_TAU_G1, g1_size(n))?;
+        let alpha_tau_g1 = read_points(&mut reader, n, read_g1)?;
+        seek(&mut reader, SNARKJS_BETA_TAU_G1, g1_size(n))?;
+        let beta_tau_g1 = read_points(&mut reader, n, read_g1)?;
+        seek(&mut reader, SNARKJS_BETA_G2, g2_size(1))?;
+        let beta_g2 = read_g2(&mut reader)?;
+
+        Ok(Self {
+            power,
+            tau_g1,
+            tau_g2,
+            alpha_tau_g1,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+}
+
+fn snarkjs_error(msg: &str) -> CircuitError {
+    CircuitError::SerializationError(format!("Invalid snarkjs ptau file: {}", msg))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> crate::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> crate::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_points<R: Read, P>(
+    reader: &mut R,
+    count: u64,
+    read: impl Fn(&mut R) -> crate::Result<P>,
+) -> crate::Result<Vec<P>> {
+    (0..count).map(|_| read(reader)).collect()
+}
+
+/// Read a base field element in snarkjs' little-endian Montgomery form
+fn read_fq<R: Read>(reader: &mut R) -> crate::Result<Fq> {
+    let mut bytes = [0u8; SNARKJS_N8];
+    reader.read_exact(&mut bytes)?;
+    let mut limbs = [0u64; 6];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().expect("8-byte chunks"));
+    }
+    let montgomery = BigInt::new(limbs);
+    if montgomery >= Fq::MODULUS {
+        return Err(CircuitError::SerializationError(
+            "Non-canonical field element".into(),
+        ));
+    }
+    Ok(Fq::new_unchecked(montgomery))
+}
+
+fn read_g1<R: Read>(reader: &mut R) -> crate::Result<G1Affine> {
+    let (x, y) = (read_fq(reader)?, read_fq(reader)?);
+    checked_point(G1Affine::new_unchecked(x, y), x.is_zero() && y.is_zero())
+}
+
+fn read_g2<R: Read>(reader: &mut R) -> crate::Result<G2Affine> {
+    let x = Fq2::new(read_fq(reader)?, read_fq(reader)?);
+    let y = Fq2::new(read_fq(reader)?, read_fq(reader)?);
+    checked_point(G2Affine::new_unchecked(x, y), x.is_zero() && y.is_zero())
+}
+
+/// snarkjs writes the point at infinity as zero coordinates
+fn checked_point<P>(
+    point: ark_ec::short_weierstrass::Affine<P>,
+    infinity: bool,
+) -> crate::Result<ark_ec::short_weierstrass::Affine<P>>
+where
+    P: ark_ec::short_weierstrass::SWCurveConfig,
+{
+    if infinity {
+        return Ok(ark_ec::short_weierstrass::Affine::identity());
+    }
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(CircuitError::SerializationError(
+            "Point not in the prime-order subgroup".into(),
+        ));
+    }
+    Ok(point)
+}
+
+/// Check `g1.1 / g1.0 == g2.1 / g2.0` in the exponent
+pub(crate) fn same_ratio<A, B>(g1: (A, A), g2: (B, B)) -> bool
+where
+    A: Into<G1Projective>,
+    B: Into<G2Projective>,
+{
+    let (a0, a1) = (g1.0.into(), g1.1.into());
+    let (b0, b1) = (g2.0.into(), g2.1.into());
+    if a0.is_zero() || a1.is_zero() || b0.is_zero() || b1.is_zero() {
+        return false;
+    }
+
+    Bls12_381::pairing(a0, b1) == Bls12_381::pairing(a1, b0)
+}
+
+/// Random linear combinations `(Σ ρ_i v1_i, Σ ρ_i v2_i)` with shared weights
+pub(crate) fn merge_pairs<G, R>(v1: &[G], v2: &[G], rng: &mut R) -> (G::Group, G::Group)
+where
+    G: AffineRepr<ScalarField = Fr>,
+    G::Group: VariableBaseMSM<MulBase = G, ScalarField = Fr>,
+    R: RngCore + CryptoRng,
+{
+    debug_assert_eq!(v1.len(), v2.len());
+    let weights: Vec<Fr> = (0..v1.len()).map(|_| Fr::rand(rng)).collect();
+
+    (
+        G::Group::msm_unchecked(v1, &weights),
+        G::Group::msm_unchecked(v2, &weights),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    /// Encode `ptau` as snarkjs does, with an unrelated section after the header
+    fn snarkjs_file(ptau: &PowersOfTau) -> Vec<u8> {
+        fn fq(out: &mut Vec<u8>, value: &Fq) {
+            out.extend_from_slice(&value.0.to_bytes_le());
+        }
+        let g1 = |out: &mut Vec<u8>, points: &[G1Affine]| {
+            for p in points {
+                fq(out, &p.x);
+                fq(out, &p.y);
+            }
+        };
+        let g2 = |out: &mut Vec<u8>, points: &[G2Affine]| {
+            for p in points {
+                for c in [p.x.c0, p.x.c1, p.y.c0, p.y.c1] {
+                    fq(out, &c);
+                }
+            }
+        };
+
+        let mut header = (SNARKJS_N8 as u32).to_le_bytes().to_vec();
+        header.extend_from_slice(&Fq::MODULUS.to_bytes_le());
+        header.extend_from_slice(&ptau.power.to_le_bytes());
+        header.extend_from_slice(&ptau.power.to_le_bytes());
+        let mut sections = vec![(SNARKJS_HEADER, header), (7, vec![0u8; 4])];
+        let mut body = Vec::new();
+        g1(&mut body, &ptau.tau_g1);
+        sections.push((SNARKJS_TAU_G1, std::mem::take(&mut body)));
+        g2(&mut body, &ptau.tau_g2);
+        sections.push((SNARKJS_TAU_G2, std::mem::take(&mut body)));
+        g1(&mut body, &ptau.alpha_tau_g1);
+        sections.push((SNARKJS_ALPHA_TAU_G1, std::mem::take(&mut body)));
+        g1(&mut body, &ptau.beta_tau_g1);
+        sections.push((SNARKJS_BETA_TAU_G1, std::mem::take(&mut body)));
+        g2(&mut body, &[ptau.beta_g2]);
+        sections.push((SNARKJS_BETA_G2, body));
+
+        let mut file = SNARKJS_MAGIC.to_vec();
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+        for (kind, data) in sections {
+            file.extend_from_slice(&kind.to_le_bytes());
+            file.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            file.extend_from_slice(&data);
+        }
+        file
+    }
+
+    #[test]
+    fn test_generate_and_validate() {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(3, &mut rng).unwrap();
+
+        assert_eq!(ptau.max_domain_size(), 8);
+        assert_eq!(ptau.tau_g1.len(), 15);
+        ptau.validate(&mut rng).unwrap();
+
+        assert!(PowersOfTau::generate_insecure(0, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_tampered_powers_rejected() {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(3, &mut rng).unwrap();
+
+        let mut tampered = ptau.clone();
+        tampered.tau_g1[5] = (tampered.tau_g1[5] + G1Affine::generator()).into_affine();
+        assert!(tampered.validate(&mut rng).is_err());
+
+        let mut tampered = ptau.clone();
+        tampered.alpha_tau_g1.swap(1, 2);
+        assert!(tampered.validate(&mut rng).is_err());
+
+        let mut tampered = ptau;
+        tampered.tau_g2.pop();
+        assert!(tampered.validate(&mut rng).is_err());
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(3, &mut rng).unwrap();
+        let basis = ptau.lagrange(4).unwrap();
+
+        // Σ L_i(τ) = 1
+        let sum: G1Projective = basis.tau_g1.iter().map(|p| p.into_group()).sum();
+        assert_eq!(sum.into_affine(), G1Affine::generator());
+        assert_eq!(basis.h_query.len(), 3);
+
+        assert!(ptau.lagrange(3).is_err());
+        assert!(ptau.lagrange(16).is_err());
+    }
+
+    #[test]
+    fn test_file_roundtrip() {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(2, &mut rng).unwrap();
+
+        let mut bytes = Vec::new();
+        ptau.write(&mut bytes).unwrap();
+        assert_eq!(PowersOfTau::read(bytes.as_slice()).unwrap(), ptau);
+
+        bytes[0] ^= 1;
+        assert!(PowersOfTau::read(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_snarkjs_import() {
+        let mut rng = test_rng();
+        let ptau = PowersOfTau::generate_insecure(3, &mut rng).unwrap();
+        let file = snarkjs_file(&ptau);
+
+        // The G1 generator in snarkjs' little-endian Montgomery encoding
+        let generator = hex::decode(concat!(
+            "160c53fd9087b35cf5ff769967fc1778c1a13b14c7954f1547e7d0f3cd6aaef040f4db21cc6eceed75fb0b9e41770112",
+            "7122e70cd593acba8efd18791a63228cce250757135f59dd945140502958ac51c05900ad3f8c1c0e6aa20850fc3ebc0b",
+        ))
+        .unwrap();
+        let tau_g1 = 12 + 4 + 8 + 60 + 4 + 8 + 4 + 4 + 8;
+        assert_eq!(file[tau_g1..tau_g1 + 96], generator[..]);
+
+        let imported = PowersOfTau::read_snarkjs(std::io::Cursor::new(&file), None).unwrap();
+        assert_eq!(imported, ptau);
+        imported.validate(&mut rng).unwrap();
+
+        // Fewer powers for a smaller ceremony
+        let truncated = PowersOfTau::read_snarkjs(std::io::Cursor::new(&file), Some(2)).unwrap();
+        assert_eq!(truncated.tau_g1, ptau.tau_g1[..7]);
+        assert_eq!(truncated.tau_g2, ptau.tau_g2[..4]);
+        truncated.validate(&mut rng).unwrap();
+        assert!(PowersOfTau::read_snarkjs(std::io::Cursor::new(&file), Some(4)).is_err());
+    }
+
+    #[test]
+    fn test_snarkjs_rejects_malformed() {
+        let ptau = PowersOfTau::generate_insecure(2, &mut test_rng()).unwrap();
+        let file = snarkjs_file(&ptau);
+        let read = |bytes: &[u8]| PowersOfTau::read_snarkjs(std::io::Cursor::new(bytes), None);
+
+        // Another curve's modulus (BN254 files have 32-byte elements)
+        let mut other = file.clone();
+        other[24] = 32;
+        assert!(read(&other).is_err());
+
+        // A point off the curve
+        let mut off_curve = file.clone();
+        off_curve[12 + 4 + 8 + 60 + 4 + 8 + 4 + 4 + 8 + 96] ^= 1;
+        assert!(read(&off_curve).is_err());
+
+        // A truncated section
+        assert!(read(&file[..file.len() - 1]).is_err());
+        assert!(read(&file[1..]).is_err());
+    }
+}
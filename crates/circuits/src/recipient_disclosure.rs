@@ -0,0 +1,260 @@
+//! Recipient disclosure circuit
+//!
+//! Proves "this output went to address Y" for a
+//! `DisclosureStatement::OutputRecipient`. For public `(cm, recipient,
+//! context)`, the circuit checks
+//! `cm = Poseidon(value, asset, recipient.x, recipient.y, rcm)`, so the note is
+//! owned by `recipient`. Value and asset stay private.
+//!
+//! The sender knows `rcm` from building the output, so either side of a
+//! payment can disclose it. `context` is only bound to the proof.
+
+use group::Curve;
+use halo2_gadgets::ecc::Point;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::disclosure::{DisclosureProof, DisclosureStatement};
+use privl1_crypto::halo2;
+use privl1_crypto::note::{asset_base, Note};
+use privl1_crypto::proof::Halo2Proof;
+use rand::{CryptoRng, RngCore};
+
+use crate::gadgets::assign_free_advice;
+use crate::note::NoteConfig;
+use crate::Result;
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 11;
+
+/// Name the recipient verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "disclose-recipient-v1";
+
+/// Version of the recipient circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows, in `DisclosureStatement::public_inputs` order
+pub const CM: usize = 0;
+pub const RECIPIENT_X: usize = 1;
+pub const RECIPIENT_Y: usize = 2;
+pub const CONTEXT: usize = 3;
+
+/// The recipient circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct RecipientCircuit {
+    value: Value<pallas::Base>,
+    asset: Value<pallas::Base>,
+    recipient: Value<pallas::Affine>,
+    rcm: Value<pallas::Base>,
+}
+
+impl Circuit<pallas::Base> for RecipientCircuit {
+    type Config = NoteConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> NoteConfig {
+        NoteConfig::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: NoteConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        config.load(&mut layouter)?;
+        config.public_base(layouter.namespace(|| "context"), CONTEXT)?;
+
+        let value = assign_free_advice(
+            layouter.namespace(|| "value"),
+            config.advices[0],
+            self.value,
+        )?;
+        let asset = assign_free_advice(
+            layouter.namespace(|| "asset"),
+            config.advices[1],
+            self.asset,
+        )?;
+        let rcm = assign_free_advice(layouter.namespace(|| "rcm"), config.advices[2], self.rcm)?;
+        let recipient = Point::new(
+            config.ecc_chip(),
+            layouter.namespace(|| "recipient"),
+            self.recipient,
+        )?;
+        config.expose_point(&mut layouter, &recipient, RECIPIENT_X)?;
+
+        let cm =
+            config.note_commitment(layouter.namespace(|| "cm"), value, asset, &recipient, rcm)?;
+        layouter.constrain_instance(cm.cell(), config.primary, CM)
+    }
+}
+
+/// Private inputs of a recipient disclosure
+#[derive(Clone, Debug)]
+pub struct RecipientWitness {
+    note: Note,
+    context: [u8; 32],
+}
+
+impl RecipientWitness {
+    /// Build the witness disclosing who `note` pays
+    pub fn new(note: &Note, context: [u8; 32]) -> Self {
+        Self {
+            note: note.clone(),
+            context,
+        }
+    }
+
+    /// The disclosed statement
+    pub fn statement(&self) -> DisclosureStatement {
+        DisclosureStatement::OutputRecipient {
+            commitment: self.note.commitment(),
+            recipient: *self.note.owner(),
+        }
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        self.statement()
+            .public_inputs(&self.context)
+            .expect("recipient statements always encode")
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> RecipientCircuit {
+        RecipientCircuit {
+            value: Value::known(pallas::Base::from(self.note.value())),
+            asset: Value::known(asset_base(self.note.asset_id())),
+            recipient: Value::known(self.note.owner().as_point().inner().to_affine()),
+            rcm: Value::known(self.note.rcm()),
+        }
+    }
+}
+
+crate::keystore::circuit_keys!("recipient", RecipientCircuit, RecipientProvingKey);
+
+/// Prove an output's recipient
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &RecipientProvingKey,
+    witness: &RecipientWitness,
+    rng: R,
+) -> Result<DisclosureProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(DisclosureProof::new(
+        witness.statement(),
+        witness.context,
+        Halo2Proof::new(
+            proof,
+            instances.iter().map(halo2::encode_instance).collect(),
+            key.verification_key().id(),
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::disclosure::DisclosureContext;
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::merkle::MerkleRoot;
+    use privl1_crypto::note::{AssetId, NoteCommitment};
+    use privl1_crypto::nullifier::Nullifier;
+    use rand::rngs::OsRng;
+
+    /// A chain holding `commitments`
+    struct Chain(Vec<NoteCommitment>);
+
+    impl DisclosureContext for Chain {
+        fn is_anchor(&self, _: &MerkleRoot) -> bool {
+            false
+        }
+
+        fn commitment_height(&self, _: u64) -> Option<u64> {
+            None
+        }
+
+        fn has_commitment(&self, commitment: &NoteCommitment) -> bool {
+            self.0.contains(commitment)
+        }
+
+        fn is_spent(&self, _: &Nullifier) -> bool {
+            false
+        }
+
+        fn is_state_root(&self, _: &MerkleRoot, _: &MerkleRoot) -> bool {
+            false
+        }
+    }
+
+    fn payment() -> (FullKeys, Note) {
+        let recipient = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(250, recipient.public, *AssetId::NATIVE.as_bytes());
+        (recipient, note)
+    }
+
+    #[test]
+    fn test_recipient_circuit() {
+        let (_, note) = payment();
+        let witness = RecipientWitness::new(&note, [7u8; 32]);
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_recipient_rejected() {
+        let (_, note) = payment();
+        let witness = RecipientWitness::new(&note, [7u8; 32]);
+
+        for row in [CM, RECIPIENT_X, RECIPIENT_Y] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+
+        // Another address, as a valid point
+        let other = FullKeys::random(&mut OsRng).public;
+        let (x, y) = other.as_point().coordinates();
+        let mut instances = witness.public_inputs();
+        instances[RECIPIENT_X] = x;
+        instances[RECIPIENT_Y] = y;
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prove_and_verify_against_chain() {
+        let (_, note) = payment();
+        let witness = RecipientWitness::new(&note, [7u8; 32]);
+        let key = keygen().unwrap();
+        let disclosure = prove(&key, &witness, OsRng).unwrap();
+
+        let chain = Chain(vec![note.commitment()]);
+        let vk = key.verification_key();
+        assert!(disclosure.verify_against(vk, &[7u8; 32], &chain).unwrap());
+
+        // Only for the verifier's context, and only for outputs on chain
+        assert!(!disclosure.verify_against(vk, &[8u8; 32], &chain).unwrap());
+        assert!(!disclosure
+            .verify_against(vk, &[7u8; 32], &Chain(vec![]))
+            .unwrap());
+    }
+}
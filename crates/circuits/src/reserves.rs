@@ -0,0 +1,840 @@
+//! Proof-of-reserves circuit
+//!
+//! Proves "my unspent notes hold at least X of asset A" for a
+//! `DisclosureStatement::ReservesAtLeast`, revealing no note and no
+//! nullifier. For public `(anchor, nullifier_root, asset, threshold, owner,
+//! context)`, the circuit checks, for each of `MAX_RESERVE_NOTES` slots:
+//! - `cm = Poseidon(value, asset, owner.x, owner.y, rcm)` with
+//!   `owner = ak + [nk]N`, so the prover holds the owner's nullifier key
+//! - `cm` is a leaf of the tree with root `anchor`
+//! - `nf = Poseidon(nk, cm, position)` and its key, the low 250 bits of `nf`,
+//!   lies strictly between the keys of a leaf `Poseidon(low, next)` of the
+//!   nullifier tree with root `nullifier_root`, so the note is unspent
+//!
+//! Both tree checks are skipped for zero-value slots, which pad the witness.
+//! Keys must strictly increase from slot to slot, so no note is counted
+//! twice, and the values must sum to at least `threshold` without u64
+//! overflow. The verifier checks both roots were committed by one block.
+//!
+//! The prover needs only the owner's [`FullViewingKey`], never the spending
+//! key. That key can build spend, swap, lock, vote and claim proofs, but
+//! their verifiers also require the signature under `rk` that only the
+//! spending key makes, so it cannot move the notes; it does reveal all of
+//! them to the prover. A proof counts at most [`MAX_RESERVE_NOTES`] notes of
+//! one owner. Larger holdings are split across owner keys, with one proof per key, and
+//! [`combined_reserves`] adds up proofs for distinct owners under the same
+//! roots, asset and context. Every note commits to its owner, so such proofs
+//! count disjoint notes. Two proofs for one owner may count the same note,
+//! since no nullifier is revealed, so they are never combined; an owner with
+//! more notes than a proof holds consolidates them first.
+
+use ff::{Field, PrimeField};
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::disclosure::{DisclosureProof, DisclosureStatement};
+use privl1_crypto::halo2;
+use privl1_crypto::keys::FullViewingKey;
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::Note;
+use privl1_crypto::nullifier::{Nullifier, NullifierDerivingKey};
+use privl1_crypto::nullifier_tree::{nullifier_key, NonMembershipProof, NULLIFIER_KEY_BITS};
+use privl1_crypto::proof::{Halo2Proof, VerificationKey};
+use privl1_crypto::{Point as NativePoint, PoseidonHash, PublicKey, Scalar};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::gadgets::{self, assign_free_advice, MerklePathConfig, U64Config};
+use crate::note::NoteConfig;
+use crate::spend::{authentication_path, merkle_root};
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 15;
+
+/// Name the reserves verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "reserves-v1";
+
+/// Version of the reserves circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Notes a proof of reserves can sum; see the module docs for larger holdings
+pub const MAX_RESERVE_NOTES: usize = 8;
+
+/// Public input rows, in `DisclosureStatement::public_inputs` order
+pub const ANCHOR: usize = 0;
+pub const NULLIFIER_ROOT: usize = 1;
+pub const ASSET: usize = 2;
+pub const THRESHOLD: usize = 3;
+pub const OWNER_X: usize = 4;
+pub const OWNER_Y: usize = 5;
+pub const CONTEXT: usize = 6;
+
+/// Configuration of the reserves circuit
+#[derive(Clone, Debug)]
+pub struct ReservesConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+/// The reserves circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct ReservesCircuit {
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    values: [Value<u64>; MAX_RESERVE_NOTES],
+    rcms: [Value<pallas::Base>; MAX_RESERVE_NOTES],
+    paths: [Value<[pallas::Base; TREE_DEPTH]>; MAX_RESERVE_NOTES],
+    positions: [Value<u64>; MAX_RESERVE_NOTES],
+    low_keys: [Value<pallas::Base>; MAX_RESERVE_NOTES],
+    next_keys: [Value<pallas::Base>; MAX_RESERVE_NOTES],
+    low_paths: [Value<[pallas::Base; TREE_DEPTH]>; MAX_RESERVE_NOTES],
+    low_positions: [Value<u64>; MAX_RESERVE_NOTES],
+}
+
+impl Circuit<pallas::Base> for ReservesCircuit {
+    type Config = ReservesConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> ReservesConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        ReservesConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: ReservesConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        let arith = &config.arith;
+        note.load(&mut layouter)?;
+
+        let anchor = note.public_base(layouter.namespace(|| "anchor"), ANCHOR)?;
+        let nullifier_root =
+            note.public_base(layouter.namespace(|| "nullifier root"), NULLIFIER_ROOT)?;
+        let asset = note.public_base(layouter.namespace(|| "asset"), ASSET)?;
+        let threshold = note.public_amount(layouter.namespace(|| "threshold"), THRESHOLD)?;
+        note.public_base(layouter.namespace(|| "context"), CONTEXT)?;
+
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        note.expose_point(&mut layouter, &owner, OWNER_X)?;
+
+        let mut total = arith.constant(layouter.namespace(|| "no reserves"), 0)?;
+        let mut previous_key = None;
+        for i in 0..MAX_RESERVE_NOTES {
+            let mut layouter = layouter.namespace(|| format!("note {}", i));
+
+            let value = arith.witness(layouter.namespace(|| "value"), self.values[i])?;
+            let rcm =
+                assign_free_advice(layouter.namespace(|| "rcm"), note.advices[2], self.rcms[i])?;
+            let cm = note.note_commitment(
+                layouter.namespace(|| "cm"),
+                value.clone(),
+                asset.clone(),
+                &owner,
+                rcm,
+            )?;
+
+            // Notes holding value are in the commitment tree
+            let (root, position) = config.merkle.calculate_root(
+                layouter.namespace(|| "merkle path"),
+                cm.clone(),
+                self.paths[i],
+                self.positions[i],
+            )?;
+            arith.assert_equal_unless_zero(
+                layouter.namespace(|| "root = anchor"),
+                &value,
+                &root,
+                &anchor,
+            )?;
+
+            // ... and their nullifier key falls in a gap of the nullifier tree
+            let nf =
+                note.nullifier(layouter.namespace(|| "nullifier"), nk.clone(), cm, position)?;
+            let key = arith.low_bits(layouter.namespace(|| "key"), &nf, NULLIFIER_KEY_BITS)?;
+            let low = assign_free_advice(
+                layouter.namespace(|| "low key"),
+                note.advices[0],
+                self.low_keys[i],
+            )?;
+            let next = assign_free_advice(
+                layouter.namespace(|| "next key"),
+                note.advices[1],
+                self.next_keys[i],
+            )?;
+            arith.assert_lt_bits(
+                layouter.namespace(|| "low < key"),
+                &low,
+                &key,
+                NULLIFIER_KEY_BITS,
+            )?;
+            arith.assert_lt_bits(
+                layouter.namespace(|| "key < next"),
+                &key,
+                &next,
+                NULLIFIER_KEY_BITS,
+            )?;
+
+            let leaf = gadgets::poseidon_hash(
+                &note.poseidon,
+                layouter.namespace(|| "low leaf"),
+                [low, next],
+            )?;
+            let (root, _) = config.merkle.calculate_root(
+                layouter.namespace(|| "low leaf path"),
+                leaf,
+                self.low_paths[i],
+                self.low_positions[i],
+            )?;
+            arith.assert_equal_unless_zero(
+                layouter.namespace(|| "root = nullifier root"),
+                &value,
+                &root,
+                &nullifier_root,
+            )?;
+
+            // Distinct notes, in key order
+            if let Some(previous_key) = previous_key {
+                arith.assert_lt_bits(
+                    layouter.namespace(|| "key order"),
+                    &previous_key,
+                    &key,
+                    NULLIFIER_KEY_BITS,
+                )?;
+            }
+            previous_key = Some(key);
+
+            total = arith.checked_add(layouter.namespace(|| "total"), &total, &value)?;
+        }
+        arith.assert_le(
+            layouter.namespace(|| "threshold <= total"),
+            &threshold,
+            &total,
+        )
+    }
+}
+
+/// A note counted towards reserves, with the proofs that it is held and
+/// unspent
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReserveNote {
+    /// The note
+    pub note: Note,
+    /// Path of the note commitment to the anchor
+    pub merkle_proof: MerkleProof,
+    /// Proof that the note's nullifier is not in the nullifier tree
+    pub unspent: NonMembershipProof,
+}
+
+/// One note slot of a proof of reserves
+#[derive(Clone, Debug)]
+struct Slot {
+    value: u64,
+    rcm: pallas::Base,
+    path: [pallas::Base; TREE_DEPTH],
+    position: u64,
+    nullifier: Nullifier,
+    low_key: pallas::Base,
+    next_key: pallas::Base,
+    low_path: [pallas::Base; TREE_DEPTH],
+    low_position: u64,
+}
+
+impl Slot {
+    fn new(reserve: &ReserveNote, nk: &NullifierDerivingKey) -> Result<Self> {
+        let position = reserve.merkle_proof.position;
+
+        Ok(Self {
+            value: reserve.note.value(),
+            rcm: reserve.note.rcm(),
+            path: authentication_path(&reserve.merkle_proof)?,
+            position,
+            nullifier: nk.derive_nullifier(&reserve.note, position),
+            low_key: reserve.unspent.low_key()?,
+            next_key: reserve.unspent.next_key()?,
+            low_path: authentication_path(&reserve.unspent.merkle_proof)?,
+            low_position: reserve.unspent.merkle_proof.position,
+        })
+    }
+
+    /// A zero-value padding slot with a fresh nullifier
+    ///
+    /// Its tree checks are skipped, so it only needs `low < key < next`.
+    fn padding<R: RngCore>(fvk: &FullViewingKey, asset_id: [u8; 32], rng: &mut R) -> Self {
+        let note = Note::with_randomness(0, fvk.public_key(), asset_id, Scalar::random(rng));
+        let nullifier = fvk.nullifier_key().derive_nullifier(&note, 0);
+        let key = nullifier_key(&nullifier);
+
+        Self {
+            value: 0,
+            rcm: note.rcm(),
+            path: [pallas::Base::ZERO; TREE_DEPTH],
+            position: 0,
+            nullifier,
+            low_key: pallas::Base::ZERO,
+            next_key: key + pallas::Base::ONE,
+            low_path: [pallas::Base::ZERO; TREE_DEPTH],
+            low_position: 0,
+        }
+    }
+
+    /// Big-endian key, so that byte order is key order
+    fn sort_key(&self) -> [u8; 32] {
+        let mut bytes = nullifier_key(&self.nullifier).to_repr();
+        bytes.reverse();
+        bytes
+    }
+}
+
+/// Private inputs of a proof of reserves
+#[derive(Clone, Debug)]
+pub struct ReservesWitness {
+    ak: NativePoint,
+    nk: pallas::Base,
+    owner: PublicKey,
+    asset_id: [u8; 32],
+    threshold: u64,
+    anchor: MerkleRoot,
+    nullifier_root: MerkleRoot,
+    slots: [Slot; MAX_RESERVE_NOTES],
+    context: [u8; 32],
+}
+
+impl ReservesWitness {
+    /// Build the witness proving that `notes`, each with its Merkle proof
+    /// under one anchor and its non-membership proof under one nullifier
+    /// root, hold at least `threshold`
+    ///
+    /// Fails if there are no notes or more than `MAX_RESERVE_NOTES`, the
+    /// owner of `fvk` does not own them all, they differ in asset, anchor or
+    /// nullifier root, a note repeats or is spent, or they hold less than
+    /// `threshold`.
+    pub fn new<R: RngCore>(
+        notes: &[ReserveNote],
+        fvk: &FullViewingKey,
+        threshold: u64,
+        context: [u8; 32],
+        rng: &mut R,
+    ) -> Result<Self> {
+        let Some(first) = notes.first() else {
+            return Err(CircuitError::InvalidParameters(
+                "No notes to prove reserves with".into(),
+            ));
+        };
+        if notes.len() > MAX_RESERVE_NOTES {
+            return Err(CircuitError::InvalidParameters(format!(
+                "At most {} notes per proof of reserves",
+                MAX_RESERVE_NOTES
+            )));
+        }
+        let asset_id = *first.note.asset_id();
+        let owner = fvk.public_key();
+
+        let mut slots: Vec<Slot> = Vec::with_capacity(MAX_RESERVE_NOTES);
+        let mut roots = Vec::with_capacity(notes.len());
+        for reserve in notes {
+            let note = &reserve.note;
+            if note.owner() != &owner {
+                return Err(CircuitError::InvalidParameters(
+                    "Note is not owned by these keys".into(),
+                ));
+            }
+            if note.asset_id() != &asset_id {
+                return Err(CircuitError::InvalidParameters(
+                    "Notes differ in asset".into(),
+                ));
+            }
+
+            let slot = Slot::new(reserve, fvk.nullifier_key())?;
+            let leaf = PoseidonHash::hash_two(slot.low_key, slot.next_key).to_field();
+            let nullifier_root = merkle_root(leaf, &slot.low_path, slot.low_position);
+            if !reserve.unspent.verify(&slot.nullifier, &nullifier_root) {
+                return Err(CircuitError::InvalidParameters("Note is spent".into()));
+            }
+            if slots.iter().any(|other| other.nullifier == slot.nullifier) {
+                return Err(CircuitError::InvalidParameters("Note counted twice".into()));
+            }
+
+            roots.push((
+                merkle_root(note.commitment().to_field(), &slot.path, slot.position),
+                nullifier_root,
+            ));
+            slots.push(slot);
+        }
+        let (anchor, nullifier_root) = roots[0];
+        if roots.iter().any(|(root, _)| *root != anchor) {
+            return Err(CircuitError::InvalidParameters(
+                "Notes differ in anchor".into(),
+            ));
+        }
+        if roots.iter().any(|(_, root)| *root != nullifier_root) {
+            return Err(CircuitError::InvalidParameters(
+                "Notes differ in nullifier root".into(),
+            ));
+        }
+
+        slots
+            .iter()
+            .try_fold(0u64, |total, slot| total.checked_add(slot.value))
+            .filter(|total| *total >= threshold)
+            .ok_or_else(|| {
+                CircuitError::InvalidParameters("Notes hold less than the threshold".into())
+            })?;
+
+        // Pad with zero-value notes, then order every slot by key
+        while slots.len() < MAX_RESERVE_NOTES {
+            slots.push(Slot::padding(fvk, asset_id, rng));
+        }
+        slots.sort_by_key(Slot::sort_key);
+
+        Ok(Self {
+            ak: *fvk.validating_key(),
+            nk: fvk.nullifier_key().to_base(),
+            owner,
+            asset_id,
+            threshold,
+            anchor,
+            nullifier_root,
+            slots: slots.try_into().expect("padded to MAX_RESERVE_NOTES"),
+            context,
+        })
+    }
+
+    /// The proven statement
+    pub fn statement(&self) -> DisclosureStatement {
+        DisclosureStatement::ReservesAtLeast {
+            anchor: self.anchor,
+            nullifier_root: self.nullifier_root,
+            asset_id: self.asset_id,
+            threshold: self.threshold,
+            owner: self.owner,
+        }
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        self.statement()
+            .public_inputs(&self.context)
+            .expect("Poseidon output is canonical")
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> ReservesCircuit {
+        let slots = &self.slots;
+
+        ReservesCircuit {
+            ak: Value::known(self.ak.inner().to_affine()),
+            nk: Value::known(self.nk),
+            values: slots.clone().map(|slot| Value::known(slot.value)),
+            rcms: slots.clone().map(|slot| Value::known(slot.rcm)),
+            paths: slots.clone().map(|slot| Value::known(slot.path)),
+            positions: slots.clone().map(|slot| Value::known(slot.position)),
+            low_keys: slots.clone().map(|slot| Value::known(slot.low_key)),
+            next_keys: slots.clone().map(|slot| Value::known(slot.next_key)),
+            low_paths: slots.clone().map(|slot| Value::known(slot.low_path)),
+            low_positions: slots.clone().map(|slot| Value::known(slot.low_position)),
+        }
+    }
+}
+
+crate::keystore::circuit_keys!("reserves", ReservesCircuit, ReservesProvingKey);
+
+/// Prove reserves
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &ReservesProvingKey,
+    witness: &ReservesWitness,
+    rng: R,
+) -> Result<DisclosureProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(DisclosureProof::new(
+        witness.statement(),
+        witness.context,
+        Halo2Proof::new(
+            proof,
+            instances.iter().map(halo2::encode_instance).collect(),
+            key.verification_key().id(),
+        ),
+    ))
+}
+
+/// Total reserves shown by `proofs` of distinct owners
+///
+/// The proofs must all be proofs of reserves under one anchor, nullifier
+/// root, asset and context, each for a different owner, and must all
+/// verify. Returns the sum of their thresholds.
+pub fn combined_reserves(proofs: &[DisclosureProof], vk: &VerificationKey) -> Result<u64> {
+    if proofs.is_empty() {
+        return Err(CircuitError::InvalidParameters(
+            "No proofs to combine".into(),
+        ));
+    }
+    let mut scope = None;
+    let mut owners = Vec::with_capacity(proofs.len());
+    let mut total = 0u64;
+    for proof in proofs {
+        let DisclosureStatement::ReservesAtLeast {
+            anchor,
+            nullifier_root,
+            asset_id,
+            threshold,
+            owner,
+        } = &proof.statement
+        else {
+            return Err(CircuitError::InvalidParameters(
+                "Not a proof of reserves".into(),
+            ));
+        };
+        let this = (*anchor, *nullifier_root, *asset_id, proof.context);
+        if *scope.get_or_insert(this) != this {
+            return Err(CircuitError::InvalidParameters(
+                "Proofs differ in roots, asset or context".into(),
+            ));
+        }
+        if owners.contains(owner) {
+            return Err(CircuitError::InvalidParameters(
+                "Two proofs for one owner may count a note twice".into(),
+            ));
+        }
+        owners.push(*owner);
+
+        if !proof.verify(vk)? {
+            return Err(CircuitError::VerificationFailed(
+                "Invalid proof of reserves".into(),
+            ));
+        }
+        total = total
+            .checked_add(*threshold)
+            .ok_or_else(|| CircuitError::InvalidParameters("Combined reserves overflow".into()))?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use privl1_crypto::nullifier_tree::NullifierTree;
+    use rand::rngs::OsRng;
+
+    /// Chain state: notes of `values` owned by one key after one unrelated
+    /// note, with the first `spent` of them spent
+    fn custody(values: &[u64], spent: usize) -> (FullKeys, Vec<ReserveNote>, NullifierTree) {
+        let (mut keys, mut reserves, nullifiers) = custodies(&[values], spent);
+        (keys.remove(0), reserves.remove(0), nullifiers)
+    }
+
+    /// Chain state: for each of `holdings`, notes of its values owned by a
+    /// key of its own, with the first `spent` of each spent
+    fn custodies(
+        holdings: &[&[u64]],
+        spent: usize,
+    ) -> (Vec<FullKeys>, Vec<Vec<ReserveNote>>, NullifierTree) {
+        let keys: Vec<_> = holdings
+            .iter()
+            .map(|_| FullKeys::random(&mut OsRng))
+            .collect();
+        let notes: Vec<Vec<_>> = holdings
+            .iter()
+            .zip(&keys)
+            .map(|(values, keys)| {
+                values
+                    .iter()
+                    .map(|value| {
+                        Note::new_with_owner(*value, keys.public, *AssetId::NATIVE.as_bytes())
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append([0u8; 32]).unwrap();
+        let positions: Vec<Vec<_>> = notes
+            .iter()
+            .map(|notes| {
+                notes
+                    .iter()
+                    .map(|note| {
+                        tree.append(note.commitment().leaf()).unwrap();
+                        tree.mark().unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut nullifiers = NullifierTree::new();
+        nullifiers.insert(Nullifier::from_bytes([3u8; 32])).unwrap();
+        for ((notes, positions), keys) in notes.iter().zip(&positions).zip(&keys) {
+            for (note, position) in notes.iter().zip(positions).take(spent) {
+                nullifiers
+                    .insert(keys.nullifier.derive_nullifier(note, *position))
+                    .unwrap();
+            }
+        }
+
+        let reserves = notes
+            .into_iter()
+            .zip(positions)
+            .zip(&keys)
+            .map(|((notes, positions), keys)| {
+                notes
+                    .into_iter()
+                    .zip(positions)
+                    .skip(spent)
+                    .map(|(note, position)| ReserveNote {
+                        unspent: nullifiers
+                            .prove_unspent(&keys.nullifier.derive_nullifier(&note, position))
+                            .unwrap(),
+                        merkle_proof: tree.prove(position).unwrap(),
+                        note,
+                    })
+                    .collect()
+            })
+            .collect();
+        (keys, reserves, nullifiers)
+    }
+
+    #[test]
+    fn test_reserves_circuit() {
+        let (keys, notes, nullifiers) = custody(&[300, 500, 200], 1);
+        let witness =
+            ReservesWitness::new(&notes, &keys.full_viewing_key(), 700, [7u8; 32], &mut OsRng)
+                .unwrap();
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+
+        // The statement names the roots and the owner, not the notes
+        match witness.statement() {
+            DisclosureStatement::ReservesAtLeast {
+                nullifier_root,
+                owner,
+                ..
+            } => {
+                assert_eq!(nullifier_root, nullifiers.root());
+                assert_eq!(owner, keys.public);
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_reserves_rejected() {
+        let (keys, mut notes, mut nullifiers) = custody(&[300, 500], 0);
+        let prove = |notes: &[ReserveNote], keys: &FullKeys, threshold| {
+            ReservesWitness::new(
+                notes,
+                &keys.full_viewing_key(),
+                threshold,
+                [7u8; 32],
+                &mut OsRng,
+            )
+        };
+        assert!(prove(&notes, &keys, 801).is_err());
+        assert!(prove(&[], &keys, 0).is_err());
+        assert!(prove(&[notes[0].clone(), notes[0].clone()], &keys, 0).is_err());
+        assert!(prove(&notes, &FullKeys::random(&mut OsRng), 0).is_err());
+
+        // Once a note is spent, proofs under the new root cannot include it
+        let spent = keys
+            .nullifier
+            .derive_nullifier(&notes[0].note, notes[0].merkle_proof.position);
+        nullifiers.insert(spent).unwrap();
+        assert!(nullifiers.prove_unspent(&spent).is_err());
+        let nf = keys
+            .nullifier
+            .derive_nullifier(&notes[1].note, notes[1].merkle_proof.position);
+        notes[1].unspent = nullifiers.prove_unspent(&nf).unwrap();
+        assert!(prove(&notes, &keys, 0).is_err());
+        notes[0].unspent = nullifiers
+            .prove_unspent(&Nullifier::from_bytes([9u8; 32]))
+            .unwrap();
+        assert!(prove(&notes, &keys, 0).is_err());
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let (keys, notes, _) = custody(&[300, 500], 0);
+        let witness =
+            ReservesWitness::new(&notes, &keys.full_viewing_key(), 800, [7u8; 32], &mut OsRng)
+                .unwrap();
+
+        for row in [ANCHOR, NULLIFIER_ROOT, ASSET, THRESHOLD, OWNER_X, OWNER_Y] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_spent_note_rejected() {
+        let (keys, mut notes, mut nullifiers) = custody(&[300, 500, 200], 0);
+        let nf = |reserve: &ReserveNote| {
+            keys.nullifier
+                .derive_nullifier(&reserve.note, reserve.merkle_proof.position)
+        };
+        let spent = nf(&notes[0]);
+        nullifiers.insert(spent).unwrap();
+        for reserve in &mut notes[1..] {
+            reserve.unspent = nullifiers.prove_unspent(&nf(reserve)).unwrap();
+        }
+        let mut witness = ReservesWitness::new(
+            &notes[1..],
+            &keys.full_viewing_key(),
+            700,
+            [7u8; 32],
+            &mut OsRng,
+        )
+        .unwrap();
+
+        // Count the spent note through the gap just above its key, which
+        // starts at the key itself
+        let above = nullifier_key(&spent) + pallas::Base::ONE;
+        notes[0].unspent = nullifiers
+            .prove_unspent(&Nullifier::from_bytes(above.to_repr()))
+            .unwrap();
+        let padding = witness
+            .slots
+            .iter()
+            .position(|slot| slot.value == 0)
+            .unwrap();
+        witness.slots[padding] = Slot::new(&notes[0], &keys.nullifier).unwrap();
+        witness.slots.sort_by_key(Slot::sort_key);
+        witness.threshold = 1_000;
+        assert!(
+            !mock_failures(&witness.circuit(), vec![witness.public_inputs()], K)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_repeated_note_rejected() {
+        let (keys, notes, _) = custody(&[300, 500], 0);
+        let mut witness =
+            ReservesWitness::new(&notes, &keys.full_viewing_key(), 800, [7u8; 32], &mut OsRng)
+                .unwrap();
+
+        // Count a note twice in place of a padding slot
+        let real = witness
+            .slots
+            .iter()
+            .position(|slot| slot.value > 0)
+            .unwrap();
+        let padding = witness
+            .slots
+            .iter()
+            .position(|slot| slot.value == 0)
+            .unwrap();
+        witness.threshold += witness.slots[real].value;
+        witness.slots[padding] = witness.slots[real].clone();
+        witness.slots.sort_by_key(Slot::sort_key);
+        assert!(
+            !mock_failures(&witness.circuit(), vec![witness.public_inputs()], K)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (keys, notes, _) = custody(&[300, 500], 0);
+        let witness =
+            ReservesWitness::new(&notes, &keys.full_viewing_key(), 750, [7u8; 32], &mut OsRng)
+                .unwrap();
+        let key = keygen().unwrap();
+
+        let proof = prove(&key, &witness, OsRng).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Claiming more reserves fails
+        let mut inflated = proof.clone();
+        if let DisclosureStatement::ReservesAtLeast { threshold, .. } = &mut inflated.statement {
+            *threshold = 900;
+        }
+        inflated.proof.public_inputs[THRESHOLD] = halo2::encode_instance(&pallas::Base::from(900));
+        assert!(!inflated.verify(key.verification_key()).unwrap());
+    }
+
+    #[test]
+    fn test_combined_reserves() {
+        let (keys, holdings, _) = custodies(&[&[300, 500], &[400]], 0);
+        let key = keygen().unwrap();
+        let vk = key.verification_key();
+        let prove_for = |i: usize, threshold, context| {
+            let fvk = keys[i].full_viewing_key();
+            let witness =
+                ReservesWitness::new(&holdings[i], &fvk, threshold, context, &mut OsRng).unwrap();
+            prove(&key, &witness, OsRng).unwrap()
+        };
+        let first = prove_for(0, 800, [7u8; 32]);
+        let second = prove_for(1, 400, [7u8; 32]);
+
+        assert_eq!(
+            combined_reserves(&[first.clone(), second.clone()], vk).unwrap(),
+            1_200
+        );
+        assert_eq!(
+            combined_reserves(std::slice::from_ref(&second), vk).unwrap(),
+            400
+        );
+        assert!(combined_reserves(&[], vk).is_err());
+
+        // Two proofs for one owner may count the same notes
+        let again = prove_for(0, 300, [7u8; 32]);
+        assert!(combined_reserves(&[first.clone(), again], vk).is_err());
+
+        // Proofs must share their context, and each must verify
+        let elsewhere = prove_for(1, 400, [8u8; 32]);
+        assert!(combined_reserves(&[first.clone(), elsewhere], vk).is_err());
+        let mut inflated = second;
+        if let DisclosureStatement::ReservesAtLeast { threshold, .. } = &mut inflated.statement {
+            *threshold = 500;
+        }
+        inflated.proof.public_inputs[THRESHOLD] = halo2::encode_instance(&pallas::Base::from(500));
+        assert!(matches!(
+            combined_reserves(&[first, inflated], vk),
+            Err(CircuitError::VerificationFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,554 @@
+//! Spend circuit
+//!
+//! Proves, for public `(anchor, cv, nf, rk, asset)`, knowledge of a note and
+//! keys such that:
+//! - `cm = Poseidon(value, asset, owner.x, owner.y, rcm)` opens the note
+//! - `cm` is a leaf of the note commitment tree with root `anchor`
+//! - `owner = ak + [nk]N`, so the prover holds the owner's nullifier key
+//! - `nf = Poseidon(nk, cm, position)`
+//! - `cv = [value]V + [rcv]R`, with `value < 2^64`
+//! - `rk = ak + [alpha]G`, the key the spend authorization is checked against
+//! - `asset` is the note's `asset_base(asset_id)`
+//!
+//! `V` is the same for every asset, so `cv` alone does not say what is spent;
+//! the public asset is what lets values be balanced per asset.
+//!
+//! The relations mirror the native definitions in `privl1-crypto`
+//! (`Note::commitment`, `NullifierDerivingKey::derive_nullifier`,
+//! `PublicKey::from_spending_key`, `keys::randomize_validating_key`), and the
+//! public inputs are laid out as in `SpendProof::public_inputs`.
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::commitment::{Commitment, PedersenCommitment};
+use privl1_crypto::halo2;
+use privl1_crypto::keys::{self, FullKeys};
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::{asset_base, Note};
+use privl1_crypto::nullifier::Nullifier;
+use privl1_crypto::proof::{Halo2Proof, SpendProof};
+use privl1_crypto::{CryptoError, Point as NativePoint, PoseidonHash, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::gadgets::{assign_free_advice, MerklePathConfig};
+use crate::note::NoteConfig;
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 12;
+
+/// Name the spend verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "spend-v1";
+
+/// Version of the spend circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Public input rows
+pub const ANCHOR: usize = 0;
+pub const CV_X: usize = 1;
+pub const CV_Y: usize = 2;
+pub const NULLIFIER: usize = 3;
+pub const RK_X: usize = 4;
+pub const RK_Y: usize = 5;
+pub const ASSET: usize = 6;
+
+/// Configuration of the spend circuit
+#[derive(Clone, Debug)]
+pub struct SpendConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+}
+
+/// The spend circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct SpendCircuit {
+    value: Value<pallas::Base>,
+    asset: Value<pallas::Base>,
+    rcm: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+    rcv: Value<pallas::Scalar>,
+    alpha: Value<pallas::Scalar>,
+}
+
+impl Circuit<pallas::Base> for SpendCircuit {
+    type Config = SpendConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> SpendConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+
+        SpendConfig { note, merkle }
+    }
+
+    fn synthesize(
+        &self,
+        config: SpendConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        note.load(&mut layouter)?;
+
+        // Note opening
+        let value =
+            assign_free_advice(layouter.namespace(|| "value"), note.advices[0], self.value)?;
+        let asset =
+            assign_free_advice(layouter.namespace(|| "asset"), note.advices[1], self.asset)?;
+        layouter.constrain_instance(asset.cell(), note.primary, ASSET)?;
+        let rcm = assign_free_advice(layouter.namespace(|| "rcm"), note.advices[2], self.rcm)?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let cm = note.note_commitment(
+            layouter.namespace(|| "cm"),
+            value.clone(),
+            asset,
+            &owner,
+            rcm,
+        )?;
+
+        // Merkle inclusion of cm under the anchor
+        let (anchor, position) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            cm.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+
+        let nf = note.nullifier(layouter.namespace(|| "nullifier"), nk, cm, position)?;
+        layouter.constrain_instance(nf.cell(), note.primary, NULLIFIER)?;
+
+        let cv =
+            note.value_commitment(layouter.namespace(|| "value commitment"), value, self.rcv)?;
+        note.expose_point(&mut layouter, &cv, CV_X)?;
+
+        let rk = note.randomize_key(layouter.namespace(|| "rk"), &ak, self.alpha)?;
+        note.expose_point(&mut layouter, &rk, RK_X)?;
+
+        Ok(())
+    }
+}
+
+/// Private inputs of a spend, built from wallet-side data
+#[derive(Clone, Debug)]
+pub struct SpendWitness {
+    pub(crate) value: u64,
+    pub(crate) asset_id: [u8; 32],
+    pub(crate) asset: pallas::Base,
+    pub(crate) rcm: pallas::Base,
+    pub(crate) cm: pallas::Base,
+    pub(crate) ak: NativePoint,
+    pub(crate) nk: pallas::Base,
+    pub(crate) path: [pallas::Base; TREE_DEPTH],
+    pub(crate) position: u64,
+    pub(crate) rcv: Scalar,
+    pub(crate) alpha: Scalar,
+    pub(crate) nullifier: Nullifier,
+}
+
+impl SpendWitness {
+    /// Build the witness for spending `note` at `merkle_proof.position`
+    ///
+    /// `rcv` is the value-commitment randomness (needed again for the binding
+    /// signature) and `alpha` re-randomizes the spend key.
+    pub fn new(
+        note: &Note,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        rcv: Scalar,
+        alpha: Scalar,
+    ) -> Result<Self> {
+        if note.owner() != &keys.public {
+            return Err(CircuitError::InvalidParameters(
+                "Note is not owned by these keys".into(),
+            ));
+        }
+        if merkle_proof.position >> TREE_DEPTH != 0 {
+            return Err(CircuitError::InvalidParameters(
+                "Position outside the tree".into(),
+            ));
+        }
+
+        let path = authentication_path(merkle_proof)?;
+
+        Ok(Self {
+            value: note.value(),
+            asset_id: *note.asset_id(),
+            asset: asset_base(note.asset_id()),
+            rcm: note.rcm(),
+            cm: note.commitment().to_field(),
+            ak: keys.spending.validating_key(),
+            nk: keys.nullifier.to_base(),
+            path,
+            position: merkle_proof.position,
+            rcv,
+            alpha,
+            nullifier: keys.nullifier.derive_nullifier(note, merkle_proof.position),
+        })
+    }
+
+    /// Build a witness with fresh `rcv` and `alpha`
+    pub fn random<R: RngCore>(
+        note: &Note,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::new(
+            note,
+            merkle_proof,
+            keys,
+            Scalar::random(rng),
+            Scalar::random(rng),
+        )
+    }
+
+    /// Root the authentication path hashes to
+    pub fn anchor(&self) -> MerkleRoot {
+        merkle_root(self.cm, &self.path, self.position)
+    }
+
+    /// Nullifier revealed by the spend
+    pub fn nullifier(&self) -> Nullifier {
+        self.nullifier
+    }
+
+    /// Value commitment revealed by the spend
+    pub fn value_commitment(&self) -> Commitment {
+        PedersenCommitment::new().commit_with_blinding(self.value, self.rcv)
+    }
+
+    /// Re-randomized spend validating key revealed by the spend
+    pub fn rk(&self) -> NativePoint {
+        keys::randomize_validating_key(&self.ak, &self.alpha)
+    }
+
+    /// Value-commitment randomness
+    pub fn rcv(&self) -> &Scalar {
+        &self.rcv
+    }
+
+    /// Randomizer of the spend key, needed to sign the spend authorization
+    pub fn alpha(&self) -> &Scalar {
+        &self.alpha
+    }
+
+    /// Public inputs, in `SpendProof::public_inputs` order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let anchor = Option::from(pallas::Base::from_repr(*self.anchor().as_bytes()))
+            .expect("Poseidon output is canonical");
+        let nf = Option::from(pallas::Base::from_repr(*self.nullifier.as_bytes()))
+            .expect("Poseidon output is canonical");
+        let (cv_x, cv_y) = self.value_commitment().as_point().coordinates();
+        let (rk_x, rk_y) = self.rk().coordinates();
+
+        vec![anchor, cv_x, cv_y, nf, rk_x, rk_y, self.asset]
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> SpendCircuit {
+        SpendCircuit {
+            value: Value::known(pallas::Base::from(self.value)),
+            asset: Value::known(self.asset),
+            rcm: Value::known(self.rcm),
+            ak: Value::known(self.ak.inner().to_affine()),
+            nk: Value::known(self.nk),
+            path: Value::known(self.path),
+            position: Value::known(self.position),
+            rcv: Value::known(*self.rcv.inner()),
+            alpha: Value::known(*self.alpha.inner()),
+        }
+    }
+}
+
+/// Parse a Merkle proof's path into field elements, leaf level first
+pub(crate) fn authentication_path(
+    merkle_proof: &MerkleProof,
+) -> Result<[pallas::Base; TREE_DEPTH]> {
+    let path: Vec<pallas::Base> = merkle_proof
+        .path
+        .iter()
+        .map(|node| {
+            Option::from(pallas::Base::from_repr(*node))
+                .ok_or_else(|| CryptoError::MerkleError("Non-canonical node".into()))
+        })
+        .collect::<std::result::Result<_, _>>()?;
+    let path = path
+        .try_into()
+        .map_err(|_| CryptoError::MerkleError("Authentication path has the wrong depth".into()))?;
+
+    Ok(path)
+}
+
+/// Root `leaf` hashes to along `path` at `position`, as `MerkleProof::verify` computes it
+pub(crate) fn merkle_root(
+    leaf: pallas::Base,
+    path: &[pallas::Base; TREE_DEPTH],
+    position: u64,
+) -> MerkleRoot {
+    let root = path
+        .iter()
+        .enumerate()
+        .fold(leaf, |node, (level, sibling)| {
+            if (position >> level) & 1 == 0 {
+                PoseidonHash::hash_two(node, *sibling).to_field()
+            } else {
+                PoseidonHash::hash_two(*sibling, node).to_field()
+            }
+        });
+    MerkleRoot::from_bytes(root.to_repr())
+}
+
+crate::keystore::circuit_keys!("spend", SpendCircuit, SpendProvingKey);
+
+/// Prove a spend
+///
+/// The spend still needs its authorization signature, added with
+/// `TransactionProof::authorize_spend` once the transaction is complete.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &SpendProvingKey,
+    witness: &SpendWitness,
+    rng: R,
+) -> Result<SpendProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    Ok(SpendProof {
+        proof: Halo2Proof::new(
+            proof,
+            instances.iter().map(halo2::encode_instance).collect(),
+            key.verification_key().id(),
+        ),
+        nullifier: witness.nullifier(),
+        anchor: witness.anchor(),
+        value_commitment: witness.value_commitment(),
+        rk: witness.rk(),
+        asset_id: witness.asset_id,
+        circuit_version: CIRCUIT_VERSION,
+        spend_auth_sig: None,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use halo2_proofs::dev::MockProver;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use privl1_crypto::proof::{BindingSignature, ProofVerifier, TransactionProof};
+    use rand::rngs::OsRng;
+
+    /// A note owned by `keys` in a tree next to other notes, with its witness
+    pub(crate) fn spend_witness(keys: &FullKeys, value: u64) -> SpendWitness {
+        let note = Note::new_with_owner(value, keys.public, *AssetId::NATIVE.as_bytes());
+        note_witness(keys, &note)
+    }
+
+    /// `note` in a tree next to other notes, with its witness
+    fn note_witness(keys: &FullKeys, note: &Note) -> SpendWitness {
+        let mut rng = OsRng;
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.commitment().leaf()).unwrap();
+        let merkle_proof = tree.prove(0).unwrap();
+        assert!(merkle_proof.verify(&note.commitment().leaf(), &tree.root()));
+
+        let witness = SpendWitness::random(note, &merkle_proof, keys, &mut rng).unwrap();
+        assert_eq!(witness.anchor(), tree.root());
+        witness
+    }
+
+    #[test]
+    fn test_spend_circuit() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = spend_witness(&keys, 1_000);
+
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = spend_witness(&keys, 42);
+
+        for row in [ANCHOR, CV_X, CV_Y, NULLIFIER, RK_X, RK_Y, ASSET] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_asset_is_bound() {
+        // A note of some other asset cannot be spent as the native asset,
+        // whose value commitment would otherwise look the same
+        let keys = FullKeys::random(&mut OsRng);
+        let worthless = Note::new_with_owner(100, keys.public, [9u8; 32]);
+        let witness = note_witness(&keys, &worthless);
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+
+        let native = *AssetId::NATIVE.as_bytes();
+        let mut instances = witness.public_inputs();
+        instances[ASSET] = asset_base(&native);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        let spend = SpendProof {
+            proof: Halo2Proof::new(
+                vec![],
+                witness
+                    .public_inputs()
+                    .iter()
+                    .map(halo2::encode_instance)
+                    .collect(),
+                [0u8; 32],
+            ),
+            nullifier: witness.nullifier(),
+            anchor: witness.anchor(),
+            value_commitment: witness.value_commitment(),
+            rk: witness.rk(),
+            asset_id: *worthless.asset_id(),
+            circuit_version: CIRCUIT_VERSION,
+            spend_auth_sig: None,
+        };
+        assert!(spend.binds_public_inputs());
+        let relabelled = SpendProof {
+            asset_id: native,
+            ..spend
+        };
+        assert!(!relabelled.binds_public_inputs());
+    }
+
+    #[test]
+    fn test_wrong_witness_rejected() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = spend_witness(&keys, 42);
+        let instances = witness.public_inputs();
+
+        // Another nullifier key changes the owner, so the note no longer opens
+        let mut circuit = witness.circuit();
+        circuit.nk = Value::known(witness.nk + pallas::Base::ONE);
+        assert!(MockProver::run(K, &circuit, vec![instances.clone()])
+            .unwrap()
+            .verify()
+            .is_err());
+
+        // A different leaf position
+        let mut circuit = witness.circuit();
+        circuit.position = Value::known(1);
+        assert!(MockProver::run(K, &circuit, vec![instances])
+            .unwrap()
+            .verify()
+            .is_err());
+    }
+
+    #[test]
+    fn test_witness_requires_owner_keys() {
+        let owner = FullKeys::random(&mut OsRng);
+        let other = FullKeys::random(&mut OsRng);
+        let note = Note::new_with_owner(5, owner.public, [0u8; 32]);
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.commitment().leaf()).unwrap();
+        let merkle_proof = tree.prove(0).unwrap();
+
+        assert!(SpendWitness::random(&note, &merkle_proof, &other, &mut OsRng).is_err());
+
+        let mut short = merkle_proof.clone();
+        short.path.pop();
+        assert!(SpendWitness::random(&note, &short, &owner, &mut OsRng).is_err());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = spend_witness(&keys, 7);
+        let key = keygen().unwrap();
+
+        let spend = prove(&key, &witness, OsRng).unwrap();
+        assert!(spend.binds_public_inputs());
+        assert_eq!(spend.nullifier, witness.nullifier());
+
+        let mut verifier = ProofVerifier::new();
+        verifier.register_vk(key.verification_key().clone());
+        let mut tx = TransactionProof {
+            spend_proofs: vec![spend.clone()],
+            output_proofs: vec![],
+            binding_sig: BindingSignature {
+                signature: vec![],
+                value_balance: 0,
+            },
+        };
+        tx.authorize_spend(0, &keys.spending, witness.alpha())
+            .unwrap();
+        assert!(verifier.verify_transaction(&tx).unwrap());
+        assert!(verifier.verify_block(&[tx.clone()]).is_valid());
+
+        // Swapping in another nullifier breaks the binding to the proof
+        let mut tampered = tx.clone();
+        tampered.spend_proofs[0].nullifier = Nullifier::from_bytes([1u8; 32]);
+        assert!(!verifier.verify_transaction(&tampered).unwrap());
+
+        // The proof alone does not authorize the spend: it is rejected with
+        // no signature, one under another key, or one over another transaction
+        let mut unsigned = tx.clone();
+        unsigned.spend_proofs[0].spend_auth_sig = None;
+        let mut wrong_key = tx.clone();
+        let other = FullKeys::random(&mut OsRng);
+        wrong_key.spend_proofs[0].spend_auth_sig = Some(
+            other
+                .spending
+                .sign_randomized(witness.alpha(), &tx.sighash()),
+        );
+        let mut wrong_message = tx.clone();
+        wrong_message.binding_sig.value_balance = 1;
+        for rejected in [&unsigned, &wrong_key, &wrong_message] {
+            assert!(verifier
+                .verify_halo2(&rejected.spend_proofs[0].proof)
+                .unwrap());
+            assert!(!verifier.verify_transaction(rejected).unwrap());
+        }
+        assert_eq!(
+            verifier
+                .verify_block(&[tx, unsigned, wrong_key, wrong_message])
+                .failed,
+            vec![1, 2, 3]
+        );
+
+        // Only the key `rk` randomizes can sign
+        assert!(tampered
+            .authorize_spend(0, &other.spending, witness.alpha())
+            .is_err());
+    }
+}
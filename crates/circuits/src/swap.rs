@@ -0,0 +1,767 @@
+//! PrivateSwap circuit
+//!
+//! Proves a trader swapped part of a shielded note against a
+//! `pallet-simple-amm` pool, without revealing the note or the amounts. For
+//! public `(anchor, nf, rk, cm_out, cm_change, asset_in, asset_out,
+//! reserve_in, reserve_out)`, the circuit checks:
+//! - the input note is spent as in the spend circuit (`anchor`, `nf`, `rk`)
+//! - its value covers `amount_in`, and `cm_change` returns the rest to its
+//!   owner in `asset_in`
+//! - `amount_out = get_amount_out(amount_in, reserve_in, reserve_out)`,
+//!   including the pallet's zero checks, u64 overflow errors and floor
+//!   division
+//! - `cm_out` pays `amount_out` of `asset_out` to the recipient
+//!
+//! Assets are public as `asset_base(asset_id)`, so the pool can check them.
+//! A `SwapProof` only verifies once signed under `rk`
+//! (see [`crate::authorization`]).
+
+use ff::PrimeField;
+use group::Curve;
+use halo2_gadgets::ecc::{NonIdentityPoint, Point};
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::halo2;
+use privl1_crypto::keys::{FullKeys, PublicKey};
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::{asset_base, Note};
+use privl1_crypto::nullifier::Nullifier;
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::authorization::{AuthorizedProof, SpendAuthorizing};
+use crate::gadgets::{assign_free_advice, AssignedBase, MerklePathConfig, U64Config};
+use crate::note::NoteConfig;
+use crate::spend::SpendWitness;
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 13;
+
+/// Name the swap verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "swap-v1";
+
+/// Version of the swap circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Fee-adjusted input per `FEE_DENOMINATOR` (a 0.25% fee), as in the pallet
+pub const FEE_NUMERATOR: u64 = 9975;
+pub const FEE_DENOMINATOR: u64 = 10000;
+
+/// Public input rows
+pub const ANCHOR: usize = 0;
+pub const NULLIFIER: usize = 1;
+pub const RK_X: usize = 2;
+pub const RK_Y: usize = 3;
+pub const CM_OUT: usize = 4;
+pub const CM_CHANGE: usize = 5;
+pub const ASSET_IN: usize = 6;
+pub const ASSET_OUT: usize = 7;
+pub const RESERVE_IN: usize = 8;
+pub const RESERVE_OUT: usize = 9;
+
+/// `pallet_simple_amm::Pallet::get_amount_out` with `Balance = u64`
+///
+/// Returns `None` where the pallet returns an error (zero amount or reserve,
+/// or overflow).
+pub fn get_amount_out(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Option<u64> {
+    if amount_in == 0 || reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    let amount_in_with_fee = amount_in.checked_mul(FEE_NUMERATOR)?;
+    let numerator = amount_in_with_fee.checked_mul(reserve_out)?;
+    let denominator = reserve_in
+        .checked_mul(FEE_DENOMINATOR)?
+        .checked_add(amount_in_with_fee)?;
+
+    numerator.checked_div(denominator)
+}
+
+/// Configuration of the swap circuit
+#[derive(Clone, Debug)]
+pub struct SwapConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+impl SwapConfig {
+    /// `get_amount_out` over range-checked cells
+    fn amount_out(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        amount_in: &AssignedBase,
+        reserve_in: &AssignedBase,
+        reserve_out: &AssignedBase,
+    ) -> std::result::Result<AssignedBase, Error> {
+        let arith = &self.arith;
+        arith.assert_nonzero(layouter.namespace(|| "amount_in"), amount_in)?;
+        arith.assert_nonzero(layouter.namespace(|| "reserve_in"), reserve_in)?;
+        arith.assert_nonzero(layouter.namespace(|| "reserve_out"), reserve_out)?;
+
+        let fee = arith.constant(layouter.namespace(|| "fee"), FEE_NUMERATOR)?;
+        let scale = arith.constant(layouter.namespace(|| "scale"), FEE_DENOMINATOR)?;
+
+        let amount_in_with_fee =
+            arith.checked_mul(layouter.namespace(|| "amount_in_with_fee"), amount_in, &fee)?;
+        let numerator = arith.checked_mul(
+            layouter.namespace(|| "numerator"),
+            &amount_in_with_fee,
+            reserve_out,
+        )?;
+        let scaled_reserve = arith.checked_mul(
+            layouter.namespace(|| "reserve_in * 10000"),
+            reserve_in,
+            &scale,
+        )?;
+        let denominator = arith.checked_add(
+            layouter.namespace(|| "denominator"),
+            &scaled_reserve,
+            &amount_in_with_fee,
+        )?;
+
+        let (amount_out, _) = arith.div_rem(
+            layouter.namespace(|| "amount_out"),
+            &numerator,
+            &denominator,
+        )?;
+        Ok(amount_out)
+    }
+}
+
+/// The swap circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct SwapCircuit {
+    value_in: Value<u64>,
+    rcm_in: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+    alpha: Value<pallas::Scalar>,
+    amount_in: Value<u64>,
+    rcm_change: Value<pallas::Base>,
+    recipient: Value<pallas::Affine>,
+    rcm_out: Value<pallas::Base>,
+}
+
+impl Circuit<pallas::Base> for SwapCircuit {
+    type Config = SwapConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> SwapConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        SwapConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: SwapConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        note.load(&mut layouter)?;
+
+        let asset_in = note.public_base(layouter.namespace(|| "asset_in"), ASSET_IN)?;
+        let asset_out = note.public_base(layouter.namespace(|| "asset_out"), ASSET_OUT)?;
+        let reserve_in = note.public_amount(layouter.namespace(|| "reserve_in"), RESERVE_IN)?;
+        let reserve_out = note.public_amount(layouter.namespace(|| "reserve_out"), RESERVE_OUT)?;
+
+        // Spend the input note
+        let value_in = config
+            .arith
+            .witness(layouter.namespace(|| "value_in"), self.value_in)?;
+        let rcm_in = assign_free_advice(
+            layouter.namespace(|| "rcm_in"),
+            note.advices[2],
+            self.rcm_in,
+        )?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let cm_in = note.note_commitment(
+            layouter.namespace(|| "cm_in"),
+            value_in.clone(),
+            asset_in.clone(),
+            &owner,
+            rcm_in,
+        )?;
+        let (anchor, position) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            cm_in.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+
+        let nf = note.nullifier(layouter.namespace(|| "nullifier"), nk, cm_in, position)?;
+        layouter.constrain_instance(nf.cell(), note.primary, NULLIFIER)?;
+
+        let rk = note.randomize_key(layouter.namespace(|| "rk"), &ak, self.alpha)?;
+        note.expose_point(&mut layouter, &rk, RK_X)?;
+
+        // Trade against the pool
+        let amount_in = config
+            .arith
+            .witness(layouter.namespace(|| "amount_in"), self.amount_in)?;
+        let change =
+            config
+                .arith
+                .checked_sub(layouter.namespace(|| "change"), &value_in, &amount_in)?;
+        let amount_out = config.amount_out(
+            layouter.namespace(|| "amm"),
+            &amount_in,
+            &reserve_in,
+            &reserve_out,
+        )?;
+
+        // Change back to the owner, proceeds to the recipient
+        let rcm_change = assign_free_advice(
+            layouter.namespace(|| "rcm_change"),
+            note.advices[2],
+            self.rcm_change,
+        )?;
+        let cm_change = note.note_commitment(
+            layouter.namespace(|| "cm_change"),
+            change,
+            asset_in,
+            &owner,
+            rcm_change,
+        )?;
+        layouter.constrain_instance(cm_change.cell(), note.primary, CM_CHANGE)?;
+
+        let recipient = Point::new(
+            note.ecc_chip(),
+            layouter.namespace(|| "recipient"),
+            self.recipient,
+        )?;
+        let rcm_out = assign_free_advice(
+            layouter.namespace(|| "rcm_out"),
+            note.advices[2],
+            self.rcm_out,
+        )?;
+        let cm_out = note.note_commitment(
+            layouter.namespace(|| "cm_out"),
+            amount_out,
+            asset_out,
+            &recipient,
+            rcm_out,
+        )?;
+        layouter.constrain_instance(cm_out.cell(), note.primary, CM_OUT)?;
+
+        Ok(())
+    }
+}
+
+/// A trade against a pool, as the trader sees it
+#[derive(Clone, Debug)]
+pub struct SwapOrder {
+    /// Amount taken from the input note
+    pub amount_in: u64,
+    /// Pool reserve of the input asset
+    pub reserve_in: u64,
+    /// Pool reserve of the output asset
+    pub reserve_out: u64,
+    /// Asset received
+    pub asset_out: [u8; 32],
+    /// Owner of the output note
+    pub recipient: PublicKey,
+}
+
+/// Private inputs of a swap
+#[derive(Clone, Debug)]
+pub struct SwapWitness {
+    input: SpendWitness,
+    input_asset_id: [u8; 32],
+    order: SwapOrder,
+    change: Note,
+    output: Note,
+}
+
+impl SwapWitness {
+    /// Build the witness for trading `order.amount_in` of `note`
+    ///
+    /// Fails if the note does not cover the amount or the pool would reject
+    /// the trade.
+    pub fn new<R: RngCore>(
+        note: &Note,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        order: SwapOrder,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let change = note.value().checked_sub(order.amount_in).ok_or_else(|| {
+            CircuitError::InvalidParameters("Note does not cover the swap".into())
+        })?;
+        let amount_out = get_amount_out(order.amount_in, order.reserve_in, order.reserve_out)
+            .ok_or_else(|| CircuitError::InvalidParameters("Pool rejects the swap".into()))?;
+
+        // The input side is a spend whose value commitment goes unused
+        let input = SpendWitness::new(
+            note,
+            merkle_proof,
+            keys,
+            Scalar::zero(),
+            Scalar::random(rng),
+        )?;
+        let change =
+            Note::with_randomness(change, keys.public, *note.asset_id(), Scalar::random(rng));
+        let output = Note::with_randomness(
+            amount_out,
+            order.recipient,
+            order.asset_out,
+            Scalar::random(rng),
+        );
+
+        Ok(Self {
+            input,
+            input_asset_id: *note.asset_id(),
+            order,
+            change,
+            output,
+        })
+    }
+
+    /// Note returning the unswapped value to the trader
+    pub fn change(&self) -> &Note {
+        &self.change
+    }
+
+    /// Note paying the swap proceeds to the recipient
+    pub fn output(&self) -> &Note {
+        &self.output
+    }
+
+    /// Root the input note's authentication path hashes to
+    pub fn anchor(&self) -> MerkleRoot {
+        self.input.anchor()
+    }
+
+    /// Nullifier of the input note
+    pub fn nullifier(&self) -> Nullifier {
+        self.input.nullifier()
+    }
+
+    /// Re-randomized key the swap authorization is checked against
+    pub fn rk(&self) -> NativePoint {
+        self.input.rk()
+    }
+
+    /// Randomizer of the spend key, needed to sign the swap authorization
+    pub fn alpha(&self) -> &Scalar {
+        self.input.alpha()
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let to_base = |bytes: &[u8; 32]| {
+            Option::from(pallas::Base::from_repr(*bytes)).expect("Poseidon output is canonical")
+        };
+        let (rk_x, rk_y) = self.rk().coordinates();
+
+        vec![
+            to_base(self.anchor().as_bytes()),
+            to_base(self.nullifier().as_bytes()),
+            rk_x,
+            rk_y,
+            self.output.commitment().to_field(),
+            self.change.commitment().to_field(),
+            asset_base(&self.input_asset_id),
+            asset_base(&self.order.asset_out),
+            pallas::Base::from(self.order.reserve_in),
+            pallas::Base::from(self.order.reserve_out),
+        ]
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> SwapCircuit {
+        let input = &self.input;
+        SwapCircuit {
+            value_in: Value::known(input.value),
+            rcm_in: Value::known(input.rcm),
+            ak: Value::known(input.ak.inner().to_affine()),
+            nk: Value::known(input.nk),
+            path: Value::known(input.path),
+            position: Value::known(input.position),
+            alpha: Value::known(*input.alpha.inner()),
+            amount_in: Value::known(self.order.amount_in),
+            rcm_change: Value::known(self.change.rcm()),
+            recipient: Value::known(self.output.owner().as_point().inner().to_affine()),
+            rcm_out: Value::known(self.output.rcm()),
+        }
+    }
+}
+
+impl SpendAuthorizing for SwapCircuit {
+    const SIGHASH_DOMAIN: &'static str = "PRIVL1_SWAP_SIGHASH";
+    const RK_X: usize = RK_X;
+    const RK_Y: usize = RK_Y;
+}
+
+/// Swap proof with the trader's spend authorization
+pub type SwapProof = AuthorizedProof<SwapCircuit>;
+
+crate::keystore::circuit_keys!("swap", SwapCircuit, SwapProvingKey);
+
+/// Prove a swap
+///
+/// The proof is unsigned; `SwapProof::authorize` it with the input note's
+/// spending key and `witness.alpha()`.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &SwapProvingKey,
+    witness: &SwapWitness,
+    rng: R,
+) -> Result<SwapProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    let proof = Halo2Proof::new(
+        proof,
+        instances.iter().map(halo2::encode_instance).collect(),
+        key.verification_key().id(),
+    );
+    Ok(SwapProof::new(proof, witness.rk()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use ff::Field;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use privl1_crypto::PoseidonHash;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    // construct_runtime! checks a `std` feature that only runtime crates have
+    #[allow(unexpected_cfgs)]
+    mod runtime {
+        use frame_support::derive_impl;
+
+        type Block = frame_system::mocking::MockBlock<Test>;
+
+        frame_support::construct_runtime!(
+            pub enum Test {
+                System: frame_system,
+                SimpleAmm: pallet_simple_amm,
+            }
+        );
+
+        #[derive_impl(frame_system::config_preludes::TestDefaultConfig)]
+        impl frame_system::Config for Test {
+            type Block = Block;
+        }
+
+        impl pallet_simple_amm::Config for Test {
+            type AssetId = u32;
+            type Balance = u64;
+        }
+
+        pub fn get_amount_out(amount_in: u64, reserve_in: u64, reserve_out: u64) -> Option<u64> {
+            pallet_simple_amm::Pallet::<Test>::get_amount_out(amount_in, reserve_in, reserve_out)
+                .ok()
+        }
+    }
+
+    const OUT_ASSET: [u8; 32] = [7u8; 32];
+
+    fn swap_witness(
+        value: u64,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<SwapWitness> {
+        let keys = FullKeys::random(&mut OsRng);
+        swap_witness_for(&keys, value, amount_in, reserve_in, reserve_out)
+    }
+
+    fn swap_witness_for(
+        keys: &FullKeys,
+        value: u64,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+    ) -> Result<SwapWitness> {
+        let note = Note::new_with_owner(value, keys.public, *AssetId::NATIVE.as_bytes());
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.commitment().leaf()).unwrap();
+        let merkle_proof = tree.prove(0).unwrap();
+
+        let order = SwapOrder {
+            amount_in,
+            reserve_in,
+            reserve_out,
+            asset_out: OUT_ASSET,
+            recipient: keys.public,
+        };
+        SwapWitness::new(&note, &merkle_proof, keys, order, &mut OsRng)
+    }
+
+    #[test]
+    fn test_amount_out_matches_pallet() {
+        let mut rng = OsRng;
+        for _ in 0..10_000 {
+            // Mix magnitudes so both successful swaps and overflows are common
+            let amount_in = rng.gen::<u64>() >> rng.gen_range(0..64);
+            let reserve_in = rng.gen::<u64>() >> rng.gen_range(0..64);
+            let reserve_out = rng.gen::<u64>() >> rng.gen_range(0..64);
+
+            assert_eq!(
+                get_amount_out(amount_in, reserve_in, reserve_out),
+                runtime::get_amount_out(amount_in, reserve_in, reserve_out),
+                "get_amount_out({}, {}, {})",
+                amount_in,
+                reserve_in,
+                reserve_out
+            );
+        }
+    }
+
+    #[test]
+    fn test_swap_circuit_matches_pallet() {
+        let mut rng = OsRng;
+        for _ in 0..3 {
+            let reserve_in = rng.gen_range(1..1u64 << 24);
+            let reserve_out = rng.gen_range(1..1u64 << 24);
+            let amount_in = rng.gen_range(1..1u64 << 24);
+            let amount_out = runtime::get_amount_out(amount_in, reserve_in, reserve_out).unwrap();
+
+            let witness = swap_witness(amount_in + 5, amount_in, reserve_in, reserve_out).unwrap();
+            assert_eq!(witness.output().value(), amount_out);
+            assert_eq!(witness.change().value(), 5);
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_swap_circuit_edge_cases() {
+        // Largest reserves the pallet accepts for a unit trade: the numerator
+        // or the denominator is within one step of u64::MAX
+        let max_reserve_in = (u64::MAX - FEE_NUMERATOR) / FEE_DENOMINATOR;
+        let max_reserve_out = u64::MAX / FEE_NUMERATOR;
+
+        for (amount_in, reserve_in, reserve_out) in [
+            (1, 1, max_reserve_out),
+            (1, max_reserve_in, 1),
+            (1, max_reserve_in, max_reserve_out),
+            // Either side of the first amount_in that floors to a nonzero
+            // output
+            (1_003, 1_000_000, 1_000),
+            (1_004, 1_000_000, 1_000),
+            // A swap the pallet accepts with zero output
+            (1, 1_000, 1_000),
+        ] {
+            let amount_out = runtime::get_amount_out(amount_in, reserve_in, reserve_out).unwrap();
+            assert_eq!(
+                get_amount_out(amount_in, reserve_in, reserve_out),
+                Some(amount_out)
+            );
+
+            let witness = swap_witness(amount_in, amount_in, reserve_in, reserve_out).unwrap();
+            assert_eq!(witness.output().value(), amount_out);
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+        assert_eq!(runtime::get_amount_out(1_003, 1_000_000, 1_000), Some(0));
+        assert_eq!(runtime::get_amount_out(1_004, 1_000_000, 1_000), Some(1));
+        assert_eq!(runtime::get_amount_out(1, 1_000, 1_000), Some(0));
+
+        // One more in either reserve overflows, so there is no witness
+        for (reserve_in, reserve_out) in [(1, max_reserve_out + 1), (max_reserve_in + 1, 1)] {
+            assert_eq!(runtime::get_amount_out(1, reserve_in, reserve_out), None);
+            assert_eq!(get_amount_out(1, reserve_in, reserve_out), None);
+            assert!(swap_witness(1, 1, reserve_in, reserve_out).is_err());
+        }
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let witness = swap_witness(1_000, 600, 50_000, 80_000).unwrap();
+
+        // A unit change of a reserve can round to the same amount out, which is
+        // still a valid swap; doubling either reserve cannot
+        for (row, delta) in [
+            (ANCHOR, 1),
+            (NULLIFIER, 1),
+            (RK_X, 1),
+            (RK_Y, 1),
+            (CM_OUT, 1),
+            (CM_CHANGE, 1),
+            (ASSET_IN, 1),
+            (ASSET_OUT, 1),
+            (RESERVE_IN, 50_000),
+            (RESERVE_OUT, 80_000),
+        ] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::from(delta);
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_amount_out_off_by_one_rejected() {
+        let witness = swap_witness(1_000, 600, 50_000, 80_000).unwrap();
+        let amount_out = witness.output().value();
+
+        // Output notes for a neighbouring amount do not open
+        for value in [amount_out - 1, amount_out + 1] {
+            let output =
+                Note::with_randomness(value, *witness.output().owner(), OUT_ASSET, Scalar::zero());
+            let mut circuit = witness.circuit();
+            circuit.rcm_out = Value::known(output.rcm());
+            let mut instances = witness.public_inputs();
+            instances[CM_OUT] = output.commitment().to_field();
+            assert!(!mock_failures(&circuit, vec![instances], K)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_uncovered_or_rejected_swaps() {
+        // The note does not cover amount_in
+        assert!(swap_witness(100, 101, 1_000, 1_000).is_err());
+        let mut witness = swap_witness(100, 100, 1_000, 1_000).unwrap();
+        witness.order.amount_in = 101;
+        let amount_out = get_amount_out(101, 1_000, 1_000).unwrap();
+        witness.output = Note::with_randomness(
+            amount_out,
+            *witness.output.owner(),
+            OUT_ASSET,
+            Scalar::zero(),
+        );
+        let mut instances = witness.public_inputs();
+        let (owner_x, owner_y) = witness.change.owner().as_point().coordinates();
+        instances[CM_CHANGE] = PoseidonHash::hash([
+            -pallas::Base::ONE,
+            witness.input.asset,
+            owner_x,
+            owner_y,
+            witness.change.rcm(),
+        ])
+        .to_field();
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // The pallet overflows computing the numerator, so no output is valid
+        let (amount_in, reserve_in, reserve_out) = (1 << 40, 1 << 20, 1 << 20);
+        assert_eq!(
+            runtime::get_amount_out(amount_in, reserve_in, reserve_out),
+            None
+        );
+        assert!(swap_witness(amount_in, amount_in, reserve_in, reserve_out).is_err());
+
+        let mut witness = swap_witness(amount_in, 1 << 10, reserve_in, reserve_out).unwrap();
+        witness.order.amount_in = amount_in;
+        let amount_out = (amount_in as u128 * FEE_NUMERATOR as u128 * reserve_out as u128
+            / (reserve_in as u128 * FEE_DENOMINATOR as u128
+                + amount_in as u128 * FEE_NUMERATOR as u128)) as u64;
+        witness.output = Note::with_randomness(
+            amount_out,
+            *witness.output.owner(),
+            OUT_ASSET,
+            Scalar::zero(),
+        );
+        witness.change = Note::with_randomness(
+            0,
+            *witness.change.owner(),
+            *AssetId::NATIVE.as_bytes(),
+            Scalar::zero(),
+        );
+        assert!(
+            !mock_failures(&witness.circuit(), vec![witness.public_inputs()], K)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = swap_witness_for(&keys, 1_000, 600, 50_000, 80_000).unwrap();
+        let key = keygen().unwrap();
+
+        let mut proof = prove(&key, &witness, OsRng).unwrap();
+        proof.authorize(&keys.spending, witness.alpha()).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Claiming other reserves fails
+        let mut tampered = proof.clone();
+        tampered.proof.public_inputs[RESERVE_OUT] =
+            halo2::encode_instance(&pallas::Base::from(90_000));
+        assert!(!tampered.verify(key.verification_key()).unwrap());
+
+        // The proof alone does not authorize the swap: it is rejected with no
+        // signature, one under another key, or one over another proof
+        let mut unsigned = proof.clone();
+        unsigned.spend_auth_sig = None;
+        let other = FullKeys::random(&mut OsRng);
+        let mut wrong_key = proof.clone();
+        wrong_key.spend_auth_sig = Some(
+            other
+                .spending
+                .sign_randomized(witness.alpha(), &proof.sighash()),
+        );
+        let mut wrong_message = proof.clone();
+        wrong_message.spend_auth_sig = Some(
+            keys.spending
+                .sign_randomized(witness.alpha(), &tampered.sighash()),
+        );
+        for rejected in [&unsigned, &wrong_key, &wrong_message] {
+            assert!(rejected.proof.verify(key.verification_key()).unwrap());
+            assert!(!rejected.verify(key.verification_key()).unwrap());
+        }
+
+        // Only the key `rk` randomizes can sign, and `rk` must be the one the
+        // proof reveals
+        assert!(unsigned
+            .authorize(&other.spending, witness.alpha())
+            .is_err());
+        let mut other_rk = proof;
+        other_rk.rk = privl1_crypto::keys::randomize_validating_key(
+            &other.spending.validating_key(),
+            witness.alpha(),
+        );
+        other_rk
+            .authorize(&other.spending, witness.alpha())
+            .unwrap();
+        assert!(!other_rk.verify(key.verification_key()).unwrap());
+    }
+}
@@ -0,0 +1,889 @@
+//! Private veNFT lock circuit
+//!
+//! Proves a `PrivateVeNFT` (see `ROADMAP.md`) locks tokens from a shielded
+//! note and carries the matching voting power, without revealing either. For
+//! public `(anchor, nf, rk, cm_change, asset, tier, lock_time, expiration,
+//! amount_commit, voting_power_commit, nft_id, ve_cm)`, the circuit checks:
+//! - the input note is spent as in the spend circuit (`anchor`, `nf`, `rk`)
+//! - its value covers a non-zero `amount`, and `cm_change` returns the rest to
+//!   its owner in `asset`
+//! - `tier < LOCK_TIERS` and
+//!   `voting_power = floor(amount × MULTIPLIER_BPS[tier] / BPS)`, as
+//!   `VotingEscrow.sol` computes it, without u64 overflow
+//! - `expiration = lock_time + TIER_DURATIONS[tier]`
+//! - `amount_commit = [amount]V + [r_amount]R` and
+//!   `voting_power_commit = [voting_power]V + [r_power]R`, as
+//!   `PedersenCommitment` computes them
+//! - `ve_cm = Poseidon(nft_id, owner.x, owner.y, vp.x, vp.y)`, the veNFT leaf
+//!   for the input note's owner and `vp = voting_power_commit`
+//!
+//! Times are in seconds. The lock time is the verifier's to check against
+//! the block the lock lands in, and `nft_id` against the next free id. The
+//! chain appends `ve_cm` to the veNFT tree as is, so every leaf the
+//! gauge-vote circuit opens was minted by a lock proof. A `VeLockProof` only
+//! verifies once signed under `rk` (see [`crate::authorization`]).
+
+use ff::{Field, PrimeField};
+use group::Curve;
+use halo2_gadgets::ecc::NonIdentityPoint;
+use halo2_proofs::circuit::{floor_planner, Layouter, Value};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+use pasta_curves::pallas;
+use privl1_crypto::commitment::{Commitment, PedersenCommitment};
+use privl1_crypto::halo2;
+use privl1_crypto::keys::{FullKeys, PublicKey};
+use privl1_crypto::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use privl1_crypto::note::{asset_base, Note};
+use privl1_crypto::nullifier::Nullifier;
+use privl1_crypto::proof::Halo2Proof;
+use privl1_crypto::{Point as NativePoint, PoseidonHash, Scalar};
+use rand::{CryptoRng, RngCore};
+
+use crate::authorization::{AuthorizedProof, SpendAuthorizing};
+use crate::gadgets::{self, assign_free_advice, AssignedBase, MerklePathConfig, U64Config};
+use crate::note::NoteConfig;
+use crate::spend::SpendWitness;
+use crate::{CircuitError, Result};
+
+/// Circuit size (2^K rows)
+pub const K: u32 = 13;
+
+/// Name the lock verifying key is registered under in `privl1_crypto::halo2`
+pub const CIRCUIT_NAME: &str = "ve-lock-v1";
+
+/// Version of the lock circuit in the verification-key registry
+pub const CIRCUIT_VERSION: u32 = 1;
+
+/// Number of lock tiers (`0..LOCK_TIERS`)
+pub const LOCK_TIERS: u8 = 5;
+
+/// Basis points of a 1x multiplier
+pub const BPS: u64 = 10_000;
+
+/// Voting-power multiplier of each tier in basis points: 1x, 1.5x, 2x, 3x
+/// and 5x
+pub const MULTIPLIER_BPS: [u64; LOCK_TIERS as usize] = [10_000, 15_000, 20_000, 30_000, 50_000];
+
+const DAY: u64 = 86_400;
+
+/// Lock duration of each tier in seconds: 30, 90, 180, 365 and 730 days
+pub const TIER_DURATIONS: [u64; LOCK_TIERS as usize] =
+    [30 * DAY, 90 * DAY, 180 * DAY, 365 * DAY, 730 * DAY];
+
+/// Public input rows
+pub const ANCHOR: usize = 0;
+pub const NULLIFIER: usize = 1;
+pub const RK_X: usize = 2;
+pub const RK_Y: usize = 3;
+pub const CM_CHANGE: usize = 4;
+pub const ASSET: usize = 5;
+pub const TIER: usize = 6;
+pub const LOCK_TIME: usize = 7;
+pub const EXPIRATION: usize = 8;
+pub const AMOUNT_X: usize = 9;
+pub const AMOUNT_Y: usize = 10;
+pub const POWER_X: usize = 11;
+pub const POWER_Y: usize = 12;
+pub const NFT_ID: usize = 13;
+pub const VE_CM: usize = 14;
+
+/// Voting-power multiplier of a tier in basis points
+pub fn tier_multiplier(tier: u8) -> Option<u64> {
+    MULTIPLIER_BPS.get(tier as usize).copied()
+}
+
+/// Voting power of `amount` locked in `tier`, `amount × bps / BPS` rounded
+/// down, or `None` on overflow
+pub fn voting_power(amount: u64, tier: u8) -> Option<u64> {
+    let power = amount as u128 * tier_multiplier(tier)? as u128 / BPS as u128;
+    power.try_into().ok()
+}
+
+/// Expiration of a lock in `tier` made at `lock_time`, or `None` on overflow
+pub fn lock_expiration(lock_time: u64, tier: u8) -> Option<u64> {
+    lock_time.checked_add(*TIER_DURATIONS.get(tier as usize)?)
+}
+
+/// Configuration of the lock circuit
+#[derive(Clone, Debug)]
+pub struct VeLockConfig {
+    note: NoteConfig,
+    merkle: MerklePathConfig<TREE_DEPTH>,
+    arith: U64Config,
+}
+
+/// The lock circuit (witness values unknown when used for keygen)
+#[derive(Clone, Debug, Default)]
+pub struct VeLockCircuit {
+    value_in: Value<u64>,
+    rcm_in: Value<pallas::Base>,
+    ak: Value<pallas::Affine>,
+    nk: Value<pallas::Base>,
+    path: Value<[pallas::Base; TREE_DEPTH]>,
+    position: Value<u64>,
+    alpha: Value<pallas::Scalar>,
+    amount: Value<u64>,
+    rcm_change: Value<pallas::Base>,
+    r_amount: Value<pallas::Scalar>,
+    r_power: Value<pallas::Scalar>,
+}
+
+impl Circuit<pallas::Base> for VeLockCircuit {
+    type Config = VeLockConfig;
+    type FloorPlanner = floor_planner::V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> VeLockConfig {
+        let note = NoteConfig::configure(meta);
+        let merkle = MerklePathConfig::configure(
+            meta,
+            note.advices[..5].try_into().unwrap(),
+            note.poseidon.clone(),
+        );
+        let arith = U64Config::configure(
+            meta,
+            note.advices[..4].try_into().unwrap(),
+            note.range.clone(),
+        );
+
+        VeLockConfig {
+            note,
+            merkle,
+            arith,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: VeLockConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), Error> {
+        let note = &config.note;
+        let arith = &config.arith;
+        note.load(&mut layouter)?;
+
+        let asset = note.public_base(layouter.namespace(|| "asset"), ASSET)?;
+        let tier = note.public_amount(layouter.namespace(|| "tier"), TIER)?;
+        let lock_time = note.public_amount(layouter.namespace(|| "lock_time"), LOCK_TIME)?;
+
+        // Spend the input note
+        let value_in = arith.witness(layouter.namespace(|| "value_in"), self.value_in)?;
+        let rcm_in = assign_free_advice(
+            layouter.namespace(|| "rcm_in"),
+            note.advices[2],
+            self.rcm_in,
+        )?;
+        let nk = assign_free_advice(layouter.namespace(|| "nk"), note.advices[3], self.nk)?;
+        let ak = NonIdentityPoint::new(note.ecc_chip(), layouter.namespace(|| "ak"), self.ak)?;
+
+        let owner = note.derive_owner(layouter.namespace(|| "owner"), &ak, nk.clone())?;
+        let cm_in = note.note_commitment(
+            layouter.namespace(|| "cm_in"),
+            value_in.clone(),
+            asset.clone(),
+            &owner,
+            rcm_in,
+        )?;
+        let (anchor, position) = config.merkle.calculate_root(
+            layouter.namespace(|| "merkle path"),
+            cm_in.clone(),
+            self.path,
+            self.position,
+        )?;
+        layouter.constrain_instance(anchor.cell(), note.primary, ANCHOR)?;
+
+        let nf = note.nullifier(layouter.namespace(|| "nullifier"), nk, cm_in, position)?;
+        layouter.constrain_instance(nf.cell(), note.primary, NULLIFIER)?;
+
+        let rk = note.randomize_key(layouter.namespace(|| "rk"), &ak, self.alpha)?;
+        note.expose_point(&mut layouter, &rk, RK_X)?;
+
+        // Lock part of it, returning the change to the owner
+        let amount = arith.witness(layouter.namespace(|| "amount"), self.amount)?;
+        arith.assert_nonzero(layouter.namespace(|| "amount"), &amount)?;
+        let change = arith.checked_sub(layouter.namespace(|| "change"), &value_in, &amount)?;
+
+        let rcm_change = assign_free_advice(
+            layouter.namespace(|| "rcm_change"),
+            note.advices[2],
+            self.rcm_change,
+        )?;
+        let cm_change = note.note_commitment(
+            layouter.namespace(|| "cm_change"),
+            change,
+            asset,
+            &owner,
+            rcm_change,
+        )?;
+        layouter.constrain_instance(cm_change.cell(), note.primary, CM_CHANGE)?;
+
+        // voting_power = amount * MULTIPLIER_BPS[tier] / BPS and
+        // expiration = lock_time + TIER_DURATIONS[tier], with tier < LOCK_TIERS
+        let tiers = arith.constant(layouter.namespace(|| "tiers"), LOCK_TIERS as u64)?;
+        let valid_tier = arith.lt(layouter.namespace(|| "tier < tiers"), &tier, &tiers)?;
+        layouter.assign_region(
+            || "valid tier",
+            |mut region| region.constrain_constant(valid_tier.cell(), pallas::Base::ONE),
+        )?;
+        let reached = tiers_reached(arith, layouter.namespace(|| "tiers reached"), &tier)?;
+
+        let bps = tier_value(
+            arith,
+            layouter.namespace(|| "bps"),
+            &reached,
+            MULTIPLIER_BPS,
+        )?;
+        let denominator = arith.constant(layouter.namespace(|| "denominator"), BPS)?;
+        let (power, _) = arith.mul_div(
+            layouter.namespace(|| "voting power"),
+            &amount,
+            &bps,
+            &denominator,
+        )?;
+
+        let duration = tier_value(
+            arith,
+            layouter.namespace(|| "duration"),
+            &reached,
+            TIER_DURATIONS,
+        )?;
+        let expiration =
+            arith.checked_add(layouter.namespace(|| "expiration"), &lock_time, &duration)?;
+        layouter.constrain_instance(expiration.cell(), note.primary, EXPIRATION)?;
+
+        let amount_commit = note.value_commitment(
+            layouter.namespace(|| "amount_commit"),
+            amount,
+            self.r_amount,
+        )?;
+        note.expose_point(&mut layouter, &amount_commit, AMOUNT_X)?;
+
+        let power_commit = note.value_commitment(
+            layouter.namespace(|| "voting_power_commit"),
+            power,
+            self.r_power,
+        )?;
+        note.expose_point(&mut layouter, &power_commit, POWER_X)?;
+
+        // The veNFT leaf the lock mints
+        let nft_id = note.public_amount(layouter.namespace(|| "nft_id"), NFT_ID)?;
+        let ve_cm = gadgets::poseidon_hash(
+            &note.poseidon,
+            layouter.namespace(|| "ve_cm"),
+            [
+                nft_id,
+                owner.inner().x(),
+                owner.inner().y(),
+                power_commit.inner().x(),
+                power_commit.inner().y(),
+            ],
+        )?;
+        layouter.constrain_instance(ve_cm.cell(), note.primary, VE_CM)
+    }
+}
+
+/// `[tier >= i]` for each tier `i` from 1, for a range-checked `tier`
+fn tiers_reached(
+    arith: &U64Config,
+    mut layouter: impl Layouter<pallas::Base>,
+    tier: &AssignedBase,
+) -> std::result::Result<Vec<AssignedBase>, Error> {
+    let one = arith.constant(layouter.namespace(|| "one"), 1)?;
+    (1..LOCK_TIERS as u64)
+        .map(|i| {
+            let threshold = arith.constant(layouter.namespace(|| "threshold"), i)?;
+            let below = arith.lt(layouter.namespace(|| "tier < i"), tier, &threshold)?;
+            arith.checked_sub(layouter.namespace(|| "tier >= i"), &one, &below)
+        })
+        .collect()
+}
+
+/// `table[tier]` for a non-decreasing `table`: `table[0]` plus each step
+/// `table[i] - table[i - 1]` the tier has reached
+fn tier_value(
+    arith: &U64Config,
+    mut layouter: impl Layouter<pallas::Base>,
+    reached: &[AssignedBase],
+    table: [u64; LOCK_TIERS as usize],
+) -> std::result::Result<AssignedBase, Error> {
+    let mut value = arith.constant(layouter.namespace(|| "tier 0"), table[0])?;
+    for (flag, step) in reached.iter().zip(table.windows(2)) {
+        let step = arith.constant(layouter.namespace(|| "step"), step[1] - step[0])?;
+        let increment = arith.checked_mul(layouter.namespace(|| "flag * step"), flag, &step)?;
+        value = arith.checked_add(layouter.namespace(|| "+ step"), &value, &increment)?;
+    }
+    Ok(value)
+}
+
+/// Terms of a lock
+#[derive(Clone, Debug)]
+pub struct VeLock {
+    /// Amount taken from the input note
+    pub amount: u64,
+    /// Lock tier (`0..LOCK_TIERS`)
+    pub tier: u8,
+    /// Time the lock is made, in seconds
+    pub lock_time: u64,
+    /// Id of the veNFT the lock mints
+    pub nft_id: u64,
+}
+
+impl VeLock {
+    /// Time the lock expires, `lock_time` plus the tier's duration
+    pub fn expiration(&self) -> Option<u64> {
+        lock_expiration(self.lock_time, self.tier)
+    }
+}
+
+/// A veNFT as committed in the veNFT tree
+///
+/// The leaf is `ve_cm = Poseidon(nft_id, owner.x, owner.y, vp.x, vp.y)`, where
+/// `vp` is the voting-power commitment. The lock circuit publishes it and the
+/// gauge-vote circuit opens it.
+#[derive(Clone, Debug)]
+pub struct VeNft {
+    /// NFT id, kept private when voting
+    pub nft_id: u64,
+    /// Owner of the lock
+    pub owner: PublicKey,
+    /// Committed voting power
+    pub voting_power: u64,
+    /// Blinding of the voting-power commitment
+    pub blinding: Scalar,
+}
+
+impl VeNft {
+    /// Commitment to the voting power, as published with the lock
+    pub fn voting_power_commit(&self) -> Commitment {
+        PedersenCommitment::new().commit_with_blinding(self.voting_power, self.blinding)
+    }
+
+    /// Leaf commitment in the veNFT tree
+    pub fn commitment(&self) -> pallas::Base {
+        let (owner_x, owner_y) = self.owner.as_point().coordinates();
+        let (vp_x, vp_y) = self.voting_power_commit().as_point().coordinates();
+        PoseidonHash::hash([
+            pallas::Base::from(self.nft_id),
+            owner_x,
+            owner_y,
+            vp_x,
+            vp_y,
+        ])
+        .to_field()
+    }
+
+    /// Leaf appended to the veNFT tree
+    pub fn leaf(&self) -> [u8; 32] {
+        self.commitment().to_repr()
+    }
+}
+
+/// Private inputs of a lock
+#[derive(Clone, Debug)]
+pub struct VeLockWitness {
+    input: SpendWitness,
+    asset_id: [u8; 32],
+    lock: VeLock,
+    power: u64,
+    change: Note,
+    r_amount: Scalar,
+    r_power: Scalar,
+}
+
+impl VeLockWitness {
+    /// Build the witness for locking `lock.amount` of `note`
+    ///
+    /// Fails if the amount is zero or not covered by the note, the tier is
+    /// unknown, or the voting power or expiration overflows.
+    pub fn new<R: RngCore>(
+        note: &Note,
+        merkle_proof: &MerkleProof,
+        keys: &FullKeys,
+        lock: VeLock,
+        rng: &mut R,
+    ) -> Result<Self> {
+        if lock.amount == 0 {
+            return Err(CircuitError::InvalidParameters("Nothing to lock".into()));
+        }
+        let change = note.value().checked_sub(lock.amount).ok_or_else(|| {
+            CircuitError::InvalidParameters("Note does not cover the lock".into())
+        })?;
+        let power = voting_power(lock.amount, lock.tier).ok_or_else(|| {
+            CircuitError::InvalidParameters(format!("Invalid lock tier {}", lock.tier))
+        })?;
+        lock.expiration()
+            .ok_or_else(|| CircuitError::InvalidParameters("Lock expiration overflows".into()))?;
+
+        // The input side is a spend whose value commitment goes unused
+        let input = SpendWitness::new(
+            note,
+            merkle_proof,
+            keys,
+            Scalar::zero(),
+            Scalar::random(rng),
+        )?;
+        let change =
+            Note::with_randomness(change, keys.public, *note.asset_id(), Scalar::random(rng));
+
+        Ok(Self {
+            input,
+            asset_id: *note.asset_id(),
+            lock,
+            power,
+            change,
+            r_amount: Scalar::random(rng),
+            r_power: Scalar::random(rng),
+        })
+    }
+
+    /// Note returning the unlocked value to the owner
+    pub fn change(&self) -> &Note {
+        &self.change
+    }
+
+    /// Voting power of the lock
+    pub fn voting_power(&self) -> u64 {
+        self.power
+    }
+
+    /// Time the lock expires
+    pub fn expiration(&self) -> u64 {
+        self.lock.expiration().expect("checked on construction")
+    }
+
+    /// Commitment to the locked amount
+    pub fn amount_commit(&self) -> Commitment {
+        PedersenCommitment::new().commit_with_blinding(self.lock.amount, self.r_amount)
+    }
+
+    /// Blinding of the amount commitment
+    pub fn amount_blinding(&self) -> &Scalar {
+        &self.r_amount
+    }
+
+    /// Commitment to the voting power
+    pub fn voting_power_commit(&self) -> Commitment {
+        PedersenCommitment::new().commit_with_blinding(self.voting_power(), self.r_power)
+    }
+
+    /// Blinding of the voting-power commitment, needed to vote with the lock
+    pub fn voting_power_blinding(&self) -> &Scalar {
+        &self.r_power
+    }
+
+    /// The veNFT this lock mints, owned by the input note's owner
+    pub fn ve_nft(&self) -> VeNft {
+        VeNft {
+            nft_id: self.lock.nft_id,
+            owner: *self.change.owner(),
+            voting_power: self.power,
+            blinding: self.r_power,
+        }
+    }
+
+    /// Root the input note's authentication path hashes to
+    pub fn anchor(&self) -> MerkleRoot {
+        self.input.anchor()
+    }
+
+    /// Nullifier of the input note
+    pub fn nullifier(&self) -> Nullifier {
+        self.input.nullifier()
+    }
+
+    /// Re-randomized key the lock authorization is checked against
+    pub fn rk(&self) -> NativePoint {
+        self.input.rk()
+    }
+
+    /// Randomizer of the spend key, needed to sign the lock authorization
+    pub fn alpha(&self) -> &Scalar {
+        self.input.alpha()
+    }
+
+    /// Public inputs, in row order
+    pub fn public_inputs(&self) -> Vec<pallas::Base> {
+        let to_base = |bytes: &[u8; 32]| {
+            Option::from(pallas::Base::from_repr(*bytes)).expect("Poseidon output is canonical")
+        };
+        let (rk_x, rk_y) = self.rk().coordinates();
+        let (amount_x, amount_y) = self.amount_commit().as_point().coordinates();
+        let (power_x, power_y) = self.voting_power_commit().as_point().coordinates();
+
+        vec![
+            to_base(self.anchor().as_bytes()),
+            to_base(self.nullifier().as_bytes()),
+            rk_x,
+            rk_y,
+            self.change.commitment().to_field(),
+            asset_base(&self.asset_id),
+            pallas::Base::from(self.lock.tier as u64),
+            pallas::Base::from(self.lock.lock_time),
+            pallas::Base::from(self.expiration()),
+            amount_x,
+            amount_y,
+            power_x,
+            power_y,
+            pallas::Base::from(self.lock.nft_id),
+            self.ve_nft().commitment(),
+        ]
+    }
+
+    /// The circuit with this witness assigned
+    pub fn circuit(&self) -> VeLockCircuit {
+        let input = &self.input;
+        VeLockCircuit {
+            value_in: Value::known(input.value),
+            rcm_in: Value::known(input.rcm),
+            ak: Value::known(input.ak.inner().to_affine()),
+            nk: Value::known(input.nk),
+            path: Value::known(input.path),
+            position: Value::known(input.position),
+            alpha: Value::known(*input.alpha.inner()),
+            amount: Value::known(self.lock.amount),
+            rcm_change: Value::known(self.change.rcm()),
+            r_amount: Value::known(*self.r_amount.inner()),
+            r_power: Value::known(*self.r_power.inner()),
+        }
+    }
+}
+
+impl SpendAuthorizing for VeLockCircuit {
+    const SIGHASH_DOMAIN: &'static str = "PRIVL1_VE_LOCK_SIGHASH";
+    const RK_X: usize = RK_X;
+    const RK_Y: usize = RK_Y;
+}
+
+/// Lock proof with the owner's spend authorization
+pub type VeLockProof = AuthorizedProof<VeLockCircuit>;
+
+crate::keystore::circuit_keys!("lock", VeLockCircuit, VeLockProvingKey);
+
+/// Prove a lock
+///
+/// The proof is unsigned; `VeLockProof::authorize` it with the input note's
+/// spending key and `witness.alpha()`.
+pub fn prove<R: RngCore + CryptoRng>(
+    key: &VeLockProvingKey,
+    witness: &VeLockWitness,
+    rng: R,
+) -> Result<VeLockProof> {
+    let instances = witness.public_inputs();
+    let proof = halo2::create_proof(
+        key.key.params(),
+        key.key.proving_key(),
+        witness.circuit(),
+        &instances,
+        rng,
+    )?;
+
+    let proof = Halo2Proof::new(
+        proof,
+        instances.iter().map(halo2::encode_instance).collect(),
+        key.verification_key().id(),
+    );
+    Ok(VeLockProof::new(proof, witness.rk()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{mock_failures, mock_prove};
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+    use privl1_crypto::note::AssetId;
+    use rand::rngs::OsRng;
+
+    /// Lock time of the test locks
+    const LOCKED_AT: u64 = 1_700_000_000;
+
+    /// Id of the test locks' veNFT
+    const TEST_NFT_ID: u64 = 3;
+
+    fn lock_witness(value: u64, amount: u64, tier: u8) -> Result<VeLockWitness> {
+        lock_witness_for(&FullKeys::random(&mut OsRng), value, amount, tier)
+    }
+
+    fn lock_witness_for(
+        keys: &FullKeys,
+        value: u64,
+        amount: u64,
+        tier: u8,
+    ) -> Result<VeLockWitness> {
+        let note = Note::new_with_owner(value, keys.public, *AssetId::NATIVE.as_bytes());
+
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.commitment().leaf()).unwrap();
+        let merkle_proof = tree.prove(0).unwrap();
+
+        let lock = VeLock {
+            amount,
+            tier,
+            lock_time: LOCKED_AT,
+            nft_id: TEST_NFT_ID,
+        };
+        VeLockWitness::new(&note, &merkle_proof, keys, lock, &mut OsRng)
+    }
+
+    /// Public inputs claiming `power`, and the leaf with it, for the witness's
+    /// lock
+    fn claim_power(witness: &VeLockWitness, power: u64) -> Vec<pallas::Base> {
+        let commit = PedersenCommitment::new().commit_with_blinding(power, witness.r_power);
+        let (power_x, power_y) = commit.as_point().coordinates();
+
+        let nft = VeNft {
+            voting_power: power,
+            ..witness.ve_nft()
+        };
+
+        let mut instances = witness.public_inputs();
+        instances[POWER_X] = power_x;
+        instances[POWER_Y] = power_y;
+        instances[VE_CM] = nft.commitment();
+        instances
+    }
+
+    #[test]
+    fn test_tier_multipliers() {
+        assert_eq!(
+            (0..LOCK_TIERS)
+                .map(|tier| tier_multiplier(tier).unwrap())
+                .collect::<Vec<_>>(),
+            [10_000, 15_000, 20_000, 30_000, 50_000]
+        );
+        assert_eq!(tier_multiplier(LOCK_TIERS), None);
+        assert_eq!(voting_power(1_000, 4), Some(5_000));
+        assert_eq!(voting_power(1_001, 1), Some(1_501));
+        assert_eq!(voting_power(u64::MAX, 0), Some(u64::MAX));
+        assert_eq!(voting_power(u64::MAX / 5, 4), Some(u64::MAX / 5 * 5));
+        assert_eq!(voting_power(u64::MAX / 5 + 1, 4), None);
+        assert_eq!(voting_power(1_000, LOCK_TIERS), None);
+    }
+
+    #[test]
+    fn test_tier_durations() {
+        assert_eq!(lock_expiration(LOCKED_AT, 0), Some(LOCKED_AT + 30 * 86_400));
+        assert_eq!(lock_expiration(LOCKED_AT, 1), Some(LOCKED_AT + 90 * 86_400));
+        assert_eq!(
+            lock_expiration(LOCKED_AT, 2),
+            Some(LOCKED_AT + 180 * 86_400)
+        );
+        assert_eq!(
+            lock_expiration(LOCKED_AT, 3),
+            Some(LOCKED_AT + 365 * 86_400)
+        );
+        assert_eq!(
+            lock_expiration(LOCKED_AT, 4),
+            Some(LOCKED_AT + 730 * 86_400)
+        );
+        assert_eq!(lock_expiration(LOCKED_AT, LOCK_TIERS), None);
+        assert_eq!(lock_expiration(u64::MAX - 86_400, 0), None);
+    }
+
+    #[test]
+    fn test_lock_circuit() {
+        for (tier, power) in (0..LOCK_TIERS).zip([600, 900, 1_200, 1_800, 3_000]) {
+            let witness = lock_witness(1_000, 600, tier).unwrap();
+            assert_eq!(witness.voting_power(), power);
+            assert_eq!(
+                witness.expiration(),
+                LOCKED_AT + TIER_DURATIONS[tier as usize]
+            );
+            assert_eq!(witness.change().value(), 400);
+            assert!(PedersenCommitment::new().verify(
+                &witness.voting_power_commit(),
+                witness.voting_power(),
+                *witness.voting_power_blinding()
+            ));
+            mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+        }
+
+        // Locking the whole note leaves a zero-value change note
+        let witness = lock_witness(1_000, 1_000, 2).unwrap();
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+
+        // Fractional voting power rounds down
+        let witness = lock_witness(1_000, 601, 1).unwrap();
+        assert_eq!(witness.voting_power(), 901);
+        mock_prove(&witness.circuit(), vec![witness.public_inputs()], K).unwrap();
+    }
+
+    #[test]
+    fn test_ve_nft_matches_lock() {
+        let witness = lock_witness(1_000, 600, 2).unwrap();
+        let nft = witness.ve_nft();
+        assert_eq!(nft.nft_id, TEST_NFT_ID);
+        assert_eq!(nft.voting_power, 1_200);
+        assert_eq!(nft.voting_power_commit(), witness.voting_power_commit());
+        assert_eq!(&nft.owner, witness.change().owner());
+
+        let instances = witness.public_inputs();
+        assert_eq!(instances[VE_CM], nft.commitment());
+
+        // The leaf binds the NFT id
+        let other = VeNft { nft_id: 4, ..nft };
+        assert_ne!(other.leaf(), witness.ve_nft().leaf());
+    }
+
+    #[test]
+    fn test_mismatched_leaf_rejected() {
+        let witness = lock_witness(1_000, 600, 2).unwrap();
+        let nft = witness.ve_nft();
+
+        // Another id, another owner, or more voting power than the lock has
+        let stranger = FullKeys::random(&mut OsRng);
+        for forged in [
+            VeNft {
+                nft_id: 4,
+                ..nft.clone()
+            },
+            VeNft {
+                owner: stranger.public,
+                ..nft.clone()
+            },
+            VeNft {
+                voting_power: 5_000,
+                ..nft.clone()
+            },
+        ] {
+            let mut instances = witness.public_inputs();
+            instances[VE_CM] = forged.commitment();
+            assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+                .unwrap()
+                .is_empty());
+        }
+
+        // Claiming another id moves the leaf with it
+        let mut instances = witness.public_inputs();
+        instances[NFT_ID] = pallas::Base::from(4);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_wrong_public_inputs_rejected() {
+        let witness = lock_witness(1_000, 600, 3).unwrap();
+
+        for row in [
+            ANCHOR, NULLIFIER, RK_X, RK_Y, CM_CHANGE, ASSET, TIER, LOCK_TIME, EXPIRATION, AMOUNT_X,
+            AMOUNT_Y, POWER_X, POWER_Y, NFT_ID, VE_CM,
+        ] {
+            let mut instances = witness.public_inputs();
+            instances[row] += pallas::Base::ONE;
+            assert!(
+                !mock_failures(&witness.circuit(), vec![instances], K)
+                    .unwrap()
+                    .is_empty(),
+                "row {} unconstrained",
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrong_voting_power_rejected() {
+        let witness = lock_witness(1_000, 600, 3).unwrap();
+
+        assert_eq!(witness.voting_power(), 1_800);
+
+        // Another tier's power, or one off
+        for power in [1_200, 3_000, 1_799, 1_801] {
+            assert!(
+                !mock_failures(&witness.circuit(), vec![claim_power(&witness, power)], K)
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrong_expiration_rejected() {
+        let witness = lock_witness(1_000, 600, 3).unwrap();
+
+        // Another tier's duration, from the same lock time
+        for tier in [2, 4] {
+            let mut instances = witness.public_inputs();
+            instances[EXPIRATION] = pallas::Base::from(LOCKED_AT + TIER_DURATIONS[tier]);
+            assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+                .unwrap()
+                .is_empty());
+        }
+
+        // The same expiration claimed from a later lock time
+        let mut instances = witness.public_inputs();
+        instances[LOCK_TIME] = pallas::Base::from(LOCKED_AT + DAY);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_invalid_locks_rejected() {
+        assert!(lock_witness(1_000, 0, 0).is_err());
+        assert!(lock_witness(1_000, 1_001, 0).is_err());
+        assert!(lock_witness(1_000, 600, LOCK_TIERS).is_err());
+        assert!(lock_witness(u64::MAX, u64::MAX / 4, 4).is_err());
+
+        // An unknown tier cannot be claimed
+        let witness = lock_witness(1_000, 600, 4).unwrap();
+        let mut instances = witness.public_inputs();
+        instances[TIER] = pallas::Base::from(LOCK_TIERS as u64);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+
+        // Nor an amount whose voting power overflows
+        let mut witness = lock_witness(u64::MAX, 1, 4).unwrap();
+        witness.lock.amount = u64::MAX / 4;
+        witness.change = Note::with_randomness(
+            u64::MAX - witness.lock.amount,
+            *witness.change.owner(),
+            *AssetId::NATIVE.as_bytes(),
+            Scalar::zero(),
+        );
+        let wrapped = (witness.lock.amount as u128 * 5) as u64;
+        let instances = claim_power(&witness, wrapped);
+        assert!(!mock_failures(&witness.circuit(), vec![instances], K)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let keys = FullKeys::random(&mut OsRng);
+        let witness = lock_witness_for(&keys, 1_000, 600, 4).unwrap();
+        let key = keygen().unwrap();
+
+        let mut proof = prove(&key, &witness, OsRng).unwrap();
+        proof.authorize(&keys.spending, witness.alpha()).unwrap();
+        assert!(proof.verify(key.verification_key()).unwrap());
+
+        // Claiming a longer lock fails
+        let mut tampered = proof.clone();
+        tampered.proof.public_inputs[EXPIRATION] =
+            halo2::encode_instance(&pallas::Base::from(2_000_000));
+        assert!(!tampered.verify(key.verification_key()).unwrap());
+
+        // The proof alone does not authorize the lock: it is rejected with no
+        // signature or one under another key
+        let mut unsigned = proof.clone();
+        unsigned.spend_auth_sig = None;
+        let other = FullKeys::random(&mut OsRng);
+        let mut wrong_key = proof.clone();
+        wrong_key.spend_auth_sig = Some(
+            other
+                .spending
+                .sign_randomized(witness.alpha(), &proof.sighash()),
+        );
+        for rejected in [&unsigned, &wrong_key] {
+            assert!(rejected.proof.verify(key.verification_key()).unwrap());
+            assert!(!rejected.verify(key.verification_key()).unwrap());
+        }
+        assert!(unsigned
+            .authorize(&other.spending, witness.alpha())
+            .is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Lending contract
+//!
+//! The contract holds three kinds of state note, each `[kind, amounts]` with
+//! the amounts in the low words of the second element:
+//!
+//! - a value note (kind 1) holds `amount` for its owner, as
+//!   `privl1_contracts::Ledger::deposit` creates it
+//! - a position (kind 2) holds `collateral` and `debt` for its owner
+//! - a pool (kind 3) holds lendable `liquidity` and has no owner: any call
+//!   may consume it, and only this contract's rules move its value
+//!
+//! Every call produces notes worth what it consumes, so the value the
+//! contract holds is the value deposited into it. A position may borrow up
+//! to half its collateral:
+//!
+//! - `init()` creates an empty pool
+//! - `supply(u32)` moves the amount from a value note into the pool
+//! - `open(u32)` locks the amount of a value note as a new position's
+//!   collateral
+//! - `borrow(u32)` pays the amount from the pool to the position's owner and
+//!   adds it to the debt, returning the new debt
+//! - `repay(u32)` pays the amount from a value note back to the pool,
+//!   returning the new debt
+//! - `withdraw(u32)` pays collateral to the owner, returning what is left
+//! - `close()` consumes a position without debt and pays out its collateral,
+//!   returning it
+//!
+//! Inputs and outputs follow the call ABI of `privl1_contracts::abi`, with
+//! the input notes in the order listed: `supply` takes the pool then a
+//! value note, `borrow` a position then the pool, `repay` a position, the
+//! pool and a value note. Change goes back to the value note's owner. The
+//! guest exits with 1 for a malformed call, 2 for an input note not in the
+//! commitment tree, 3 when a position would be undercollateralized, 4 when
+//! a note is consumed without its owner's signature and 5 when a value note
+//! or the pool does not cover the amount.
+
+#![no_std]
+#![no_main]
+
+use privl1_zkvm_guest::precompile::{
+    poseidon, verify_merkle_path, verify_signature, MERKLE_DEPTH, SIGNATURE_LEN,
+};
+use privl1_zkvm_guest::{commit, halt, private_input, public_input, Reader};
+
+privl1_zkvm_guest::entry!(main);
+
+const EXIT_MALFORMED: u32 = 1;
+const EXIT_INVALID_NOTE: u32 = 2;
+const EXIT_UNDERCOLLATERALIZED: u32 = 3;
+const EXIT_UNAUTHORIZED: u32 = 4;
+const EXIT_INSUFFICIENT_FUNDS: u32 = 5;
+
+// Note kinds
+const VALUE: u32 = 1;
+const POSITION: u32 = 2;
+const POOL: u32 = 3;
+
+// Selectors, the first four bytes of the BLAKE3 hash of each signature
+/// `init()`
+const INIT: [u8; 4] = [0x39, 0xc8, 0xe4, 0xc6];
+/// `supply(u32)`
+const SUPPLY: [u8; 4] = [0x2d, 0x02, 0x5a, 0x74];
+/// `open(u32)`
+const OPEN: [u8; 4] = [0x9f, 0x11, 0xdb, 0x19];
+/// `borrow(u32)`
+const BORROW: [u8; 4] = [0xab, 0x4f, 0x5d, 0x98];
+/// `repay(u32)`
+const REPAY: [u8; 4] = [0x61, 0xcc, 0xe8, 0x89];
+/// `withdraw(u32)`
+const WITHDRAW: [u8; 4] = [0xda, 0x73, 0x17, 0xe2];
+/// `close()`
+const CLOSE: [u8; 4] = [0x22, 0x91, 0xad, 0x1d];
+
+/// Length of the public input up to the arguments
+const CALL_HEADER_LEN: usize = 72;
+
+/// Longest public input of a lending call, with a `u32` argument
+const MAX_CALL_LEN: usize = CALL_HEADER_LEN + 4;
+
+/// Most notes a call consumes or produces
+const MAX_NOTES: usize = 3;
+
+/// The owner tag of the pool
+const NO_OWNER: Felt = [0u8; 32];
+
+/// A canonical field element
+type Felt = [u8; 32];
+
+/// A note's kind, owner and amounts: `[amount, 0]` for a value note,
+/// `[collateral, debt]` for a position and `[liquidity, 0]` for the pool
+#[derive(Clone, Copy)]
+struct Holding {
+    kind: u32,
+    owner: Felt,
+    amounts: [u32; 2],
+}
+
+impl Holding {
+    fn value(owner: Felt, amount: u32) -> Self {
+        Self {
+            kind: VALUE,
+            owner,
+            amounts: [amount, 0],
+        }
+    }
+
+    fn pool(liquidity: u32) -> Self {
+        Self {
+            kind: POOL,
+            owner: NO_OWNER,
+            amounts: [liquidity, 0],
+        }
+    }
+}
+
+/// Nullifiers of the notes consumed and commitments of those produced so far
+struct Notes {
+    nullifiers: [Felt; MAX_NOTES],
+    consumed: usize,
+    commitments: [Felt; MAX_NOTES],
+    produced: usize,
+}
+
+/// The call's public input
+struct Call<'a> {
+    /// The encoded call, which owners sign
+    encoded: &'a [u8],
+    contract: Felt,
+    root: [u8; 32],
+    selector: [u8; 4],
+    args: &'a [u8],
+}
+
+fn main() {
+    let call = read_call();
+    let mut witness = Reader::new(private_input());
+    let inputs = or_malformed(witness.read_u32());
+    let mut notes = Notes::new();
+
+    let result = match call.selector {
+        INIT => {
+            no_arguments(&call);
+            expect_inputs(inputs, 0);
+            notes.start_outputs(&mut witness, 1);
+            notes.produce(&call, &mut witness, Holding::pool(0));
+            None
+        }
+        SUPPLY => {
+            let amount = argument(&call);
+            expect_inputs(inputs, 2);
+            let pool = notes.consume(&call, &mut witness, POOL);
+            let value = notes.consume(&call, &mut witness, VALUE);
+            let change = value.amounts[0]
+                .checked_sub(amount)
+                .unwrap_or_else(|| halt(EXIT_INSUFFICIENT_FUNDS));
+            let liquidity = or_malformed(pool.amounts[0].checked_add(amount));
+            notes.start_outputs(&mut witness, 2);
+            notes.produce(&call, &mut witness, Holding::pool(liquidity));
+            notes.produce(&call, &mut witness, Holding::value(value.owner, change));
+            Some(liquidity)
+        }
+        OPEN => {
+            let collateral = argument(&call);
+            expect_inputs(inputs, 1);
+            let value = notes.consume(&call, &mut witness, VALUE);
+            let change = value.amounts[0]
+                .checked_sub(collateral)
+                .unwrap_or_else(|| halt(EXIT_INSUFFICIENT_FUNDS));
+            let position = Holding {
+                kind: POSITION,
+                owner: value.owner,
+                amounts: [collateral, 0],
+            };
+            notes.start_outputs(&mut witness, 2);
+            notes.produce(&call, &mut witness, position);
+            notes.produce(&call, &mut witness, Holding::value(value.owner, change));
+            None
+        }
+        BORROW => {
+            let amount = argument(&call);
+            expect_inputs(inputs, 2);
+            let mut position = notes.consume(&call, &mut witness, POSITION);
+            let pool = notes.consume(&call, &mut witness, POOL);
+            let [collateral, debt] = position.amounts;
+            let debt = debt
+                .checked_add(amount)
+                .unwrap_or_else(|| halt(EXIT_UNDERCOLLATERALIZED));
+            if debt > collateral / 2 {
+                halt(EXIT_UNDERCOLLATERALIZED);
+            }
+            let liquidity = pool.amounts[0]
+                .checked_sub(amount)
+                .unwrap_or_else(|| halt(EXIT_INSUFFICIENT_FUNDS));
+            position.amounts[1] = debt;
+            notes.start_outputs(&mut witness, 3);
+            notes.produce(&call, &mut witness, position);
+            notes.produce(&call, &mut witness, Holding::pool(liquidity));
+            notes.produce(&call, &mut witness, Holding::value(position.owner, amount));
+            Some(debt)
+        }
+        REPAY => {
+            let amount = argument(&call);
+            expect_inputs(inputs, 3);
+            let mut position = notes.consume(&call, &mut witness, POSITION);
+            let pool = notes.consume(&call, &mut witness, POOL);
+            let value = notes.consume(&call, &mut witness, VALUE);
+            let debt = or_malformed(position.amounts[1].checked_sub(amount));
+            let change = value.amounts[0]
+                .checked_sub(amount)
+                .unwrap_or_else(|| halt(EXIT_INSUFFICIENT_FUNDS));
+            let liquidity = or_malformed(pool.amounts[0].checked_add(amount));
+            position.amounts[1] = debt;
+            notes.start_outputs(&mut witness, 3);
+            notes.produce(&call, &mut witness, position);
+            notes.produce(&call, &mut witness, Holding::pool(liquidity));
+            notes.produce(&call, &mut witness, Holding::value(value.owner, change));
+            Some(debt)
+        }
+        WITHDRAW => {
+            let amount = argument(&call);
+            expect_inputs(inputs, 1);
+            let mut position = notes.consume(&call, &mut witness, POSITION);
+            let [collateral, debt] = position.amounts;
+            let collateral = collateral
+                .checked_sub(amount)
+                .unwrap_or_else(|| halt(EXIT_UNDERCOLLATERALIZED));
+            if collateral / 2 < debt {
+                halt(EXIT_UNDERCOLLATERALIZED);
+            }
+            position.amounts[0] = collateral;
+            notes.start_outputs(&mut witness, 2);
+            notes.produce(&call, &mut witness, position);
+            notes.produce(&call, &mut witness, Holding::value(position.owner, amount));
+            Some(collateral)
+        }
+        CLOSE => {
+            no_arguments(&call);
+            expect_inputs(inputs, 1);
+            let position = notes.consume(&call, &mut witness, POSITION);
+            let [collateral, debt] = position.amounts;
+            if debt != 0 {
+                halt(EXIT_UNDERCOLLATERALIZED);
+            }
+            notes.start_outputs(&mut witness, 1);
+            notes.produce(
+                &call,
+                &mut witness,
+                Holding::value(position.owner, collateral),
+            );
+            Some(collateral)
+        }
+        _ => halt(EXIT_MALFORMED),
+    };
+    notes.finish(result);
+}
+
+fn read_call() -> Call<'static> {
+    let encoded = public_input();
+    let mut reader = Reader::new(encoded);
+    let contract = or_malformed(reader.read_array());
+    let root = or_malformed(reader.read_array());
+    let selector = or_malformed(reader.read_array());
+    let len = or_malformed(reader.read_u32());
+    let args = or_malformed(reader.read_bytes(len as usize));
+    if !reader.remaining().is_empty() {
+        halt(EXIT_MALFORMED);
+    }
+    Call {
+        encoded,
+        contract,
+        root,
+        selector,
+        args,
+    }
+}
+
+/// The call's single `u32` argument
+fn argument(call: &Call) -> u32 {
+    let bytes: [u8; 4] = or_malformed(call.args.try_into().ok());
+    u32::from_le_bytes(bytes)
+}
+
+fn no_arguments(call: &Call) {
+    if !call.args.is_empty() {
+        halt(EXIT_MALFORMED);
+    }
+}
+
+fn expect_inputs(inputs: u32, expected: u32) {
+    if inputs != expected {
+        halt(EXIT_MALFORMED);
+    }
+}
+
+impl Notes {
+    fn new() -> Self {
+        Self {
+            nullifiers: [[0u8; 32]; MAX_NOTES],
+            consumed: 0,
+            commitments: [[0u8; 32]; MAX_NOTES],
+            produced: 0,
+        }
+    }
+
+    /// Check the next input note against the root, its kind and, unless it
+    /// is the pool, its owner's signature, returning what it holds
+    fn consume(&mut self, call: &Call, witness: &mut Reader, kind: u32) -> Holding {
+        // The note, path, key and signature are borrowed from the input:
+        // copying them would cost more cycles than checking them
+        let note: &[Felt; 5] = read_elements(witness);
+        let position = or_malformed(witness.read_u64());
+        let path: &[Felt; MERKLE_DEPTH] = read_elements(witness);
+        let key: &[u8; 32] = read_ref(witness);
+        let signature: &[u8; SIGNATURE_LEN] = read_ref(witness);
+
+        // A note held by another contract is not in this contract's tree
+        if note[0] != call.contract {
+            halt(EXIT_INVALID_NOTE);
+        }
+        let commitment = poseidon(note);
+        if !verify_merkle_path(&commitment, &call.root, path, position) {
+            halt(EXIT_INVALID_NOTE);
+        }
+        let nullifier = poseidon(&[commitment, note[4]]);
+        if note[2] != element(kind) {
+            halt(EXIT_MALFORMED);
+        }
+
+        if kind == POOL {
+            if note[1] != NO_OWNER {
+                halt(EXIT_MALFORMED);
+            }
+        } else {
+            // The key must be the owner's and sign the nullifier and the call
+            if owner_of(key) != note[1] {
+                halt(EXIT_UNAUTHORIZED);
+            }
+            let mut message = [0u8; 32 + MAX_CALL_LEN];
+            let len = 32 + call.encoded.len();
+            message[..32].copy_from_slice(&nullifier);
+            message[32..len].copy_from_slice(call.encoded);
+            if !verify_signature(key, signature, &message[..len]) {
+                halt(EXIT_UNAUTHORIZED);
+            }
+        }
+
+        self.nullifiers[self.consumed] = nullifier;
+        self.consumed += 1;
+        Holding {
+            kind,
+            owner: note[1],
+            amounts: [word(&note[3], 0), word(&note[3], 1)],
+        }
+    }
+
+    /// Skip the owner tag, as new notes go to the owners of the inputs, and
+    /// check there are blindings for `count` notes
+    fn start_outputs(&self, witness: &mut Reader, count: usize) {
+        or_malformed(witness.read_bytes(32));
+        let blindings = or_malformed(witness.read_u32());
+        if (blindings as usize) < count {
+            halt(EXIT_MALFORMED);
+        }
+    }
+
+    /// Commit `holding` to a note with the next blinding
+    fn produce(&mut self, call: &Call, witness: &mut Reader, holding: Holding) {
+        let blinding = or_malformed(witness.read_array());
+        let mut amounts = [0u8; 32];
+        amounts[..4].copy_from_slice(&holding.amounts[0].to_le_bytes());
+        amounts[4..8].copy_from_slice(&holding.amounts[1].to_le_bytes());
+        self.commitments[self.produced] = poseidon(&[
+            call.contract,
+            holding.owner,
+            element(holding.kind),
+            amounts,
+            blinding,
+        ]);
+        self.produced += 1;
+    }
+
+    /// Publish the output: nullifiers, commitments, then the return word
+    fn finish(&self, result: Option<u32>) {
+        for list in [
+            &self.nullifiers[..self.consumed],
+            &self.commitments[..self.produced],
+        ] {
+            commit(&(list.len() as u32).to_le_bytes());
+            for value in list {
+                commit(value);
+            }
+        }
+        if let Some(result) = result {
+            commit(&result.to_le_bytes());
+        }
+    }
+}
+
+/// Owner tag of a validating key: the Poseidon hash of its 16-byte halves
+fn owner_of(key: &[u8; 32]) -> Felt {
+    let mut halves = [[0u8; 32]; 2];
+    halves[0][..16].copy_from_slice(&key[..16]);
+    halves[1][..16].copy_from_slice(&key[16..]);
+    poseidon(&halves)
+}
+
+fn element(value: u32) -> Felt {
+    let mut bytes = [0u8; 32];
+    bytes[..4].copy_from_slice(&value.to_le_bytes());
+    bytes
+}
+
+/// Little-endian word `index` of `element`
+fn word(element: &Felt, index: usize) -> u32 {
+    let start = 4 * index;
+    u32::from_le_bytes([
+        element[start],
+        element[start + 1],
+        element[start + 2],
+        element[start + 3],
+    ])
+}
+
+/// The next `N` bytes of `reader`, borrowed
+fn read_ref<'a, const N: usize>(reader: &mut Reader<'a>) -> &'a [u8; N] {
+    or_malformed(reader.read_bytes(N))
+        .try_into()
+        .expect("length checked")
+}
+
+/// The next `N` field elements of `reader`, borrowed
+fn read_elements<'a, const N: usize>(reader: &mut Reader<'a>) -> &'a [Felt; N] {
+    or_malformed(reader.read_bytes(32 * N))
+        .as_chunks()
+        .0
+        .try_into()
+        .expect("whole elements")
+}
+
+fn or_malformed<T>(value: Option<T>) -> T {
+    value.unwrap_or_else(|| halt(EXIT_MALFORMED))
+}
@@ -0,0 +1,323 @@
+//! Call ABI
+//!
+//! A call names a contract, a method [`Selector`] and encoded arguments. The
+//! contract's program runs in the zkVM with these guest inputs and output,
+//! each laid out after its region's length word:
+//!
+//! - Public input, [`CallInput`]: the contract address, the contract's
+//!   commitment tree root, the selector, then the argument length as a `u32`
+//!   and the arguments
+//! - Private input, [`CallWitness`]: a `u32` count of input notes, each the
+//!   note's encoding, its position as a `u64`, its authentication path, the
+//!   owner's validating key and signature; then the owner tag for new
+//!   notes, a `u32` count of blindings for output notes and the blindings
+//! - Public output, [`CallOutput`]: a `u32` count of nullifiers and the
+//!   nullifiers, a `u32` count of commitments and the commitments, then the
+//!   return data
+//!
+//! The ledger trusts the program to check its inputs, so a contract must,
+//! for each input note, overwrite the note's address with its own, hash the
+//! note into its commitment with `SYS_POSEIDON`, check the commitment
+//! against the root with `SYS_MERKLE_VERIFY` and publish
+//! `Poseidon(commitment, blinding)` as the nullifier. It must then check
+//! that the key hashes to the note's owner tag and, with
+//! `SYS_SIGNATURE_VERIFY`, that the signature signs
+//! [`CallInput::authorization`]. Output notes must be addressed to the
+//! contract. Exiting with a nonzero code reverts the call.
+
+use privl1_zkvm::field::{self, Felt};
+use privl1_zkvm::precompile::{MERKLE_DEPTH, SIGNATURE_LEN};
+use serde::{Deserialize, Serialize};
+
+use crate::note::{ContractAddress, StateNote, NOTE_LEN};
+use crate::{ContractError, Result};
+
+/// Offset of the selector in the public input
+pub const SELECTOR_OFFSET: usize = 64;
+
+/// Offset of the argument length in the public input
+pub const ARGS_OFFSET: usize = 68;
+
+/// Length of an input note in the private input
+pub const INPUT_NOTE_LEN: usize = NOTE_LEN + 8 + 32 * MERKLE_DEPTH + 32 + SIGNATURE_LEN;
+
+/// Method identifier: the first four bytes of the BLAKE3 hash of the
+/// method's signature, such as `borrow(u32)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Selector(pub [u8; 4]);
+
+impl Selector {
+    pub fn of(signature: &str) -> Self {
+        let hash = blake3::hash(signature.as_bytes());
+        Self(hash.as_bytes()[..4].try_into().expect("in bounds"))
+    }
+
+    /// The selector as the little-endian word a guest loads
+    pub fn to_word(self) -> u32 {
+        u32::from_le_bytes(self.0)
+    }
+}
+
+/// Public input of a call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallInput {
+    pub contract: ContractAddress,
+    /// Root of the commitment tree the input notes are proven against
+    pub root: [u8; 32],
+    pub selector: Selector,
+    pub args: Vec<u8>,
+}
+
+impl CallInput {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ARGS_OFFSET + 4 + self.args.len());
+        bytes.extend_from_slice(self.contract.as_bytes());
+        bytes.extend_from_slice(&self.root);
+        bytes.extend_from_slice(&self.selector.0);
+        bytes.extend_from_slice(&(self.args.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.args);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        let contract = ContractAddress::from_bytes(reader.array()?)
+            .ok_or_else(|| invalid("the contract address is not a field element"))?;
+        let root = reader.array()?;
+        let selector = Selector(reader.array()?);
+        let len = reader.u32()? as usize;
+        let args = reader.bytes(len)?.to_vec();
+        reader.finish()?;
+        Ok(Self {
+            contract,
+            root,
+            selector,
+            args,
+        })
+    }
+
+    /// Message the owner of the note with `nullifier` signs to let this
+    /// call consume it: the nullifier, then the encoded call
+    pub fn authorization(&self, nullifier: &[u8; 32]) -> Vec<u8> {
+        [nullifier.as_slice(), &self.encode()].concat()
+    }
+}
+
+/// A note a call consumes, with its place in the commitment tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputNote {
+    pub note: StateNote,
+    pub position: u64,
+    pub path: [[u8; 32]; MERKLE_DEPTH],
+    /// Validating key of the note's owner
+    pub key: [u8; 32],
+    /// The owner's signature over [`CallInput::authorization`]
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+/// Private input of a call
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallWitness {
+    pub inputs: Vec<InputNote>,
+    /// Owner tag for notes the call creates for the caller
+    pub owner: Felt,
+    /// Blindings for the notes the call produces, used in order
+    pub blindings: Vec<Felt>,
+}
+
+impl CallWitness {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(40 + self.inputs.len() * INPUT_NOTE_LEN + 32 * self.blindings.len());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.note.to_bytes());
+            bytes.extend_from_slice(&input.position.to_le_bytes());
+            bytes.extend(input.path.iter().flatten());
+            bytes.extend_from_slice(&input.key);
+            bytes.extend_from_slice(&input.signature);
+        }
+        bytes.extend_from_slice(&field::to_bytes(&self.owner));
+        bytes.extend_from_slice(&(self.blindings.len() as u32).to_le_bytes());
+        for blinding in &self.blindings {
+            bytes.extend_from_slice(&field::to_bytes(blinding));
+        }
+        bytes
+    }
+}
+
+/// Public output of a call
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallOutput {
+    /// Nullifiers of the consumed notes
+    pub nullifiers: Vec<[u8; 32]>,
+    /// Commitments to the produced notes
+    pub commitments: Vec<[u8; 32]>,
+    pub return_data: Vec<u8>,
+}
+
+impl CallOutput {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for list in [&self.nullifiers, &self.commitments] {
+            bytes.extend_from_slice(&(list.len() as u32).to_le_bytes());
+            bytes.extend(list.iter().flatten());
+        }
+        bytes.extend_from_slice(&self.return_data);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        let nullifiers = reader.list()?;
+        let commitments = reader.list()?;
+        Ok(Self {
+            nullifiers,
+            commitments,
+            return_data: reader.0.to_vec(),
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let (head, rest) = self
+            .0
+            .split_at_checked(len)
+            .ok_or_else(|| invalid("truncated"))?;
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().expect("length checked"))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn list(&mut self) -> Result<Vec<[u8; 32]>> {
+        let count = self.u32()? as usize;
+        let bytes = self.bytes(count.checked_mul(32).ok_or_else(|| invalid("truncated"))?)?;
+        Ok(bytes
+            .chunks(32)
+            .map(|chunk| chunk.try_into().expect("32-byte chunks"))
+            .collect())
+    }
+
+    fn finish(self) -> Result<()> {
+        if !self.0.is_empty() {
+            return Err(invalid("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> ContractError {
+    ContractError::InvalidCall(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> CallInput {
+        CallInput {
+            contract: ContractAddress::derive(&[1; 32], &[0; 32]),
+            root: [7; 32],
+            selector: Selector::of("borrow(u32)"),
+            args: 500u32.to_le_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_selectors() {
+        let selector = Selector::of("borrow(u32)");
+
+        assert_eq!(selector, Selector::of("borrow(u32)"));
+        assert_ne!(selector, Selector::of("repay(u32)"));
+        assert_eq!(selector.to_word().to_le_bytes(), selector.0);
+    }
+
+    #[test]
+    fn test_call_input_layout() {
+        let input = input();
+        let bytes = input.encode();
+
+        assert_eq!(bytes[32..64], [7; 32]);
+        assert_eq!(bytes[SELECTOR_OFFSET..ARGS_OFFSET], input.selector.0);
+        assert_eq!(bytes[ARGS_OFFSET..ARGS_OFFSET + 4], 4u32.to_le_bytes());
+        assert_eq!(bytes[ARGS_OFFSET + 4..], 500u32.to_le_bytes());
+        assert_eq!(CallInput::decode(&bytes).unwrap(), input);
+
+        assert!(CallInput::decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(CallInput::decode(&[bytes.as_slice(), &[0]].concat()).is_err());
+        let mut bytes = bytes;
+        bytes[..32].fill(0xff);
+        assert!(matches!(
+            CallInput::decode(&bytes),
+            Err(ContractError::InvalidCall(_))
+        ));
+    }
+
+    #[test]
+    fn test_call_witness_layout() {
+        let note = StateNote {
+            contract: input().contract,
+            owner: Felt::from(1),
+            state: [Felt::from(2), Felt::from(3)],
+            blinding: Felt::from(4),
+        };
+        let witness = CallWitness {
+            inputs: vec![InputNote {
+                note,
+                position: 9,
+                path: [[5; 32]; MERKLE_DEPTH],
+                key: [6; 32],
+                signature: [7; SIGNATURE_LEN],
+            }],
+            owner: Felt::from(8),
+            blindings: vec![Felt::from(9)],
+        };
+        let bytes = witness.encode();
+        let end = 4 + INPUT_NOTE_LEN;
+
+        assert_eq!(bytes.len(), 40 + INPUT_NOTE_LEN + 32);
+        assert_eq!(bytes[..4], 1u32.to_le_bytes());
+        assert_eq!(bytes[4..4 + NOTE_LEN], note.to_bytes());
+        assert_eq!(bytes[4 + NOTE_LEN..12 + NOTE_LEN], 9u64.to_le_bytes());
+        assert_eq!(bytes[end - 96..end - 64], [6; 32]);
+        assert_eq!(bytes[end - 64..end], [7; SIGNATURE_LEN]);
+        assert_eq!(bytes[end], 8);
+        assert_eq!(bytes[end + 32..end + 36], 1u32.to_le_bytes());
+        assert_eq!(bytes[end + 36], 9);
+    }
+
+    #[test]
+    fn test_authorization_binds_note_and_call() {
+        let input = input();
+        let message = input.authorization(&[3; 32]);
+
+        assert_eq!(message[..32], [3; 32]);
+        assert_eq!(message[32..], input.encode());
+        assert_ne!(message, input.authorization(&[4; 32]));
+    }
+
+    #[test]
+    fn test_call_output_round_trip() {
+        let output = CallOutput {
+            nullifiers: vec![[1; 32]],
+            commitments: vec![[2; 32], [3; 32]],
+            return_data: vec![4, 5],
+        };
+        let bytes = output.encode();
+
+        assert_eq!(bytes.len(), 4 + 32 + 4 + 64 + 2);
+        assert_eq!(CallOutput::decode(&bytes).unwrap(), output);
+        assert!(CallOutput::decode(&bytes[..50]).is_err());
+        assert_eq!(CallOutput::decode(&[0; 8]).unwrap(), CallOutput::default());
+    }
+}
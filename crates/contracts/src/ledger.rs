@@ -0,0 +1,434 @@
+//! Contract ledger
+//!
+//! The public state of the contract system: deployed code and, for each
+//! contract, its note commitment tree with every root it has had and its
+//! revealed nullifiers. [`Ledger::submit`] accepts a call once its zkVM
+//! proof verifies against the callee's program. A call may be proven
+//! against any past root of the callee's tree, so notes stay spendable while
+//! other calls land.
+//!
+//! Each tree is `privl1_crypto`'s [`IncrementalMerkleTree`], the note
+//! commitment tree of the shielded pool, with every leaf marked as it is
+//! appended so the ledger can serve the path of any note.
+//!
+//! Commitments a call produces go to the callee's tree only, and its root
+//! and nullifiers are checked against the callee's, so a contract cannot
+//! mint notes another contract accepts or spend another contract's
+//! nullifiers.
+//!
+//! Value enters a contract through [`Ledger::deposit`], which appends a
+//! value note and counts its amount towards the contract's deposits. It
+//! stands in for a transfer out of the shielded pool, which this crate does
+//! not model.
+
+use std::collections::{HashMap, HashSet};
+
+use privl1_crypto::merkle::IncrementalMerkleTree;
+use privl1_zkvm::precompile::MERKLE_DEPTH;
+use privl1_zkvm::{verify, Program, Proof, Statement};
+
+use crate::abi::{CallInput, CallOutput};
+use crate::note::{ContractAddress, StateNote};
+use crate::{ContractError, Result};
+
+/// Deployed contracts and their notes
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    /// Registered programs by code hash
+    code: HashMap<[u8; 32], Program>,
+    contracts: HashMap<ContractAddress, Contract>,
+}
+
+/// A deployed contract's code and notes
+#[derive(Clone, Debug)]
+struct Contract {
+    code_hash: [u8; 32],
+    tree: IncrementalMerkleTree,
+    roots: HashSet<[u8; 32]>,
+    nullifiers: HashSet<[u8; 32]>,
+    /// Total amount of the value notes deposited
+    deposited: u64,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self {
+            code: HashMap::new(),
+            contracts: HashMap::new(),
+        }
+    }
+
+    /// Register `program` and deploy it at the address derived from its
+    /// code hash and `salt`
+    pub fn deploy(&mut self, program: Program, salt: [u8; 32]) -> Result<ContractAddress> {
+        let code_hash = program.hash();
+        let address = ContractAddress::derive(&code_hash, &salt);
+        if self.contracts.contains_key(&address) {
+            return Err(ContractError::AlreadyDeployed(address));
+        }
+        self.code.entry(code_hash).or_insert(program);
+        let tree = IncrementalMerkleTree::new();
+        self.contracts.insert(
+            address,
+            Contract {
+                code_hash,
+                roots: HashSet::from([*tree.root().as_bytes()]),
+                tree,
+                nullifiers: HashSet::new(),
+                deposited: 0,
+            },
+        );
+        Ok(address)
+    }
+
+    /// Code hash of the contract at `address`
+    pub fn code_hash(&self, address: &ContractAddress) -> Option<[u8; 32]> {
+        self.contracts
+            .get(address)
+            .map(|contract| contract.code_hash)
+    }
+
+    /// Program of the contract at `address`
+    pub fn program(&self, address: &ContractAddress) -> Option<&Program> {
+        self.code.get(&self.contracts.get(address)?.code_hash)
+    }
+
+    /// Commitment tree of the contract at `address`
+    pub fn tree(&self, address: &ContractAddress) -> Option<&IncrementalMerkleTree> {
+        self.contracts.get(address).map(|contract| &contract.tree)
+    }
+
+    /// Current root of the commitment tree of the contract at `address`
+    pub fn root(&self, address: &ContractAddress) -> Option<[u8; 32]> {
+        self.tree(address).map(|tree| *tree.root().as_bytes())
+    }
+
+    /// Authentication path of the note at `position` in the commitment tree
+    /// of the contract at `address`, from the leaf up
+    pub fn path(
+        &self,
+        address: &ContractAddress,
+        position: u64,
+    ) -> Option<[[u8; 32]; MERKLE_DEPTH]> {
+        let proof = self.tree(address)?.prove(position).ok()?;
+        Some(proof.path.try_into().expect("trees are MERKLE_DEPTH deep"))
+    }
+
+    /// Whether the commitment tree of the contract at `address` has had
+    /// `root`
+    pub fn is_known_root(&self, address: &ContractAddress, root: &[u8; 32]) -> bool {
+        self.contracts
+            .get(address)
+            .is_some_and(|contract| contract.roots.contains(root))
+    }
+
+    /// Whether a call to the contract at `address` has revealed `nullifier`
+    pub fn is_spent(&self, address: &ContractAddress, nullifier: &[u8; 32]) -> bool {
+        self.contracts
+            .get(address)
+            .is_some_and(|contract| contract.nullifiers.contains(nullifier))
+    }
+
+    /// Total amount deposited into the contract at `address`
+    pub fn deposited(&self, address: &ContractAddress) -> Option<u64> {
+        self.contracts
+            .get(address)
+            .map(|contract| contract.deposited)
+    }
+
+    /// Append the value note `note` to its contract's tree, returning its
+    /// position
+    pub fn deposit(&mut self, note: &StateNote) -> Result<u64> {
+        let contract = self
+            .contracts
+            .get_mut(&note.contract)
+            .ok_or(ContractError::UnknownContract(note.contract))?;
+        let amount = note
+            .value()
+            .ok_or_else(|| ContractError::InvalidNote("not a value note".into()))?;
+        let position = contract.append(note.commitment())?;
+        contract.deposited += amount as u64;
+        Ok(position)
+    }
+
+    /// Verify `proof` of the call `statement` claims and apply it, returning
+    /// the positions of the produced notes
+    pub fn submit(&mut self, statement: &Statement, proof: &Proof) -> Result<Vec<u64>> {
+        let (contract, output) = self.check(statement)?;
+        let program = self.program(&contract).expect("checked deployed");
+        verify(program, statement, proof)?;
+        self.apply(contract, output)
+    }
+
+    /// Apply a call the host executed itself, without a proof
+    pub(crate) fn submit_unproven(&mut self, statement: &Statement) -> Result<Vec<u64>> {
+        let (contract, output) = self.check(statement)?;
+        self.apply(contract, output)
+    }
+
+    /// Check the claims of `statement` against the ledger
+    fn check(&self, statement: &Statement) -> Result<(ContractAddress, CallOutput)> {
+        let input = CallInput::decode(&statement.public_input)?;
+        let contract = self
+            .contracts
+            .get(&input.contract)
+            .ok_or(ContractError::UnknownContract(input.contract))?;
+        if statement.program_hash != contract.code_hash {
+            return Err(ContractError::InvalidCall(
+                "the statement is for another program".into(),
+            ));
+        }
+        if statement.exit_code != 0 {
+            return Err(ContractError::Reverted(statement.exit_code));
+        }
+        if !contract.roots.contains(&input.root) {
+            return Err(ContractError::UnknownRoot);
+        }
+
+        let output = CallOutput::decode(&statement.public_output)?;
+        for (i, nullifier) in output.nullifiers.iter().enumerate() {
+            if contract.nullifiers.contains(nullifier) || output.nullifiers[..i].contains(nullifier)
+            {
+                return Err(ContractError::DoubleSpend(hex::encode(nullifier)));
+            }
+        }
+        if contract.tree.num_leaves() + output.commitments.len() as u64 > 1 << MERKLE_DEPTH {
+            return Err(ContractError::TreeFull);
+        }
+        Ok((input.contract, output))
+    }
+
+    fn apply(&mut self, address: ContractAddress, output: CallOutput) -> Result<Vec<u64>> {
+        let contract = self.contracts.get_mut(&address).expect("checked deployed");
+        contract.nullifiers.extend(output.nullifiers);
+        let positions = output
+            .commitments
+            .into_iter()
+            .map(|commitment| contract.append(commitment))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(positions)
+    }
+}
+
+impl Contract {
+    /// Append `commitment`, keeping its path, and record the new root
+    fn append(&mut self, commitment: [u8; 32]) -> Result<u64> {
+        self.tree
+            .append(commitment)
+            .map_err(|_| ContractError::TreeFull)?;
+        let position = self.tree.mark().expect("a leaf was just appended");
+        self.roots.insert(*self.tree.root().as_bytes());
+        Ok(position)
+    }
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use privl1_zkvm::field::Felt;
+
+    use crate::abi::Selector;
+
+    fn program() -> Program {
+        Program {
+            entry: 0x1000,
+            segments: Vec::new(),
+        }
+    }
+
+    fn statement(ledger: &Ledger, contract: ContractAddress, output: &CallOutput) -> Statement {
+        let input = CallInput {
+            contract,
+            root: ledger.root(&contract).unwrap_or_default(),
+            selector: Selector::of("run()"),
+            args: Vec::new(),
+        };
+        Statement {
+            program_hash: program().hash(),
+            public_input: input.encode(),
+            public_output: output.encode(),
+            exit_code: 0,
+            gas_used: 100,
+        }
+    }
+
+    #[test]
+    fn test_deploy() {
+        let mut ledger = Ledger::new();
+        let address = ledger.deploy(program(), [0; 32]).unwrap();
+
+        assert_eq!(
+            address,
+            ContractAddress::derive(&program().hash(), &[0; 32])
+        );
+        assert_eq!(ledger.code_hash(&address), Some(program().hash()));
+        assert_eq!(ledger.program(&address), Some(&program()));
+        assert!(matches!(
+            ledger.deploy(program(), [0; 32]),
+            Err(ContractError::AlreadyDeployed(a)) if a == address
+        ));
+
+        let other = ledger.deploy(program(), [1; 32]).unwrap();
+        assert_ne!(other, address);
+        assert_eq!(ledger.code.len(), 1);
+    }
+
+    #[test]
+    fn test_calls_update_tree_and_nullifiers() {
+        let mut ledger = Ledger::new();
+        let contract = ledger.deploy(program(), [0; 32]).unwrap();
+        let first_root = ledger.root(&contract).unwrap();
+
+        let output = CallOutput {
+            nullifiers: vec![[1; 32]],
+            commitments: vec![[2; 32], [3; 32]],
+            return_data: Vec::new(),
+        };
+        let statement = statement(&ledger, contract, &output);
+        assert_eq!(ledger.submit_unproven(&statement).unwrap(), vec![0, 1]);
+        assert!(ledger.is_spent(&contract, &[1; 32]));
+        assert_eq!(ledger.tree(&contract).unwrap().num_leaves(), 2);
+        assert_ne!(ledger.root(&contract), Some(first_root));
+        assert!(ledger.is_known_root(&contract, &first_root));
+
+        assert!(matches!(
+            ledger.submit_unproven(&statement),
+            Err(ContractError::DoubleSpend(_))
+        ));
+        assert_eq!(ledger.tree(&contract).unwrap().num_leaves(), 2);
+    }
+
+    #[test]
+    fn test_contracts_have_separate_notes() {
+        let mut ledger = Ledger::new();
+        let first = ledger.deploy(program(), [0; 32]).unwrap();
+        let second = ledger.deploy(program(), [1; 32]).unwrap();
+        let output = CallOutput {
+            nullifiers: vec![[1; 32]],
+            commitments: vec![[2; 32]],
+            return_data: Vec::new(),
+        };
+
+        ledger
+            .submit_unproven(&statement(&ledger, first, &output))
+            .unwrap();
+        assert!(ledger.is_spent(&first, &[1; 32]));
+        assert!(!ledger.is_spent(&second, &[1; 32]));
+        assert_eq!(ledger.tree(&second).unwrap().num_leaves(), 0);
+
+        // The first contract's root is unknown to the second
+        let mut input =
+            CallInput::decode(&statement(&ledger, second, &output).public_input).unwrap();
+        input.root = ledger.root(&first).unwrap();
+        let foreign_root = Statement {
+            public_input: input.encode(),
+            ..statement(&ledger, second, &output)
+        };
+        assert!(matches!(
+            ledger.check(&foreign_root),
+            Err(ContractError::UnknownRoot)
+        ));
+
+        assert_eq!(
+            ledger
+                .submit_unproven(&statement(&ledger, second, &output))
+                .unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn test_deposits() {
+        let mut ledger = Ledger::new();
+        let contract = ledger.deploy(program(), [0; 32]).unwrap();
+        let note = StateNote::value_note(contract, Felt::from(7), 300, Felt::from(9));
+
+        assert_eq!(ledger.deposit(&note).unwrap(), 0);
+        let other = StateNote::value_note(contract, Felt::from(7), 200, Felt::from(10));
+        assert_eq!(ledger.deposit(&other).unwrap(), 1);
+        assert_eq!(ledger.deposited(&contract), Some(500));
+        assert_eq!(ledger.tree(&contract).unwrap().num_leaves(), 2);
+        assert!(ledger.is_known_root(&contract, &ledger.root(&contract).unwrap()));
+
+        let position = StateNote {
+            state: [Felt::from(2), Felt::from(300)],
+            ..note
+        };
+        assert!(matches!(
+            ledger.deposit(&position),
+            Err(ContractError::InvalidNote(_))
+        ));
+        let elsewhere = StateNote {
+            contract: ContractAddress::derive(&[9; 32], &[0; 32]),
+            ..note
+        };
+        assert!(matches!(
+            ledger.deposit(&elsewhere),
+            Err(ContractError::UnknownContract(_))
+        ));
+        assert_eq!(ledger.deposited(&contract), Some(500));
+    }
+
+    #[test]
+    fn test_invalid_calls_rejected() {
+        let mut ledger = Ledger::new();
+        let contract = ledger.deploy(program(), [0; 32]).unwrap();
+        let output = CallOutput {
+            nullifiers: vec![[1; 32], [1; 32]],
+            ..CallOutput::default()
+        };
+        let valid = statement(&ledger, contract, &CallOutput::default());
+        let check = |statement: Statement| ledger.check(&statement).map(|_| ());
+
+        assert!(check(valid.clone()).is_ok());
+        assert!(matches!(
+            check(statement(&ledger, contract, &output)),
+            Err(ContractError::DoubleSpend(_))
+        ));
+        assert!(matches!(
+            check(statement(
+                &ledger,
+                ContractAddress::derive(&[9; 32], &[0; 32]),
+                &output
+            )),
+            Err(ContractError::UnknownContract(_))
+        ));
+        assert!(matches!(
+            check(Statement {
+                program_hash: [0; 32],
+                ..valid.clone()
+            }),
+            Err(ContractError::InvalidCall(_))
+        ));
+        assert!(matches!(
+            check(Statement {
+                exit_code: 3,
+                ..valid.clone()
+            }),
+            Err(ContractError::Reverted(3))
+        ));
+
+        let mut input = CallInput::decode(&valid.public_input).unwrap();
+        input.root = [5; 32];
+        assert!(matches!(
+            check(Statement {
+                public_input: input.encode(),
+                ..valid.clone()
+            }),
+            Err(ContractError::UnknownRoot)
+        ));
+        assert!(matches!(
+            check(Statement {
+                public_output: vec![1, 0, 0, 0],
+                ..valid
+            }),
+            Err(ContractError::InvalidCall(_))
+        ));
+    }
+}
@@ -0,0 +1,510 @@
+//! Lending example contract
+//!
+//! The contract is the Rust guest in `guests/lending`, whose build is
+//! checked in as `guests/lending/lending.elf`. Its state notes are
+//! `[kind, amounts]`, with the amounts in the low words of the second
+//! element: value notes (kind 1, see
+//! [`StateNote::value_note`](crate::StateNote::value_note)), positions
+//! `[collateral, debt]` (kind 2) owned by a borrower, and a pool of lendable
+//! liquidity (kind 3) with no owner. Every call produces notes worth what it
+//! consumes, so the contract holds what was deposited into it through
+//! [`Ledger::deposit`](crate::Ledger::deposit). A position may borrow up to
+//! half its collateral:
+//!
+//! - `init()` creates an empty pool
+//! - `supply(u32)` moves the amount from a value note into the pool
+//! - `open(u32)` locks the amount of a value note as a new position's
+//!   collateral
+//! - `borrow(u32)` pays the amount from the pool to the position's owner,
+//!   returning the new debt
+//! - `repay(u32)` pays the amount from a value note into the pool,
+//!   returning the new debt
+//! - `withdraw(u32)` pays collateral out to the owner, returning what is
+//!   left
+//! - `close()` pays out the collateral of a position without debt,
+//!   returning it
+//!
+//! The guest exits with 1 for a malformed call, 2 for an input note not in
+//! the commitment tree, 3 when a position would be undercollateralized, 4
+//! when a note is consumed without its owner's signature and 5 when a value
+//! note or the pool does not cover the amount.
+
+use std::sync::OnceLock;
+
+use privl1_zkvm::Program;
+
+pub(crate) const EXIT_MALFORMED: u32 = 1;
+pub(crate) const EXIT_INVALID_NOTE: u32 = 2;
+pub(crate) const EXIT_UNDERCOLLATERALIZED: u32 = 3;
+pub(crate) const EXIT_UNAUTHORIZED: u32 = 4;
+pub(crate) const EXIT_INSUFFICIENT_FUNDS: u32 = 5;
+
+/// The lending contract's program
+pub(crate) fn program() -> Program {
+    static PROGRAM: OnceLock<Program> = OnceLock::new();
+    PROGRAM
+        .get_or_init(|| {
+            Program::from_elf(include_bytes!("../guests/lending/lending.elf"))
+                .expect("valid guest ELF")
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pasta_curves::group::ff::Field;
+    use privl1_crypto::SpendingKey;
+    use privl1_zkvm::field::{self, Felt};
+    use privl1_zkvm::{abi, ResourceLimits};
+
+    use crate::abi::{CallInput, CallWitness, InputNote, Selector};
+    use crate::note::{ContractAddress, StateNote};
+    use crate::runner::{Runner, SpendableNote};
+    use crate::ContractError;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// The amounts of a note: collateral and debt of a position, liquidity
+    /// of the pool, the amount of a value note
+    fn amounts(note: &SpendableNote) -> [u32; 2] {
+        let bytes = field::to_bytes(&note.note.state[1]);
+        [word(&bytes[..4]), word(&bytes[4..8])]
+    }
+
+    fn word(data: &[u8]) -> u32 {
+        u32::from_le_bytes(data.try_into().unwrap())
+    }
+
+    fn amount(value: u32) -> [u8; 4] {
+        value.to_le_bytes()
+    }
+
+    fn key(seed: u8) -> SpendingKey {
+        SpendingKey::from_seed(&[seed; 32])
+    }
+
+    /// Deploy the contract and fill its pool with `liquidity` from a lender
+    fn setup(runner: &mut Runner, liquidity: u32) -> (ContractAddress, SpendableNote) {
+        let lender = key(9);
+        let lending = runner.deploy(program(), [0; 32]).unwrap();
+        let pool = runner
+            .call(lending, "init()", &[], &[], &lender)
+            .unwrap()
+            .outputs[0];
+        let value = runner.deposit(lending, &lender, liquidity).unwrap();
+        let supply = runner
+            .call(
+                lending,
+                "supply(u32)",
+                &amount(liquidity),
+                &[pool, value],
+                &lender,
+            )
+            .unwrap();
+        (lending, supply.outputs[0])
+    }
+
+    /// Open a position for `owner` with `collateral` deposited for it
+    fn open(
+        runner: &mut Runner,
+        lending: ContractAddress,
+        owner: &SpendingKey,
+        collateral: u32,
+    ) -> SpendableNote {
+        let value = runner.deposit(lending, owner, collateral).unwrap();
+        runner
+            .call(lending, "open(u32)", &amount(collateral), &[value], owner)
+            .unwrap()
+            .outputs[0]
+    }
+
+    #[test]
+    fn test_lending_flow() {
+        let mut runner = Runner::new();
+        let lending = runner.deploy(program(), [0; 32]).unwrap();
+        let (alice, bob) = (key(1), key(2));
+        let owner = StateNote::owner_of(&alice.validating_key());
+
+        // Anyone can consume the pool, which has no owner
+        let init = runner.call(lending, "init()", &[], &[], &bob).unwrap();
+        let [pool] = init.outputs[..] else {
+            panic!("one pool")
+        };
+        assert_eq!(pool.note.owner, Felt::ZERO);
+        let lent = runner.deposit(lending, &bob, 1000).unwrap();
+        let supply = runner
+            .call(lending, "supply(u32)", &amount(600), &[pool, lent], &bob)
+            .unwrap();
+        let [pool, bob_change] = supply.outputs[..] else {
+            panic!("pool and change")
+        };
+        assert_eq!(word(&supply.return_data), 600);
+        assert_eq!(amounts(&pool), [600, 0]);
+        assert_eq!(bob_change.note.value(), Some(400));
+
+        // The collateral comes out of a value note
+        let deposit = runner.deposit(lending, &alice, 1000).unwrap();
+        let open = runner
+            .call(lending, "open(u32)", &amount(1000), &[deposit], &alice)
+            .unwrap();
+        let [opened, open_change] = open.outputs[..] else {
+            panic!("position and change")
+        };
+        assert_eq!(opened.note.contract, lending);
+        assert_eq!(opened.note.owner, owner);
+        assert_eq!(amounts(&opened), [1000, 0]);
+        assert_eq!(open_change.note.value(), Some(0));
+        assert!(open.return_data.is_empty());
+
+        // Borrowing pays the owner out of the pool
+        let borrow = runner
+            .call(
+                lending,
+                "borrow(u32)",
+                &amount(400),
+                &[opened, pool],
+                &alice,
+            )
+            .unwrap();
+        let [borrowed, pool, paid] = borrow.outputs[..] else {
+            panic!("position, pool and payment")
+        };
+        assert_eq!(amounts(&borrowed), [1000, 400]);
+        assert_eq!(amounts(&pool), [200, 0]);
+        assert_eq!((paid.note.owner, paid.note.value()), (owner, Some(400)));
+        assert_eq!(word(&borrow.return_data), 400);
+        assert!(runner.ledger().is_spent(&lending, &opened.note.nullifier()));
+
+        // At most half the collateral can be borrowed, and a note is
+        // consumed once
+        assert!(matches!(
+            runner.call(
+                lending,
+                "borrow(u32)",
+                &amount(101),
+                &[borrowed, pool],
+                &alice
+            ),
+            Err(ContractError::Reverted(EXIT_UNDERCOLLATERALIZED))
+        ));
+        assert!(matches!(
+            runner.call(
+                lending,
+                "borrow(u32)",
+                &amount(100),
+                &[opened, pool],
+                &alice
+            ),
+            Err(ContractError::DoubleSpend(_))
+        ));
+        assert!(matches!(
+            runner.call(lending, "withdraw(u32)", &amount(201), &[borrowed], &alice),
+            Err(ContractError::Reverted(EXIT_UNDERCOLLATERALIZED))
+        ));
+        assert!(matches!(
+            runner.call(lending, "close()", &[], &[borrowed], &alice),
+            Err(ContractError::Reverted(EXIT_UNDERCOLLATERALIZED))
+        ));
+
+        let repay = runner
+            .call(
+                lending,
+                "repay(u32)",
+                &amount(400),
+                &[borrowed, pool, paid],
+                &alice,
+            )
+            .unwrap();
+        let [repaid, pool, repay_change] = repay.outputs[..] else {
+            panic!("position, pool and change")
+        };
+        assert_eq!(word(&repay.return_data), 0);
+        assert_eq!(amounts(&pool), [600, 0]);
+        assert_eq!(repay_change.note.value(), Some(0));
+
+        let withdraw = runner
+            .call(lending, "withdraw(u32)", &amount(600), &[repaid], &alice)
+            .unwrap();
+        let [withdrawn, withdrawal] = withdraw.outputs[..] else {
+            panic!("position and payment")
+        };
+        assert_eq!(amounts(&withdrawn), [400, 0]);
+        assert_eq!(withdrawal.note.value(), Some(600));
+        assert_eq!(word(&withdraw.return_data), 400);
+
+        let close = runner
+            .call(lending, "close()", &[], &[withdrawn], &alice)
+            .unwrap();
+        let [payout] = close.outputs[..] else {
+            panic!("one payment")
+        };
+        assert_eq!((payout.note.owner, payout.note.value()), (owner, Some(400)));
+        assert_eq!(word(&close.return_data), 400);
+        assert!(close.proof.is_none());
+
+        // What the contract holds is what was deposited
+        let held: u64 = [bob_change, open_change, repay_change, withdrawal, payout]
+            .iter()
+            .map(|note| note.note.value().unwrap() as u64)
+            .sum::<u64>()
+            + amounts(&pool)[0] as u64;
+        assert_eq!(runner.ledger().deposited(&lending), Some(held));
+        assert_eq!(held, 2000);
+        assert_eq!(runner.ledger().tree(&lending).unwrap().num_leaves(), 16);
+    }
+
+    #[test]
+    fn test_payments_need_funds() {
+        let mut runner = Runner::new();
+        let (lending, pool) = setup(&mut runner, 100);
+        let alice = key(1);
+
+        let small = runner.deposit(lending, &alice, 50).unwrap();
+        assert!(matches!(
+            runner.call(lending, "open(u32)", &amount(51), &[small], &alice),
+            Err(ContractError::Reverted(EXIT_INSUFFICIENT_FUNDS))
+        ));
+        assert!(matches!(
+            runner.call(lending, "supply(u32)", &amount(51), &[pool, small], &alice),
+            Err(ContractError::Reverted(EXIT_INSUFFICIENT_FUNDS))
+        ));
+
+        // The position allows 500, but the pool holds 100
+        let opened = open(&mut runner, lending, &alice, 1000);
+        assert!(matches!(
+            runner.call(
+                lending,
+                "borrow(u32)",
+                &amount(101),
+                &[opened, pool],
+                &alice
+            ),
+            Err(ContractError::Reverted(EXIT_INSUFFICIENT_FUNDS))
+        ));
+        let borrow = runner
+            .call(
+                lending,
+                "borrow(u32)",
+                &amount(100),
+                &[opened, pool],
+                &alice,
+            )
+            .unwrap();
+        let [borrowed, pool, _] = borrow.outputs[..] else {
+            panic!("position, pool and payment")
+        };
+        assert_eq!(amounts(&pool), [0, 0]);
+
+        // Repaying takes a value note covering the amount
+        assert!(matches!(
+            runner.call(
+                lending,
+                "repay(u32)",
+                &amount(60),
+                &[borrowed, pool, small],
+                &alice
+            ),
+            Err(ContractError::Reverted(EXIT_INSUFFICIENT_FUNDS))
+        ));
+        let repay = runner
+            .call(
+                lending,
+                "repay(u32)",
+                &amount(50),
+                &[borrowed, pool, small],
+                &alice,
+            )
+            .unwrap();
+        assert_eq!(word(&repay.return_data), 50);
+        assert_eq!(amounts(&repay.outputs[1]), [50, 0]);
+        assert_eq!(repay.outputs[2].note.value(), Some(0));
+    }
+
+    #[test]
+    fn test_malformed_calls_revert() {
+        let mut runner = Runner::new();
+        let (lending, pool) = setup(&mut runner, 1000);
+        let alice = key(1);
+        let value = runner.deposit(lending, &alice, 1000).unwrap();
+        let opened = open(&mut runner, lending, &alice, 500);
+
+        for (signature, args, inputs) in [
+            ("liquidate(u32)", &[0u8; 4][..], &[opened][..]),
+            ("init()", &[0], &[]),
+            ("borrow(u32)", &[0; 8], &[opened, pool]),
+            ("borrow(u32)", &[0; 4], &[opened]),
+            // Notes of the wrong kind, or in the wrong order
+            ("open(u32)", &[0; 4], &[opened]),
+            ("borrow(u32)", &[0; 4], &[pool, opened]),
+            ("close()", &[], &[value]),
+            // Repaying more than the debt
+            ("repay(u32)", &[1, 0, 0, 0], &[opened, pool, value]),
+        ] {
+            assert!(matches!(
+                runner.call(lending, signature, args, inputs, &alice),
+                Err(ContractError::Reverted(EXIT_MALFORMED))
+            ));
+        }
+
+        // A note not in the tree, claimed at an existing position
+        let mut forged = value;
+        forged.note.state[1] = Felt::from(1_000_000);
+        assert!(matches!(
+            runner.call(lending, "open(u32)", &amount(1000), &[forged], &alice),
+            Err(ContractError::Reverted(EXIT_INVALID_NOTE))
+        ));
+    }
+
+    #[test]
+    fn test_notes_need_owner_signature() {
+        let mut runner = Runner::new();
+        let (lending, pool) = setup(&mut runner, 1000);
+        let (alice, mallory) = (key(1), key(2));
+        let value = runner.deposit(lending, &alice, 1000).unwrap();
+        let opened = open(&mut runner, lending, &alice, 1000);
+
+        // Knowing the opening is not enough to consume a note
+        for (signature, args, inputs) in [
+            ("withdraw(u32)", &amount(1000)[..], &[opened][..]),
+            ("close()", &[], &[opened]),
+            ("borrow(u32)", &amount(100), &[opened, pool]),
+            ("open(u32)", &amount(1000), &[value]),
+        ] {
+            assert!(matches!(
+                runner.call(lending, signature, args, inputs, &mallory),
+                Err(ContractError::Reverted(EXIT_UNAUTHORIZED))
+            ));
+        }
+
+        // Nor is the owner's signature for another call or another note
+        let program = program();
+        let root = runner.ledger().root(&lending).unwrap();
+        let call = |amount: u32| CallInput {
+            contract: lending,
+            root,
+            selector: Selector::of("withdraw(u32)"),
+            args: amount.to_le_bytes().to_vec(),
+        };
+        let run = |signed: &CallInput, nullifier: [u8; 32], called: &CallInput| {
+            let witness = CallWitness {
+                inputs: vec![InputNote {
+                    note: opened.note,
+                    position: opened.position,
+                    path: runner.ledger().path(&lending, opened.position).unwrap(),
+                    key: alice.validating_key().to_bytes(),
+                    signature: alice
+                        .sign(&signed.authorization(&nullifier))
+                        .to_bytes()
+                        .try_into()
+                        .unwrap(),
+                }],
+                owner: Felt::ZERO,
+                blindings: vec![Felt::ONE, Felt::from(2)],
+            };
+            abi::execute(
+                &program,
+                &witness.encode(),
+                &called.encode(),
+                ResourceLimits::default(),
+            )
+            .unwrap()
+            .trace
+            .exit_code
+        };
+        let nullifier = opened.note.nullifier();
+        assert_eq!(run(&call(100), nullifier, &call(100)), 0);
+        assert_eq!(run(&call(100), nullifier, &call(1000)), EXIT_UNAUTHORIZED);
+        assert_eq!(run(&call(100), [0; 32], &call(100)), EXIT_UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_proven_lending_calls() {
+        let mut runner = Runner::new();
+        let (lending, pool) = setup(&mut runner, 500);
+        let alice = key(1);
+        let opened = open(&mut runner, lending, &alice, 1000);
+
+        let mut runner = runner.with_proofs(true);
+        let mut ledger = runner.ledger().clone();
+        let next = ledger.tree(&lending).unwrap().num_leaves();
+        let borrow = runner
+            .call(
+                lending,
+                "borrow(u32)",
+                &amount(500),
+                &[opened, pool],
+                &alice,
+            )
+            .unwrap();
+        assert_eq!(amounts(&borrow.outputs[0]), [1000, 500]);
+        assert_eq!(borrow.outputs[2].note.value(), Some(500));
+        assert!(runner.ledger().is_spent(&lending, &opened.note.nullifier()));
+
+        // The proof is bound to its statement, and a proven call lands once
+        let proof = borrow.proof.unwrap();
+        let mut statement = borrow.statement.clone();
+        statement
+            .public_output
+            .truncate(statement.public_output.len() - 4);
+        assert!(matches!(
+            ledger.submit(&statement, &proof),
+            Err(ContractError::Zkvm(_))
+        ));
+        assert_eq!(
+            ledger.submit(&borrow.statement, &proof).unwrap(),
+            vec![next, next + 1, next + 2]
+        );
+        assert!(matches!(
+            ledger.submit(&borrow.statement, &proof),
+            Err(ContractError::DoubleSpend(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "rebuilds the guest; needs the riscv32im-unknown-none-elf target"]
+    fn test_checked_in_guest_is_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("guests/lending");
+        let built = Program::from_elf(&build_guest(&dir, "privl1-lending-guest")).unwrap();
+        assert_eq!(
+            built.hash(),
+            program().hash(),
+            "lending.elf is stale; rebuild it (see the README)"
+        );
+    }
+
+    /// Rebuild the guest in `dir` as the README does and read its ELF
+    fn build_guest(dir: &Path, package: &str) -> Vec<u8> {
+        let target = "riscv32im-unknown-none-elf";
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .canonicalize()
+            .unwrap();
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(std::env::var_os("HOME").unwrap()).join(".cargo"));
+
+        // The guest's rust-toolchain.toml picks the compiler, and remapping
+        // the source paths keeps the build independent of the checkout
+        let status = Command::new("cargo")
+            .args(["build", "--release", "--target", target])
+            .current_dir(dir)
+            .env_remove("RUSTUP_TOOLCHAIN")
+            .env_remove("CARGO_TARGET_DIR")
+            .env_remove("CARGO_ENCODED_RUSTFLAGS")
+            .env(
+                "RUSTFLAGS",
+                format!(
+                    "--remap-path-prefix={}=/privl1 --remap-path-prefix={}=/cargo",
+                    root.display(),
+                    cargo_home.display()
+                ),
+            )
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let elf = dir.join(format!("target/{}/release/{}", target, package));
+        std::fs::read(elf).unwrap()
+    }
+}
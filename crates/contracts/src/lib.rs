@@ -1 +1,65 @@
 //! PRIVL1 contracts module
+//!
+//! Smart contracts executed by the zkVM:
+//! - Contract addresses derived from a program's code hash, registered on
+//!   deployment
+//! - State held in notes owned by a contract address, published only as
+//!   commitments; a call consumes notes by revealing their nullifiers and
+//!   produces new commitments
+//! - Value notes, which enter a contract only by deposit
+//! - A call ABI of a method selector and encoded arguments, and the guest
+//!   I/O layout contracts implement
+//! - A ledger checking calls against the commitment tree and nullifier set,
+//!   and a host runner executing or proving calls for local testing
+//!
+//! zkVM proofs are zero-knowledge, so a proven call publishes only its
+//! public input, the call and its nullifiers and commitments; see the README
+//! for what remains public.
+
+pub mod abi;
+pub mod ledger;
+pub mod note;
+pub mod runner;
+
+#[cfg(test)]
+mod lending;
+
+pub use abi::{CallInput, CallOutput, CallWitness, InputNote, Selector};
+pub use ledger::Ledger;
+pub use note::{ContractAddress, StateNote, STATE_LEN, VALUE_KIND};
+pub use runner::{Receipt, Runner, SpendableNote};
+
+use privl1_zkvm::ZkvmError;
+
+/// Error type for contract deployment and calls
+#[derive(Debug, thiserror::Error)]
+pub enum ContractError {
+    #[error("No contract deployed at {0}")]
+    UnknownContract(ContractAddress),
+
+    #[error("A contract is already deployed at {0}")]
+    AlreadyDeployed(ContractAddress),
+
+    #[error("Malformed call: {0}")]
+    InvalidCall(String),
+
+    #[error("Call reverted with exit code {0}")]
+    Reverted(u32),
+
+    #[error("Call was executed against an unknown commitment tree root")]
+    UnknownRoot,
+
+    #[error("Nullifier {0} has already been revealed")]
+    DoubleSpend(String),
+
+    #[error("Invalid state note: {0}")]
+    InvalidNote(String),
+
+    #[error("Note commitment tree is full")]
+    TreeFull,
+
+    #[error("zkVM error: {0}")]
+    Zkvm(#[from] ZkvmError),
+}
+
+pub type Result<T> = std::result::Result<T, ContractError>;
@@ -0,0 +1,269 @@
+//! Contract addresses and state notes
+//!
+//! A contract's state is a set of shielded [`StateNote`]s held by its
+//! address, each holding [`STATE_LEN`] field elements for an owner. Only
+//! commitments and nullifiers are published:
+//!
+//! ```text
+//! owner      = Poseidon(ak[..16], ak[16..])
+//! commitment = Poseidon(contract, owner, state[0], state[1], blinding)
+//! nullifier  = Poseidon(commitment, blinding)
+//! ```
+//!
+//! `ak` is the owner's validating key. zkVM proofs keep the inputs of
+//! precompile calls private, so a note's opening stays with its owner; a
+//! call consuming a note carries the owner's signature over the note's
+//! nullifier and the call (see [`crate::abi`]), which binds the
+//! authorization to the exact call.
+//!
+//! Commitments go to a tree of the shielded pool's kind, but the nullifier
+//! is not the pool's `PRF_nk`: it is keyed only by the blinding, which
+//! whoever creates the note chooses and must tell the owner. The creator of
+//! a note can therefore see when it is spent. Contract notes are opened by
+//! the guest program, and owners are tagged by `ak` alone with no nullifier
+//! key to derive from; a keyed nullifier needs one in the owner tag and
+//! rebuilt guests.
+//!
+//! A value note, `[VALUE_KIND, amount]`, holds an amount for its owner.
+//! Value enters a contract only through [`crate::Ledger::deposit`], so a
+//! contract that conserves value across its calls holds what was deposited.
+
+use std::fmt;
+
+use pasta_curves::group::ff::{Field, FromUniformBytes};
+use privl1_crypto::{Point, PoseidonHash};
+use privl1_zkvm::field::{self, Felt};
+use serde::{Deserialize, Serialize};
+
+/// Field elements of state per note
+pub const STATE_LEN: usize = 2;
+
+/// Kind tag in `state[0]` of a value note
+pub const VALUE_KIND: u64 = 1;
+
+/// Length of an encoded note: the address, the owner, the state and the
+/// blinding
+pub const NOTE_LEN: usize = 32 * (STATE_LEN + 3);
+
+/// Address of a deployed contract, a canonical field element
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContractAddress([u8; 32]);
+
+impl ContractAddress {
+    /// Address of the contract with `code_hash` deployed with `salt`
+    pub fn derive(code_hash: &[u8; 32], salt: &[u8; 32]) -> Self {
+        let mut hasher = blake3::Hasher::new_derive_key("privl1-contracts address");
+        hasher.update(code_hash);
+        hasher.update(salt);
+        let mut wide = [0u8; 64];
+        hasher.finalize_xof().fill(&mut wide);
+        Self(field::to_bytes(&Felt::from_uniform_bytes(&wide)))
+    }
+
+    /// Decode an address, or `None` if `bytes` is not a canonical field
+    /// element
+    pub fn from_bytes(bytes: [u8; 32]) -> Option<Self> {
+        field::from_bytes(&bytes).map(|_| Self(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_field(&self) -> Felt {
+        field::from_bytes(&self.0).expect("addresses are canonical")
+    }
+}
+
+impl fmt::Display for ContractAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// A piece of contract state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateNote {
+    /// Holding contract
+    pub contract: ContractAddress,
+    /// Tag of the key authorizing calls that consume the note, see
+    /// [`Self::owner_of`]
+    pub owner: Felt,
+    pub state: [Felt; STATE_LEN],
+    /// Commitment randomness, which also keys the nullifier
+    pub blinding: Felt,
+}
+
+impl StateNote {
+    /// Owner tag of the validating key `key`: the Poseidon hash of its two
+    /// 16-byte halves
+    pub fn owner_of(key: &Point) -> Felt {
+        let bytes = key.to_bytes();
+        let half = |range: std::ops::Range<usize>| {
+            let mut element = [0u8; 32];
+            element[..16].copy_from_slice(&bytes[range]);
+            field::from_bytes(&element).expect("16 bytes are canonical")
+        };
+        PoseidonHash::hash([half(0..16), half(16..32)]).to_field()
+    }
+
+    /// A value note holding `amount` for `owner`
+    pub fn value_note(contract: ContractAddress, owner: Felt, amount: u32, blinding: Felt) -> Self {
+        Self {
+            contract,
+            owner,
+            state: [Felt::from(VALUE_KIND), Felt::from(amount as u64)],
+            blinding,
+        }
+    }
+
+    /// The amount of a value note, or `None` if this is another kind of note
+    pub fn value(&self) -> Option<u32> {
+        if self.state[0] != Felt::from(VALUE_KIND) {
+            return None;
+        }
+        let bytes = field::to_bytes(&self.state[1]);
+        if bytes[4..].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        Some(u32::from_le_bytes(
+            bytes[..4].try_into().expect("in bounds"),
+        ))
+    }
+
+    /// The leaf the note is committed to in the commitment tree
+    pub fn commitment(&self) -> [u8; 32] {
+        let [a, b] = self.state;
+        let hash = PoseidonHash::hash([self.contract.to_field(), self.owner, a, b, self.blinding]);
+        field::to_bytes(&hash.to_field())
+    }
+
+    /// The tag revealed when the note is consumed
+    pub fn nullifier(&self) -> [u8; 32] {
+        let commitment = field::from_bytes(&self.commitment()).expect("hashes are canonical");
+        field::to_bytes(&PoseidonHash::hash([commitment, self.blinding]).to_field())
+    }
+
+    /// Encode as the Poseidon preimage of the commitment
+    pub fn to_bytes(&self) -> [u8; NOTE_LEN] {
+        let mut bytes = [0u8; NOTE_LEN];
+        bytes[..32].copy_from_slice(self.contract.as_bytes());
+        for (chunk, value) in bytes[32..].chunks_mut(32).zip(
+            [&self.owner]
+                .into_iter()
+                .chain(&self.state)
+                .chain([&self.blinding]),
+        ) {
+            chunk.copy_from_slice(&field::to_bytes(value));
+        }
+        bytes
+    }
+
+    /// Decode the encoding of [`Self::to_bytes`], or `None` if an element is
+    /// not canonical
+    pub fn from_bytes(bytes: &[u8; NOTE_LEN]) -> Option<Self> {
+        let element = |i: usize| {
+            field::from_bytes(bytes[32 * i..32 * (i + 1)].try_into().expect("in bounds"))
+        };
+        let mut state = [Felt::ZERO; STATE_LEN];
+        for (i, value) in state.iter_mut().enumerate() {
+            *value = element(i + 2)?;
+        }
+        Some(Self {
+            contract: ContractAddress::from_bytes(bytes[..32].try_into().expect("in bounds"))?,
+            owner: element(1)?,
+            state,
+            blinding: element(STATE_LEN + 2)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use privl1_crypto::SpendingKey;
+
+    fn note() -> StateNote {
+        StateNote {
+            contract: ContractAddress::derive(&[1; 32], &[0; 32]),
+            owner: Felt::from(7),
+            state: [Felt::from(10), Felt::from(20)],
+            blinding: Felt::from(99),
+        }
+    }
+
+    #[test]
+    fn test_address_derivation() {
+        let address = ContractAddress::derive(&[1; 32], &[0; 32]);
+
+        assert_eq!(address, ContractAddress::derive(&[1; 32], &[0; 32]));
+        assert_ne!(address, ContractAddress::derive(&[1; 32], &[1; 32]));
+        assert_ne!(address, ContractAddress::derive(&[2; 32], &[0; 32]));
+        assert_eq!(
+            ContractAddress::from_bytes(*address.as_bytes()),
+            Some(address)
+        );
+        assert_eq!(ContractAddress::from_bytes([0xff; 32]), None);
+        assert_eq!(address.to_string().len(), 64);
+    }
+
+    #[test]
+    fn test_commitment_binds_note() {
+        let note = note();
+        let mut other = note;
+        other.state[1] = Felt::from(21);
+        assert_ne!(note.commitment(), other.commitment());
+
+        let mut other = note;
+        other.owner = Felt::from(8);
+        assert_ne!(note.commitment(), other.commitment());
+
+        let mut other = note;
+        other.contract = ContractAddress::derive(&[1; 32], &[1; 32]);
+        assert_ne!(note.commitment(), other.commitment());
+
+        let mut other = note;
+        other.blinding = Felt::from(98);
+        assert_ne!(note.commitment(), other.commitment());
+        assert_ne!(note.nullifier(), other.nullifier());
+        assert_ne!(note.nullifier(), note.commitment());
+    }
+
+    #[test]
+    fn test_note_encoding() {
+        let note = note();
+        let bytes = note.to_bytes();
+
+        assert_eq!(bytes[..32], *note.contract.as_bytes());
+        assert_eq!(bytes[32], 7);
+        assert_eq!(bytes[64], 10);
+        assert_eq!(bytes[128], 99);
+        assert_eq!(StateNote::from_bytes(&bytes), Some(note));
+
+        let mut bytes = bytes;
+        bytes[64..96].fill(0xff);
+        assert_eq!(StateNote::from_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn test_value_notes() {
+        let contract = note().contract;
+        let value = StateNote::value_note(contract, Felt::from(7), 500, Felt::from(99));
+
+        assert_eq!(value.value(), Some(500));
+        assert_eq!(field::to_bytes(&value.state[1])[..4], 500u32.to_le_bytes());
+        assert_eq!(note().value(), None);
+
+        let mut wide = value;
+        wide.state[1] = Felt::from(1u64 << 32);
+        assert_eq!(wide.value(), None);
+    }
+
+    #[test]
+    fn test_owner_tags() {
+        let key = |seed: u8| SpendingKey::from_seed(&[seed; 32]).validating_key();
+
+        assert_eq!(StateNote::owner_of(&key(1)), StateNote::owner_of(&key(1)));
+        assert_ne!(StateNote::owner_of(&key(1)), StateNote::owner_of(&key(2)));
+    }
+}
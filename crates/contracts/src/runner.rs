@@ -0,0 +1,329 @@
+//! Host runner
+//!
+//! [`Runner`] deploys contracts to a local [`Ledger`] and calls them: it
+//! lays out the guest inputs from the caller's notes, signs each note's
+//! authorization with the caller's key, runs the contract's program and
+//! submits the result. With proofs enabled the run is proven
+//! and the ledger verifies the proof; otherwise the ledger takes the run on
+//! trust, which keeps tests fast in debug builds.
+//!
+//! Produced notes are recovered from the run's `SYS_POSEIDON` calls, whose
+//! inputs are the openings of the output commitments. These are read from
+//! the host's trace, which proofs do not reveal, so only the caller learns
+//! the notes a call produces.
+
+use pasta_curves::group::ff::Field;
+use privl1_crypto::SpendingKey;
+use privl1_zkvm::field::Felt;
+use privl1_zkvm::{abi, prove, Program, Proof, ResourceLimits, Statement};
+
+use crate::abi::{CallInput, CallOutput, CallWitness, InputNote, Selector};
+use crate::ledger::Ledger;
+use crate::note::{ContractAddress, StateNote, NOTE_LEN};
+use crate::{ContractError, Result};
+
+/// Blindings supplied to each call, the most notes it can produce
+pub const MAX_OUTPUTS: usize = 4;
+
+/// A note with its position in the commitment tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendableNote {
+    pub note: StateNote,
+    pub position: u64,
+}
+
+/// Result of a call
+#[derive(Clone, Debug)]
+pub struct Receipt {
+    /// Notes the call produced, in order
+    pub outputs: Vec<SpendableNote>,
+    pub return_data: Vec<u8>,
+    pub gas_used: u64,
+    pub statement: Statement,
+    /// The proof submitted to the ledger, if proofs are enabled
+    pub proof: Option<Proof>,
+}
+
+/// Local contract execution against a [`Ledger`]
+#[derive(Clone, Debug)]
+pub struct Runner {
+    ledger: Ledger,
+    limits: ResourceLimits,
+    prove: bool,
+}
+
+impl Runner {
+    /// Runner over an empty ledger, executing calls without proofs
+    pub fn new() -> Self {
+        Self {
+            ledger: Ledger::new(),
+            limits: ResourceLimits::default(),
+            prove: false,
+        }
+    }
+
+    /// Prove every call and have the ledger verify it
+    pub fn with_proofs(mut self, prove: bool) -> Self {
+        self.prove = prove;
+        self
+    }
+
+    /// Run calls under `limits`
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    /// Deploy `program` with `salt`
+    pub fn deploy(&mut self, program: Program, salt: [u8; 32]) -> Result<ContractAddress> {
+        self.ledger.deploy(program, salt)
+    }
+
+    /// Deposit a value note worth `amount` into `contract` for `owner`
+    pub fn deposit(
+        &mut self,
+        contract: ContractAddress,
+        owner: &SpendingKey,
+        amount: u32,
+    ) -> Result<SpendableNote> {
+        let note = StateNote::value_note(
+            contract,
+            StateNote::owner_of(&owner.validating_key()),
+            amount,
+            Felt::random(&mut rand::thread_rng()),
+        );
+        let position = self.ledger.deposit(&note)?;
+        Ok(SpendableNote { note, position })
+    }
+
+    /// Call the method with `signature` on `contract` with encoded `args`,
+    /// consuming `inputs` owned by `caller`, who also owns notes the call
+    /// creates
+    pub fn call(
+        &mut self,
+        contract: ContractAddress,
+        signature: &str,
+        args: &[u8],
+        inputs: &[SpendableNote],
+        caller: &SpendingKey,
+    ) -> Result<Receipt> {
+        let program = self
+            .ledger
+            .program(&contract)
+            .ok_or(ContractError::UnknownContract(contract))?
+            .clone();
+        let call = CallInput {
+            contract,
+            root: self.ledger.root(&contract).expect("deployed"),
+            selector: Selector::of(signature),
+            args: args.to_vec(),
+        };
+        let key = caller.validating_key().to_bytes();
+        let inputs = inputs
+            .iter()
+            .map(|input| {
+                if input.note.contract != contract {
+                    return Err(ContractError::InvalidNote(format!(
+                        "note is held by {}",
+                        input.note.contract
+                    )));
+                }
+                let path = self.ledger.path(&contract, input.position).ok_or_else(|| {
+                    ContractError::InvalidNote(format!("no note at position {}", input.position))
+                })?;
+                let signature = caller
+                    .sign(&call.authorization(&input.note.nullifier()))
+                    .to_bytes()
+                    .try_into()
+                    .expect("64-byte signatures");
+                Ok(InputNote {
+                    note: input.note,
+                    position: input.position,
+                    path,
+                    key,
+                    signature,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut rng = rand::thread_rng();
+        let witness = CallWitness {
+            inputs,
+            owner: StateNote::owner_of(&caller.validating_key()),
+            blindings: (0..MAX_OUTPUTS).map(|_| Felt::random(&mut rng)).collect(),
+        }
+        .encode();
+        let input = call.encode();
+
+        let run = abi::execute(&program, &witness, &input, self.limits.clone())?;
+        if run.trace.exit_code != 0 {
+            return Err(ContractError::Reverted(run.trace.exit_code));
+        }
+        let output = CallOutput::decode(&run.public_output)?;
+        let notes = output
+            .commitments
+            .iter()
+            .map(|commitment| {
+                let opening = run
+                    .trace
+                    .precompiles
+                    .poseidon
+                    .iter()
+                    .find(|call| call.output == *commitment && call.inputs.len() * 32 == NOTE_LEN)
+                    .and_then(|call| {
+                        StateNote::from_bytes(
+                            &call.inputs.concat().try_into().expect("length checked"),
+                        )
+                    })
+                    .filter(|note| note.contract == contract && note.commitment() == *commitment);
+                opening.ok_or_else(|| {
+                    ContractError::InvalidNote("the run produced a note it did not open".into())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (statement, proof) = if self.prove {
+            let (statement, proof) = prove(&program, &witness, &input, self.limits.clone())?;
+            (statement, Some(proof))
+        } else {
+            let statement = Statement {
+                program_hash: program.hash(),
+                public_input: input,
+                public_output: run.public_output,
+                exit_code: run.trace.exit_code,
+                gas_used: run.trace.gas_used,
+            };
+            (statement, None)
+        };
+        let positions = match &proof {
+            Some(proof) => self.ledger.submit(&statement, proof)?,
+            None => self.ledger.submit_unproven(&statement)?,
+        };
+
+        Ok(Receipt {
+            outputs: notes
+                .into_iter()
+                .zip(positions)
+                .map(|(note, position)| SpendableNote { note, position })
+                .collect(),
+            return_data: output.return_data,
+            gas_used: statement.gas_used,
+            statement,
+            proof,
+        })
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lending;
+    use privl1_crypto::merkle::IncrementalMerkleTree;
+
+    fn caller() -> SpendingKey {
+        SpendingKey::from_seed(&[1; 32])
+    }
+
+    #[test]
+    fn test_deposit() {
+        let mut runner = Runner::new();
+        let program = Program {
+            entry: 0x1000,
+            segments: Vec::new(),
+        };
+        let contract = runner.deploy(program, [0; 32]).unwrap();
+
+        let note = runner.deposit(contract, &caller(), 250).unwrap();
+        assert_eq!(note.position, 0);
+        assert_eq!(note.note.value(), Some(250));
+        assert_eq!(
+            note.note.owner,
+            StateNote::owner_of(&caller().validating_key())
+        );
+        let mut tree = IncrementalMerkleTree::new();
+        tree.append(note.note.commitment()).unwrap();
+        assert_eq!(
+            runner.ledger().root(&contract),
+            Some(*tree.root().as_bytes())
+        );
+        assert_eq!(runner.ledger().deposited(&contract), Some(250));
+    }
+
+    #[test]
+    fn test_call_checks_inputs() {
+        let mut runner = Runner::new();
+        let contract = runner.deploy(lending::program(), [0; 32]).unwrap();
+        let other = runner.deploy(lending::program(), [1; 32]).unwrap();
+        let unknown = ContractAddress::derive(&[0; 32], &[0; 32]);
+        assert!(matches!(
+            runner.call(unknown, "init()", &[], &[], &caller()),
+            Err(ContractError::UnknownContract(a)) if a == unknown
+        ));
+
+        let note = runner
+            .call(contract, "init()", &[], &[], &caller())
+            .unwrap()
+            .outputs[0];
+        assert_eq!(note.position, 0);
+        assert!(matches!(
+            runner.call(other, "close()", &[], &[note], &caller()),
+            Err(ContractError::InvalidNote(_))
+        ));
+        let missing = SpendableNote {
+            position: 1,
+            ..note
+        };
+        assert!(matches!(
+            runner.call(contract, "close()", &[], &[missing], &caller()),
+            Err(ContractError::InvalidNote(_))
+        ));
+    }
+
+    #[test]
+    fn test_receipts() {
+        let mut runner = Runner::new().with_limits(ResourceLimits {
+            gas: 100_000,
+            ..ResourceLimits::default()
+        });
+        let contract = runner.deploy(lending::program(), [0; 32]).unwrap();
+
+        let receipt = runner
+            .call(contract, "init()", &[], &[], &caller())
+            .unwrap();
+        let note = receipt.outputs[0];
+        assert_eq!((note.position, note.note.contract), (0, contract));
+        assert!(receipt.gas_used > 0);
+        assert_eq!(receipt.statement.gas_used, receipt.gas_used);
+        assert_eq!(receipt.statement.program_hash, lending::program().hash());
+        assert_eq!(
+            CallInput::decode(&receipt.statement.public_input)
+                .unwrap()
+                .selector,
+            Selector::of("init()")
+        );
+        assert_eq!(
+            CallOutput::decode(&receipt.statement.public_output)
+                .unwrap()
+                .commitments,
+            vec![note.note.commitment()]
+        );
+
+        let mut runner = runner.with_limits(ResourceLimits {
+            gas: 10,
+            ..ResourceLimits::default()
+        });
+        assert!(matches!(
+            runner.call(contract, "close()", &[], &[note], &caller()),
+            Err(ContractError::Zkvm(_))
+        ));
+    }
+}
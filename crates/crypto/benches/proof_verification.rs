@@ -0,0 +1,181 @@
+//! Block verification benchmarks: sequential vs batched Halo2 verification
+//!
+//! Run with `cargo bench -p privl1-crypto --bench proof_verification`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector,
+};
+use halo2_proofs::poly::Rotation;
+use pasta_curves::pallas;
+use privl1_crypto::commitment::PedersenCommitment;
+use privl1_crypto::halo2::{self, Halo2Params, Halo2VerifyingKey};
+use privl1_crypto::note::Note;
+use privl1_crypto::proof::{
+    BindingSignature, Halo2Proof, OutputProof, ProofVerifier, TransactionProof,
+};
+use privl1_crypto::CryptoError;
+use rand::RngCore;
+
+const BENCH_CIRCUIT: &str = "bench-square-chain";
+const BENCH_K: u32 = 8;
+const CHAIN_LENGTH: usize = 200;
+
+/// Repeated squaring from `x`, with the output circuit's public inputs
+/// `[cm, cv.x, cv.y, asset]`: `x` is `cm` and the rest are exposed as is
+#[derive(Clone, Default)]
+struct SquareChain {
+    x: Value<pallas::Base>,
+    rest: [Value<pallas::Base>; 3],
+}
+
+#[derive(Clone)]
+struct SquareChainConfig {
+    advice: Column<Advice>,
+    instance: Column<Instance>,
+    selector: Selector,
+}
+
+impl Circuit<pallas::Base> for SquareChain {
+    type Config = SquareChainConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> SquareChainConfig {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        let selector = meta.selector();
+        meta.enable_equality(advice);
+        meta.enable_equality(instance);
+
+        meta.create_gate("square", |meta| {
+            let s = meta.query_selector(selector);
+            let x = meta.query_advice(advice, Rotation::cur());
+            let y = meta.query_advice(advice, Rotation::next());
+            vec![s * (x.clone() * x - y)]
+        });
+
+        SquareChainConfig {
+            advice,
+            instance,
+            selector,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: SquareChainConfig,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> Result<(), Error> {
+        let x = layouter.assign_region(
+            || "square chain",
+            |mut region| {
+                let mut value = self.x;
+                let x = region.assign_advice(|| "x", config.advice, 0, || value)?;
+                for row in 0..CHAIN_LENGTH {
+                    config.selector.enable(&mut region, row)?;
+                    value = value.map(|v| v.square());
+                    region.assign_advice(|| "square", config.advice, row + 1, || value)?;
+                }
+                Ok(x)
+            },
+        )?;
+        layouter.constrain_instance(x.cell(), config.instance, 0)?;
+
+        for (row, value) in self.rest.iter().enumerate() {
+            let cell = layouter.assign_region(
+                || "input",
+                |mut region| region.assign_advice(|| "input", config.advice, 0, || *value),
+            )?;
+            layouter.constrain_instance(cell.cell(), config.instance, row + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn build_vk(params: &Halo2Params) -> privl1_crypto::Result<Halo2VerifyingKey> {
+    keygen_vk(params, &SquareChain::default())
+        .map_err(|e| CryptoError::OperationFailed(format!("{:?}", e)))
+}
+
+/// Build a block of single-output transactions and a verifier that knows their key
+fn setup(num_proofs: usize) -> (ProofVerifier, Vec<TransactionProof>) {
+    halo2::register_circuit(BENCH_CIRCUIT, build_vk);
+
+    let params = halo2::params(BENCH_K).unwrap();
+    let vk = build_vk(&params).unwrap();
+    let pk = keygen_pk(&params, vk.clone(), &SquareChain::default()).unwrap();
+    let key = halo2::verification_key(BENCH_CIRCUIT, BENCH_K, &vk);
+
+    let mut rng = rand::rngs::OsRng;
+    let block = (0..num_proofs)
+        .map(|_| {
+            let note = Note::new(rng.next_u64(), [0u8; 32]);
+            let (value_commitment, _) = PedersenCommitment::new().commit(note.value(), &mut rng);
+            let mut output = OutputProof {
+                proof: Halo2Proof::new(vec![], vec![], key.id()),
+                commitment: note.commitment(),
+                value_commitment,
+                circuit_version: 1,
+            };
+            let public_inputs = output.public_inputs();
+            let instances = halo2::decode_instances(&public_inputs).unwrap();
+            let circuit = SquareChain {
+                x: Value::known(instances[0]),
+                rest: [1, 2, 3].map(|i| Value::known(instances[i])),
+            };
+            output.proof.proof =
+                halo2::create_proof(&params, &pk, circuit, &instances, rng).unwrap();
+            output.proof.public_inputs = public_inputs;
+
+            TransactionProof {
+                spend_proofs: vec![],
+                output_proofs: vec![output],
+                binding_sig: BindingSignature {
+                    signature: vec![],
+                    value_balance: 0,
+                },
+            }
+        })
+        .collect();
+
+    let mut verifier = ProofVerifier::new();
+    verifier.register_vk(key);
+    (verifier, block)
+}
+
+fn bench_block_verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block_verification");
+    group.sample_size(10);
+
+    for num_proofs in [8usize, 32, 128] {
+        let (verifier, block) = setup(num_proofs);
+
+        group.bench_with_input(
+            BenchmarkId::new("sequential", num_proofs),
+            &block,
+            |b, block| {
+                b.iter(|| {
+                    for tx in block {
+                        assert!(verifier.verify_transaction(tx).unwrap());
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("batched", num_proofs),
+            &block,
+            |b, block| b.iter(|| assert!(verifier.verify_block(block).is_valid())),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_block_verification);
+criterion_main!(benches);
@@ -0,0 +1,490 @@
+//! Aggregation of Halo2 proofs by IPA accumulation
+//!
+//! Verifying a Halo2 proof over IPA parameters ends with one expensive step:
+//! checking that the final commitment `G` equals `<s(u), G_vec>`, an MSM over
+//! all 2^k generators. Following Halo, aggregation defers that step for
+//! every proof, records each claimed `G_i`, and proves all claims at once by
+//! opening `Σ α^i G_i` at a random point with a single IPA. The accumulator
+//! is checked once.
+//!
+//! A verifier of an aggregated proof therefore:
+//! - Reads each inner transcript (logarithmic work, done in parallel)
+//! - Checks all inner relations and the single opening with one combined MSM
+//! - Pays the linear-time generator MSM once, instead of once per proof
+//!
+//! The aggregated proof still carries every inner proof, so its size and the
+//! transcript work grow with N; constant-size aggregation needs an in-circuit
+//! verifier over the Pasta cycle. Proofs in one aggregate must share a
+//! verifying key (and therefore the same parameters).
+//!
+//! Layout: count (u32 LE) || per proof: len (u32 LE) || proof || G_i
+//! (32 bytes) || the opening transcript.
+
+use ff::Field;
+use halo2_proofs::plonk::{self, VerificationStrategy};
+use halo2_proofs::poly::commitment::{self, Blind, Guard, MSM};
+use halo2_proofs::poly::EvaluationDomain;
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, EncodedChallenge, Transcript};
+use pasta_curves::group::prime::PrimeCurveAffine;
+use pasta_curves::group::GroupEncoding;
+use pasta_curves::{pallas, vesta};
+use rand::{CryptoRng, RngCore};
+use rayon::prelude::*;
+
+use crate::halo2::{read_proof, Halo2Params, Halo2VerifyingKey};
+use crate::hash::{merkle_hash, DomainSeparatedHasher};
+use crate::{CryptoError, Result};
+
+/// Maximum number of proofs in one aggregate
+pub const MAX_AGGREGATED_PROOFS: usize = 4096;
+
+/// A deferred IPA claim: challenges `u` and the claimed `G = <s(u), G_vec>`
+type Claim = (Vec<pallas::Base>, vesta::Affine);
+
+/// Verification strategy that stops before the final generator MSM
+///
+/// With `g` unset the claimed `G` is computed (prover side); with `g` set
+/// the supplied value is used as-is (verifier side).
+struct Accumulate<'params> {
+    msm: MSM<'params, vesta::Affine>,
+    g: Option<vesta::Affine>,
+}
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for Accumulate<'params> {
+    type Output = (MSM<'params, vesta::Affine>, Claim);
+
+    fn process<E: EncodedChallenge<vesta::Affine>>(
+        self,
+        f: impl FnOnce(
+            MSM<'params, vesta::Affine>,
+        ) -> std::result::Result<Guard<'params, vesta::Affine, E>, plonk::Error>,
+    ) -> std::result::Result<Self::Output, plonk::Error> {
+        let guard = f(self.msm)?;
+        let g = self.g.unwrap_or_else(|| guard.compute_g());
+        let (msm, accumulator) = guard.use_g(g);
+
+        let u = accumulator
+            .u_packed
+            .iter()
+            .map(|u_j| *u_j.as_challenge_scalar::<()>())
+            .collect();
+
+        Ok((msm, (u, g)))
+    }
+}
+
+/// Coefficients of `s(X) = Π_j (1 + u_{k-1-j} X^{2^j})`
+fn compute_s(u: &[pallas::Base]) -> Vec<pallas::Base> {
+    let mut s = vec![pallas::Base::zero(); 1 << u.len()];
+    s[0] = pallas::Base::one();
+
+    for (j, u_j) in u.iter().rev().enumerate() {
+        let len = 1 << j;
+        let (left, right) = s.split_at_mut(len);
+        for (r, l) in right[..len].iter_mut().zip(left.iter()) {
+            *r = *l * u_j;
+        }
+    }
+    s
+}
+
+/// Evaluate `s(x)` in O(k)
+fn compute_b(x: pallas::Base, u: &[pallas::Base]) -> pallas::Base {
+    let mut b = pallas::Base::one();
+    let mut cur = x;
+    for u_j in u.iter().rev() {
+        b *= pallas::Base::one() + *u_j * cur;
+        cur = cur.square();
+    }
+    b
+}
+
+/// Aggregate proofs for one circuit of size 2^k into a single proof
+///
+/// Every inner proof is fully verified first; invalid proofs are rejected.
+pub fn aggregate<R: RngCore + CryptoRng>(
+    params: &Halo2Params,
+    vk: &Halo2VerifyingKey,
+    k: u32,
+    proofs: &[(&[u8], &[pallas::Base])],
+    rng: R,
+) -> Result<Vec<u8>> {
+    if proofs.is_empty() || proofs.len() > MAX_AGGREGATED_PROOFS {
+        return Err(CryptoError::OperationFailed(format!(
+            "Cannot aggregate {} proofs",
+            proofs.len()
+        )));
+    }
+
+    let claims: Vec<Claim> = proofs
+        .par_iter()
+        .map(|(proof, instances)| {
+            let (msm, claim) = read_proof(proof, |transcript| {
+                let strategy = Accumulate {
+                    msm: params.empty_msm(),
+                    g: None,
+                };
+                plonk::verify_proof(params, vk, strategy, &[&[*instances]], transcript).ok()
+            })
+            .ok_or(CryptoError::InvalidProof)?;
+
+            if msm.eval() {
+                Ok(claim)
+            } else {
+                Err(CryptoError::InvalidProof)
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(proofs.len() as u32).to_le_bytes());
+    for ((proof, _), (_, g)) in proofs.iter().zip(&claims) {
+        bytes.extend_from_slice(&(proof.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(proof);
+        bytes.extend_from_slice(&g.to_bytes());
+    }
+
+    // Fold all claims into one polynomial and open it at a random point
+    let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+    let (alpha, x) = squeeze_challenges(&mut transcript, &claims)?;
+
+    let domain = EvaluationDomain::<pallas::Base>::new(1, k);
+    let mut poly = domain.empty_coeff();
+    let mut power = pallas::Base::one();
+    for (u, _) in &claims {
+        for (c, s) in poly.iter_mut().zip(compute_s(u)) {
+            *c += power * s;
+        }
+        power *= alpha;
+    }
+
+    commitment::create_proof(
+        params,
+        rng,
+        &mut transcript,
+        &poly,
+        Blind(pallas::Base::zero()),
+        x,
+    )
+    .map_err(|e| CryptoError::OperationFailed(format!("Aggregation failed: {}", e)))?;
+    bytes.extend_from_slice(&transcript.finalize());
+
+    Ok(bytes)
+}
+
+/// Verify an aggregated proof against the public inputs of each inner proof
+pub fn verify_aggregate(
+    params: &Halo2Params,
+    vk: &Halo2VerifyingKey,
+    aggregate: &[u8],
+    instances: &[Vec<pallas::Base>],
+) -> bool {
+    let Some((inner, opening)) = parse_aggregate(aggregate) else {
+        return false;
+    };
+    if inner.len() != instances.len() {
+        return false;
+    }
+
+    let checked: Option<Vec<(MSM<'_, vesta::Affine>, Claim)>> = inner
+        .par_iter()
+        .zip(instances.par_iter())
+        .map(|((proof, g), instances)| {
+            read_proof(proof, |transcript| {
+                let strategy = Accumulate {
+                    msm: params.empty_msm(),
+                    g: Some(*g),
+                };
+                plonk::verify_proof(params, vk, strategy, &[&[instances.as_slice()]], transcript)
+                    .ok()
+            })
+        })
+        .collect();
+    let Some(checked) = checked else {
+        return false;
+    };
+
+    // Combine all inner relations under random weights
+    let mut rng = rand::rngs::OsRng;
+    let mut acc = params.empty_msm();
+    let mut claims = Vec::with_capacity(checked.len());
+    for (mut msm, claim) in checked {
+        msm.scale(pallas::Base::random(&mut rng));
+        acc.add_msm(&msm);
+        claims.push(claim);
+    }
+
+    let guard = read_proof(opening, |transcript| {
+        let (alpha, x) = squeeze_challenges(transcript, &claims).ok()?;
+
+        let mut p_msm = params.empty_msm();
+        let mut v = pallas::Base::zero();
+        let mut power = pallas::Base::one();
+        for (u, g) in &claims {
+            p_msm.append_term(power, *g);
+            v += power * compute_b(x, u);
+            power *= alpha;
+        }
+
+        commitment::verify_proof(params, p_msm, transcript, x, v).ok()
+    });
+    let Some(guard) = guard else {
+        return false;
+    };
+
+    // The one linear-time MSM over the generators
+    let mut msm = guard.use_challenges();
+    msm.scale(pallas::Base::random(&mut rng));
+    acc.add_msm(&msm);
+
+    acc.eval()
+}
+
+/// Merkle root over the public inputs of each aggregated proof
+///
+/// Leaves hash one proof's inputs; the tree is padded with zero leaves to a
+/// power of two.
+pub fn aggregation_root<I: AsRef<[Vec<u8>]>>(public_inputs: &[I]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = public_inputs
+        .iter()
+        .map(|inputs| {
+            let mut hasher = DomainSeparatedHasher::new("PRIVL1_AGGREGATION_LEAF");
+            for input in inputs.as_ref() {
+                hasher.update(&(input.len() as u32).to_le_bytes());
+                hasher.update(input);
+            }
+            *hasher.finalize().as_bytes()
+        })
+        .collect();
+
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    level.resize(level.len().next_power_of_two(), [0u8; 32]);
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| merkle_hash(&pair[0], &pair[1]))
+            .collect();
+    }
+    level[0]
+}
+
+/// Bind every claim and squeeze the folding and evaluation challenges
+///
+/// Each claim's `u_i` are the inner proof's IPA challenges, which its
+/// transcript derived from the verifying key, public inputs and every proof
+/// element. Absorbing them with `G_i` makes `alpha` and `x` depend on the
+/// whole statement, not only on the claimed commitments.
+fn squeeze_challenges<T: Transcript<vesta::Affine, Challenge255<vesta::Affine>>>(
+    transcript: &mut T,
+    claims: &[Claim],
+) -> Result<(pallas::Base, pallas::Base)> {
+    let absorb_failed =
+        |e: std::io::Error| CryptoError::OperationFailed(format!("Transcript: {}", e));
+    for (u, g) in claims {
+        for u_j in u {
+            transcript.common_scalar(*u_j).map_err(absorb_failed)?;
+        }
+        transcript.common_point(*g).map_err(absorb_failed)?;
+    }
+
+    let alpha = *transcript.squeeze_challenge_scalar::<()>();
+    let x = *transcript.squeeze_challenge_scalar::<()>();
+    Ok((alpha, x))
+}
+
+/// Inner proofs with their claimed `G`, and the opening transcript
+type Parsed<'a> = (Vec<(&'a [u8], vesta::Affine)>, &'a [u8]);
+
+/// Split an aggregated proof into (inner proof, claimed G) pairs and the
+/// opening
+fn parse_aggregate(bytes: &[u8]) -> Option<Parsed<'_>> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let chunk: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        Some(u32::from_le_bytes(chunk) as usize)
+    };
+
+    let count = read_u32(0)?;
+    if count == 0 || count > MAX_AGGREGATED_PROOFS {
+        return None;
+    }
+
+    let mut offset = 4;
+    let mut inner = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = read_u32(offset)?;
+        offset += 4;
+        let proof = bytes.get(offset..offset.checked_add(len)?)?;
+        offset += len;
+
+        let g_bytes: [u8; 32] = bytes.get(offset..offset + 32)?.try_into().ok()?;
+        let g: vesta::Affine = Option::from(vesta::Affine::from_bytes(&g_bytes))?;
+        if bool::from(g.is_identity()) {
+            return None;
+        }
+        offset += 32;
+
+        inner.push((proof, g));
+    }
+
+    Some((inner, &bytes[offset..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::halo2::tests::{square_proof, TEST_K};
+    use crate::halo2::{encode_instance, load_verifying_key};
+
+    fn square_proofs(
+        n: u64,
+    ) -> (
+        Vec<Vec<u8>>,
+        Vec<Vec<pallas::Base>>,
+        crate::proof::VerificationKey,
+    ) {
+        let mut vk = None;
+        let mut proofs = Vec::new();
+        let mut instances = Vec::new();
+        for x in 1..=n {
+            let (key, proof, y) = square_proof(x);
+            vk = Some(key);
+            proofs.push(proof);
+            instances.push(vec![y]);
+        }
+        (proofs, instances, vk.unwrap())
+    }
+
+    fn inputs<'a>(
+        proofs: &'a [Vec<u8>],
+        instances: &'a [Vec<pallas::Base>],
+    ) -> Vec<(&'a [u8], &'a [pallas::Base])> {
+        proofs
+            .iter()
+            .zip(instances)
+            .map(|(p, i)| (p.as_slice(), i.as_slice()))
+            .collect()
+    }
+
+    #[test]
+    fn test_compute_b_matches_s() {
+        let mut rng = rand::rngs::OsRng;
+        let u: Vec<_> = (0..4).map(|_| pallas::Base::random(&mut rng)).collect();
+        let x = pallas::Base::random(&mut rng);
+
+        let s = compute_s(&u);
+        let eval = s
+            .iter()
+            .rev()
+            .fold(pallas::Base::zero(), |acc, c| acc * x + c);
+        assert_eq!(eval, compute_b(x, &u));
+    }
+
+    #[test]
+    fn test_aggregate_and_verify() {
+        let (proofs, instances, vk) = square_proofs(5);
+        let (params, halo2_vk) = load_verifying_key(&vk).unwrap();
+
+        let aggregated = aggregate(
+            &params,
+            &halo2_vk,
+            TEST_K,
+            &inputs(&proofs, &instances),
+            rand::rngs::OsRng,
+        )
+        .unwrap();
+        assert!(verify_aggregate(
+            &params,
+            &halo2_vk,
+            &aggregated,
+            &instances
+        ));
+
+        // Wrong public input
+        let mut wrong = instances.clone();
+        wrong[3][0] += pallas::Base::one();
+        assert!(!verify_aggregate(&params, &halo2_vk, &aggregated, &wrong));
+
+        // Wrong number of proofs
+        assert!(!verify_aggregate(
+            &params,
+            &halo2_vk,
+            &aggregated,
+            &instances[..4]
+        ));
+
+        // Corrupted opening
+        let mut corrupted = aggregated.clone();
+        let last = corrupted.len() - 5;
+        corrupted[last] ^= 1;
+        assert!(!verify_aggregate(
+            &params, &halo2_vk, &corrupted, &instances
+        ));
+
+        // Bytes after the opening
+        let mut padded = aggregated.clone();
+        padded.push(0);
+        assert!(!verify_aggregate(&params, &halo2_vk, &padded, &instances));
+    }
+
+    #[test]
+    fn test_aggregate_rejects_invalid_proof() {
+        let (proofs, mut instances, vk) = square_proofs(3);
+        let (params, halo2_vk) = load_verifying_key(&vk).unwrap();
+
+        instances[1][0] += pallas::Base::one();
+        assert!(aggregate(
+            &params,
+            &halo2_vk,
+            TEST_K,
+            &inputs(&proofs, &instances),
+            rand::rngs::OsRng
+        )
+        .is_err());
+        assert!(aggregate(&params, &halo2_vk, TEST_K, &[], rand::rngs::OsRng).is_err());
+    }
+
+    #[test]
+    fn test_challenges_bind_inner_challenges() {
+        let squeeze = |u: pallas::Base| {
+            let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+            squeeze_challenges(&mut transcript, &[(vec![u], vesta::Affine::generator())]).unwrap()
+        };
+        // Same claimed G, different inner proof
+        assert_ne!(squeeze(pallas::Base::one()), squeeze(pallas::Base::from(2)));
+    }
+
+    #[test]
+    fn test_swapped_claim_fails() {
+        let (proofs, instances, vk) = square_proofs(2);
+        let (params, halo2_vk) = load_verifying_key(&vk).unwrap();
+        let aggregated = aggregate(
+            &params,
+            &halo2_vk,
+            TEST_K,
+            &inputs(&proofs, &instances),
+            rand::rngs::OsRng,
+        )
+        .unwrap();
+
+        // Replace the first claimed G with the generator
+        let offset = 4 + 4 + proofs[0].len();
+        let mut tampered = aggregated.clone();
+        tampered[offset..offset + 32].copy_from_slice(&vesta::Affine::generator().to_bytes());
+        assert!(!verify_aggregate(&params, &halo2_vk, &tampered, &instances));
+    }
+
+    #[test]
+    fn test_aggregation_root() {
+        let a = vec![encode_instance(&pallas::Base::from(1u64))];
+        let b = vec![encode_instance(&pallas::Base::from(2u64))];
+
+        let root = aggregation_root(&[a.clone(), b.clone()]);
+        assert_eq!(root, aggregation_root(&[a.clone(), b.clone()]));
+        assert_ne!(root, aggregation_root(&[b.clone(), a.clone()]));
+        assert_ne!(root, aggregation_root(&[a.clone(), b, a]));
+        assert_eq!(aggregation_root::<Vec<Vec<u8>>>(&[]), [0u8; 32]);
+    }
+}
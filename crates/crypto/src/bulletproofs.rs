@@ -0,0 +1,757 @@
+//! Bulletproofs range proofs over Pallas
+//!
+//! This module implements aggregated Bulletproofs range proofs (Bünz et al. 2018)
+//! against the `PedersenCommitment` generators. A proof shows that each of `m`
+//! committed values lies in `[0, 2^n)` and is `O(log(n·m))` group elements long.
+//!
+//! Range proofs are needed outside of Halo2 for transparent-to-shielded bridging
+//! and confidential AMM reserves.
+
+use std::sync::OnceLock;
+
+use ff::{BatchInvert, Field, FromUniformBytes, PrimeField};
+use group::prime::PrimeCurveAffine;
+use group::{Curve, Group, GroupEncoding};
+use halo2_proofs::arithmetic::best_multiexp;
+use pasta_curves::arithmetic::CurveExt;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+
+use crate::commitment::{Commitment, PedersenCommitment};
+use crate::{CryptoError, Result, Scalar};
+
+/// Supported range sizes in bits
+pub const SUPPORTED_RANGE_BITS: [usize; 4] = [8, 16, 32, 64];
+
+/// Maximum number of values in one aggregated proof
+pub const MAX_AGGREGATION: usize = 16;
+
+/// Transcript domain separator
+const TRANSCRIPT_DOMAIN: &str = "PRIVL1_BULLETPROOFS_V1";
+
+/// Vector generators `G_i`, `H_i` shared by all range proofs
+struct BulletproofGens {
+    g: Vec<pallas::Affine>,
+    h: Vec<pallas::Affine>,
+}
+
+fn bulletproof_gens() -> &'static BulletproofGens {
+    static GENS: OnceLock<BulletproofGens> = OnceLock::new();
+
+    GENS.get_or_init(|| {
+        let hasher = pallas::Point::hash_to_curve("PRIVL1_BULLETPROOFS");
+        let capacity = 64 * MAX_AGGREGATION;
+
+        let derive = |label: &[u8]| -> Vec<pallas::Affine> {
+            let points: Vec<pallas::Point> = (0..capacity as u32)
+                .map(|i| hasher(&[label, &i.to_le_bytes()[..]].concat()))
+                .collect();
+            let mut affine = vec![pallas::Affine::identity(); capacity];
+            pallas::Point::batch_normalize(&points, &mut affine);
+            affine
+        };
+
+        BulletproofGens {
+            g: derive(b"G"),
+            h: derive(b"H"),
+        }
+    })
+}
+
+/// Fiat-Shamir transcript backed by Blake3
+#[derive(Clone)]
+struct Transcript {
+    hasher: blake3::Hasher,
+}
+
+impl Transcript {
+    fn new() -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(TRANSCRIPT_DOMAIN.as_bytes());
+        hasher.update(&[0u8]);
+        Self { hasher }
+    }
+
+    fn append(&mut self, label: &'static [u8], data: &[u8]) {
+        self.hasher.update(label);
+        self.hasher.update(&(data.len() as u64).to_le_bytes());
+        self.hasher.update(data);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &pallas::Point) {
+        self.append(label, &point.to_bytes());
+    }
+
+    fn append_scalar(&mut self, label: &'static [u8], scalar: &pallas::Scalar) {
+        self.append(label, &scalar.to_repr());
+    }
+
+    fn challenge(&mut self, label: &'static [u8]) -> pallas::Scalar {
+        self.hasher.update(label);
+
+        let mut wide = [0u8; 64];
+        self.hasher.clone().finalize_xof().fill(&mut wide);
+        let challenge = pallas::Scalar::from_uniform_bytes(&wide);
+
+        // Feed the challenge back so later challenges depend on it
+        self.append_scalar(b"challenge", &challenge);
+        challenge
+    }
+}
+
+/// A decoded range proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RangeProof {
+    a: pallas::Point,
+    s: pallas::Point,
+    t1: pallas::Point,
+    t2: pallas::Point,
+    tau_x: pallas::Scalar,
+    mu: pallas::Scalar,
+    t_hat: pallas::Scalar,
+    l_vec: Vec<pallas::Point>,
+    r_vec: Vec<pallas::Point>,
+    ipp_a: pallas::Scalar,
+    ipp_b: pallas::Scalar,
+}
+
+impl RangeProof {
+    /// Serialize: A || S || T1 || T2 || tau_x || mu || t_hat || (L_i || R_i)* || a || b
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 * (9 + 2 * self.l_vec.len()));
+        for point in [&self.a, &self.s, &self.t1, &self.t2] {
+            bytes.extend_from_slice(&point.to_bytes());
+        }
+        for scalar in [&self.tau_x, &self.mu, &self.t_hat] {
+            bytes.extend_from_slice(&scalar.to_repr());
+        }
+        for (l, r) in self.l_vec.iter().zip(&self.r_vec) {
+            bytes.extend_from_slice(&l.to_bytes());
+            bytes.extend_from_slice(&r.to_bytes());
+        }
+        bytes.extend_from_slice(&self.ipp_a.to_repr());
+        bytes.extend_from_slice(&self.ipp_b.to_repr());
+        bytes
+    }
+
+    /// Deserialize, rejecting non-canonical encodings
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !bytes.len().is_multiple_of(32)
+            || bytes.len() < 32 * 9
+            || !(bytes.len() / 32 - 9).is_multiple_of(2)
+        {
+            return Err(CryptoError::SerializationError(
+                "Invalid range proof length".into(),
+            ));
+        }
+
+        let chunks: Vec<&[u8]> = bytes.chunks(32).collect();
+        let rounds = (chunks.len() - 9) / 2;
+        if rounds > 32 {
+            return Err(CryptoError::SerializationError(
+                "Too many IPA rounds".into(),
+            ));
+        }
+
+        let point = |chunk: &[u8]| -> Result<pallas::Point> {
+            let mut repr = [0u8; 32];
+            repr.copy_from_slice(chunk);
+            Option::from(pallas::Point::from_bytes(&repr)).ok_or(CryptoError::InvalidProof)
+        };
+        let scalar = |chunk: &[u8]| -> Result<pallas::Scalar> {
+            let mut repr = [0u8; 32];
+            repr.copy_from_slice(chunk);
+            Option::from(pallas::Scalar::from_repr(repr)).ok_or(CryptoError::InvalidProof)
+        };
+
+        let mut l_vec = Vec::with_capacity(rounds);
+        let mut r_vec = Vec::with_capacity(rounds);
+        for i in 0..rounds {
+            l_vec.push(point(chunks[7 + 2 * i])?);
+            r_vec.push(point(chunks[8 + 2 * i])?);
+        }
+
+        Ok(Self {
+            a: point(chunks[0])?,
+            s: point(chunks[1])?,
+            t1: point(chunks[2])?,
+            t2: point(chunks[3])?,
+            tau_x: scalar(chunks[4])?,
+            mu: scalar(chunks[5])?,
+            t_hat: scalar(chunks[6])?,
+            l_vec,
+            r_vec,
+            ipp_a: scalar(chunks[chunks.len() - 2])?,
+            ipp_b: scalar(chunks[chunks.len() - 1])?,
+        })
+    }
+}
+
+/// Check that `n` bits and `m` values form a valid proof shape
+fn check_shape(n: usize, m: usize) -> Result<()> {
+    if !SUPPORTED_RANGE_BITS.contains(&n) {
+        return Err(CryptoError::OperationFailed(format!(
+            "Unsupported range size: {} bits",
+            n
+        )));
+    }
+    if m == 0 || m > MAX_AGGREGATION || !m.is_power_of_two() {
+        return Err(CryptoError::OperationFailed(format!(
+            "Aggregation size must be a power of two up to {}, got {}",
+            MAX_AGGREGATION, m
+        )));
+    }
+    Ok(())
+}
+
+/// Prove that each value lies in `[0, 2^n)`
+///
+/// Returns the proof and the commitments `v_j·G + γ_j·H` it is bound to.
+pub(crate) fn prove<R: RngCore + CryptoRng>(
+    values: &[u64],
+    blindings: &[Scalar],
+    n: usize,
+    rng: &mut R,
+) -> Result<(RangeProof, Vec<Commitment>)> {
+    let m = values.len();
+    check_shape(n, m)?;
+    if blindings.len() != m {
+        return Err(CryptoError::OperationFailed(
+            "One blinding factor per value required".into(),
+        ));
+    }
+    if n < 64 && values.iter().any(|v| v >> n != 0) {
+        return Err(CryptoError::OperationFailed("Value out of range".into()));
+    }
+
+    let nm = n * m;
+    let pedersen = PedersenCommitment::new();
+    let b = *pedersen.value_generator().inner();
+    let b_blinding = *pedersen.blinding_generator().inner();
+    let gens = bulletproof_gens();
+    let (g, h) = (&gens.g[..nm], &gens.h[..nm]);
+
+    let commitments: Vec<Commitment> = values
+        .iter()
+        .zip(blindings)
+        .map(|(v, gamma)| pedersen.commit_with_blinding(*v, *gamma))
+        .collect();
+
+    let mut transcript = Transcript::new();
+    transcript.append(b"n", &(n as u64).to_le_bytes());
+    transcript.append(b"m", &(m as u64).to_le_bytes());
+    for commitment in &commitments {
+        transcript.append_point(b"V", commitment.as_point().inner());
+    }
+
+    // Bit decomposition: a_L ∈ {0,1}^nm, a_R = a_L - 1
+    let a_l: Vec<pallas::Scalar> = values
+        .iter()
+        .flat_map(|v| (0..n).map(move |i| pallas::Scalar::from((v >> i) & 1)))
+        .collect();
+    let a_r: Vec<pallas::Scalar> = a_l.iter().map(|bit| *bit - pallas::Scalar::ONE).collect();
+
+    let alpha = pallas::Scalar::random(&mut *rng);
+    let a = commit_vectors(alpha, b_blinding, &a_l, g, &a_r, h);
+
+    let s_l: Vec<pallas::Scalar> = (0..nm).map(|_| pallas::Scalar::random(&mut *rng)).collect();
+    let s_r: Vec<pallas::Scalar> = (0..nm).map(|_| pallas::Scalar::random(&mut *rng)).collect();
+    let rho = pallas::Scalar::random(&mut *rng);
+    let s = commit_vectors(rho, b_blinding, &s_l, g, &s_r, h);
+
+    transcript.append_point(b"A", &a);
+    transcript.append_point(b"S", &s);
+    let y = transcript.challenge(b"y");
+    let z = transcript.challenge(b"z");
+
+    // l(X) = (a_L - z) + s_L·X
+    // r(X) = y^nm ∘ (a_R + z + s_R·X) + Σ_j z^(2+j)·(0 || 2^n || 0)
+    let y_pows = powers(y, nm);
+    let z_pows = powers(z, m + 3);
+    let two_pows = powers(pallas::Scalar::from(2), n);
+
+    let l0: Vec<pallas::Scalar> = a_l.iter().map(|bit| *bit - z).collect();
+    let l1 = s_l;
+    let r0: Vec<pallas::Scalar> = (0..nm)
+        .map(|i| y_pows[i] * (a_r[i] + z) + z_pows[2 + i / n] * two_pows[i % n])
+        .collect();
+    let r1: Vec<pallas::Scalar> = (0..nm).map(|i| y_pows[i] * s_r[i]).collect();
+
+    let t1_coeff = inner_product(&l0, &r1) + inner_product(&l1, &r0);
+    let t2_coeff = inner_product(&l1, &r1);
+
+    let tau1 = pallas::Scalar::random(&mut *rng);
+    let tau2 = pallas::Scalar::random(&mut *rng);
+    let t1 = b * t1_coeff + b_blinding * tau1;
+    let t2 = b * t2_coeff + b_blinding * tau2;
+
+    transcript.append_point(b"T1", &t1);
+    transcript.append_point(b"T2", &t2);
+    let x = transcript.challenge(b"x");
+
+    let tau_x = tau2 * x.square()
+        + tau1 * x
+        + blindings
+            .iter()
+            .enumerate()
+            .fold(pallas::Scalar::ZERO, |acc, (j, gamma)| {
+                acc + z_pows[2 + j] * gamma.inner()
+            });
+    let mu = alpha + rho * x;
+
+    let l: Vec<pallas::Scalar> = l0.iter().zip(&l1).map(|(l0, l1)| *l0 + *l1 * x).collect();
+    let r: Vec<pallas::Scalar> = r0.iter().zip(&r1).map(|(r0, r1)| *r0 + *r1 * x).collect();
+    let t_hat = inner_product(&l, &r);
+
+    transcript.append_scalar(b"tau_x", &tau_x);
+    transcript.append_scalar(b"mu", &mu);
+    transcript.append_scalar(b"t_hat", &t_hat);
+    let w = transcript.challenge(b"w");
+    let q = b * w;
+
+    // H'_i = y^-i · H_i
+    let y_inv: pallas::Scalar = Option::from(y.invert()).ok_or(CryptoError::InvalidProof)?;
+    let h_prime: Vec<pallas::Point> = h
+        .iter()
+        .zip(powers(y_inv, nm))
+        .map(|(h_i, y_inv_i)| pallas::Point::from(*h_i) * y_inv_i)
+        .collect();
+    let g_proj: Vec<pallas::Point> = g.iter().map(|g_i| pallas::Point::from(*g_i)).collect();
+
+    let (l_vec, r_vec, ipp_a, ipp_b) =
+        inner_product_prove(&mut transcript, q, g_proj, h_prime, l, r)?;
+
+    let proof = RangeProof {
+        a,
+        s,
+        t1,
+        t2,
+        tau_x,
+        mu,
+        t_hat,
+        l_vec,
+        r_vec,
+        ipp_a,
+        ipp_b,
+    };
+
+    Ok((proof, commitments))
+}
+
+/// Inner-product argument: proves `P = <a,G> + <b,H> + <a,b>·Q`
+fn inner_product_prove(
+    transcript: &mut Transcript,
+    q: pallas::Point,
+    mut g: Vec<pallas::Point>,
+    mut h: Vec<pallas::Point>,
+    mut a: Vec<pallas::Scalar>,
+    mut b: Vec<pallas::Scalar>,
+) -> Result<(
+    Vec<pallas::Point>,
+    Vec<pallas::Point>,
+    pallas::Scalar,
+    pallas::Scalar,
+)> {
+    let mut l_vec = Vec::new();
+    let mut r_vec = Vec::new();
+
+    while a.len() > 1 {
+        let k = a.len() / 2;
+        let (a_lo, a_hi) = a.split_at(k);
+        let (b_lo, b_hi) = b.split_at(k);
+        let (g_lo, g_hi) = g.split_at(k);
+        let (h_lo, h_hi) = h.split_at(k);
+
+        let c_l = inner_product(a_lo, b_hi);
+        let c_r = inner_product(a_hi, b_lo);
+
+        let l = msm_projective(
+            &[a_lo, b_hi, &[c_l][..]].concat(),
+            &[g_hi, h_lo, &[q][..]].concat(),
+        );
+        let r = msm_projective(
+            &[a_hi, b_lo, &[c_r][..]].concat(),
+            &[g_lo, h_hi, &[q][..]].concat(),
+        );
+
+        transcript.append_point(b"L", &l);
+        transcript.append_point(b"R", &r);
+        let u = transcript.challenge(b"u");
+        let u_inv: pallas::Scalar = Option::from(u.invert()).ok_or(CryptoError::InvalidProof)?;
+
+        let a_next = (0..k).map(|i| a_lo[i] * u + a_hi[i] * u_inv).collect();
+        let b_next = (0..k).map(|i| b_lo[i] * u_inv + b_hi[i] * u).collect();
+        let g_next = (0..k).map(|i| g_lo[i] * u_inv + g_hi[i] * u).collect();
+        let h_next = (0..k).map(|i| h_lo[i] * u + h_hi[i] * u_inv).collect();
+
+        a = a_next;
+        b = b_next;
+        g = g_next;
+        h = h_next;
+        l_vec.push(l);
+        r_vec.push(r);
+    }
+
+    Ok((l_vec, r_vec, a[0], b[0]))
+}
+
+/// Accumulates verification equations into one multi-scalar multiplication
+///
+/// Each proof contributes a random multiple of its equations, so a batch of
+/// proofs is accepted iff (with overwhelming probability) every proof is valid.
+struct VerificationBatch {
+    b: pallas::Scalar,
+    b_blinding: pallas::Scalar,
+    g: Vec<pallas::Scalar>,
+    h: Vec<pallas::Scalar>,
+    dynamic_scalars: Vec<pallas::Scalar>,
+    dynamic_points: Vec<pallas::Point>,
+}
+
+impl VerificationBatch {
+    fn new() -> Self {
+        Self {
+            b: pallas::Scalar::ZERO,
+            b_blinding: pallas::Scalar::ZERO,
+            g: Vec::new(),
+            h: Vec::new(),
+            dynamic_scalars: Vec::new(),
+            dynamic_points: Vec::new(),
+        }
+    }
+
+    /// Add one proof's equations under fresh random weights
+    fn add<R: RngCore>(
+        &mut self,
+        proof: &RangeProof,
+        commitments: &[pallas::Point],
+        n: usize,
+        rng: &mut R,
+    ) -> Result<()> {
+        let m = commitments.len();
+        check_shape(n, m)?;
+        let nm = n * m;
+        let rounds = nm.trailing_zeros() as usize;
+        if proof.l_vec.len() != rounds || proof.r_vec.len() != rounds {
+            self.reject();
+            return Ok(());
+        }
+
+        // Replay the transcript
+        let mut transcript = Transcript::new();
+        transcript.append(b"n", &(n as u64).to_le_bytes());
+        transcript.append(b"m", &(m as u64).to_le_bytes());
+        for v in commitments {
+            transcript.append_point(b"V", v);
+        }
+        transcript.append_point(b"A", &proof.a);
+        transcript.append_point(b"S", &proof.s);
+        let y = transcript.challenge(b"y");
+        let z = transcript.challenge(b"z");
+        transcript.append_point(b"T1", &proof.t1);
+        transcript.append_point(b"T2", &proof.t2);
+        let x = transcript.challenge(b"x");
+        transcript.append_scalar(b"tau_x", &proof.tau_x);
+        transcript.append_scalar(b"mu", &proof.mu);
+        transcript.append_scalar(b"t_hat", &proof.t_hat);
+        let w = transcript.challenge(b"w");
+
+        let mut u_sq = Vec::with_capacity(rounds);
+        let mut u_inv_sq = Vec::with_capacity(rounds);
+        let mut u_inv_all = pallas::Scalar::ONE;
+        for (l, r) in proof.l_vec.iter().zip(&proof.r_vec) {
+            transcript.append_point(b"L", l);
+            transcript.append_point(b"R", r);
+            let u = transcript.challenge(b"u");
+            let u_inv: pallas::Scalar = match Option::from(u.invert()) {
+                Some(u_inv) => u_inv,
+                None => {
+                    self.reject();
+                    return Ok(());
+                }
+            };
+            u_sq.push(u.square());
+            u_inv_sq.push(u_inv.square());
+            u_inv_all *= u_inv;
+        }
+
+        // s_i = Π_j u_j^(±1), where round j folds on bit (rounds - 1 - j) of i
+        let mut s = vec![u_inv_all; nm];
+        for i in 1..nm {
+            let lg = usize::BITS as usize - 1 - i.leading_zeros() as usize;
+            s[i] = s[i - (1 << lg)] * u_sq[rounds - 1 - lg];
+        }
+
+        let y_pows = powers(y, nm);
+        let y_inv: pallas::Scalar = Option::from(y.invert()).ok_or(CryptoError::InvalidProof)?;
+        let y_inv_pows = powers(y_inv, nm);
+        let z_pows = powers(z, m + 3);
+        let two_pows = powers(pallas::Scalar::from(2), n);
+
+        // δ(y,z) = (z - z²)·<1, y^nm> - Σ_j z^(3+j)·<1, 2^n>
+        let sum_y: pallas::Scalar = y_pows.iter().sum();
+        let sum_two: pallas::Scalar = two_pows.iter().sum();
+        let delta = (z - z.square()) * sum_y
+            - (0..m).fold(pallas::Scalar::ZERO, |acc, j| acc + z_pows[3 + j]) * sum_two;
+
+        // Random weights: c combines proofs, c·c1 separates the two checks
+        let c = pallas::Scalar::random(&mut *rng);
+        let c1 = c * pallas::Scalar::random(&mut *rng);
+        let ab = proof.ipp_a * proof.ipp_b;
+
+        // Check 1: (t̂ - δ)·B + τx·B̃ - Σ z^(2+j)·V_j - x·T1 - x²·T2 = 0
+        // Check 2: A + x·S - μ·B̃ + Σ(u²L + u⁻²R) + Σ(-z - a·s_i)·G_i
+        //          + Σ(z + y^-i·(z^(2+j)·2^k - b·s_i⁻¹))·H_i + w·(t̂ - ab)·B = 0
+        self.b += c1 * (proof.t_hat - delta) + c * w * (proof.t_hat - ab);
+        self.b_blinding += c1 * proof.tau_x - c * proof.mu;
+
+        if self.g.len() < nm {
+            self.g.resize(nm, pallas::Scalar::ZERO);
+            self.h.resize(nm, pallas::Scalar::ZERO);
+        }
+        let s_inv = batch_invert(&s);
+        for i in 0..nm {
+            self.g[i] += c * (-z - proof.ipp_a * s[i]);
+            self.h[i] += c
+                * (z + y_inv_pows[i]
+                    * (z_pows[2 + i / n] * two_pows[i % n] - proof.ipp_b * s_inv[i]));
+        }
+
+        for (j, v) in commitments.iter().enumerate() {
+            self.push(-c1 * z_pows[2 + j], *v);
+        }
+        self.push(-c1 * x, proof.t1);
+        self.push(-c1 * x.square(), proof.t2);
+        self.push(c, proof.a);
+        self.push(c * x, proof.s);
+        for i in 0..rounds {
+            self.push(c * u_sq[i], proof.l_vec[i]);
+            self.push(c * u_inv_sq[i], proof.r_vec[i]);
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, scalar: pallas::Scalar, point: pallas::Point) {
+        self.dynamic_scalars.push(scalar);
+        self.dynamic_points.push(point);
+    }
+
+    /// Force the batch to fail (malformed proof shape)
+    fn reject(&mut self) {
+        self.push(pallas::Scalar::ONE, pallas::Point::generator());
+    }
+
+    /// Evaluate the combined equation
+    fn verify(self) -> bool {
+        let pedersen = PedersenCommitment::new();
+        let gens = bulletproof_gens();
+        let nm = self.g.len();
+
+        let mut scalars = Vec::with_capacity(2 + 2 * nm + self.dynamic_scalars.len());
+        let mut points = Vec::with_capacity(scalars.capacity());
+
+        scalars.push(self.b);
+        points.push(pedersen.value_generator().inner().to_affine());
+        scalars.push(self.b_blinding);
+        points.push(pedersen.blinding_generator().inner().to_affine());
+        scalars.extend(self.g);
+        points.extend_from_slice(&gens.g[..nm]);
+        scalars.extend(self.h);
+        points.extend_from_slice(&gens.h[..nm]);
+
+        let mut dynamic = vec![pallas::Affine::identity(); self.dynamic_points.len()];
+        pallas::Point::batch_normalize(&self.dynamic_points, &mut dynamic);
+        scalars.extend(self.dynamic_scalars);
+        points.extend(dynamic);
+
+        bool::from(best_multiexp(&scalars, &points).is_identity())
+    }
+}
+
+/// Verify a single (possibly aggregated) range proof
+#[cfg(test)]
+pub(crate) fn verify(proof: &RangeProof, commitments: &[Commitment], n: usize) -> Result<bool> {
+    verify_batch(&[(proof, commitments, n)])
+}
+
+/// Verify many range proofs with one multi-scalar multiplication
+pub(crate) fn verify_batch(proofs: &[(&RangeProof, &[Commitment], usize)]) -> Result<bool> {
+    let mut rng = rand::thread_rng();
+    let mut batch = VerificationBatch::new();
+
+    for (proof, commitments, n) in proofs {
+        let points: Vec<pallas::Point> =
+            commitments.iter().map(|c| *c.as_point().inner()).collect();
+        batch.add(proof, &points, *n, &mut rng)?;
+    }
+
+    Ok(batch.verify())
+}
+
+/// `blinding·B̃ + <l, G> + <r, H>`
+fn commit_vectors(
+    blinding: pallas::Scalar,
+    b_blinding: pallas::Point,
+    l: &[pallas::Scalar],
+    g: &[pallas::Affine],
+    r: &[pallas::Scalar],
+    h: &[pallas::Affine],
+) -> pallas::Point {
+    let scalars = [&[blinding][..], l, r].concat();
+    let points = [&[b_blinding.to_affine()][..], g, h].concat();
+    best_multiexp(&scalars, &points)
+}
+
+fn msm_projective(scalars: &[pallas::Scalar], points: &[pallas::Point]) -> pallas::Point {
+    let mut affine = vec![pallas::Affine::identity(); points.len()];
+    pallas::Point::batch_normalize(points, &mut affine);
+    best_multiexp(scalars, &affine)
+}
+
+fn inner_product(a: &[pallas::Scalar], b: &[pallas::Scalar]) -> pallas::Scalar {
+    a.iter().zip(b).map(|(a, b)| *a * b).sum()
+}
+
+/// `[1, x, x², ..., x^(n-1)]`
+fn powers(x: pallas::Scalar, n: usize) -> Vec<pallas::Scalar> {
+    let mut pows = Vec::with_capacity(n);
+    let mut current = pallas::Scalar::ONE;
+    for _ in 0..n {
+        pows.push(current);
+        current *= x;
+    }
+    pows
+}
+
+/// Invert all (non-zero) elements with a single field inversion
+fn batch_invert(values: &[pallas::Scalar]) -> Vec<pallas::Scalar> {
+    let mut inverted = values.to_vec();
+    inverted.iter_mut().batch_invert();
+    inverted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    fn blindings<R: RngCore>(rng: &mut R, m: usize) -> Vec<Scalar> {
+        (0..m).map(|_| Scalar::random(rng)).collect()
+    }
+
+    #[test]
+    fn test_single_range_proof() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 1);
+
+        let (proof, commitments) = prove(&[u64::MAX], &gammas, 64, &mut rng).unwrap();
+        assert_eq!(proof.l_vec.len(), 6);
+        assert!(verify(&proof, &commitments, 64).unwrap());
+    }
+
+    #[test]
+    fn test_aggregated_range_proof() {
+        let mut rng = test_rng();
+        let values = [0u64, 1, 1 << 40, u64::MAX];
+        let gammas = blindings(&mut rng, values.len());
+
+        let (proof, commitments) = prove(&values, &gammas, 64, &mut rng).unwrap();
+        assert_eq!(proof.l_vec.len(), 8);
+        assert!(verify(&proof, &commitments, 64).unwrap());
+    }
+
+    #[test]
+    fn test_out_of_range_rejected() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 1);
+
+        // Prover refuses to build a proof for an out-of-range value
+        assert!(prove(&[256], &gammas, 8, &mut rng).is_err());
+
+        // A valid 8-bit proof does not verify against a different commitment
+        let (proof, _) = prove(&[255], &gammas, 8, &mut rng).unwrap();
+        let other = PedersenCommitment::new().commit_with_blinding(256, gammas[0]);
+        assert!(!verify(&proof, &[other], 8).unwrap());
+    }
+
+    #[test]
+    fn test_wrong_range_size_rejected() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 1);
+
+        let (proof, commitments) = prove(&[7], &gammas, 32, &mut rng).unwrap();
+        assert!(!verify(&proof, &commitments, 64).unwrap());
+        assert!(verify(&proof, &commitments, 32).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_shapes() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 3);
+
+        assert!(prove(&[1, 2, 3], &gammas, 64, &mut rng).is_err());
+        assert!(prove(&[1], &gammas[..1], 12, &mut rng).is_err());
+        assert!(prove(&[1, 2], &gammas[..1], 64, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_tampered_proof_rejected() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 2);
+        let (proof, commitments) = prove(&[10, 20], &gammas, 16, &mut rng).unwrap();
+
+        let mut tampered = proof.clone();
+        tampered.t_hat += pallas::Scalar::ONE;
+        assert!(!verify(&tampered, &commitments, 16).unwrap());
+
+        let mut tampered = proof.clone();
+        tampered.l_vec[0] += pallas::Point::generator();
+        assert!(!verify(&tampered, &commitments, 16).unwrap());
+
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        assert!(!verify(&proof, &swapped, 16).unwrap());
+    }
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let mut rng = test_rng();
+        let gammas = blindings(&mut rng, 2);
+        let (proof, _) = prove(&[3, 4], &gammas, 32, &mut rng).unwrap();
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 32 * (9 + 2 * 6));
+        assert_eq!(RangeProof::from_bytes(&bytes).unwrap(), proof);
+
+        assert!(RangeProof::from_bytes(&bytes[..bytes.len() - 32]).is_err());
+    }
+
+    #[test]
+    fn test_batch_verification() {
+        let mut rng = test_rng();
+        let mut proofs = Vec::new();
+        for (values, n) in [(vec![1u64], 64), (vec![2u64, 3], 32), (vec![4u64; 4], 8)] {
+            let gammas = blindings(&mut rng, values.len());
+            proofs.push((prove(&values, &gammas, n, &mut rng).unwrap(), n));
+        }
+
+        let batch: Vec<_> = proofs
+            .iter()
+            .map(|((p, c), n)| (p, c.as_slice(), *n))
+            .collect();
+        assert!(verify_batch(&batch).unwrap());
+
+        // One bad proof fails the whole batch
+        let mut bad = proofs[1].0 .0.clone();
+        bad.mu += pallas::Scalar::ONE;
+        let mut batch = batch;
+        batch[1].0 = &bad;
+        assert!(!verify_batch(&batch).unwrap());
+    }
+}
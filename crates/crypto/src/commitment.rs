@@ -4,6 +4,8 @@
 //! to hide transaction amounts while maintaining homomorphic properties.
 
 use ark_std::rand::Rng;
+use pasta_curves::arithmetic::CurveExt;
+use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Sub};
 
@@ -27,23 +29,25 @@ pub struct PedersenCommitment {
 impl PedersenCommitment {
     /// Create a new Pedersen commitment scheme with default generators
     pub fn new() -> Self {
-        // Use standard generators for Pallas curve
-        // In production, these would be generated via a transparent setup ceremony
+        // Use the standard generator for the value component
         let g = Point::generator();
 
-        // Generate h = hash_to_curve("PRIVL1_PEDERSEN_H")
-        // For now, using a simple deterministic derivation
-        let h = {
-            let mut h_point = g;
-            for _ in 0..128 {
-                h_point = h_point + h_point; // Double the point
-            }
-            h_point
-        };
+        // h = hash_to_curve("PRIVL1_PEDERSEN")("H"), so nobody knows log_g(h)
+        let h = Point::from_inner(pallas::Point::hash_to_curve("PRIVL1_PEDERSEN")(b"H"));
 
         Self { g, h }
     }
 
+    /// Get the value generator
+    pub fn value_generator(&self) -> &Point {
+        &self.g
+    }
+
+    /// Get the blinding generator
+    pub fn blinding_generator(&self) -> &Point {
+        &self.h
+    }
+
     /// Commit to a value with a random blinding factor
     pub fn commit<R: Rng>(&self, value: u64, rng: &mut R) -> (Commitment, Scalar) {
         let blinding = Scalar::random(rng);
@@ -96,6 +100,11 @@ impl Commitment {
     pub fn is_zero(&self) -> bool {
         self.point.is_identity()
     }
+
+    /// Get the underlying curve point
+    pub fn as_point(&self) -> &Point {
+        &self.point
+    }
 }
 
 /// Homomorphic addition of commitments
@@ -104,7 +113,7 @@ impl Add for Commitment {
 
     fn add(self, other: Self) -> Self {
         Commitment {
-            point: (self.point + other.point).into(),
+            point: (self.point + other.point),
         }
     }
 }
@@ -115,7 +124,7 @@ impl Sub for Commitment {
 
     fn sub(self, other: Self) -> Self {
         Commitment {
-            point: (self.point - other.point).into(),
+            point: (self.point - other.point),
         }
     }
 }
@@ -176,7 +185,7 @@ mod tests {
         assert!(!pedersen.verify(&commitment, value + 1, blinding));
 
         // Wrong blinding should fail
-        let wrong_blinding = pallas::Scalar::rand(&mut rng);
+        let wrong_blinding = Scalar::random(&mut rng);
         assert!(!pedersen.verify(&commitment, value, wrong_blinding));
     }
 
@@ -0,0 +1,437 @@
+//! Selective-disclosure proofs
+//!
+//! A [`DisclosureProof`] proves one fact about shielded notes to a third party
+//! without revealing anything else:
+//! - [`DisclosureStatement::NoteOrigin`]: a nullifier spends a note appended
+//!   to the commitment tree at a given position, i.e. at a known height
+//! - [`DisclosureStatement::BalanceAtLeast`]: unspent notes of one key hold at
+//!   least `threshold` of an asset
+//! - [`DisclosureStatement::OutputRecipient`]: a note commitment pays a given
+//!   address
+//! - [`DisclosureStatement::ReservesAtLeast`]: notes of a custodian's key that
+//!   are unspent as of a nullifier-tree root hold at least `threshold` of an
+//!   asset, without revealing any note or nullifier
+//!
+//! The proofs come from the disclosure circuits in `privl1-circuits`, whose
+//! public inputs are laid out as in [`DisclosureStatement::public_inputs`].
+//! Each proof is also bound to a verifier-chosen `context`, so it cannot be
+//! replayed to another verifier. [`DisclosureProof::verify_against`] checks
+//! the statement against chain state as well as the proof.
+
+use ff::PrimeField;
+use pasta_curves::pallas;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::halo2;
+use crate::hash::bytes_to_base;
+use crate::merkle::MerkleRoot;
+use crate::note::{asset_base, NoteCommitment};
+use crate::nullifier::Nullifier;
+use crate::proof::{Halo2Proof, VerificationKey};
+use crate::{CryptoError, PublicKey, Result};
+
+/// Notes a balance disclosure can sum; unused slots hold zero-value notes
+pub const MAX_BALANCE_NOTES: usize = 4;
+
+/// A fact disclosed by a [`DisclosureProof`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisclosureStatement {
+    /// `nullifier` spends the note at `position` of the tree with root `anchor`
+    NoteOrigin {
+        anchor: MerkleRoot,
+        position: u64,
+        /// Height the note was appended at, checked against chain state
+        height: u64,
+        nullifier: Nullifier,
+    },
+    /// Notes with these nullifiers, in the tree with root `anchor`, hold at
+    /// least `threshold` of `asset_id` between them
+    BalanceAtLeast {
+        anchor: MerkleRoot,
+        asset_id: [u8; 32],
+        threshold: u64,
+        nullifiers: [Nullifier; MAX_BALANCE_NOTES],
+    },
+    /// `commitment` is a note owned by `recipient`
+    OutputRecipient {
+        commitment: NoteCommitment,
+        recipient: PublicKey,
+    },
+    /// Notes owned by `owner`, in the tree with root `anchor` and with no
+    /// nullifier in the nullifier tree with root `nullifier_root`, hold at
+    /// least `threshold` of `asset_id` between them
+    ReservesAtLeast {
+        anchor: MerkleRoot,
+        nullifier_root: MerkleRoot,
+        asset_id: [u8; 32],
+        threshold: u64,
+        owner: PublicKey,
+    },
+}
+
+/// Chain state a disclosure is checked against
+pub trait DisclosureContext {
+    /// Whether `anchor` is a current or historical commitment-tree root
+    fn is_anchor(&self, anchor: &MerkleRoot) -> bool;
+
+    /// Height at which the leaf at `position` was appended
+    fn commitment_height(&self, position: u64) -> Option<u64>;
+
+    /// Whether `commitment` is in the commitment tree
+    fn has_commitment(&self, commitment: &NoteCommitment) -> bool;
+
+    /// Whether `nullifier` has been revealed by a spend
+    fn is_spent(&self, nullifier: &Nullifier) -> bool;
+
+    /// Whether one block committed to both `anchor` and `nullifier_root`
+    fn is_state_root(&self, anchor: &MerkleRoot, nullifier_root: &MerkleRoot) -> bool;
+}
+
+impl DisclosureStatement {
+    /// Public inputs of the statement's circuit for `context`, in row order
+    ///
+    /// Fails if an anchor or nullifier is not a canonical field encoding.
+    pub fn public_inputs(&self, context: &[u8; 32]) -> Result<Vec<pallas::Base>> {
+        let context = bytes_to_base(context);
+
+        Ok(match self {
+            Self::NoteOrigin {
+                anchor,
+                position,
+                nullifier,
+                ..
+            } => vec![
+                canonical_base(anchor.as_bytes())?,
+                pallas::Base::from(*position),
+                canonical_base(nullifier.as_bytes())?,
+                context,
+            ],
+            Self::BalanceAtLeast {
+                anchor,
+                asset_id,
+                threshold,
+                nullifiers,
+            } => {
+                let mut instances = vec![
+                    canonical_base(anchor.as_bytes())?,
+                    asset_base(asset_id),
+                    pallas::Base::from(*threshold),
+                    context,
+                ];
+                for nullifier in nullifiers {
+                    instances.push(canonical_base(nullifier.as_bytes())?);
+                }
+                instances
+            }
+            Self::OutputRecipient {
+                commitment,
+                recipient,
+            } => {
+                let (x, y) = recipient.as_point().coordinates();
+                vec![commitment.to_field(), x, y, context]
+            }
+            Self::ReservesAtLeast {
+                anchor,
+                nullifier_root,
+                asset_id,
+                threshold,
+                owner,
+            } => {
+                let (x, y) = owner.as_point().coordinates();
+                vec![
+                    canonical_base(anchor.as_bytes())?,
+                    canonical_base(nullifier_root.as_bytes())?,
+                    asset_base(asset_id),
+                    pallas::Base::from(*threshold),
+                    x,
+                    y,
+                    context,
+                ]
+            }
+        })
+    }
+
+    /// Check the statement against chain state
+    ///
+    /// The anchor must be known, a disclosed origin must be spent and appended
+    /// at `height`, balance notes must be distinct and unspent, a disclosed
+    /// output must be on chain, and reserves must be proven against the
+    /// nullifier root of the anchor's block.
+    pub fn holds_on(&self, chain: &impl DisclosureContext) -> bool {
+        match self {
+            Self::NoteOrigin {
+                anchor,
+                position,
+                height,
+                nullifier,
+            } => {
+                chain.is_anchor(anchor)
+                    && chain.commitment_height(*position) == Some(*height)
+                    && chain.is_spent(nullifier)
+            }
+            Self::BalanceAtLeast {
+                anchor, nullifiers, ..
+            } => {
+                let distinct: HashSet<_> = nullifiers.iter().collect();
+                chain.is_anchor(anchor)
+                    && distinct.len() == nullifiers.len()
+                    && !nullifiers.iter().any(|nullifier| chain.is_spent(nullifier))
+            }
+            Self::OutputRecipient { commitment, .. } => chain.has_commitment(commitment),
+            Self::ReservesAtLeast {
+                anchor,
+                nullifier_root,
+                ..
+            } => chain.is_anchor(anchor) && chain.is_state_root(anchor, nullifier_root),
+        }
+    }
+}
+
+/// A serializable proof of a [`DisclosureStatement`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisclosureProof {
+    /// The disclosed fact
+    pub statement: DisclosureStatement,
+    /// Verifier-chosen context the proof is bound to
+    pub context: [u8; 32],
+    /// Proof from the statement's disclosure circuit
+    pub proof: Halo2Proof,
+}
+
+impl DisclosureProof {
+    /// Create a disclosure proof
+    pub fn new(statement: DisclosureStatement, context: [u8; 32], proof: Halo2Proof) -> Self {
+        Self {
+            statement,
+            context,
+            proof,
+        }
+    }
+
+    /// Check that the proof's public inputs are the statement's
+    pub fn binds_statement(&self) -> bool {
+        self.statement
+            .public_inputs(&self.context)
+            .is_ok_and(|inputs| {
+                inputs.iter().map(halo2::encode_instance).eq(self
+                    .proof
+                    .public_inputs
+                    .iter()
+                    .cloned())
+            })
+    }
+
+    /// Verify the proof of the statement
+    ///
+    /// Does not check chain state; see [`DisclosureProof::verify_against`].
+    pub fn verify(&self, vk: &VerificationKey) -> Result<bool> {
+        if !self.binds_statement() {
+            return Ok(false);
+        }
+        self.proof.verify(vk)
+    }
+
+    /// Verify the proof for `context` and check its statement against `chain`
+    pub fn verify_against(
+        &self,
+        vk: &VerificationKey,
+        context: &[u8; 32],
+        chain: &impl DisclosureContext,
+    ) -> Result<bool> {
+        if self.context != *context || !self.statement.holds_on(chain) {
+            return Ok(false);
+        }
+        self.verify(vk)
+    }
+}
+
+fn canonical_base(bytes: &[u8; 32]) -> Result<pallas::Base> {
+    Option::from(pallas::Base::from_repr(*bytes)).ok_or(CryptoError::InvalidProof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::FullKeys;
+    use crate::note::{AssetId, Note};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct Chain {
+        anchors: Vec<MerkleRoot>,
+        heights: HashMap<u64, u64>,
+        commitments: Vec<NoteCommitment>,
+        spent: Vec<Nullifier>,
+        state_roots: Vec<(MerkleRoot, MerkleRoot)>,
+    }
+
+    impl DisclosureContext for Chain {
+        fn is_anchor(&self, anchor: &MerkleRoot) -> bool {
+            self.anchors.contains(anchor)
+        }
+
+        fn commitment_height(&self, position: u64) -> Option<u64> {
+            self.heights.get(&position).copied()
+        }
+
+        fn has_commitment(&self, commitment: &NoteCommitment) -> bool {
+            self.commitments.contains(commitment)
+        }
+
+        fn is_spent(&self, nullifier: &Nullifier) -> bool {
+            self.spent.contains(nullifier)
+        }
+
+        fn is_state_root(&self, anchor: &MerkleRoot, nullifier_root: &MerkleRoot) -> bool {
+            self.state_roots.contains(&(*anchor, *nullifier_root))
+        }
+    }
+
+    fn nullifier(seed: u64) -> Nullifier {
+        Nullifier::from_bytes(pallas::Base::from(seed).to_repr())
+    }
+
+    fn anchor() -> MerkleRoot {
+        MerkleRoot::from_bytes(pallas::Base::from(99).to_repr())
+    }
+
+    #[test]
+    fn test_public_inputs_layout() {
+        let origin = DisclosureStatement::NoteOrigin {
+            anchor: anchor(),
+            position: 5,
+            height: 10,
+            nullifier: nullifier(1),
+        };
+        let inputs = origin.public_inputs(&[3u8; 32]).unwrap();
+        assert_eq!(inputs.len(), 4);
+        assert_eq!(inputs[1], pallas::Base::from(5));
+        assert_ne!(inputs, origin.public_inputs(&[4u8; 32]).unwrap());
+
+        let balance = DisclosureStatement::BalanceAtLeast {
+            anchor: anchor(),
+            asset_id: *AssetId::NATIVE.as_bytes(),
+            threshold: 100,
+            nullifiers: [nullifier(1), nullifier(2), nullifier(3), nullifier(4)],
+        };
+        assert_eq!(
+            balance.public_inputs(&[0u8; 32]).unwrap().len(),
+            4 + MAX_BALANCE_NOTES
+        );
+
+        let owner = FullKeys::random(&mut rand::thread_rng()).public;
+        let reserves = DisclosureStatement::ReservesAtLeast {
+            anchor: anchor(),
+            nullifier_root: anchor(),
+            asset_id: *AssetId::NATIVE.as_bytes(),
+            threshold: 100,
+            owner,
+        };
+        let inputs = reserves.public_inputs(&[0u8; 32]).unwrap();
+        assert_eq!(inputs.len(), 7);
+        assert_eq!(inputs[4], owner.as_point().coordinates().0);
+
+        // Non-canonical encodings are rejected
+        let bad = DisclosureStatement::NoteOrigin {
+            anchor: MerkleRoot::from_bytes([0xff; 32]),
+            position: 5,
+            height: 10,
+            nullifier: nullifier(1),
+        };
+        assert!(bad.public_inputs(&[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_statements_checked_against_chain() {
+        let mut chain = Chain {
+            anchors: vec![anchor()],
+            ..Default::default()
+        };
+        chain.heights.insert(5, 10);
+        chain.spent.push(nullifier(1));
+
+        let origin = |height| DisclosureStatement::NoteOrigin {
+            anchor: anchor(),
+            position: 5,
+            height,
+            nullifier: nullifier(1),
+        };
+        assert!(origin(10).holds_on(&chain));
+        assert!(!origin(11).holds_on(&chain));
+
+        let balance = |nullifiers| DisclosureStatement::BalanceAtLeast {
+            anchor: anchor(),
+            asset_id: *AssetId::NATIVE.as_bytes(),
+            threshold: 100,
+            nullifiers,
+        };
+        assert!(balance([nullifier(2), nullifier(3), nullifier(4), nullifier(5)]).holds_on(&chain));
+        // Spent or repeated notes do not count
+        assert!(
+            !balance([nullifier(1), nullifier(3), nullifier(4), nullifier(5)]).holds_on(&chain)
+        );
+        assert!(
+            !balance([nullifier(2), nullifier(2), nullifier(4), nullifier(5)]).holds_on(&chain)
+        );
+
+        let keys = FullKeys::random(&mut rand::thread_rng());
+        let note = Note::new_with_owner(5, keys.public, *AssetId::NATIVE.as_bytes());
+        let output = DisclosureStatement::OutputRecipient {
+            commitment: note.commitment(),
+            recipient: keys.public,
+        };
+        assert!(!output.holds_on(&chain));
+        chain.commitments.push(note.commitment());
+        assert!(output.holds_on(&chain));
+
+        // Reserves need the nullifier root of the anchor's block
+        let reserves = |nullifier_root| DisclosureStatement::ReservesAtLeast {
+            anchor: anchor(),
+            nullifier_root,
+            asset_id: *AssetId::NATIVE.as_bytes(),
+            threshold: 100,
+            owner: keys.public,
+        };
+        let nullifier_root = MerkleRoot::from_bytes(pallas::Base::from(7).to_repr());
+        chain.state_roots.push((anchor(), nullifier_root));
+        assert!(reserves(nullifier_root).holds_on(&chain));
+        assert!(!reserves(anchor()).holds_on(&chain));
+    }
+
+    #[test]
+    fn test_proof_bound_to_statement() {
+        let statement = DisclosureStatement::NoteOrigin {
+            anchor: anchor(),
+            position: 5,
+            height: 10,
+            nullifier: nullifier(1),
+        };
+        let inputs = statement.public_inputs(&[3u8; 32]).unwrap();
+        let proof = Halo2Proof::new(
+            vec![],
+            inputs.iter().map(halo2::encode_instance).collect(),
+            [0u8; 32],
+        );
+
+        let disclosure = DisclosureProof::new(statement.clone(), [3u8; 32], proof.clone());
+        assert!(disclosure.binds_statement());
+
+        // Another context or statement does not match the proof
+        assert!(!DisclosureProof::new(statement, [4u8; 32], proof.clone()).binds_statement());
+        let other = DisclosureStatement::NoteOrigin {
+            anchor: anchor(),
+            position: 6,
+            height: 10,
+            nullifier: nullifier(1),
+        };
+        let disclosure = DisclosureProof::new(other, [3u8; 32], proof);
+        assert!(!disclosure.binds_statement());
+        assert!(!disclosure
+            .verify(&VerificationKey::new(
+                crate::proof::ProofSystem::Halo2,
+                vec![]
+            ))
+            .unwrap());
+    }
+}
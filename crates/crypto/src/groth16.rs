@@ -0,0 +1,299 @@
+//! Groth16 proving and verification over BLS12-381
+//!
+//! Groth16 gives constant-size proofs (192 bytes compressed) and a three-pairing
+//! verifier, which suits the bridge and EVM-facing circuits. Circuits are R1CS
+//! constraint systems written against `ark-relations`.
+//!
+//! Verifying keys are stored compressed in `VerificationKey.key_data`. Loading
+//! one validates all points (on-curve and in the prime-order subgroup) and
+//! caches the prepared key by the hash of its bytes, so the pairing
+//! precomputation happens once.
+//!
+//! Keys from [`setup`] come from a single-party setup and are only suitable for
+//! tests; production keys must come out of a phase-2 ceremony.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use rand::{CryptoRng, RngCore};
+
+use crate::proof::{ProofSystem, VerificationKey};
+use crate::{CryptoError, Result};
+
+/// Size of a compressed Groth16 proof over BLS12-381
+pub const PROOF_SIZE: usize = 192;
+
+/// Size of an encoded public input (compressed `Fr`)
+pub const INPUT_SIZE: usize = 32;
+
+/// A Groth16 proving key
+pub type Groth16ProvingKey = ProvingKey<Bls12_381>;
+
+/// A Groth16 verifying key
+pub type Groth16VerifyingKey = VerifyingKey<Bls12_381>;
+
+/// Prepared verifying keys by verification key id
+type PreparedVkCache = RwLock<HashMap<[u8; 32], Arc<PreparedVerifyingKey<Bls12_381>>>>;
+
+fn prepared_vk_cache() -> &'static PreparedVkCache {
+    static CACHE: OnceLock<PreparedVkCache> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Run a circuit-specific setup (single party; tests and development only)
+pub fn setup<C, R>(circuit: C, rng: &mut R) -> Result<(Groth16ProvingKey, Groth16VerifyingKey)>
+where
+    C: ConstraintSynthesizer<Fr>,
+    R: RngCore + CryptoRng,
+{
+    Groth16::<Bls12_381>::circuit_specific_setup(circuit, rng)
+        .map_err(|e| CryptoError::OperationFailed(format!("Groth16 setup failed: {}", e)))
+}
+
+/// Create a compressed proof
+pub fn prove<C, R>(pk: &Groth16ProvingKey, circuit: C, rng: &mut R) -> Result<Vec<u8>>
+where
+    C: ConstraintSynthesizer<Fr>,
+    R: RngCore + CryptoRng,
+{
+    let proof = Groth16::<Bls12_381>::prove(pk, circuit, rng)
+        .map_err(|e| CryptoError::OperationFailed(format!("Groth16 proving failed: {}", e)))?;
+
+    serialize(&proof)
+}
+
+/// Build the `VerificationKey` for a Groth16 verifying key
+pub fn verification_key(vk: &Groth16VerifyingKey) -> Result<VerificationKey> {
+    Ok(VerificationKey::new(ProofSystem::Groth16, serialize(vk)?))
+}
+
+/// Load and cache the prepared verifying key described by a `VerificationKey`
+pub fn load_prepared_vk(vk: &VerificationKey) -> Result<Arc<PreparedVerifyingKey<Bls12_381>>> {
+    if !matches!(vk.key_type, ProofSystem::Groth16) {
+        return Err(CryptoError::InvalidKey);
+    }
+
+    // Keyed by the recomputed id: a key claiming another key's id must not
+    // read or overwrite that key's entry
+    let id = vk.checked_id()?;
+    if let Some(cached) = prepared_vk_cache()
+        .read()
+        .expect("vk cache poisoned")
+        .get(&id)
+    {
+        return Ok(cached.clone());
+    }
+
+    // Trailing bytes would let keys with different ids share one prepared key
+    let mut reader = vk.key_data.as_slice();
+    let groth16_vk = Groth16VerifyingKey::deserialize_compressed(&mut reader)
+        .map_err(|_| CryptoError::InvalidKey)?;
+    if !reader.is_empty() {
+        return Err(CryptoError::InvalidKey);
+    }
+    let prepared =
+        Groth16::<Bls12_381>::process_vk(&groth16_vk).map_err(|_| CryptoError::InvalidKey)?;
+
+    let prepared = Arc::new(prepared);
+    prepared_vk_cache()
+        .write()
+        .expect("vk cache poisoned")
+        .insert(id, prepared.clone());
+
+    Ok(prepared)
+}
+
+/// Encode a field element as a public input
+pub fn encode_input(value: &Fr) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(INPUT_SIZE);
+    value
+        .serialize_compressed(&mut bytes)
+        .expect("serializing into a Vec cannot fail");
+    bytes
+}
+
+/// Decode public inputs (canonical 32-byte encodings)
+pub fn decode_inputs(public_inputs: &[Vec<u8>]) -> Result<Vec<Fr>> {
+    public_inputs
+        .iter()
+        .map(|bytes| {
+            if bytes.len() != INPUT_SIZE {
+                return Err(CryptoError::InvalidProof);
+            }
+            Fr::deserialize_compressed(bytes.as_slice()).map_err(|_| CryptoError::InvalidProof)
+        })
+        .collect()
+}
+
+/// Verify a compressed proof against decoded public inputs
+///
+/// Returns an error if the proof bytes are malformed.
+pub fn verify(pvk: &PreparedVerifyingKey<Bls12_381>, proof: &[u8], inputs: &[Fr]) -> Result<bool> {
+    if proof.len() != PROOF_SIZE {
+        return Err(CryptoError::InvalidProof);
+    }
+    let proof =
+        Proof::<Bls12_381>::deserialize_compressed(proof).map_err(|_| CryptoError::InvalidProof)?;
+
+    // A wrong number of inputs is a verification failure, not a malformed proof
+    if inputs.len() + 1 != pvk.vk.gamma_abc_g1.len() {
+        return Ok(false);
+    }
+
+    Groth16::<Bls12_381>::verify_with_processed_vk(pvk, inputs, &proof)
+        .map_err(|e| CryptoError::OperationFailed(format!("Groth16 verification failed: {}", e)))
+}
+
+fn serialize<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(value.compressed_size());
+    value
+        .serialize_compressed(&mut bytes)
+        .map_err(|e| CryptoError::SerializationError(e.to_string()))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ark_ff::Field;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Proves knowledge of `x` such that `x³ + x + 5 = y` for a public `y`
+    #[derive(Clone, Default)]
+    pub(crate) struct CubicCircuit {
+        pub x: Option<Fr>,
+    }
+
+    impl CubicCircuit {
+        pub(crate) fn output(x: Fr) -> Fr {
+            x.square() * x + x + Fr::from(5u64)
+        }
+    }
+
+    impl ConstraintSynthesizer<Fr> for CubicCircuit {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<Fr>,
+        ) -> std::result::Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x2_value = x_value.map(|x| x.square());
+            let x3_value = x_value.map(|x| x.square() * x);
+
+            let y = cs.new_input_variable(|| {
+                x_value
+                    .map(Self::output)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x = cs.new_witness_variable(|| x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x2 =
+                cs.new_witness_variable(|| x2_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x3 =
+                cs.new_witness_variable(|| x3_value.ok_or(SynthesisError::AssignmentMissing))?;
+
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x2)?;
+            cs.enforce_constraint(lc!() + x2, lc!() + x, lc!() + x3)?;
+            cs.enforce_constraint(
+                lc!() + x3 + x + (Fr::from(5u64), ark_relations::r1cs::Variable::One),
+                lc!() + ark_relations::r1cs::Variable::One,
+                lc!() + y,
+            )?;
+            Ok(())
+        }
+    }
+
+    /// Set up the cubic circuit and prove it for `x`
+    pub(crate) fn cubic_proof(x: u64) -> (VerificationKey, Vec<u8>, Fr) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (pk, vk) = setup(CubicCircuit::default(), &mut rng).unwrap();
+
+        let x = Fr::from(x);
+        let proof = prove(&pk, CubicCircuit { x: Some(x) }, &mut rng).unwrap();
+
+        (
+            verification_key(&vk).unwrap(),
+            proof,
+            CubicCircuit::output(x),
+        )
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (vk, proof, y) = cubic_proof(3);
+        assert_eq!(proof.len(), PROOF_SIZE);
+        assert_eq!(y, Fr::from(35u64));
+
+        let pvk = load_prepared_vk(&vk).unwrap();
+        assert!(verify(&pvk, &proof, &[y]).unwrap());
+        assert!(!verify(&pvk, &proof, &[y + Fr::ONE]).unwrap());
+        assert!(!verify(&pvk, &proof, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_proof() {
+        let (vk, proof, y) = cubic_proof(4);
+        let pvk = load_prepared_vk(&vk).unwrap();
+
+        assert!(verify(&pvk, &proof[..100], &[y]).is_err());
+
+        // `A` with an x-coordinate past the modulus
+        let mut bytes = [0xffu8; PROOF_SIZE];
+        bytes[0] = 0x9f;
+        assert!(verify(&pvk, &bytes, &[y]).is_err());
+    }
+
+    #[test]
+    fn test_prepared_vk_cached() {
+        let (vk, _, _) = cubic_proof(5);
+        let p1 = load_prepared_vk(&vk).unwrap();
+        let p2 = load_prepared_vk(&vk).unwrap();
+        assert!(Arc::ptr_eq(&p1, &p2));
+
+        let halo2_key = VerificationKey::new(ProofSystem::Halo2, vk.key_data.clone());
+        assert!(load_prepared_vk(&halo2_key).is_err());
+
+        let truncated = VerificationKey::new(ProofSystem::Groth16, vk.key_data[..50].to_vec());
+        assert!(load_prepared_vk(&truncated).is_err());
+
+        let mut padded = vk.key_data.clone();
+        padded.push(0);
+        let padded = VerificationKey::new(ProofSystem::Groth16, padded);
+        assert_ne!(padded.id(), vk.id());
+        assert!(load_prepared_vk(&padded).is_err());
+    }
+
+    #[test]
+    fn test_cache_rejects_borrowed_ids() {
+        let (vk, proof, y) = cubic_proof(6);
+
+        // Another setup's key, relabelled with the honest key's id, before
+        // and after the honest key is cached
+        let (_, other) = setup(CubicCircuit::default(), &mut StdRng::seed_from_u64(1)).unwrap();
+        let mut forged = verification_key(&other).unwrap();
+        forged.key_hash = vk.id();
+        assert!(load_prepared_vk(&forged).is_err());
+        let pvk = load_prepared_vk(&vk).unwrap();
+        assert!(load_prepared_vk(&forged).is_err());
+
+        assert!(verify(&pvk, &proof, &[y]).unwrap());
+        assert!(Arc::ptr_eq(&pvk, &load_prepared_vk(&vk).unwrap()));
+    }
+
+    #[test]
+    fn test_input_encoding() {
+        let value = Fr::from(123456789u64);
+        let encoded = encode_input(&value);
+        assert_eq!(encoded.len(), INPUT_SIZE);
+        assert_eq!(decode_inputs(&[encoded]).unwrap(), vec![value]);
+
+        assert!(decode_inputs(&[vec![0xff; INPUT_SIZE]]).is_err());
+        assert!(decode_inputs(&[vec![1, 2, 3]]).is_err());
+    }
+}
@@ -0,0 +1,633 @@
+//! Halo2 proving and verification backend
+//!
+//! PRIVL1 circuits are defined over `pallas::Base`, so their proofs commit with
+//! IPA parameters over Vesta (`Params<vesta::Affine>`). This module owns:
+//! - A per-size cache of IPA parameters (generated once, no trusted setup)
+//! - A registry of circuits whose verifying keys can be rebuilt from `key_data`
+//! - Encoding of public inputs as `pallas::Base` instances
+//! - Proof creation and verification on top of `halo2_proofs`
+//!
+//! Halo2 verifying keys are not serialized directly. `VerificationKey.key_data`
+//! names a registered circuit, its size `k` and a hash of the pinned verifying
+//! key. Loading rebuilds the key with `keygen_vk` and fails closed if the hash
+//! does not match, so a key can never silently change under a given id.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use ff::{Field, PrimeField};
+use halo2_proofs::plonk::{self, Circuit, ProvingKey, SingleVerifier, VerificationStrategy};
+use halo2_proofs::poly::commitment::{Guard, Params, MSM};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, Transcript,
+};
+use pasta_curves::{pallas, vesta};
+use rand::{CryptoRng, RngCore};
+use rayon::prelude::*;
+
+use crate::hash::Blake3Hash;
+use crate::proof::{ProofSystem, VerificationKey};
+use crate::{CryptoError, Result};
+
+/// IPA parameters for PRIVL1 circuits
+pub type Halo2Params = Params<vesta::Affine>;
+
+/// A Halo2 verifying key for a PRIVL1 circuit
+pub type Halo2VerifyingKey = plonk::VerifyingKey<vesta::Affine>;
+
+/// A Halo2 proving key for a PRIVL1 circuit
+pub type Halo2ProvingKey = ProvingKey<vesta::Affine>;
+
+/// Rebuilds a circuit's verifying key from IPA parameters
+pub type VkBuilder = fn(&Halo2Params) -> Result<Halo2VerifyingKey>;
+
+/// Largest supported circuit size (2^k rows)
+pub const MAX_K: u32 = 20;
+
+fn params_cache() -> &'static RwLock<HashMap<u32, Arc<Halo2Params>>> {
+    static CACHE: OnceLock<RwLock<HashMap<u32, Arc<Halo2Params>>>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn circuit_registry() -> &'static RwLock<HashMap<String, VkBuilder>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, VkBuilder>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn vk_cache() -> &'static RwLock<HashMap<[u8; 32], Arc<Halo2VerifyingKey>>> {
+    static CACHE: OnceLock<RwLock<HashMap<[u8; 32], Arc<Halo2VerifyingKey>>>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Get the IPA parameters for circuits of size 2^k (generated once per k)
+pub fn params(k: u32) -> Result<Arc<Halo2Params>> {
+    if k == 0 || k > MAX_K {
+        return Err(CryptoError::OperationFailed(format!(
+            "Unsupported circuit size k={}",
+            k
+        )));
+    }
+
+    if let Some(params) = params_cache()
+        .read()
+        .expect("params cache poisoned")
+        .get(&k)
+    {
+        return Ok(params.clone());
+    }
+
+    // Generate outside the lock; a racing thread may generate the same
+    // (deterministic) parameters, and the first insert wins.
+    let generated = Arc::new(Halo2Params::new(k));
+    let mut cache = params_cache().write().expect("params cache poisoned");
+    Ok(cache.entry(k).or_insert(generated).clone())
+}
+
+/// Install IPA parameters for size 2^k, e.g. read back from disk
+///
+/// Parameters are deterministic, so the first install or generation wins and
+/// later ones are dropped. Callers must have checked `params` (for instance by
+/// rebuilding a pinned verifying key from them) before installing.
+pub fn install_params(k: u32, params: Halo2Params) -> Result<Arc<Halo2Params>> {
+    if k == 0 || k > MAX_K {
+        return Err(CryptoError::OperationFailed(format!(
+            "Unsupported circuit size k={}",
+            k
+        )));
+    }
+
+    let mut cache = params_cache().write().expect("params cache poisoned");
+    Ok(cache.entry(k).or_insert_with(|| Arc::new(params)).clone())
+}
+
+/// Register a circuit so its verifying keys can be rebuilt during verification
+pub fn register_circuit(name: &str, builder: VkBuilder) {
+    circuit_registry()
+        .write()
+        .expect("circuit registry poisoned")
+        .insert(name.to_string(), builder);
+}
+
+/// Check if a circuit is registered
+pub fn is_registered(name: &str) -> bool {
+    circuit_registry()
+        .read()
+        .expect("circuit registry poisoned")
+        .contains_key(name)
+}
+
+/// Contents of `VerificationKey.key_data` for Halo2 keys
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Halo2KeyData {
+    /// Registered circuit name
+    pub circuit: String,
+    /// Circuit size (2^k rows)
+    pub k: u32,
+    /// Blake3 hash of the pinned verifying key
+    pub pinned_hash: [u8; 32],
+}
+
+impl Halo2KeyData {
+    /// Serialize: k (u32 LE) || pinned_hash || circuit name (UTF-8)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(36 + self.circuit.len());
+        bytes.extend_from_slice(&self.k.to_le_bytes());
+        bytes.extend_from_slice(&self.pinned_hash);
+        bytes.extend_from_slice(self.circuit.as_bytes());
+        bytes
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() <= 36 {
+            return Err(CryptoError::InvalidKey);
+        }
+
+        let mut k = [0u8; 4];
+        let mut pinned_hash = [0u8; 32];
+        k.copy_from_slice(&bytes[0..4]);
+        pinned_hash.copy_from_slice(&bytes[4..36]);
+
+        let circuit = std::str::from_utf8(&bytes[36..])
+            .map_err(|_| CryptoError::InvalidKey)?
+            .to_string();
+
+        Ok(Self {
+            circuit,
+            k: u32::from_le_bytes(k),
+            pinned_hash,
+        })
+    }
+}
+
+/// Hash of a verifying key's transcript representation
+///
+/// `hash_into` absorbs the scalar halo2 binds every proof transcript to, so
+/// the hash changes exactly when proofs stop verifying under the key. It is
+/// read back as a challenge from a fresh transcript and hashed in its
+/// canonical encoding.
+pub fn pinned_hash(vk: &Halo2VerifyingKey) -> [u8; 32] {
+    let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+    vk.hash_into(&mut transcript)
+        .expect("hashing into an in-memory transcript cannot fail");
+    let repr: pallas::Base = transcript.squeeze_challenge().get_scalar();
+
+    *Blake3Hash::hash(&repr.to_repr()).as_bytes()
+}
+
+/// Build the `VerificationKey` that identifies a circuit's Halo2 verifying key
+pub fn verification_key(circuit: &str, k: u32, vk: &Halo2VerifyingKey) -> VerificationKey {
+    let key_data = Halo2KeyData {
+        circuit: circuit.to_string(),
+        k,
+        pinned_hash: pinned_hash(vk),
+    };
+
+    VerificationKey::new(ProofSystem::Halo2, key_data.to_bytes())
+}
+
+/// Load the Halo2 verifying key described by a `VerificationKey`
+///
+/// Fails closed if the key is not a Halo2 key, its id does not hash its
+/// `key_data`, it names an unknown circuit, or it rebuilds to a verifying key
+/// whose pinned hash differs from `key_data`.
+pub fn load_verifying_key(
+    vk: &VerificationKey,
+) -> Result<(Arc<Halo2Params>, Arc<Halo2VerifyingKey>)> {
+    if !matches!(vk.key_type, ProofSystem::Halo2) {
+        return Err(CryptoError::InvalidKey);
+    }
+
+    // Cache entries are keyed by the recomputed id, so a key claiming another
+    // key's id cannot reach that key's entry
+    let id = vk.checked_id()?;
+    let key_data = Halo2KeyData::from_bytes(&vk.key_data)?;
+
+    // Parameters are generated last: an untrusted key naming an unknown
+    // circuit must not cost the verifier a 2^k setup
+    let builder = *circuit_registry()
+        .read()
+        .expect("circuit registry poisoned")
+        .get(&key_data.circuit)
+        .ok_or_else(|| {
+            CryptoError::OperationFailed(format!("Unknown circuit: {}", key_data.circuit))
+        })?;
+
+    let cached = vk_cache()
+        .read()
+        .expect("vk cache poisoned")
+        .get(&id)
+        .cloned();
+    let params = params(key_data.k)?;
+    if let Some(cached) = cached {
+        return Ok((params, cached));
+    }
+
+    let halo2_vk = builder(&params)?;
+    if pinned_hash(&halo2_vk) != key_data.pinned_hash {
+        return Err(CryptoError::InvalidKey);
+    }
+
+    let halo2_vk = Arc::new(halo2_vk);
+    vk_cache()
+        .write()
+        .expect("vk cache poisoned")
+        .insert(id, halo2_vk.clone());
+
+    Ok((params, halo2_vk))
+}
+
+/// Encode a field element as a public input
+pub fn encode_instance(value: &pallas::Base) -> Vec<u8> {
+    value.to_repr().to_vec()
+}
+
+/// Decode public inputs as `pallas::Base` instances (canonical 32-byte encodings)
+pub fn decode_instances(public_inputs: &[Vec<u8>]) -> Result<Vec<pallas::Base>> {
+    public_inputs
+        .iter()
+        .map(|bytes| {
+            let repr: [u8; 32] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| CryptoError::InvalidProof)?;
+            Option::from(pallas::Base::from_repr(repr)).ok_or(CryptoError::InvalidProof)
+        })
+        .collect()
+}
+
+/// Create a proof for a circuit with a single instance column
+pub fn create_proof<C, R>(
+    params: &Halo2Params,
+    pk: &Halo2ProvingKey,
+    circuit: C,
+    instances: &[pallas::Base],
+    rng: R,
+) -> Result<Vec<u8>>
+where
+    C: Circuit<pallas::Base>,
+    R: RngCore + CryptoRng,
+{
+    let mut transcript = Blake2bWrite::<_, vesta::Affine, Challenge255<_>>::init(vec![]);
+    plonk::create_proof(
+        params,
+        pk,
+        &[circuit],
+        &[&[instances]],
+        rng,
+        &mut transcript,
+    )
+    .map_err(|e| CryptoError::OperationFailed(format!("Proof creation failed: {:?}", e)))?;
+
+    Ok(transcript.finalize())
+}
+
+/// Verify a proof for a circuit with a single instance column
+///
+/// The proof must be exactly the transcript: trailing bytes would let
+/// distinct byte strings, and so distinct proof hashes, verify as one proof.
+pub fn verify_proof(
+    params: &Halo2Params,
+    vk: &Halo2VerifyingKey,
+    proof: &[u8],
+    instances: &[pallas::Base],
+) -> bool {
+    read_proof(proof, |transcript| {
+        let strategy = SingleVerifier::new(params);
+        plonk::verify_proof(params, vk, strategy, &[&[instances]], transcript).ok()
+    })
+    .is_some()
+}
+
+/// Verify many proofs for the same circuit with one accumulated MSM
+///
+/// Returns `true` iff every proof verifies. The expensive multi-scalar
+/// multiplications of all proofs are combined under random weights, and the
+/// per-proof transcript work runs on the current rayon thread pool. As in
+/// [`verify_proof`], each proof must be exactly its transcript.
+pub fn batch_verify(
+    params: &Halo2Params,
+    vk: &Halo2VerifyingKey,
+    proofs: &[(&[u8], &[pallas::Base])],
+) -> bool {
+    let msms: Option<Vec<MSM<'_, vesta::Affine>>> = proofs
+        .par_iter()
+        .map(|(proof, instances)| {
+            read_proof(proof, |transcript| {
+                let strategy = DeferMsm(params.empty_msm());
+                plonk::verify_proof(params, vk, strategy, &[&[*instances]], transcript).ok()
+            })
+        })
+        .collect();
+    let Some(msms) = msms else {
+        return false;
+    };
+
+    // Random weights keep one proof's MSM from cancelling another's
+    let mut rng = rand::rngs::OsRng;
+    let mut acc = params.empty_msm();
+    for mut msm in msms {
+        msm.scale(pallas::Base::random(&mut rng));
+        acc.add_msm(&msm);
+    }
+    acc.eval()
+}
+
+/// Transcript reader over a proof's bytes
+pub(crate) type ProofTranscript<'a, 'b> =
+    Blake2bRead<&'a mut &'b [u8], vesta::Affine, Challenge255<vesta::Affine>>;
+
+/// Run `verify` on a transcript of `proof`, failing if it leaves bytes unread
+pub(crate) fn read_proof<T>(
+    proof: &[u8],
+    verify: impl FnOnce(&mut ProofTranscript<'_, '_>) -> Option<T>,
+) -> Option<T> {
+    let mut reader = proof;
+    let output = verify(&mut Blake2bRead::init(&mut reader));
+    output.filter(|_| reader.is_empty())
+}
+
+/// Verification strategy that returns a proof's MSM instead of evaluating it
+struct DeferMsm<'params>(MSM<'params, vesta::Affine>);
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for DeferMsm<'params> {
+    type Output = MSM<'params, vesta::Affine>;
+
+    fn process<E: EncodedChallenge<vesta::Affine>>(
+        self,
+        f: impl FnOnce(
+            MSM<'params, vesta::Affine>,
+        ) -> std::result::Result<Guard<'params, vesta::Affine, E>, plonk::Error>,
+    ) -> std::result::Result<Self::Output, plonk::Error> {
+        Ok(f(self.0)?.use_challenges())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::plonk::{
+        keygen_pk, keygen_vk, Advice, Column, ConstraintSystem, Instance, Selector,
+    };
+    use halo2_proofs::poly::Rotation;
+
+    pub(crate) const TEST_CIRCUIT: &str = "test-square";
+    pub(crate) const TEST_K: u32 = 4;
+
+    /// Proves knowledge of `x` such that `x² = y` for a public `y`
+    #[derive(Clone, Default)]
+    pub(crate) struct SquareCircuit {
+        pub x: Value<pallas::Base>,
+    }
+
+    #[derive(Clone)]
+    pub(crate) struct SquareConfig {
+        advice: Column<Advice>,
+        instance: Column<Instance>,
+        selector: Selector,
+    }
+
+    impl Circuit<pallas::Base> for SquareCircuit {
+        type Config = SquareConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> SquareConfig {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            let selector = meta.selector();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+
+            meta.create_gate("square", |meta| {
+                let s = meta.query_selector(selector);
+                let x = meta.query_advice(advice, Rotation::cur());
+                let y = meta.query_advice(advice, Rotation::next());
+                vec![s * (x.clone() * x - y)]
+            });
+
+            SquareConfig {
+                advice,
+                instance,
+                selector,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: SquareConfig,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> std::result::Result<(), plonk::Error> {
+            let y = layouter.assign_region(
+                || "square",
+                |mut region| {
+                    config.selector.enable(&mut region, 0)?;
+                    region.assign_advice(|| "x", config.advice, 0, || self.x)?;
+                    region.assign_advice(|| "y", config.advice, 1, || self.x.map(|x| x.square()))
+                },
+            )?;
+
+            layouter.constrain_instance(y.cell(), config.instance, 0)
+        }
+    }
+
+    pub(crate) fn build_square_vk(params: &Halo2Params) -> Result<Halo2VerifyingKey> {
+        keygen_vk(params, &SquareCircuit::default())
+            .map_err(|e| CryptoError::OperationFailed(format!("{:?}", e)))
+    }
+
+    /// Register the test circuit and prove `x² = y`
+    pub(crate) fn square_proof(x: u64) -> (VerificationKey, Vec<u8>, pallas::Base) {
+        register_circuit(TEST_CIRCUIT, build_square_vk);
+
+        let params = params(TEST_K).unwrap();
+        let vk = build_square_vk(&params).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &SquareCircuit::default()).unwrap();
+
+        let x = pallas::Base::from(x);
+        let y = x.square();
+        let circuit = SquareCircuit { x: Value::known(x) };
+        let proof = create_proof(&params, &pk, circuit, &[y], rand::rngs::OsRng).unwrap();
+
+        (verification_key(TEST_CIRCUIT, TEST_K, &vk), proof, y)
+    }
+
+    #[test]
+    fn test_params_cached() {
+        let p1 = params(TEST_K).unwrap();
+        let p2 = params(TEST_K).unwrap();
+        assert!(Arc::ptr_eq(&p1, &p2));
+
+        assert!(params(0).is_err());
+        assert!(params(MAX_K + 1).is_err());
+    }
+
+    #[test]
+    fn test_install_params_first_wins() {
+        // k=3 is not used by other tests, so the first install is this one
+        let installed = install_params(3, Halo2Params::new(3)).unwrap();
+        assert!(Arc::ptr_eq(&installed, &params(3).unwrap()));
+        assert!(Arc::ptr_eq(
+            &installed,
+            &install_params(3, Halo2Params::new(3)).unwrap()
+        ));
+
+        assert!(install_params(MAX_K + 1, Halo2Params::new(1)).is_err());
+    }
+
+    #[test]
+    fn test_key_data_roundtrip() {
+        let key_data = Halo2KeyData {
+            circuit: "spend".into(),
+            k: 11,
+            pinned_hash: [9u8; 32],
+        };
+        assert_eq!(
+            Halo2KeyData::from_bytes(&key_data.to_bytes()).unwrap(),
+            key_data
+        );
+
+        assert!(Halo2KeyData::from_bytes(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (vk, proof, y) = square_proof(7);
+        let (params, halo2_vk) = load_verifying_key(&vk).unwrap();
+
+        assert!(verify_proof(&params, &halo2_vk, &proof, &[y]));
+
+        // Wrong public input
+        assert!(!verify_proof(
+            &params,
+            &halo2_vk,
+            &proof,
+            &[y + pallas::Base::one()]
+        ));
+
+        // Corrupted proof
+        let mut corrupted = proof.clone();
+        corrupted[10] ^= 1;
+        assert!(!verify_proof(&params, &halo2_vk, &corrupted, &[y]));
+
+        // Truncated proof
+        assert!(!verify_proof(
+            &params,
+            &halo2_vk,
+            &proof[..proof.len() / 2],
+            &[y]
+        ));
+
+        // Trailing bytes
+        let padded = [&proof[..], &[0]].concat();
+        assert!(!verify_proof(&params, &halo2_vk, &padded, &[y]));
+    }
+
+    #[test]
+    fn test_pinned_hash_mismatch_fails_closed() {
+        register_circuit(TEST_CIRCUIT, build_square_vk);
+
+        let key_data = Halo2KeyData {
+            circuit: TEST_CIRCUIT.into(),
+            k: TEST_K,
+            pinned_hash: [0u8; 32],
+        };
+        let vk = VerificationKey::new(ProofSystem::Halo2, key_data.to_bytes());
+        assert!(load_verifying_key(&vk).is_err());
+
+        let unknown = Halo2KeyData {
+            circuit: "no-such-circuit".into(),
+            k: TEST_K,
+            pinned_hash: [0u8; 32],
+        };
+        let vk = VerificationKey::new(ProofSystem::Halo2, unknown.to_bytes());
+        assert!(load_verifying_key(&vk).is_err());
+    }
+
+    #[test]
+    fn test_unknown_circuit_generates_no_params() {
+        let unknown = Halo2KeyData {
+            circuit: "no-such-circuit".into(),
+            k: MAX_K,
+            pinned_hash: [0u8; 32],
+        };
+        let vk = VerificationKey::new(ProofSystem::Halo2, unknown.to_bytes());
+        assert!(load_verifying_key(&vk).is_err());
+        assert!(!params_cache()
+            .read()
+            .expect("params cache poisoned")
+            .contains_key(&MAX_K));
+    }
+
+    #[test]
+    fn test_cache_keyed_by_recomputed_id() {
+        let (vk, proof, y) = square_proof(5);
+        load_verifying_key(&vk).unwrap();
+
+        // Key data that fails its pinned hash, claiming the cached key's id
+        let forged_data = Halo2KeyData {
+            circuit: TEST_CIRCUIT.into(),
+            k: TEST_K,
+            pinned_hash: [0u8; 32],
+        };
+        let mut forged = VerificationKey::new(ProofSystem::Halo2, forged_data.to_bytes());
+        forged.key_hash = vk.id();
+        assert!(load_verifying_key(&forged).is_err());
+
+        // The genuine key data under a wrong id is rejected too
+        let mut renamed = vk.clone();
+        renamed.key_hash = [7u8; 32];
+        assert!(load_verifying_key(&renamed).is_err());
+
+        let (params, halo2_vk) = load_verifying_key(&vk).unwrap();
+        assert!(verify_proof(&params, &halo2_vk, &proof, &[y]));
+    }
+
+    #[test]
+    fn test_pinned_hash_is_stable() {
+        let params = params(TEST_K).unwrap();
+        let first = build_square_vk(&params).unwrap();
+        let second = build_square_vk(&params).unwrap();
+        assert_eq!(pinned_hash(&first), pinned_hash(&second));
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let proofs: Vec<_> = (1..=4).map(square_proof).collect();
+        let (params, halo2_vk) = load_verifying_key(&proofs[0].0).unwrap();
+
+        let batch: Vec<(&[u8], &[pallas::Base])> = proofs
+            .iter()
+            .map(|(_, proof, y)| (proof.as_slice(), std::slice::from_ref(y)))
+            .collect();
+        assert!(batch_verify(&params, &halo2_vk, &batch));
+        assert!(batch_verify(&params, &halo2_vk, &[]));
+
+        // One wrong instance fails the whole batch
+        let wrong = proofs[0].2 + pallas::Base::one();
+        let mut bad_batch = batch.clone();
+        bad_batch[2].1 = std::slice::from_ref(&wrong);
+        assert!(!batch_verify(&params, &halo2_vk, &bad_batch));
+
+        // So does one proof with trailing bytes
+        let padded = [proofs[1].1.as_slice(), &[0]].concat();
+        let mut padded_batch = batch.clone();
+        padded_batch[1].0 = &padded;
+        assert!(!batch_verify(&params, &halo2_vk, &padded_batch));
+    }
+
+    #[test]
+    fn test_decode_instances() {
+        let value = pallas::Base::from(42u64);
+        let decoded = decode_instances(&[encode_instance(&value)]).unwrap();
+        assert_eq!(decoded, vec![value]);
+
+        // Wrong length and non-canonical encodings are rejected
+        assert!(decode_instances(&[vec![1, 2, 3]]).is_err());
+        assert!(decode_instances(&[vec![0xff; 32]]).is_err());
+    }
+}
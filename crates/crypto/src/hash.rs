@@ -5,6 +5,8 @@
 //! - Poseidon: ZK-friendly algebraic hash function
 
 use blake3::Hasher as Blake3Hasher;
+use ff::FromUniformBytes;
+use halo2_gadgets::poseidon::primitives::{self as poseidon, ConstantLength, P128Pow5T3};
 use pasta_curves::group::ff::PrimeField;
 use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
@@ -60,7 +62,7 @@ impl fmt::Debug for Blake3Hash {
 
 impl fmt::Display for Blake3Hash {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", hex::encode(&self.0))
+        write!(f, "{}", hex::encode(self.0))
     }
 }
 
@@ -116,18 +118,17 @@ impl PoseidonHash {
         self.0
     }
 
+    /// Hash a fixed number of field elements
+    ///
+    /// Uses the P128Pow5T3 instance of Poseidon, the same permutation as the
+    /// `halo2_gadgets` Poseidon chip, so circuits can recompute the hash.
+    pub fn hash<const L: usize>(inputs: [pallas::Base; L]) -> Self {
+        Self(poseidon::Hash::<_, P128Pow5T3, ConstantLength<L>, 3, 2>::init().hash(inputs))
+    }
+
     /// Hash two field elements (2-to-1 hash)
     pub fn hash_two(left: pallas::Base, right: pallas::Base) -> Self {
-        // Simplified Poseidon permutation
-        // In production, this would use the full Poseidon specification
-        // with proper round constants and S-box operations
-
-        // Placeholder: simple combination
-        let sum = left + right;
-        let squared = sum.square();
-        let cubed = squared * sum;
-
-        Self(cubed)
+        Self::hash([left, right])
     }
 
     /// Hash multiple field elements
@@ -153,7 +154,7 @@ impl PoseidonHash {
             // Use 31 bytes to ensure we're below the field modulus
             let mut bytes = [0u8; 32];
             bytes[1..1 + chunk.len()].copy_from_slice(chunk);
-            let field = pallas::Base::from_repr(bytes.into()).unwrap_or(pallas::Base::zero());
+            let field = pallas::Base::from_repr(bytes).unwrap_or(pallas::Base::zero());
             fields.push(field);
         }
 
@@ -197,7 +198,7 @@ impl Hash {
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
             Hash::Blake3(h) => h.0.to_vec(),
-            Hash::Poseidon(h) => {
+            Hash::Poseidon(_) => {
                 // Convert field element to bytes
                 vec![]
             }
@@ -216,7 +217,6 @@ impl fmt::Debug for Hash {
 
 /// Domain separation for different hash contexts
 pub struct DomainSeparatedHasher {
-    domain: &'static str,
     hasher: Blake3,
 }
 
@@ -227,7 +227,7 @@ impl DomainSeparatedHasher {
         hasher.update(domain.as_bytes());
         hasher.update(&[0u8]); // Null separator
 
-        Self { domain, hasher }
+        Self { hasher }
     }
 
     /// Update with data
@@ -239,17 +239,31 @@ impl DomainSeparatedHasher {
     pub fn finalize(self) -> Blake3Hash {
         self.hasher.finalize()
     }
+
+    /// Finalize to 64 bytes of output, enough to reduce into a field without
+    /// noticeable bias
+    pub fn finalize_wide(self) -> [u8; 64] {
+        let mut wide = [0u8; 64];
+        self.hasher.hasher.finalize_xof().fill(&mut wide);
+        wide
+    }
+}
+
+/// Map 32 bytes to a field element, reducing non-canonical encodings mod p
+pub fn bytes_to_base(bytes: &[u8; 32]) -> pallas::Base {
+    let mut wide = [0u8; 64];
+    wide[..32].copy_from_slice(bytes);
+    pallas::Base::from_uniform_bytes(&wide)
 }
 
 /// Hash function for Merkle trees (Poseidon 2-to-1)
+///
+/// Nodes are canonical `pallas::Base` encodings, which is what the spend
+/// circuit hashes; other bytes are reduced mod p first.
 pub fn merkle_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    // For ZK circuits, we'd use Poseidon
-    // For now, using Blake3 for simplicity
-    let mut hasher = Blake3::new();
-    hasher.update(left);
-    hasher.update(right);
-    let hash = hasher.finalize();
-    *hash.as_bytes()
+    PoseidonHash::hash_two(bytes_to_base(left), bytes_to_base(right))
+        .to_field()
+        .to_repr()
 }
 
 #[cfg(test)]
@@ -274,6 +288,7 @@ mod tests {
     fn test_poseidon_hash() {
         let data = b"Test data";
         let hash1 = PoseidonHash::hash_bytes(data);
+        assert_eq!(hash1, PoseidonHash::hash_bytes(data));
 
         // Test field element hashing
         let field1 = pallas::Base::from(42u64);
@@ -319,5 +334,9 @@ mod tests {
         // Order matters
         let hash3 = merkle_hash(&right, &left);
         assert_ne!(hash1, hash3);
+
+        // Matches Poseidon over the decoded field elements
+        let expected = PoseidonHash::hash_two(bytes_to_base(&left), bytes_to_base(&right));
+        assert_eq!(hash1, expected.to_field().to_repr());
     }
 }
\ No newline at end of file
@@ -4,14 +4,36 @@
 //! - Spending keys (for authorizing spends)
 //! - Viewing keys (for decrypting notes)
 //! - Nullifier deriving keys (for generating nullifiers)
+//! - Full viewing keys (for proving what a user holds without spending it)
+//! - Payment addresses (for encrypting notes to a recipient)
 
+use pasta_curves::arithmetic::CurveExt;
+use pasta_curves::group::ff::FromUniformBytes;
+use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::hash::DomainSeparatedHasher;
+use crate::note_encryption::{self, KemMode};
 use crate::nullifier::NullifierDerivingKey;
 use crate::{CryptoError, Point, Result, Scalar};
 
+/// Fixed base `N` binding the nullifier deriving key into the public key
+///
+/// `N = hash_to_curve("PRIVL1_KEYS")("N")`, so its discrete log relative to
+/// the generator is unknown.
+pub fn nullifier_key_base() -> Point {
+    Point::from_inner(pallas::Point::hash_to_curve("PRIVL1_KEYS")(b"N"))
+}
+
+/// Re-randomize a spend validating key: `rk = ak + [alpha]G`
+///
+/// Spends reveal `rk` rather than `ak`, so they cannot be linked to the owner.
+/// The matching signing key is [`SpendingKey::randomize`].
+pub fn randomize_validating_key(ak: &Point, alpha: &Scalar) -> Point {
+    *ak + Point::generator().mul(alpha)
+}
+
 /// A spending key - the root of all other keys
 #[derive(Clone, Debug)]
 pub struct SpendingKey {
@@ -29,14 +51,9 @@ impl SpendingKey {
 
     /// Derive from a seed
     pub fn from_seed(seed: &[u8; 32]) -> Self {
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_SPENDING_KEY");
-        hasher.update(seed);
-        let hash = hasher.finalize();
-
-        // Convert to scalar from hash
-        let sk = Scalar::from_bytes(hash.as_bytes()).unwrap_or(Scalar::zero());
-
-        Self { sk }
+        Self {
+            sk: derive_key_scalar("PRIVL1_SPENDING_KEY", &[seed]),
+        }
     }
 
     /// Derive the nullifier deriving key
@@ -59,14 +76,35 @@ impl SpendingKey {
         PublicKey::from_spending_key(self)
     }
 
-    /// Sign a message
-    pub fn sign(&self, _message: &[u8]) -> Signature {
-        // Simplified Schnorr signature
-        // In production, use proper signature scheme
-        Signature {
-            r: Point::generator(),
-            s: self.sk,
-        }
+    /// Get the full viewing key `(ak, nk)`
+    pub fn full_viewing_key(&self) -> FullViewingKey {
+        FullViewingKey::new(self.validating_key(), self.nullifier_key())
+    }
+
+    /// Get the spend validating key `ak = [sk]G`
+    pub fn validating_key(&self) -> Point {
+        Point::generator().mul(&self.sk)
+    }
+
+    /// Get the signing key for the re-randomized key `rk = ak + [alpha]G`
+    pub fn randomize(&self, alpha: &Scalar) -> Scalar {
+        self.sk + *alpha
+    }
+
+    /// Sign a message under the validating key `ak = [sk]G`
+    ///
+    /// Schnorr with a deterministic nonce `k = H(sk, message)`; verify with
+    /// [`Signature::verify`].
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature::sign(&self.sk, message)
+    }
+
+    /// Sign a message under the re-randomized key `rk = ak + [alpha]G`
+    ///
+    /// This is the spend authorization signature a spend revealing `rk`
+    /// carries; only the spending key can produce it.
+    pub fn sign_randomized(&self, alpha: &Scalar, message: &[u8]) -> Signature {
+        Signature::sign(&self.randomize(alpha), message)
     }
 
     /// Get the secret scalar
@@ -83,17 +121,12 @@ pub struct PublicKey {
 }
 
 impl PublicKey {
-    /// Derive from spending key
+    /// Derive from spending key: `[sk]G + [nk]N`
+    ///
+    /// Committing to `nk` lets the spend circuit check that a note's nullifier
+    /// is derived with its owner's nullifier key.
     pub fn from_spending_key(sk: &SpendingKey) -> Self {
-        let point = Point::generator().mul(&sk.sk);
-        Self { point }
-    }
-
-    /// Verify a signature
-    pub fn verify(&self, message: &[u8], signature: &Signature) -> bool {
-        // Simplified verification
-        // In production, use proper signature verification
-        true
+        sk.full_viewing_key().public_key()
     }
 
     /// Serialize to bytes
@@ -122,43 +155,81 @@ impl fmt::Display for PublicKey {
 
 /// A viewing key (for decrypting notes)
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "SerializedViewingKey")]
 pub struct ViewingKey {
     /// Incoming viewing key (decrypt received notes)
     ivk: Scalar,
     /// Outgoing viewing key (decrypt sent notes)
     ovk: Scalar,
+    /// Seed for the ML-KEM key pair used by hybrid note encryption
+    kem_seed: [u8; 32],
+}
+
+/// Serialized viewing key, where keys serialized before hybrid encryption
+/// lack the KEM seed
+#[derive(Deserialize)]
+struct SerializedViewingKey {
+    ivk: Scalar,
+    ovk: Scalar,
+    kem_seed: Option<[u8; 32]>,
+}
+
+impl From<SerializedViewingKey> for ViewingKey {
+    fn from(key: SerializedViewingKey) -> Self {
+        // Legacy keys have no spending key at hand, so their seed is derived
+        // from the secret viewing keys instead
+        let kem_seed = key.kem_seed.unwrap_or_else(|| {
+            let mut hasher = DomainSeparatedHasher::new("PRIVL1_DERIVE_LEGACY_KEM_SEED");
+            hasher.update(&key.ivk.to_bytes());
+            hasher.update(&key.ovk.to_bytes());
+            *hasher.finalize().as_bytes()
+        });
+        Self {
+            ivk: key.ivk,
+            ovk: key.ovk,
+            kem_seed,
+        }
+    }
 }
 
 impl ViewingKey {
     /// Derive from spending key
     pub fn derive_from_spending_key(sk: &SpendingKey) -> Self {
-        // Derive incoming viewing key
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_DERIVE_IVK");
         let sk_bytes = sk.sk.to_bytes();
-        hasher.update(&sk_bytes);
-        let ivk_hash = hasher.finalize();
 
-        // Derive outgoing viewing key
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_DERIVE_OVK");
+        // Derive the post-quantum KEM seed
+        let mut hasher = DomainSeparatedHasher::new("PRIVL1_DERIVE_KEM_SEED");
         hasher.update(&sk_bytes);
-        let ovk_hash = hasher.finalize();
+        let kem_hash = hasher.finalize();
 
-        // Convert to scalars
+        // Reduce the incoming and outgoing viewing keys from hashes, which
+        // are not canonical scalars in general
         Self {
-            ivk: Scalar::from_bytes(ivk_hash.as_bytes()).unwrap_or(Scalar::zero()),
-            ovk: Scalar::from_bytes(ovk_hash.as_bytes()).unwrap_or(Scalar::zero()),
+            ivk: derive_key_scalar("PRIVL1_DERIVE_IVK", &[&sk_bytes]),
+            ovk: derive_key_scalar("PRIVL1_DERIVE_OVK", &[&sk_bytes]),
+            kem_seed: *kem_hash.as_bytes(),
         }
     }
 
     /// Decrypt a note encrypted to this viewing key
     pub fn decrypt_note(&self, encrypted_note: &EncryptedNote) -> Result<DecryptedNote> {
-        // Simplified decryption
-        // In production, use proper encryption scheme
-        Ok(DecryptedNote {
-            value: 0,
-            asset_id: [0u8; 32],
-            memo: vec![],
-        })
+        note_encryption::decrypt_note(encrypted_note, self)
+    }
+
+    /// Get the transmission key (ECDH target for note encryption)
+    pub fn transmission_key(&self) -> Point {
+        Point::generator().mul(&self.ivk)
+    }
+
+    /// Get the encoded ML-KEM encapsulation key for hybrid addresses
+    pub fn kem_encapsulation_key(&self) -> Vec<u8> {
+        let (_, ek) = note_encryption::kem_keypair(&self.kem_seed);
+        note_encryption::encode_encapsulation_key(&ek)
+    }
+
+    /// Get the ML-KEM seed
+    pub(crate) fn kem_seed(&self) -> &[u8; 32] {
+        &self.kem_seed
     }
 
     /// Get incoming viewing key
@@ -172,6 +243,133 @@ impl ViewingKey {
     }
 }
 
+/// Address flag: the recipient accepts hybrid (ECDH + ML-KEM) note encryption
+pub const ADDRESS_FLAG_HYBRID_KEM: u8 = 0b0000_0001;
+
+/// A payment address (what a recipient publishes to receive notes)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "SerializedPaymentAddress")]
+pub struct PaymentAddress {
+    /// The note owner
+    owner: PublicKey,
+    /// ECDH target for note encryption
+    transmission_key: Point,
+    /// Address flags
+    flags: u8,
+    /// Encoded ML-KEM encapsulation key (hybrid addresses only)
+    kem_key: Option<Vec<u8>>,
+}
+
+/// Serialized payment address, checked as `PaymentAddress::from_bytes` checks
+/// the byte encoding
+#[derive(Deserialize)]
+struct SerializedPaymentAddress {
+    owner: PublicKey,
+    transmission_key: Point,
+    flags: u8,
+    kem_key: Option<Vec<u8>>,
+}
+
+impl TryFrom<SerializedPaymentAddress> for PaymentAddress {
+    type Error = CryptoError;
+
+    fn try_from(address: SerializedPaymentAddress) -> Result<Self> {
+        let unchecked = Self {
+            owner: address.owner,
+            transmission_key: address.transmission_key,
+            flags: address.flags,
+            kem_key: address.kem_key,
+        };
+        Self::from_bytes(&unchecked.to_bytes())
+    }
+}
+
+impl PaymentAddress {
+    /// Create a classical address
+    pub fn new(owner: PublicKey, transmission_key: Point) -> Self {
+        Self {
+            owner,
+            transmission_key,
+            flags: 0,
+            kem_key: None,
+        }
+    }
+
+    /// Create a hybrid address that opts in to ML-KEM note encryption
+    pub fn hybrid(owner: PublicKey, transmission_key: Point, kem_key: Vec<u8>) -> Result<Self> {
+        if kem_key.len() != note_encryption::KEM_ENCAPSULATION_KEY_SIZE {
+            return Err(CryptoError::InvalidKey);
+        }
+
+        Ok(Self {
+            owner,
+            transmission_key,
+            flags: ADDRESS_FLAG_HYBRID_KEM,
+            kem_key: Some(kem_key),
+        })
+    }
+
+    /// Get the owner's public key
+    pub fn owner(&self) -> &PublicKey {
+        &self.owner
+    }
+
+    /// Get the transmission key
+    pub fn transmission_key(&self) -> &Point {
+        &self.transmission_key
+    }
+
+    /// Get the address flags
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Check if the recipient opted in to hybrid note encryption
+    pub fn is_hybrid(&self) -> bool {
+        self.flags & ADDRESS_FLAG_HYBRID_KEM != 0
+    }
+
+    /// Get the encoded ML-KEM encapsulation key
+    pub fn kem_key(&self) -> Option<&[u8]> {
+        self.kem_key.as_deref()
+    }
+
+    /// Serialize to bytes: owner || transmission_key || flags || kem_key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(65 + self.kem_key.as_ref().map_or(0, |k| k.len()));
+        bytes.extend_from_slice(&self.owner.to_bytes());
+        bytes.extend_from_slice(&self.transmission_key.to_bytes());
+        bytes.push(self.flags);
+        if let Some(kem_key) = &self.kem_key {
+            bytes.extend_from_slice(kem_key);
+        }
+        bytes
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 65 {
+            return Err(CryptoError::InvalidKey);
+        }
+
+        let mut owner = [0u8; 32];
+        let mut transmission_key = [0u8; 32];
+        owner.copy_from_slice(&bytes[0..32]);
+        transmission_key.copy_from_slice(&bytes[32..64]);
+
+        let owner = PublicKey::from_bytes(&owner)?;
+        let transmission_key = Point::from_bytes(&transmission_key)?;
+        let flags = bytes[64];
+
+        // Any other flag byte would not survive a round trip
+        match flags {
+            ADDRESS_FLAG_HYBRID_KEM => Self::hybrid(owner, transmission_key, bytes[65..].to_vec()),
+            0 if bytes.len() == 65 => Ok(Self::new(owner, transmission_key)),
+            _ => Err(CryptoError::InvalidKey),
+        }
+    }
+}
+
 /// An encrypted note
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EncryptedNote {
@@ -181,6 +379,20 @@ pub struct EncryptedNote {
     pub ciphertext: Vec<u8>,
     /// MAC tag
     pub tag: [u8; 16],
+    /// ML-KEM ciphertext (hybrid mode only)
+    #[serde(default)]
+    pub kem_ciphertext: Option<Vec<u8>>,
+}
+
+impl EncryptedNote {
+    /// Get the key agreement mode used for this note
+    pub fn mode(&self) -> KemMode {
+        if self.kem_ciphertext.is_some() {
+            KemMode::Hybrid
+        } else {
+            KemMode::Classical
+        }
+    }
 }
 
 /// A decrypted note
@@ -190,8 +402,12 @@ pub struct DecryptedNote {
     pub value: u64,
     /// The asset ID
     pub asset_id: [u8; 32],
+    /// The owner's public key
+    pub owner: PublicKey,
+    /// The note randomness
+    pub randomness: Scalar,
     /// Optional memo
-    pub memo: Vec<u8>,
+    pub memo: Option<Vec<u8>>,
 }
 
 /// A signature
@@ -204,6 +420,14 @@ pub struct Signature {
 }
 
 impl Signature {
+    /// Schnorr signature under `[sk]G`, with a deterministic nonce
+    fn sign(sk: &Scalar, message: &[u8]) -> Self {
+        let k = hash_to_scalar("PRIVL1_SIGNATURE_NONCE", &[&sk.to_bytes(), message]);
+        let r = Point::generator().mul(&k);
+        let c = challenge(&r, &Point::generator().mul(sk), message);
+        Self { r, s: k + c * *sk }
+    }
+
     /// Serialize to bytes
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(64);
@@ -212,6 +436,18 @@ impl Signature {
         bytes
     }
 
+    /// Verify against a validating key `ak`, or a randomized key `rk`
+    ///
+    /// Checks `[s]G = R + [c]key` with `c = H(R, key, message)`. The identity
+    /// key is rejected, since any `(R, s)` with `[s]G = R` would verify under it.
+    pub fn verify(&self, key: &Point, message: &[u8]) -> bool {
+        if key.is_identity() {
+            return false;
+        }
+        let c = challenge(&self.r, key, message);
+        Point::generator().mul(&self.s) == self.r + key.mul(&c)
+    }
+
     /// Deserialize from bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
         if bytes.len() != 64 {
@@ -230,6 +466,105 @@ impl Signature {
     }
 }
 
+/// Schnorr challenge `c = H(R, key, message)`
+fn challenge(r: &Point, key: &Point, message: &[u8]) -> Scalar {
+    hash_to_scalar(
+        "PRIVL1_SIGNATURE_CHALLENGE",
+        &[&r.to_bytes(), &key.to_bytes(), message],
+    )
+}
+
+/// Hash `parts` under `domain` to a scalar, reducing 64 bytes of output
+fn hash_to_scalar(domain: &'static str, parts: &[&[u8]]) -> Scalar {
+    Scalar::from_inner(pallas::Scalar::from_uniform_bytes(&hash_wide(domain, parts)))
+}
+
+/// Derive a secret key scalar from `parts` under `domain`
+///
+/// Keys used to be the 32-byte hash read as a scalar, or zero when the hash
+/// was not a canonical scalar, which is most of the time. A seed whose old
+/// key was a valid nonzero scalar keeps that key; the first 32 bytes of the
+/// wide hash are the old hash. Any other seed used to derive the zero key,
+/// shared by every such seed, and now derives the reduced wide hash, so
+/// funds held under the zero key must be moved with the old key.
+pub(crate) fn derive_key_scalar(domain: &'static str, parts: &[&[u8]]) -> Scalar {
+    let wide = hash_wide(domain, parts);
+    let legacy: [u8; 32] = wide[..32].try_into().expect("32-byte prefix");
+    match Scalar::from_bytes(&legacy) {
+        Ok(sk) if sk != Scalar::zero() => sk,
+        _ => Scalar::from_inner(pallas::Scalar::from_uniform_bytes(&wide)),
+    }
+}
+
+fn hash_wide(domain: &'static str, parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = DomainSeparatedHasher::new(domain);
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize_wide()
+}
+
+/// The validating key `ak` and nullifier deriving key `nk` of a user
+///
+/// They identify the user's notes and derive their nullifiers, which is all
+/// a proof of what the user holds needs. They are also enough to build the
+/// proof of any action on a note, but every such action is verified with a
+/// signature that needs the spending key: a transaction's spends under `rk`
+/// (`TransactionProof::authorizes_spends`), the swap, veNFT lock, gauge vote
+/// and bribe claim proofs of `privl1-circuits` under `rk`, and contract notes
+/// under `ak`. Whoever holds them sees and can link all of the user's notes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FullViewingKey {
+    ak: Point,
+    nk: NullifierDerivingKey,
+}
+
+impl FullViewingKey {
+    /// Length of [`Self::to_bytes`]
+    pub const LEN: usize = 64;
+
+    pub fn new(ak: Point, nk: NullifierDerivingKey) -> Self {
+        Self { ak, nk }
+    }
+
+    /// Get the spend validating key `ak`
+    pub fn validating_key(&self) -> &Point {
+        &self.ak
+    }
+
+    /// Get the nullifier deriving key `nk`
+    pub fn nullifier_key(&self) -> &NullifierDerivingKey {
+        &self.nk
+    }
+
+    /// The public key `ak + [nk]N` owning the user's notes
+    pub fn public_key(&self) -> PublicKey {
+        let nk = Scalar::from_base(self.nk.to_base());
+        PublicKey {
+            point: self.ak + nullifier_key_base().mul(&nk),
+        }
+    }
+
+    /// Serialize as `ak || nk`
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[..32].copy_from_slice(&self.ak.to_bytes());
+        bytes[32..].copy_from_slice(&self.nk.as_scalar().to_bytes());
+        bytes
+    }
+
+    /// Deserialize from [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8; Self::LEN]) -> Result<Self> {
+        let half = |range: std::ops::Range<usize>| -> [u8; 32] {
+            bytes[range].try_into().expect("32-byte half")
+        };
+        Ok(Self {
+            ak: Point::from_bytes(&half(0..32))?,
+            nk: NullifierDerivingKey::from_bytes(&half(32..64))?,
+        })
+    }
+}
+
 /// Full key set for a user
 #[derive(Clone, Debug)]
 pub struct FullKeys {
@@ -269,6 +604,26 @@ impl FullKeys {
         let spending = SpendingKey::from_seed(seed);
         Self::from_spending_key(spending)
     }
+
+    /// Get the full viewing key `(ak, nk)`
+    pub fn full_viewing_key(&self) -> FullViewingKey {
+        FullViewingKey::new(self.spending.validating_key(), self.nullifier.clone())
+    }
+
+    /// Get a classical payment address
+    pub fn address(&self) -> PaymentAddress {
+        PaymentAddress::new(self.public, self.viewing.transmission_key())
+    }
+
+    /// Get a hybrid payment address (opts in to ML-KEM note encryption)
+    pub fn hybrid_address(&self) -> PaymentAddress {
+        PaymentAddress::hybrid(
+            self.public,
+            self.viewing.transmission_key(),
+            self.viewing.kem_encapsulation_key(),
+        )
+        .expect("derived ML-KEM key has the encoded size")
+    }
 }
 
 #[cfg(test)]
@@ -302,6 +657,84 @@ mod tests {
         assert_eq!(keys1.spending.as_scalar(), keys2.spending.as_scalar());
     }
 
+    #[test]
+    fn test_seed_derivation_is_never_zero() {
+        // Seeds are reduced from a wide hash rather than falling back to zero
+        // when the hash is not a canonical scalar
+        for i in 0..16u8 {
+            let keys = FullKeys::from_seed(&[i; 32]);
+            assert_ne!(keys.spending.as_scalar(), &Scalar::zero());
+            assert_ne!(keys.spending.nullifier_key().as_scalar(), &Scalar::zero());
+        }
+    }
+
+    #[test]
+    fn test_seed_derivation_keeps_legacy_keys() {
+        // The old derivation read the 32-byte hash as a scalar, or zero
+        let legacy = |seed: &[u8; 32]| {
+            let mut hasher = DomainSeparatedHasher::new("PRIVL1_SPENDING_KEY");
+            hasher.update(seed);
+            Scalar::from_bytes(hasher.finalize().as_bytes()).unwrap_or(Scalar::zero())
+        };
+
+        let (mut kept, mut replaced) = (0, 0);
+        for i in 0..32u8 {
+            let seed = [i; 32];
+            let sk = SpendingKey::from_seed(&seed);
+            if legacy(&seed) == Scalar::zero() {
+                assert_ne!(sk.as_scalar(), &Scalar::zero());
+                replaced += 1;
+            } else {
+                assert_eq!(sk.as_scalar(), &legacy(&seed));
+                kept += 1;
+            }
+        }
+        assert!(kept > 0 && replaced > 0);
+    }
+
+    #[test]
+    fn test_public_key_binds_nullifier_key() {
+        let keys = FullKeys::from_seed(&[7u8; 32]);
+        let nk = Scalar::from_base(keys.nullifier.to_base());
+
+        let expected = keys.spending.validating_key() + nullifier_key_base().mul(&nk);
+        assert_eq!(keys.public.as_point(), &expected);
+    }
+
+    #[test]
+    fn test_full_viewing_key() {
+        let keys = FullKeys::from_seed(&[7u8; 32]);
+        let fvk = keys.full_viewing_key();
+
+        assert_eq!(fvk.public_key(), keys.public);
+        assert_eq!(*fvk.validating_key(), keys.spending.validating_key());
+        assert_eq!(
+            fvk.nullifier_key().to_base(),
+            keys.spending.full_viewing_key().nullifier_key().to_base()
+        );
+
+        let decoded = FullViewingKey::from_bytes(&fvk.to_bytes()).unwrap();
+        assert_eq!(decoded.public_key(), keys.public);
+        assert_eq!(decoded.to_bytes(), fvk.to_bytes());
+    }
+
+    #[test]
+    fn test_randomized_key() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let alpha = Scalar::random(&mut rng);
+
+        let rk = randomize_validating_key(&keys.spending.validating_key(), &alpha);
+        assert_eq!(Point::generator().mul(&keys.spending.randomize(&alpha)), rk);
+        assert_ne!(rk, keys.spending.validating_key());
+
+        // Spend authorization signatures verify under `rk` only
+        let signature = keys.spending.sign_randomized(&alpha, b"sighash");
+        assert!(signature.verify(&rk, b"sighash"));
+        assert!(!signature.verify(&rk, b"other sighash"));
+        assert!(!signature.verify(&keys.spending.validating_key(), b"sighash"));
+    }
+
     #[test]
     fn test_signature() {
         let mut rng = test_rng();
@@ -310,8 +743,32 @@ mod tests {
         let message = b"Hello, PRIVL1!";
         let signature = keys.spending.sign(message);
 
-        // Verify signature
-        assert!(keys.public.verify(message, &signature));
+        // Signatures verify under `ak`, not the public key `ak + [nk]N`
+        let ak = keys.spending.validating_key();
+        assert!(signature.verify(&ak, message));
+        assert!(!signature.verify(&ak, b"Hello, PRIVL2!"));
+        assert!(!signature.verify(keys.public.as_point(), message));
+
+        let recovered = Signature::from_bytes(&signature.to_bytes()).unwrap();
+        assert!(recovered.verify(&ak, message));
+    }
+
+    #[test]
+    fn test_identity_key_rejected() {
+        // Every seed yields a usable key, rather than falling back to zero
+        for i in 0..16u8 {
+            let keys = FullKeys::from_seed(&[i; 32]);
+            assert_ne!(keys.spending.as_scalar(), &Scalar::zero());
+            assert!(!keys.spending.validating_key().is_identity());
+        }
+
+        // Under the identity key, `[s]G = R` would verify any message
+        let s = Scalar::from_inner(pallas::Scalar::from(5));
+        let forged = Signature {
+            r: Point::generator().mul(&s),
+            s,
+        };
+        assert!(!forged.verify(&Point::identity(), b"any message"));
     }
 
     #[test]
@@ -323,7 +780,77 @@ mod tests {
         let recovered = PublicKey::from_bytes(&bytes).unwrap();
 
         // Serialization should round-trip
-        // (This is simplified - actual test would check equality)
         assert_eq!(bytes.len(), 32);
+        assert_eq!(recovered, keys.public);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_payment_address_serialization() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+
+        let classical = keys.address();
+        assert!(!classical.is_hybrid());
+        assert_eq!(
+            PaymentAddress::from_bytes(&classical.to_bytes()).unwrap(),
+            classical
+        );
+
+        let hybrid = keys.hybrid_address();
+        assert!(hybrid.is_hybrid());
+        assert_eq!(
+            PaymentAddress::from_bytes(&hybrid.to_bytes()).unwrap(),
+            hybrid
+        );
+
+        // Hybrid flag without a full ML-KEM key is rejected
+        let mut truncated = hybrid.to_bytes();
+        truncated.truncate(100);
+        assert!(PaymentAddress::from_bytes(&truncated).is_err());
+
+        // Undefined flag bits are rejected, with or without the hybrid bit
+        for flags in [0x80, 0x81, 0x03] {
+            let mut bytes = hybrid.to_bytes();
+            bytes[64] = flags;
+            assert!(PaymentAddress::from_bytes(&bytes).is_err());
+        }
+
+        // Deserialization checks the same invariants
+        for address in [&classical, &hybrid] {
+            let encoded = serde_json::to_value(address).unwrap();
+            assert_eq!(
+                &serde_json::from_value::<PaymentAddress>(encoded).unwrap(),
+                address
+            );
+        }
+        let mut encoded = serde_json::to_value(&hybrid).unwrap();
+        encoded["kem_key"] = serde_json::json!(vec![0u8; 16]);
+        assert!(serde_json::from_value::<PaymentAddress>(encoded).is_err());
+        let mut encoded = serde_json::to_value(&classical).unwrap();
+        encoded["flags"] = serde_json::json!(0x80);
+        assert!(serde_json::from_value::<PaymentAddress>(encoded).is_err());
+    }
+
+    #[test]
+    fn test_legacy_viewing_key_derives_kem_seed() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let encoded = serde_json::to_value(&keys.viewing).unwrap();
+        let decoded: ViewingKey = serde_json::from_value(encoded.clone()).unwrap();
+        assert_eq!(decoded.kem_seed(), keys.viewing.kem_seed());
+
+        // A key serialized without a KEM seed must not get a public one
+        let legacy = |keys: &FullKeys| {
+            let mut encoded = serde_json::to_value(&keys.viewing).unwrap();
+            encoded.as_object_mut().unwrap().remove("kem_seed");
+            serde_json::from_value::<ViewingKey>(encoded).unwrap()
+        };
+        let decoded = legacy(&keys);
+        assert_ne!(decoded.kem_seed(), &[0u8; 32]);
+        assert_eq!(decoded.kem_seed(), legacy(&keys).kem_seed());
+        assert_ne!(
+            decoded.kem_seed(),
+            legacy(&FullKeys::random(&mut rng)).kem_seed()
+        );
+    }
+}
@@ -2,28 +2,45 @@
 //!
 //! This crate provides the core cryptographic building blocks for the PRIVL1 blockchain:
 //! - Pedersen commitments for hiding values
+//! - Bulletproofs range proofs over Pedersen commitments
 //! - Incremental Merkle trees for note commitments
 //! - Nullifier derivation for preventing double-spending
+//! - Indexed nullifier tree for proofs that a note is unspent
 //! - Key generation and management
+//! - Note encryption with optional hybrid post-quantum key agreement
 //! - Hash functions optimized for zero-knowledge circuits
+//! - Halo2 proof creation and verification
+//! - Halo2 proof aggregation by IPA accumulation
+//! - Groth16 proving and verification over BLS12-381
+//! - Versioned verification-key registry with circuit upgrade scheduling
+//! - Selective-disclosure proofs checked against chain state
 
+pub mod aggregation;
+pub mod bulletproofs;
 pub mod commitment;
+pub mod disclosure;
+pub mod groth16;
+pub mod halo2;
 pub mod hash;
 pub mod keys;
 pub mod merkle;
 pub mod note;
+pub mod note_encryption;
 pub mod nullifier;
+pub mod nullifier_tree;
 pub mod point;
 pub mod primitives;
 pub mod proof;
+pub mod registry;
 pub mod scalar;
 
 // Re-export commonly used types
 pub use commitment::{Commitment, PedersenCommitment};
 pub use hash::{Blake3Hash, Hash, Hasher, PoseidonHash};
-pub use keys::{PublicKey, SpendingKey, ViewingKey};
+pub use keys::{FullViewingKey, PaymentAddress, PublicKey, SpendingKey, ViewingKey};
 pub use merkle::{IncrementalMerkleTree, MerkleProof, MerkleRoot};
 pub use note::{Note, NoteCommitment};
+pub use note_encryption::KemMode;
 pub use nullifier::{Nullifier, NullifierDerivingKey};
 pub use point::Point;
 pub use scalar::Scalar;
@@ -46,6 +63,9 @@ pub enum CryptoError {
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
+    #[error("Verification key registry error: {0}")]
+    RegistryError(String),
+
     #[error("Cryptographic operation failed: {0}")]
     OperationFailed(String),
 }
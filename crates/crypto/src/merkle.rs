@@ -4,10 +4,11 @@
 //! maintains a commitment to all notes in the system while allowing for efficient
 //! proofs of membership.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-use crate::hash::{merkle_hash, Blake3Hash};
+use crate::hash::merkle_hash;
 use crate::{CryptoError, Result};
 
 /// The depth of the Merkle tree (2^32 leaves)
@@ -27,7 +28,7 @@ impl MerkleRoot {
     }
 
     pub fn to_hex(&self) -> String {
-        hex::encode(&self.0)
+        hex::encode(self.0)
     }
 }
 
@@ -66,14 +67,23 @@ impl MerkleProof {
 }
 
 /// An incremental Merkle tree that supports efficient appends
+///
+/// Only the frontier is kept: the newest left node at each level, so memory
+/// is `O(TREE_DEPTH)` however many leaves are appended. The latest leaf can
+/// always be proven; earlier leaves only if they were [`mark`]ed when they
+/// were the latest, after which their path is kept up to date on each append.
+///
+/// [`mark`]: IncrementalMerkleTree::mark
 #[derive(Clone, Debug)]
 pub struct IncrementalMerkleTree {
     /// Current number of leaves
     num_leaves: u64,
-    /// Frontier nodes (rightmost nodes at each level)
+    /// The newest left node at each level
     frontier: Vec<[u8; 32]>,
-    /// Cached subtree roots for efficiency
-    cached_roots: HashMap<(usize, u64), [u8; 32]>,
+    /// The current root
+    root: [u8; 32],
+    /// Authentication paths of the marked positions
+    witnesses: BTreeMap<u64, Vec<[u8; 32]>>,
     /// Empty subtree hashes at each level
     empty_hashes: Vec<[u8; 32]>,
 }
@@ -94,8 +104,9 @@ impl IncrementalMerkleTree {
 
         Self {
             num_leaves: 0,
-            frontier: Vec::new(),
-            cached_roots: HashMap::new(),
+            frontier: empty_hashes[..TREE_DEPTH].to_vec(),
+            root: empty_hashes[TREE_DEPTH],
+            witnesses: BTreeMap::new(),
             empty_hashes,
         }
     }
@@ -108,127 +119,84 @@ impl IncrementalMerkleTree {
             return Err(CryptoError::MerkleError("Tree is full".into()));
         }
 
-        // Update frontier nodes
-        self.update_frontier(leaf);
-
-        self.num_leaves += 1;
-        Ok(position)
-    }
-
-    /// Update the frontier with a new leaf
-    fn update_frontier(&mut self, leaf: [u8; 32]) {
-        let mut current = leaf;
-        let mut index = self.num_leaves;
-        let mut new_frontier = Vec::new();
-
-        // Traverse up the tree, updating frontier nodes
+        // Rehash the path from the new leaf to the root. `node` is the
+        // current hash of the subtree at `level` that holds the new leaf,
+        // which is the sibling of every marked path that meets it there.
+        let mut node = leaf;
+        let mut index = position;
         for level in 0..TREE_DEPTH {
-            if index & 1 == 0 {
-                // This is a left child, it becomes the new frontier node
-                new_frontier.push(current);
-                break;
-            } else {
-                // This is a right child, combine with sibling from frontier
-                if level < self.frontier.len() {
-                    let sibling = self.frontier[level];
-                    current = merkle_hash(&sibling, &current);
-                    // Continue up the tree
-                } else {
-                    // Use empty hash as sibling
-                    let sibling = self.empty_hashes[level];
-                    current = merkle_hash(&sibling, &current);
+            for (marked, path) in self.witnesses.iter_mut() {
+                if (marked >> level) ^ 1 == index {
+                    path[level] = node;
                 }
             }
+
+            node = if index & 1 == 0 {
+                self.frontier[level] = node;
+                merkle_hash(&node, &self.empty_hashes[level])
+            } else {
+                merkle_hash(&self.frontier[level], &node)
+            };
             index >>= 1;
         }
 
-        // Replace frontier with updated nodes
-        if !new_frontier.is_empty() {
-            let frontier_len = new_frontier.len();
-            self.frontier.truncate(frontier_len - 1);
-            self.frontier.extend(new_frontier);
-        }
+        self.root = node;
+        self.num_leaves += 1;
+        Ok(position)
     }
 
-    /// Get the current root of the tree
-    pub fn root(&self) -> MerkleRoot {
-        if self.num_leaves == 0 {
-            return MerkleRoot(self.empty_hashes[TREE_DEPTH]);
-        }
-
-        let mut current_hash = self.frontier[0];
-        let mut current_index = self.num_leaves - 1;
+    /// Keep the path of the latest leaf so it can be proven after later appends
+    pub fn mark(&mut self) -> Result<u64> {
+        let position = self
+            .num_leaves
+            .checked_sub(1)
+            .ok_or_else(|| CryptoError::MerkleError("Tree is empty".into()))?;
+        let path = self.latest_path();
+        self.witnesses.entry(position).or_insert(path);
+        Ok(position)
+    }
 
-        // Compute root from frontier
-        for level in 0..TREE_DEPTH {
-            if level < self.frontier.len() - 1 {
-                // We have a frontier node at the next level
-                if (current_index >> (level + 1)) & 1 == 1 {
-                    // Current subtree is a right child
-                    let sibling = if level + 1 < self.frontier.len() {
-                        self.frontier[level + 1]
-                    } else {
-                        self.empty_hashes[level + 1]
-                    };
-                    current_hash = merkle_hash(&sibling, &current_hash);
-                }
-            } else {
-                // Pad with empty hashes
-                let sibling = self.empty_hashes[level];
-                if (current_index >> level) & 1 == 0 {
-                    current_hash = merkle_hash(&current_hash, &sibling);
-                } else {
-                    current_hash = merkle_hash(&sibling, &current_hash);
-                }
-            }
-        }
+    /// Stop keeping the path of a marked position
+    pub fn forget(&mut self, position: u64) -> bool {
+        self.witnesses.remove(&position).is_some()
+    }
 
-        MerkleRoot(current_hash)
+    /// Get the current root of the tree
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot(self.root)
     }
 
-    /// Generate a Merkle proof for a leaf at the given position
+    /// Generate a Merkle proof for the latest leaf or a marked position
     pub fn prove(&self, position: u64) -> Result<MerkleProof> {
         if position >= self.num_leaves {
             return Err(CryptoError::MerkleError("Position out of bounds".into()));
         }
 
-        let mut path = Vec::with_capacity(TREE_DEPTH);
-        let mut current_index = position;
-
-        // Build authentication path
-        for level in 0..TREE_DEPTH {
-            let sibling_index = current_index ^ 1;
-
-            let sibling = if sibling_index < self.num_leaves {
-                // Sibling exists, need to compute or retrieve it
-                self.get_node(level, sibling_index)?
-            } else {
-                // Use empty hash for non-existent sibling
-                self.empty_hashes[level]
-            };
-
-            path.push(sibling);
-            current_index >>= 1;
-        }
+        let path = if position + 1 == self.num_leaves {
+            self.latest_path()
+        } else {
+            self.witnesses
+                .get(&position)
+                .cloned()
+                .ok_or_else(|| CryptoError::MerkleError("Position is not marked".into()))?
+        };
 
         Ok(MerkleProof { path, position })
     }
 
-    /// Get a node at a specific level and index (for proof generation)
-    fn get_node(&self, level: usize, index: u64) -> Result<[u8; 32]> {
-        // Check cache first
-        if let Some(&hash) = self.cached_roots.get(&(level, index)) {
-            return Ok(hash);
-        }
-
-        // For frontier nodes, we can retrieve directly
-        if level < self.frontier.len() && index == (self.num_leaves - 1) >> level {
-            return Ok(self.frontier[level]);
-        }
-
-        // Otherwise, need to compute (this would be more complex in production)
-        // For now, return empty hash as placeholder
-        Ok(self.empty_hashes[level])
+    /// The authentication path of the latest leaf: left siblings come from
+    /// the frontier and everything to its right is still empty
+    fn latest_path(&self) -> Vec<[u8; 32]> {
+        let position = self.num_leaves.saturating_sub(1);
+        (0..TREE_DEPTH)
+            .map(|level| {
+                if (position >> level) & 1 == 1 {
+                    self.frontier[level]
+                } else {
+                    self.empty_hashes[level]
+                }
+            })
+            .collect()
     }
 
     /// Get the number of leaves in the tree
@@ -294,6 +262,12 @@ impl BatchMerkleTree {
     }
 }
 
+impl Default for BatchMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,11 +300,12 @@ mod tests {
     #[test]
     fn test_multiple_leaves() {
         let mut tree = IncrementalMerkleTree::new();
-        let leaves = vec![[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
+        let leaves = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
 
         for (i, leaf) in leaves.iter().enumerate() {
             let position = tree.append(*leaf).unwrap();
             assert_eq!(position, i as u64);
+            assert_eq!(tree.mark().unwrap(), position);
         }
 
         let root = tree.root();
@@ -403,4 +378,62 @@ mod tests {
         let wrong_root = MerkleRoot([99u8; 32]);
         assert!(!proof.verify(&leaf, &wrong_root));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_proofs_at_every_position() {
+        let mut tree = IncrementalMerkleTree::new();
+        let leaves: Vec<[u8; 32]> = (0..13u8).map(|i| [i + 1; 32]).collect();
+
+        for (count, leaf) in leaves.iter().enumerate() {
+            tree.append(*leaf).unwrap();
+            tree.mark().unwrap();
+            let root = tree.root();
+            for (i, leaf) in leaves[..=count].iter().enumerate() {
+                let proof = tree.prove(i as u64).unwrap();
+                assert!(proof.verify(leaf, &root), "leaf {} of {}", i, count + 1);
+            }
+        }
+        assert!(tree.prove(13).is_err());
+    }
+
+    #[test]
+    fn test_only_latest_and_marked_positions_prove() {
+        let mut tree = IncrementalMerkleTree::new();
+        assert!(tree.mark().is_err());
+        for i in 0..6u8 {
+            tree.append([i + 1; 32]).unwrap();
+            if i == 2 {
+                tree.mark().unwrap();
+            }
+        }
+
+        let root = tree.root();
+        assert!(tree.prove(2).unwrap().verify(&[3u8; 32], &root));
+        assert!(tree.prove(5).unwrap().verify(&[6u8; 32], &root));
+        assert!(tree.prove(1).is_err());
+
+        assert!(tree.forget(2));
+        assert!(tree.prove(2).is_err());
+    }
+
+    #[test]
+    fn test_frontier_root_matches_full_recomputation() {
+        let leaves: Vec<[u8; 32]> = (0..9u8).map(|i| [i + 1; 32]).collect();
+        let mut tree = IncrementalMerkleTree::new();
+        for leaf in &leaves {
+            tree.append(*leaf).unwrap();
+        }
+
+        let mut level = leaves;
+        for depth in 0..TREE_DEPTH {
+            if level.len() % 2 == 1 {
+                level.push(tree.empty_hashes[depth]);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| merkle_hash(&pair[0], &pair[1]))
+                .collect();
+        }
+        assert_eq!(tree.root(), MerkleRoot(level[0]));
+    }
+}
@@ -3,13 +3,15 @@
 //! Notes are the fundamental unit of value in PRIVL1, similar to UTXOs
 //! but with privacy-preserving properties via commitments.
 
+use pasta_curves::group::ff::PrimeField;
+use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-use crate::commitment::{Commitment, PedersenCommitment};
-use crate::hash::Blake3Hash;
-use crate::keys::{EncryptedNote, PublicKey, ViewingKey};
-use crate::{CryptoError, Point, Result, Scalar};
+use crate::hash::{bytes_to_base, Blake3Hash, DomainSeparatedHasher, PoseidonHash};
+use crate::keys::{EncryptedNote, PaymentAddress, PublicKey, ViewingKey};
+use crate::note_encryption;
+use crate::{Result, Scalar};
 
 /// A note representing value in the system
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,27 +26,13 @@ pub struct Note {
     randomness: Scalar,
     /// Optional memo (encrypted)
     memo: Option<Vec<u8>>,
-    /// Cached commitment (for the simplified test version)
-    #[serde(skip)]
-    cached_commitment: Option<Commitment>,
 }
 
 impl Note {
-    /// Create a new note (simplified for testing - uses provided commitment)
-    pub fn new(value: u64, commitment: Commitment, asset_id: [u8; 32]) -> Self {
-        let mut rng = rand::thread_rng();
-
-        // For testing, we'll create a simplified note with dummy owner
+    /// Create a new note without an owner (for testing)
+    pub fn new(value: u64, asset_id: [u8; 32]) -> Self {
         let owner = PublicKey::from_bytes(&[0u8; 32]).unwrap();
-
-        Self {
-            value,
-            asset_id,
-            owner,
-            randomness: Scalar::random(&mut rng),
-            memo: None,
-            cached_commitment: Some(commitment),
-        }
+        Self::new_with_owner(value, owner, asset_id)
     }
 
     /// Create a new note with owner
@@ -57,7 +45,6 @@ impl Note {
             owner,
             randomness: Scalar::random(&mut rng),
             memo: None,
-            cached_commitment: None,
         }
     }
 
@@ -74,7 +61,6 @@ impl Note {
             owner,
             randomness,
             memo: None,
-            cached_commitment: None,
         }
     }
 
@@ -104,34 +90,48 @@ impl Note {
         &self.randomness
     }
 
+    /// Get the memo
+    pub fn memo(&self) -> Option<&Vec<u8>> {
+        self.memo.as_ref()
+    }
+
+    /// Get the commitment randomness as a circuit field element
+    pub fn rcm(&self) -> pallas::Base {
+        bytes_to_base(&self.randomness.to_bytes())
+    }
+
     /// Compute the note commitment
+    ///
+    /// `cm = Poseidon(value, asset, owner.x, owner.y, rcm)`, which the spend
+    /// and output circuits recompute in-circuit.
     pub fn commitment(&self) -> NoteCommitment {
-        // Use cached commitment if available (for testing)
-        let commitment = if let Some(cached) = self.cached_commitment {
-            cached
-        } else {
-            let pedersen = PedersenCommitment::new();
-            // Commit to all note components
-            // In production, this would be a more complex commitment
-            // that includes all fields
-            pedersen.commit_with_blinding(self.value, self.randomness)
-        };
-
-        NoteCommitment {
-            inner: commitment,
-            asset_id: self.asset_id,
-        }
+        let (owner_x, owner_y) = self.owner.as_point().coordinates();
+        let cm = PoseidonHash::hash([
+            pallas::Base::from(self.value),
+            asset_base(&self.asset_id),
+            owner_x,
+            owner_y,
+            self.rcm(),
+        ]);
+
+        NoteCommitment::new(cm.to_field(), self.asset_id)
     }
 
     /// Encrypt the note for the recipient
-    pub fn encrypt(&self, recipient: &PublicKey) -> EncryptedNote {
-        // Simplified encryption
-        // In production, use proper encryption (ChaCha20Poly1305)
-        EncryptedNote {
-            epk: Point::identity(),
-            ciphertext: vec![],
-            tag: [0u8; 16],
-        }
+    ///
+    /// Uses hybrid ECDH + ML-KEM key agreement if the address opted in to it.
+    pub fn encrypt(&self, recipient: &PaymentAddress) -> Result<EncryptedNote> {
+        let mut rng = rand::thread_rng();
+        self.encrypt_with_rng(recipient, &mut rng)
+    }
+
+    /// Encrypt the note for the recipient with a specific RNG
+    pub fn encrypt_with_rng<R: rand::RngCore + rand::CryptoRng>(
+        &self,
+        recipient: &PaymentAddress,
+        rng: &mut R,
+    ) -> Result<EncryptedNote> {
+        note_encryption::encrypt_note(self, recipient, rng)
     }
 
     /// Try to decrypt a note with a viewing key
@@ -141,10 +141,9 @@ impl Note {
         Ok(Self {
             value: decrypted.value,
             asset_id: decrypted.asset_id,
-            owner: PublicKey::from_bytes(&[0u8; 32])?,
-            randomness: Scalar::zero(),
-            memo: Some(decrypted.memo),
-            cached_commitment: None,
+            owner: decrypted.owner,
+            randomness: decrypted.randomness,
+            memo: decrypted.memo,
         })
     }
 
@@ -161,7 +160,6 @@ impl Note {
             owner: PublicKey::from_bytes(&[0u8; 32]).unwrap(),
             randomness: Scalar::zero(),
             memo: None,
-            cached_commitment: None,
         }
     }
 
@@ -171,25 +169,41 @@ impl Note {
     }
 }
 
+/// Map an asset ID into the circuit field
+///
+/// Asset IDs are arbitrary 32-byte strings, so they are hashed rather than
+/// reduced: two IDs differing by the field modulus must not collide.
+pub fn asset_base(asset_id: &[u8; 32]) -> pallas::Base {
+    let mut hasher = DomainSeparatedHasher::new("PRIVL1_ASSET_BASE");
+    hasher.update(asset_id);
+    bytes_to_base(hasher.finalize().as_bytes())
+}
+
 /// A note commitment (hides the note's contents)
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NoteCommitment {
-    inner: Commitment,
+    /// Canonical encoding of the commitment field element
+    cm: [u8; 32],
     asset_id: [u8; 32],
 }
 
 impl NoteCommitment {
-    /// Create from a commitment and asset ID
-    pub fn new(commitment: Commitment, asset_id: [u8; 32]) -> Self {
+    /// Create from a commitment field element and asset ID
+    pub fn new(cm: pallas::Base, asset_id: [u8; 32]) -> Self {
         Self {
-            inner: commitment,
+            cm: cm.to_repr(),
             asset_id,
         }
     }
 
-    /// Get the inner commitment
-    pub fn inner(&self) -> &Commitment {
-        &self.inner
+    /// Get the commitment as a field element
+    pub fn to_field(&self) -> pallas::Base {
+        bytes_to_base(&self.cm)
+    }
+
+    /// Get the leaf appended to the note commitment tree
+    pub fn leaf(&self) -> [u8; 32] {
+        self.cm
     }
 
     /// Get the asset ID
@@ -200,7 +214,7 @@ impl NoteCommitment {
     /// Convert to bytes
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(64);
-        bytes.extend_from_slice(&self.inner.to_bytes());
+        bytes.extend_from_slice(&self.cm);
         bytes.extend_from_slice(&self.asset_id);
         bytes
     }
@@ -322,6 +336,11 @@ mod tests {
         // Same note should give same commitment (deterministic)
         let comm1b = note1.commitment();
         assert_eq!(comm1, comm1b);
+
+        // The owner is bound into the commitment
+        let other = crate::keys::FullKeys::random(&mut rng).public;
+        let moved = Note::with_randomness(100, other, AssetId::NATIVE.0, *note1.randomness());
+        assert_ne!(moved.commitment(), comm1);
     }
 
     #[test]
@@ -330,7 +349,7 @@ mod tests {
 
         assert!(dummy.is_dummy());
         assert_eq!(dummy.value(), 0);
-        assert_eq!(dummy.randomness, pallas::Scalar::zero());
+        assert_eq!(dummy.randomness, Scalar::zero());
     }
 
     #[test]
@@ -371,4 +390,4 @@ mod tests {
         let hash2 = commitment.hash();
         assert_eq!(hash1, hash2); // Hashing is deterministic
     }
-}
\ No newline at end of file
+}
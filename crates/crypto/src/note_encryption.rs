@@ -0,0 +1,339 @@
+//! Note encryption for recipients
+//!
+//! Notes are encrypted to a recipient's `PaymentAddress` with ChaCha20-Poly1305.
+//! The symmetric key is derived with the note-encryption KDF from a shared secret:
+//! - Classical mode: Pallas ECDH between an ephemeral key and the transmission key
+//! - Hybrid mode: the ECDH secret combined with an ML-KEM-768 encapsulation
+//!
+//! Note ciphertexts live on chain forever. Hybrid mode stays confidential as long
+//! as either ECDH or ML-KEM holds, which protects new notes against "harvest now,
+//! decrypt later" attacks by a future quantum adversary.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ml_kem::kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768, B32};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{DecryptedNote, EncryptedNote, PaymentAddress, PublicKey, ViewingKey};
+use crate::note::Note;
+use crate::primitives::kdf;
+use crate::{CryptoError, Point, Result, Scalar};
+
+/// Size of an encoded ML-KEM-768 encapsulation key
+pub const KEM_ENCAPSULATION_KEY_SIZE: usize = 1184;
+
+/// Size of an ML-KEM-768 ciphertext
+pub const KEM_CIPHERTEXT_SIZE: usize = 1088;
+
+/// Size of the Poly1305 authentication tag
+const TAG_SIZE: usize = 16;
+
+/// Fixed plaintext header: memo flag, value, asset, owner, randomness
+const PLAINTEXT_HEADER_SIZE: usize = 1 + 8 + 32 + 32 + 32;
+
+/// KDF domains (distinct so a hybrid key can never equal a classical one)
+const DOMAIN_CLASSICAL: &[u8] = b"PRIVL1_NOTE_ENC";
+const DOMAIN_HYBRID: &[u8] = b"PRIVL1_NOTE_ENC_HYBRID";
+const DOMAIN_KEM_KEYGEN: &[u8] = b"PRIVL1_MLKEM_KEYGEN";
+
+type KemDecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type KemEncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Key agreement used for a note ciphertext
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KemMode {
+    /// Pallas ECDH only
+    Classical,
+    /// Pallas ECDH combined with ML-KEM-768
+    Hybrid,
+}
+
+/// Encrypt a note to a payment address
+///
+/// The mode is chosen by the address: recipients that published an ML-KEM key
+/// (and set the hybrid flag) always receive hybrid ciphertexts.
+pub fn encrypt_note<R: RngCore + CryptoRng>(
+    note: &Note,
+    recipient: &PaymentAddress,
+    rng: &mut R,
+) -> Result<EncryptedNote> {
+    // Ephemeral ECDH key pair
+    let esk = Scalar::random(rng);
+    let epk = Point::generator().mul(&esk);
+    let shared = recipient.transmission_key().mul(&esk);
+
+    let (key, kem_ciphertext) = if recipient.is_hybrid() {
+        let ek = decode_encapsulation_key(recipient.kem_key().ok_or(CryptoError::InvalidKey)?)?;
+        let (ct, kem_secret) = ek
+            .encapsulate(rng)
+            .map_err(|_| CryptoError::OperationFailed("ML-KEM encapsulation failed".into()))?;
+
+        let key = derive_hybrid_key(&shared, &epk, kem_secret.as_slice(), ct.as_slice());
+        (key, Some(ct.to_vec()))
+    } else {
+        (derive_classical_key(&shared, &epk), None)
+    };
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    // Every note uses a fresh ephemeral key, so a fixed nonce is safe
+    let mut ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&[0u8; 12]),
+            encode_plaintext(note).as_ref(),
+        )
+        .map_err(|_| CryptoError::OperationFailed("Note encryption failed".into()))?;
+
+    let mut tag = [0u8; TAG_SIZE];
+    tag.copy_from_slice(&ciphertext[ciphertext.len() - TAG_SIZE..]);
+    ciphertext.truncate(ciphertext.len() - TAG_SIZE);
+
+    Ok(EncryptedNote {
+        epk,
+        ciphertext,
+        tag,
+        kem_ciphertext,
+    })
+}
+
+/// Decrypt a note with the recipient's viewing key
+///
+/// Fails if the ciphertext was not addressed to this key, was tampered with,
+/// or had its ML-KEM component stripped or swapped.
+pub fn decrypt_note(encrypted: &EncryptedNote, vk: &ViewingKey) -> Result<DecryptedNote> {
+    let shared = encrypted.epk.mul(vk.incoming());
+
+    let key = match &encrypted.kem_ciphertext {
+        Some(ct_bytes) => {
+            let dk = kem_keypair(vk.kem_seed()).0;
+            let ct = Ciphertext::<MlKem768>::try_from(ct_bytes.as_slice())
+                .map_err(|_| CryptoError::SerializationError("Invalid ML-KEM ciphertext".into()))?;
+            let kem_secret = dk
+                .decapsulate(&ct)
+                .map_err(|_| CryptoError::OperationFailed("ML-KEM decapsulation failed".into()))?;
+
+            derive_hybrid_key(&shared, &encrypted.epk, kem_secret.as_slice(), ct_bytes)
+        }
+        None => derive_classical_key(&shared, &encrypted.epk),
+    };
+
+    let mut sealed = Vec::with_capacity(encrypted.ciphertext.len() + TAG_SIZE);
+    sealed.extend_from_slice(&encrypted.ciphertext);
+    sealed.extend_from_slice(&encrypted.tag);
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&[0u8; 12]), sealed.as_ref())
+        .map_err(|_| CryptoError::OperationFailed("Note decryption failed".into()))?;
+
+    decode_plaintext(&plaintext)
+}
+
+/// Deterministically derive the ML-KEM key pair for a viewing key seed
+pub(crate) fn kem_keypair(seed: &[u8; 32]) -> (KemDecapsulationKey, KemEncapsulationKey) {
+    let expanded = kdf(seed, DOMAIN_KEM_KEYGEN, 64);
+    let d = B32::try_from(&expanded[..32]).expect("KDF output is 64 bytes");
+    let z = B32::try_from(&expanded[32..]).expect("KDF output is 64 bytes");
+
+    MlKem768::generate_deterministic(&d, &z)
+}
+
+/// Encode an ML-KEM encapsulation key for publishing in an address
+pub(crate) fn encode_encapsulation_key(ek: &KemEncapsulationKey) -> Vec<u8> {
+    ek.as_bytes().to_vec()
+}
+
+fn decode_encapsulation_key(bytes: &[u8]) -> Result<KemEncapsulationKey> {
+    let encoded =
+        Encoded::<KemEncapsulationKey>::try_from(bytes).map_err(|_| CryptoError::InvalidKey)?;
+    Ok(KemEncapsulationKey::from_bytes(&encoded))
+}
+
+/// Classical key: KDF(ECDH secret || epk)
+fn derive_classical_key(shared: &Point, epk: &Point) -> [u8; 32] {
+    let mut master = Vec::with_capacity(64);
+    master.extend_from_slice(&shared.to_bytes());
+    master.extend_from_slice(&epk.to_bytes());
+
+    to_key(kdf(&master, DOMAIN_CLASSICAL, 32))
+}
+
+/// Hybrid key: KDF(ECDH secret || ML-KEM secret || epk || ML-KEM ciphertext)
+///
+/// Both ciphertexts are bound into the key, so neither component can be
+/// replaced without breaking decryption.
+fn derive_hybrid_key(shared: &Point, epk: &Point, kem_secret: &[u8], kem_ct: &[u8]) -> [u8; 32] {
+    let mut master = Vec::with_capacity(96 + kem_ct.len());
+    master.extend_from_slice(&shared.to_bytes());
+    master.extend_from_slice(kem_secret);
+    master.extend_from_slice(&epk.to_bytes());
+    master.extend_from_slice(kem_ct);
+
+    to_key(kdf(&master, DOMAIN_HYBRID, 32))
+}
+
+fn to_key(bytes: Vec<u8>) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    key
+}
+
+/// Note plaintext: memo_flag || value || asset_id || owner || randomness || memo
+fn encode_plaintext(note: &Note) -> Vec<u8> {
+    let memo = note.memo();
+    let mut bytes = Vec::with_capacity(PLAINTEXT_HEADER_SIZE + memo.map_or(0, |m| m.len()));
+
+    bytes.push(memo.is_some() as u8);
+    bytes.extend_from_slice(&note.value().to_le_bytes());
+    bytes.extend_from_slice(note.asset_id());
+    bytes.extend_from_slice(&note.owner().to_bytes());
+    bytes.extend_from_slice(&note.randomness().to_bytes());
+    if let Some(memo) = memo {
+        bytes.extend_from_slice(memo);
+    }
+
+    bytes
+}
+
+fn decode_plaintext(bytes: &[u8]) -> Result<DecryptedNote> {
+    if bytes.len() < PLAINTEXT_HEADER_SIZE || bytes[0] > 1 {
+        return Err(CryptoError::SerializationError(
+            "Malformed note plaintext".into(),
+        ));
+    }
+
+    let mut value = [0u8; 8];
+    let mut asset_id = [0u8; 32];
+    let mut owner = [0u8; 32];
+    let mut randomness = [0u8; 32];
+    value.copy_from_slice(&bytes[1..9]);
+    asset_id.copy_from_slice(&bytes[9..41]);
+    owner.copy_from_slice(&bytes[41..73]);
+    randomness.copy_from_slice(&bytes[73..105]);
+
+    let memo = &bytes[PLAINTEXT_HEADER_SIZE..];
+    if bytes[0] == 0 && !memo.is_empty() {
+        return Err(CryptoError::SerializationError(
+            "Malformed note plaintext".into(),
+        ));
+    }
+
+    Ok(DecryptedNote {
+        value: u64::from_le_bytes(value),
+        asset_id,
+        owner: PublicKey::from_bytes(&owner)?,
+        randomness: Scalar::from_bytes(&randomness)?,
+        memo: (bytes[0] == 1).then(|| memo.to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::FullKeys;
+    use crate::note::AssetId;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn test_rng() -> StdRng {
+        StdRng::seed_from_u64(0)
+    }
+
+    #[test]
+    fn test_classical_roundtrip() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let address = keys.address();
+
+        let note = Note::new_with_owner(100, keys.public, *AssetId::NATIVE.as_bytes())
+            .with_memo(b"hi".to_vec());
+        let encrypted = encrypt_note(&note, &address, &mut rng).unwrap();
+        assert_eq!(encrypted.mode(), KemMode::Classical);
+
+        let decrypted = Note::decrypt(&encrypted, &keys.viewing).unwrap();
+        assert_eq!(decrypted.value(), 100);
+        assert_eq!(decrypted.commitment(), note.commitment());
+        assert_eq!(decrypted.memo(), Some(&b"hi".to_vec()));
+    }
+
+    #[test]
+    fn test_hybrid_roundtrip() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let address = keys.hybrid_address();
+        assert!(address.is_hybrid());
+        assert_eq!(address.kem_key().unwrap().len(), KEM_ENCAPSULATION_KEY_SIZE);
+
+        let note = Note::new_with_owner(42, keys.public, [7u8; 32]);
+        let encrypted = encrypt_note(&note, &address, &mut rng).unwrap();
+        assert_eq!(encrypted.mode(), KemMode::Hybrid);
+        assert_eq!(
+            encrypted.kem_ciphertext.as_ref().unwrap().len(),
+            KEM_CIPHERTEXT_SIZE
+        );
+
+        let decrypted = Note::decrypt(&encrypted, &keys.viewing).unwrap();
+        assert_eq!(decrypted.value(), 42);
+        assert_eq!(decrypted.asset_id(), &[7u8; 32]);
+        assert_eq!(decrypted.commitment(), note.commitment());
+        assert!(decrypted.memo().is_none());
+    }
+
+    #[test]
+    fn test_wrong_viewing_key_fails() {
+        let mut rng = test_rng();
+        let alice = FullKeys::random(&mut rng);
+        let bob = FullKeys::random(&mut rng);
+
+        let note = Note::new_with_owner(5, alice.public, *AssetId::NATIVE.as_bytes());
+        for address in [alice.address(), alice.hybrid_address()] {
+            let encrypted = encrypt_note(&note, &address, &mut rng).unwrap();
+            assert!(decrypt_note(&encrypted, &bob.viewing).is_err());
+        }
+    }
+
+    #[test]
+    fn test_stripped_kem_ciphertext_fails() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let note = Note::new_with_owner(5, keys.public, *AssetId::NATIVE.as_bytes());
+
+        // Downgrading a hybrid ciphertext to classical must not decrypt
+        let mut encrypted = encrypt_note(&note, &keys.hybrid_address(), &mut rng).unwrap();
+        encrypted.kem_ciphertext = None;
+        assert!(decrypt_note(&encrypted, &keys.viewing).is_err());
+
+        // Neither must a truncated ML-KEM ciphertext
+        let mut encrypted = encrypt_note(&note, &keys.hybrid_address(), &mut rng).unwrap();
+        encrypted.kem_ciphertext.as_mut().unwrap().pop();
+        assert!(decrypt_note(&encrypted, &keys.viewing).is_err());
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_fails() {
+        let mut rng = test_rng();
+        let keys = FullKeys::random(&mut rng);
+        let note = Note::new_with_owner(5, keys.public, *AssetId::NATIVE.as_bytes());
+
+        let mut encrypted = encrypt_note(&note, &keys.hybrid_address(), &mut rng).unwrap();
+        encrypted.ciphertext[0] ^= 1;
+        assert!(decrypt_note(&encrypted, &keys.viewing).is_err());
+    }
+
+    #[test]
+    fn test_kem_keypair_deterministic() {
+        let (_, ek1) = kem_keypair(&[1u8; 32]);
+        let (_, ek2) = kem_keypair(&[1u8; 32]);
+        let (_, ek3) = kem_keypair(&[2u8; 32]);
+
+        assert_eq!(
+            encode_encapsulation_key(&ek1),
+            encode_encapsulation_key(&ek2)
+        );
+        assert_ne!(
+            encode_encapsulation_key(&ek1),
+            encode_encapsulation_key(&ek3)
+        );
+    }
+}
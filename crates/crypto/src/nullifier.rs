@@ -3,14 +3,26 @@
 //! Nullifiers are unique identifiers derived from notes that are revealed when
 //! the note is spent, preventing the same note from being spent twice.
 
+use pasta_curves::group::ff::PrimeField;
+use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
 
-use crate::hash::{Blake3Hash, DomainSeparatedHasher};
+use crate::hash::{bytes_to_base, PoseidonHash};
+use crate::keys::derive_key_scalar;
+use crate::merkle::MerkleRoot;
 use crate::note::Note;
+use crate::nullifier_tree::{NonMembershipProof, NullifierTree};
 use crate::{CryptoError, Result, Scalar};
 
+/// Domain tag of vote nullifiers ("vote"), keeping them apart from note
+/// nullifiers
+pub const VOTE_NULLIFIER_DOMAIN: u64 = 0x766f_7465;
+
+/// Domain tag of bribe-claim nullifiers ("clam")
+pub const CLAIM_NULLIFIER_DOMAIN: u64 = 0x636c_616d;
+
 /// A nullifier - reveals when a note is spent
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Nullifier([u8; 32]);
@@ -28,7 +40,7 @@ impl Nullifier {
 
     /// Convert to hex string
     pub fn to_hex(&self) -> String {
-        hex::encode(&self.0)
+        hex::encode(self.0)
     }
 }
 
@@ -81,40 +93,71 @@ impl NullifierDerivingKey {
 
     /// Derive from a seed
     pub fn from_seed(seed: &[u8; 32]) -> Self {
-        // Use domain separation
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_NULLIFIER_KEY");
-        hasher.update(seed);
-        let hash = hasher.finalize();
-
-        // Convert hash to scalar
-        let nk = Scalar::from_bytes(hash.as_bytes()).unwrap_or(Scalar::zero());
+        Self {
+            nk: derive_key_scalar("PRIVL1_NULLIFIER_KEY", &[seed]),
+        }
+    }
 
-        Self { nk }
+    /// Deserialize from the scalar encoding returned by [`Self::as_scalar`]
+    pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self> {
+        Ok(Self {
+            nk: Scalar::from_bytes(bytes)?,
+        })
     }
 
-    /// Derive a nullifier for a note
+    /// Derive a nullifier for a note at a tree position
+    ///
+    /// `nf = Poseidon(nk, cm, position)`, the relation the spend circuit
+    /// enforces. Including the position gives two copies of the same note
+    /// distinct nullifiers.
     pub fn derive_nullifier(&self, note: &Note, position: u64) -> Nullifier {
-        // Nullifier = Hash(nk || note_commitment || position)
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_NULLIFIER");
-
-        // Add nullifier key
-        let nk_bytes = self.nk.to_bytes();
-        hasher.update(&nk_bytes);
-
-        // Add note commitment
-        hasher.update(note.commitment().to_bytes().as_ref());
+        let nf = PoseidonHash::hash([
+            self.to_base(),
+            note.commitment().to_field(),
+            pallas::Base::from(position),
+        ]);
+        Nullifier(nf.to_field().to_repr())
+    }
 
-        // Add position in commitment tree
-        hasher.update(&position.to_le_bytes());
+    /// Derive the nullifier a veNFT votes with in an epoch
+    ///
+    /// `vnf = Poseidon(VOTE_NULLIFIER_DOMAIN, nk, ve_cm, epoch)`, the relation
+    /// the gauge-vote circuit enforces. It is fixed per NFT and epoch, so a
+    /// second vote in an epoch is caught, while votes from different epochs
+    /// cannot be linked without `nk`.
+    pub fn derive_vote_nullifier(&self, ve_commitment: pallas::Base, epoch: u64) -> Nullifier {
+        let vnf = PoseidonHash::hash([
+            pallas::Base::from(VOTE_NULLIFIER_DOMAIN),
+            self.to_base(),
+            ve_commitment,
+            pallas::Base::from(epoch),
+        ]);
+        Nullifier(vnf.to_field().to_repr())
+    }
 
-        let hash = hasher.finalize();
-        Nullifier(*hash.as_bytes())
+    /// Derive the nullifier that claims a bribe for a vote receipt
+    ///
+    /// `cnf = Poseidon(CLAIM_NULLIFIER_DOMAIN, nk, receipt)`, the relation the
+    /// bribe-claim circuit enforces. Each receipt can be claimed once, and the
+    /// claim cannot be linked to the vote without `nk`.
+    pub fn derive_claim_nullifier(&self, receipt: pallas::Base) -> Nullifier {
+        let cnf = PoseidonHash::hash([
+            pallas::Base::from(CLAIM_NULLIFIER_DOMAIN),
+            self.to_base(),
+            receipt,
+        ]);
+        Nullifier(cnf.to_field().to_repr())
     }
 
     /// Get the underlying scalar
     pub fn as_scalar(&self) -> &Scalar {
         &self.nk
     }
+
+    /// Get the key as a circuit field element
+    pub fn to_base(&self) -> pallas::Base {
+        bytes_to_base(&self.nk.to_bytes())
+    }
 }
 
 /// A set tracking spent nullifiers
@@ -197,6 +240,8 @@ impl NullifierSet {
 pub struct PersistentNullifierSet {
     /// In-memory set for fast lookups
     set: NullifierSet,
+    /// Indexed tree committing to the set, for non-membership proofs
+    tree: NullifierTree,
     /// Database path (in production, this would be RocksDB)
     db_path: String,
 }
@@ -208,10 +253,16 @@ impl PersistentNullifierSet {
         // For now, just create an in-memory set
         Ok(Self {
             set: NullifierSet::new(),
+            tree: NullifierTree::new(),
             db_path,
         })
     }
 
+    /// Get the database path
+    pub fn db_path(&self) -> &str {
+        &self.db_path
+    }
+
     /// Check if a nullifier is spent
     pub fn is_spent(&self, nullifier: &Nullifier) -> bool {
         self.set.is_spent(nullifier)
@@ -219,6 +270,7 @@ impl PersistentNullifierSet {
 
     /// Spend a nullifier (persisted to disk)
     pub fn spend(&mut self, nullifier: Nullifier) -> Result<()> {
+        self.tree.insert(nullifier)?;
         self.set.spend(nullifier)?;
 
         // In production, persist to RocksDB
@@ -230,6 +282,7 @@ impl PersistentNullifierSet {
 
     /// Batch spend with atomic persistence
     pub fn spend_batch(&mut self, nullifiers: &[Nullifier]) -> Result<()> {
+        self.tree.insert_batch(nullifiers)?;
         self.set.spend_batch(nullifiers)?;
         self.persist()?;
         Ok(())
@@ -243,27 +296,22 @@ impl PersistentNullifierSet {
     }
 
     /// Get a snapshot of the nullifier set root (for consensus)
-    pub fn root_hash(&self) -> Blake3Hash {
-        // Compute Merkle root of all nullifiers
-        // This allows light clients to verify nullifier non-membership
-        let mut hasher = DomainSeparatedHasher::new("PRIVL1_NULLIFIER_ROOT");
-
-        // Sort nullifiers for deterministic hash
-        let mut nullifiers = self.set.all_nullifiers();
-        nullifiers.sort_by_key(|n| *n.as_bytes());
-
-        for nullifier in nullifiers {
-            hasher.update(nullifier.as_bytes());
-        }
+    ///
+    /// This is the root of the indexed nullifier tree, so light clients and
+    /// circuits can verify nullifier non-membership against it.
+    pub fn root_hash(&self) -> MerkleRoot {
+        self.tree.root()
+    }
 
-        hasher.finalize()
+    /// Prove that `nullifier` is unspent as of [`Self::root_hash`]
+    pub fn prove_unspent(&self, nullifier: &Nullifier) -> Result<NonMembershipProof> {
+        self.tree.prove_unspent(nullifier)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commitment::PedersenCommitment;
     use ark_std::test_rng;
 
     #[test]
@@ -272,9 +320,7 @@ mod tests {
         let nk = NullifierDerivingKey::random(&mut rng);
 
         // Create a test note
-        let pedersen = PedersenCommitment::new();
-        let (commitment, _) = pedersen.commit(100, &mut rng);
-        let note = Note::new(100, commitment, [1u8; 32]);
+        let note = Note::new(100, [1u8; 32]);
 
         // Derive nullifier
         let nullifier1 = nk.derive_nullifier(&note, 0);
@@ -286,6 +332,52 @@ mod tests {
         // Different position should give different nullifier
         let nullifier3 = nk.derive_nullifier(&note, 1);
         assert_ne!(nullifier1, nullifier3);
+
+        // Different key should give different nullifier
+        let other = NullifierDerivingKey::random(&mut rng);
+        assert_ne!(nullifier1, other.derive_nullifier(&note, 0));
+    }
+
+    #[test]
+    fn test_vote_nullifier_derivation() {
+        let mut rng = test_rng();
+        let nk = NullifierDerivingKey::random(&mut rng);
+        let note = Note::new(100, [1u8; 32]);
+        let ve_cm = note.commitment().to_field();
+
+        // One nullifier per NFT and epoch
+        assert_eq!(
+            nk.derive_vote_nullifier(ve_cm, 7),
+            nk.derive_vote_nullifier(ve_cm, 7)
+        );
+        assert_ne!(
+            nk.derive_vote_nullifier(ve_cm, 7),
+            nk.derive_vote_nullifier(ve_cm, 8)
+        );
+        assert_ne!(
+            nk.derive_vote_nullifier(ve_cm, 7),
+            NullifierDerivingKey::random(&mut rng).derive_vote_nullifier(ve_cm, 7)
+        );
+
+        // Never equal to the note nullifier at the same position
+        assert_ne!(
+            nk.derive_vote_nullifier(ve_cm, 0),
+            nk.derive_nullifier(&note, 0)
+        );
+
+        // Claim nullifiers are per receipt and differ from the vote's
+        let vnf = nk.derive_vote_nullifier(ve_cm, 7);
+        let receipt =
+            PoseidonHash::hash_two(bytes_to_base(vnf.as_bytes()), pallas::Base::one()).to_field();
+        assert_eq!(
+            nk.derive_claim_nullifier(receipt),
+            nk.derive_claim_nullifier(receipt)
+        );
+        assert_ne!(nk.derive_claim_nullifier(receipt), vnf);
+        assert_ne!(
+            nk.derive_claim_nullifier(receipt),
+            nk.derive_claim_nullifier(receipt + pallas::Base::one())
+        );
     }
 
     #[test]
@@ -362,6 +454,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_persistent_set_root() {
+        let mut set = PersistentNullifierSet::open("unused".into()).unwrap();
+        let (spent, unspent) = (Nullifier([1u8; 32]), Nullifier([2u8; 32]));
+        let empty = set.root_hash();
+
+        set.spend(spent).unwrap();
+        assert_ne!(set.root_hash(), empty);
+        assert!(set.prove_unspent(&spent).is_err());
+        assert!(set
+            .prove_unspent(&unspent)
+            .unwrap()
+            .verify(&unspent, &set.root_hash()));
+
+        // A failed batch leaves the root unchanged
+        let root = set.root_hash();
+        assert!(set.spend_batch(&[unspent, spent]).is_err());
+        assert_eq!(set.root_hash(), root);
+        assert!(!set.is_spent(&unspent));
+    }
+
     #[test]
     fn test_nullifier_from_seed() {
         let seed1 = [1u8; 32];
@@ -377,4 +490,4 @@ mod tests {
         // Different seed should give different key
         assert_ne!(nk1.as_scalar(), nk3.as_scalar());
     }
-}
\ No newline at end of file
+}
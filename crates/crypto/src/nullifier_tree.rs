@@ -0,0 +1,360 @@
+//! Indexed Merkle tree of spent nullifiers
+//!
+//! Spent nullifiers form a sorted linked list stored in a Poseidon Merkle tree
+//! of depth [`TREE_DEPTH`]. Each leaf is `Poseidon(key, next_key)`: a spent
+//! key and the smallest spent key above it. Leaf 0 is a sentinel from `0` to
+//! `2^250 - 1`, so every unspent key falls strictly between the two keys of
+//! exactly one leaf. A [`NonMembershipProof`] opens that leaf, which proves
+//! the nullifier is not spent as of the tree's root.
+//!
+//! Keys are the low [`NULLIFIER_KEY_BITS`] bits of the nullifier, so circuits
+//! can compare them with range checks.
+
+use ff::{Field, PrimeField};
+use pasta_curves::pallas;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::hash::{bytes_to_base, merkle_hash, PoseidonHash};
+use crate::merkle::{MerkleProof, MerkleRoot, TREE_DEPTH};
+use crate::nullifier::Nullifier;
+use crate::{CryptoError, Result};
+
+/// Bits of a nullifier used as its key in the tree
+pub const NULLIFIER_KEY_BITS: usize = 250;
+
+/// Key of `nullifier` in the tree: its low `NULLIFIER_KEY_BITS` bits
+pub fn nullifier_key(nullifier: &Nullifier) -> pallas::Base {
+    let mut bytes = *nullifier.as_bytes();
+    bytes[31] &= 0x03;
+    bytes_to_base(&bytes)
+}
+
+/// Leaf linking `key` to the next spent key, `Poseidon(key, next_key)`
+pub fn nullifier_leaf(key: pallas::Base, next_key: pallas::Base) -> [u8; 32] {
+    PoseidonHash::hash_two(key, next_key).to_field().to_repr()
+}
+
+/// Largest key, the upper end of the sentinel leaf
+fn max_key() -> pallas::Base {
+    pallas::Base::from(2).pow_vartime([NULLIFIER_KEY_BITS as u64]) - pallas::Base::ONE
+}
+
+/// Big-endian bytes of a key, so that byte order is numeric order
+fn sort_key(key: &pallas::Base) -> [u8; 32] {
+    let mut bytes = key.to_repr();
+    bytes.reverse();
+    bytes
+}
+
+/// A proof that a nullifier is not in the tree with a given root
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NonMembershipProof {
+    /// Key of the leaf whose range covers the nullifier
+    pub low_key: [u8; 32],
+    /// Next spent key after `low_key`
+    pub next_key: [u8; 32],
+    /// Authentication path of the leaf
+    pub merkle_proof: MerkleProof,
+}
+
+impl NonMembershipProof {
+    /// Key of the covering leaf as a field element
+    pub fn low_key(&self) -> Result<pallas::Base> {
+        canonical_key(&self.low_key)
+    }
+
+    /// Upper end of the covering leaf as a field element
+    pub fn next_key(&self) -> Result<pallas::Base> {
+        canonical_key(&self.next_key)
+    }
+
+    /// Verify that `nullifier` is unspent in the tree with root `root`
+    pub fn verify(&self, nullifier: &Nullifier, root: &MerkleRoot) -> bool {
+        let (Ok(low_key), Ok(next_key)) = (self.low_key(), self.next_key()) else {
+            return false;
+        };
+        let key = sort_key(&nullifier_key(nullifier));
+
+        sort_key(&low_key) < key
+            && key < sort_key(&next_key)
+            && self
+                .merkle_proof
+                .verify(&nullifier_leaf(low_key, next_key), root)
+    }
+}
+
+fn canonical_key(bytes: &[u8; 32]) -> Result<pallas::Base> {
+    Option::from(pallas::Base::from_repr(*bytes))
+        .ok_or_else(|| CryptoError::MerkleError("Non-canonical key".into()))
+}
+
+/// An indexed Merkle tree of spent nullifiers
+#[derive(Clone, Debug)]
+pub struct NullifierTree {
+    /// `(key, next_key)` of each leaf, by position
+    leaves: Vec<(pallas::Base, pallas::Base)>,
+    /// Leaf position of each key, ordered by key
+    positions: BTreeMap<[u8; 32], u64>,
+    /// Nodes that differ from the empty subtree, by `(level, index)`
+    nodes: HashMap<(usize, u64), [u8; 32]>,
+    /// Empty subtree hashes at each level
+    empty_hashes: Vec<[u8; 32]>,
+}
+
+impl NullifierTree {
+    /// Create a tree holding only the sentinel leaf
+    pub fn new() -> Self {
+        // Same empty leaves as the note commitment tree
+        let mut empty_hashes = vec![[0u8; 32]; TREE_DEPTH + 1];
+        for level in 1..=TREE_DEPTH {
+            let child = empty_hashes[level - 1];
+            empty_hashes[level] = merkle_hash(&child, &child);
+        }
+
+        let mut tree = Self {
+            leaves: Vec::new(),
+            positions: BTreeMap::new(),
+            nodes: HashMap::new(),
+            empty_hashes,
+        };
+        tree.push(pallas::Base::ZERO, max_key());
+        tree
+    }
+
+    /// Insert a spent nullifier, returning its leaf position
+    pub fn insert(&mut self, nullifier: Nullifier) -> Result<u64> {
+        if self.contains(&nullifier) {
+            return Err(CryptoError::OperationFailed("Double spend detected".into()));
+        }
+        if self.leaves.len() as u64 >= 1u64 << TREE_DEPTH {
+            return Err(CryptoError::MerkleError("Tree is full".into()));
+        }
+        let key = nullifier_key(&nullifier);
+
+        // Splice the key into the list after the covering leaf
+        let low = self.low_position(&key)?;
+        let (low_key, next_key) = self.leaves[low as usize];
+        self.set_leaf(low, low_key, key);
+        Ok(self.push(key, next_key))
+    }
+
+    /// Insert a batch of spent nullifiers, or none if any is already spent
+    /// or repeated
+    pub fn insert_batch(&mut self, nullifiers: &[Nullifier]) -> Result<()> {
+        let mut keys = HashSet::new();
+        for nullifier in nullifiers {
+            if self.contains(nullifier) || !keys.insert(sort_key(&nullifier_key(nullifier))) {
+                return Err(CryptoError::OperationFailed(format!(
+                    "Double spend detected: {}",
+                    nullifier.to_hex()
+                )));
+            }
+        }
+        if (self.leaves.len() + nullifiers.len()) as u64 > 1u64 << TREE_DEPTH {
+            return Err(CryptoError::MerkleError("Tree is full".into()));
+        }
+
+        for nullifier in nullifiers {
+            self.insert(*nullifier)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `nullifier`'s key is in the tree
+    ///
+    /// The sentinel keys `0` and `2^250 - 1` count as spent.
+    pub fn contains(&self, nullifier: &Nullifier) -> bool {
+        self.positions
+            .contains_key(&sort_key(&nullifier_key(nullifier)))
+    }
+
+    /// Prove that `nullifier` is not in the tree
+    pub fn prove_unspent(&self, nullifier: &Nullifier) -> Result<NonMembershipProof> {
+        if self.contains(nullifier) {
+            return Err(CryptoError::MerkleError("Nullifier is spent".into()));
+        }
+        let position = self.low_position(&nullifier_key(nullifier))?;
+        let (low_key, next_key) = self.leaves[position as usize];
+
+        let path = (0..TREE_DEPTH)
+            .map(|level| self.node(level, (position >> level) ^ 1))
+            .collect();
+        Ok(NonMembershipProof {
+            low_key: low_key.to_repr(),
+            next_key: next_key.to_repr(),
+            merkle_proof: MerkleProof { path, position },
+        })
+    }
+
+    /// Get the current root of the tree
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot::from_bytes(self.node(TREE_DEPTH, 0))
+    }
+
+    /// Number of spent nullifiers, not counting the sentinel
+    pub fn len(&self) -> usize {
+        self.leaves.len() - 1
+    }
+
+    /// Check if no nullifier has been spent
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of the leaf with the largest key below `key`
+    fn low_position(&self, key: &pallas::Base) -> Result<u64> {
+        self.positions
+            .range(..sort_key(key))
+            .next_back()
+            .map(|(_, position)| *position)
+            .ok_or_else(|| CryptoError::MerkleError("Key below the sentinel".into()))
+    }
+
+    /// Append a leaf, returning its position
+    fn push(&mut self, key: pallas::Base, next_key: pallas::Base) -> u64 {
+        let position = self.leaves.len() as u64;
+        self.leaves.push((key, next_key));
+        self.positions.insert(sort_key(&key), position);
+        self.set_leaf(position, key, next_key);
+        position
+    }
+
+    /// Overwrite a leaf and rehash its path to the root
+    fn set_leaf(&mut self, position: u64, key: pallas::Base, next_key: pallas::Base) {
+        self.leaves[position as usize] = (key, next_key);
+
+        let mut current = nullifier_leaf(key, next_key);
+        let mut index = position;
+        self.nodes.insert((0, index), current);
+        for level in 0..TREE_DEPTH {
+            let sibling = self.node(level, index ^ 1);
+            current = if index & 1 == 0 {
+                merkle_hash(&current, &sibling)
+            } else {
+                merkle_hash(&sibling, &current)
+            };
+            index >>= 1;
+            self.nodes.insert((level + 1, index), current);
+        }
+    }
+
+    /// Node at `(level, index)`, or the empty subtree hash
+    fn node(&self, level: usize, index: u64) -> [u8; 32] {
+        self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty_hashes[level])
+    }
+}
+
+impl Default for NullifierTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(seed: u64) -> Nullifier {
+        Nullifier::from_bytes(
+            PoseidonHash::hash_two(pallas::Base::from(seed), pallas::Base::ONE)
+                .to_field()
+                .to_repr(),
+        )
+    }
+
+    #[test]
+    fn test_nullifier_key() {
+        let nf = Nullifier::from_bytes([0xff; 32]);
+        let key = nullifier_key(&nf);
+        assert_eq!(key, max_key());
+
+        // The key drops the nullifier's top bits
+        let mut bytes = *nullifier(1).as_bytes();
+        bytes[31] ^= 0x20;
+        assert_eq!(
+            nullifier_key(&Nullifier::from_bytes(bytes)),
+            nullifier_key(&nullifier(1))
+        );
+    }
+
+    #[test]
+    fn test_non_membership() {
+        let mut tree = NullifierTree::new();
+        assert!(tree.is_empty());
+
+        let spent: Vec<_> = (0..10).map(nullifier).collect();
+        for nf in &spent {
+            tree.insert(*nf).unwrap();
+        }
+        assert_eq!(tree.len(), 10);
+        let root = tree.root();
+
+        for seed in 10..20 {
+            let nf = nullifier(seed);
+            let proof = tree.prove_unspent(&nf).unwrap();
+            assert!(proof.verify(&nf, &root));
+            // Not against another root
+            assert!(!proof.verify(&nf, &NullifierTree::new().root()));
+        }
+        for nf in &spent {
+            assert!(tree.contains(nf));
+            assert!(tree.prove_unspent(nf).is_err());
+        }
+    }
+
+    #[test]
+    fn test_spent_nullifier_cannot_be_proven_unspent() {
+        let mut tree = NullifierTree::new();
+        let nf = nullifier(1);
+        let proof = tree.prove_unspent(&nf).unwrap();
+        let before = tree.root();
+
+        tree.insert(nf).unwrap();
+        assert_ne!(tree.root(), before);
+        // The old proof only holds against the old root
+        assert!(proof.verify(&nf, &before));
+        assert!(!proof.verify(&nf, &tree.root()));
+
+        // The covering leaf now ends at the spent key
+        let other = nullifier(2);
+        let proof = tree.prove_unspent(&other).unwrap();
+        assert!(proof.verify(&other, &tree.root()));
+        assert!(!proof.verify(&nf, &tree.root()));
+    }
+
+    #[test]
+    fn test_double_spend_rejected() {
+        let mut tree = NullifierTree::new();
+        tree.insert(nullifier(1)).unwrap();
+        assert!(tree.insert(nullifier(1)).is_err());
+
+        // Batches are all or nothing
+        let root = tree.root();
+        assert!(tree.insert_batch(&[nullifier(2), nullifier(1)]).is_err());
+        assert!(tree.insert_batch(&[nullifier(2), nullifier(2)]).is_err());
+        assert_eq!(tree.root(), root);
+        assert!(!tree.contains(&nullifier(2)));
+
+        tree.insert_batch(&[nullifier(2), nullifier(3)]).unwrap();
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn test_historical_roots_keep_proofs() {
+        let mut tree = NullifierTree::new();
+        let mut proofs = Vec::new();
+        for seed in 0..4 {
+            tree.insert(nullifier(seed)).unwrap();
+            proofs.push((tree.root(), tree.prove_unspent(&nullifier(100)).unwrap()));
+        }
+
+        // Every historical root keeps its own valid proof
+        for (root, proof) in &proofs {
+            assert!(proof.verify(&nullifier(100), root));
+        }
+    }
+}
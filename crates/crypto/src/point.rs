@@ -1,7 +1,7 @@
 //! Point wrapper with proper serialization
 
-use ark_ec::CurveGroup;
-use pasta_curves::group::Group;
+use pasta_curves::arithmetic::{Coordinates, CurveAffine};
+use pasta_curves::group::{Curve, Group, GroupEncoding};
 use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Sub};
@@ -25,15 +25,15 @@ impl Point {
 
     /// Create from bytes (compressed format)
     pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self> {
-        // For now, simplified - in production would use proper point compression
-        Ok(Self(pallas::Point::identity()))
+        // The all-zero encoding is the identity, so dummy owners still round-trip
+        Option::from(pallas::Point::from_bytes(bytes))
+            .map(Self)
+            .ok_or(CryptoError::InvalidKey)
     }
 
     /// Convert to bytes (compressed format)
     pub fn to_bytes(&self) -> [u8; 32] {
-        // Simplified serialization
-        // In production, use proper point compression
-        [0u8; 32]
+        self.0.to_bytes()
     }
 
     /// Scalar multiplication
@@ -46,6 +46,14 @@ impl Point {
         self.0.is_identity().into()
     }
 
+    /// Affine coordinates, with the identity mapped to `(0, 0)` as in circuits
+    pub fn coordinates(&self) -> (pallas::Base, pallas::Base) {
+        Option::<Coordinates<pallas::Affine>>::from(self.0.to_affine().coordinates())
+            .map_or((pallas::Base::zero(), pallas::Base::zero()), |c| {
+                (*c.x(), *c.y())
+            })
+    }
+
     /// Get the inner pallas::Point
     pub fn inner(&self) -> &pallas::Point {
         &self.0
@@ -119,4 +127,26 @@ mod tests {
         assert!(id.is_identity());
         assert!(!double_g.is_identity());
     }
+
+    #[test]
+    fn test_point_serialization() {
+        let g = Point::generator();
+        let double_g = g + g;
+
+        let recovered = Point::from_bytes(&double_g.to_bytes()).unwrap();
+        assert_eq!(recovered, double_g);
+
+        // Identity encodes as all zeros
+        assert_eq!(Point::identity().to_bytes(), [0u8; 32]);
+        assert!(Point::from_bytes(&[0u8; 32]).unwrap().is_identity());
+    }
+
+    #[test]
+    fn test_point_coordinates() {
+        let (x, y) = Point::generator().coordinates();
+        assert_eq!(y.square(), x.square() * x + pallas::Base::from(5));
+
+        let (x, y) = Point::identity().coordinates();
+        assert_eq!((x, y), (pallas::Base::zero(), pallas::Base::zero()));
+    }
 }
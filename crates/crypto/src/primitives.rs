@@ -2,7 +2,7 @@
 
 use rand::RngCore;
 
-use crate::{Point, Scalar};
+use crate::Scalar;
 
 /// Generate a random field element
 pub fn random_field<R: RngCore>(rng: &mut R) -> Scalar {
@@ -3,10 +3,26 @@
 //! This module provides abstractions for the various ZK proofs used in PRIVL1.
 //! The actual circuit implementations will be in the circuits crate.
 
+use ff::PrimeField;
+use pasta_curves::pallas;
+use rand::{CryptoRng, RngCore};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
-use crate::{CryptoError, Result};
+use crate::aggregation;
+use crate::bulletproofs;
+use crate::commitment::Commitment;
+use crate::groth16;
+use crate::halo2;
+use crate::hash::DomainSeparatedHasher;
+use crate::keys::{Signature, SpendingKey};
+use crate::note::asset_base;
+use crate::registry::{self, CircuitId, VkRegistry, OUTPUT_CIRCUIT, SPEND_CIRCUIT};
+use crate::{CryptoError, Point, Result, Scalar};
 
 /// A zero-knowledge proof
 #[derive(Clone, Serialize, Deserialize)]
@@ -45,11 +61,29 @@ impl Halo2Proof {
         self.proof.len()
     }
 
+    /// Decode the public inputs as `pallas::Base` instances
+    pub fn instances(&self) -> Result<Vec<pallas::Base>> {
+        halo2::decode_instances(&self.public_inputs)
+    }
+
     /// Verify the proof
+    ///
+    /// Returns `Ok(false)` for a well-formed proof that does not verify, and an
+    /// error if the key, public inputs or proof bytes are malformed.
     pub fn verify(&self, vk: &VerificationKey) -> Result<bool> {
-        // In production, this would call the Halo2 verifier
-        // For now, placeholder
-        Ok(true)
+        if self.vk_id != vk.id() {
+            return Ok(false);
+        }
+
+        let (params, halo2_vk) = halo2::load_verifying_key(vk)?;
+        let instances = self.instances()?;
+
+        Ok(halo2::verify_proof(
+            &params,
+            &halo2_vk,
+            &self.proof,
+            &instances,
+        ))
     }
 }
 
@@ -69,16 +103,37 @@ impl fmt::Debug for Halo2Proof {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Groth16Proof {
     /// The proof data (compressed)
-    pub proof: Vec<u8>, // ~192 bytes for BLS12-381
+    pub proof: Vec<u8>, // 192 bytes for BLS12-381
     /// Public inputs
     pub public_inputs: Vec<Vec<u8>>,
+    /// Verification key identifier
+    #[serde(default)]
+    pub vk_id: [u8; 32],
 }
 
 impl Groth16Proof {
+    /// Create a new Groth16 proof
+    pub fn new(proof: Vec<u8>, public_inputs: Vec<Vec<u8>>, vk_id: [u8; 32]) -> Self {
+        Self {
+            proof,
+            public_inputs,
+            vk_id,
+        }
+    }
+
     /// Verify the proof
+    ///
+    /// Returns `Ok(false)` for a well-formed proof that does not verify, and an
+    /// error if the key, public inputs or proof bytes are malformed.
     pub fn verify(&self, vk: &VerificationKey) -> Result<bool> {
-        // Placeholder
-        Ok(true)
+        if self.vk_id != vk.id() {
+            return Ok(false);
+        }
+
+        let pvk = groth16::load_prepared_vk(vk)?;
+        let inputs = groth16::decode_inputs(&self.public_inputs)?;
+
+        groth16::verify(&pvk, &self.proof, &inputs)
     }
 }
 
@@ -87,14 +142,79 @@ impl Groth16Proof {
 pub struct BulletproofsRangeProof {
     /// The proof data
     pub proof: Vec<u8>,
-    /// Committed value (hidden)
-    pub commitment: [u8; 32],
-    /// Range: value is in [0, 2^range_bits)
+    /// Committed values (hidden), one per aggregated range proof
+    pub commitments: Vec<[u8; 32]>,
+    /// Range: each value is in [0, 2^range_bits)
     pub range_bits: u8,
 }
 
+impl BulletproofsRangeProof {
+    /// Prove that a committed value is in [0, 2^range_bits)
+    pub fn prove<R: RngCore + CryptoRng>(
+        value: u64,
+        blinding: Scalar,
+        range_bits: u8,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::prove_aggregated(&[value], &[blinding], range_bits, rng)
+    }
+
+    /// Prove that m committed values are in range with a single proof
+    ///
+    /// m must be a power of two (pad with commitments to zero if needed).
+    pub fn prove_aggregated<R: RngCore + CryptoRng>(
+        values: &[u64],
+        blindings: &[Scalar],
+        range_bits: u8,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let (proof, commitments) =
+            bulletproofs::prove(values, blindings, range_bits as usize, rng)?;
+
+        Ok(Self {
+            proof: proof.to_bytes(),
+            commitments: commitments.iter().map(Commitment::to_bytes).collect(),
+            range_bits,
+        })
+    }
+
+    /// Get the commitments this proof is bound to
+    pub fn commitments(&self) -> Result<Vec<Commitment>> {
+        self.commitments
+            .iter()
+            .map(|bytes| Commitment::from_bytes(bytes).map_err(|_| CryptoError::InvalidCommitment))
+            .collect()
+    }
+
+    /// Verify the proof
+    pub fn verify(&self) -> Result<bool> {
+        Self::verify_batch(std::slice::from_ref(self))
+    }
+
+    /// Verify many range proofs at once (one multi-scalar multiplication)
+    pub fn verify_batch(proofs: &[Self]) -> Result<bool> {
+        let decoded = proofs
+            .iter()
+            .map(|p| {
+                Ok((
+                    bulletproofs::RangeProof::from_bytes(&p.proof)?,
+                    p.commitments()?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let batch: Vec<_> = decoded
+            .iter()
+            .zip(proofs)
+            .map(|((proof, commitments), p)| (proof, commitments.as_slice(), p.range_bits as usize))
+            .collect();
+
+        bulletproofs::verify_batch(&batch)
+    }
+}
+
 /// A verification key
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VerificationKey {
     /// The key type
     pub key_type: ProofSystem,
@@ -122,6 +242,20 @@ impl VerificationKey {
     pub fn id(&self) -> [u8; 32] {
         self.key_hash
     }
+
+    /// Identifier recomputed from `key_data`, failing if `key_hash` disagrees
+    ///
+    /// `key_hash` arrives with the key, so anything that indexes by the id
+    /// must use this rather than trust the field.
+    pub fn checked_id(&self) -> Result<[u8; 32]> {
+        use crate::hash::Blake3Hash;
+
+        let id = *Blake3Hash::hash(&self.key_data).as_bytes();
+        if id != self.key_hash {
+            return Err(CryptoError::InvalidKey);
+        }
+        Ok(id)
+    }
 }
 
 /// Proof system types
@@ -143,6 +277,76 @@ pub struct TransactionProof {
     pub binding_sig: BindingSignature,
 }
 
+impl TransactionProof {
+    /// Message the spend authorization signatures sign
+    ///
+    /// Commits to every proof and its declared inputs and to the value
+    /// balance, but not to the signatures themselves.
+    pub fn sighash(&self) -> [u8; 32] {
+        let mut hasher = DomainSeparatedHasher::new("PRIVL1_SIGHASH");
+        hasher.update(&(self.spend_proofs.len() as u64).to_le_bytes());
+        for spend in &self.spend_proofs {
+            update_with_proof(&mut hasher, &spend.proof, spend.circuit_version);
+            hasher.update(spend.anchor.as_bytes());
+            hasher.update(spend.nullifier.as_bytes());
+            hasher.update(&spend.value_commitment.to_bytes());
+            hasher.update(&spend.rk.to_bytes());
+            hasher.update(&spend.asset_id);
+        }
+        hasher.update(&(self.output_proofs.len() as u64).to_le_bytes());
+        for output in &self.output_proofs {
+            update_with_proof(&mut hasher, &output.proof, output.circuit_version);
+            hasher.update(&output.commitment.to_bytes());
+            hasher.update(&output.value_commitment.to_bytes());
+        }
+        hasher.update(&self.binding_sig.value_balance.to_le_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Sign the `index`-th spend with the key it re-randomizes by `alpha`
+    ///
+    /// Sign once every proof is in place, since the signature covers them all.
+    /// Fails if `rk` is not `ak + [alpha]G` for this key.
+    pub fn authorize_spend(
+        &mut self,
+        index: usize,
+        key: &SpendingKey,
+        alpha: &Scalar,
+    ) -> Result<()> {
+        let sighash = self.sighash();
+        let spend = self
+            .spend_proofs
+            .get_mut(index)
+            .ok_or(CryptoError::InvalidProof)?;
+        if crate::keys::randomize_validating_key(&key.validating_key(), alpha) != spend.rk {
+            return Err(CryptoError::InvalidKey);
+        }
+
+        spend.spend_auth_sig = Some(key.sign_randomized(alpha, &sighash));
+        Ok(())
+    }
+
+    /// Check every spend's authorization signature against the sighash
+    pub fn authorizes_spends(&self) -> bool {
+        let sighash = self.sighash();
+        self.spend_proofs
+            .iter()
+            .all(|spend| spend.is_authorized(&sighash))
+    }
+}
+
+fn update_with_proof(hasher: &mut DomainSeparatedHasher, proof: &Halo2Proof, version: u32) {
+    hasher.update(&proof.vk_id);
+    hasher.update(&version.to_le_bytes());
+    hasher.update(&(proof.proof.len() as u64).to_le_bytes());
+    hasher.update(&proof.proof);
+    hasher.update(&(proof.public_inputs.len() as u64).to_le_bytes());
+    for input in &proof.public_inputs {
+        hasher.update(&(input.len() as u64).to_le_bytes());
+        hasher.update(input);
+    }
+}
+
 /// Proof of spending a note
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SpendProof {
@@ -152,6 +356,58 @@ pub struct SpendProof {
     pub nullifier: crate::nullifier::Nullifier,
     /// The Merkle root being anchored to
     pub anchor: crate::merkle::MerkleRoot,
+    /// Commitment to the spent value
+    pub value_commitment: Commitment,
+    /// Re-randomized spend validating key (verifies the spend authorization)
+    pub rk: Point,
+    /// Asset of the spent note
+    pub asset_id: [u8; 32],
+    /// Version of the spend circuit the proof targets
+    #[serde(default = "initial_circuit_version")]
+    pub circuit_version: u32,
+    /// Signature over the transaction's sighash under `rk`
+    ///
+    /// The proof shows knowledge of `ak` and `nk`, which the full viewing key
+    /// also holds; only this signature needs the spending key.
+    #[serde(default)]
+    pub spend_auth_sig: Option<Signature>,
+}
+
+impl SpendProof {
+    /// Public inputs of the spend circuit:
+    /// `[anchor, cv.x, cv.y, nf, rk.x, rk.y, asset_base(asset_id)]`
+    ///
+    /// Fails if the anchor or nullifier is not a canonical field encoding, so
+    /// two byte strings can never be bound to the same proof.
+    pub fn public_inputs(&self) -> Result<Vec<Vec<u8>>> {
+        let anchor = canonical_base(self.anchor.as_bytes())?;
+        let nullifier = canonical_base(self.nullifier.as_bytes())?;
+        let (cv_x, cv_y) = self.value_commitment.as_point().coordinates();
+        let (rk_x, rk_y) = self.rk.coordinates();
+        let asset = asset_base(&self.asset_id);
+
+        Ok([anchor, cv_x, cv_y, nullifier, rk_x, rk_y, asset]
+            .iter()
+            .map(halo2::encode_instance)
+            .collect())
+    }
+
+    /// Check that the proof's public inputs are the ones this spend declares
+    pub fn binds_public_inputs(&self) -> bool {
+        self.public_inputs()
+            .is_ok_and(|inputs| inputs == self.proof.public_inputs)
+    }
+
+    /// Check the spend authorization signature over `sighash`
+    pub fn is_authorized(&self, sighash: &[u8; 32]) -> bool {
+        self.spend_auth_sig
+            .as_ref()
+            .is_some_and(|sig| sig.verify(&self.rk, sighash))
+    }
+}
+
+fn canonical_base(bytes: &[u8; 32]) -> Result<pallas::Base> {
+    Option::from(pallas::Base::from_repr(*bytes)).ok_or(CryptoError::InvalidProof)
 }
 
 /// Proof of creating a note
@@ -161,6 +417,34 @@ pub struct OutputProof {
     pub proof: Halo2Proof,
     /// The commitment being created
     pub commitment: crate::note::NoteCommitment,
+    /// Commitment to the created value
+    pub value_commitment: Commitment,
+    /// Version of the output circuit the proof targets
+    #[serde(default = "initial_circuit_version")]
+    pub circuit_version: u32,
+}
+
+impl OutputProof {
+    /// Public inputs of the output circuit:
+    /// `[cm, cv.x, cv.y, asset_base(commitment.asset_id)]`
+    pub fn public_inputs(&self) -> Vec<Vec<u8>> {
+        let (cv_x, cv_y) = self.value_commitment.as_point().coordinates();
+        let asset = asset_base(self.commitment.asset_id());
+
+        [self.commitment.to_field(), cv_x, cv_y, asset]
+            .iter()
+            .map(halo2::encode_instance)
+            .collect()
+    }
+
+    /// Check that the proof's public inputs are the ones this output declares
+    pub fn binds_public_inputs(&self) -> bool {
+        self.public_inputs() == self.proof.public_inputs
+    }
+}
+
+fn initial_circuit_version() -> u32 {
+    registry::INITIAL_VERSION
 }
 
 /// Binding signature for value conservation
@@ -173,34 +457,126 @@ pub struct BindingSignature {
 }
 
 /// Aggregated proof (for block-level aggregation)
+///
+/// Folds N Halo2 proofs for one verification key via IPA accumulation (see
+/// [`crate::aggregation`]). `proof.public_inputs` holds every inner proof's
+/// inputs in order. The proof still contains every inner proof, and
+/// verifying it still checks each of them; it shares only the final
+/// generator MSM, so it saves verification time but not space.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct AggregatedProof {
     /// The aggregated Halo2 proof
     pub proof: Halo2Proof,
     /// Number of proofs aggregated
     pub num_proofs: u32,
-    /// Root of aggregation tree
+    /// Merkle root of the aggregated public inputs
     pub aggregation_root: [u8; 32],
 }
 
 impl AggregatedProof {
-    /// Create a new aggregated proof
-    pub fn new(proof: Halo2Proof, num_proofs: u32) -> Self {
-        use crate::hash::Blake3Hash;
+    /// Aggregate proofs that share a verification key
+    ///
+    /// Fails if any proof does not verify or the proofs carry differing
+    /// numbers of public inputs.
+    pub fn aggregate<R: RngCore + CryptoRng>(
+        vk: &VerificationKey,
+        proofs: &[&Halo2Proof],
+        rng: R,
+    ) -> Result<Self> {
+        let first = proofs.first().ok_or(CryptoError::InvalidProof)?;
+        if proofs
+            .iter()
+            .any(|p| p.vk_id != vk.id() || p.public_inputs.len() != first.public_inputs.len())
+        {
+            return Err(CryptoError::InvalidProof);
+        }
 
-        // Compute aggregation root
-        let root = Blake3Hash::hash(&proof.proof);
+        let (params, halo2_vk) = halo2::load_verifying_key(vk)?;
+        let k = halo2::Halo2KeyData::from_bytes(&vk.key_data)?.k;
 
-        Self {
-            proof,
-            num_proofs,
-            aggregation_root: *root.as_bytes(),
-        }
+        let instances = proofs
+            .iter()
+            .map(|p| p.instances())
+            .collect::<Result<Vec<_>>>()?;
+        let inputs: Vec<(&[u8], &[pallas::Base])> = proofs
+            .iter()
+            .zip(&instances)
+            .map(|(p, i)| (p.proof.as_slice(), i.as_slice()))
+            .collect();
+        let aggregated = aggregation::aggregate(&params, &halo2_vk, k, &inputs, rng)?;
+
+        let public_inputs: Vec<&[Vec<u8>]> =
+            proofs.iter().map(|p| p.public_inputs.as_slice()).collect();
+
+        Ok(Self {
+            proof: Halo2Proof::new(aggregated, public_inputs.concat(), vk.id()),
+            num_proofs: proofs.len() as u32,
+            aggregation_root: aggregation::aggregation_root(&public_inputs),
+        })
+    }
+
+    /// Public inputs of the `index`-th aggregated proof
+    pub fn public_inputs_of(&self, index: usize) -> Option<&[Vec<u8>]> {
+        let per_proof = self.inputs_per_proof()?;
+        self.proof
+            .public_inputs
+            .get(index * per_proof..(index + 1) * per_proof)
     }
 
     /// Verify the aggregated proof
+    ///
+    /// Checks `num_proofs` and `aggregation_root` against the proof itself, so
+    /// neither can be chosen freely by the sender.
     pub fn verify(&self, vk: &VerificationKey) -> Result<bool> {
-        self.proof.verify(vk)
+        if self.proof.vk_id != vk.id() {
+            return Ok(false);
+        }
+        let per_proof = self.inputs_per_proof().ok_or(CryptoError::InvalidProof)?;
+
+        let grouped: Vec<&[Vec<u8>]> = if per_proof == 0 {
+            vec![&[][..]; self.num_proofs as usize]
+        } else {
+            self.proof.public_inputs.chunks(per_proof).collect()
+        };
+        if aggregation::aggregation_root(&grouped) != self.aggregation_root {
+            return Ok(false);
+        }
+
+        let (params, halo2_vk) = halo2::load_verifying_key(vk)?;
+        let instances = grouped
+            .iter()
+            .map(|inputs| halo2::decode_instances(inputs))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(aggregation::verify_aggregate(
+            &params,
+            &halo2_vk,
+            &self.proof.proof,
+            &instances,
+        ))
+    }
+
+    /// Number of public inputs per aggregated proof
+    fn inputs_per_proof(&self) -> Option<usize> {
+        let n = self.num_proofs as usize;
+        if n == 0 || !self.proof.public_inputs.len().is_multiple_of(n) {
+            return None;
+        }
+        Some(self.proof.public_inputs.len() / n)
+    }
+}
+
+/// Outcome of batch verification
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchVerification {
+    /// Indices of the items that failed (sorted, deduplicated)
+    pub failed: Vec<usize>,
+}
+
+impl BatchVerification {
+    /// Check if every item verified
+    pub fn is_valid(&self) -> bool {
+        self.failed.is_empty()
     }
 }
 
@@ -208,6 +584,12 @@ impl AggregatedProof {
 pub struct ProofVerifier {
     /// Verification keys
     vks: std::collections::HashMap<[u8; 32], VerificationKey>,
+    /// Versioned circuit registry
+    registry: VkRegistry,
+    /// Chain height circuit versions are checked at when none is given
+    height: u64,
+    /// Dedicated thread pool for batch verification (global pool if unset)
+    pool: Option<Arc<ThreadPool>>,
 }
 
 impl ProofVerifier {
@@ -215,36 +597,128 @@ impl ProofVerifier {
     pub fn new() -> Self {
         Self {
             vks: std::collections::HashMap::new(),
+            registry: VkRegistry::new(),
+            height: 0,
+            pool: None,
         }
     }
 
+    /// Create a proof verifier with its own batch-verification thread pool
+    pub fn with_threads(num_threads: usize) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("privl1-verify-{}", i))
+            .build()
+            .map_err(|e| CryptoError::OperationFailed(format!("Thread pool: {}", e)))?;
+
+        Ok(Self {
+            vks: std::collections::HashMap::new(),
+            registry: VkRegistry::new(),
+            height: 0,
+            pool: Some(Arc::new(pool)),
+        })
+    }
+
     /// Register a verification key
     pub fn register_vk(&mut self, vk: VerificationKey) {
         self.vks.insert(vk.id(), vk);
     }
 
+    /// Get the versioned circuit registry
+    pub fn registry(&self) -> &VkRegistry {
+        &self.registry
+    }
+
+    /// Get the versioned circuit registry for upgrades
+    pub fn registry_mut(&mut self) -> &mut VkRegistry {
+        &mut self.registry
+    }
+
+    /// Set the chain height at which `verify_transaction` and `verify_block`
+    /// check circuit versions
+    pub fn set_height(&mut self, height: u64) {
+        self.height = height;
+    }
+
+    /// Look up a verification key, unversioned keys first
+    pub fn get_vk(&self, vk_id: &[u8; 32]) -> Option<&VerificationKey> {
+        self.vks.get(vk_id).or_else(|| {
+            let circuit = self.registry.circuit_for_vk(vk_id)?;
+            self.registry.get(circuit).map(|entry| &entry.vk)
+        })
+    }
+
     /// Verify a Halo2 proof
     pub fn verify_halo2(&self, proof: &Halo2Proof) -> Result<bool> {
-        let vk = self
-            .vks
-            .get(&proof.vk_id)
-            .ok_or(CryptoError::InvalidProof)?;
+        let vk = self.get_vk(&proof.vk_id).ok_or(CryptoError::InvalidProof)?;
 
         proof.verify(vk)
     }
 
-    /// Verify a transaction proof
+    /// Check that every proof targets a circuit version accepted at `height`
+    /// and uses that version's key
+    ///
+    /// Proofs under keys added with `register_vk` are unversioned and skip
+    /// the check.
+    pub fn check_circuit_versions(&self, tx_proof: &TransactionProof, height: u64) -> Result<()> {
+        let declared = tx_proof
+            .spend_proofs
+            .iter()
+            .map(|p| (SPEND_CIRCUIT, p.circuit_version, &p.proof))
+            .chain(
+                tx_proof
+                    .output_proofs
+                    .iter()
+                    .map(|p| (OUTPUT_CIRCUIT, p.circuit_version, &p.proof)),
+            );
+
+        for (name, version, proof) in declared {
+            if self.vks.contains_key(&proof.vk_id) {
+                continue;
+            }
+            let vk = self
+                .registry
+                .resolve(&CircuitId::new(name, version), height)?;
+            if vk.id() != proof.vk_id {
+                return Err(CryptoError::RegistryError(format!(
+                    "Proof key does not match {} v{}",
+                    name, version
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify a transaction proof at a block height, including each spend's
+    /// authorization signature
+    ///
+    /// Errors if a declared circuit version is not accepted at `height`.
+    pub fn verify_transaction_at(&self, tx_proof: &TransactionProof, height: u64) -> Result<bool> {
+        self.check_circuit_versions(tx_proof, height)?;
+        self.verify_transaction_proofs(tx_proof)
+    }
+
+    /// Verify a transaction proof at the height set with `set_height`
     pub fn verify_transaction(&self, tx_proof: &TransactionProof) -> Result<bool> {
+        self.verify_transaction_at(tx_proof, self.height)
+    }
+
+    /// Verify a transaction's proofs and signatures, not its circuit versions
+    fn verify_transaction_proofs(&self, tx_proof: &TransactionProof) -> Result<bool> {
+        if !tx_proof.authorizes_spends() {
+            return Ok(false);
+        }
+
         // Verify all spend proofs
         for spend in &tx_proof.spend_proofs {
-            if !self.verify_halo2(&spend.proof)? {
+            if !spend.binds_public_inputs() || !self.verify_halo2(&spend.proof)? {
                 return Ok(false);
             }
         }
 
         // Verify all output proofs
         for output in &tx_proof.output_proofs {
-            if !self.verify_halo2(&output.proof)? {
+            if !output.binds_public_inputs() || !self.verify_halo2(&output.proof)? {
                 return Ok(false);
             }
         }
@@ -254,6 +728,150 @@ impl ProofVerifier {
 
         Ok(true)
     }
+
+    /// Batch-verify Halo2 proofs
+    ///
+    /// Proofs are grouped by verification key and each group is checked with a
+    /// single accumulated MSM. If a group fails, its proofs are re-checked one by
+    /// one to find the culprits. Unknown keys and malformed inputs count as
+    /// failures rather than aborting the batch.
+    pub fn verify_halo2_batch(&self, proofs: &[&Halo2Proof]) -> BatchVerification {
+        self.install(|| {
+            let mut groups: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+            for (index, proof) in proofs.iter().enumerate() {
+                groups.entry(proof.vk_id).or_default().push(index);
+            }
+
+            let mut failed: Vec<usize> = groups
+                .into_par_iter()
+                .flat_map(|(vk_id, indices)| self.verify_group(&vk_id, indices, proofs))
+                .collect();
+            failed.sort_unstable();
+
+            BatchVerification { failed }
+        })
+    }
+
+    /// Batch-verify a block at the height set with `set_height`
+    pub fn verify_block(&self, transactions: &[TransactionProof]) -> BatchVerification {
+        self.verify_block_at(transactions, self.height)
+    }
+
+    /// Batch-verify a block at its height, also rejecting transactions whose
+    /// declared circuit versions are not accepted at that height
+    ///
+    /// `failed` holds the indices of the invalid transactions, including any
+    /// with a missing or invalid spend authorization signature.
+    pub fn verify_block_at(
+        &self,
+        transactions: &[TransactionProof],
+        height: u64,
+    ) -> BatchVerification {
+        let mut failed: Vec<usize> = transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| self.check_circuit_versions(tx, height).is_err())
+            .map(|(index, _)| index)
+            .collect();
+
+        failed.extend(self.verify_block_proofs(transactions).failed);
+        failed.sort_unstable();
+        failed.dedup();
+
+        BatchVerification { failed }
+    }
+
+    /// Batch-verify a block's proofs and signatures, not its circuit versions
+    fn verify_block_proofs(&self, transactions: &[TransactionProof]) -> BatchVerification {
+        let mut proofs = Vec::new();
+        let mut owners = Vec::new();
+        let mut failed = Vec::new();
+
+        for (tx_index, tx) in transactions.iter().enumerate() {
+            if !tx.authorizes_spends()
+                || !tx.spend_proofs.iter().all(SpendProof::binds_public_inputs)
+                || !tx
+                    .output_proofs
+                    .iter()
+                    .all(OutputProof::binds_public_inputs)
+            {
+                failed.push(tx_index);
+                continue;
+            }
+            for spend in &tx.spend_proofs {
+                proofs.push(&spend.proof);
+                owners.push(tx_index);
+            }
+            for output in &tx.output_proofs {
+                proofs.push(&output.proof);
+                owners.push(tx_index);
+            }
+        }
+
+        failed.extend(
+            self.verify_halo2_batch(&proofs)
+                .failed
+                .into_iter()
+                .map(|index| owners[index]),
+        );
+        failed.sort_unstable();
+        failed.dedup();
+
+        BatchVerification { failed }
+    }
+
+    /// Verify proofs sharing one verification key, returning the failing indices
+    fn verify_group(
+        &self,
+        vk_id: &[u8; 32],
+        indices: Vec<usize>,
+        proofs: &[&Halo2Proof],
+    ) -> Vec<usize> {
+        let loaded = self
+            .get_vk(vk_id)
+            .ok_or(CryptoError::InvalidProof)
+            .and_then(halo2::load_verifying_key);
+        let (params, vk) = match loaded {
+            Ok(loaded) => loaded,
+            Err(_) => return indices,
+        };
+
+        let mut failed = Vec::new();
+        let mut decoded = Vec::with_capacity(indices.len());
+        for index in indices {
+            match proofs[index].instances() {
+                Ok(instances) => decoded.push((index, instances)),
+                Err(_) => failed.push(index),
+            }
+        }
+
+        let batch: Vec<(&[u8], &[pallas::Base])> = decoded
+            .iter()
+            .map(|(index, instances)| (proofs[*index].proof.as_slice(), instances.as_slice()))
+            .collect();
+        if halo2::batch_verify(&params, &vk, &batch) {
+            return failed;
+        }
+
+        // Fall back to per-proof checks to find the failing ones
+        failed.par_extend(
+            decoded
+                .par_iter()
+                .filter(|(index, instances)| {
+                    !halo2::verify_proof(&params, &vk, &proofs[*index].proof, instances)
+                })
+                .map(|(index, _)| *index),
+        );
+        failed
+    }
+
+    /// Run on the dedicated thread pool, if any
+    fn install<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
 }
 
 impl Default for ProofVerifier {
@@ -265,6 +883,10 @@ impl Default for ProofVerifier {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::plonk::{
+        self, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Instance,
+    };
 
     #[test]
     fn test_halo2_proof_creation() {
@@ -286,12 +908,69 @@ mod tests {
         assert_eq!(id1, id2); // ID should be deterministic
     }
 
+    #[test]
+    fn test_verify_at_height() {
+        let (vk, tx) = output_tx(&[6]);
+        let mut verifier = ProofVerifier::new();
+        let v1 = verifier
+            .registry_mut()
+            .schedule_upgrade(OUTPUT_CIRCUIT, vk.clone(), 10, 0)
+            .unwrap();
+        assert_eq!(v1.version, registry::INITIAL_VERSION);
+
+        // Not active yet
+        assert!(verifier.verify_transaction_at(&tx, 9).is_err());
+        assert!(verifier.verify_transaction_at(&tx, 10).unwrap());
+
+        // Declaring a version that does not exist
+        let mut undeclared = tx.clone();
+        undeclared.output_proofs[0].circuit_version = 2;
+        assert!(verifier.verify_transaction_at(&undeclared, 10).is_err());
+
+        // Upgrade to v2 with a new key; v1 retired after a 5-block grace period
+        let new_key = VerificationKey::new(ProofSystem::Halo2, vec![9, 9, 9]);
+        verifier
+            .registry_mut()
+            .schedule_upgrade(OUTPUT_CIRCUIT, new_key, 20, 5)
+            .unwrap();
+        assert!(verifier.verify_transaction_at(&tx, 24).unwrap());
+        assert!(verifier.verify_transaction_at(&tx, 25).is_err());
+
+        // A v2 declaration must use the v2 key
+        assert!(verifier.verify_transaction_at(&undeclared, 25).is_err());
+
+        let result = verifier.verify_block_at(&[tx.clone(), tx.clone()], 30);
+        assert_eq!(result.failed, vec![0, 1]);
+
+        // Without a height, versions are checked at the one set on the verifier
+        verifier.set_height(24);
+        assert!(verifier.verify_transaction(&tx).unwrap());
+        verifier.set_height(25);
+        assert!(verifier.verify_transaction(&tx).is_err());
+        assert_eq!(verifier.verify_block(&[tx]).failed, vec![0]);
+    }
+
     #[test]
     fn test_aggregated_proof() {
-        let halo2 = Halo2Proof::new(vec![1, 2, 3], vec![], [0u8; 32]);
-        let aggregated = AggregatedProof::new(halo2, 100);
+        let (vk, tx) = output_tx(&[2, 3, 4]);
+        let proofs: Vec<&Halo2Proof> = tx.output_proofs.iter().map(|o| &o.proof).collect();
+
+        let aggregated = AggregatedProof::aggregate(&vk, &proofs, rand::rngs::OsRng).unwrap();
+        assert_eq!(aggregated.num_proofs, 3);
+        assert_eq!(
+            aggregated.public_inputs_of(1),
+            Some(&proofs[1].public_inputs[..])
+        );
+        assert!(aggregated.verify(&vk).unwrap());
 
-        assert_eq!(aggregated.num_proofs, 100);
+        // A claimed count or root that does not match the proof is rejected
+        let mut inflated = aggregated.clone();
+        inflated.num_proofs = 100;
+        assert!(inflated.verify(&vk).is_err());
+
+        let mut wrong_root = aggregated;
+        wrong_root.aggregation_root = [0u8; 32];
+        assert!(!wrong_root.verify(&vk).unwrap());
     }
 
     #[test]
@@ -305,7 +984,244 @@ mod tests {
 
         let proof = Halo2Proof::new(vec![1], vec![], vk_id);
 
-        // Should verify successfully (placeholder always returns true)
+        // Malformed key data fails closed
+        assert!(verifier.verify_halo2(&proof).is_err());
+    }
+
+    #[test]
+    fn test_halo2_proof_verify() {
+        let (vk, proof_bytes, y) = halo2::tests::square_proof(3);
+        let mut verifier = ProofVerifier::new();
+        verifier.register_vk(vk.clone());
+
+        let proof = Halo2Proof::new(
+            proof_bytes.clone(),
+            vec![halo2::encode_instance(&y)],
+            vk.id(),
+        );
         assert!(verifier.verify_halo2(&proof).unwrap());
+
+        // Wrong public input
+        let wrong = halo2::encode_instance(&(y + pallas::Base::one()));
+        let proof = Halo2Proof::new(proof_bytes.clone(), vec![wrong], vk.id());
+        assert!(!verifier.verify_halo2(&proof).unwrap());
+
+        // Garbage proof bytes
+        let proof = Halo2Proof::new(vec![0xab; 64], vec![halo2::encode_instance(&y)], vk.id());
+        assert!(!verifier.verify_halo2(&proof).unwrap());
+
+        // Malformed public input encoding
+        let proof = Halo2Proof::new(proof_bytes, vec![vec![0xff; 32]], vk.id());
+        assert!(verifier.verify_halo2(&proof).is_err());
+    }
+
+    const TEST_OUTPUT_CIRCUIT: &str = "test-output-inputs";
+
+    /// Exposes four witnessed values, standing in for the output circuit's
+    /// `[cm, cv.x, cv.y, asset]`
+    #[derive(Clone, Default)]
+    struct ExposeCircuit {
+        values: [Value<pallas::Base>; 4],
+    }
+
+    impl Circuit<pallas::Base> for ExposeCircuit {
+        type Config = (Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+            (advice, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, instance): Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> std::result::Result<(), plonk::Error> {
+            for (row, value) in self.values.iter().enumerate() {
+                let cell = layouter.assign_region(
+                    || "value",
+                    |mut region| region.assign_advice(|| "value", advice, 0, || *value),
+                )?;
+                layouter.constrain_instance(cell.cell(), instance, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    fn build_expose_vk(params: &halo2::Halo2Params) -> Result<halo2::Halo2VerifyingKey> {
+        keygen_vk(params, &ExposeCircuit::default())
+            .map_err(|e| CryptoError::OperationFailed(format!("{:?}", e)))
+    }
+
+    /// A transaction with one output proof per value, each bound to a note of
+    /// that value and its value commitment
+    fn output_tx(values: &[u64]) -> (VerificationKey, TransactionProof) {
+        halo2::register_circuit(TEST_OUTPUT_CIRCUIT, build_expose_vk);
+        let params = halo2::params(halo2::tests::TEST_K).unwrap();
+        let vk = build_expose_vk(&params).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &ExposeCircuit::default()).unwrap();
+        let key = halo2::verification_key(TEST_OUTPUT_CIRCUIT, halo2::tests::TEST_K, &vk);
+
+        let mut rng = rand::rngs::OsRng;
+        let output_proofs = values
+            .iter()
+            .map(|value| {
+                let note = crate::note::Note::new(*value, [0u8; 32]);
+                let value_commitment = crate::commitment::PedersenCommitment::new()
+                    .commit_with_blinding(*value, Scalar::random(&mut rng));
+                let mut output = OutputProof {
+                    proof: Halo2Proof::new(vec![], vec![], key.id()),
+                    commitment: note.commitment(),
+                    value_commitment,
+                    circuit_version: registry::INITIAL_VERSION,
+                };
+                let instances = halo2::decode_instances(&output.public_inputs()).unwrap();
+                let circuit = ExposeCircuit {
+                    values: [0, 1, 2, 3].map(|i| Value::known(instances[i])),
+                };
+                output.proof.proof =
+                    halo2::create_proof(&params, &pk, circuit, &instances, rng).unwrap();
+                output.proof.public_inputs = output.public_inputs();
+                output
+            })
+            .collect();
+
+        let tx = TransactionProof {
+            spend_proofs: vec![],
+            output_proofs,
+            binding_sig: BindingSignature {
+                signature: vec![],
+                value_balance: 0,
+            },
+        };
+        (key, tx)
+    }
+
+    #[test]
+    fn test_output_binds_commitments() {
+        let (vk, tx) = output_tx(&[10, 20]);
+        let mut verifier = ProofVerifier::new();
+        verifier.register_vk(vk);
+        assert!(tx
+            .output_proofs
+            .iter()
+            .all(OutputProof::binds_public_inputs));
+        assert!(verifier.verify_transaction(&tx).unwrap());
+
+        // Each proof verifies on its own, but not for the other output's
+        // note or value commitment
+        let mut swapped = tx.clone();
+        let (first, second) = swapped.output_proofs.split_at_mut(1);
+        std::mem::swap(&mut first[0].commitment, &mut second[0].commitment);
+        assert!(verifier
+            .verify_halo2(&swapped.output_proofs[0].proof)
+            .unwrap());
+        assert!(!verifier.verify_transaction(&swapped).unwrap());
+
+        let mut swapped_cv = tx.clone();
+        let (first, second) = swapped_cv.output_proofs.split_at_mut(1);
+        std::mem::swap(
+            &mut first[0].value_commitment,
+            &mut second[0].value_commitment,
+        );
+        assert!(!verifier.verify_transaction(&swapped_cv).unwrap());
+
+        let result = verifier.verify_block(&[tx, swapped, swapped_cv]);
+        assert_eq!(result.failed, vec![1, 2]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_verify_block() {
+        let mut verifier = ProofVerifier::with_threads(2).unwrap();
+
+        let (vk, tx0) = output_tx(&[1, 2]);
+        let (_, tx1) = output_tx(&[3]);
+        let (_, mut tx2) = output_tx(&[4, 5]);
+        verifier.register_vk(vk);
+
+        let block = vec![tx0.clone(), tx1.clone(), tx2.clone()];
+        assert!(verifier.verify_block(&block).is_valid());
+        assert!(verifier.verify_transaction(&tx0).unwrap());
+
+        // Corrupt one proof in tx2 and use an unknown key in tx0
+        tx2.output_proofs[1].proof.public_inputs[0] =
+            halo2::encode_instance(&pallas::Base::from(99u64));
+        let mut tx0_bad = tx0;
+        tx0_bad.output_proofs[0].proof.vk_id = [0xee; 32];
+
+        let result = verifier.verify_block(&[tx0_bad, tx1, tx2]);
+        assert_eq!(result.failed, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_batch_finds_the_failing_proof() {
+        let mut verifier = ProofVerifier::new();
+        let (vk, tx0) = output_tx(&[1, 2]);
+        let (_, mut tx1) = output_tx(&[3, 4]);
+        verifier.register_vk(vk);
+
+        // The second output carries the first one's proof: its public inputs
+        // still match its note, so the proof check rather than the input
+        // binding rejects it, and the failed batch is re-checked per proof
+        tx1.output_proofs[1].proof.proof = tx1.output_proofs[0].proof.proof.clone();
+        assert!(tx1
+            .output_proofs
+            .iter()
+            .all(OutputProof::binds_public_inputs));
+
+        let proofs: Vec<&Halo2Proof> = tx0
+            .output_proofs
+            .iter()
+            .chain(&tx1.output_proofs)
+            .map(|output| &output.proof)
+            .collect();
+        assert_eq!(verifier.verify_halo2_batch(&proofs).failed, vec![3]);
+
+        let result = verifier.verify_block(&[tx0, tx1]);
+        assert_eq!(result.failed, vec![1]);
+    }
+
+    #[test]
+    fn test_groth16_proof_verify() {
+        let (vk, proof, y) = groth16::tests::cubic_proof(2);
+        let groth16_proof = Groth16Proof::new(proof, vec![groth16::encode_input(&y)], vk.id());
+        assert!(groth16_proof.verify(&vk).unwrap());
+
+        // Wrong key id
+        let other = VerificationKey::new(ProofSystem::Groth16, vec![1, 2, 3]);
+        assert!(!groth16_proof.verify(&other).unwrap());
+
+        // Wrong public input
+        let mut wrong = groth16_proof.clone();
+        wrong.public_inputs = vec![groth16::encode_input(&ark_bls12_381::Fr::from(1u64))];
+        assert!(!wrong.verify(&vk).unwrap());
+    }
+
+    #[test]
+    fn test_bulletproofs_range_proof() {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0);
+        let blindings = [Scalar::random(&mut rng), Scalar::random(&mut rng)];
+
+        let single = BulletproofsRangeProof::prove(1000, blindings[0], 64, &mut rng).unwrap();
+        assert_eq!(single.commitments.len(), 1);
+        assert!(single.verify().unwrap());
+
+        let aggregated =
+            BulletproofsRangeProof::prove_aggregated(&[5, 6], &blindings, 32, &mut rng).unwrap();
+        assert_eq!(aggregated.commitments.len(), 2);
+        assert!(BulletproofsRangeProof::verify_batch(&[single, aggregated.clone()]).unwrap());
+
+        // Malformed bytes fail closed
+        let mut truncated = aggregated;
+        truncated.proof.truncate(64);
+        assert!(truncated.verify().is_err());
+    }
+}
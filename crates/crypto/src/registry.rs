@@ -0,0 +1,432 @@
+//! Versioned verification-key registry
+//!
+//! Circuits are identified by name and version. Each version maps to one
+//! verification key and is valid for a window of block heights:
+//! `activation_height <= height < deprecation_height`.
+//!
+//! Proofs declare the circuit version they target, so fixing a circuit is a
+//! registry update rather than a change to the proof format: register the
+//! fixed version with a future activation height and schedule the old one's
+//! deprecation. Both versions are accepted during the overlap, which gives
+//! wallets time to switch.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::proof::VerificationKey;
+use crate::{CryptoError, Result};
+
+/// Circuit name of spend proofs
+pub const SPEND_CIRCUIT: &str = "spend";
+
+/// Circuit name of output proofs
+pub const OUTPUT_CIRCUIT: &str = "output";
+
+/// Version assumed for proofs that predate version declarations
+pub const INITIAL_VERSION: u32 = 1;
+
+/// A circuit name and version
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CircuitId {
+    /// Circuit name
+    pub name: String,
+    /// Circuit version
+    pub version: u32,
+}
+
+impl CircuitId {
+    /// Create a new circuit identifier
+    pub fn new(name: &str, version: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            version,
+        }
+    }
+}
+
+/// A registered circuit version
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// The circuit version
+    pub circuit: CircuitId,
+    /// Its verification key
+    pub vk: VerificationKey,
+    /// First height at which proofs for this version are accepted
+    pub activation_height: u64,
+    /// First height at which proofs are no longer accepted
+    pub deprecation_height: Option<u64>,
+}
+
+impl RegistryEntry {
+    /// Check if the version is accepted at a height
+    pub fn is_active(&self, height: u64) -> bool {
+        height >= self.activation_height && self.deprecation_height.is_none_or(|d| height < d)
+    }
+}
+
+/// Registry of circuit versions and their verification keys
+///
+/// Serialized as its list of entries, since neither map has string keys.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<RegistryEntry>", try_from = "Vec<RegistryEntry>")]
+pub struct VkRegistry {
+    /// Entries by circuit version
+    entries: HashMap<CircuitId, RegistryEntry>,
+    /// Circuit version by verification key id
+    by_vk: HashMap<[u8; 32], CircuitId>,
+}
+
+impl VkRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a circuit version
+    ///
+    /// Versions are immutable once registered, and a verification key can
+    /// belong to only one circuit version.
+    pub fn register(
+        &mut self,
+        circuit: CircuitId,
+        vk: VerificationKey,
+        activation_height: u64,
+    ) -> Result<()> {
+        if self.entries.contains_key(&circuit) {
+            return Err(CryptoError::RegistryError(format!(
+                "{} v{} already registered",
+                circuit.name, circuit.version
+            )));
+        }
+        if let Some(existing) = self.by_vk.get(&vk.id()) {
+            return Err(CryptoError::RegistryError(format!(
+                "Key already registered for {} v{}",
+                existing.name, existing.version
+            )));
+        }
+
+        self.by_vk.insert(vk.id(), circuit.clone());
+        self.entries.insert(
+            circuit.clone(),
+            RegistryEntry {
+                circuit,
+                vk,
+                activation_height,
+                deprecation_height: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Schedule a version to stop being accepted at `height`
+    ///
+    /// A deprecation can be brought forward but never pushed back or removed.
+    pub fn schedule_deprecation(&mut self, circuit: &CircuitId, height: u64) -> Result<()> {
+        let entry = self.entry_mut(circuit)?;
+        if height <= entry.activation_height {
+            return Err(CryptoError::RegistryError(
+                "Deprecation must come after activation".into(),
+            ));
+        }
+        if entry
+            .deprecation_height
+            .is_some_and(|current| height > current)
+        {
+            return Err(CryptoError::RegistryError(
+                "Deprecation cannot be postponed".into(),
+            ));
+        }
+
+        entry.deprecation_height = Some(height);
+        Ok(())
+    }
+
+    /// Register the next version of a circuit and retire the older ones
+    ///
+    /// The new version activates at `activation_height`; older versions stay
+    /// accepted for `grace_blocks` more blocks. Fails if an older version
+    /// activates no earlier than that, since it could not be retired. Returns
+    /// the new circuit id.
+    pub fn schedule_upgrade(
+        &mut self,
+        name: &str,
+        vk: VerificationKey,
+        activation_height: u64,
+        grace_blocks: u64,
+    ) -> Result<CircuitId> {
+        let previous = self.versions(name);
+        let version = previous.last().map_or(INITIAL_VERSION, |v| v.version + 1);
+        let circuit = CircuitId::new(name, version);
+
+        let retire_at = activation_height.saturating_add(grace_blocks);
+        let retires = |entry: &RegistryEntry| {
+            entry.deprecation_height.is_some_and(|d| d <= retire_at)
+                || retire_at > entry.activation_height
+        };
+        if let Some(old) = previous.iter().find(|old| !retires(&self.entries[*old])) {
+            return Err(CryptoError::RegistryError(format!(
+                "{} v{} activates after the upgrade would retire it",
+                old.name, old.version
+            )));
+        }
+
+        self.register(circuit.clone(), vk, activation_height)?;
+
+        for old in previous {
+            let entry = self.entry_mut(&old)?;
+            if entry.deprecation_height.is_none_or(|d| d > retire_at) {
+                entry.deprecation_height = Some(retire_at);
+            }
+        }
+
+        Ok(circuit)
+    }
+
+    /// Get a registered version
+    pub fn get(&self, circuit: &CircuitId) -> Option<&RegistryEntry> {
+        self.entries.get(circuit)
+    }
+
+    /// Find the circuit version a verification key belongs to
+    pub fn circuit_for_vk(&self, vk_id: &[u8; 32]) -> Option<&CircuitId> {
+        self.by_vk.get(vk_id)
+    }
+
+    /// All versions of a circuit, in ascending order
+    pub fn versions(&self, name: &str) -> Vec<CircuitId> {
+        let mut versions: Vec<CircuitId> = self
+            .entries
+            .keys()
+            .filter(|c| c.name == name)
+            .cloned()
+            .collect();
+        versions.sort_by_key(|c| c.version);
+        versions
+    }
+
+    /// The newest version of a circuit accepted at a height
+    pub fn latest_active(&self, name: &str, height: u64) -> Option<&RegistryEntry> {
+        self.entries
+            .values()
+            .filter(|e| e.circuit.name == name && e.is_active(height))
+            .max_by_key(|e| e.circuit.version)
+    }
+
+    /// Resolve the verification key for a declared circuit version at a height
+    ///
+    /// Fails if the version is unknown, not yet active, or deprecated.
+    pub fn resolve(&self, circuit: &CircuitId, height: u64) -> Result<&VerificationKey> {
+        let entry = self.entries.get(circuit).ok_or_else(|| {
+            CryptoError::RegistryError(format!(
+                "Unknown circuit {} v{}",
+                circuit.name, circuit.version
+            ))
+        })?;
+
+        if height < entry.activation_height {
+            return Err(CryptoError::RegistryError(format!(
+                "{} v{} not active until height {}",
+                circuit.name, circuit.version, entry.activation_height
+            )));
+        }
+        if let Some(deprecated) = entry.deprecation_height.filter(|d| height >= *d) {
+            return Err(CryptoError::RegistryError(format!(
+                "{} v{} deprecated at height {}",
+                circuit.name, circuit.version, deprecated
+            )));
+        }
+
+        Ok(&entry.vk)
+    }
+
+    /// Iterate over all registered versions
+    pub fn entries(&self) -> impl Iterator<Item = &RegistryEntry> {
+        self.entries.values()
+    }
+
+    fn entry_mut(&mut self, circuit: &CircuitId) -> Result<&mut RegistryEntry> {
+        self.entries.get_mut(circuit).ok_or_else(|| {
+            CryptoError::RegistryError(format!(
+                "Unknown circuit {} v{}",
+                circuit.name, circuit.version
+            ))
+        })
+    }
+}
+
+impl From<VkRegistry> for Vec<RegistryEntry> {
+    fn from(registry: VkRegistry) -> Self {
+        let mut entries: Vec<RegistryEntry> = registry.entries.into_values().collect();
+        entries.sort_by(|a, b| {
+            (&a.circuit.name, a.circuit.version).cmp(&(&b.circuit.name, b.circuit.version))
+        });
+        entries
+    }
+}
+
+impl TryFrom<Vec<RegistryEntry>> for VkRegistry {
+    type Error = CryptoError;
+
+    /// Rebuild the registry under the checks `register` and
+    /// `schedule_deprecation` apply
+    fn try_from(entries: Vec<RegistryEntry>) -> Result<Self> {
+        let mut registry = Self::new();
+        for entry in entries {
+            entry.vk.checked_id()?;
+            registry.register(entry.circuit.clone(), entry.vk, entry.activation_height)?;
+            if let Some(height) = entry.deprecation_height {
+                registry.schedule_deprecation(&entry.circuit, height)?;
+            }
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::ProofSystem;
+
+    fn key(tag: u8) -> VerificationKey {
+        VerificationKey::new(ProofSystem::Halo2, vec![tag])
+    }
+
+    #[test]
+    fn test_register_and_resolve() {
+        let mut registry = VkRegistry::new();
+        let spend = CircuitId::new(SPEND_CIRCUIT, 1);
+        registry.register(spend.clone(), key(1), 10).unwrap();
+
+        assert!(registry.resolve(&spend, 9).is_err());
+        assert_eq!(registry.resolve(&spend, 10).unwrap().id(), key(1).id());
+        assert_eq!(registry.circuit_for_vk(&key(1).id()), Some(&spend));
+        assert!(registry
+            .resolve(&CircuitId::new(SPEND_CIRCUIT, 2), 10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_duplicates_rejected() {
+        let mut registry = VkRegistry::new();
+        registry
+            .register(CircuitId::new(SPEND_CIRCUIT, 1), key(1), 0)
+            .unwrap();
+
+        // Same version again
+        assert!(registry
+            .register(CircuitId::new(SPEND_CIRCUIT, 1), key(2), 0)
+            .is_err());
+        // Same key under another circuit
+        assert!(registry
+            .register(CircuitId::new(OUTPUT_CIRCUIT, 1), key(1), 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_upgrade_schedule() {
+        let mut registry = VkRegistry::new();
+        let v1 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(1), 0, 0)
+            .unwrap();
+        assert_eq!(v1.version, 1);
+
+        let v2 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(2), 100, 50)
+            .unwrap();
+        assert_eq!(v2.version, 2);
+
+        // Before activation only v1; during the overlap both; afterwards only v2
+        assert_eq!(
+            registry.latest_active(SPEND_CIRCUIT, 99).unwrap().circuit,
+            v1
+        );
+        assert!(registry.resolve(&v2, 99).is_err());
+        assert!(registry.resolve(&v1, 120).is_ok());
+        assert_eq!(
+            registry.latest_active(SPEND_CIRCUIT, 120).unwrap().circuit,
+            v2
+        );
+        assert!(registry.resolve(&v1, 150).is_err());
+        assert!(registry.resolve(&v2, 150).is_ok());
+    }
+
+    #[test]
+    fn test_upgrade_must_retire_older_versions() {
+        let mut registry = VkRegistry::new();
+        let v1 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(1), 500, 0)
+            .unwrap();
+
+        // v1 would activate after the upgrade retires it and stay live
+        assert!(registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(2), 100, 50)
+            .is_err());
+        assert!(registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(2), 400, 100)
+            .is_err());
+        assert_eq!(registry.versions(SPEND_CIRCUIT), vec![v1.clone()]);
+        assert_eq!(registry.get(&v1).unwrap().deprecation_height, None);
+
+        let v2 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(2), 400, 101)
+            .unwrap();
+        assert_eq!(registry.get(&v1).unwrap().deprecation_height, Some(501));
+        assert!(registry.resolve(&v1, 500).is_ok());
+        assert!(registry.resolve(&v1, 501).is_err());
+        assert!(registry.resolve(&v2, 501).is_ok());
+    }
+
+    #[test]
+    fn test_deprecation_rules() {
+        let mut registry = VkRegistry::new();
+        let v1 = CircuitId::new(OUTPUT_CIRCUIT, 1);
+        registry.register(v1.clone(), key(1), 10).unwrap();
+
+        assert!(registry.schedule_deprecation(&v1, 10).is_err());
+        registry.schedule_deprecation(&v1, 100).unwrap();
+        registry.schedule_deprecation(&v1, 50).unwrap();
+        assert!(registry.schedule_deprecation(&v1, 80).is_err());
+
+        assert!(registry.get(&v1).unwrap().is_active(49));
+        assert!(!registry.get(&v1).unwrap().is_active(50));
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut registry = VkRegistry::new();
+        let v1 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(1), 0, 0)
+            .unwrap();
+        let v2 = registry
+            .schedule_upgrade(SPEND_CIRCUIT, key(2), 100, 50)
+            .unwrap();
+        let output = CircuitId::new(OUTPUT_CIRCUIT, 1);
+        registry.register(output.clone(), key(3), 10).unwrap();
+
+        let json = serde_json::to_string(&registry).unwrap();
+        let decoded: VkRegistry = serde_json::from_str(&json).unwrap();
+        for circuit in [&v1, &v2, &output] {
+            let (entry, original) = (
+                decoded.get(circuit).unwrap(),
+                registry.get(circuit).unwrap(),
+            );
+            assert_eq!(entry.vk.id(), original.vk.id());
+            assert_eq!(entry.activation_height, original.activation_height);
+            assert_eq!(entry.deprecation_height, original.deprecation_height);
+        }
+        assert_eq!(decoded.circuit_for_vk(&key(2).id()), Some(&v2));
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+
+        // Decoding applies the registration checks
+        let decode = |entries: &[RegistryEntry]| {
+            serde_json::from_value::<VkRegistry>(serde_json::to_value(entries).unwrap())
+        };
+        let entries: Vec<RegistryEntry> = registry.into();
+        let mut duplicated = entries.clone();
+        duplicated.push(entries[0].clone());
+        assert!(decode(&duplicated).is_err());
+        let mut mislabelled = entries;
+        mislabelled[0].vk.key_hash = key(9).id();
+        assert!(decode(&mislabelled).is_err());
+    }
+}
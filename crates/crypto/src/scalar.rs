@@ -1,6 +1,5 @@
 //! Scalar wrapper with proper serialization
 
-use ark_ff::UniformRand;
 use pasta_curves::group::ff::PrimeField;
 use pasta_curves::pallas;
 use serde::{Deserialize, Serialize};
@@ -38,15 +37,14 @@ impl Scalar {
     /// Create from bytes (little-endian)
     pub fn from_bytes(bytes: &[u8; 32]) -> Result<Self> {
         let repr: [u8; 32] = *bytes;
-        Option::from(pallas::Scalar::from_repr(repr.into()))
+        Option::from(pallas::Scalar::from_repr(repr))
             .ok_or(CryptoError::InvalidKey)
             .map(Self)
     }
 
     /// Convert to bytes (little-endian)
     pub fn to_bytes(&self) -> [u8; 32] {
-        let repr = self.0.to_repr();
-        repr.into()
+        self.0.to_repr()
     }
 
     /// Get the inner pallas::Scalar
@@ -58,6 +56,15 @@ impl Scalar {
     pub fn from_inner(scalar: pallas::Scalar) -> Self {
         Self(scalar)
     }
+
+    /// Reinterpret a base field element as a scalar
+    ///
+    /// Always succeeds since the Pallas base modulus is below the scalar
+    /// modulus; this is the scalar circuits multiply by in base-field
+    /// fixed-base multiplication.
+    pub fn from_base(base: pallas::Base) -> Self {
+        Self(pallas::Scalar::from_repr(base.to_repr()).expect("p < q"))
+    }
 }
 
 // Serialization
@@ -25,10 +25,7 @@ pub mod pallet {
 
     /// The pallet's configuration trait
     #[pallet::config]
-    pub trait Config: frame_system::Config {
-        /// The overarching event type
-        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
-
+    pub trait Config: frame_system::Config<RuntimeEvent: From<Event<Self>>> {
         /// Asset ID type (for now we'll use u32, can be generic later)
         type AssetId: Parameter + Member + Copy + Default + MaxEncodedLen;
 
@@ -153,7 +150,7 @@ pub mod pallet {
         /// * `token0` - First token ID
         /// * `token1` - Second token ID
         #[pallet::call_index(0)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(Weight::from_parts(10_000, 0))]
         pub fn create_pool(
             origin: OriginFor<T>,
             token0: T::AssetId,
@@ -201,7 +198,7 @@ pub mod pallet {
         /// * `amount0_min` - Minimum amount of token0 (slippage protection)
         /// * `amount1_min` - Minimum amount of token1 (slippage protection)
         #[pallet::call_index(1)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(Weight::from_parts(10_000, 0))]
         pub fn add_liquidity(
             origin: OriginFor<T>,
             pool_id: u32,
@@ -297,7 +294,7 @@ pub mod pallet {
         /// * `amount0_min` - Minimum amount of token0 to receive
         /// * `amount1_min` - Minimum amount of token1 to receive
         #[pallet::call_index(2)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(Weight::from_parts(10_000, 0))]
         pub fn remove_liquidity(
             origin: OriginFor<T>,
             pool_id: u32,
@@ -370,7 +367,7 @@ pub mod pallet {
         ///
         /// This implements the constant product formula from PuddelPair.sol
         #[pallet::call_index(3)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(Weight::from_parts(10_000, 0))]
         pub fn swap(
             origin: OriginFor<T>,
             pool_id: u32,
@@ -460,7 +457,7 @@ pub mod pallet {
         /// uint numerator = amountInWithFee.mul(reserveOut);
         /// uint denominator = reserveIn.mul(10000).add(amountInWithFee);
         /// amountOut = numerator / denominator;
-        fn get_amount_out(
+        pub fn get_amount_out(
             amount_in: T::Balance,
             reserve_in: T::Balance,
             reserve_out: T::Balance,
@@ -0,0 +1,72 @@
+//! Reading structured input
+
+/// Cursor over an input buffer reading little-endian values
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Read from the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Next `len` bytes, or `None` if fewer remain
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    /// Next `N` bytes as an array
+    pub fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.read_bytes(N)
+            .map(|bytes| bytes.try_into().expect("length checked"))
+    }
+
+    /// Next little-endian `u32`
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    /// Next little-endian `u64`
+    pub fn read_u64(&mut self) -> Option<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_in_order() {
+        let data = [1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 7, 8, 9];
+        let mut reader = Reader::new(&data);
+
+        assert_eq!(reader.read_u32(), Some(1));
+        assert_eq!(reader.read_u64(), Some(2));
+        assert_eq!(reader.read_array::<2>(), Some([7, 8]));
+        assert_eq!(reader.remaining(), &[9]);
+    }
+
+    #[test]
+    fn test_short_input() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+
+        assert_eq!(reader.read_u32(), None);
+        // A failed read consumes nothing
+        assert_eq!(reader.read_bytes(3), Some(&[1, 2, 3][..]));
+        assert_eq!(reader.read_bytes(0), Some(&[][..]));
+        assert_eq!(reader.read_bytes(1), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! PRIVL1 zkVM guest runtime
+//!
+//! Link this crate into a `no_std` program built for
+//! `riscv32im-unknown-none-elf` to run it in the PRIVL1 zkVM:
+//!
+//! ```ignore
+//! #![no_std]
+//! #![no_main]
+//!
+//! use privl1_zkvm_guest::{commit, private_input, Reader};
+//!
+//! privl1_zkvm_guest::entry!(main);
+//!
+//! fn main() {
+//!     let balance = Reader::new(private_input()).read_u64().unwrap();
+//!     commit(&[(balance >= 100) as u8]);
+//! }
+//! ```
+//!
+//! The crate provides `_start`, which calls the `entry!` function and exits
+//! with code 0, and a panic handler exiting with [`PANIC_EXIT_CODE`]. The
+//! host sets the stack pointer to [`STACK_TOP`] before the first instruction.
+//!
+//! Inputs and the public output live in fixed memory regions of
+//! [`IO_REGION_SIZE`] bytes, each a little-endian `u32` length followed by
+//! that many bytes. The constants below are the memory map the host loader
+//! uses.
+//!
+//! [`precompile`] wraps the system calls that run `privl1-crypto` operations
+//! on the host, streaming their inputs and outputs a word at a time.
+
+#![no_std]
+
+mod io;
+pub mod precompile;
+
+pub use io::Reader;
+
+/// Initial stack pointer; the stack grows down from here
+pub const STACK_TOP: u32 = 0x0800_0000;
+
+/// Public input region, readable by the guest and part of the proven statement
+pub const PUBLIC_INPUT_START: u32 = 0x0800_0000;
+
+/// Private input region, readable by the guest only
+pub const PRIVATE_INPUT_START: u32 = 0x0900_0000;
+
+/// Public output region, written by [`commit`]
+pub const PUBLIC_OUTPUT_START: u32 = 0x0a00_0000;
+
+/// Size of each I/O region, including its length word
+pub const IO_REGION_SIZE: u32 = 0x0100_0000;
+
+/// End of the I/O regions; program segments must lie below [`STACK_TOP`]
+pub const IO_END: u32 = PUBLIC_OUTPUT_START + IO_REGION_SIZE;
+
+/// System call number of `exit` (Linux's, as used by riscv-tests)
+pub const SYS_EXIT: u32 = 93;
+
+/// Exit code of a guest that panicked
+pub const PANIC_EXIT_CODE: u32 = 101;
+
+/// Define the guest's entry point, a `fn()` called from `_start`
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[no_mangle]
+        extern "C" fn __privl1_main() {
+            let main: fn() = $main;
+            main()
+        }
+    };
+}
+
+/// The public input
+#[cfg(target_arch = "riscv32")]
+pub fn public_input() -> &'static [u8] {
+    // Safety: the host writes the region before the first instruction
+    unsafe { region(PUBLIC_INPUT_START) }
+}
+
+/// The private input
+#[cfg(target_arch = "riscv32")]
+pub fn private_input() -> &'static [u8] {
+    // Safety: as for `public_input`
+    unsafe { region(PRIVATE_INPUT_START) }
+}
+
+/// Append `bytes` to the public output
+///
+/// Panics if the output would overflow its region.
+#[cfg(target_arch = "riscv32")]
+pub fn commit(bytes: &[u8]) {
+    let header = PUBLIC_OUTPUT_START as *mut u32;
+    // Safety: the region is reserved for the output and only written here
+    unsafe {
+        let len = header.read_volatile();
+        assert!(
+            bytes.len() as u32 <= IO_REGION_SIZE - 4 - len,
+            "public output overflows its region"
+        );
+        let end = (PUBLIC_OUTPUT_START + 4 + len) as *mut u8;
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), end, bytes.len());
+        header.write_volatile(len + bytes.len() as u32);
+    }
+}
+
+/// Stop the guest with `exit_code`
+#[cfg(target_arch = "riscv32")]
+pub fn halt(exit_code: u32) -> ! {
+    // Safety: the exit system call never returns
+    unsafe {
+        core::arch::asm!("ecall", in("a0") exit_code, in("a7") SYS_EXIT, options(noreturn));
+    }
+}
+
+#[cfg(target_arch = "riscv32")]
+unsafe fn region(start: u32) -> &'static [u8] {
+    let len = (start as *const u32).read_volatile();
+    core::slice::from_raw_parts((start + 4) as *const u8, len as usize)
+}
+
+#[cfg(all(target_arch = "riscv32", target_os = "none"))]
+core::arch::global_asm!(
+    ".section .text._start, \"ax\"",
+    ".globl _start",
+    "_start:",
+    "    call __privl1_main",
+    "    li a0, 0",
+    "    li a7, {exit}",
+    "    ecall",
+    exit = const SYS_EXIT,
+);
+
+#[cfg(all(target_arch = "riscv32", target_os = "none"))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    halt(PANIC_EXIT_CODE)
+}
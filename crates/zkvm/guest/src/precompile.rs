@@ -0,0 +1,244 @@
+//! Cryptographic precompiles
+//!
+//! System calls the host executes natively with `privl1-crypto`, so hashing
+//! and curve arithmetic cost a few cycles per input word instead of
+//! millions. A call is streamed one word at a time: every `ecall` has the
+//! call number in `a7` and one word in `a0`. The first is a header with the
+//! call's parameter (0 for calls without one), then come the input words,
+//! each answered with 0, then one `ecall` per output word, answered with
+//! that word. A call must be finished before another starts or the guest
+//! exits.
+//!
+//! Field elements, scalars and points are their canonical 32-byte encodings,
+//! sent as eight little-endian words. A call with malformed input (a
+//! non-canonical field element, an invalid point, too many hash inputs)
+//! faults the guest.
+//!
+//! The words only pass through registers, and the prover's AIR checks what
+//! each call computes from them, so inputs stay as private as the rest of
+//! the run.
+
+/// Parameter: input count; inputs 1 to [`MAX_POSEIDON_INPUTS`] field
+/// elements; output the field element `Poseidon(inputs)`
+pub const SYS_POSEIDON: u32 = 0x100;
+
+/// Inputs the value as two words, low first, and the blinding scalar; output
+/// the Pedersen commitment `[value]G + [blinding]H`
+pub const SYS_PEDERSEN_COMMIT: u32 = 0x101;
+
+/// Inputs leaf, root, path of [`MERKLE_DEPTH`] siblings and the position as
+/// two words, low first; output 1 if the leaf is at that position in the note
+/// commitment tree with that root, else 0
+pub const SYS_MERKLE_VERIFY: u32 = 0x102;
+
+/// Inputs a note's value as two words, low first, [`asset_hash`] of its
+/// asset id, owner key and commitment randomness, then the nullifier deriving
+/// key and the position as two words, low first; output the note commitment,
+/// then its nullifier at that position
+pub const SYS_NOTE_NULLIFIER: u32 = 0x103;
+
+/// Inputs validating key, signature ([`SIGNATURE_LEN`] bytes) and the
+/// [`CHALLENGE_LEN`]-byte [`signature_challenge`]; output 1 if the signature
+/// is valid for that challenge, else 0
+pub const SYS_SIGNATURE_VERIFY: u32 = 0x104;
+
+/// Most field elements one Poseidon call hashes
+pub const MAX_POSEIDON_INPUTS: usize = 5;
+
+/// Depth of the note commitment tree
+pub const MERKLE_DEPTH: usize = 32;
+
+/// Length of an encoded Schnorr signature `(R, s)`
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Length of a signature challenge, reduced modulo the scalar field order
+pub const CHALLENGE_LEN: usize = 64;
+
+/// Domain of the signature challenge hash
+pub const SIGNATURE_CHALLENGE_DOMAIN: &str = "PRIVL1_SIGNATURE_CHALLENGE";
+
+/// Domain of the hash of an asset id into its asset base
+pub const ASSET_BASE_DOMAIN: &str = "PRIVL1_ASSET_BASE";
+
+/// The fields of a note, as passed to [`SYS_NOTE_NULLIFIER`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoteRecord {
+    pub value: u64,
+    pub asset_id: [u8; 32],
+    /// Owner public key
+    pub owner: [u8; 32],
+    /// Commitment randomness scalar
+    pub randomness: [u8; 32],
+}
+
+/// Schnorr challenge of the nonce commitment `r` and validating key `key` on
+/// `message`: 64 bytes of BLAKE3 output, as `privl1-crypto` signs it
+pub fn signature_challenge(r: &[u8; 32], key: &[u8; 32], message: &[u8]) -> [u8; CHALLENGE_LEN] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(SIGNATURE_CHALLENGE_DOMAIN.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(r);
+    hasher.update(key);
+    hasher.update(message);
+    let mut challenge = [0; CHALLENGE_LEN];
+    hasher.finalize_xof().fill(&mut challenge);
+    challenge
+}
+
+/// BLAKE3 hash of `asset_id`, which reduced modulo `p` is the asset base a
+/// note commitment hashes, as in `privl1-crypto`
+pub fn asset_hash(asset_id: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(ASSET_BASE_DOMAIN.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(asset_id);
+    *hasher.finalize().as_bytes()
+}
+
+/// Poseidon hash of up to [`MAX_POSEIDON_INPUTS`] field elements
+#[cfg(target_arch = "riscv32")]
+pub fn poseidon(inputs: &[[u8; 32]]) -> [u8; 32] {
+    let call = Call::start(SYS_POSEIDON, inputs.len() as u32);
+    for input in inputs {
+        call.send(input);
+    }
+    let mut output = [0; 32];
+    call.receive(&mut output);
+    output
+}
+
+/// Pedersen commitment to `value` with the given blinding scalar
+#[cfg(target_arch = "riscv32")]
+pub fn pedersen_commit(value: u64, blinding: &[u8; 32]) -> [u8; 32] {
+    let call = Call::start(SYS_PEDERSEN_COMMIT, 0);
+    call.word(value as u32);
+    call.word((value >> 32) as u32);
+    call.send(blinding);
+    let mut commitment = [0; 32];
+    call.receive(&mut commitment);
+    commitment
+}
+
+/// The commitment of `note` and its nullifier at `position`
+#[cfg(target_arch = "riscv32")]
+pub fn note_nullifier(
+    note: &NoteRecord,
+    nullifier_key: &[u8; 32],
+    position: u64,
+) -> ([u8; 32], [u8; 32]) {
+    let call = Call::start(SYS_NOTE_NULLIFIER, 0);
+    call.word(note.value as u32);
+    call.word((note.value >> 32) as u32);
+    call.send(&asset_hash(&note.asset_id));
+    call.send(&note.owner);
+    call.send(&note.randomness);
+    call.send(nullifier_key);
+    call.word(position as u32);
+    call.word((position >> 32) as u32);
+    let (mut commitment, mut nullifier) = ([0; 32], [0; 32]);
+    call.receive(&mut commitment);
+    call.receive(&mut nullifier);
+    (commitment, nullifier)
+}
+
+/// Whether `leaf` is at `position` in the note commitment tree with `root`
+#[cfg(target_arch = "riscv32")]
+pub fn verify_merkle_path(
+    leaf: &[u8; 32],
+    root: &[u8; 32],
+    path: &[[u8; 32]; MERKLE_DEPTH],
+    position: u64,
+) -> bool {
+    let call = Call::start(SYS_MERKLE_VERIFY, 0);
+    call.send(leaf);
+    call.send(root);
+    for sibling in path {
+        call.send(sibling);
+    }
+    call.word(position as u32);
+    call.word((position >> 32) as u32);
+    call.result()
+}
+
+/// Whether `signature` signs `message` under the validating key `key`
+#[cfg(target_arch = "riscv32")]
+pub fn verify_signature(key: &[u8; 32], signature: &[u8; SIGNATURE_LEN], message: &[u8]) -> bool {
+    let (r, s) = signature.split_at(32);
+    let challenge = signature_challenge(r.try_into().expect("32-byte half"), key, message);
+    let call = Call::start(SYS_SIGNATURE_VERIFY, 0);
+    call.send(key);
+    call.send(r);
+    call.send(s);
+    call.send(&challenge);
+    call.result()
+}
+
+/// A call being streamed to the host
+#[cfg(target_arch = "riscv32")]
+struct Call {
+    number: u32,
+}
+
+#[cfg(target_arch = "riscv32")]
+impl Call {
+    fn start(number: u32, param: u32) -> Self {
+        let call = Self { number };
+        call.word(param);
+        call
+    }
+
+    /// Send one word; inputs are answered with 0
+    fn word(&self, word: u32) -> u32 {
+        let result;
+        // Safety: the host only reads the registers, and writes `a0`
+        unsafe {
+            core::arch::asm!(
+                "ecall",
+                inlateout("a0") word => result,
+                in("a7") self.number,
+                options(nostack),
+            );
+        }
+        result
+    }
+
+    /// Send `bytes`, a whole number of words
+    fn send(&self, bytes: &[u8]) {
+        for word in bytes.as_chunks().0 {
+            self.word(u32::from_le_bytes(*word));
+        }
+    }
+
+    /// Receive `output`, a whole number of words, storing each word's bytes
+    /// in place rather than through a `memcpy` call per word
+    fn receive(&self, output: &mut [u8]) {
+        for word in output.as_chunks_mut().0 {
+            *word = self.word(0).to_le_bytes();
+        }
+    }
+
+    /// Receive a 0 or 1 result word
+    fn result(&self) -> bool {
+        self.word(0) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_numbers_distinct() {
+        let numbers = [
+            crate::SYS_EXIT,
+            SYS_POSEIDON,
+            SYS_PEDERSEN_COMMIT,
+            SYS_MERKLE_VERIFY,
+            SYS_NOTE_NULLIFIER,
+            SYS_SIGNATURE_VERIFY,
+        ];
+        for (i, a) in numbers.iter().enumerate() {
+            assert!(numbers[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
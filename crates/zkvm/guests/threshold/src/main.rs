@@ -0,0 +1,19 @@
+//! Balance threshold check
+//!
+//! Reads a `u64` threshold from the public input and a `u64` balance from
+//! the private input, and commits the threshold followed by one byte, 1 if
+//! the balance reaches it. Malformed inputs panic.
+
+#![no_std]
+#![no_main]
+
+use privl1_zkvm_guest::{commit, private_input, public_input, Reader};
+
+privl1_zkvm_guest::entry!(main);
+
+fn main() {
+    let threshold = Reader::new(public_input()).read_u64().unwrap();
+    let balance = Reader::new(private_input()).read_u64().unwrap();
+    commit(&threshold.to_le_bytes());
+    commit(&[(balance >= threshold) as u8]);
+}
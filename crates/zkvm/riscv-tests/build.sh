@@ -0,0 +1,63 @@
+#!/bin/sh
+# Build the riscv-tests rv32ui and rv32um sources for the zkVM
+#
+# Usage: build.sh [riscv-tests checkout]
+#
+# The suite is pinned to the commit in COMMIT; RISCV_TESTS_REV overrides it
+# when bumping the pin. Without a checkout, clones that commit into src/. A
+# checkout given as an argument must be at it too. Each test is preprocessed
+# with env/riscv_test.h in place of the suite's environment, assembled for
+# rv32im and linked with link.ld into bin/<suite>-p-<test>. bin/REVISION
+# records the suite commit. Run the binaries with
+# `cargo test -p privl1-zkvm -- --ignored test_upstream_binaries`.
+#
+# The tools default to a host C preprocessor, LLVM's assembler and lld:
+#   CPP  (default: cpp)
+#   AS   (default: llvm-mc -triple=riscv32 -mattr=+m -filetype=obj)
+#   LD   (default: ld.lld; `rust-lld -flavor gnu` also works)
+set -eu
+
+here=$(cd "$(dirname "$0")" && pwd)
+src=${1:-$here/src}
+CPP=${CPP:-cpp}
+AS=${AS:-llvm-mc -triple=riscv32 -mattr=+m -filetype=obj}
+LD=${LD:-ld.lld}
+
+pinned=$(sed -e 's/#.*//' "$here/COMMIT" | tr -d '[:space:]')
+rev=${RISCV_TESTS_REV:-$pinned}
+if ! printf '%s\n' "$rev" | grep -Eqx '[0-9a-f]{40}'; then
+    echo "riscv-tests/COMMIT must hold a full commit hash, not '$rev'" >&2
+    exit 1
+fi
+
+if [ ! -d "$src/isa" ]; then
+    git clone https://github.com/riscv-software-src/riscv-tests "$src"
+    git -C "$src" checkout "$rev"
+fi
+if [ "$(git -C "$src" rev-parse HEAD)" != "$rev" ]; then
+    echo "$src is not at riscv-tests commit $rev" >&2
+    exit 1
+fi
+
+# fence_i needs Zifencei and ma_data misaligned accesses, which the zkVM
+# does not implement
+skip="fence_i ma_data"
+
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+rm -rf "$here/bin"
+mkdir -p "$here/bin"
+
+for suite in rv32ui rv32um; do
+    for test in "$src/isa/$suite"/*.S; do
+        name=$(basename "$test" .S)
+        case " $skip " in *" $name "*) continue ;; esac
+
+        $CPP -x assembler-with-cpp -P -D__riscv_xlen=32 \
+            -I"$here/env" -I"$src/isa/macros/scalar" "$test" -o "$tmp/$name.s"
+        $AS "$tmp/$name.s" -o "$tmp/$name.o"
+        $LD -T "$here/link.ld" "$tmp/$name.o" -o "$here/bin/$suite-p-$name"
+    done
+done
+
+git -C "$src" rev-parse HEAD > "$here/bin/REVISION"
@@ -0,0 +1,50 @@
+// Test environment for running the riscv-tests `rv32ui`/`rv32um` sources on
+// the zkVM, in place of the suite's `env/p/riscv_test.h`
+//
+// The zkVM has no CSRs, traps or `tohost`, so the environment only sets up
+// `_start` and exits through the `exit` system call: with code 0 on success,
+// or `(TESTNUM << 1) | 1` naming the failing case.
+
+#ifndef _ENV_ZKVM_H
+#define _ENV_ZKVM_H
+
+#define RVTEST_RV64U .macro init; .endm
+#define RVTEST_RV32U .macro init; .endm
+
+#define TESTNUM gp
+
+#define RVTEST_CODE_BEGIN                                               \
+        .section .text.init;                                            \
+        .align 2;                                                       \
+        .globl _start;                                                  \
+_start:                                                                 \
+        li TESTNUM, 0;                                                  \
+        init;
+
+#define RVTEST_CODE_END                                                 \
+        unimp
+
+#define RVTEST_PASS                                                     \
+        fence;                                                          \
+        li TESTNUM, 1;                                                  \
+        li a7, 93;                                                      \
+        li a0, 0;                                                       \
+        ecall
+
+#define RVTEST_FAIL                                                     \
+        fence;                                                          \
+        sll TESTNUM, TESTNUM, 1;                                        \
+        or TESTNUM, TESTNUM, 1;                                         \
+        li a7, 93;                                                      \
+        addi a0, TESTNUM, 0;                                            \
+        ecall
+
+#define EXTRA_DATA
+
+#define RVTEST_DATA_BEGIN                                               \
+        EXTRA_DATA                                                      \
+        .align 4; .global begin_signature; begin_signature:
+
+#define RVTEST_DATA_END .align 4; .global end_signature; end_signature:
+
+#endif
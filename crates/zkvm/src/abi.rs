@@ -0,0 +1,395 @@
+//! Guest program ABI
+//!
+//! The host side of the interface implemented by `privl1-zkvm-guest`.
+//! [`load`] maps a program's segments, points `sp` at [`STACK_TOP`] and
+//! writes the public and private inputs to their regions, each as a `u32`
+//! length followed by the bytes. After the guest exits, [`public_output`]
+//! reads back what it committed.
+//!
+//! Program segments must lie below [`STACK_TOP`], so that the I/O regions
+//! can only be changed by the guest at run time.
+//!
+//! [`execute`] runs under [`ResourceLimits`]: the loaded image counts towards
+//! the page limit, and the public output must fit `max_output`.
+
+use crate::elf::Program;
+use crate::gas::ResourceLimits;
+use crate::memory::{Memory, Width};
+use crate::trace::ExecutionTrace;
+use crate::vm::Vm;
+use crate::{Result, ZkvmError};
+
+pub use privl1_zkvm_guest::{
+    IO_END, IO_REGION_SIZE, PANIC_EXIT_CODE, PRIVATE_INPUT_START, PUBLIC_INPUT_START,
+    PUBLIC_OUTPUT_START, STACK_TOP,
+};
+
+/// Stack pointer register (`x2`)
+pub const REG_SP: u8 = 2;
+
+/// Largest input or output, after the region's length word
+pub const MAX_IO_LEN: usize = IO_REGION_SIZE as usize - 4;
+
+/// Result of running a guest to completion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GuestRun {
+    pub trace: ExecutionTrace,
+    pub public_output: Vec<u8>,
+}
+
+/// Prepare a VM to run `program` on the given inputs
+pub fn load(program: &Program, private_input: &[u8], public_input: &[u8]) -> Result<Vm> {
+    if let Some(segment) = program
+        .segments
+        .iter()
+        .find(|segment| segment.end() > STACK_TOP as u64)
+    {
+        return Err(ZkvmError::InvalidElf(format!(
+            "segment at {:#010x} extends past the stack top {:#010x}",
+            segment.address, STACK_TOP
+        )));
+    }
+
+    let mut memory = Memory::new();
+    program.load_into(&mut memory)?;
+    write_region(&mut memory, PUBLIC_INPUT_START, public_input)?;
+    write_region(&mut memory, PRIVATE_INPUT_START, private_input)?;
+
+    let mut vm = Vm::new(memory, program.entry);
+    vm.set_register(REG_SP, STACK_TOP);
+    Ok(vm)
+}
+
+/// The bytes committed to the public output region of `memory`
+pub fn public_output(memory: &Memory) -> Result<Vec<u8>> {
+    let len = memory.load(PUBLIC_OUTPUT_START, Width::Word)? as usize;
+    if len > MAX_IO_LEN {
+        return Err(ZkvmError::IoTooLarge {
+            len,
+            max: MAX_IO_LEN,
+        });
+    }
+    memory.read_bytes(PUBLIC_OUTPUT_START + 4, len)
+}
+
+/// Run `program` on the given inputs until it exits, within `limits`
+pub fn execute(
+    program: &Program,
+    private_input: &[u8],
+    public_input: &[u8],
+    limits: ResourceLimits,
+) -> Result<GuestRun> {
+    let vm = load(program, private_input, public_input)?;
+    if vm.memory().page_count() > limits.max_pages {
+        return Err(ZkvmError::MemoryLimit {
+            pages: vm.memory().page_count(),
+            max: limits.max_pages,
+        });
+    }
+
+    let max_cycles = limits.max_cycles;
+    let max_output = limits.max_output;
+    let mut vm = vm.with_limits(limits);
+    let trace = vm.run(max_cycles)?;
+    let public_output = public_output(vm.memory())?;
+    if public_output.len() > max_output {
+        return Err(ZkvmError::IoTooLarge {
+            len: public_output.len(),
+            max: max_output,
+        });
+    }
+
+    Ok(GuestRun {
+        trace,
+        public_output,
+    })
+}
+
+fn write_region(memory: &mut Memory, start: u32, bytes: &[u8]) -> Result<()> {
+    if bytes.len() > MAX_IO_LEN {
+        return Err(ZkvmError::IoTooLarge {
+            len: bytes.len(),
+            max: MAX_IO_LEN,
+        });
+    }
+    memory.store(start, Width::Word, bytes.len() as u32)?;
+    memory.write_bytes(start + 4, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::Segment;
+    use crate::instruction::{AluOp, BranchCondition, Instruction, LoadOp};
+    use crate::vm::{REG_A0, REG_A7, SYS_EXIT};
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const ENTRY: u32 = 0x1_0000;
+
+    fn addi(rd: u8, rs1: u8, imm: i32) -> Instruction {
+        Instruction::AluImm {
+            op: AluOp::Add,
+            rd,
+            rs1,
+            imm,
+        }
+    }
+
+    /// Sum the private input bytes, commit the sum as a word and exit with
+    /// the public input length
+    fn program() -> Program {
+        let (t0, t1, t2, t3) = (5, 6, 7, 28);
+        let code = [
+            Instruction::Lui {
+                rd: t0,
+                imm: PRIVATE_INPUT_START,
+            },
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: t1,
+                rs1: t0,
+                offset: 0,
+            },
+            addi(t0, t0, 4),
+            addi(REG_A0, 0, 0),
+            // loop: while t1 != 0
+            Instruction::Branch {
+                cond: BranchCondition::Eq,
+                rs1: t1,
+                rs2: 0,
+                offset: 24,
+            },
+            Instruction::Load {
+                op: LoadOp::Lbu,
+                rd: t2,
+                rs1: t0,
+                offset: 0,
+            },
+            Instruction::Alu {
+                op: AluOp::Add,
+                rd: REG_A0,
+                rs1: REG_A0,
+                rs2: t2,
+            },
+            addi(t0, t0, 1),
+            addi(t1, t1, -1),
+            Instruction::Jal { rd: 0, offset: -20 },
+            // Commit the sum: length 4, then the word
+            Instruction::Lui {
+                rd: t3,
+                imm: PUBLIC_OUTPUT_START,
+            },
+            Instruction::Store {
+                width: Width::Word,
+                rs1: t3,
+                rs2: REG_A0,
+                offset: 4,
+            },
+            addi(t2, 0, 4),
+            Instruction::Store {
+                width: Width::Word,
+                rs1: t3,
+                rs2: t2,
+                offset: 0,
+            },
+            Instruction::Lui {
+                rd: t0,
+                imm: PUBLIC_INPUT_START,
+            },
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: REG_A0,
+                rs1: t0,
+                offset: 0,
+            },
+            addi(REG_A7, 0, SYS_EXIT as i32),
+            Instruction::Ecall,
+        ];
+
+        Program {
+            entry: ENTRY,
+            segments: vec![Segment {
+                address: ENTRY,
+                data: code
+                    .iter()
+                    .flat_map(|i| i.encode().unwrap().to_le_bytes())
+                    .collect(),
+                size: 4 * code.len() as u32,
+                executable: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_load_sets_up_guest() {
+        let vm = load(&program(), &[1, 2, 3], &[9; 5]).unwrap();
+
+        assert_eq!(vm.pc(), ENTRY);
+        assert_eq!(vm.register(REG_SP), STACK_TOP);
+        assert_eq!(
+            vm.memory().load(PUBLIC_INPUT_START, Width::Word).unwrap(),
+            5
+        );
+        assert_eq!(
+            vm.memory().read_bytes(PUBLIC_INPUT_START + 4, 6).unwrap(),
+            vec![9, 9, 9, 9, 9, 0]
+        );
+        assert_eq!(
+            vm.memory().load(PRIVATE_INPUT_START, Width::Word).unwrap(),
+            3
+        );
+        assert_eq!(public_output(vm.memory()).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_execute_guest() {
+        let run = execute(
+            &program(),
+            &[1, 2, 3, 250],
+            &[9; 7],
+            ResourceLimits::default(),
+        )
+        .unwrap();
+
+        assert_eq!(run.trace.exit_code, 7);
+        assert_eq!(run.public_output, 256u32.to_le_bytes());
+        assert!(run.trace.gas_used > run.trace.len() as u64);
+
+        let empty = execute(&program(), &[], &[], ResourceLimits::default()).unwrap();
+        assert_eq!(empty.trace.exit_code, 0);
+        assert_eq!(empty.public_output, 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_execute_compiled_guest() {
+        // Built from `guests/threshold`; see the README to rebuild it
+        let program =
+            Program::from_elf(include_bytes!("../guests/threshold/threshold.elf")).unwrap();
+        let run = |balance: u64| {
+            execute(
+                &program,
+                &balance.to_le_bytes(),
+                &100u64.to_le_bytes(),
+                ResourceLimits::default(),
+            )
+            .unwrap()
+        };
+
+        for (balance, reached) in [(150, 1), (100, 1), (99, 0)] {
+            let run = run(balance);
+            assert_eq!(run.trace.exit_code, 0);
+            assert_eq!(run.public_output[..8], 100u64.to_le_bytes());
+            assert_eq!(run.public_output[8..], [reached]);
+        }
+
+        // A missing balance panics in the guest
+        let run = execute(&program, &[], &[0; 8], ResourceLimits::default()).unwrap();
+        assert_eq!(run.trace.exit_code, PANIC_EXIT_CODE);
+    }
+
+    #[test]
+    #[ignore = "rebuilds the guest; needs the riscv32im-unknown-none-elf target"]
+    fn test_checked_in_guest_is_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("guests/threshold");
+        let checked_in =
+            Program::from_elf(include_bytes!("../guests/threshold/threshold.elf")).unwrap();
+        let built = Program::from_elf(&build_guest(&dir, "privl1-threshold-guest")).unwrap();
+        assert_eq!(
+            built.hash(),
+            checked_in.hash(),
+            "threshold.elf is stale; rebuild it (see the README)"
+        );
+    }
+
+    /// Rebuild the guest in `dir` as the README does and read its ELF
+    fn build_guest(dir: &Path, package: &str) -> Vec<u8> {
+        let target = "riscv32im-unknown-none-elf";
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .canonicalize()
+            .unwrap();
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(std::env::var_os("HOME").unwrap()).join(".cargo"));
+
+        // The guest's rust-toolchain.toml picks the compiler, and remapping
+        // the source paths keeps the build independent of the checkout
+        let status = Command::new("cargo")
+            .args(["build", "--release", "--target", target])
+            .current_dir(dir)
+            .env_remove("RUSTUP_TOOLCHAIN")
+            .env_remove("CARGO_TARGET_DIR")
+            .env_remove("CARGO_ENCODED_RUSTFLAGS")
+            .env(
+                "RUSTFLAGS",
+                format!(
+                    "--remap-path-prefix={}=/privl1 --remap-path-prefix={}=/cargo",
+                    root.display(),
+                    cargo_home.display()
+                ),
+            )
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let elf = dir.join(format!("target/{}/release/{}", target, package));
+        std::fs::read(elf).unwrap()
+    }
+
+    #[test]
+    fn test_segments_must_stay_below_stack() {
+        let mut program = program();
+        program.segments.push(Segment {
+            address: STACK_TOP - 4,
+            data: vec![],
+            size: 8,
+            executable: false,
+        });
+        assert!(matches!(
+            load(&program, &[], &[]),
+            Err(ZkvmError::InvalidElf(_))
+        ));
+    }
+
+    #[test]
+    fn test_io_size_limits() {
+        let too_large = vec![0u8; MAX_IO_LEN + 1];
+        assert!(matches!(
+            load(&program(), &too_large, &[]),
+            Err(ZkvmError::IoTooLarge { .. })
+        ));
+
+        let mut memory = Memory::new();
+        memory
+            .store(PUBLIC_OUTPUT_START, Width::Word, u32::MAX)
+            .unwrap();
+        assert!(public_output(&memory).is_err());
+    }
+
+    #[test]
+    fn test_execute_enforces_limits() {
+        let limits = |max_pages, max_output| ResourceLimits {
+            max_pages,
+            max_output,
+            ..ResourceLimits::default()
+        };
+
+        // Loading maps the program and both input regions; committing
+        // allocates the output page
+        assert!(execute(&program(), &[1], &[], limits(4, 64))
+            .is_ok_and(|run| run.public_output.len() == 4));
+        assert!(matches!(
+            execute(&program(), &[1], &[], limits(3, 64)),
+            Err(ZkvmError::MemoryLimit { pages: 4, max: 3 })
+        ));
+        assert!(matches!(
+            execute(&program(), &[1], &[], limits(2, 64)),
+            Err(ZkvmError::MemoryLimit { pages: 3, max: 2 })
+        ));
+        assert!(matches!(
+            execute(&program(), &[1], &[], limits(4, 3)),
+            Err(ZkvmError::IoTooLarge { len: 4, max: 3 })
+        ));
+    }
+}
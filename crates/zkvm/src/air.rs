@@ -0,0 +1,1447 @@
+//! Algebraic intermediate representation of zkVM execution
+//!
+//! The main trace has one row per cycle, then padding rows after the exit
+//! call. A cycle row holds the instruction word and its bits, a one-hot
+//! selector per [`Op`], the operands and their bit decompositions, and the
+//! helper values (carries, inverses, old values and timestamps of accesses)
+//! that turn every operation into polynomial constraints of degree at most 3.
+//!
+//! Registers, memory words and page write flags form one space of cells.
+//! Each cycle makes up to [`SLOTS`] accesses to it, each reading a cell and
+//! writing it back at a later timestamp. Memory consistency is a logUp
+//! argument: the accesses, the initial and final value of every touched cell
+//! (listed in a sorted table in the same rows), and the public data the
+//! verifier knows must sum to zero. The aux trace holds one fraction column
+//! per pair of lookups and their running sum.
+//!
+//! Precompile calls are checked by units sharing the rows with the CPU and
+//! balanced by the same argument: the [stream](stream) of words the calls
+//! pass through `ecall`, the [Poseidon permutations](permutation), the
+//! [scalar multiplications](scalar) and the [control](control) unit tying
+//! each call's words to what it computes. Their words never leave the trace.
+//!
+//! Constraints hold on every row but the last [`RANDOM_ROWS`], which are
+//! random, so that the rows a proof opens reveal nothing about the run.
+
+use pasta_curves::group::ff::{Field, PrimeField};
+
+use crate::field::{self, pow2, Felt};
+use crate::gas::{GasSchedule, InstructionClass};
+use crate::instruction::{
+    AluOp, BranchCondition, Instruction, LoadOp, ECALL, OPCODE_AUIPC, OPCODE_BRANCH, OPCODE_JAL,
+    OPCODE_JALR, OPCODE_LOAD, OPCODE_LUI, OPCODE_MISC_MEM, OPCODE_OP, OPCODE_OP_IMM, OPCODE_STORE,
+};
+use crate::memory::Width;
+use crate::precompile::{blinding_base, coordinates};
+use crate::vm::SYS_EXIT;
+
+pub(crate) mod control;
+pub(crate) mod permutation;
+pub(crate) mod scalar;
+pub(crate) mod stream;
+
+/// Cell accesses per cycle: fetch, `rs1`, `rs2`, data word, page flag, `rd`
+pub(crate) const SLOTS: usize = 6;
+pub(crate) const SLOT_FETCH: usize = 0;
+pub(crate) const SLOT_RS1: usize = 1;
+pub(crate) const SLOT_RS2: usize = 2;
+pub(crate) const SLOT_MEMORY: usize = 3;
+pub(crate) const SLOT_PAGE: usize = 4;
+pub(crate) const SLOT_RD: usize = 5;
+
+/// Bits of the gap between an access and the previous access to its cell
+pub(crate) const TS_BITS: usize = 20;
+/// Bits of `next_pc / 4`, keeping every pc below 2^31
+pub(crate) const PC_BITS: usize = 29;
+/// Bits of a word's offset into the private input region
+pub(crate) const PRIVATE_BITS: usize = 22;
+
+/// Longest provable trace, keeping timestamps below 2^TS_BITS
+pub(crate) const MAX_TRACE_LEN: usize = 1 << 17;
+pub(crate) const MIN_TRACE_LEN: usize = 256;
+/// Random rows ending every trace: one per value of a column a proof opens
+pub(crate) const RANDOM_ROWS: usize = crate::proof::QUERIES + 2;
+/// Rows of the periodic columns' period
+pub(crate) const PERIOD: usize = 64;
+
+/// Memory words are cells `address / 4`, below 2^30; registers follow
+pub(crate) const REGISTER_CELLS: u64 = 1 << 30;
+/// Page write flags, 1 once the guest has written to the page
+pub(crate) const PAGE_CELLS: u64 = 1 << 31;
+/// First cell of the private input region, the only cells that may start
+/// nonzero without being public
+pub(crate) const PRIVATE_CELLS: u64 = crate::abi::PRIVATE_INPUT_START as u64 / 4;
+
+/// Fingerprint tags of the lookup tuples
+const TAG_MEMORY: u64 = 0;
+const TAG_OUTPUT: u64 = 1;
+const TAG_CALL: u64 = 2;
+const TAG_EXIT: u64 = 3;
+const TAG_HEADER: u64 = 4;
+const TAG_WORD: u64 = 5;
+const TAG_ELEMENT: u64 = 6;
+const TAG_PERMUTATION: u64 = 7;
+const TAG_CHAIN: u64 = 8;
+
+// Cycle columns
+pub(crate) const CYCLE: usize = 0;
+pub(crate) const PC: usize = 1;
+pub(crate) const WORD: usize = 2;
+pub(crate) const IMM: usize = 3;
+pub(crate) const RS1: usize = 4;
+pub(crate) const RS2: usize = 5;
+pub(crate) const RD_OLD: usize = 6;
+/// Value the instruction writes to `rd`, if it writes
+pub(crate) const RESULT: usize = 7;
+pub(crate) const RD_NEW: usize = 8;
+pub(crate) const ZERO_INV: usize = 9;
+pub(crate) const REM_ZERO_INV: usize = 10;
+pub(crate) const RD_INV: usize = 11;
+/// Gas of a precompile call beyond the `ecall`
+pub(crate) const CALL_GAS: usize = 12;
+/// Gas used before the cycle
+pub(crate) const GAS: usize = 13;
+pub(crate) const MEM_NEW: usize = 14;
+/// Precompile `ecall`s before the cycle
+pub(crate) const ECALLS: usize = 15;
+/// Timestamp of the previous access to each slot's cell
+pub(crate) const OLD_TS: usize = 16;
+
+// Boolean columns
+const BOOLEANS: usize = OLD_TS + SLOTS;
+pub(crate) const PADDING: usize = BOOLEANS;
+pub(crate) const CARRY: usize = PADDING + 1;
+pub(crate) const TARGET_CARRY: usize = CARRY + 1;
+pub(crate) const LOW_BIT: usize = TARGET_CARRY + 1;
+pub(crate) const ZERO: usize = LOW_BIT + 1;
+pub(crate) const REM_ZERO: usize = ZERO + 1;
+pub(crate) const OVERFLOW: usize = REM_ZERO + 1;
+pub(crate) const RD_NONZERO: usize = OVERFLOW + 1;
+pub(crate) const EXIT: usize = RD_NONZERO + 1;
+pub(crate) const PAGE_OLD: usize = EXIT + 1;
+/// One-hot byte offset of a load or store
+pub(crate) const OFFSET: usize = PAGE_OLD + 1;
+pub(crate) const WORD_BITS: usize = OFFSET + 4;
+/// `rs1`
+pub(crate) const X_BITS: usize = WORD_BITS + 32;
+/// Second operand: `rs2` or the immediate
+pub(crate) const Y_BITS: usize = X_BITS + 32;
+pub(crate) const Z_BITS: usize = Y_BITS + 32;
+pub(crate) const W_BITS: usize = Z_BITS + 32;
+pub(crate) const V_BITS: usize = W_BITS + 32;
+/// One-hot shift amount
+pub(crate) const SHIFT: usize = V_BITS + 32;
+pub(crate) const NEXT_PC_BITS: usize = SHIFT + 32;
+pub(crate) const TS_DIFF_BITS: usize = NEXT_PC_BITS + PC_BITS;
+pub(crate) const SELECTORS: usize = TS_DIFF_BITS + SLOTS * TS_BITS;
+
+// Memory table: one touched cell per row, by increasing address
+pub(crate) const M_ACTIVE: usize = SELECTORS + OP_COUNT;
+pub(crate) const M_PUBLIC: usize = M_ACTIVE + 1;
+pub(crate) const M_PRIVATE: usize = M_PUBLIC + 1;
+pub(crate) const M_OUTPUT: usize = M_PRIVATE + 1;
+pub(crate) const M_DIFF_BITS: usize = M_OUTPUT + 1;
+pub(crate) const M_PRIVATE_BITS: usize = M_DIFF_BITS + 32;
+const BOOLEANS_END: usize = M_PRIVATE_BITS + PRIVATE_BITS;
+pub(crate) const M_ADDRESS: usize = BOOLEANS_END;
+pub(crate) const M_INIT: usize = M_ADDRESS + 1;
+pub(crate) const M_FINAL: usize = M_INIT + 1;
+pub(crate) const M_FINAL_TS: usize = M_FINAL + 1;
+const TABLE_END: usize = M_FINAL_TS + 1;
+
+pub(crate) const MAIN_WIDTH: usize = control::END;
+
+/// Aux columns: one per lookup of the CPU and memory table, one per pair of
+/// lookups of the precompile units, then their running sum
+pub(crate) const LOOKUPS: usize = SLOTS + 4 + UNIT_LOOKUPS / 2;
+const UNIT_LOOKUPS: usize =
+    stream::LOOKUPS + permutation::LOOKUPS + scalar::LOOKUPS + control::LOOKUPS;
+pub(crate) const RUNNING_SUM: usize = LOOKUPS;
+pub(crate) const AUX_WIDTH: usize = RUNNING_SUM + 1;
+
+pub(crate) const OP_COUNT: usize = 47;
+
+/// Operations with their own selector column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    Lui,
+    Auipc,
+    Jal,
+    Jalr,
+    Branch(BranchCondition),
+    Load(LoadOp),
+    Store(Width),
+    AluImm(AluOp),
+    Alu(AluOp),
+    Fence,
+    Ecall,
+}
+
+/// Immediate encodings, by the instruction format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    I,
+    S,
+    B,
+    U,
+    J,
+}
+
+impl Op {
+    pub const ALL: [Op; OP_COUNT] = [
+        Op::Lui,
+        Op::Auipc,
+        Op::Jal,
+        Op::Jalr,
+        Op::Branch(BranchCondition::Eq),
+        Op::Branch(BranchCondition::Ne),
+        Op::Branch(BranchCondition::Lt),
+        Op::Branch(BranchCondition::Ge),
+        Op::Branch(BranchCondition::Ltu),
+        Op::Branch(BranchCondition::Geu),
+        Op::Load(LoadOp::Lb),
+        Op::Load(LoadOp::Lh),
+        Op::Load(LoadOp::Lw),
+        Op::Load(LoadOp::Lbu),
+        Op::Load(LoadOp::Lhu),
+        Op::Store(Width::Byte),
+        Op::Store(Width::Half),
+        Op::Store(Width::Word),
+        Op::AluImm(AluOp::Add),
+        Op::AluImm(AluOp::Sll),
+        Op::AluImm(AluOp::Slt),
+        Op::AluImm(AluOp::Sltu),
+        Op::AluImm(AluOp::Xor),
+        Op::AluImm(AluOp::Srl),
+        Op::AluImm(AluOp::Sra),
+        Op::AluImm(AluOp::Or),
+        Op::AluImm(AluOp::And),
+        Op::Alu(AluOp::Add),
+        Op::Alu(AluOp::Sub),
+        Op::Alu(AluOp::Sll),
+        Op::Alu(AluOp::Slt),
+        Op::Alu(AluOp::Sltu),
+        Op::Alu(AluOp::Xor),
+        Op::Alu(AluOp::Srl),
+        Op::Alu(AluOp::Sra),
+        Op::Alu(AluOp::Or),
+        Op::Alu(AluOp::And),
+        Op::Alu(AluOp::Mul),
+        Op::Alu(AluOp::Mulh),
+        Op::Alu(AluOp::Mulhsu),
+        Op::Alu(AluOp::Mulhu),
+        Op::Alu(AluOp::Div),
+        Op::Alu(AluOp::Divu),
+        Op::Alu(AluOp::Rem),
+        Op::Alu(AluOp::Remu),
+        Op::Fence,
+        Op::Ecall,
+    ];
+
+    /// The operation of `instruction`; `ebreak` always faults and has none
+    pub fn of(instruction: &Instruction) -> Option<Self> {
+        Some(match *instruction {
+            Instruction::Lui { .. } => Op::Lui,
+            Instruction::Auipc { .. } => Op::Auipc,
+            Instruction::Jal { .. } => Op::Jal,
+            Instruction::Jalr { .. } => Op::Jalr,
+            Instruction::Branch { cond, .. } => Op::Branch(cond),
+            Instruction::Load { op, .. } => Op::Load(op),
+            Instruction::Store { width, .. } => Op::Store(width),
+            Instruction::AluImm { op, .. } => Op::AluImm(op),
+            Instruction::Alu { op, .. } => Op::Alu(op),
+            Instruction::Fence => Op::Fence,
+            Instruction::Ecall => Op::Ecall,
+            Instruction::Ebreak => return None,
+        })
+    }
+
+    /// Selector column of the operation
+    pub fn column(self) -> usize {
+        SELECTORS
+            + Op::ALL
+                .iter()
+                .position(|&op| op == self)
+                .expect("every operation is listed")
+    }
+
+    /// `(opcode, funct3, funct7)` fields the encoding fixes; `ecall` fixes the
+    /// whole word instead
+    fn encoding(self) -> Option<(u32, Option<u32>, Option<u32>)> {
+        Some(match self {
+            Op::Lui => (OPCODE_LUI, None, None),
+            Op::Auipc => (OPCODE_AUIPC, None, None),
+            Op::Jal => (OPCODE_JAL, None, None),
+            Op::Jalr => (OPCODE_JALR, Some(0), None),
+            Op::Branch(cond) => (OPCODE_BRANCH, Some(cond.funct3()), None),
+            Op::Load(op) => (OPCODE_LOAD, Some(op.funct3()), None),
+            Op::Store(width) => (OPCODE_STORE, Some(width as u32), None),
+            Op::AluImm(op) => {
+                let (funct7, funct3) = op.funct();
+                (OPCODE_OP_IMM, Some(funct3), op.is_shift().then_some(funct7))
+            }
+            Op::Alu(op) => {
+                let (funct7, funct3) = op.funct();
+                (OPCODE_OP, Some(funct3), Some(funct7))
+            }
+            Op::Fence => (OPCODE_MISC_MEM, Some(0), None),
+            Op::Ecall => return None,
+        })
+    }
+
+    pub fn format(self) -> Option<Format> {
+        match self {
+            Op::Lui | Op::Auipc => Some(Format::U),
+            Op::Jal => Some(Format::J),
+            Op::Jalr | Op::Load(_) | Op::AluImm(_) => Some(Format::I),
+            Op::Branch(_) => Some(Format::B),
+            Op::Store(_) => Some(Format::S),
+            Op::Alu(_) | Op::Fence | Op::Ecall => None,
+        }
+    }
+
+    /// Whether the operation writes `rd`
+    fn writes(self) -> bool {
+        matches!(
+            self,
+            Op::Lui | Op::Auipc | Op::Jal | Op::Jalr | Op::Load(_) | Op::AluImm(_) | Op::Alu(_)
+        )
+    }
+
+    pub fn class(self) -> InstructionClass {
+        match self {
+            Op::Lui | Op::Auipc | Op::AluImm(_) => InstructionClass::Alu,
+            Op::Alu(AluOp::Mul | AluOp::Mulh | AluOp::Mulhsu | AluOp::Mulhu) => {
+                InstructionClass::Multiply
+            }
+            Op::Alu(AluOp::Div | AluOp::Divu | AluOp::Rem | AluOp::Remu) => {
+                InstructionClass::Divide
+            }
+            Op::Alu(_) => InstructionClass::Alu,
+            Op::Load(_) => InstructionClass::Load,
+            Op::Store(_) => InstructionClass::Store,
+            Op::Branch(_) => InstructionClass::Branch,
+            Op::Jal | Op::Jalr => InstructionClass::Jump,
+            Op::Fence | Op::Ecall => InstructionClass::System,
+        }
+    }
+}
+
+/// Timestamp of access `slot` in `cycle`; cells start at timestamp 0
+pub(crate) fn timestamp(cycle: u64, slot: usize) -> u64 {
+    8 * cycle + slot as u64 + 1
+}
+
+pub(crate) fn register_cell(index: u8) -> u64 {
+    REGISTER_CELLS + index as u64
+}
+
+pub(crate) fn page_cell(page: u32) -> u64 {
+    PAGE_CELLS + page as u64
+}
+
+/// Cell of the word containing byte `address`
+pub(crate) fn word_cell(address: u32) -> u64 {
+    address as u64 / 4
+}
+
+/// Last row the constraints hold on in a trace of `n` rows
+pub(crate) fn last_row(n: usize) -> usize {
+    n - RANDOM_ROWS - 1
+}
+
+/// Two consecutive rows of a trace
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Frame<'a> {
+    pub current: &'a [Felt],
+    pub next: &'a [Felt],
+}
+
+/// Receives constraint values, each required to be zero on its rows
+pub(crate) trait Constraints {
+    /// Holds on every row
+    fn every(&mut self, name: &'static str, value: Felt);
+    /// Relates every row but the last to the next
+    fn transition(&mut self, name: &'static str, value: Felt);
+    fn first(&mut self, name: &'static str, value: Felt);
+    fn last(&mut self, name: &'static str, value: Felt);
+}
+
+/// Random challenges the lookup fingerprints are taken at
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Challenges {
+    pub alpha: Felt,
+    pub beta: Felt,
+}
+
+impl Challenges {
+    /// `alpha - (tag + beta * values[0] + beta^2 * values[1] + ...)`
+    fn denominator(&self, tag: u64, values: &[Felt]) -> Felt {
+        let mut fingerprint = Felt::from(tag);
+        let mut power = self.beta;
+        for value in values {
+            fingerprint += power * value;
+            power *= self.beta;
+        }
+        self.alpha - fingerprint
+    }
+
+    fn memory(&self, cell: u64, value: u32, timestamp: u64) -> Felt {
+        self.denominator(
+            TAG_MEMORY,
+            &[
+                Felt::from(cell),
+                Felt::from(value as u64),
+                Felt::from(timestamp),
+            ],
+        )
+    }
+}
+
+/// An aux column: the sum of `numerators[i] / denominators[i]`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Lookup {
+    pub numerators: [Felt; 2],
+    pub denominators: [Felt; 2],
+}
+
+impl Lookup {
+    fn single(numerator: Felt, denominator: Felt) -> Self {
+        Self {
+            numerators: [numerator, Felt::ZERO],
+            denominators: [denominator, Felt::ONE],
+        }
+    }
+}
+
+/// A cell access by one slot
+struct Access {
+    gate: Felt,
+    cell: Felt,
+    old: Felt,
+    new: Felt,
+    old_ts: Felt,
+    ts: Felt,
+}
+
+/// Selectors summed by the role of their operation
+#[derive(Default)]
+struct Groups {
+    sum: Felt,
+    alu: [Felt; 18],
+    branch: [Felt; 6],
+    load: [Felt; 5],
+    store: [Felt; 3],
+    lui: Felt,
+    auipc: Felt,
+    jal: Felt,
+    jalr: Felt,
+    ecall: Felt,
+    format: [Felt; 5],
+    writes: Felt,
+    rs2_operand: Felt,
+    imm_operand: Felt,
+    /// Gas of the instruction itself
+    cost: Felt,
+}
+
+/// The constraints of runs of a program with one entry point
+pub(crate) struct Air {
+    entry: Felt,
+    costs: [Felt; OP_COUNT],
+    page_cost: Felt,
+    /// Gas of a Poseidon, Pedersen, Merkle path, note and signature call
+    call_costs: [Felt; 5],
+    /// Affine coordinates of `H`, the blinding generator of commitments
+    blinding_base: [Felt; 2],
+    /// `2^i` up to `2^64`
+    powers: [Felt; 65],
+    /// `2^-i` below `2^-32`
+    inverse_powers: [Felt; 32],
+    /// `2^(32 * k)`, the weight of word `k` of an element
+    word_powers: [Felt; 8],
+    /// Words of `p - 1` and `q - 1`, the largest canonical field element and
+    /// scalar
+    bounds: [[Felt; 8]; 2],
+    permutation: permutation::Constants,
+}
+
+impl Air {
+    pub fn new(entry: u32, schedule: &GasSchedule) -> Self {
+        let half = Felt::from(2u64).invert().expect("2 is nonzero");
+        let words = |bytes: [u8; 32]| {
+            std::array::from_fn(|k| {
+                let word = u32::from_le_bytes(bytes[4 * k..4 * k + 4].try_into().expect("4 bytes"));
+                Felt::from(word as u64)
+            })
+        };
+        Self {
+            entry: Felt::from(entry as u64),
+            costs: Op::ALL.map(|op| Felt::from(schedule.class(op.class()))),
+            page_cost: Felt::from(schedule.page),
+            call_costs: [
+                schedule.poseidon,
+                schedule.pedersen_commit,
+                schedule.merkle_verify,
+                schedule.note_nullifier,
+                schedule.signature_verify,
+            ]
+            .map(Felt::from),
+            blinding_base: {
+                let (x, y) = coordinates(&blinding_base());
+                [x, y]
+            },
+            powers: std::array::from_fn(|i| pow2(i as u32)),
+            inverse_powers: std::array::from_fn(|i| half.pow_vartime([i as u64])),
+            word_powers: std::array::from_fn(|k| pow2(32 * k as u32)),
+            bounds: [
+                words((-Felt::ONE).to_repr()),
+                words((-pasta_curves::pallas::Scalar::ONE).to_repr()),
+            ],
+            permutation: permutation::Constants::new(),
+        }
+    }
+
+    /// Values of the periodic columns on `row`
+    pub fn periodic(&self, row: usize) -> Vec<Felt> {
+        let row = row % PERIOD;
+        let mut values = permutation::periodic(&self.permutation, row);
+        values.extend(scalar::periodic(row));
+        debug_assert_eq!(values.len(), scalar::PERIODIC_END);
+        values
+    }
+
+    /// Constraints on the main trace, given the periodic values of the
+    /// current row
+    pub fn evaluate_main(&self, frame: &Frame, periodic: &[Felt], out: &mut impl Constraints) {
+        let (c, next) = (frame.current, frame.next);
+        let p = &self.powers;
+        let one = Felt::ONE;
+        let two32 = p[32];
+        let groups = self.groups(c);
+        let value = |column: usize, bits: usize| self.combine(&c[column..column + bits]);
+
+        for &bit in &c[BOOLEANS..BOOLEANS_END] {
+            out.every("boolean", bit * (bit - one));
+        }
+
+        // Decoding
+        let word_bit = |i: usize| c[WORD_BITS + i];
+        let field = |start: usize, bits: usize| value(WORD_BITS + start, bits);
+        out.every("word bits", c[WORD] - field(0, 32));
+        let (opcode, funct3, funct7) = (field(0, 7), field(12, 3), field(25, 7));
+        for (op, &selector) in Op::ALL.iter().zip(&c[SELECTORS..]) {
+            let Some((op_opcode, op_funct3, op_funct7)) = op.encoding() else {
+                out.every("decode", selector * (c[WORD] - Felt::from(ECALL as u64)));
+                continue;
+            };
+            out.every("decode", selector * (opcode - Felt::from(op_opcode as u64)));
+            if let Some(op_funct3) = op_funct3 {
+                out.every("decode", selector * (funct3 - Felt::from(op_funct3 as u64)));
+            }
+            if let Some(op_funct7) = op_funct7 {
+                out.every("decode", selector * (funct7 - Felt::from(op_funct7 as u64)));
+            }
+        }
+        let padding = c[PADDING];
+        out.every("one operation", groups.sum - (one - padding));
+
+        let sign = word_bit(31);
+        let imm_i = field(20, 11) + sign * (two32 - p[11]);
+        let imm_s = field(7, 5) + field(25, 6) * p[5] + sign * (two32 - p[11]);
+        let imm_b =
+            field(8, 4) * p[1] + field(25, 6) * p[5] + word_bit(7) * p[11] + sign * (two32 - p[12]);
+        let imm_u = field(12, 20) * p[12];
+        let imm_j = field(21, 10) * p[1]
+            + word_bit(20) * p[11]
+            + field(12, 8) * p[12]
+            + sign * (two32 - p[20]);
+        let immediate = [imm_i, imm_s, imm_b, imm_u, imm_j]
+            .iter()
+            .zip(&groups.format)
+            .map(|(imm, format)| *imm * format)
+            .sum::<Felt>();
+        out.every("immediate", c[IMM] - immediate);
+
+        // Operands
+        let (x, y, z, w, v) = (
+            value(X_BITS, 32),
+            value(Y_BITS, 32),
+            value(Z_BITS, 32),
+            value(W_BITS, 32),
+            value(V_BITS, 32),
+        );
+        let (x31, y31, v31, w31) = (
+            c[X_BITS + 31],
+            c[Y_BITS + 31],
+            c[V_BITS + 31],
+            c[W_BITS + 31],
+        );
+        out.every("rs1 bits", c[RS1] - x);
+        out.every(
+            "operand",
+            groups.rs2_operand * (y - c[RS2]) + groups.imm_operand * (y - c[IMM]),
+        );
+
+        // Arithmetic and logic
+        let alu = &groups.alu;
+        let op = |op: AluOp| alu[op as usize];
+        let (result, carry) = (c[RESULT], c[CARRY]);
+        let difference = x - y + two32 * carry - z;
+        let signed_difference = difference + two32 * (y31 - x31);
+        out.every("add", op(AluOp::Add) * (x + y - z - two32 * carry));
+        out.every("sub", (op(AluOp::Sub) + op(AluOp::Sltu)) * difference);
+        out.every("slt", op(AluOp::Slt) * signed_difference);
+        out.every(
+            "alu result",
+            (op(AluOp::Add) + op(AluOp::Sub)) * (result - z),
+        );
+        out.every(
+            "alu result",
+            (op(AluOp::Slt) + op(AluOp::Sltu)) * (result - carry),
+        );
+
+        let and = (0..32)
+            .map(|i| c[X_BITS + i] * c[Y_BITS + i] * p[i])
+            .sum::<Felt>();
+        out.every("xor", op(AluOp::Xor) * (result - (x + y - and - and)));
+        out.every("or", op(AluOp::Or) * (result - (x + y - and)));
+        out.every("and", op(AluOp::And) * (result - and));
+
+        let shift = op(AluOp::Sll) + op(AluOp::Srl) + op(AluOp::Sra);
+        let amounts = &c[SHIFT..SHIFT + 32];
+        let amount = amounts
+            .iter()
+            .enumerate()
+            .map(|(k, bit)| *bit * Felt::from(k as u64))
+            .sum::<Felt>();
+        out.every("shift", shift * (amounts.iter().sum::<Felt>() - one));
+        out.every("shift", shift * (amount - value(Y_BITS, 5)));
+        let mut prefix = [Felt::ZERO; 33];
+        for i in 0..32 {
+            prefix[i + 1] = prefix[i] + c[X_BITS + i] * p[i];
+        }
+        let (mut left, mut right, mut fill) = (Felt::ZERO, Felt::ZERO, Felt::ZERO);
+        for (k, bit) in amounts.iter().enumerate() {
+            left += *bit * prefix[32 - k] * p[k];
+            right += *bit * (x - prefix[k]) * self.inverse_powers[k];
+            fill += *bit * (two32 - p[32 - k]);
+        }
+        out.every("sll", op(AluOp::Sll) * (result - left));
+        out.every("srl", op(AluOp::Srl) * (result - right));
+        out.every("sra", op(AluOp::Sra) * (result - right - x31 * fill));
+
+        let (xs, ys) = (x - two32 * x31, y - two32 * y31);
+        let two64 = p[64];
+        out.every("mul", op(AluOp::Mul) * (x * y - z - two32 * w));
+        out.every("mulhu", op(AluOp::Mulhu) * (x * y - w - two32 * z));
+        out.every(
+            "mulh",
+            op(AluOp::Mulh) * (xs * ys - w - two32 * z + two64 * carry),
+        );
+        out.every(
+            "mulhsu",
+            op(AluOp::Mulhsu) * (xs * y - w - two32 * z + two64 * carry),
+        );
+        let multiply = op(AluOp::Mul) + op(AluOp::Mulh) + op(AluOp::Mulhsu) + op(AluOp::Mulhu);
+        out.every("mul result", multiply * (result - z));
+
+        // Division: quotient in w, remainder in v, and z bounds the remainder
+        let (zero, overflow, rem_zero) = (c[ZERO], c[OVERFLOW], c[REM_ZERO]);
+        let unsigned = op(AluOp::Divu) + op(AluOp::Remu);
+        let signed = op(AluOp::Div) + op(AluOp::Rem);
+        out.every("divu", unsigned * (x - w * y - v));
+        out.every("divu", unsigned * (z - y + v + one - two32 * zero));
+        let (vs, ws) = (v - two32 * v31, w - two32 * w31);
+        let y_abs = ys * (one - y31 - y31);
+        let v_abs = vs * (one - v31 - v31);
+        out.every("div", signed * (xs - vs + two32 * overflow - ws * ys));
+        out.every("div", signed * overflow * (x - p[31]));
+        out.every("div", signed * overflow * (y - (two32 - one)));
+        out.every("div", signed * (z - y_abs + v_abs + one - two32 * zero));
+        out.every("div", signed * v31 * (one - x31));
+        out.every("div", signed * x31 * (one - v31 - rem_zero));
+        out.every("div", signed * v * rem_zero);
+        out.every("div", signed * (v * c[REM_ZERO_INV] - one + rem_zero));
+        let division = unsigned + signed;
+        out.every("divide by zero", division * y * zero);
+        out.every("divide by zero", division * (y * c[ZERO_INV] - one + zero));
+        out.every("divide by zero", division * zero * (w - (two32 - one)));
+        out.every(
+            "div result",
+            (op(AluOp::Div) + op(AluOp::Divu)) * (result - w),
+        );
+        out.every(
+            "rem result",
+            (op(AluOp::Rem) + op(AluOp::Remu)) * (result - v),
+        );
+
+        // Control flow
+        let [beq, bne, blt, bge, bltu, bgeu] = groups.branch;
+        let equal = beq + bne;
+        let rs_difference = c[RS1] - c[RS2];
+        out.every("branch", equal * rs_difference * zero);
+        out.every("branch", equal * (rs_difference * c[ZERO_INV] - one + zero));
+        out.every("branch", (blt + bge) * signed_difference);
+        out.every("branch", (bltu + bgeu) * difference);
+        let branch = groups.branch.iter().sum::<Felt>();
+        let taken =
+            beq * zero + bne * (one - zero) + (blt + bltu) * carry + (bge + bgeu) * (one - carry);
+        let next_pc = value(NEXT_PC_BITS, PC_BITS) * p[2];
+        let four = Felt::from(4u64);
+        let target_carry = c[TARGET_CARRY];
+        out.every(
+            "branch target",
+            branch * (next_pc - c[PC] - four) - taken * (c[IMM] - four - two32 * target_carry),
+        );
+        let (jal, jalr) = (groups.jal, groups.jalr);
+        out.every(
+            "jal",
+            jal * (c[PC] + c[IMM] - next_pc - two32 * target_carry),
+        );
+        out.every(
+            "jalr",
+            jalr * (c[RS1] + c[IMM] - next_pc - c[LOW_BIT] - two32 * target_carry),
+        );
+        out.every("link", (jal + jalr) * (result - c[PC] - four));
+        let sequential = one - padding - jal - jalr - branch;
+        out.every("next pc", sequential * (next_pc - c[PC] - four));
+        out.every("lui", groups.lui * (result - c[IMM]));
+        out.every("auipc", groups.auipc * (c[PC] + c[IMM] - z - two32 * carry));
+        out.every("auipc", groups.auipc * (result - z));
+
+        // Loads and stores of the word v at address z
+        let loads = groups.load.iter().sum::<Felt>();
+        let stores = groups.store.iter().sum::<Felt>();
+        let memory = loads + stores;
+        let offset = &c[OFFSET..OFFSET + 4];
+        out.every("address", memory * (c[RS1] + c[IMM] - z - two32 * carry));
+        out.every("offset", memory * (offset.iter().sum::<Felt>() - one));
+        out.every("offset", memory * (c[Z_BITS] - offset[1] - offset[3]));
+        out.every("offset", memory * (c[Z_BITS + 1] - offset[2] - offset[3]));
+        let [lb, lh, lw, lbu, lhu] = groups.load;
+        let [sb, sh, sw] = groups.store;
+        out.every("alignment", (lw + sw) * (one - offset[0]));
+        out.every("alignment", (lh + lhu + sh) * (offset[1] + offset[3]));
+        let bytes: [Felt; 4] = std::array::from_fn(|k| value(V_BITS + 8 * k, 8));
+        let (low, high) = (bytes[0] + bytes[1] * p[8], bytes[2] + bytes[3] * p[8]);
+        let half = low + offset[2] * (high - low);
+        let half_sign = c[V_BITS + 15] + offset[2] * (c[V_BITS + 31] - c[V_BITS + 15]);
+        let byte = (0..4).map(|k| offset[k] * bytes[k]).sum::<Felt>();
+        let byte_sign = (0..4)
+            .map(|k| offset[k] * c[V_BITS + 8 * k + 7])
+            .sum::<Felt>();
+        out.every("load", lw * (result - v));
+        out.every("load", lhu * (result - half));
+        out.every("load", lh * (result - half - half_sign * (two32 - p[16])));
+        out.every("load", lbu * (result - byte));
+        out.every("load", lb * (result - byte - byte_sign * (two32 - p[8])));
+        out.every("load", loads * (c[MEM_NEW] - v));
+        let (y_half, y_byte) = (value(Y_BITS, 16), value(Y_BITS, 8));
+        out.every("store", sw * (c[MEM_NEW] - c[RS2]));
+        out.every(
+            "store",
+            sh * (c[MEM_NEW]
+                - v
+                - offset[0] * (y_half - low)
+                - offset[2] * (y_half - high) * p[16]),
+        );
+        let stored = (0..4)
+            .map(|k| offset[k] * (y_byte - bytes[k]) * p[8 * k])
+            .sum::<Felt>();
+        out.every("store", sb * (c[MEM_NEW] - v - stored));
+
+        // Register writes and system calls
+        let rd = field(7, 5);
+        let nonzero = c[RD_NONZERO];
+        out.every("rd", rd * (one - nonzero));
+        out.every("rd", rd * c[RD_INV] - nonzero);
+        let (ecall, exit) = (groups.ecall, c[EXIT]);
+        let call = ecall - ecall * exit;
+        let write = nonzero * groups.writes + call;
+        out.every("rd", c[RD_NEW] - c[RD_OLD] - write * (result - c[RD_OLD]));
+        out.every(
+            "exit",
+            ecall * exit * (c[RS1] - Felt::from(SYS_EXIT as u64)),
+        );
+        out.every("exit", exit * (one - ecall));
+        out.every("call gas", c[CALL_GAS] * (one - call));
+        out.transition("ecalls", next[ECALLS] - c[ECALLS] - call);
+        out.first("ecalls", c[ECALLS]);
+
+        // Accesses read the value of the cell's previous access
+        let gates = self.slot_gates(c, &groups);
+        for (slot, gate) in gates.iter().enumerate() {
+            let gap = value(TS_DIFF_BITS + slot * TS_BITS, TS_BITS);
+            out.every(
+                "timestamp",
+                *gate * (self.timestamp(c, slot) - c[OLD_TS + slot] - one - gap),
+            );
+        }
+
+        out.transition("cycle", next[CYCLE] - c[CYCLE] - one);
+        out.transition("padding", next[PADDING] - padding - ecall * exit);
+        out.transition("pc", (one - next[PADDING]) * (next[PC] - next_pc));
+        out.transition("gas", next[GAS] - c[GAS] - self.cost(c, &groups));
+        out.first("cycle", c[CYCLE]);
+        out.first("padding", padding);
+        out.first("pc", c[PC] - self.entry);
+        out.first("gas", c[GAS]);
+        out.last("padding", padding - one);
+
+        // Memory table
+        let (active, public, private) = (c[M_ACTIVE], c[M_PUBLIC], c[M_PRIVATE]);
+        for flag in [public, private, c[M_OUTPUT]] {
+            out.every("table", flag * (one - active));
+        }
+        out.every("table", public * private);
+        out.every("table", (one - public) * (one - private) * c[M_INIT]);
+        out.every(
+            "table",
+            private
+                * (c[M_ADDRESS] - Felt::from(PRIVATE_CELLS) - value(M_PRIVATE_BITS, PRIVATE_BITS)),
+        );
+        out.transition("table", next[M_ACTIVE] * (one - active));
+        out.transition(
+            "table",
+            next[M_ACTIVE] * (next[M_ADDRESS] - c[M_ADDRESS] - one - value(M_DIFF_BITS, 32)),
+        );
+
+        stream::evaluate(self, frame, out);
+        permutation::evaluate(self, frame, periodic, out);
+        scalar::evaluate(frame, periodic, out);
+        control::evaluate(self, frame, out);
+    }
+
+    /// Constraints on the aux trace, which balances when the lookups of all
+    /// rows and the verifier's `public_sum` add up to zero
+    pub fn evaluate_aux(
+        &self,
+        main: &Frame,
+        aux: &Frame,
+        periodic: &[Felt],
+        challenges: &Challenges,
+        public_sum: Felt,
+        out: &mut impl Constraints,
+    ) {
+        let lookups = self.lookups(main.current, periodic, challenges);
+        for (lookup, fraction) in lookups.iter().zip(aux.current) {
+            let [n0, n1] = lookup.numerators;
+            let [d0, d1] = lookup.denominators;
+            out.every("lookup", *fraction * d0 * d1 - n0 * d1 - n1 * d0);
+        }
+
+        let sum = aux.current[RUNNING_SUM];
+        let delta = aux.current[..LOOKUPS].iter().sum::<Felt>();
+        out.first("running sum", sum);
+        out.transition("running sum", aux.next[RUNNING_SUM] - sum - delta);
+        out.last("running sum", sum + delta + public_sum);
+    }
+
+    /// The lookups of a main trace row, one per aux column
+    pub fn lookups(&self, c: &[Felt], periodic: &[Felt], challenges: &Challenges) -> Vec<Lookup> {
+        let groups = self.groups(c);
+        let memory = |cell, value, ts| challenges.denominator(TAG_MEMORY, &[cell, value, ts]);
+        let mut lookups: Vec<Lookup> = self
+            .accesses(c, &groups)
+            .iter()
+            .map(|access| Lookup {
+                numerators: [access.gate, -access.gate],
+                denominators: [
+                    memory(access.cell, access.new, access.ts),
+                    memory(access.cell, access.old, access.old_ts),
+                ],
+            })
+            .collect();
+
+        let (ecall, exit) = (groups.ecall, c[EXIT]);
+        let call = challenges.denominator(
+            TAG_CALL,
+            &[c[ECALLS], c[RS1], c[RS2], c[RESULT], c[CALL_GAS]],
+        );
+        lookups.push(Lookup::single(ecall * exit - ecall, call));
+        let gas_used = c[GAS] + self.cost(c, &groups);
+        lookups.push(Lookup::single(
+            -ecall * exit,
+            challenges.denominator(TAG_EXIT, &[c[RS2], gas_used]),
+        ));
+
+        let (active, public) = (c[M_ACTIVE], c[M_PUBLIC]);
+        lookups.push(Lookup {
+            numerators: [active - public, -active],
+            denominators: [
+                memory(c[M_ADDRESS], c[M_INIT], Felt::ZERO),
+                memory(c[M_ADDRESS], c[M_FINAL], c[M_FINAL_TS]),
+            ],
+        });
+        lookups.push(Lookup::single(
+            -c[M_OUTPUT],
+            challenges.denominator(TAG_OUTPUT, &[c[M_ADDRESS], c[M_FINAL]]),
+        ));
+
+        // The units' lookups have linear denominators, so they pair up
+        let mut units = stream::lookups(c, challenges);
+        units.extend(permutation::lookups(self, c, periodic, challenges));
+        units.extend(scalar::lookups(c, periodic, challenges));
+        units.extend(control::lookups(self, c, challenges));
+        debug_assert_eq!(units.len(), UNIT_LOOKUPS);
+        lookups.extend(units.chunks(2).map(|pair| Lookup {
+            numerators: [pair[0].0, pair[1].0],
+            denominators: [pair[0].1, pair[1].1],
+        }));
+        lookups
+    }
+
+    fn groups(&self, c: &[Felt]) -> Groups {
+        let mut groups = Groups::default();
+        for ((op, &selector), cost) in Op::ALL.iter().zip(&c[SELECTORS..]).zip(&self.costs) {
+            groups.sum += selector;
+            groups.cost += selector * cost;
+            match *op {
+                Op::Lui => groups.lui += selector,
+                Op::Auipc => groups.auipc += selector,
+                Op::Jal => groups.jal += selector,
+                Op::Jalr => groups.jalr += selector,
+                Op::Branch(cond) => {
+                    groups.branch[cond as usize] += selector;
+                    groups.rs2_operand += selector;
+                }
+                Op::Load(op) => groups.load[op as usize] += selector,
+                Op::Store(width) => {
+                    groups.store[width as usize] += selector;
+                    groups.rs2_operand += selector;
+                }
+                Op::AluImm(op) => {
+                    groups.alu[op as usize] += selector;
+                    groups.imm_operand += selector;
+                }
+                Op::Alu(op) => {
+                    groups.alu[op as usize] += selector;
+                    groups.rs2_operand += selector;
+                }
+                Op::Fence => {}
+                Op::Ecall => groups.ecall += selector,
+            }
+            if let Some(format) = op.format() {
+                groups.format[format as usize] += selector;
+            }
+            if op.writes() {
+                groups.writes += selector;
+            }
+        }
+        groups
+    }
+
+    /// Whether each slot accesses its cell
+    fn slot_gates(&self, c: &[Felt], groups: &Groups) -> [Felt; SLOTS] {
+        let running = Felt::ONE - c[PADDING];
+        let stores = groups.store.iter().sum::<Felt>();
+        let memory = groups.load.iter().sum::<Felt>() + stores;
+        [running, running, running, memory, stores, running]
+    }
+
+    fn accesses(&self, c: &[Felt], groups: &Groups) -> [Access; SLOTS] {
+        let gates = self.slot_gates(c, groups);
+        let register = |start: usize, ecall_register: u64| {
+            Felt::from(REGISTER_CELLS)
+                + self.combine(&c[WORD_BITS + start..WORD_BITS + start + 5])
+                + groups.ecall * Felt::from(ecall_register)
+        };
+        let quarter = self.inverse_powers[2];
+        let cells = [
+            c[PC] * quarter,
+            register(15, 17),
+            register(20, 10),
+            self.combine(&c[Z_BITS + 2..Z_BITS + 32]),
+            Felt::from(PAGE_CELLS) + self.combine(&c[Z_BITS + 12..Z_BITS + 32]),
+            register(7, 10),
+        ];
+        let values = [
+            (c[WORD], c[WORD]),
+            (c[RS1], c[RS1]),
+            (c[RS2], c[RS2]),
+            (self.combine(&c[V_BITS..V_BITS + 32]), c[MEM_NEW]),
+            (c[PAGE_OLD], Felt::ONE),
+            (c[RD_OLD], c[RD_NEW]),
+        ];
+        std::array::from_fn(|slot| Access {
+            gate: gates[slot],
+            cell: cells[slot],
+            old: values[slot].0,
+            new: values[slot].1,
+            old_ts: c[OLD_TS + slot],
+            ts: self.timestamp(c, slot),
+        })
+    }
+
+    fn timestamp(&self, c: &[Felt], slot: usize) -> Felt {
+        c[CYCLE] * Felt::from(8u64) + Felt::from(slot as u64 + 1)
+    }
+
+    /// Gas of the cycle: the instruction, first writes to a page by a store
+    /// and the precompile call
+    fn cost(&self, c: &[Felt], groups: &Groups) -> Felt {
+        let stores = groups.store.iter().sum::<Felt>();
+        groups.cost + stores * (Felt::ONE - c[PAGE_OLD]) * self.page_cost + c[CALL_GAS]
+    }
+
+    /// `sum bits[i] * 2^i`
+    fn combine(&self, bits: &[Felt]) -> Felt {
+        bits.iter()
+            .zip(&self.powers)
+            .map(|(bit, power)| *bit * power)
+            .sum()
+    }
+}
+
+/// The lookups balancing data the verifier knows: initial `public_memory`
+/// and the `output` cells, as `(cell, value)`, and the exit
+pub(crate) fn public_sum(
+    challenges: &Challenges,
+    public_memory: &[(u64, u32)],
+    output: &[(u64, u32)],
+    exit_code: u32,
+    gas_used: u64,
+) -> Felt {
+    let mut fractions = Vec::new();
+    for &(cell, value) in public_memory {
+        fractions.push((Felt::ONE, challenges.memory(cell, value, 0)));
+    }
+    for &(cell, value) in output {
+        let denominator =
+            challenges.denominator(TAG_OUTPUT, &[Felt::from(cell), Felt::from(value as u64)]);
+        fractions.push((Felt::ONE, denominator));
+    }
+    let exit = [exit_code as u64, gas_used].map(Felt::from);
+    fractions.push((Felt::ONE, challenges.denominator(TAG_EXIT, &exit)));
+
+    let mut denominators: Vec<Felt> = fractions
+        .iter()
+        .map(|(_, denominator)| *denominator)
+        .collect();
+    field::batch_invert(&mut denominators);
+    fractions
+        .iter()
+        .zip(denominators)
+        .map(|((numerator, _), inverse)| *numerator * inverse)
+        .sum()
+}
+
+/// Constraints the traces of `witness` fail, as `(row, name)`
+#[cfg(test)]
+pub(crate) fn unsatisfied(witness: &crate::witness::Witness) -> Vec<(usize, &'static str)> {
+    struct Checker {
+        row: usize,
+        last: usize,
+        failures: Vec<(usize, &'static str)>,
+    }
+
+    impl Checker {
+        fn check(&mut self, applies: bool, name: &'static str, value: Felt) {
+            if applies && value != Felt::ZERO {
+                self.failures.push((self.row, name));
+            }
+        }
+    }
+
+    impl Constraints for Checker {
+        fn every(&mut self, name: &'static str, value: Felt) {
+            self.check(true, name, value);
+        }
+
+        fn transition(&mut self, name: &'static str, value: Felt) {
+            self.check(self.row != self.last, name, value);
+        }
+
+        fn first(&mut self, name: &'static str, value: Felt) {
+            self.check(self.row == 0, name, value);
+        }
+
+        fn last(&mut self, name: &'static str, value: Felt) {
+            self.check(self.row == self.last, name, value);
+        }
+    }
+
+    let schedule = GasSchedule::default();
+    let air = Air::new(witness.entry, &schedule);
+    let mut transcript = crate::transcript::Transcript::new("unsatisfied");
+    let challenges = Challenges {
+        alpha: transcript.challenge(),
+        beta: transcript.challenge(),
+    };
+    let aux = witness.aux(&air, &challenges);
+    let public_sum = public_sum(
+        &challenges,
+        &witness.public_memory,
+        &witness.output,
+        witness.exit_code,
+        witness.gas_used,
+    );
+
+    let main = &witness.rows;
+    let last = last_row(main.len());
+    let mut checker = Checker {
+        row: 0,
+        last,
+        failures: Vec::new(),
+    };
+    for row in 0..=last {
+        checker.row = row;
+        let frame = Frame {
+            current: &main[row],
+            next: &main[row + 1],
+        };
+        let aux_frame = Frame {
+            current: &aux[row],
+            next: &aux[row + 1],
+        };
+        let periodic = air.periodic(row);
+        air.evaluate_main(&frame, &periodic, &mut checker);
+        air.evaluate_aux(
+            &frame,
+            &aux_frame,
+            &periodic,
+            &challenges,
+            public_sum,
+            &mut checker,
+        );
+    }
+    checker.failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precompile::{
+        output_words, split, SYS_MERKLE_VERIFY, SYS_NOTE_NULLIFIER, SYS_PEDERSEN_COMMIT,
+        SYS_POSEIDON, SYS_SIGNATURE_VERIFY,
+    };
+    use crate::vm::{Vm, REG_A0, REG_A7};
+    use crate::witness::Witness;
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::IncrementalMerkleTree;
+    use privl1_zkvm_guest::precompile::signature_challenge;
+
+    const CODE: u32 = 0x1000;
+    const DATA: u32 = 0x8000;
+
+    fn sample(op: Op) -> Instruction {
+        match op {
+            Op::Lui => Instruction::Lui { rd: 1, imm: 0x1000 },
+            Op::Auipc => Instruction::Auipc { rd: 1, imm: 0x1000 },
+            Op::Jal => Instruction::Jal { rd: 1, offset: 8 },
+            Op::Jalr => Instruction::Jalr {
+                rd: 1,
+                rs1: 2,
+                offset: 8,
+            },
+            Op::Branch(cond) => Instruction::Branch {
+                cond,
+                rs1: 1,
+                rs2: 2,
+                offset: 8,
+            },
+            Op::Load(op) => Instruction::Load {
+                op,
+                rd: 1,
+                rs1: 2,
+                offset: 4,
+            },
+            Op::Store(width) => Instruction::Store {
+                width,
+                rs1: 1,
+                rs2: 2,
+                offset: 4,
+            },
+            Op::AluImm(op) => Instruction::AluImm {
+                op,
+                rd: 1,
+                rs1: 2,
+                imm: 3,
+            },
+            Op::Alu(op) => Instruction::Alu {
+                op,
+                rd: 1,
+                rs1: 2,
+                rs2: 3,
+            },
+            Op::Fence => Instruction::Fence,
+            Op::Ecall => Instruction::Ecall,
+        }
+    }
+
+    /// Whether `word` has the fields the encoding of `op` fixes
+    fn matches(op: Op, word: u32) -> bool {
+        match op.encoding() {
+            Some((opcode, funct3, funct7)) => {
+                word & 0x7f == opcode
+                    && funct3.is_none_or(|funct3| (word >> 12) & 0x7 == funct3)
+                    && funct7.is_none_or(|funct7| word >> 25 == funct7)
+            }
+            None => word == ECALL,
+        }
+    }
+
+    /// Store a word, load a byte of it back and exit with it
+    fn witness() -> Witness {
+        let program = [
+            Instruction::Lui { rd: 1, imm: DATA },
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: 2,
+                rs1: 1,
+                offset: 0,
+            },
+            Instruction::Store {
+                width: Width::Word,
+                rs1: 1,
+                rs2: 2,
+                offset: 4,
+            },
+            Instruction::Load {
+                op: LoadOp::Lbu,
+                rd: REG_A0,
+                rs1: 1,
+                offset: 5,
+            },
+            Instruction::AluImm {
+                op: AluOp::Add,
+                rd: REG_A7,
+                rs1: 0,
+                imm: SYS_EXIT as i32,
+            },
+            Instruction::Ecall,
+        ];
+        let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+        let mut vm = Vm::from_program(CODE, &words).unwrap();
+        vm.memory_mut()
+            .store(DATA, Width::Word, 0x1234_5678)
+            .unwrap();
+        Witness::build(vm).unwrap()
+    }
+
+    /// Load the 32-bit `value` into `rd`
+    fn li(rd: u8, value: u32) -> [Instruction; 2] {
+        let low = ((value as i32) << 20) >> 20;
+        [
+            Instruction::Lui {
+                rd,
+                imm: value.wrapping_sub(low as u32),
+            },
+            Instruction::AluImm {
+                op: AluOp::Add,
+                rd,
+                rs1: rd,
+                imm: low,
+            },
+        ]
+    }
+
+    /// Stream a precompile call with `words` as its inputs, then exit with
+    /// the last word of its output
+    fn call_witness(number: u32, param: u32, words: &[u32]) -> Witness {
+        let mut program = li(REG_A7, number).to_vec();
+        program.extend(li(REG_A0, param));
+        program.push(Instruction::Ecall);
+        for &word in words {
+            program.extend(li(REG_A0, word));
+            program.push(Instruction::Ecall);
+        }
+        program.extend(vec![Instruction::Ecall; output_words(number)]);
+        program.extend(li(REG_A7, SYS_EXIT));
+        program.push(Instruction::Ecall);
+        let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+        Witness::build(Vm::from_program(CODE, &words).unwrap()).unwrap()
+    }
+
+    fn field_bytes(value: u64) -> [u8; 32] {
+        Felt::from(value).to_repr()
+    }
+
+    /// Merkle calls on a path of the tree of leaves 1 to 3, at `position`
+    fn merkle_witness(position: u32) -> Witness {
+        let mut tree = IncrementalMerkleTree::new();
+        for leaf in 1..=3 {
+            tree.append(field_bytes(leaf)).unwrap();
+            if leaf == 2 {
+                tree.mark().unwrap();
+            }
+        }
+        let proof = tree.prove(1).unwrap();
+        let mut words = split(&field_bytes(2));
+        words.extend(split(tree.root().as_bytes()));
+        words.extend(split(&proof.path.concat()));
+        words.extend([position, 0]);
+        call_witness(SYS_MERKLE_VERIFY, 0, &words)
+    }
+
+    /// A signature call on `message` under `key`, signed by a fixed key
+    fn signature_witness(key: Option<[u8; 32]>, message: &[u8]) -> Witness {
+        let keys = FullKeys::from_seed(&[5; 32]);
+        let key = key.unwrap_or_else(|| keys.spending.validating_key().to_bytes());
+        let signature = keys.spending.sign(b"transfer 10").to_bytes();
+        let r: [u8; 32] = signature[..32].try_into().unwrap();
+        let mut words = split(&key);
+        words.extend(split(&signature));
+        words.extend(split(&signature_challenge(&r, &key, message)));
+        call_witness(SYS_SIGNATURE_VERIFY, 0, &words)
+    }
+
+    /// A Pedersen commitment to `value` with blinding `blinding`
+    fn pedersen_witness(value: u64, blinding: u64) -> Witness {
+        let mut words = vec![value as u32, (value >> 32) as u32];
+        words.extend(split(
+            &pasta_curves::pallas::Scalar::from(blinding).to_repr(),
+        ));
+        call_witness(SYS_PEDERSEN_COMMIT, 0, &words)
+    }
+
+    /// A note commitment and nullifier of a note owned by `owner`
+    fn note_witness(owner: [u8; 32]) -> Witness {
+        let keys = FullKeys::from_seed(&[5; 32]);
+        let mut words = vec![1_000, 1];
+        words.extend(split(&[7; 32]));
+        words.extend(split(&owner));
+        words.extend(split(&field_bytes(11)));
+        words.extend(split(&keys.nullifier.as_scalar().to_bytes()));
+        words.extend([9, 0]);
+        call_witness(SYS_NOTE_NULLIFIER, 0, &words)
+    }
+
+    #[test]
+    fn test_operations_decode_uniquely() {
+        for op in Op::ALL {
+            let instruction = sample(op);
+            let word = instruction.encode().unwrap();
+            assert_eq!(Op::of(&Instruction::decode(word).unwrap()), Some(op));
+            assert_eq!(op.class(), InstructionClass::of(&instruction), "{op:?}");
+            assert_eq!(
+                Op::ALL
+                    .iter()
+                    .filter(|&&other| matches(other, word))
+                    .collect::<Vec<_>>(),
+                vec![&op]
+            );
+        }
+
+        let columns: std::collections::BTreeSet<usize> =
+            Op::ALL.iter().map(|op| op.column()).collect();
+        assert_eq!(columns, (SELECTORS..SELECTORS + OP_COUNT).collect());
+        assert_eq!(Op::of(&Instruction::Ebreak), None);
+    }
+
+    #[test]
+    fn test_run_satisfies_constraints() {
+        let witness = witness();
+        assert_eq!(witness.exit_code, 0x56);
+        assert!(witness.rows.len().is_power_of_two());
+        assert!(witness.rows.iter().all(|row| row.len() == MAIN_WIDTH));
+        assert_eq!(unsatisfied(&witness), vec![]);
+    }
+
+    #[test]
+    fn test_calls_satisfy_constraints() {
+        let inputs = split(&[field_bytes(1), field_bytes(2), field_bytes(3)].concat());
+        let poseidon = call_witness(SYS_POSEIDON, 3, &inputs);
+        assert_eq!(unsatisfied(&poseidon), vec![]);
+
+        // Valid and invalid results alike are constrained
+        for (position, valid) in [(1, 1), (2, 0)] {
+            let witness = merkle_witness(position);
+            assert_eq!(witness.exit_code, valid);
+            assert_eq!(unsatisfied(&witness), vec![]);
+        }
+        let cases = [
+            (None, &b"transfer 10"[..], 1),
+            (None, b"transfer 11", 0),
+            (Some([0xff; 32]), b"transfer 10", 0),
+            (Some([0; 32]), b"transfer 10", 0),
+        ];
+        for (key, message, valid) in cases {
+            let witness = signature_witness(key, message);
+            assert_eq!(witness.exit_code, valid, "{key:?}");
+            assert_eq!(unsatisfied(&witness), vec![], "{key:?}");
+        }
+
+        for (value, blinding) in [(0, 0), (1_000, 99), (u64::MAX, 1 << 40)] {
+            let witness = pedersen_witness(value, blinding);
+            assert_eq!(unsatisfied(&witness), vec![], "{value} {blinding}");
+        }
+        let owner = FullKeys::from_seed(&[5; 32]).public.to_bytes();
+        for owner in [owner, [0; 32]] {
+            assert_eq!(unsatisfied(&note_witness(owner)), vec![], "{owner:?}");
+        }
+    }
+
+    #[test]
+    fn test_call_results_are_bound() {
+        // Flip the low bit of a call's single-word result in the stream: the
+        // stream's rows stay consistent, but the lookups do not balance
+        let tampered = [
+            merkle_witness(1),
+            merkle_witness(2),
+            signature_witness(None, b"transfer 10"),
+            signature_witness(Some([0; 32]), b"transfer 10"),
+        ];
+        for mut witness in tampered {
+            let last = last_row(witness.rows.len());
+            let row = witness
+                .rows
+                .iter_mut()
+                .filter(|row| row[stream::OUTPUT] == Felt::ONE)
+                .last()
+                .unwrap();
+            let bit = Felt::ONE - row[stream::BITS];
+            let change = bit - row[stream::BITS];
+            row[stream::BITS] = bit;
+            row[stream::WORD] += change;
+            row[stream::OUT] += change;
+            assert_eq!(unsatisfied(&witness), vec![(last, "running sum")]);
+        }
+    }
+
+    #[test]
+    fn test_tampered_rows_fail() {
+        let mut wrong_result = witness();
+        wrong_result.rows[3][RESULT] += Felt::ONE;
+        let failures = unsatisfied(&wrong_result);
+        assert!(failures.contains(&(3, "load")), "{failures:?}");
+
+        // Loading another value than was stored breaks memory consistency
+        let mut wrong_load = witness();
+        let row = &mut wrong_load.rows[3];
+        row[V_BITS + 8] = Felt::ONE;
+        row[MEM_NEW] += Felt::from(0x100u64);
+        for column in [RESULT, RD_NEW] {
+            row[column] = Felt::from(0x57u64);
+        }
+        let failures = unsatisfied(&wrong_load);
+        assert_eq!(
+            failures,
+            vec![(last_row(wrong_load.rows.len()), "running sum")]
+        );
+
+        let mut skipped = witness();
+        skipped.rows[2][PC] += Felt::from(4u64);
+        assert!(unsatisfied(&skipped).contains(&(1, "pc")));
+    }
+
+    #[test]
+    fn test_public_data_is_bound() {
+        let last = last_row(witness().rows.len());
+        let mut wrong_exit = witness();
+        wrong_exit.exit_code += 1;
+        assert_eq!(unsatisfied(&wrong_exit), vec![(last, "running sum")]);
+
+        let mut wrong_gas = witness();
+        wrong_gas.gas_used -= 1;
+        assert_eq!(unsatisfied(&wrong_gas), vec![(last, "running sum")]);
+
+        // The data word starts as public memory; it cannot be claimed private
+        let mut wrong_memory = witness();
+        wrong_memory
+            .public_memory
+            .retain(|&(cell, _)| cell != word_cell(DATA));
+        assert_eq!(unsatisfied(&wrong_memory), vec![(last, "running sum")]);
+    }
+}
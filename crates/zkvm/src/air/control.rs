@@ -0,0 +1,690 @@
+//! Precompile control unit
+//!
+//! The rows of each call, in call order, tie the words of its
+//! [stream](super::stream) to what it computes:
+//! - A Poseidon hash of `L` elements is a row per permutation: the first
+//!   absorbs one or two input elements into the initial state with capacity
+//!   `L * 2^64`, each next row adds one or two to the previous output, and
+//!   the last row's first word is the output element
+//! - A Merkle path check is a row per level, hashing the node with the
+//!   sibling in the order the position's bit gives; the last row compares
+//!   the root and outputs whether it was canonical and equal
+//! - A signature check is two rows decoding the key and the nonce
+//!   commitment `R`, which look up `[c] K` and `[s] G` from the
+//!   [scalar unit](super::scalar), add `R` to the first and compare
+//! - A Pedersen commitment is a row decoding the output `C`, which looks up
+//!   `[v] G` and `[r] H`, adds them and compares the sum with `C`
+//! - A note is a row per permutation of its two hashes: the first three
+//!   absorb the value, asset, decoded owner key and randomness into the
+//!   commitment, the last two the nullifier key, commitment and position
+//!   into the nullifier, and both are outputs
+//!
+//! Every row looks up the permutations it runs from the
+//! [permutation unit](super::permutation) and its elements from the stream
+//! through three slots, `A`, `B` and `C`, whose index and kind follow from
+//! the row's flag.
+//!
+//! Decoding a point checks that its `y` coordinate, a virtual element of the
+//! stream, is on the curve with the encoded sign, or proves the encoding
+//! invalid: `x` not canonical, or `x^3 + 5` not a square, as `5 (x^3 + 5)`
+//! is one since 5 is not.
+
+use pasta_curves::group::ff::Field;
+
+use super::permutation::WIDTH;
+use super::scalar::complete_sum;
+use super::stream::{CODE_CHECK, CODE_MOD_Q, CODE_OUTPUT, CODE_POINT, CODE_VIRTUAL};
+use super::{
+    Air, Challenges, Constraints, Frame, TAG_CHAIN, TAG_ELEMENT, TAG_HEADER, TAG_PERMUTATION,
+    TAG_WORD,
+};
+use crate::field::Felt;
+use crate::precompile::{
+    ELEMENT_WORDS, MERKLE_DEPTH, SYS_MERKLE_VERIFY, SYS_NOTE_NULLIFIER, SYS_PEDERSEN_COMMIT,
+    SYS_POSEIDON, SYS_SIGNATURE_VERIFY,
+};
+
+// Stream indices of the elements and words of a call
+const POSEIDON_INPUTS: u64 = 1;
+const MERKLE_LEAF: u64 = 1;
+const MERKLE_ROOT: u64 = 9;
+const MERKLE_PATH: u64 = 17;
+pub(crate) const MERKLE_POSITION: u64 = MERKLE_PATH + (ELEMENT_WORDS * MERKLE_DEPTH) as u64;
+const MERKLE_OUTPUT: u64 = MERKLE_POSITION + 2;
+const SIGNATURE_KEY: u64 = 1;
+const SIGNATURE_NONCE: u64 = 9;
+const SIGNATURE_RESPONSE: u64 = 17;
+const SIGNATURE_CHALLENGE: u64 = 25;
+pub(crate) const SIGNATURE_OUTPUT: u64 = 41;
+/// Offset of the virtual `y` coordinate of a point from its encoding
+pub(crate) const SIGNATURE_Y: u64 = SIGNATURE_OUTPUT + 1 - SIGNATURE_KEY;
+const PEDERSEN_VALUE: u64 = 1;
+pub(crate) const PEDERSEN_BLINDING: u64 = 3;
+pub(crate) const PEDERSEN_OUTPUT: u64 = 11;
+pub(crate) const PEDERSEN_Y: u64 = ELEMENT_WORDS as u64;
+const NOTE_VALUE: u64 = 1;
+pub(crate) const NOTE_ASSET: u64 = 3;
+pub(crate) const NOTE_OWNER: u64 = 11;
+pub(crate) const NOTE_RANDOMNESS: u64 = 19;
+pub(crate) const NOTE_KEY: u64 = 27;
+pub(crate) const NOTE_POSITION: u64 = 35;
+pub(crate) const NOTE_COMMITMENT: u64 = 37;
+pub(crate) const NOTE_NULLIFIER: u64 = 45;
+pub(crate) const NOTE_Y: u64 = NOTE_NULLIFIER + ELEMENT_WORDS as u64 - NOTE_OWNER;
+
+// Flags, one per active row
+pub(crate) const POSEIDON: usize = super::scalar::END;
+pub(crate) const PATH_FIRST: usize = POSEIDON + 3;
+pub(crate) const PATH: usize = PATH_FIRST + 1;
+pub(crate) const PATH_LAST: usize = PATH + 1;
+/// Decoding the key
+pub(crate) const KEY: usize = PATH_LAST + 1;
+/// Decoding `R` and comparing
+pub(crate) const SIGNATURE: usize = KEY + 1;
+pub(crate) const PEDERSEN: usize = SIGNATURE + 1;
+/// The rows of a note, one per permutation
+pub(crate) const NOTE: usize = PEDERSEN + 1;
+pub(crate) const NOTE_ROWS: usize = 5;
+const FLAGS_END: usize = NOTE + NOTE_ROWS;
+
+// Other boolean columns
+/// Last row of a hash
+pub(crate) const HASH_LAST: usize = FLAGS_END;
+/// The hash row absorbs two elements
+pub(crate) const PAIR: usize = HASH_LAST + 1;
+/// Bit of the position at the row's level
+pub(crate) const BIT: usize = PAIR + 1;
+/// The root is the hash of the path
+pub(crate) const EQUAL: usize = BIT + 1;
+pub(crate) const X_ZERO: usize = EQUAL + 1;
+/// The point decodes
+pub(crate) const DECODES: usize = X_ZERO + 1;
+pub(crate) const EQUAL_X: usize = DECODES + 1;
+pub(crate) const EQUAL_Y: usize = EQUAL_X + 1;
+const BOOLEANS_END: usize = EQUAL_Y + 1;
+
+// Value columns
+pub(crate) const CALL: usize = BOOLEANS_END;
+pub(crate) const PARAM: usize = CALL + 1;
+/// Slot `A`: index, value, whether canonical, sign and parity
+pub(crate) const A: usize = PARAM + 1;
+/// Slot `B`: value, whether canonical and parity
+pub(crate) const B: usize = A + 5;
+/// Slot `C`: index, value, whether canonical and parity
+pub(crate) const C: usize = B + 3;
+pub(crate) const PERMUTATION_IN: usize = C + 4;
+pub(crate) const PERMUTATION_OUT: usize = PERMUTATION_IN + WIDTH;
+pub(crate) const NODE: usize = PERMUTATION_OUT + WIDTH;
+/// `2^level`
+pub(crate) const POWER: usize = NODE + 1;
+pub(crate) const POWER_INV: usize = POWER + 1;
+/// The position's bits up to the level
+pub(crate) const POSITION: usize = POWER_INV + 1;
+pub(crate) const ROOT: usize = POSITION + 1;
+pub(crate) const ROOT_VALID: usize = ROOT + 1;
+pub(crate) const ROOT_INV: usize = ROOT_VALID + 1;
+/// Single words: the position's low or high word, and the output
+pub(crate) const WORDS: usize = ROOT_INV + 1;
+pub(crate) const X_SQUARE: usize = WORDS + 2;
+pub(crate) const X_INV: usize = X_SQUARE + 1;
+/// The encoding is of the identity
+pub(crate) const IDENTITY: usize = X_INV + 1;
+/// The encoding is canonical but does not decode
+pub(crate) const NOT_ON_CURVE: usize = IDENTITY + 1;
+/// Square root of `5 (x^3 + 5)`
+pub(crate) const ROOT_5: usize = NOT_ON_CURVE + 1;
+/// `[c] K`, or `[r] H` of a commitment
+pub(crate) const CHALLENGE_KEY: usize = ROOT_5 + 1;
+/// The key decodes to a point other than the identity
+pub(crate) const KEY_VALID: usize = CHALLENGE_KEY + 3;
+/// `[s] G`, or `[v] G` of a commitment
+pub(crate) const RESPONSE_BASE: usize = KEY_VALID + 1;
+/// Products of [`complete_sum`] for `R + [c] K`, or `[v] G + [r] H`
+pub(crate) const PRODUCTS: usize = RESPONSE_BASE + 3;
+pub(crate) const SUM: usize = PRODUCTS + 6;
+/// Cross products comparing `R + [c] K` and `[s] G`, and their inverses
+pub(crate) const DIFFERENCES: usize = SUM + 3;
+pub(crate) const DIFFERENCE_INVS: usize = DIFFERENCES + 2;
+/// `KEY_VALID * DECODES` and `C_VALID * EQUAL_X * EQUAL_Y`
+pub(crate) const PARTS: usize = DIFFERENCE_INVS + 2;
+/// The point the challenge chain multiplies: the key, or `H`
+pub(crate) const CHAIN_Q: usize = PARTS + 2;
+pub(crate) const END: usize = CHAIN_Q + 2;
+
+pub(crate) const LOOKUPS: usize = 9;
+
+// Slot offsets
+const INDEX: usize = 0;
+const VALUE: usize = 1;
+const VALID: usize = 2;
+const SIGN: usize = 3;
+const PARITY: usize = 4;
+const B_VALUE: usize = B;
+const B_VALID: usize = B + 1;
+const B_PARITY: usize = B + 2;
+const C_INDEX: usize = C;
+const C_VALUE: usize = C + 1;
+const C_VALID: usize = C + 2;
+const C_PARITY: usize = C + 3;
+
+/// Affine coordinates of the Pallas generator
+const GENERATOR: [i64; 2] = [-1, 2];
+
+/// Sums of the flags by role
+struct Roles {
+    hash: [Felt; 3],
+    hash_any: Felt,
+    path_first: Felt,
+    path: Felt,
+    path_last: Felt,
+    merkle: Felt,
+    key: Felt,
+    signature: Felt,
+    pedersen: Felt,
+    note: [Felt; NOTE_ROWS],
+    note_any: Felt,
+    decode: Felt,
+    active: Felt,
+    first: Felt,
+    last: Felt,
+}
+
+fn roles(c: &[Felt]) -> Roles {
+    let hash = [c[POSEIDON], c[POSEIDON + 1], c[POSEIDON + 2]];
+    let hash_any = hash.iter().sum::<Felt>();
+    let (path_first, path, path_last) = (c[PATH_FIRST], c[PATH], c[PATH_LAST]);
+    let (key, signature, pedersen) = (c[KEY], c[SIGNATURE], c[PEDERSEN]);
+    let note: [Felt; NOTE_ROWS] = std::array::from_fn(|i| c[NOTE + i]);
+    Roles {
+        hash,
+        hash_any,
+        path_first,
+        path,
+        path_last,
+        merkle: path_first + path + path_last,
+        key,
+        signature,
+        pedersen,
+        note,
+        note_any: note.iter().sum(),
+        decode: key + signature + pedersen + note[0],
+        active: c[POSEIDON..FLAGS_END].iter().sum(),
+        first: hash[0] + path_first + key + pedersen + note[0],
+        last: c[HASH_LAST] + path_last + signature + pedersen + note[NOTE_ROWS - 1],
+    }
+}
+
+fn constant(value: u64) -> Felt {
+    Felt::from(value)
+}
+
+pub(super) fn evaluate(air: &Air, frame: &Frame, out: &mut impl Constraints) {
+    let (c, next) = (frame.current, frame.next);
+    let one = Felt::ONE;
+    let r = roles(c);
+    let n = roles(next);
+
+    for &bit in &c[POSEIDON..BOOLEANS_END] {
+        out.every("control boolean", bit * (bit - one));
+    }
+
+    // Calls run on a prefix of the rows, one flag per row, each starting
+    // right after the last row of the one before
+    out.every("control active", r.active * (one - r.active));
+    out.first("control active", r.active - r.first);
+    out.last("control active", r.active);
+    out.transition("control active", n.active * (one - r.active));
+    out.transition("control sequence", n.first - r.last * n.active);
+    let hash_last = c[HASH_LAST];
+    out.transition(
+        "control sequence",
+        n.hash[1] - r.hash[0] * (one - hash_last),
+    );
+    out.transition(
+        "control sequence",
+        n.hash[2] - r.hash[1] * (one - hash_last),
+    );
+    out.every("control sequence", r.hash[2] * (one - hash_last));
+    out.every("control sequence", hash_last * (one - r.hash_any));
+    out.transition(
+        "control sequence",
+        n.path + n.path_last - r.path_first - r.path,
+    );
+    out.transition("control sequence", n.signature - r.key);
+    for i in 1..NOTE_ROWS {
+        out.transition("control sequence", n.note[i] - r.note[i - 1]);
+    }
+    out.first("control call", c[CALL]);
+    out.transition("control call", next[CALL] - c[CALL] - n.first);
+    out.transition(
+        "control param",
+        (one - n.first) * n.active * (next[PARAM] - c[PARAM]),
+    );
+    out.every(
+        "control param",
+        (r.path_first + r.key + r.pedersen + r.note[0]) * c[PARAM],
+    );
+
+    // Poseidon: the sponge absorbs elements two at a time
+    let pair = c[PAIR];
+    let (a, b) = (c[A + VALUE], c[B_VALUE]);
+    let (input, output) = (
+        &c[PERMUTATION_IN..PERMUTATION_IN + WIDTH],
+        &c[PERMUTATION_OUT..PERMUTATION_OUT + WIDTH],
+    );
+    out.every(
+        "poseidon inputs",
+        hash_last * (c[PARAM] - one - pair - (r.hash[1] + r.hash[2].double()).double()),
+    );
+    out.every("poseidon inputs", r.hash[2] * pair);
+    out.every(
+        "poseidon inputs",
+        r.hash_any * (one - hash_last) * (one - pair),
+    );
+    out.every("poseidon inputs", pair * (one - r.hash_any));
+    let capacity = air.powers[64];
+    out.every("poseidon absorb", r.hash[0] * (input[0] - a));
+    out.every("poseidon absorb", r.hash[0] * (input[1] - pair * b));
+    out.every(
+        "poseidon absorb",
+        r.hash[0] * (input[2] - c[PARAM] * capacity),
+    );
+    let absorbs = n.hash[1] + n.hash[2];
+    out.transition(
+        "poseidon absorb",
+        absorbs * (next[PERMUTATION_IN] - output[0] - next[A + VALUE]),
+    );
+    out.transition(
+        "poseidon absorb",
+        absorbs * (next[PERMUTATION_IN + 1] - output[1] - next[PAIR] * next[B_VALUE]),
+    );
+    out.transition(
+        "poseidon absorb",
+        absorbs * (next[PERMUTATION_IN + 2] - output[2]),
+    );
+    out.every("poseidon canonical", r.hash_any * (one - c[A + VALID]));
+    out.every("poseidon canonical", pair * (one - c[B_VALID]));
+    out.every(
+        "poseidon output",
+        hash_last
+            * (c[C_INDEX] - constant(POSEIDON_INPUTS) - constant(ELEMENT_WORDS as u64) * c[PARAM]),
+    );
+    out.every("poseidon output", hash_last * (c[C_VALUE] - output[0]));
+    out.every("poseidon output", hash_last * (one - c[C_VALID]));
+
+    // Merkle paths: hash the node with the sibling, in the bit's order
+    let (bit, node) = (c[BIT], c[NODE]);
+    let inner = r.path_first + r.path;
+    out.every("merkle node", r.path_first * (node - b));
+    out.transition("merkle node", inner * (next[NODE] - output[0]));
+    out.every(
+        "merkle hash",
+        r.merkle * (input[0] - node - bit * (a - node)),
+    );
+    out.every("merkle hash", r.merkle * (input[1] - a - bit * (node - a)));
+    out.every(
+        "merkle hash",
+        r.merkle * (input[2] - constant(2) * capacity),
+    );
+    let (power, top) = (c[POWER], air.powers[MERKLE_DEPTH - 1]);
+    out.every("merkle level", r.path_first * (power - one));
+    out.transition("merkle level", inner * (next[POWER] - power.double()));
+    out.every("merkle level", r.path_last * (power - top));
+    out.every("merkle level", inner * ((power - top) * c[POWER_INV] - one));
+    out.every("merkle position", r.path_first * (c[POSITION] - bit));
+    out.transition(
+        "merkle position",
+        inner * (next[POSITION] - c[POSITION] - next[BIT] * next[POWER]),
+    );
+    out.every("merkle root", r.path_first * (c[ROOT] - c[C_VALUE]));
+    out.every("merkle root", r.path_first * (c[ROOT_VALID] - c[C_VALID]));
+    for column in [ROOT, ROOT_VALID] {
+        out.transition("merkle root", inner * (next[column] - c[column]));
+    }
+    let difference = output[0] - c[ROOT];
+    out.every("merkle root", c[EQUAL] * difference);
+    out.every(
+        "merkle root",
+        r.path_last * (difference * c[ROOT_INV] - one + c[EQUAL]),
+    );
+    out.every("merkle output", r.path_last * (c[WORDS] - c[POSITION]));
+    out.every(
+        "merkle output",
+        r.path_last * (c[WORDS + 1] - c[ROOT_VALID] * c[EQUAL]),
+    );
+    out.every(
+        "merkle index",
+        r.path_first * (c[A + INDEX] - constant(MERKLE_PATH)),
+    );
+    out.transition(
+        "merkle index",
+        inner * (next[A + INDEX] - c[A + INDEX] - constant(ELEMENT_WORDS as u64)),
+    );
+    out.every(
+        "merkle index",
+        r.path_first * (c[C_INDEX] - constant(MERKLE_ROOT)),
+    );
+    let index = r.hash[0] * constant(POSEIDON_INPUTS)
+        + r.hash[1] * constant(POSEIDON_INPUTS + 2 * ELEMENT_WORDS as u64)
+        + r.hash[2] * constant(POSEIDON_INPUTS + 4 * ELEMENT_WORDS as u64)
+        + r.key * constant(SIGNATURE_KEY)
+        + r.signature * constant(SIGNATURE_NONCE)
+        + r.pedersen * constant(PEDERSEN_OUTPUT)
+        + r.note[0] * constant(NOTE_OWNER)
+        + r.note[1] * constant(NOTE_RANDOMNESS)
+        + r.note[2] * constant(NOTE_KEY)
+        + r.note[4] * constant(NOTE_NULLIFIER);
+    out.every("control index", (one - r.merkle) * (c[A + INDEX] - index));
+
+    // Notes: cm = Poseidon(value, asset, owner x, owner y, rcm), then
+    // nf = Poseidon(nk, cm, position), each element read as its value mod p
+    let note = r.note;
+    out.every("note owner", note[0] * (one - c[DECODES]));
+    let value = c[WORDS] + air.powers[32] * c[WORDS + 1];
+    out.every("note absorb", note[0] * (input[0] - value));
+    out.every("note absorb", note[0] * (input[1] - c[C_VALUE]));
+    out.every("note absorb", note[0] * (input[2] - constant(5) * capacity));
+    out.transition(
+        "note absorb",
+        (note[0] + note[1] + note[3]) * (next[PERMUTATION_IN + 2] - output[2]),
+    );
+    out.transition(
+        "note absorb",
+        note[0] * (next[PERMUTATION_IN] - output[0] - a),
+    );
+    out.transition(
+        "note absorb",
+        note[0] * (next[PERMUTATION_IN + 1] - output[1] - b),
+    );
+    out.transition(
+        "note absorb",
+        note[1] * (next[PERMUTATION_IN] - output[0] - a),
+    );
+    out.transition(
+        "note absorb",
+        (note[1] + note[3]) * (next[PERMUTATION_IN + 1] - output[1]),
+    );
+    out.transition("note absorb", note[2] * (next[PERMUTATION_IN] - a));
+    out.transition(
+        "note absorb",
+        note[2] * (next[PERMUTATION_IN + 1] - output[0]),
+    );
+    out.transition(
+        "note absorb",
+        note[2] * (next[PERMUTATION_IN + 2] - constant(3) * capacity),
+    );
+    out.transition(
+        "note absorb",
+        note[3] * (next[PERMUTATION_IN] - output[0] - value),
+    );
+    out.every(
+        "note canonical",
+        (note[1] + note[2] + note[4]) * (one - c[A + VALID]),
+    );
+    out.every("note output", note[2] * (c[C_VALUE] - output[0]));
+    out.every("note output", note[2] * (one - c[C_VALID]));
+    out.every("note output", note[4] * (a - output[0]));
+    out.every("note index", note[0] * (c[C_INDEX] - constant(NOTE_ASSET)));
+    out.every(
+        "note index",
+        note[2] * (c[C_INDEX] - constant(NOTE_COMMITMENT)),
+    );
+
+    // Decoding the point x = A with y = B
+    let (x, y) = (a, b);
+    let (x_zero, decodes) = (c[X_ZERO], c[DECODES]);
+    let (identity, sign) = (c[IDENTITY], c[A + SIGN]);
+    out.every("decode", r.decode * (c[X_SQUARE] - x.square()));
+    out.every("decode", r.decode * (x * c[X_INV] - one + x_zero));
+    out.every("decode", x * x_zero);
+    out.every("decode", x_zero * (one - r.decode));
+    out.every(
+        "decode identity",
+        identity - x_zero * (one - sign) * c[A + VALID],
+    );
+    out.every("decode identity", identity * (one - decodes));
+    out.every("decode identity", identity * y);
+    let x_cube = c[X_SQUARE] * x;
+    let five = constant(5);
+    out.every(
+        "decode point",
+        (decodes - identity) * (y.square() - x_cube - five),
+    );
+    out.every("decode point", (decodes - identity) * (c[B_PARITY] - sign));
+    out.every("decode point", decodes * (one - c[A + VALID]));
+    out.every("decode point", decodes * (one - r.decode));
+    out.every(
+        "decode invalid",
+        c[NOT_ON_CURVE] - r.decode * c[A + VALID] * (one - decodes),
+    );
+    out.every(
+        "decode invalid",
+        c[NOT_ON_CURVE] * (c[ROOT_5].square() - five * x_cube - five * five),
+    );
+    out.every("decode canonical", r.decode * (one - c[B_VALID]));
+
+    // Signatures: [s] G = R + [c] K
+    let key = &c[CHALLENGE_KEY..CHALLENGE_KEY + 3];
+    for i in 0..3 {
+        out.transition("signature key", r.key * (next[CHALLENGE_KEY + i] - key[i]));
+    }
+    out.transition(
+        "signature key",
+        r.key * (next[KEY_VALID] - decodes + identity),
+    );
+    let signature = r.signature;
+    out.every(
+        "signature response",
+        signature * (c[C_INDEX] - constant(SIGNATURE_RESPONSE)),
+    );
+    let (point, base) = (
+        [x, y + identity, one - identity],
+        &c[RESPONSE_BASE..RESPONSE_BASE + 3],
+    );
+    for (i, product) in products(&point, key).iter().enumerate() {
+        out.every("signature sum", signature * (c[PRODUCTS + i] - product));
+    }
+    for (i, product) in products(base, key).iter().enumerate() {
+        out.every("pedersen sum", r.pedersen * (c[PRODUCTS + i] - product));
+    }
+    let [m, n_, t, e, f, g] = std::array::from_fn(|i| c[PRODUCTS + i]);
+    let sum = complete_sum(m, n_, t, e, f, g);
+    for (i, value) in sum.iter().enumerate() {
+        out.every(
+            "signature sum",
+            (signature + r.pedersen) * (c[SUM + i] - value),
+        );
+    }
+    let sum = &c[SUM..SUM + 3];
+    let differences = [
+        sum[0] * base[2] - base[0] * sum[2],
+        sum[1] * base[2] - base[1] * sum[2],
+    ];
+    for (i, (difference, equal)) in differences.iter().zip([EQUAL_X, EQUAL_Y]).enumerate() {
+        out.every(
+            "signature compare",
+            signature * (c[DIFFERENCES + i] - difference),
+        );
+        out.every("signature compare", c[equal] * c[DIFFERENCES + i]);
+        out.every(
+            "signature compare",
+            signature * (c[DIFFERENCES + i] * c[DIFFERENCE_INVS + i] - one + c[equal]),
+        );
+    }
+    out.every("signature output", c[PARTS] - c[KEY_VALID] * decodes);
+    out.every(
+        "signature output",
+        c[PARTS + 1] - c[C_VALID] * c[EQUAL_X] * c[EQUAL_Y],
+    );
+    out.every(
+        "signature output",
+        signature * (c[WORDS + 1] - c[PARTS] * c[PARTS + 1]),
+    );
+    for i in 0..2 {
+        out.every("signature chain", r.key * (c[CHAIN_Q + i] - [a, b][i]));
+    }
+
+    // Pedersen commitments: the output C decodes to [v] G + [r] H
+    let pedersen = r.pedersen;
+    out.every("pedersen output", pedersen * (one - decodes));
+    for (i, value) in point.iter().take(2).enumerate() {
+        out.every(
+            "pedersen output",
+            pedersen * (sum[i] * point[2] - value * sum[2]),
+        );
+    }
+    for i in 0..2 {
+        out.every(
+            "pedersen chain",
+            pedersen * (c[CHAIN_Q + i] - air.blinding_base[i]),
+        );
+    }
+    out.every(
+        "pedersen blinding",
+        pedersen * (c[C_INDEX] - constant(PEDERSEN_BLINDING)),
+    );
+    out.every("pedersen blinding", pedersen * (one - c[C_VALID]));
+}
+
+/// The products of [`complete_sum`] adding the projective `p` and `q`
+fn products(p: &[Felt], q: &[Felt]) -> [Felt; 6] {
+    [
+        p[0] * q[0],
+        p[1] * q[1],
+        p[2] * q[2],
+        p[0] * q[1] + q[0] * p[1],
+        p[1] * q[2] + q[1] * p[2],
+        p[0] * q[2] + q[0] * p[2],
+    ]
+}
+
+/// The header, single words, elements, permutations and chains the row
+/// looks up
+pub(super) fn lookups(air: &Air, c: &[Felt], challenges: &Challenges) -> Vec<(Felt, Felt)> {
+    let r = roles(c);
+    let call = c[CALL];
+    let note = r.note;
+    let number = r.hash[0] * constant(SYS_POSEIDON as u64)
+        + r.pedersen * constant(SYS_PEDERSEN_COMMIT as u64)
+        + r.path_first * constant(SYS_MERKLE_VERIFY as u64)
+        + note[0] * constant(SYS_NOTE_NULLIFIER as u64)
+        + r.key * constant(SYS_SIGNATURE_VERIFY as u64);
+    let [poseidon_gas, pedersen_gas, merkle_gas, note_gas, signature_gas] = air.call_costs;
+    let gas = r.hash[0] * poseidon_gas
+        + r.pedersen * pedersen_gas
+        + r.path_first * merkle_gas
+        + note[0] * note_gas
+        + r.key * signature_gas;
+    let header = challenges.denominator(TAG_HEADER, &[call, number, c[PARAM], gas]);
+
+    let word = |index: Felt, value: Felt, output: Felt| {
+        challenges.denominator(TAG_WORD, &[call, index, value, output])
+    };
+    let position = word(
+        r.path_first * constant(MERKLE_POSITION + 1)
+            + r.path_last * constant(MERKLE_POSITION)
+            + note[0] * constant(NOTE_VALUE)
+            + note[3] * constant(NOTE_POSITION),
+        c[WORDS],
+        Felt::ZERO,
+    );
+    let result = word(
+        r.path_last * constant(MERKLE_OUTPUT)
+            + r.signature * constant(SIGNATURE_OUTPUT)
+            + note[0] * constant(NOTE_VALUE + 1)
+            + note[3] * constant(NOTE_POSITION + 1),
+        c[WORDS + 1],
+        r.path_last + r.signature,
+    );
+
+    let element = |index: Felt, value: Felt, code: Felt, valid: Felt, sign: Felt, parity: Felt| {
+        challenges.denominator(
+            TAG_ELEMENT,
+            &[call, index, value, code, valid, sign, parity],
+        )
+    };
+    let a = element(
+        c[A + INDEX],
+        c[A + VALUE],
+        r.hash_any * constant(CODE_CHECK)
+            + r.decode * constant(CODE_CHECK + CODE_POINT)
+            + r.pedersen * constant(CODE_OUTPUT)
+            + (note[1] + note[2]) * constant(CODE_CHECK + CODE_MOD_Q)
+            + note[4] * constant(CODE_CHECK + CODE_OUTPUT),
+        c[A + VALID],
+        c[A + SIGN],
+        c[A + PARITY],
+    );
+    let element_words = constant(ELEMENT_WORDS as u64);
+    let b = element(
+        c[A + INDEX] + r.hash_any * element_words
+            - r.path_first * constant(MERKLE_PATH - MERKLE_LEAF)
+            + (r.key + r.signature) * constant(SIGNATURE_Y)
+            + r.pedersen * constant(PEDERSEN_Y)
+            + note[0] * constant(NOTE_Y),
+        c[B_VALUE],
+        r.hash_any * constant(CODE_CHECK) + r.decode * constant(CODE_CHECK + CODE_VIRTUAL),
+        c[B_VALID],
+        Felt::ZERO,
+        c[B_PARITY],
+    );
+    let hash_last = c[HASH_LAST];
+    let c_slot = element(
+        c[C_INDEX],
+        c[C_VALUE],
+        hash_last * constant(CODE_CHECK + CODE_OUTPUT)
+            + r.path_first * constant(CODE_CHECK)
+            + (r.signature + r.pedersen) * constant(CODE_CHECK + CODE_MOD_Q)
+            + note[2] * constant(CODE_CHECK + CODE_OUTPUT),
+        c[C_VALID],
+        Felt::ZERO,
+        c[C_PARITY],
+    );
+
+    let permutation =
+        challenges.denominator(TAG_PERMUTATION, &c[PERMUTATION_IN..PERMUTATION_OUT + WIDTH]);
+    let chain = |high: Felt, low: Felt, q: [Felt; 2], product: usize| {
+        let mut values = vec![call, high, low, q[0], q[1]];
+        values.extend_from_slice(&c[product..product + 3]);
+        challenges.denominator(TAG_CHAIN, &values)
+    };
+    let words = ELEMENT_WORDS as u64;
+    let generator = GENERATOR.map(|coordinate| {
+        let value = constant(coordinate.unsigned_abs());
+        if coordinate < 0 {
+            -value
+        } else {
+            value
+        }
+    });
+    let (signature, pedersen) = (r.signature, r.pedersen);
+    let response = chain(
+        signature * constant(SIGNATURE_RESPONSE + words - 1)
+            + pedersen * constant(PEDERSEN_VALUE + 1),
+        signature * constant(SIGNATURE_RESPONSE) + pedersen * constant(PEDERSEN_VALUE),
+        generator,
+        RESPONSE_BASE,
+    );
+    let challenge = chain(
+        r.key * constant(SIGNATURE_CHALLENGE + 2 * words - 1)
+            + pedersen * constant(PEDERSEN_BLINDING + words - 1),
+        r.key * constant(SIGNATURE_CHALLENGE) + pedersen * constant(PEDERSEN_BLINDING),
+        [c[CHAIN_Q], c[CHAIN_Q + 1]],
+        CHALLENGE_KEY,
+    );
+
+    vec![
+        (-r.first, header),
+        (-(r.path_first + r.path_last + note[0] + note[3]), position),
+        (-(r.path_last + signature + note[0] + note[3]), result),
+        (-(r.active - note[3]), a),
+        (-(c[PAIR] + r.path_first + r.decode), b),
+        (
+            -(hash_last + r.path_first + signature + pedersen + note[0] + note[2]),
+            c_slot,
+        ),
+        (-(r.hash_any + r.merkle + r.note_any), permutation),
+        (-(signature + pedersen), response),
+        (-(r.key + pedersen), challenge),
+    ]
+}
@@ -0,0 +1,166 @@
+//! Poseidon permutation unit
+//!
+//! Blocks of [`PERIOD`] rows, aligned to the period, each run the
+//! P128Pow5T3 permutation that `privl1-crypto` hashes with: row `r` of a
+//! block holds the state before round `r`, and periodic columns give the
+//! round's constants and whether it is a full round. The last row of a used
+//! block balances a lookup of the block's input and output by the
+//! [control unit](super::control). Unused blocks permute zero.
+
+use halo2_gadgets::poseidon::primitives::{P128Pow5T3, Spec};
+use pasta_curves::group::ff::Field;
+
+use super::{Air, Challenges, Constraints, Frame, PERIOD, TAG_PERMUTATION};
+use crate::field::Felt;
+
+/// Width of the permutation's state
+pub(crate) const WIDTH: usize = 3;
+/// Full rounds before and after the partial rounds
+const HALF_FULL_ROUNDS: usize = 4;
+
+pub(crate) const STATE: usize = super::stream::END;
+/// `a^2` of each S-box input `a`, or 1 where a partial round skips it
+pub(crate) const SQUARE: usize = STATE + WIDTH;
+pub(crate) const SBOX: usize = SQUARE + WIDTH;
+/// The block's input, repeated on each of its rows
+pub(crate) const INPUT: usize = SBOX + WIDTH;
+/// Whether the block is looked up
+pub(crate) const USED: usize = INPUT + WIDTH;
+pub(crate) const END: usize = USED + 1;
+
+// Periodic columns
+const ROUND_CONSTANTS: usize = 0;
+const FULL: usize = ROUND_CONSTANTS + WIDTH;
+const FIRST: usize = FULL + 1;
+const LAST: usize = FIRST + 1;
+pub(crate) const PERIODIC: usize = LAST + 1;
+
+pub(crate) const LOOKUPS: usize = 1;
+
+/// Round constants and MDS matrix of the permutation
+pub(crate) struct Constants {
+    round_constants: Vec<[Felt; WIDTH]>,
+    mds: [[Felt; WIDTH]; WIDTH],
+}
+
+/// The values of one round on its row
+pub(crate) struct Round {
+    pub square: [Felt; WIDTH],
+    pub sbox: [Felt; WIDTH],
+    /// The state after the round
+    pub output: [Felt; WIDTH],
+}
+
+impl Constants {
+    pub fn new() -> Self {
+        let (round_constants, mds, _) = <P128Pow5T3 as Spec<Felt, WIDTH, 2>>::constants();
+        assert_eq!(round_constants.len(), PERIOD, "a round per row");
+        Self {
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Round `round` on `state`
+    pub fn round(&self, round: usize, state: &[Felt; WIDTH]) -> Round {
+        let full = is_full(round);
+        let a: [Felt; WIDTH] = std::array::from_fn(|i| state[i] + self.round_constants[round][i]);
+        let square: [Felt; WIDTH] = std::array::from_fn(|i| {
+            if i == 0 || full {
+                a[i].square()
+            } else {
+                Felt::ONE
+            }
+        });
+        let sbox = std::array::from_fn(|i| square[i].square() * a[i]);
+        Round {
+            square,
+            sbox,
+            output: self.mix(&sbox),
+        }
+    }
+
+    /// The permutation of `state`
+    #[cfg(test)]
+    pub fn permute(&self, state: [Felt; WIDTH]) -> [Felt; WIDTH] {
+        (0..PERIOD).fold(state, |state, round| self.round(round, &state).output)
+    }
+
+    fn mix(&self, values: &[Felt]) -> [Felt; WIDTH] {
+        self.mds
+            .map(|row| row.iter().zip(values).map(|(m, v)| *m * v).sum())
+    }
+}
+
+fn is_full(round: usize) -> bool {
+    !(HALF_FULL_ROUNDS..PERIOD - HALF_FULL_ROUNDS).contains(&round)
+}
+
+/// The periodic columns on row `row` of a block
+pub(crate) fn periodic(constants: &Constants, row: usize) -> Vec<Felt> {
+    let mut values = constants.round_constants[row].to_vec();
+    values.extend([is_full(row), row == 0, row == PERIOD - 1].map(|flag| Felt::from(flag as u64)));
+    values
+}
+
+pub(super) fn evaluate(air: &Air, frame: &Frame, periodic: &[Felt], out: &mut impl Constraints) {
+    let (c, next) = (frame.current, frame.next);
+    let one = Felt::ONE;
+    let (full, first, last) = (periodic[FULL], periodic[FIRST], periodic[LAST]);
+
+    let output = air.permutation.mix(&c[SBOX..SBOX + WIDTH]);
+    for i in 0..WIDTH {
+        let a = c[STATE + i] + periodic[ROUND_CONSTANTS + i];
+        let square = if i == 0 {
+            a.square()
+        } else {
+            full * a.square() + one - full
+        };
+        out.every("poseidon sbox", c[SQUARE + i] - square);
+        out.every("poseidon sbox", c[SBOX + i] - c[SQUARE + i].square() * a);
+        out.every("poseidon input", first * (c[STATE + i] - c[INPUT + i]));
+        out.transition(
+            "poseidon round",
+            (one - last) * (next[STATE + i] - output[i]),
+        );
+        out.transition(
+            "poseidon input",
+            (one - last) * (next[INPUT + i] - c[INPUT + i]),
+        );
+    }
+    let used = c[USED];
+    out.every("poseidon used", used * (used - one));
+    out.transition("poseidon used", (one - last) * (next[USED] - used));
+}
+
+/// The lookup of a used block's input and output, on its last row
+pub(super) fn lookups(
+    air: &Air,
+    c: &[Felt],
+    periodic: &[Felt],
+    challenges: &Challenges,
+) -> Vec<(Felt, Felt)> {
+    let output = air.permutation.mix(&c[SBOX..SBOX + WIDTH]);
+    let mut values = c[INPUT..INPUT + WIDTH].to_vec();
+    values.extend(output);
+    vec![(
+        periodic[LAST] * c[USED],
+        challenges.denominator(TAG_PERMUTATION, &values),
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precompile::poseidon_hash;
+
+    #[test]
+    fn test_permutation_matches_hash() {
+        // A one-element hash is the first word of the permutation of the
+        // element and the padding, under the capacity `1 * 2^64`
+        let constants = Constants::new();
+        let element = Felt::from(5u64);
+        let state = constants.permute([element, Felt::ZERO, crate::field::pow2(64)]);
+        assert_eq!(state[0], poseidon_hash(&[element]));
+    }
+}
@@ -0,0 +1,256 @@
+//! Scalar multiplication unit
+//!
+//! A chain multiplies a Pallas point `Q` by a scalar of whole 32-bit words
+//! of a call's stream, most significant word and bit first, one bit per row:
+//! each row doubles its accumulator and adds `Q` if its bit is set. Points
+//! are projective and use the complete formulas for `a = 0` of Renes,
+//! Costello and Batina, so no case needs a branch. A chain starts from the
+//! identity on a word boundary, looks up each of its words from the stream,
+//! and its result is looked up by the [control unit](super::control) as
+//! `(call, first word, last word, Q, [scalar] Q)`.
+
+use pasta_curves::group::ff::Field;
+
+use super::{Challenges, Constraints, Frame, PERIOD, TAG_CHAIN, TAG_WORD};
+use crate::field::Felt;
+
+/// Bits of a word, and rows of a chain per word
+const WORD_ROWS: usize = 32;
+
+/// `3b` for the curve `y^2 = x^3 + 5`
+const B3: u64 = 15;
+
+// Boolean columns
+pub(crate) const ACTIVE: usize = super::permutation::END;
+/// First row of a chain
+pub(crate) const START: usize = ACTIVE + 1;
+/// Last row of a chain
+pub(crate) const FINISH: usize = START + 1;
+pub(crate) const BIT: usize = FINISH + 1;
+
+// Value columns
+pub(crate) const CALL: usize = BIT + 1;
+/// Stream index of the chain's first, most significant word
+pub(crate) const HIGH: usize = CALL + 1;
+/// Stream index of the row's word
+pub(crate) const INDEX: usize = HIGH + 1;
+pub(crate) const QX: usize = INDEX + 1;
+pub(crate) const QY: usize = QX + 1;
+/// The bits of the row's word so far
+pub(crate) const REM: usize = QY + 1;
+/// Accumulator before the row
+pub(crate) const ACC: usize = REM + 1;
+/// `y^2` and `z^2` of the accumulator
+pub(crate) const SQUARES: usize = ACC + 3;
+/// The doubled accumulator
+pub(crate) const DOUBLE: usize = SQUARES + 2;
+/// Products of [`complete_sum`] adding `Q` to the doubled accumulator
+pub(crate) const PRODUCTS: usize = DOUBLE + 3;
+/// Accumulator after the row
+pub(crate) const OUT: usize = PRODUCTS + 5;
+pub(crate) const END: usize = OUT + 3;
+
+// Periodic columns
+const WORD_FIRST: usize = super::permutation::PERIODIC;
+const WORD_LAST: usize = WORD_FIRST + 1;
+pub(crate) const PERIODIC_END: usize = WORD_LAST + 1;
+
+pub(crate) const LOOKUPS: usize = 2;
+
+/// The periodic columns on row `row` of a period
+pub(crate) fn periodic(row: usize) -> Vec<Felt> {
+    let bit = row % WORD_ROWS;
+    debug_assert!(PERIOD.is_multiple_of(WORD_ROWS));
+    [bit == 0, bit == WORD_ROWS - 1]
+        .map(|flag| Felt::from(flag as u64))
+        .to_vec()
+}
+
+/// The sum of two projective points from the products
+/// `m = x1 x2`, `n = y1 y2`, `t = z1 z2`, `e = x1 y2 + x2 y1`,
+/// `f = y1 z2 + y2 z1` and `g = x1 z2 + x2 z1`
+pub(crate) fn complete_sum(m: Felt, n: Felt, t: Felt, e: Felt, f: Felt, g: Felt) -> [Felt; 3] {
+    let b3 = Felt::from(B3);
+    let (low, high) = (n - b3 * t, n + b3 * t);
+    [
+        e * low - b3 * f * g,
+        low * high + Felt::from(3 * B3) * m * g,
+        high * f + Felt::from(3u64) * m * e,
+    ]
+}
+
+/// `2 P` of the projective point `p`, given `y^2` and `z^2`
+fn double(p: &[Felt], y2: Felt, z2: Felt) -> [Felt; 3] {
+    let (x, y, z) = (p[0], p[1], p[2]);
+    let b3 = Felt::from(B3);
+    let t = y2 - Felt::from(3 * B3) * z2;
+    [
+        Felt::from(2u64) * t * x * y,
+        Felt::from(8 * B3) * y2 * z2 + t * (y2 + b3 * z2),
+        Felt::from(8u64) * y2 * y * z,
+    ]
+}
+
+/// The products of adding the affine `q` to the projective `d`, in the
+/// order of [`PRODUCTS`]: `m`, `e`, `n`, `f`, `g`
+fn mixed_products(d: &[Felt], q: [Felt; 2]) -> [Felt; 5] {
+    let [qx, qy] = q;
+    [
+        qx * d[0],
+        d[0] * qy + qx * d[1],
+        d[1] * qy,
+        qy * d[2] + d[1],
+        qx * d[2] + d[0],
+    ]
+}
+
+/// The values of one row of a chain
+pub(crate) struct Step {
+    pub squares: [Felt; 2],
+    pub double: [Felt; 3],
+    pub products: [Felt; 5],
+    pub out: [Felt; 3],
+}
+
+/// The row doubling `acc` and adding `q` if `bit` is set
+pub(crate) fn step(acc: &[Felt; 3], q: [Felt; 2], bit: bool) -> Step {
+    let squares = [acc[1].square(), acc[2].square()];
+    let double = double(acc, squares[0], squares[1]);
+    let products = mixed_products(&double, q);
+    let [m, e, n, f, g] = products;
+    let out = if bit {
+        complete_sum(m, n, double[2], e, f, g)
+    } else {
+        double
+    };
+    Step {
+        squares,
+        double,
+        products,
+        out,
+    }
+}
+
+pub(super) fn evaluate(frame: &Frame, periodic: &[Felt], out: &mut impl Constraints) {
+    let (c, next) = (frame.current, frame.next);
+    let one = Felt::ONE;
+    let (word_first, word_last) = (periodic[WORD_FIRST], periodic[WORD_LAST]);
+
+    for &bit in &c[ACTIVE..CALL] {
+        out.every("scalar boolean", bit * (bit - one));
+    }
+
+    // Double, then add Q if the bit is set
+    let acc = &c[ACC..ACC + 3];
+    let (y2, z2) = (c[SQUARES], c[SQUARES + 1]);
+    out.every("scalar double", y2 - acc[1].square());
+    out.every("scalar double", z2 - acc[2].square());
+    let double = double(acc, y2, z2);
+    for i in 0..3 {
+        out.every("scalar double", c[DOUBLE + i] - double[i]);
+    }
+    let d = &c[DOUBLE..DOUBLE + 3];
+    let products = mixed_products(d, [c[QX], c[QY]]);
+    for (i, product) in products.iter().enumerate() {
+        out.every("scalar add", c[PRODUCTS + i] - product);
+    }
+    let [m, e, n, f, g] = std::array::from_fn(|i| c[PRODUCTS + i]);
+    let sum = complete_sum(m, n, d[2], e, f, g);
+    let bit = c[BIT];
+    for i in 0..3 {
+        out.every("scalar add", c[OUT + i] - d[i] - bit * (sum[i] - d[i]));
+    }
+
+    // Chains start from the identity and carry their accumulator and point
+    let (active, start, finish) = (c[ACTIVE], c[START], c[FINISH]);
+    out.every("scalar start", start * acc[0]);
+    out.every("scalar start", start * (acc[1] - one));
+    out.every("scalar start", start * acc[2]);
+    let continues = next[ACTIVE] - next[START];
+    for i in 0..3 {
+        out.transition("scalar chain", continues * (next[ACC + i] - c[OUT + i]));
+    }
+    for column in [QX, QY, CALL, HIGH] {
+        out.transition("scalar chain", continues * (next[column] - c[column]));
+    }
+    out.transition(
+        "scalar chain",
+        continues * (next[INDEX] - c[INDEX] + word_last),
+    );
+    out.every("scalar start", start * (c[INDEX] - c[HIGH]));
+
+    // Words, most significant bit first
+    out.every("scalar word", word_first * (c[REM] - bit));
+    out.transition(
+        "scalar word",
+        (one - word_last) * (next[REM] - c[REM].double() - next[BIT]),
+    );
+
+    // Chains of whole words, on a prefix of the rows
+    out.every("scalar start", start * (one - word_first));
+    out.every("scalar finish", finish * (one - word_last));
+    out.every("scalar finish", finish * (one - active));
+    out.transition("scalar start", next[START] - next[ACTIVE] + active - finish);
+    out.transition("scalar active", next[ACTIVE] * (one - active));
+    out.first("scalar start", start - active);
+    out.last("scalar active", active);
+}
+
+/// The lookups of each word of a chain and of its result
+pub(super) fn lookups(c: &[Felt], periodic: &[Felt], challenges: &Challenges) -> Vec<(Felt, Felt)> {
+    let word = challenges.denominator(TAG_WORD, &[c[CALL], c[INDEX], c[REM], Felt::ZERO]);
+    let mut values = vec![c[CALL], c[HIGH], c[INDEX], c[QX], c[QY]];
+    values.extend_from_slice(&c[OUT..OUT + 3]);
+    vec![
+        (-c[ACTIVE] * periodic[WORD_LAST], word),
+        (c[FINISH], challenges.denominator(TAG_CHAIN, &values)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precompile::coordinates;
+    use pasta_curves::arithmetic::CurveAffine;
+    use pasta_curves::group::{Curve, Group};
+    use pasta_curves::pallas;
+
+    /// The affine point of projective `p`
+    fn affine(p: &[Felt; 3]) -> pallas::Point {
+        if p[2] == Felt::ZERO {
+            return pallas::Point::identity();
+        }
+        let z = p[2].invert().unwrap();
+        let point = pallas::Affine::from_xy(p[0] * z, p[1] * z).unwrap();
+        pallas::Point::from(point)
+    }
+
+    #[test]
+    fn test_chain_multiplies() {
+        let q = pallas::Point::generator() * pallas::Scalar::from(12345u64);
+        let (qx, qy) = coordinates(&q);
+        let scalar = 0xdead_beefu64;
+        let mut acc = [Felt::ZERO, Felt::ONE, Felt::ZERO];
+        for i in (0..32).rev() {
+            acc = step(&acc, [qx, qy], (scalar >> i) & 1 == 1).out;
+        }
+        assert_eq!(
+            affine(&acc).to_affine(),
+            (q * pallas::Scalar::from(scalar)).to_affine()
+        );
+    }
+
+    #[test]
+    fn test_complete_sum_handles_doubling_and_identity() {
+        let p = pallas::Point::generator() * pallas::Scalar::from(7u64);
+        let (x, y) = coordinates(&p);
+        // P + P through the general formulas
+        let sum = complete_sum(x * x, y * y, Felt::ONE, x * y + x * y, y + y, x + x);
+        assert_eq!(affine(&sum).to_affine(), p.double().to_affine());
+        // P + (-P) is the identity
+        let sum = complete_sum(x * x, -y * y, Felt::ONE, x * (-y) + x * y, y - y, x + x);
+        assert_eq!(sum[0], Felt::ZERO);
+        assert_eq!(sum[2], Felt::ZERO);
+        assert_ne!(sum[1], Felt::ZERO);
+    }
+}
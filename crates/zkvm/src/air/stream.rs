@@ -0,0 +1,204 @@
+//! Precompile stream unit
+//!
+//! One row per word a precompile call passes through `ecall`, in the order
+//! the guest makes them: the header with the call's parameter at index 0,
+//! then its inputs and outputs at increasing indices. Rows balance the CPU's
+//! lookup of every non-exit `ecall` by the count of `ecall`s before it, so
+//! the stream holds exactly the guest's words, and each row is looked up
+//! once by the unit that uses it: the header by the
+//! [control unit](super::control), single words by the control or
+//! [scalar](super::scalar) units, and runs of 8 words by the control unit as
+//! a little-endian element.
+//!
+//! An element row may be checked against `p - 1`, or `q - 1` for scalars,
+//! and a point's top bit is its sign, so a lookup of an element carries its
+//! value, whether it is canonical, its sign and the parity of its low word.
+//! Calls that decode points end with virtual elements, the `y` coordinates
+//! of the points, which are checked canonical like the others but are not
+//! `ecall`s.
+
+use pasta_curves::group::ff::Field;
+
+use super::{Air, Challenges, Constraints, Frame, TAG_CALL, TAG_ELEMENT, TAG_HEADER, TAG_WORD};
+use crate::field::Felt;
+use crate::precompile::ELEMENT_WORDS;
+
+// Boolean columns
+pub(crate) const HEADER: usize = super::TABLE_END;
+/// The row is one of the 8 words of an element
+pub(crate) const ELEMENT: usize = HEADER + 1;
+/// The row is looked up as a single word
+pub(crate) const SINGLE: usize = ELEMENT + 1;
+/// The row is an `ecall`, not a virtual element
+pub(crate) const REAL: usize = SINGLE + 1;
+pub(crate) const OUTPUT: usize = REAL + 1;
+/// One-hot position of the word in its element
+pub(crate) const POSITION: usize = OUTPUT + 1;
+/// The element is checked canonical
+pub(crate) const CHECK: usize = POSITION + ELEMENT_WORDS;
+/// The element is a scalar, checked against `q - 1`
+pub(crate) const MOD_Q: usize = CHECK + 1;
+/// The element is a point, whose top bit is its sign
+pub(crate) const POINT: usize = MOD_Q + 1;
+pub(crate) const SIGN: usize = POINT + 1;
+/// Borrow out of the word of `bound - value`
+pub(crate) const BORROW: usize = SIGN + 1;
+pub(crate) const BITS: usize = BORROW + 1;
+/// The word of `bound - value`
+pub(crate) const CHECK_BITS: usize = BITS + 32;
+const BOOLEANS_END: usize = CHECK_BITS + 32;
+
+// Value columns
+pub(crate) const ACTIVE: usize = BOOLEANS_END;
+/// `ecall`s before the row
+pub(crate) const ECALL: usize = ACTIVE + 1;
+/// Calls before the row's call
+pub(crate) const CALL: usize = ECALL + 1;
+pub(crate) const INDEX: usize = CALL + 1;
+pub(crate) const NUMBER: usize = INDEX + 1;
+/// `a0` of the `ecall`
+pub(crate) const IN: usize = NUMBER + 1;
+/// Result of the `ecall`
+pub(crate) const OUT: usize = IN + 1;
+pub(crate) const WORD: usize = OUT + 1;
+pub(crate) const GAS: usize = WORD + 1;
+/// The element's words so far
+pub(crate) const VALUE: usize = GAS + 1;
+pub(crate) const PARITY: usize = VALUE + 1;
+pub(crate) const BORROW_IN: usize = PARITY + 1;
+pub(crate) const END: usize = BORROW_IN + 1;
+
+pub(crate) const LOOKUPS: usize = 4;
+
+/// Bits of an element code, the kind of element a lookup expects
+pub(crate) const CODE_CHECK: u64 = 1;
+pub(crate) const CODE_MOD_Q: u64 = 2;
+pub(crate) const CODE_POINT: u64 = 4;
+pub(crate) const CODE_OUTPUT: u64 = 8;
+pub(crate) const CODE_VIRTUAL: u64 = 16;
+
+pub(super) fn evaluate(air: &Air, frame: &Frame, out: &mut impl Constraints) {
+    let (c, next) = (frame.current, frame.next);
+    let one = Felt::ONE;
+    let position = |k: usize| c[POSITION + k];
+
+    for &bit in &c[HEADER..BOOLEANS_END] {
+        out.every("stream boolean", bit * (bit - one));
+    }
+    let (header, element, single, real, output) =
+        (c[HEADER], c[ELEMENT], c[SINGLE], c[REAL], c[OUTPUT]);
+    let active = c[ACTIVE];
+    out.every(
+        "stream active",
+        active - header - element - single + element * single,
+    );
+    out.every("stream header", header * element);
+    out.every("stream header", header * single);
+    out.every("stream header", header * output);
+    out.every("stream real", real * (one - active));
+    out.every("stream real", (active - real) * (header + single + output));
+
+    // Words pass through the ecall: inputs from a0, outputs to it
+    let word = c[WORD];
+    out.every("stream word", word - air.combine(&c[BITS..BITS + 32]));
+    out.every("stream word", (one - output) * (word - c[IN]));
+    out.every("stream word", output * (word - c[OUT]));
+    out.every("stream word", (one - output) * c[OUT]);
+    out.every("stream gas", c[GAS] * (one - header));
+    out.every("stream index", header * c[INDEX]);
+    let continues = next[ACTIVE] * (one - next[HEADER]);
+    out.transition("stream index", continues * (next[INDEX] - c[INDEX] - one));
+    out.transition("stream number", continues * (next[NUMBER] - c[NUMBER]));
+
+    out.first("stream ecall", c[ECALL]);
+    out.transition("stream ecall", next[ECALL] - c[ECALL] - real);
+    out.first("stream call", c[CALL]);
+    out.transition("stream call", next[CALL] - c[CALL] - next[HEADER]);
+    out.first("stream active", active - header);
+    out.transition("stream active", next[ACTIVE] * (one - active));
+    out.last("stream active", active);
+
+    // Elements: runs of 8 words sharing their kind, summed into a value
+    let last = position(ELEMENT_WORDS - 1);
+    let within = element - last;
+    out.every(
+        "stream element",
+        c[POSITION..POSITION + ELEMENT_WORDS].iter().sum::<Felt>() - element,
+    );
+    for k in 0..ELEMENT_WORDS - 1 {
+        out.transition("stream element", next[POSITION + k + 1] - position(k));
+    }
+    for column in [CHECK, MOD_Q, POINT, OUTPUT, REAL, PARITY] {
+        out.transition("stream element", within * (next[column] - c[column]));
+    }
+    out.every("stream sign", c[SIGN] - c[POINT] * last * c[BITS + 31]);
+    out.every("stream parity", position(0) * (c[PARITY] - c[BITS]));
+    let sign_weight = air.powers[31];
+    let x = word - sign_weight * c[SIGN];
+    let next_x = next[WORD] - sign_weight * next[SIGN];
+    out.every("stream value", position(0) * (c[VALUE] - x));
+    for k in 0..ELEMENT_WORDS - 1 {
+        out.transition(
+            "stream value",
+            position(k) * (next[VALUE] - c[VALUE] - next_x * air.word_powers[k + 1]),
+        );
+    }
+
+    // Canonical elements: the borrow out of `bound - value` word by word
+    let (check, mod_q, borrow) = (c[CHECK], c[MOD_Q], c[BORROW]);
+    let [p, q] = &air.bounds;
+    let bound = (0..ELEMENT_WORDS)
+        .map(|k| position(k) * (p[k] + mod_q * (q[k] - p[k])))
+        .sum::<Felt>();
+    out.every(
+        "stream canonical",
+        check
+            * (air.combine(&c[CHECK_BITS..CHECK_BITS + 32]) - bound + x + c[BORROW_IN]
+                - air.powers[32] * borrow),
+    );
+    out.every("stream canonical", position(0) * c[BORROW_IN]);
+    out.transition("stream canonical", within * (next[BORROW_IN] - borrow));
+    out.every("stream canonical", (one - check) * borrow);
+    out.every("stream canonical", check * (one - element));
+}
+
+/// The code of the row's element, as in the lookups that expect it
+fn code(c: &[Felt]) -> Felt {
+    [
+        (CHECK, CODE_CHECK),
+        (MOD_Q, CODE_MOD_Q),
+        (POINT, CODE_POINT),
+        (OUTPUT, CODE_OUTPUT),
+    ]
+    .iter()
+    .map(|&(column, bit)| c[column] * Felt::from(bit))
+    .sum::<Felt>()
+        + (c[ACTIVE] - c[REAL]) * Felt::from(CODE_VIRTUAL)
+}
+
+/// The lookups balancing the CPU's `ecall`s, and the ones the words of each
+/// call balance
+pub(super) fn lookups(c: &[Felt], challenges: &Challenges) -> Vec<(Felt, Felt)> {
+    let call = challenges.denominator(TAG_CALL, &[c[ECALL], c[NUMBER], c[IN], c[OUT], c[GAS]]);
+    let header = challenges.denominator(TAG_HEADER, &[c[CALL], c[NUMBER], c[WORD], c[GAS]]);
+    let word = challenges.denominator(TAG_WORD, &[c[CALL], c[INDEX], c[WORD], c[OUTPUT]]);
+    let last = c[POSITION + ELEMENT_WORDS - 1];
+    let element = challenges.denominator(
+        TAG_ELEMENT,
+        &[
+            c[CALL],
+            c[INDEX] - Felt::from(ELEMENT_WORDS as u64 - 1),
+            c[VALUE],
+            code(c),
+            Felt::ONE - c[BORROW],
+            c[SIGN],
+            c[PARITY],
+        ],
+    );
+    vec![
+        (c[REAL], call),
+        (c[HEADER], header),
+        (c[SINGLE], word),
+        (last, element),
+    ]
+}
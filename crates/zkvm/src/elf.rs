@@ -0,0 +1,293 @@
+//! ELF32 program loading
+//!
+//! [`Program::from_elf`] reads the loadable segments of a little-endian
+//! RISC-V executable, as produced for `riscv32im-unknown-none-elf`. Section
+//! headers and symbols are ignored. Executables built with the compressed
+//! extension are rejected, since the interpreter only runs 32-bit
+//! instructions.
+
+use crate::memory::Memory;
+use crate::{Result, ZkvmError};
+
+const ELF_MAGIC: [u8; 4] = *b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u32 = 1;
+const ET_EXEC: u16 = 2;
+const EM_RISCV: u16 = 243;
+const EF_RISCV_RVC: u32 = 0x1;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 0x1;
+
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+
+/// A loadable segment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// Virtual address of the first byte
+    pub address: u32,
+    /// Bytes from the file; the rest of the segment is zero
+    pub data: Vec<u8>,
+    /// Size in memory, at least `data.len()`
+    pub size: u32,
+    pub executable: bool,
+}
+
+impl Segment {
+    /// One past the last address of the segment
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.size as u64
+    }
+}
+
+/// An executable: its entry point and segments, sorted by address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+}
+
+impl Program {
+    /// Parse an ELF32 RISC-V executable
+    pub fn from_elf(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < EHDR_SIZE || bytes[..4] != ELF_MAGIC {
+            return Err(invalid("not an ELF file"));
+        }
+        if bytes[4] != ELFCLASS32 || bytes[5] != ELFDATA2LSB {
+            return Err(invalid("not a little-endian 32-bit ELF file"));
+        }
+        if read_u32(bytes, 20) != EV_CURRENT || read_u16(bytes, 16) != ET_EXEC {
+            return Err(invalid("not an executable"));
+        }
+        if read_u16(bytes, 18) != EM_RISCV {
+            return Err(invalid("not a RISC-V executable"));
+        }
+        if read_u32(bytes, 36) & EF_RISCV_RVC != 0 {
+            return Err(invalid("compressed instructions are not supported"));
+        }
+
+        let entry = read_u32(bytes, 24);
+        let phoff = read_u32(bytes, 28) as usize;
+        let phentsize = read_u16(bytes, 42) as usize;
+        let phnum = read_u16(bytes, 44) as usize;
+        if phnum > 0 && phentsize != PHDR_SIZE {
+            return Err(invalid("unexpected program header size"));
+        }
+        if phoff
+            .checked_add(phnum * PHDR_SIZE)
+            .is_none_or(|end| end > bytes.len())
+        {
+            return Err(invalid("program headers out of bounds"));
+        }
+
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let header = &bytes[phoff + i * PHDR_SIZE..][..PHDR_SIZE];
+            if read_u32(header, 0) != PT_LOAD {
+                continue;
+            }
+
+            let offset = read_u32(header, 4) as usize;
+            let address = read_u32(header, 8);
+            let file_size = read_u32(header, 16) as usize;
+            let size = read_u32(header, 20);
+            if file_size > size as usize {
+                return Err(invalid("segment file size exceeds its memory size"));
+            }
+            if offset
+                .checked_add(file_size)
+                .is_none_or(|end| end > bytes.len())
+            {
+                return Err(invalid("segment data out of bounds"));
+            }
+            if size == 0 {
+                continue;
+            }
+
+            let segment = Segment {
+                address,
+                data: bytes[offset..offset + file_size].to_vec(),
+                size,
+                executable: read_u32(header, 24) & PF_X != 0,
+            };
+            if segment.end() > 1 << 32 {
+                return Err(invalid("segment wraps the address space"));
+            }
+            segments.push(segment);
+        }
+
+        segments.sort_by_key(|segment| segment.address);
+        if segments
+            .windows(2)
+            .any(|pair| pair[0].end() > pair[1].address as u64)
+        {
+            return Err(invalid("segments overlap"));
+        }
+        if !entry.is_multiple_of(4) {
+            return Err(invalid("misaligned entry point"));
+        }
+        if !segments.iter().any(|segment| {
+            segment.executable && (segment.address..=(segment.end() - 1) as u32).contains(&entry)
+        }) {
+            return Err(invalid("entry point is not in an executable segment"));
+        }
+
+        Ok(Self { entry, segments })
+    }
+
+    /// Commitment to the entry point and segments, identifying the program
+    /// in proofs of its execution
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new_derive_key("privl1-zkvm program");
+        hasher.update(&self.entry.to_le_bytes());
+        hasher.update(&(self.segments.len() as u64).to_le_bytes());
+        for segment in &self.segments {
+            hasher.update(&segment.address.to_le_bytes());
+            hasher.update(&segment.size.to_le_bytes());
+            hasher.update(&[segment.executable as u8]);
+            hasher.update(&(segment.data.len() as u64).to_le_bytes());
+            hasher.update(&segment.data);
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Copy the segments into `memory`
+    pub fn load_into(&self, memory: &mut Memory) -> Result<()> {
+        for segment in &self.segments {
+            memory.write_bytes(segment.address, &segment.data)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> ZkvmError {
+    ZkvmError::InvalidElf(reason.into())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Width;
+
+    /// `(p_vaddr, data, p_memsz, p_flags)` of each program header
+    type Segments<'a> = &'a [(u32, &'a [u8], u32, u32)];
+
+    /// Build an ELF32 RISC-V executable
+    fn elf(entry: u32, segments: Segments) -> Vec<u8> {
+        let mut bytes = vec![0u8; EHDR_SIZE];
+        bytes[..4].copy_from_slice(&ELF_MAGIC);
+        bytes[4] = ELFCLASS32;
+        bytes[5] = ELFDATA2LSB;
+        bytes[6] = 1;
+        bytes[16..18].copy_from_slice(&ET_EXEC.to_le_bytes());
+        bytes[18..20].copy_from_slice(&EM_RISCV.to_le_bytes());
+        bytes[20..24].copy_from_slice(&EV_CURRENT.to_le_bytes());
+        bytes[24..28].copy_from_slice(&entry.to_le_bytes());
+        bytes[28..32].copy_from_slice(&(EHDR_SIZE as u32).to_le_bytes());
+        bytes[40..42].copy_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+        bytes[42..44].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        bytes[44..46].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+
+        let mut offset = EHDR_SIZE + segments.len() * PHDR_SIZE;
+        for (address, data, size, flags) in segments {
+            for field in [
+                PT_LOAD,
+                offset as u32,
+                *address,
+                *address,
+                data.len() as u32,
+                *size,
+                *flags,
+                4,
+            ] {
+                bytes.extend(field.to_le_bytes());
+            }
+            offset += data.len();
+        }
+        for (_, data, _, _) in segments {
+            bytes.extend(*data);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_load_segments() {
+        let text = [0x13, 0x05, 0xa0, 0x02];
+        let bytes = elf(
+            0x1_0000,
+            &[(0x2_0000, &[1, 2, 3], 16, 0x6), (0x1_0000, &text, 4, 0x5)],
+        );
+        let program = Program::from_elf(&bytes).unwrap();
+
+        assert_eq!(program.entry, 0x1_0000);
+        assert_eq!(program.segments.len(), 2);
+        assert_eq!(program.segments[0].address, 0x1_0000);
+        assert!(program.segments[0].executable);
+        assert!(!program.segments[1].executable);
+        assert_eq!(program.segments[1].end(), 0x2_0010);
+
+        let mut memory = Memory::new();
+        program.load_into(&mut memory).unwrap();
+        assert_eq!(memory.load(0x1_0000, Width::Word).unwrap(), 0x02a0_0513);
+        assert_eq!(memory.read_bytes(0x2_0000, 5).unwrap(), vec![1, 2, 3, 0, 0]);
+
+        let mut patched = program.clone();
+        patched.segments[1].data[0] = 4;
+        assert_ne!(patched.hash(), program.hash());
+        patched.segments[1].data[0] = 1;
+        patched.segments[1].size += 1;
+        assert_ne!(patched.hash(), program.hash());
+    }
+
+    #[test]
+    fn test_invalid_headers_rejected() {
+        let valid = elf(0x1000, &[(0x1000, &[0; 4], 4, PF_X)]);
+        assert!(Program::from_elf(&valid).is_ok());
+
+        let corrupt = |offset: usize, value: u8| {
+            let mut bytes = valid.clone();
+            bytes[offset] = value;
+            Program::from_elf(&bytes)
+        };
+        assert!(corrupt(0, 0).is_err()); // magic
+        assert!(corrupt(4, 2).is_err()); // 64-bit
+        assert!(corrupt(5, 2).is_err()); // big-endian
+        assert!(corrupt(16, 3).is_err()); // shared object
+        assert!(corrupt(18, 62).is_err()); // x86-64
+        assert!(corrupt(36, 1).is_err()); // RVC
+        assert!(Program::from_elf(&valid[..40]).is_err());
+        assert!(Program::from_elf(&valid[..valid.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_layout_rejected() {
+        // Overlapping segments
+        let bytes = elf(
+            0x1000,
+            &[(0x1000, &[0; 8], 8, PF_X), (0x1004, &[0; 4], 4, 0)],
+        );
+        assert!(Program::from_elf(&bytes).is_err());
+
+        // Entry outside any executable segment, or misaligned
+        assert!(Program::from_elf(&elf(0x2000, &[(0x1000, &[0; 4], 4, PF_X)])).is_err());
+        assert!(Program::from_elf(&elf(0x1000, &[(0x1000, &[0; 4], 4, 0)])).is_err());
+        assert!(Program::from_elf(&elf(0x1002, &[(0x1000, &[0; 4], 4, PF_X)])).is_err());
+
+        // File size larger than memory size, or wrapping past 2^32
+        assert!(Program::from_elf(&elf(0x1000, &[(0x1000, &[0; 8], 4, PF_X)])).is_err());
+        assert!(Program::from_elf(&elf(
+            0x1000,
+            &[(0x1000, &[0; 4], 4, PF_X), (0xffff_fff0, &[], 32, 0)]
+        ))
+        .is_err());
+    }
+}
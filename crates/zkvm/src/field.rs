@@ -0,0 +1,204 @@
+//! Field arithmetic for the STARK backend
+//!
+//! Traces are encoded over the Pallas base field. Its multiplicative group
+//! has a subgroup of order 2^32, so trace columns are interpolated and
+//! extended with radix-2 FFTs over power-of-two subgroups and their cosets.
+
+use pasta_curves::group::ff::{Field, PrimeField};
+use pasta_curves::pallas;
+
+/// Trace field element
+pub type Felt = pallas::Base;
+
+/// Generator of the subgroup of order `n`, a power of two
+pub fn root_of_unity(n: usize) -> Felt {
+    assert!(
+        n.is_power_of_two() && n.trailing_zeros() <= Felt::S,
+        "no subgroup of order {n}"
+    );
+    let mut root = Felt::ROOT_OF_UNITY;
+    for _ in n.trailing_zeros()..Felt::S {
+        root = root.square();
+    }
+    root
+}
+
+/// Shift of the coset the trace is extended over; not in any power-of-two
+/// subgroup
+pub fn coset_shift() -> Felt {
+    Felt::MULTIPLICATIVE_GENERATOR
+}
+
+/// Evaluate the polynomial with `values` as coefficients at the powers of
+/// `root`, a root of unity of order `values.len()`, in place
+pub fn fft(values: &mut [Felt], root: Felt) {
+    let n = values.len();
+    assert!(n.is_power_of_two(), "FFT size {n} is not a power of two");
+    if n == 1 {
+        return;
+    }
+
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut twiddles = Vec::with_capacity(n / 2);
+    let mut len = 2;
+    while len <= n {
+        let step = root.pow_vartime([(n / len) as u64]);
+        twiddles.clear();
+        twiddles.extend(std::iter::successors(Some(Felt::ONE), |w| Some(*w * step)).take(len / 2));
+        for chunk in values.chunks_mut(len) {
+            let (low, high) = chunk.split_at_mut(len / 2);
+            for ((a, b), w) in low.iter_mut().zip(high).zip(&twiddles) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+            }
+        }
+        len *= 2;
+    }
+}
+
+/// Inverse of [`fft`]: coefficients of the polynomial taking `values` at the
+/// powers of `root`
+pub fn ifft(values: &mut [Felt], root: Felt) {
+    fft(values, root.invert().expect("roots of unity are nonzero"));
+    let n_inv = Felt::from(values.len() as u64)
+        .invert()
+        .expect("size is nonzero");
+    for value in values.iter_mut() {
+        *value *= n_inv;
+    }
+}
+
+/// Evaluations of the polynomial with `coefficients` on the coset
+/// `shift * <w>` of order `size`
+pub fn coset_evaluate(coefficients: &[Felt], shift: Felt, size: usize) -> Vec<Felt> {
+    assert!(coefficients.len() <= size, "degree exceeds the domain");
+    let mut values = vec![Felt::ZERO; size];
+    let mut power = Felt::ONE;
+    for (value, coefficient) in values.iter_mut().zip(coefficients) {
+        *value = *coefficient * power;
+        power *= shift;
+    }
+    fft(&mut values, root_of_unity(size));
+    values
+}
+
+/// Coefficients of the polynomial taking `values` on the coset `shift * <w>`
+pub fn coset_interpolate(values: &[Felt], shift: Felt) -> Vec<Felt> {
+    let mut coefficients = values.to_vec();
+    ifft(&mut coefficients, root_of_unity(values.len()));
+    let shift_inv = shift.invert().expect("shift is nonzero");
+    let mut power = Felt::ONE;
+    for coefficient in coefficients.iter_mut() {
+        *coefficient *= power;
+        power *= shift_inv;
+    }
+    coefficients
+}
+
+/// Evaluate the polynomial with `coefficients` at `x`
+pub fn evaluate(coefficients: &[Felt], x: Felt) -> Felt {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Felt::ZERO, |acc, coefficient| acc * x + coefficient)
+}
+
+/// Invert every element in place with a single field inversion
+///
+/// Panics if an element is zero.
+pub fn batch_invert(values: &mut [Felt]) {
+    let mut products = Vec::with_capacity(values.len());
+    let mut product = Felt::ONE;
+    for value in values.iter() {
+        products.push(product);
+        product *= value;
+    }
+    let mut inverse = product.invert().expect("batch inversion of zero");
+    for (value, prefix) in values.iter_mut().zip(products).rev() {
+        let next = inverse * *value;
+        *value = inverse * prefix;
+        inverse = next;
+    }
+}
+
+/// `2^exponent`
+pub fn pow2(exponent: u32) -> Felt {
+    Felt::from(2u64).pow_vartime([exponent as u64])
+}
+
+/// Canonical encoding
+pub fn to_bytes(value: &Felt) -> [u8; 32] {
+    value.to_repr()
+}
+
+/// Decode a canonical encoding
+pub fn from_bytes(bytes: &[u8; 32]) -> Option<Felt> {
+    Felt::from_repr(*bytes).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_of_unity_order() {
+        let root = root_of_unity(16);
+        assert_eq!(root.pow_vartime([16]), Felt::ONE);
+        assert_ne!(root.pow_vartime([8]), Felt::ONE);
+        assert_eq!(root_of_unity(1), Felt::ONE);
+    }
+
+    #[test]
+    fn test_fft_round_trip() {
+        let coefficients: Vec<Felt> = (1..=8u64).map(Felt::from).collect();
+        let root = root_of_unity(8);
+        let mut values = coefficients.clone();
+        fft(&mut values, root);
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(
+                *value,
+                evaluate(&coefficients, root.pow_vartime([i as u64]))
+            );
+        }
+
+        ifft(&mut values, root);
+        assert_eq!(values, coefficients);
+    }
+
+    #[test]
+    fn test_coset_extension() {
+        let coefficients: Vec<Felt> = (0..4u64).map(|i| Felt::from(i * i + 1)).collect();
+        let shift = coset_shift();
+        let values = coset_evaluate(&coefficients, shift, 16);
+        let root = root_of_unity(16);
+        assert_eq!(
+            values[5],
+            evaluate(&coefficients, shift * root.pow_vartime([5]))
+        );
+
+        let mut recovered = coset_interpolate(&values, shift);
+        assert!(recovered[4..].iter().all(|c| bool::from(c.is_zero())));
+        recovered.truncate(4);
+        assert_eq!(recovered, coefficients);
+    }
+
+    #[test]
+    fn test_batch_invert_and_encoding() {
+        let mut values: Vec<Felt> = (1..6u64).map(Felt::from).collect();
+        let expected: Vec<Felt> = values.iter().map(|v| v.invert().unwrap()).collect();
+        batch_invert(&mut values);
+        assert_eq!(values, expected);
+
+        assert_eq!(pow2(64), Felt::from(u64::MAX) + Felt::ONE);
+        assert_eq!(from_bytes(&to_bytes(&values[2])), Some(values[2]));
+        assert_eq!(from_bytes(&[0xff; 32]), None);
+    }
+}
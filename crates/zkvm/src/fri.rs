@@ -0,0 +1,268 @@
+//! FRI low-degree test
+//!
+//! Proves that evaluations over a coset are close to a polynomial of degree
+//! below a power-of-two bound. Each round commits to the current layer,
+//! draws a challenge `z` and folds the evaluations at `x` and `-x` into
+//! `f'(x^2) = (f(x) + f(-x)) / 2 + z * (f(x) - f(-x)) / 2x`, halving the
+//! degree and the domain, until the polynomial is a constant sent in the
+//! clear. A query checks one folding path through every layer.
+
+use pasta_curves::group::ff::Field;
+use serde::{Deserialize, Serialize};
+
+use crate::field::{self, Felt};
+use crate::merkle::{self, Digest, MerkleTree};
+use crate::transcript::Transcript;
+use crate::{Result, ZkvmError};
+
+/// Layer commitments and the final constant
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriProof {
+    pub roots: Vec<Digest>,
+    pub final_value: [u8; 32],
+}
+
+/// Opening of one layer at a query: the folded pair and its Merkle path
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriOpening {
+    pub values: [[u8; 32]; 2],
+    pub path: Vec<Digest>,
+}
+
+/// Committed FRI layers, kept to answer queries
+pub(crate) struct FriProver {
+    /// Evaluations of each committed layer and their tree, whose leaf `j`
+    /// holds the pair at `j` and `j + len / 2`
+    layers: Vec<(Vec<Felt>, MerkleTree)>,
+    final_value: Felt,
+}
+
+impl FriProver {
+    /// Commit to `values`, evaluations over the coset `shift * <w>` of a
+    /// polynomial of degree below `degree_bound`
+    pub fn commit(
+        values: Vec<Felt>,
+        shift: Felt,
+        degree_bound: usize,
+        transcript: &mut Transcript,
+    ) -> Self {
+        assert!(degree_bound.is_power_of_two() && degree_bound < values.len());
+
+        let mut layers = Vec::new();
+        let mut values = values;
+        let mut shift = shift;
+        for _ in 0..degree_bound.trailing_zeros() {
+            let half = values.len() / 2;
+            let pairs: Vec<[Felt; 2]> = (0..half).map(|j| [values[j], values[j + half]]).collect();
+            let tree = MerkleTree::new(pairs.iter().map(|pair| &pair[..]));
+            transcript.absorb(&tree.root());
+            let challenge = transcript.challenge();
+
+            let folded = fold(&values, shift, challenge);
+            layers.push((values, tree));
+            values = folded;
+            shift = shift.square();
+        }
+
+        let final_value = values[0];
+        transcript.absorb_felts(&[final_value]);
+        Self {
+            layers,
+            final_value,
+        }
+    }
+
+    pub fn proof(&self) -> FriProof {
+        FriProof {
+            roots: self.layers.iter().map(|(_, tree)| tree.root()).collect(),
+            final_value: field::to_bytes(&self.final_value),
+        }
+    }
+
+    /// Openings of the folding path starting at `index` of the first layer
+    pub fn open(&self, index: usize) -> Vec<FriOpening> {
+        let mut index = index;
+        self.layers
+            .iter()
+            .map(|(values, tree)| {
+                let half = values.len() / 2;
+                let j = index % half;
+                index = j;
+                FriOpening {
+                    values: [
+                        field::to_bytes(&values[j]),
+                        field::to_bytes(&values[j + half]),
+                    ],
+                    path: tree.path(j),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks queries against a [`FriProof`]
+pub(crate) struct FriVerifier<'a> {
+    proof: &'a FriProof,
+    challenges: Vec<Felt>,
+    final_value: Felt,
+    shift: Felt,
+    domain_size: usize,
+}
+
+impl<'a> FriVerifier<'a> {
+    /// Replay the commit phase of `proof` for evaluations over the coset
+    /// `shift * <w>` of order `domain_size`, of degree below `degree_bound`
+    pub fn new(
+        proof: &'a FriProof,
+        shift: Felt,
+        domain_size: usize,
+        degree_bound: usize,
+        transcript: &mut Transcript,
+    ) -> Result<Self> {
+        if proof.roots.len() != degree_bound.trailing_zeros() as usize {
+            return Err(invalid("wrong number of FRI layers"));
+        }
+        let challenges = proof
+            .roots
+            .iter()
+            .map(|root| {
+                transcript.absorb(root);
+                transcript.challenge()
+            })
+            .collect();
+        let final_value = decode(&proof.final_value)?;
+        transcript.absorb_felts(&[final_value]);
+
+        Ok(Self {
+            proof,
+            challenges,
+            final_value,
+            shift,
+            domain_size,
+        })
+    }
+
+    /// Check that `value` at `index` of the first layer folds down to the
+    /// final constant
+    pub fn check(&self, index: usize, value: Felt, openings: &[FriOpening]) -> Result<()> {
+        if openings.len() != self.proof.roots.len() {
+            return Err(invalid("wrong number of FRI openings"));
+        }
+
+        let two_inv = Felt::from(2u64).invert().expect("2 is nonzero");
+        let mut index = index;
+        let mut value = value;
+        let mut size = self.domain_size;
+        let mut shift = self.shift;
+        let mut root = field::root_of_unity(size);
+        for ((opening, layer_root), challenge) in
+            openings.iter().zip(&self.proof.roots).zip(&self.challenges)
+        {
+            let half = size / 2;
+            let j = index % half;
+            let pair = [decode(&opening.values[0])?, decode(&opening.values[1])?];
+            if pair[(index >= half) as usize] != value {
+                return Err(invalid("FRI layers are inconsistent"));
+            }
+            if !merkle::verify(layer_root, j, &pair, &opening.path) {
+                return Err(invalid("invalid FRI layer opening"));
+            }
+
+            let x_inv = (shift * root.pow_vartime([j as u64]))
+                .invert()
+                .expect("coset points are nonzero");
+            value = two_inv * (pair[0] + pair[1] + *challenge * (pair[0] - pair[1]) * x_inv);
+            index = j;
+            size = half;
+            shift = shift.square();
+            root = root.square();
+        }
+
+        if value != self.final_value {
+            return Err(invalid("FRI query does not reach the final value"));
+        }
+        Ok(())
+    }
+}
+
+/// Fold evaluations over `shift * <w>` into evaluations over its square
+fn fold(values: &[Felt], shift: Felt, challenge: Felt) -> Vec<Felt> {
+    let half = values.len() / 2;
+    let two_inv = Felt::from(2u64).invert().expect("2 is nonzero");
+    let root_inv = field::root_of_unity(values.len())
+        .invert()
+        .expect("roots of unity are nonzero");
+    let mut x_inv = shift.invert().expect("shift is nonzero");
+
+    (0..half)
+        .map(|j| {
+            let (a, b) = (values[j], values[j + half]);
+            let folded = two_inv * (a + b + challenge * (a - b) * x_inv);
+            x_inv *= root_inv;
+            folded
+        })
+        .collect()
+}
+
+pub(crate) fn decode(bytes: &[u8; 32]) -> Result<Felt> {
+    field::from_bytes(bytes).ok_or_else(|| invalid("non-canonical field element"))
+}
+
+fn invalid(reason: &str) -> ZkvmError {
+    ZkvmError::InvalidProof(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 64;
+    const DEGREE: usize = 8;
+
+    fn evaluations(degree: usize) -> Vec<Felt> {
+        let coefficients: Vec<Felt> = (0..degree as u64).map(|i| Felt::from(3 * i + 1)).collect();
+        field::coset_evaluate(&coefficients, field::coset_shift(), SIZE)
+    }
+
+    fn prove_and_check(values: Vec<Felt>, indices: &[usize]) -> Result<()> {
+        let shift = field::coset_shift();
+        let prover = FriProver::commit(values.clone(), shift, DEGREE, &mut Transcript::new("fri"));
+        let proof = prover.proof();
+
+        let verifier = FriVerifier::new(&proof, shift, SIZE, DEGREE, &mut Transcript::new("fri"))?;
+        for &index in indices {
+            verifier.check(index, values[index], &prover.open(index))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_low_degree_accepted() {
+        prove_and_check(evaluations(DEGREE), &[0, 1, 31, 32, 63]).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_value_rejected() {
+        let shift = field::coset_shift();
+        let values = evaluations(DEGREE);
+        let prover = FriProver::commit(values.clone(), shift, DEGREE, &mut Transcript::new("fri"));
+        let proof = prover.proof();
+        let verifier =
+            FriVerifier::new(&proof, shift, SIZE, DEGREE, &mut Transcript::new("fri")).unwrap();
+
+        assert!(verifier
+            .check(5, values[5] + Felt::ONE, &prover.open(5))
+            .is_err());
+        assert!(verifier.check(5, values[5], &prover.open(6)).is_err());
+        assert!(verifier.check(5, values[5], &prover.open(5)[1..]).is_err());
+    }
+
+    #[test]
+    fn test_high_degree_rejected() {
+        let values = evaluations(4 * DEGREE);
+        let failures = (0..SIZE)
+            .filter(|&i| prove_and_check(values.clone(), &[i]).is_err())
+            .count();
+        assert!(failures > SIZE / 2);
+    }
+}
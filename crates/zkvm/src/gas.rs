@@ -0,0 +1,233 @@
+//! Gas metering and resource limits
+//!
+//! Every instruction is charged by its [`InstructionClass`], precompile calls
+//! a flat cost by their operation on the `ecall` that starts them, and the
+//! first write by the guest to each page pays for the page. [`crate::Vm`]
+//! charges before executing an instruction, so running out of gas or memory
+//! faults like any other instruction: deterministically and with the state
+//! unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::instruction::{AluOp, Instruction};
+use crate::precompile::{
+    SYS_MERKLE_VERIFY, SYS_NOTE_NULLIFIER, SYS_PEDERSEN_COMMIT, SYS_POSEIDON, SYS_SIGNATURE_VERIFY,
+};
+
+/// Instruction classes with distinct costs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstructionClass {
+    /// Integer arithmetic, logic, shifts, `lui` and `auipc`
+    Alu,
+    Multiply,
+    /// Division and remainder
+    Divide,
+    Load,
+    Store,
+    Branch,
+    Jump,
+    /// `fence`, `ecall` and `ebreak`; precompiles are charged on top
+    System,
+}
+
+impl InstructionClass {
+    /// The class of `instruction`
+    pub fn of(instruction: &Instruction) -> Self {
+        match instruction {
+            Instruction::Lui { .. } | Instruction::Auipc { .. } | Instruction::AluImm { .. } => {
+                Self::Alu
+            }
+            Instruction::Alu { op, .. } => match op {
+                AluOp::Mul | AluOp::Mulh | AluOp::Mulhsu | AluOp::Mulhu => Self::Multiply,
+                AluOp::Div | AluOp::Divu | AluOp::Rem | AluOp::Remu => Self::Divide,
+                _ => Self::Alu,
+            },
+            Instruction::Load { .. } => Self::Load,
+            Instruction::Store { .. } => Self::Store,
+            Instruction::Branch { .. } => Self::Branch,
+            Instruction::Jal { .. } | Instruction::Jalr { .. } => Self::Jump,
+            Instruction::Fence | Instruction::Ecall | Instruction::Ebreak => Self::System,
+        }
+    }
+}
+
+/// Gas charged per instruction class, precompile and page of memory
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasSchedule {
+    pub alu: u64,
+    pub multiply: u64,
+    pub divide: u64,
+    pub load: u64,
+    pub store: u64,
+    pub branch: u64,
+    pub jump: u64,
+    pub system: u64,
+    pub poseidon: u64,
+    pub pedersen_commit: u64,
+    pub merkle_verify: u64,
+    pub note_nullifier: u64,
+    pub signature_verify: u64,
+    /// Per page of memory the guest writes to
+    pub page: u64,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self {
+            alu: 1,
+            multiply: 2,
+            divide: 4,
+            load: 2,
+            store: 2,
+            branch: 1,
+            jump: 1,
+            system: 1,
+            poseidon: 300,
+            pedersen_commit: 2_000,
+            merkle_verify: 10_000,
+            note_nullifier: 1_000,
+            signature_verify: 3_000,
+            page: 1_024,
+        }
+    }
+}
+
+impl GasSchedule {
+    /// Cost of executing `instruction`, excluding precompile and memory charges
+    pub fn instruction(&self, instruction: &Instruction) -> u64 {
+        self.class(InstructionClass::of(instruction))
+    }
+
+    /// Cost of an instruction of `class`
+    pub fn class(&self, class: InstructionClass) -> u64 {
+        match class {
+            InstructionClass::Alu => self.alu,
+            InstructionClass::Multiply => self.multiply,
+            InstructionClass::Divide => self.divide,
+            InstructionClass::Load => self.load,
+            InstructionClass::Store => self.store,
+            InstructionClass::Branch => self.branch,
+            InstructionClass::Jump => self.jump,
+            InstructionClass::System => self.system,
+        }
+    }
+
+    /// Additional cost of a call to system call `number`, charged on the
+    /// `ecall` that starts it
+    ///
+    /// Exit and unsupported calls cost nothing beyond the `ecall` itself.
+    pub fn syscall(&self, number: u32) -> u64 {
+        match number {
+            SYS_POSEIDON => self.poseidon,
+            SYS_PEDERSEN_COMMIT => self.pedersen_commit,
+            SYS_MERKLE_VERIFY => self.merkle_verify,
+            SYS_NOTE_NULLIFIER => self.note_nullifier,
+            SYS_SIGNATURE_VERIFY => self.signature_verify,
+            _ => 0,
+        }
+    }
+
+    /// Cost of writing to `pages` pages for the first time
+    pub fn memory(&self, pages: usize) -> u64 {
+        self.page.saturating_mul(pages as u64)
+    }
+}
+
+/// Hard limits on a run
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Gas available to the run
+    pub gas: u64,
+    pub max_cycles: u64,
+    /// Most pages of memory allocated at once, including the program and inputs
+    pub max_pages: usize,
+    /// Largest public output, in bytes
+    pub max_output: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            gas: 1_000_000_000,
+            max_cycles: 100_000_000,
+            max_pages: 16_384,
+            max_output: 64 * 1024,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Width;
+
+    #[test]
+    fn test_instruction_classes() {
+        let alu = |op| Instruction::Alu {
+            op,
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        };
+        assert_eq!(
+            InstructionClass::of(&alu(AluOp::Xor)),
+            InstructionClass::Alu
+        );
+        assert_eq!(
+            InstructionClass::of(&alu(AluOp::Mulhu)),
+            InstructionClass::Multiply
+        );
+        assert_eq!(
+            InstructionClass::of(&alu(AluOp::Remu)),
+            InstructionClass::Divide
+        );
+        assert_eq!(
+            InstructionClass::of(&Instruction::Lui { rd: 1, imm: 0x1000 }),
+            InstructionClass::Alu
+        );
+        assert_eq!(
+            InstructionClass::of(&Instruction::Store {
+                width: Width::Byte,
+                rs1: 1,
+                rs2: 2,
+                offset: 0
+            }),
+            InstructionClass::Store
+        );
+        assert_eq!(
+            InstructionClass::of(&Instruction::Ecall),
+            InstructionClass::System
+        );
+    }
+
+    #[test]
+    fn test_syscall_costs() {
+        let schedule = GasSchedule::default();
+
+        assert_eq!(schedule.syscall(SYS_POSEIDON), schedule.poseidon);
+        assert_eq!(
+            schedule.syscall(SYS_PEDERSEN_COMMIT),
+            schedule.pedersen_commit
+        );
+        assert_eq!(schedule.syscall(SYS_MERKLE_VERIFY), schedule.merkle_verify);
+        assert_eq!(
+            schedule.syscall(SYS_NOTE_NULLIFIER),
+            schedule.note_nullifier
+        );
+        assert_eq!(
+            schedule.syscall(SYS_SIGNATURE_VERIFY),
+            schedule.signature_verify
+        );
+        assert_eq!(schedule.syscall(crate::vm::SYS_EXIT), 0);
+        assert_eq!(schedule.memory(3), 3 * schedule.page);
+    }
+
+    #[test]
+    fn test_costs_saturate() {
+        let schedule = GasSchedule {
+            page: u64::MAX,
+            ..GasSchedule::default()
+        };
+        assert_eq!(schedule.memory(2), u64::MAX);
+    }
+}
@@ -0,0 +1,780 @@
+//! RV32IM instructions
+//!
+//! [`Instruction::decode`] accepts exactly the RV32I base set (with `FENCE`
+//! as a no-op) and the M extension. Compressed, CSR and `FENCE.I`
+//! instructions are illegal. [`Instruction::encode`] is its inverse, used to
+//! build guest programs and test vectors.
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::Width;
+use crate::{Result, ZkvmError};
+
+pub(crate) const OPCODE_LOAD: u32 = 0x03;
+pub(crate) const OPCODE_MISC_MEM: u32 = 0x0f;
+pub(crate) const OPCODE_OP_IMM: u32 = 0x13;
+pub(crate) const OPCODE_AUIPC: u32 = 0x17;
+pub(crate) const OPCODE_STORE: u32 = 0x23;
+pub(crate) const OPCODE_OP: u32 = 0x33;
+pub(crate) const OPCODE_LUI: u32 = 0x37;
+pub(crate) const OPCODE_BRANCH: u32 = 0x63;
+pub(crate) const OPCODE_JALR: u32 = 0x67;
+pub(crate) const OPCODE_JAL: u32 = 0x6f;
+pub(crate) const OPCODE_SYSTEM: u32 = 0x73;
+
+pub(crate) const ECALL: u32 = 0x0000_0073;
+const EBREAK: u32 = 0x0010_0073;
+/// `fence iorw, iorw`
+const FENCE: u32 = 0x0ff0_000f;
+
+/// Register-register and register-immediate operations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AluOp {
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+}
+
+impl AluOp {
+    pub(crate) const ALL: [AluOp; 18] = [
+        AluOp::Add,
+        AluOp::Sub,
+        AluOp::Sll,
+        AluOp::Slt,
+        AluOp::Sltu,
+        AluOp::Xor,
+        AluOp::Srl,
+        AluOp::Sra,
+        AluOp::Or,
+        AluOp::And,
+        AluOp::Mul,
+        AluOp::Mulh,
+        AluOp::Mulhsu,
+        AluOp::Mulhu,
+        AluOp::Div,
+        AluOp::Divu,
+        AluOp::Rem,
+        AluOp::Remu,
+    ];
+
+    /// Apply the operation; division by zero and overflow follow the M spec
+    pub fn apply(self, a: u32, b: u32) -> u32 {
+        let shamt = b & 0x1f;
+        match self {
+            AluOp::Add => a.wrapping_add(b),
+            AluOp::Sub => a.wrapping_sub(b),
+            AluOp::Sll => a << shamt,
+            AluOp::Slt => ((a as i32) < (b as i32)) as u32,
+            AluOp::Sltu => (a < b) as u32,
+            AluOp::Xor => a ^ b,
+            AluOp::Srl => a >> shamt,
+            AluOp::Sra => ((a as i32) >> shamt) as u32,
+            AluOp::Or => a | b,
+            AluOp::And => a & b,
+            AluOp::Mul => a.wrapping_mul(b),
+            AluOp::Mulh => ((a as i32 as i64 * b as i32 as i64) >> 32) as u32,
+            AluOp::Mulhsu => ((a as i32 as i64 * b as i64) >> 32) as u32,
+            AluOp::Mulhu => ((a as u64 * b as u64) >> 32) as u32,
+            AluOp::Div if b == 0 => u32::MAX,
+            AluOp::Div => (a as i32).wrapping_div(b as i32) as u32,
+            AluOp::Divu if b == 0 => u32::MAX,
+            AluOp::Divu => a / b,
+            AluOp::Rem if b == 0 => a,
+            AluOp::Rem => (a as i32).wrapping_rem(b as i32) as u32,
+            AluOp::Remu if b == 0 => a,
+            AluOp::Remu => a % b,
+        }
+    }
+
+    /// Whether the operation has an OP-IMM form
+    pub fn has_immediate(self) -> bool {
+        matches!(
+            self,
+            AluOp::Add
+                | AluOp::Sll
+                | AluOp::Slt
+                | AluOp::Sltu
+                | AluOp::Xor
+                | AluOp::Srl
+                | AluOp::Sra
+                | AluOp::Or
+                | AluOp::And
+        )
+    }
+
+    /// Whether the immediate form takes a shift amount rather than an immediate
+    pub(crate) fn is_shift(self) -> bool {
+        matches!(self, AluOp::Sll | AluOp::Srl | AluOp::Sra)
+    }
+
+    /// `(funct7, funct3)` of the OP encoding
+    pub(crate) fn funct(self) -> (u32, u32) {
+        match self {
+            AluOp::Add => (0x00, 0),
+            AluOp::Sub => (0x20, 0),
+            AluOp::Sll => (0x00, 1),
+            AluOp::Slt => (0x00, 2),
+            AluOp::Sltu => (0x00, 3),
+            AluOp::Xor => (0x00, 4),
+            AluOp::Srl => (0x00, 5),
+            AluOp::Sra => (0x20, 5),
+            AluOp::Or => (0x00, 6),
+            AluOp::And => (0x00, 7),
+            AluOp::Mul => (0x01, 0),
+            AluOp::Mulh => (0x01, 1),
+            AluOp::Mulhsu => (0x01, 2),
+            AluOp::Mulhu => (0x01, 3),
+            AluOp::Div => (0x01, 4),
+            AluOp::Divu => (0x01, 5),
+            AluOp::Rem => (0x01, 6),
+            AluOp::Remu => (0x01, 7),
+        }
+    }
+
+    fn from_funct(funct7: u32, funct3: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.funct() == (funct7, funct3))
+    }
+}
+
+/// Conditional branch comparisons
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BranchCondition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+impl BranchCondition {
+    /// Whether the branch is taken for `rs1 = a`, `rs2 = b`
+    pub fn holds(self, a: u32, b: u32) -> bool {
+        match self {
+            BranchCondition::Eq => a == b,
+            BranchCondition::Ne => a != b,
+            BranchCondition::Lt => (a as i32) < (b as i32),
+            BranchCondition::Ge => (a as i32) >= (b as i32),
+            BranchCondition::Ltu => a < b,
+            BranchCondition::Geu => a >= b,
+        }
+    }
+
+    pub(crate) fn funct3(self) -> u32 {
+        match self {
+            BranchCondition::Eq => 0,
+            BranchCondition::Ne => 1,
+            BranchCondition::Lt => 4,
+            BranchCondition::Ge => 5,
+            BranchCondition::Ltu => 6,
+            BranchCondition::Geu => 7,
+        }
+    }
+}
+
+/// Load widths and extensions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LoadOp {
+    Lb,
+    Lh,
+    Lw,
+    Lbu,
+    Lhu,
+}
+
+impl LoadOp {
+    /// Width of the memory access
+    pub fn width(self) -> Width {
+        match self {
+            LoadOp::Lb | LoadOp::Lbu => Width::Byte,
+            LoadOp::Lh | LoadOp::Lhu => Width::Half,
+            LoadOp::Lw => Width::Word,
+        }
+    }
+
+    /// Extend a loaded (zero-extended) value to 32 bits
+    pub fn extend(self, value: u32) -> u32 {
+        match self {
+            LoadOp::Lb => value as u8 as i8 as u32,
+            LoadOp::Lh => value as u16 as i16 as u32,
+            LoadOp::Lw | LoadOp::Lbu | LoadOp::Lhu => value,
+        }
+    }
+
+    pub(crate) fn funct3(self) -> u32 {
+        match self {
+            LoadOp::Lb => 0,
+            LoadOp::Lh => 1,
+            LoadOp::Lw => 2,
+            LoadOp::Lbu => 4,
+            LoadOp::Lhu => 5,
+        }
+    }
+}
+
+/// A decoded RV32IM instruction
+///
+/// Registers are indices below 32. Offsets and immediates are sign-extended;
+/// `Lui` and `Auipc` hold the immediate already shifted into the upper 20 bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Instruction {
+    Lui {
+        rd: u8,
+        imm: u32,
+    },
+    Auipc {
+        rd: u8,
+        imm: u32,
+    },
+    Jal {
+        rd: u8,
+        offset: i32,
+    },
+    Jalr {
+        rd: u8,
+        rs1: u8,
+        offset: i32,
+    },
+    Branch {
+        cond: BranchCondition,
+        rs1: u8,
+        rs2: u8,
+        offset: i32,
+    },
+    Load {
+        op: LoadOp,
+        rd: u8,
+        rs1: u8,
+        offset: i32,
+    },
+    Store {
+        width: Width,
+        rs1: u8,
+        rs2: u8,
+        offset: i32,
+    },
+    AluImm {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        imm: i32,
+    },
+    Alu {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    Fence,
+    Ecall,
+    Ebreak,
+}
+
+impl Instruction {
+    /// Decode a 32-bit instruction word, or `None` if it is not RV32IM
+    pub fn decode(word: u32) -> Option<Self> {
+        let rd = ((word >> 7) & 0x1f) as u8;
+        let rs1 = ((word >> 15) & 0x1f) as u8;
+        let rs2 = ((word >> 20) & 0x1f) as u8;
+        let funct3 = (word >> 12) & 0x7;
+        let funct7 = word >> 25;
+        let i_imm = (word as i32) >> 20;
+
+        let instruction = match word & 0x7f {
+            OPCODE_LUI => Instruction::Lui {
+                rd,
+                imm: word & 0xffff_f000,
+            },
+            OPCODE_AUIPC => Instruction::Auipc {
+                rd,
+                imm: word & 0xffff_f000,
+            },
+            OPCODE_JAL => Instruction::Jal {
+                rd,
+                offset: j_immediate(word),
+            },
+            OPCODE_JALR if funct3 == 0 => Instruction::Jalr {
+                rd,
+                rs1,
+                offset: i_imm,
+            },
+            OPCODE_BRANCH => {
+                let cond = match funct3 {
+                    0 => BranchCondition::Eq,
+                    1 => BranchCondition::Ne,
+                    4 => BranchCondition::Lt,
+                    5 => BranchCondition::Ge,
+                    6 => BranchCondition::Ltu,
+                    7 => BranchCondition::Geu,
+                    _ => return None,
+                };
+                Instruction::Branch {
+                    cond,
+                    rs1,
+                    rs2,
+                    offset: b_immediate(word),
+                }
+            }
+            OPCODE_LOAD => {
+                let op = match funct3 {
+                    0 => LoadOp::Lb,
+                    1 => LoadOp::Lh,
+                    2 => LoadOp::Lw,
+                    4 => LoadOp::Lbu,
+                    5 => LoadOp::Lhu,
+                    _ => return None,
+                };
+                Instruction::Load {
+                    op,
+                    rd,
+                    rs1,
+                    offset: i_imm,
+                }
+            }
+            OPCODE_STORE => {
+                let width = match funct3 {
+                    0 => Width::Byte,
+                    1 => Width::Half,
+                    2 => Width::Word,
+                    _ => return None,
+                };
+                Instruction::Store {
+                    width,
+                    rs1,
+                    rs2,
+                    offset: s_immediate(word),
+                }
+            }
+            OPCODE_OP_IMM => {
+                let (op, imm) = match funct3 {
+                    1 | 5 => {
+                        // Shifts by a 5-bit amount; funct7 selects SRLI/SRAI
+                        let op = AluOp::from_funct(funct7, funct3)?;
+                        if !op.is_shift() {
+                            return None;
+                        }
+                        (op, rs2 as i32)
+                    }
+                    _ => (AluOp::from_funct(0, funct3)?, i_imm),
+                };
+                Instruction::AluImm { op, rd, rs1, imm }
+            }
+            OPCODE_OP => Instruction::Alu {
+                op: AluOp::from_funct(funct7, funct3)?,
+                rd,
+                rs1,
+                rs2,
+            },
+            OPCODE_MISC_MEM if funct3 == 0 => Instruction::Fence,
+            OPCODE_SYSTEM if word == ECALL => Instruction::Ecall,
+            OPCODE_SYSTEM if word == EBREAK => Instruction::Ebreak,
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    /// Encode to a 32-bit instruction word
+    pub fn encode(&self) -> Result<u32> {
+        let word = match *self {
+            Instruction::Lui { rd, imm } => u_type(OPCODE_LUI, rd, imm)?,
+            Instruction::Auipc { rd, imm } => u_type(OPCODE_AUIPC, rd, imm)?,
+            Instruction::Jal { rd, offset } => {
+                check_offset(offset, 21)?;
+                let imm = offset as u32;
+                let imm = ((imm >> 20) & 1) << 31
+                    | ((imm >> 1) & 0x3ff) << 21
+                    | ((imm >> 11) & 1) << 20
+                    | ((imm >> 12) & 0xff) << 12;
+                imm | register(rd)? << 7 | OPCODE_JAL
+            }
+            Instruction::Jalr { rd, rs1, offset } => i_type(OPCODE_JALR, 0, rd, rs1, offset)?,
+            Instruction::Branch {
+                cond,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                check_offset(offset, 13)?;
+                let imm = offset as u32;
+                let imm = ((imm >> 12) & 1) << 31
+                    | ((imm >> 5) & 0x3f) << 25
+                    | ((imm >> 1) & 0xf) << 8
+                    | ((imm >> 11) & 1) << 7;
+                imm | register(rs2)? << 20
+                    | register(rs1)? << 15
+                    | cond.funct3() << 12
+                    | OPCODE_BRANCH
+            }
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset,
+            } => i_type(OPCODE_LOAD, op.funct3(), rd, rs1, offset)?,
+            Instruction::Store {
+                width,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                check_immediate(offset)?;
+                let imm = offset as u32;
+                let funct3 = match width {
+                    Width::Byte => 0,
+                    Width::Half => 1,
+                    Width::Word => 2,
+                };
+                ((imm >> 5) & 0x7f) << 25
+                    | register(rs2)? << 20
+                    | register(rs1)? << 15
+                    | funct3 << 12
+                    | (imm & 0x1f) << 7
+                    | OPCODE_STORE
+            }
+            Instruction::AluImm { op, rd, rs1, imm } => {
+                let (funct7, funct3) = op.funct();
+                if !op.has_immediate() {
+                    return Err(ZkvmError::InvalidInstruction(format!(
+                        "{:?} has no immediate form",
+                        op
+                    )));
+                }
+                if op.is_shift() {
+                    if !(0..32).contains(&imm) {
+                        return Err(ZkvmError::InvalidInstruction(format!(
+                            "Shift amount {} out of range",
+                            imm
+                        )));
+                    }
+                    i_type(OPCODE_OP_IMM, funct3, rd, rs1, (funct7 << 5) as i32 | imm)?
+                } else {
+                    i_type(OPCODE_OP_IMM, funct3, rd, rs1, imm)?
+                }
+            }
+            Instruction::Alu { op, rd, rs1, rs2 } => {
+                let (funct7, funct3) = op.funct();
+                funct7 << 25
+                    | register(rs2)? << 20
+                    | register(rs1)? << 15
+                    | funct3 << 12
+                    | register(rd)? << 7
+                    | OPCODE_OP
+            }
+            Instruction::Fence => FENCE,
+            Instruction::Ecall => ECALL,
+            Instruction::Ebreak => EBREAK,
+        };
+
+        Ok(word)
+    }
+}
+
+pub(crate) fn b_immediate(word: u32) -> i32 {
+    let imm = ((word >> 7) & 1) << 11 | ((word >> 25) & 0x3f) << 5 | ((word >> 8) & 0xf) << 1;
+    ((word as i32) >> 31 << 12) | imm as i32
+}
+
+pub(crate) fn j_immediate(word: u32) -> i32 {
+    let imm = (word & 0x000f_f000) | ((word >> 20) & 1) << 11 | ((word >> 21) & 0x3ff) << 1;
+    ((word as i32) >> 31 << 20) | imm as i32
+}
+
+pub(crate) fn s_immediate(word: u32) -> i32 {
+    ((word as i32) >> 25 << 5) | ((word >> 7) & 0x1f) as i32
+}
+
+fn register(index: u8) -> Result<u32> {
+    if index >= 32 {
+        return Err(ZkvmError::InvalidInstruction(format!(
+            "No register x{}",
+            index
+        )));
+    }
+    Ok(index as u32)
+}
+
+fn check_immediate(imm: i32) -> Result<()> {
+    if !(-2048..2048).contains(&imm) {
+        return Err(ZkvmError::InvalidInstruction(format!(
+            "Immediate {} does not fit 12 bits",
+            imm
+        )));
+    }
+    Ok(())
+}
+
+/// Check a jump or branch offset: even and within `bits` signed bits
+fn check_offset(offset: i32, bits: u32) -> Result<()> {
+    let bound = 1i32 << (bits - 1);
+    if offset % 2 != 0 || !(-bound..bound).contains(&offset) {
+        return Err(ZkvmError::InvalidInstruction(format!(
+            "Offset {} out of range",
+            offset
+        )));
+    }
+    Ok(())
+}
+
+fn i_type(opcode: u32, funct3: u32, rd: u8, rs1: u8, imm: i32) -> Result<u32> {
+    // Shift encodings carry funct7 in the top bits and are checked by the caller
+    if opcode != OPCODE_OP_IMM || !(funct3 == 1 || funct3 == 5) {
+        check_immediate(imm)?;
+    }
+    Ok((imm as u32 & 0xfff) << 20
+        | register(rs1)? << 15
+        | funct3 << 12
+        | register(rd)? << 7
+        | opcode)
+}
+
+fn u_type(opcode: u32, rd: u8, imm: u32) -> Result<u32> {
+    if imm & 0xfff != 0 {
+        return Err(ZkvmError::InvalidInstruction(format!(
+            "Upper immediate {:#x} has low bits set",
+            imm
+        )));
+    }
+    Ok(imm | register(rd)? << 7 | opcode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_known_words() {
+        // addi a0, zero, -1
+        assert_eq!(
+            Instruction::decode(0xfff0_0513),
+            Some(Instruction::AluImm {
+                op: AluOp::Add,
+                rd: 10,
+                rs1: 0,
+                imm: -1
+            })
+        );
+        // srai t0, t1, 3
+        assert_eq!(
+            Instruction::decode(0x4033_5293),
+            Some(Instruction::AluImm {
+                op: AluOp::Sra,
+                rd: 5,
+                rs1: 6,
+                imm: 3
+            })
+        );
+        // bne a0, a1, -8
+        assert_eq!(
+            Instruction::decode(0xfeb5_1ce3),
+            Some(Instruction::Branch {
+                cond: BranchCondition::Ne,
+                rs1: 10,
+                rs2: 11,
+                offset: -8
+            })
+        );
+        // jal ra, 2048
+        assert_eq!(
+            Instruction::decode(0x0010_00ef),
+            Some(Instruction::Jal {
+                rd: 1,
+                offset: 2048
+            })
+        );
+        // sw a1, -4(sp)
+        assert_eq!(
+            Instruction::decode(0xfeb1_2e23),
+            Some(Instruction::Store {
+                width: Width::Word,
+                rs1: 2,
+                rs2: 11,
+                offset: -4
+            })
+        );
+        // mulhu a0, a1, a2
+        assert_eq!(
+            Instruction::decode(0x02c5_b533),
+            Some(Instruction::Alu {
+                op: AluOp::Mulhu,
+                rd: 10,
+                rs1: 11,
+                rs2: 12
+            })
+        );
+        assert_eq!(Instruction::decode(0x0000_0073), Some(Instruction::Ecall));
+    }
+
+    #[test]
+    fn test_illegal_words_rejected() {
+        for word in [
+            0x0000_0000, // all zeros is defined illegal
+            0xffff_ffff,
+            0x0000_100f, // fence.i
+            0x3000_2573, // csrr a0, mstatus
+            0x0200_5013, // srli with shamt bit 5 set (RV64 only)
+            0x4000_7033, // and with funct7 0x20
+            0x0000_3003, // ld
+            0x0000_2063, // branch with funct3 2
+        ] {
+            assert_eq!(Instruction::decode(word), None, "{:#010x} decoded", word);
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut instructions = vec![
+            Instruction::Lui {
+                rd: 1,
+                imm: 0xfffff000,
+            },
+            Instruction::Auipc {
+                rd: 31,
+                imm: 0x1000,
+            },
+            Instruction::Jal {
+                rd: 0,
+                offset: -(1 << 20),
+            },
+            Instruction::Jal {
+                rd: 1,
+                offset: (1 << 20) - 2,
+            },
+            Instruction::Jalr {
+                rd: 1,
+                rs1: 5,
+                offset: -2048,
+            },
+            Instruction::Branch {
+                cond: BranchCondition::Geu,
+                rs1: 3,
+                rs2: 4,
+                offset: 4094,
+            },
+            Instruction::Branch {
+                cond: BranchCondition::Lt,
+                rs1: 3,
+                rs2: 4,
+                offset: -4096,
+            },
+            Instruction::Load {
+                op: LoadOp::Lhu,
+                rd: 7,
+                rs1: 8,
+                offset: 2047,
+            },
+            Instruction::Store {
+                width: Width::Byte,
+                rs1: 9,
+                rs2: 10,
+                offset: -1,
+            },
+            Instruction::AluImm {
+                op: AluOp::Srl,
+                rd: 1,
+                rs1: 2,
+                imm: 31,
+            },
+            Instruction::Fence,
+            Instruction::Ecall,
+            Instruction::Ebreak,
+        ];
+        for op in AluOp::ALL {
+            instructions.push(Instruction::Alu {
+                op,
+                rd: 11,
+                rs1: 12,
+                rs2: 13,
+            });
+            if op.has_immediate() {
+                instructions.push(Instruction::AluImm {
+                    op,
+                    rd: 14,
+                    rs1: 15,
+                    imm: if op.is_shift() { 17 } else { -100 },
+                });
+            }
+        }
+
+        for instruction in instructions {
+            let word = instruction.encode().unwrap();
+            assert_eq!(
+                Instruction::decode(word),
+                Some(instruction),
+                "{:#010x}",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn test_unencodable_rejected() {
+        for instruction in [
+            Instruction::AluImm {
+                op: AluOp::Sub,
+                rd: 1,
+                rs1: 1,
+                imm: 1,
+            },
+            Instruction::AluImm {
+                op: AluOp::Sll,
+                rd: 1,
+                rs1: 1,
+                imm: 32,
+            },
+            Instruction::AluImm {
+                op: AluOp::Add,
+                rd: 1,
+                rs1: 1,
+                imm: 2048,
+            },
+            Instruction::Jal { rd: 1, offset: 3 },
+            Instruction::Branch {
+                cond: BranchCondition::Eq,
+                rs1: 0,
+                rs2: 0,
+                offset: 4096,
+            },
+            Instruction::Lui { rd: 1, imm: 0x123 },
+            Instruction::Alu {
+                op: AluOp::Add,
+                rd: 32,
+                rs1: 0,
+                rs2: 0,
+            },
+        ] {
+            assert!(instruction.encode().is_err(), "{:?} encoded", instruction);
+        }
+    }
+
+    #[test]
+    fn test_m_extension_edge_cases() {
+        let min = i32::MIN as u32;
+        assert_eq!(AluOp::Div.apply(min, u32::MAX), min);
+        assert_eq!(AluOp::Rem.apply(min, u32::MAX), 0);
+        assert_eq!(AluOp::Div.apply(7, 0), u32::MAX);
+        assert_eq!(AluOp::Divu.apply(7, 0), u32::MAX);
+        assert_eq!(AluOp::Rem.apply(7, 0), 7);
+        assert_eq!(AluOp::Remu.apply(7, 0), 7);
+        assert_eq!(AluOp::Mulhsu.apply(u32::MAX, u32::MAX), u32::MAX);
+        assert_eq!(AluOp::Mulhu.apply(u32::MAX, u32::MAX), 0xffff_fffe);
+        assert_eq!(AluOp::Mulh.apply(min, min), 0x4000_0000);
+    }
+}
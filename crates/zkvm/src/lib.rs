@@ -1 +1,101 @@
-//! PRIVL1 zkvm module
+//! PRIVL1 zkVM
+//!
+//! A deterministic RV32IM interpreter whose runs can be proven:
+//! - Instruction decoding and encoding for RV32I and the M extension
+//! - Paged, zero-initialized little-endian memory
+//! - Per-cycle execution traces (pc, instruction, register and memory
+//!   accesses) used as the proving witness
+//! - ELF32 loading and the guest I/O ABI implemented by `privl1-zkvm-guest`
+//! - Gas metering per instruction class, precompile and memory page, with
+//!   hard limits on gas, cycles, memory and output size
+//! - Precompiled Poseidon, Pedersen, Merkle, note nullifier and signature
+//!   system calls backed by `privl1-crypto`, each traced in its own table
+//! - Zero-knowledge STARK proofs of runs: [`prove`] a guest's exit code, gas
+//!   used and public output, and [`verify`] them against the program and
+//!   public input. The AIR constrains precompile calls like any other
+//!   instruction
+
+pub mod abi;
+mod air;
+pub mod elf;
+pub mod field;
+pub mod fri;
+pub mod gas;
+pub mod instruction;
+pub mod memory;
+pub mod merkle;
+pub mod precompile;
+pub mod proof;
+pub mod prover;
+pub mod trace;
+pub mod transcript;
+pub mod verifier;
+pub mod vm;
+mod witness;
+
+#[cfg(test)]
+mod riscv_tests;
+
+pub use elf::Program;
+pub use gas::{GasSchedule, ResourceLimits};
+pub use instruction::Instruction;
+pub use memory::{Memory, Width};
+pub use proof::{Proof, Statement};
+pub use prover::prove;
+pub use trace::{ExecutionTrace, PrecompileTables, TraceRow};
+pub use verifier::verify;
+pub use vm::Vm;
+
+/// Error type for zkVM execution
+#[derive(Debug, thiserror::Error)]
+pub enum ZkvmError {
+    #[error("Illegal instruction {word:#010x} at pc {pc:#010x}")]
+    IllegalInstruction { pc: u32, word: u32 },
+
+    #[error("Cannot encode instruction: {0}")]
+    InvalidInstruction(String),
+
+    #[error("Misaligned {width}-byte access at {address:#010x}")]
+    MisalignedAccess { address: u32, width: u32 },
+
+    #[error("Misaligned jump target {0:#010x}")]
+    MisalignedJump(u32),
+
+    #[error("Memory range at {address:#010x} of {len} bytes wraps the address space")]
+    AddressOverflow { address: u32, len: usize },
+
+    #[error("Invalid ELF executable: {0}")]
+    InvalidElf(String),
+
+    #[error("Guest I/O of {len} bytes exceeds the {max}-byte region")]
+    IoTooLarge { len: usize, max: usize },
+
+    #[error("Unsupported system call {0}")]
+    UnsupportedSyscall(u32),
+
+    #[error("Invalid input to system call {number:#x}: {reason}")]
+    InvalidSyscallInput { number: u32, reason: String },
+
+    #[error("Breakpoint at pc {0:#010x}")]
+    Breakpoint(u32),
+
+    #[error("Program did not halt within {0} cycles")]
+    CycleLimit(u64),
+
+    #[error("Out of gas: {required} required with a limit of {limit}")]
+    OutOfGas { limit: u64, required: u64 },
+
+    #[error("Memory limit exceeded: {pages} pages with a limit of {max}")]
+    MemoryLimit { pages: usize, max: usize },
+
+    #[error("Program has already halted")]
+    Halted,
+
+    #[error("Run cannot be proven: {0}")]
+    Unprovable(String),
+
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+}
+
+pub type Result<T> = std::result::Result<T, ZkvmError>;
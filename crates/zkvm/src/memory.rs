@@ -0,0 +1,231 @@
+//! Paged guest memory
+//!
+//! The 32-bit address space is split into [`PAGE_SIZE`]-byte pages that are
+//! allocated on first write. Unwritten memory reads as zero, and pages are
+//! kept in address order, so two runs of the same program see and leave
+//! identical memory.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::{Result, ZkvmError};
+
+/// Bytes per memory page
+pub const PAGE_SIZE: usize = 4096;
+
+/// Width of a memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Width {
+    Byte,
+    Half,
+    Word,
+}
+
+impl Width {
+    /// Size of the access in bytes
+    pub fn bytes(self) -> u32 {
+        match self {
+            Width::Byte => 1,
+            Width::Half => 2,
+            Width::Word => 4,
+        }
+    }
+}
+
+/// Sparse, zero-initialized byte-addressed memory
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+    pages: BTreeMap<u32, Box<[u8; PAGE_SIZE]>>,
+}
+
+impl Memory {
+    /// Create an empty memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read one byte
+    pub fn read_byte(&self, address: u32) -> u8 {
+        self.pages
+            .get(&page_number(address))
+            .map_or(0, |page| page[page_offset(address)])
+    }
+
+    /// Write one byte, allocating its page if needed
+    pub fn write_byte(&mut self, address: u32, value: u8) {
+        let page = self
+            .pages
+            .entry(page_number(address))
+            .or_insert_with(|| Box::new([0u8; PAGE_SIZE]));
+        page[page_offset(address)] = value;
+    }
+
+    /// Load a naturally aligned value, zero-extended to 32 bits
+    pub fn load(&self, address: u32, width: Width) -> Result<u32> {
+        check_alignment(address, width)?;
+
+        let mut value = 0u32;
+        for i in (0..width.bytes()).rev() {
+            value = (value << 8) | self.read_byte(address + i) as u32;
+        }
+        Ok(value)
+    }
+
+    /// Store the low `width` bytes of `value` at a naturally aligned address
+    pub fn store(&mut self, address: u32, width: Width, value: u32) -> Result<()> {
+        check_alignment(address, width)?;
+
+        for i in 0..width.bytes() {
+            self.write_byte(address + i, (value >> (8 * i)) as u8);
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes starting at `address`
+    pub fn read_bytes(&self, address: u32, len: usize) -> Result<Vec<u8>> {
+        check_range(address, len)?;
+        Ok((0..len as u32)
+            .map(|i| self.read_byte(address + i))
+            .collect())
+    }
+
+    /// Write `bytes` starting at `address`, e.g. to load a program image
+    pub fn write_bytes(&mut self, address: u32, bytes: &[u8]) -> Result<()> {
+        check_range(address, bytes.len())?;
+        for (i, byte) in bytes.iter().enumerate() {
+            self.write_byte(address + i as u32, *byte);
+        }
+        Ok(())
+    }
+
+    /// Number of allocated pages
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Nonzero aligned words in address order, as `(address, value)`
+    pub fn words(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.pages.iter().flat_map(|(number, page)| {
+            page.chunks(4).enumerate().filter_map(move |(i, bytes)| {
+                let value = u32::from_le_bytes(bytes.try_into().expect("4-byte chunks"));
+                (value != 0).then(|| (number * PAGE_SIZE as u32 + 4 * i as u32, value))
+            })
+        })
+    }
+
+    /// Number of pages a write of `len` bytes at `address` would allocate
+    pub fn new_pages(&self, address: u32, len: usize) -> usize {
+        pages(address, len)
+            .filter(|page| !self.pages.contains_key(page))
+            .count()
+    }
+}
+
+/// Numbers of the pages `len` bytes at `address` span, clamped to the
+/// address space
+pub fn pages(address: u32, len: usize) -> Range<u32> {
+    let first = page_number(address);
+    if len == 0 {
+        return first..first;
+    }
+    let last = (address as u64 + len as u64 - 1).min(u32::MAX as u64) as u32;
+    first..page_number(last) + 1
+}
+
+fn page_number(address: u32) -> u32 {
+    address / PAGE_SIZE as u32
+}
+
+fn page_offset(address: u32) -> usize {
+    address as usize % PAGE_SIZE
+}
+
+fn check_alignment(address: u32, width: Width) -> Result<()> {
+    if !address.is_multiple_of(width.bytes()) {
+        return Err(ZkvmError::MisalignedAccess {
+            address,
+            width: width.bytes(),
+        });
+    }
+    Ok(())
+}
+
+fn check_range(address: u32, len: usize) -> Result<()> {
+    if address as u64 + len as u64 > 1 << 32 {
+        return Err(ZkvmError::AddressOverflow { address, len });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unwritten_memory_is_zero() {
+        let memory = Memory::new();
+        assert_eq!(memory.load(0x8000_0000, Width::Word).unwrap(), 0);
+        assert_eq!(memory.page_count(), 0);
+    }
+
+    #[test]
+    fn test_little_endian() {
+        let mut memory = Memory::new();
+        memory.store(0x100, Width::Word, 0x1234_5678).unwrap();
+
+        assert_eq!(
+            memory.read_bytes(0x100, 4).unwrap(),
+            vec![0x78, 0x56, 0x34, 0x12]
+        );
+        assert_eq!(memory.load(0x102, Width::Half).unwrap(), 0x1234);
+        assert_eq!(memory.load(0x101, Width::Byte).unwrap(), 0x56);
+
+        memory.store(0x100, Width::Byte, 0xffff_ffaa).unwrap();
+        assert_eq!(memory.load(0x100, Width::Word).unwrap(), 0x1234_56aa);
+    }
+
+    #[test]
+    fn test_pages_allocated_on_write() {
+        let mut memory = Memory::new();
+        assert_eq!(memory.new_pages(PAGE_SIZE as u32 - 2, 4), 2);
+        memory
+            .write_bytes(PAGE_SIZE as u32 - 2, &[1, 2, 3, 4])
+            .unwrap();
+
+        assert_eq!(memory.page_count(), 2);
+        assert_eq!(memory.new_pages(PAGE_SIZE as u32, 2 * PAGE_SIZE), 1);
+        assert_eq!(memory.new_pages(0, 0), 0);
+        assert_eq!(memory.load(PAGE_SIZE as u32, Width::Half).unwrap(), 0x0403);
+        assert_eq!(memory.read_byte(u32::MAX), 0);
+        assert_eq!(
+            memory.words().collect::<Vec<_>>(),
+            vec![
+                (PAGE_SIZE as u32 - 4, 0x0201_0000),
+                (PAGE_SIZE as u32, 0x0403)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misaligned_access_rejected() {
+        let mut memory = Memory::new();
+        assert!(matches!(
+            memory.load(0x102, Width::Word),
+            Err(ZkvmError::MisalignedAccess {
+                address: 0x102,
+                width: 4
+            })
+        ));
+        assert!(memory.store(0x101, Width::Half, 0).is_err());
+        assert!(memory.store(0x101, Width::Byte, 0).is_ok());
+    }
+
+    #[test]
+    fn test_range_must_fit_address_space() {
+        let mut memory = Memory::new();
+        assert!(memory.write_bytes(u32::MAX, &[1]).is_ok());
+        assert!(memory.write_bytes(u32::MAX, &[1, 2]).is_err());
+        assert!(memory.read_bytes(u32::MAX - 1, 2).is_ok());
+    }
+}
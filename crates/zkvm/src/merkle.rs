@@ -0,0 +1,141 @@
+//! BLAKE3 Merkle commitments to evaluation tables
+//!
+//! The prover commits to the rows of each extended trace, and to each FRI
+//! layer, with a binary Merkle tree; an opening is the leaf's values and the
+//! sibling hashes up to the root. Leaves and internal nodes are hashed with
+//! distinct prefixes.
+
+use crate::field::{self, Felt};
+
+/// A 32-byte BLAKE3 digest
+pub type Digest = [u8; 32];
+
+const LEAF: u8 = 0;
+const NODE: u8 = 1;
+
+/// A Merkle tree over a power-of-two number of leaves
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    /// Leaf hashes first, the root last
+    layers: Vec<Vec<Digest>>,
+}
+
+impl MerkleTree {
+    /// Build a tree over `leaves`, each a row of field elements
+    pub fn new<'a>(leaves: impl IntoIterator<Item = &'a [Felt]>) -> Self {
+        Self::from_leaf_hashes(leaves.into_iter().map(hash_leaf).collect())
+    }
+
+    /// Build a tree over leaves already hashed with [`hash_leaf`]
+    pub fn from_leaf_hashes(hashes: Vec<Digest>) -> Self {
+        assert!(
+            hashes.len().is_power_of_two(),
+            "{} leaves is not a power of two",
+            hashes.len()
+        );
+
+        let mut layers = vec![hashes];
+        while layers.last().expect("at least one layer").len() > 1 {
+            let next = layers
+                .last()
+                .expect("at least one layer")
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], &pair[1]))
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    pub fn root(&self) -> Digest {
+        self.layers.last().expect("at least one layer")[0]
+    }
+
+    /// Sibling hashes from leaf `index` up to the root
+    pub fn path(&self, index: usize) -> Vec<Digest> {
+        let mut index = index;
+        self.layers[..self.layers.len() - 1]
+            .iter()
+            .map(|layer| {
+                let sibling = layer[index ^ 1];
+                index /= 2;
+                sibling
+            })
+            .collect()
+    }
+}
+
+/// Whether `values` are leaf `index` of the tree with `root`
+pub fn verify(root: &Digest, index: usize, values: &[Felt], path: &[Digest]) -> bool {
+    let mut index = index;
+    let mut hash = hash_leaf(values);
+    for sibling in path {
+        hash = if index.is_multiple_of(2) {
+            hash_node(&hash, sibling)
+        } else {
+            hash_node(sibling, &hash)
+        };
+        index /= 2;
+    }
+    index == 0 && hash == *root
+}
+
+/// Hash of a leaf holding `values`
+pub fn hash_leaf(values: &[Felt]) -> Digest {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF]);
+    for value in values {
+        hasher.update(&field::to_bytes(value));
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn hash_node(left: &Digest, right: &Digest) -> Digest {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[NODE]);
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves() -> Vec<Vec<Felt>> {
+        (0..8u64)
+            .map(|i| vec![Felt::from(i), Felt::from(i * i)])
+            .collect()
+    }
+
+    #[test]
+    fn test_openings_verify() {
+        let leaves = leaves();
+        let tree = MerkleTree::new(leaves.iter().map(Vec::as_slice));
+        for (index, leaf) in leaves.iter().enumerate() {
+            let path = tree.path(index);
+            assert_eq!(path.len(), 3);
+            assert!(verify(&tree.root(), index, leaf, &path));
+        }
+    }
+
+    #[test]
+    fn test_wrong_openings_rejected() {
+        let leaves = leaves();
+        let tree = MerkleTree::new(leaves.iter().map(Vec::as_slice));
+        let path = tree.path(3);
+
+        assert!(!verify(&tree.root(), 2, &leaves[3], &path));
+        assert!(!verify(&tree.root(), 3, &leaves[4], &path));
+        assert!(!verify(&tree.root(), 3 + 8, &leaves[3], &path));
+        assert!(!verify(&tree.root(), 3, &leaves[3], &path[..2]));
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let leaf = [Felt::from(7u64)];
+        let tree = MerkleTree::new([&leaf[..]]);
+        assert!(tree.path(0).is_empty());
+        assert!(verify(&tree.root(), 0, &leaf, &[]));
+    }
+}
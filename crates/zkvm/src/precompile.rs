@@ -0,0 +1,577 @@
+//! Cryptographic precompiles
+//!
+//! Host handlers for the system calls in `privl1_zkvm_guest::precompile`.
+//! A call is streamed one word per `ecall`: a header with the call's
+//! parameter, [`input_words`] input words, then [`output_words`] output
+//! words. [`crate::Vm`] collects the inputs and runs the call natively with
+//! `privl1-crypto` on the last one, appending it to its table in
+//! [`PrecompileTables`]. Calls never read or write guest memory.
+//!
+//! A call with malformed input faults with
+//! [`ZkvmError::InvalidSyscallInput`]. Invalid keys or signatures passed for
+//! verification are not faults: the call outputs 0.
+//!
+//! The [AIR](crate::air) constrains every call to compute what its handler
+//! here does, so the words stay private to the prover.
+
+use std::sync::OnceLock;
+
+use pasta_curves::arithmetic::{Coordinates, CurveAffine};
+use pasta_curves::group::ff::{FromUniformBytes, PrimeField};
+use pasta_curves::group::{Curve, Group, GroupEncoding};
+use pasta_curves::pallas;
+use privl1_crypto::hash::bytes_to_base;
+use privl1_crypto::{MerkleProof, MerkleRoot, PedersenCommitment, PoseidonHash, Scalar};
+
+use crate::trace::{
+    MerkleCall, NullifierCall, PedersenCall, PoseidonCall, PrecompileTables, SignatureCall,
+};
+use crate::{Result, ZkvmError};
+
+pub use privl1_zkvm_guest::precompile::{
+    CHALLENGE_LEN, MAX_POSEIDON_INPUTS, MERKLE_DEPTH, SIGNATURE_LEN, SYS_MERKLE_VERIFY,
+    SYS_NOTE_NULLIFIER, SYS_PEDERSEN_COMMIT, SYS_POSEIDON, SYS_SIGNATURE_VERIFY,
+};
+
+/// Words of a field element, scalar or point
+pub const ELEMENT_WORDS: usize = 8;
+
+/// Whether `number` is a precompile system call
+pub fn is_precompile(number: u32) -> bool {
+    matches!(
+        number,
+        SYS_POSEIDON
+            | SYS_PEDERSEN_COMMIT
+            | SYS_MERKLE_VERIFY
+            | SYS_NOTE_NULLIFIER
+            | SYS_SIGNATURE_VERIFY
+    )
+}
+
+/// Input words of a call to precompile `number` with header parameter
+/// `param`, or the fault of a header with that parameter
+pub fn input_words(number: u32, param: u32) -> Result<usize> {
+    match number {
+        SYS_POSEIDON => {
+            let count = param as usize;
+            if count == 0 || count > MAX_POSEIDON_INPUTS {
+                return Err(invalid(
+                    number,
+                    format!("expected 1 to {MAX_POSEIDON_INPUTS} inputs, got {count}"),
+                ));
+            }
+            Ok(ELEMENT_WORDS * count)
+        }
+        SYS_PEDERSEN_COMMIT | SYS_MERKLE_VERIFY | SYS_NOTE_NULLIFIER | SYS_SIGNATURE_VERIFY
+            if param != 0 =>
+        {
+            Err(invalid(
+                number,
+                format!("expected parameter 0, got {param}"),
+            ))
+        }
+        SYS_PEDERSEN_COMMIT => Ok(2 + ELEMENT_WORDS),
+        SYS_MERKLE_VERIFY => Ok(ELEMENT_WORDS * (2 + MERKLE_DEPTH) + 2),
+        SYS_NOTE_NULLIFIER => Ok(2 + ELEMENT_WORDS * 4 + 2),
+        SYS_SIGNATURE_VERIFY => Ok(ELEMENT_WORDS * 3 + CHALLENGE_LEN / 4),
+        _ => Err(ZkvmError::UnsupportedSyscall(number)),
+    }
+}
+
+/// Output words of a call to precompile `number`
+pub fn output_words(number: u32) -> usize {
+    match number {
+        SYS_POSEIDON | SYS_PEDERSEN_COMMIT => ELEMENT_WORDS,
+        SYS_NOTE_NULLIFIER => 2 * ELEMENT_WORDS,
+        _ => 1,
+    }
+}
+
+/// Execute precompile `number`, started at `cycle` with parameter `param`, on
+/// its input `words` and return its output words
+pub(crate) fn call(
+    number: u32,
+    param: u32,
+    words: &[u32],
+    cycle: u64,
+    tables: &mut PrecompileTables,
+) -> Result<Vec<u32>> {
+    debug_assert_eq!(input_words(number, param).ok(), Some(words.len()));
+    match number {
+        SYS_POSEIDON => poseidon(words, cycle, tables),
+        SYS_PEDERSEN_COMMIT => pedersen_commit(words, cycle, tables),
+        SYS_MERKLE_VERIFY => merkle_verify(words, cycle, tables),
+        SYS_NOTE_NULLIFIER => note_nullifier(words, cycle, tables),
+        SYS_SIGNATURE_VERIFY => signature_verify(words, cycle, tables),
+        _ => Err(ZkvmError::UnsupportedSyscall(number)),
+    }
+}
+
+fn poseidon(words: &[u32], cycle: u64, tables: &mut PrecompileTables) -> Result<Vec<u32>> {
+    let inputs: Vec<[u8; 32]> = words.chunks(ELEMENT_WORDS).map(element).collect();
+    let fields = inputs
+        .iter()
+        .map(|bytes| base(SYS_POSEIDON, bytes))
+        .collect::<Result<Vec<_>>>()?;
+    let output = poseidon_hash(&fields).to_repr();
+
+    tables.poseidon.push(PoseidonCall {
+        cycle,
+        inputs,
+        output,
+    });
+    Ok(split(&output))
+}
+
+fn pedersen_commit(words: &[u32], cycle: u64, tables: &mut PrecompileTables) -> Result<Vec<u32>> {
+    let value = join(words[0], words[1]);
+    let blinding = element(&words[2..]);
+    let scalar = Scalar::from_bytes(&blinding)
+        .map_err(|_| invalid(SYS_PEDERSEN_COMMIT, "non-canonical blinding"))?;
+    let commitment = pedersen().commit_with_blinding(value, scalar).to_bytes();
+
+    tables.pedersen.push(PedersenCall {
+        cycle,
+        value,
+        blinding,
+        commitment,
+    });
+    Ok(split(&commitment))
+}
+
+fn merkle_verify(words: &[u32], cycle: u64, tables: &mut PrecompileTables) -> Result<Vec<u32>> {
+    let mut elements = words.chunks_exact(ELEMENT_WORDS).map(element);
+    let leaf = elements.next().expect("a leaf");
+    let root = elements.next().expect("a root");
+    let path: Vec<[u8; 32]> = elements.collect();
+    let position = join(words[words.len() - 2], words[words.len() - 1]);
+
+    let proof = MerkleProof {
+        path: path.clone(),
+        position,
+    };
+    let valid = proof.verify(&leaf, &MerkleRoot::from_bytes(root));
+
+    tables.merkle.push(MerkleCall {
+        cycle,
+        leaf,
+        root,
+        path,
+        position,
+        valid,
+    });
+    Ok(vec![valid as u32])
+}
+
+fn note_nullifier(words: &[u32], cycle: u64, tables: &mut PrecompileTables) -> Result<Vec<u32>> {
+    let value = join(words[0], words[1]);
+    let mut elements = words[2..].chunks_exact(ELEMENT_WORDS).map(element);
+    let mut next = || elements.next().expect("four elements");
+    let (asset, owner, randomness, nullifier_key) = (next(), next(), next(), next());
+    let position = join(words[words.len() - 2], words[words.len() - 1]);
+
+    let (owner_x, owner_y) = point(&owner)
+        .map(|point| coordinates(&point))
+        .ok_or_else(|| invalid(SYS_NOTE_NULLIFIER, "invalid owner key"))?;
+    if scalar(&randomness).is_none() {
+        return Err(invalid(SYS_NOTE_NULLIFIER, "non-canonical randomness"));
+    }
+    if scalar(&nullifier_key).is_none() {
+        return Err(invalid(SYS_NOTE_NULLIFIER, "non-canonical nullifier key"));
+    }
+    // cm = Poseidon(value, asset, owner.x, owner.y, rcm) and
+    // nf = Poseidon(nk, cm, position), as `privl1-crypto` derives them
+    let commitment = poseidon_hash(&[
+        pallas::Base::from(value),
+        bytes_to_base(&asset),
+        owner_x,
+        owner_y,
+        bytes_to_base(&randomness),
+    ]);
+    let nullifier = poseidon_hash(&[
+        bytes_to_base(&nullifier_key),
+        commitment,
+        pallas::Base::from(position),
+    ]);
+    let (commitment, nullifier) = (commitment.to_repr(), nullifier.to_repr());
+
+    tables.nullifier.push(NullifierCall {
+        cycle,
+        value,
+        asset,
+        owner,
+        randomness,
+        nullifier_key,
+        position,
+        commitment,
+        nullifier,
+    });
+    Ok(split(&[commitment, nullifier].concat()))
+}
+
+fn signature_verify(words: &[u32], cycle: u64, tables: &mut PrecompileTables) -> Result<Vec<u32>> {
+    let key = element(&words[..ELEMENT_WORDS]);
+    let signature: Vec<u8> = words[ELEMENT_WORDS..3 * ELEMENT_WORDS]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let challenge: Vec<u8> = words[3 * ELEMENT_WORDS..]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let valid = signature_valid(
+        &key,
+        signature[..32]
+            .try_into()
+            .expect("32-byte nonce commitment"),
+        signature[32..].try_into().expect("32-byte response"),
+        challenge[..].try_into().expect("64-byte challenge"),
+    );
+
+    tables.signature.push(SignatureCall {
+        cycle,
+        key,
+        signature,
+        challenge,
+        valid,
+    });
+    Ok(vec![valid as u32])
+}
+
+/// Poseidon hash of 1 to [`MAX_POSEIDON_INPUTS`] field elements
+pub(crate) fn poseidon_hash(inputs: &[pallas::Base]) -> pallas::Base {
+    let hash = match *inputs {
+        [a] => PoseidonHash::hash([a]),
+        [a, b] => PoseidonHash::hash([a, b]),
+        [a, b, c] => PoseidonHash::hash([a, b, c]),
+        [a, b, c, d] => PoseidonHash::hash([a, b, c, d]),
+        [a, b, c, d, e] => PoseidonHash::hash([a, b, c, d, e]),
+        _ => unreachable!("input count checked"),
+    };
+    hash.to_field()
+}
+
+/// Whether `(r, s)` is a Schnorr signature for `challenge` under `key`: the
+/// key is a point other than the identity, `r` a point, `s` a canonical
+/// scalar, and `[s]G = R + [c]K` for the challenge `c` reduced modulo the
+/// scalar field order
+pub(crate) fn signature_valid(
+    key: &[u8; 32],
+    r: &[u8; 32],
+    s: &[u8; 32],
+    challenge: &[u8; CHALLENGE_LEN],
+) -> bool {
+    let (Some(key), Some(r), Some(s)) = (point(key), point(r), scalar(s)) else {
+        return false;
+    };
+    let c = pallas::Scalar::from_uniform_bytes(challenge);
+    !bool::from(key.is_identity()) && pallas::Point::generator() * s == r + key * c
+}
+
+/// The Pedersen commitment scheme of `privl1-crypto`
+fn pedersen() -> &'static PedersenCommitment {
+    static PEDERSEN: OnceLock<PedersenCommitment> = OnceLock::new();
+    PEDERSEN.get_or_init(PedersenCommitment::new)
+}
+
+/// The generator `H` Pedersen commitments multiply the blinding by
+pub(crate) fn blinding_base() -> pallas::Point {
+    *pedersen().blinding_generator().inner()
+}
+
+/// Decode a point from its canonical encoding
+pub(crate) fn point(bytes: &[u8; 32]) -> Option<pallas::Point> {
+    pallas::Point::from_bytes(bytes).into()
+}
+
+/// Affine coordinates of `point`, `(0, 0)` for the identity
+pub(crate) fn coordinates(point: &pallas::Point) -> (pallas::Base, pallas::Base) {
+    Option::from(point.to_affine().coordinates())
+        .map(|c: Coordinates<pallas::Affine>| (*c.x(), *c.y()))
+        .unwrap_or_default()
+}
+
+fn scalar(bytes: &[u8; 32]) -> Option<pallas::Scalar> {
+    pallas::Scalar::from_repr(*bytes).into()
+}
+
+fn base(number: u32, bytes: &[u8; 32]) -> Result<pallas::Base> {
+    Option::from(pallas::Base::from_repr(*bytes))
+        .ok_or_else(|| invalid(number, "non-canonical field element"))
+}
+
+/// The 32 bytes of eight little-endian words
+pub(crate) fn element(words: &[u32]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// Little-endian words of `bytes`, a whole number of words
+pub(crate) fn split(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().expect("4-byte chunks")))
+        .collect()
+}
+
+fn join(low: u32, high: u32) -> u64 {
+    (high as u64) << 32 | low as u64
+}
+
+fn invalid(number: u32, reason: impl Into<String>) -> ZkvmError {
+    ZkvmError::InvalidSyscallInput {
+        number,
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{AluOp, Instruction};
+    use crate::vm::{Vm, REG_A0, REG_A7, SYS_EXIT};
+    use privl1_crypto::keys::FullKeys;
+    use privl1_crypto::{IncrementalMerkleTree, Note};
+    use privl1_zkvm_guest::precompile::{asset_hash, signature_challenge};
+
+    fn field(value: u64) -> [u8; 32] {
+        pallas::Base::from(value).to_repr()
+    }
+
+    fn run(number: u32, param: u32, words: &[u32]) -> (Result<Vec<u32>>, PrecompileTables) {
+        let mut tables = PrecompileTables::default();
+        assert_eq!(input_words(number, param).unwrap(), words.len());
+        let result = call(number, param, words, 7, &mut tables);
+        (result, tables)
+    }
+
+    #[test]
+    fn test_poseidon_matches_crypto() {
+        let inputs = [field(1), field(2), field(3)];
+        let (output, tables) = run(SYS_POSEIDON, 3, &split(&inputs.concat()));
+
+        let expected = PoseidonHash::hash([1u64, 2, 3].map(pallas::Base::from))
+            .to_field()
+            .to_repr();
+        assert_eq!(output.unwrap(), split(&expected));
+        assert_eq!(output_words(SYS_POSEIDON), ELEMENT_WORDS);
+        assert_eq!(tables.poseidon.len(), 1);
+        assert_eq!(tables.poseidon[0].cycle, 7);
+        assert_eq!(tables.poseidon[0].inputs, inputs.to_vec());
+        assert_eq!(tables.poseidon[0].output, expected);
+    }
+
+    #[test]
+    fn test_malformed_input_faults() {
+        let (result, tables) = run(SYS_POSEIDON, 2, &[u32::MAX; 16]);
+        assert!(matches!(
+            result,
+            Err(ZkvmError::InvalidSyscallInput {
+                number: SYS_POSEIDON,
+                ..
+            })
+        ));
+        assert!(tables.is_empty());
+
+        for (number, param) in [
+            (SYS_POSEIDON, 0),
+            (SYS_POSEIDON, MAX_POSEIDON_INPUTS as u32 + 1),
+            (SYS_PEDERSEN_COMMIT, 1),
+            (SYS_MERKLE_VERIFY, 1),
+            (SYS_NOTE_NULLIFIER, 2),
+            (SYS_SIGNATURE_VERIFY, 64),
+        ] {
+            assert!(matches!(
+                input_words(number, param),
+                Err(ZkvmError::InvalidSyscallInput { number: n, .. }) if n == number
+            ));
+        }
+        assert!(matches!(
+            input_words(SYS_EXIT, 0),
+            Err(ZkvmError::UnsupportedSyscall(SYS_EXIT))
+        ));
+    }
+
+    #[test]
+    fn test_pedersen_commit_matches_crypto() {
+        let value = (5 << 32) | 7;
+        let blinding = Scalar::from_inner(pallas::Scalar::from(99u64));
+        let mut words = vec![7, 5];
+        words.extend(split(&blinding.to_bytes()));
+
+        let (output, tables) = run(SYS_PEDERSEN_COMMIT, 0, &words);
+        let expected = PedersenCommitment::new()
+            .commit_with_blinding(value, blinding)
+            .to_bytes();
+        assert_eq!(output.unwrap(), split(&expected));
+        assert_eq!(tables.pedersen[0].value, value);
+        assert_eq!(tables.pedersen[0].commitment, expected);
+
+        // A blinding of q or more is malformed
+        words[2..].fill(u32::MAX);
+        let (result, tables) = run(SYS_PEDERSEN_COMMIT, 0, &words);
+        assert!(result.is_err());
+        assert!(tables.is_empty());
+    }
+
+    #[test]
+    fn test_note_nullifier_matches_crypto() {
+        let keys = FullKeys::from_seed(&[5; 32]);
+        let asset_id = [3; 32];
+        let randomness = Scalar::from_inner(pallas::Scalar::from(11u64));
+        let note = Note::with_randomness(1_000, keys.public, asset_id, randomness);
+        let words = |owner: &[u8; 32]| {
+            let mut words = vec![1_000, 0];
+            words.extend(split(&asset_hash(&asset_id)));
+            words.extend(split(owner));
+            words.extend(split(&randomness.to_bytes()));
+            words.extend(split(&keys.nullifier.as_scalar().to_bytes()));
+            words.extend([9, 0]);
+            words
+        };
+
+        let (output, tables) = run(SYS_NOTE_NULLIFIER, 0, &words(&keys.public.to_bytes()));
+        let commitment = note.commitment().leaf();
+        let nullifier = *keys.nullifier.derive_nullifier(&note, 9).as_bytes();
+        assert_eq!(output.unwrap(), split(&[commitment, nullifier].concat()));
+        assert_eq!(output_words(SYS_NOTE_NULLIFIER), 2 * ELEMENT_WORDS);
+        assert_eq!(tables.nullifier[0].commitment, commitment);
+        assert_eq!(tables.nullifier[0].nullifier, nullifier);
+
+        // An owner key that does not decode is malformed
+        let (result, tables) = run(SYS_NOTE_NULLIFIER, 0, &words(&[0xff; 32]));
+        assert!(result.is_err());
+        assert!(tables.is_empty());
+    }
+
+    #[test]
+    fn test_merkle_verify() {
+        let mut tree = IncrementalMerkleTree::new();
+        for leaf in 1..=3 {
+            tree.append(field(leaf)).unwrap();
+            if leaf == 2 {
+                tree.mark().unwrap();
+            }
+        }
+        let proof = tree.prove(1).unwrap();
+        let words = |position: u32| {
+            let mut words = split(&field(2));
+            words.extend(split(tree.root().as_bytes()));
+            words.extend(split(&proof.path.concat()));
+            words.extend([position, 0]);
+            words
+        };
+
+        let (valid, tables) = run(SYS_MERKLE_VERIFY, 0, &words(1));
+        assert_eq!(valid.unwrap(), vec![1]);
+        assert!(tables.merkle[0].valid);
+        assert_eq!(tables.merkle[0].path, proof.path);
+
+        let (wrong_position, tables) = run(SYS_MERKLE_VERIFY, 0, &words(2));
+        assert_eq!(wrong_position.unwrap(), vec![0]);
+        assert!(!tables.merkle[0].valid);
+    }
+
+    #[test]
+    fn test_signature_verify() {
+        let keys = FullKeys::from_seed(&[5; 32]);
+        let key = keys.spending.validating_key().to_bytes();
+        let message = b"transfer 10";
+        let signature = keys.spending.sign(message).to_bytes();
+        let r: [u8; 32] = signature[..32].try_into().unwrap();
+        let words = |key: &[u8; 32], message: &[u8]| {
+            let mut words = split(key);
+            words.extend(split(&signature));
+            words.extend(split(&signature_challenge(&r, key, message)));
+            words
+        };
+
+        // The guest's challenge is the one privl1-crypto signs
+        let (valid, tables) = run(SYS_SIGNATURE_VERIFY, 0, &words(&key, message));
+        assert_eq!(valid.unwrap(), vec![1]);
+        assert_eq!(tables.signature[0].signature, signature);
+
+        // Another message, a key that does not decode, and the identity
+        let (wrong_message, _) = run(SYS_SIGNATURE_VERIFY, 0, &words(&key, b"transfer 11"));
+        assert_eq!(wrong_message.unwrap(), vec![0]);
+        for key in [[0xff; 32], [0; 32]] {
+            let (bad_key, tables) = run(SYS_SIGNATURE_VERIFY, 0, &words(&key, message));
+            assert_eq!(bad_key.unwrap(), vec![0]);
+            assert!(!tables.signature[0].valid);
+        }
+    }
+
+    #[test]
+    fn test_guest_call_is_traced() {
+        let li = |rd: u8, imm: i32| Instruction::AluImm {
+            op: AluOp::Add,
+            rd,
+            rs1: 0,
+            imm,
+        };
+        // Hash the field element 5, then exit with the low byte of the hash
+        let mut program = vec![li(REG_A7, SYS_POSEIDON as i32), li(REG_A0, 1)];
+        program.push(Instruction::Ecall);
+        program.extend([li(REG_A0, 5), Instruction::Ecall, li(REG_A0, 0)]);
+        program.extend([Instruction::Ecall; 2 * ELEMENT_WORDS - 1]);
+        program.extend([li(REG_A7, SYS_EXIT as i32), Instruction::Ecall]);
+        let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+        let mut vm = Vm::from_program(0x1000, &words).unwrap();
+        let trace = vm.run(100).unwrap();
+
+        let expected = split(
+            &PoseidonHash::hash([pallas::Base::from(5)])
+                .to_field()
+                .to_repr(),
+        );
+        let call = &trace.precompiles.poseidon[0];
+        assert_eq!(
+            trace.rows[call.cycle as usize].rs1.unwrap().value,
+            SYS_POSEIDON
+        );
+        let results: Vec<u32> = trace
+            .rows
+            .iter()
+            .filter(|row| row.instruction == Instruction::Ecall.encode().unwrap())
+            .map(|row| row.rd.map_or(0, |rd| rd.value))
+            .collect();
+        assert_eq!(results[..1 + ELEMENT_WORDS], [0; 1 + ELEMENT_WORDS]);
+        assert_eq!(
+            results[1 + ELEMENT_WORDS..1 + 2 * ELEMENT_WORDS],
+            expected[..]
+        );
+        assert_eq!(trace.precompiles.len(), 1);
+        assert_eq!(trace.exit_code, expected[ELEMENT_WORDS - 1]);
+        assert!(vm.precompiles().is_empty());
+    }
+
+    #[test]
+    fn test_unfinished_calls_fault() {
+        let li = |rd: u8, imm: i32| Instruction::AluImm {
+            op: AluOp::Add,
+            rd,
+            rs1: 0,
+            imm,
+        };
+        let start = [
+            li(REG_A7, SYS_MERKLE_VERIFY as i32),
+            Instruction::Ecall,
+            Instruction::Ecall,
+        ];
+        for number in [SYS_EXIT, SYS_POSEIDON] {
+            let mut program = start.to_vec();
+            program.extend([li(REG_A7, number as i32), Instruction::Ecall]);
+            let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+            let mut vm = Vm::from_program(0x1000, &words).unwrap();
+            assert!(matches!(
+                vm.run(100),
+                Err(ZkvmError::InvalidSyscallInput { number: n, .. }) if n == number
+            ));
+            assert_eq!(vm.pc(), 0x1000 + 16);
+            assert_eq!(vm.exit_code(), None);
+        }
+    }
+}
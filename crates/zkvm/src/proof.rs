@@ -0,0 +1,258 @@
+//! Proofs of execution
+//!
+//! A [`Proof`] shows a [`Statement`]: that the program with a given hash,
+//! run on a public input and some private input, exits with a given code
+//! after using a given amount of gas under the default [`GasSchedule`],
+//! having committed a public output. It is a STARK over the Pallas base
+//! field for the [AIR](crate::air): Merkle commitments to the low-degree
+//! extended main and aux traces and to the composition polynomial, a DEEP
+//! opening at a random point, and a FRI proof.
+//!
+//! Proofs are zero-knowledge. The traces end with [`RANDOM_ROWS`] random
+//! rows the constraints skip, one more than the values of each column a
+//! proof opens: its value at the out-of-domain point and the next row, and
+//! one per query. The composition polynomial is split into chunks below
+//! `n - RANDOM_ROWS` blinded with random polynomials that cancel in their
+//! sum, and committed next to a random mask column, so the openings of the
+//! composition table and the DEEP polynomial are random too. Precompile
+//! calls are constrained by the AIR like every other instruction.
+
+use pasta_curves::group::ff::Field;
+use serde::{Deserialize, Serialize};
+
+use crate::air::{
+    self, Air, Challenges, Constraints, Frame, AUX_WIDTH, MAIN_WIDTH, PERIOD, RANDOM_ROWS,
+};
+use crate::field::{self, Felt};
+use crate::fri::{FriOpening, FriProof};
+use crate::merkle::Digest;
+use crate::transcript::Transcript;
+
+/// Blowup of the low-degree extension
+pub(crate) const BLOWUP: usize = 8;
+/// Queries against the extended traces and FRI
+pub(crate) const QUERIES: usize = 40;
+/// Chunks the composition polynomial is split into, each below
+/// `n - RANDOM_ROWS` before blinding
+pub(crate) const COMPOSITION_CHUNKS: usize = 3;
+
+const PROTOCOL: &str = "privl1-zkvm stark v2";
+
+/// The claim a [`Proof`] shows
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Statement {
+    /// [`Program::hash`](crate::Program::hash) of the guest
+    pub program_hash: [u8; 32],
+    pub public_input: Vec<u8>,
+    pub public_output: Vec<u8>,
+    pub exit_code: u32,
+    pub gas_used: u64,
+}
+
+/// Values of a committed row and its Merkle path
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opening {
+    pub values: Vec<[u8; 32]>,
+    pub path: Vec<Digest>,
+}
+
+/// Openings at one query position
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    pub main: Opening,
+    pub aux: Opening,
+    pub composition: Opening,
+    pub fri: Vec<FriOpening>,
+}
+
+/// A STARK proof of a [`Statement`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    pub log_trace_len: u32,
+    pub main_root: Digest,
+    pub aux_root: Digest,
+    pub composition_root: Digest,
+    /// Main and aux trace columns at the out-of-domain point `z` and at the
+    /// next row, `z * g`
+    pub main_ood: [Vec<[u8; 32]>; 2],
+    pub aux_ood: [Vec<[u8; 32]>; 2],
+    /// Blinded composition chunks and the mask column at `z`
+    pub composition_ood: Vec<[u8; 32]>,
+    pub fri: FriProof,
+    pub queries: Vec<Query>,
+}
+
+/// Decoded out-of-domain values
+pub(crate) struct Ood {
+    pub main: [Vec<Felt>; 2],
+    pub aux: [Vec<Felt>; 2],
+    pub composition: Vec<Felt>,
+}
+
+impl Ood {
+    pub fn absorb(&self, transcript: &mut Transcript) {
+        for values in self.main.iter().chain(&self.aux) {
+            transcript.absorb_felts(values);
+        }
+        transcript.absorb_felts(&self.composition);
+    }
+
+    /// The DEEP composition at `x`: every committed column `t` contributes
+    /// `(t(x) - t(z)) / (x - z)`, and trace columns `(t(x) - t(zg)) / (x - zg)`,
+    /// weighted by successive powers of `lambda`
+    pub fn deep(
+        &self,
+        lambda: Felt,
+        rows: [&[Felt]; 3],
+        z_inverse: Felt,
+        zg_inverse: Felt,
+    ) -> Felt {
+        let [main, aux, composition] = rows;
+        let mut power = Felt::ONE;
+        let (mut at_z, mut at_zg) = (Felt::ZERO, Felt::ZERO);
+        for (row, ood) in [(main, &self.main), (aux, &self.aux)] {
+            for ((value, z), zg) in row.iter().zip(&ood[0]).zip(&ood[1]) {
+                at_z += power * (*value - z);
+                power *= lambda;
+                at_zg += power * (*value - zg);
+                power *= lambda;
+            }
+        }
+        for (value, z) in composition.iter().zip(&self.composition) {
+            at_z += power * (*value - z);
+            power *= lambda;
+        }
+        at_z * z_inverse + at_zg * zg_inverse
+    }
+}
+
+/// Folds constraint values with successive powers of a challenge, by the
+/// rows they hold on
+pub(crate) struct Folder {
+    gamma: Felt,
+    power: Felt,
+    /// Every row, transitions, first row, last row
+    sums: [Felt; 4],
+}
+
+impl Folder {
+    /// Fold the constraints on a main and aux frame, given the periodic
+    /// columns
+    pub fn fold(
+        air: &Air,
+        main: &Frame,
+        aux: &Frame,
+        periodic: &[Felt],
+        challenges: &Challenges,
+        public_sum: Felt,
+        gamma: Felt,
+    ) -> Self {
+        let mut folder = Self {
+            gamma,
+            power: Felt::ONE,
+            sums: [Felt::ZERO; 4],
+        };
+        air.evaluate_main(main, periodic, &mut folder);
+        air.evaluate_aux(main, aux, periodic, challenges, public_sum, &mut folder);
+        folder
+    }
+
+    /// The composition polynomial at `x`: each sum divided by the zerofier
+    /// of its rows, given `random(x)`, `x - g^l` for the last row `g^l` the
+    /// constraints hold on, and the inverses of `x^n - 1`, `x - 1` and
+    /// `x - g^l`
+    pub fn composition(&self, random: Felt, x_last: Felt, inverses: [Felt; 3]) -> Felt {
+        let [every, transition, first, last] = self.sums;
+        let [zerofier, first_inverse, last_inverse] = inverses;
+        (every + transition * x_last) * random * zerofier
+            + first * first_inverse
+            + last * last_inverse
+    }
+
+    fn add(&mut self, kind: usize, value: Felt) {
+        self.sums[kind] += self.power * value;
+        self.power *= self.gamma;
+    }
+}
+
+impl Constraints for Folder {
+    fn every(&mut self, _: &'static str, value: Felt) {
+        self.add(0, value);
+    }
+
+    fn transition(&mut self, _: &'static str, value: Felt) {
+        self.add(1, value);
+    }
+
+    fn first(&mut self, _: &'static str, value: Felt) {
+        self.add(2, value);
+    }
+
+    fn last(&mut self, _: &'static str, value: Felt) {
+        self.add(3, value);
+    }
+}
+
+/// A transcript bound to the statement and trace length
+pub(crate) fn transcript(statement: &Statement, log_trace_len: u32) -> Transcript {
+    let mut transcript = Transcript::new(PROTOCOL);
+    transcript.absorb(&statement.program_hash);
+    transcript.absorb(&statement.public_input);
+    transcript.absorb(&statement.public_output);
+    transcript.absorb_u64(statement.exit_code as u64);
+    transcript.absorb_u64(statement.gas_used);
+    transcript.absorb_u64(log_trace_len as u64);
+    transcript
+}
+
+/// Draw the out-of-domain point, outside the trace domain of order `n` and
+/// the extended domain
+pub(crate) fn ood_point(transcript: &mut Transcript, n: usize) -> Felt {
+    let extended_shift = field::coset_shift().pow_vartime([(n * BLOWUP) as u64]);
+    loop {
+        let z = transcript.challenge();
+        if z.pow_vartime([n as u64]) != Felt::ONE
+            && z.pow_vartime([(n * BLOWUP) as u64]) != extended_shift
+        {
+            return z;
+        }
+    }
+}
+
+/// `g^l`, the last row the constraints hold on in a trace of `n` rows
+pub(crate) fn last_row(n: usize) -> Felt {
+    field::root_of_unity(n).pow_vartime([air::last_row(n) as u64])
+}
+
+/// The product of `x - g^i` over the random rows, which the zerofiers of
+/// the constraints leave out
+pub(crate) fn random(x: Felt, n: usize) -> Felt {
+    let g = field::root_of_unity(n);
+    let mut row = g.pow_vartime([(n - RANDOM_ROWS) as u64]);
+    let mut product = Felt::ONE;
+    for _ in 0..RANDOM_ROWS {
+        product *= x - row;
+        row *= g;
+    }
+    product
+}
+
+/// Coefficients of each periodic column as a polynomial in `x^(n / PERIOD)`
+pub(crate) fn periodic_columns(air: &Air) -> Vec<Vec<Felt>> {
+    let rows: Vec<Vec<Felt>> = (0..PERIOD).map(|row| air.periodic(row)).collect();
+    (0..rows[0].len())
+        .map(|column| {
+            let values: Vec<Felt> = rows.iter().map(|row| row[column]).collect();
+            field::coset_interpolate(&values, Felt::ONE)
+        })
+        .collect()
+}
+
+/// Length of the chunks of the composition polynomial, before blinding
+pub(crate) fn chunk_len(n: usize) -> usize {
+    n - RANDOM_ROWS
+}
+
+/// Width of each committed row: main trace, aux trace, composition chunks
+/// and mask
+pub(crate) const WIDTHS: [usize; 3] = [MAIN_WIDTH, AUX_WIDTH, COMPOSITION_CHUNKS + 1];
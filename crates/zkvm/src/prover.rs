@@ -0,0 +1,431 @@
+//! STARK prover
+//!
+//! [`prove`] runs a guest like [`abi::execute`], lays the run out as a
+//! [`Witness`] and proves it. The traces are interpolated over the subgroup
+//! of order `n`, the trace length, and extended over a coset of order
+//! `BLOWUP * n`, where the constraints are folded into the composition
+//! polynomial. Every extended table is committed row by row, so that a query
+//! opens one leaf of each. The main trace is too wide to keep extended: it
+//! is extended a coset of the trace domain at a time, once to commit and
+//! once to compose, and its queried rows are evaluated from its
+//! coefficients.
+
+use pasta_curves::group::ff::Field;
+use rand::rngs::OsRng;
+
+use crate::abi;
+use crate::air::{self, Air, Challenges, Frame, PERIOD, RANDOM_ROWS};
+use crate::elf::Program;
+use crate::field::{self, Felt};
+use crate::fri::FriProver;
+use crate::gas::{GasSchedule, ResourceLimits};
+use crate::merkle::{self, MerkleTree};
+use crate::proof::{
+    self, Folder, Ood, Opening, Proof, Query, Statement, BLOWUP, COMPOSITION_CHUNKS, QUERIES,
+};
+use crate::witness::Witness;
+use crate::{Result, ZkvmError};
+
+/// Run `program` on the given inputs within `limits` and prove the run
+pub fn prove(
+    program: &Program,
+    private_input: &[u8],
+    public_input: &[u8],
+    limits: ResourceLimits,
+) -> Result<(Statement, Proof)> {
+    let vm = abi::load(program, private_input, public_input)?;
+    if vm.memory().page_count() > limits.max_pages {
+        return Err(ZkvmError::MemoryLimit {
+            pages: vm.memory().page_count(),
+            max: limits.max_pages,
+        });
+    }
+    let max_output = limits.max_output;
+    let witness = Witness::build(vm.with_limits(limits))?;
+    if witness.public_output.len() > max_output {
+        return Err(ZkvmError::IoTooLarge {
+            len: witness.public_output.len(),
+            max: max_output,
+        });
+    }
+
+    let statement = Statement {
+        program_hash: program.hash(),
+        public_input: public_input.to_vec(),
+        public_output: witness.public_output.clone(),
+        exit_code: witness.exit_code,
+        gas_used: witness.gas_used,
+    };
+    let proof = prove_witness(&statement, &witness)?;
+    Ok((statement, proof))
+}
+
+/// Prove `witness`, a run with the claims of `statement`
+pub(crate) fn prove_witness(statement: &Statement, witness: &Witness) -> Result<Proof> {
+    let n = witness.rows.len();
+    let size = n * BLOWUP;
+    let shift = field::coset_shift();
+    let log_trace_len = n.trailing_zeros();
+    let air = Air::new(witness.entry, &GasSchedule::default());
+    let mut transcript = proof::transcript(statement, log_trace_len);
+
+    let main = Committed::lean(&witness.rows, size);
+    transcript.absorb(&main.tree.root());
+    let challenges = Challenges {
+        alpha: transcript.challenge(),
+        beta: transcript.challenge(),
+    };
+    let aux = Committed::trace(&witness.aux(&air, &challenges), size);
+    transcript.absorb(&aux.tree.root());
+    let gamma = transcript.challenge();
+
+    // The composition polynomial over the extended domain, a coset of the
+    // main trace's extension at a time
+    let public_sum = air::public_sum(
+        &challenges,
+        &witness.public_memory,
+        &witness.output,
+        witness.exit_code,
+        witness.gas_used,
+    );
+    let root = field::root_of_unity(size);
+    let last_row = proof::last_row(n);
+    let points: Vec<Felt> = std::iter::successors(Some(shift), |x| Some(*x * root))
+        .take(size)
+        .collect();
+    let mut inverses: Vec<Felt> = points
+        .iter()
+        .flat_map(|x| {
+            [
+                x.pow_vartime([n as u64]) - Felt::ONE,
+                *x - Felt::ONE,
+                *x - last_row,
+            ]
+        })
+        .collect();
+    field::batch_invert(&mut inverses);
+    let period = BLOWUP * PERIOD;
+    let periodic: Vec<Vec<Felt>> = proof::periodic_columns(&air)
+        .iter()
+        .map(|column| {
+            field::coset_evaluate(column, shift.pow_vartime([(n / PERIOD) as u64]), period)
+        })
+        .collect();
+    let mut composition = vec![Felt::ZERO; size];
+    for k in 0..BLOWUP {
+        let rows = main.coset(k);
+        for j in 0..n {
+            let i = k + BLOWUP * j;
+            let next = (j + 1) % n;
+            let periodic: Vec<Felt> = periodic.iter().map(|column| column[i % period]).collect();
+            let folder = Folder::fold(
+                &air,
+                &Frame {
+                    current: &rows[j],
+                    next: &rows[next],
+                },
+                &Frame {
+                    current: &aux.rows[i],
+                    next: &aux.rows[(i + BLOWUP) % size],
+                },
+                &periodic,
+                &challenges,
+                public_sum,
+                gamma,
+            );
+            let inverses = [inverses[3 * i], inverses[3 * i + 1], inverses[3 * i + 2]];
+            composition[i] =
+                folder.composition(proof::random(points[i], n), points[i] - last_row, inverses);
+        }
+    }
+    let coefficients = field::coset_interpolate(&composition, shift);
+    let chunk = proof::chunk_len(n);
+    if coefficients[COMPOSITION_CHUNKS * chunk..]
+        .iter()
+        .any(|c| !bool::from(c.is_zero()))
+    {
+        return Err(ZkvmError::Unprovable(
+            "trace does not satisfy the constraints".into(),
+        ));
+    }
+
+    // Blind the chunks with random polynomials of degree below the random
+    // rows, each added to one chunk's top and taken from the next one's
+    // bottom, and add the mask column
+    let mut chunks: Vec<Vec<Felt>> = coefficients[..COMPOSITION_CHUNKS * chunk]
+        .chunks(chunk)
+        .map(|coefficients| {
+            let mut chunk = coefficients.to_vec();
+            chunk.resize(n, Felt::ZERO);
+            chunk
+        })
+        .collect();
+    for i in 0..COMPOSITION_CHUNKS - 1 {
+        for j in 0..RANDOM_ROWS {
+            let blind = Felt::random(OsRng);
+            chunks[i][chunk + j] += blind;
+            chunks[i + 1][j] -= blind;
+        }
+    }
+    chunks.push((0..n).map(|_| Felt::random(OsRng)).collect());
+    let composition = Committed::new(chunks, size);
+    transcript.absorb(&composition.tree.root());
+
+    // Out-of-domain evaluations and the DEEP composition
+    let z = proof::ood_point(&mut transcript, n);
+    let zg = z * field::root_of_unity(n);
+    let ood = Ood {
+        main: [main.evaluate(z), main.evaluate(zg)],
+        aux: [aux.evaluate(z), aux.evaluate(zg)],
+        composition: composition.evaluate(z),
+    };
+    ood.absorb(&mut transcript);
+    let lambda = transcript.challenge();
+    let deep = deep(
+        lambda,
+        [&main.columns, &aux.columns, &composition.columns],
+        &points,
+        [z, zg],
+    );
+
+    let fri = FriProver::commit(deep, shift, n, &mut transcript);
+    let queries = transcript
+        .indices(QUERIES, size)
+        .into_iter()
+        .map(|index| Query {
+            main: main.open(index, points[index]),
+            aux: aux.open(index, points[index]),
+            composition: composition.open(index, points[index]),
+            fri: fri.open(index),
+        })
+        .collect();
+
+    Ok(Proof {
+        log_trace_len,
+        main_root: main.tree.root(),
+        aux_root: aux.tree.root(),
+        composition_root: composition.tree.root(),
+        main_ood: ood.main.clone().map(|values| encode(&values)),
+        aux_ood: ood.aux.clone().map(|values| encode(&values)),
+        composition_ood: encode(&ood.composition),
+        fri: fri.proof(),
+        queries,
+    })
+}
+
+/// The DEEP composition at every point, as [`Ood::deep`] takes it from the
+/// rows: the columns weighted as there are summed first, so the extension
+/// is of two polynomials rather than every column
+fn deep(lambda: Felt, tables: [&[Vec<Felt>]; 3], points: &[Felt], [z, zg]: [Felt; 2]) -> Vec<Felt> {
+    let n = tables[0][0].len();
+    let (mut at_z, mut at_zg) = (vec![Felt::ZERO; n], vec![Felt::ZERO; n]);
+    let add = |sum: &mut Vec<Felt>, column: &[Felt], weight: Felt| {
+        for (total, coefficient) in sum.iter_mut().zip(column) {
+            *total += weight * coefficient;
+        }
+    };
+    let mut power = Felt::ONE;
+    let [main, aux, composition] = tables;
+    for column in main.iter().chain(aux) {
+        add(&mut at_z, column, power);
+        power *= lambda;
+        add(&mut at_zg, column, power);
+        power *= lambda;
+    }
+    for column in composition {
+        add(&mut at_z, column, power);
+        power *= lambda;
+    }
+
+    let shift = field::coset_shift();
+    let (z_value, zg_value) = (field::evaluate(&at_z, z), field::evaluate(&at_zg, zg));
+    let at_z = field::coset_evaluate(&at_z, shift, points.len());
+    let at_zg = field::coset_evaluate(&at_zg, shift, points.len());
+    let mut quotients: Vec<Felt> = points.iter().flat_map(|x| [*x - z, *x - zg]).collect();
+    field::batch_invert(&mut quotients);
+    (0..points.len())
+        .map(|i| {
+            (at_z[i] - z_value) * quotients[2 * i] + (at_zg[i] - zg_value) * quotients[2 * i + 1]
+        })
+        .collect()
+}
+
+/// Columns extended over the coset and committed row by row
+struct Committed {
+    /// Coefficients of each column
+    columns: Vec<Vec<Felt>>,
+    /// Rows of the extension, unless too large to keep
+    rows: Vec<Vec<Felt>>,
+    tree: MerkleTree,
+}
+
+impl Committed {
+    /// Commit to the columns interpolating trace `rows`
+    fn trace(rows: &[Vec<Felt>], size: usize) -> Self {
+        Self::new(interpolate(rows), size)
+    }
+
+    /// Commit to the columns interpolating trace `rows` without keeping the
+    /// extension: each coset of the trace domain is extended, hashed and
+    /// dropped in turn
+    fn lean(rows: &[Vec<Felt>], size: usize) -> Self {
+        let columns = interpolate(rows);
+        let mut hashes = vec![[0; 32]; size];
+        for k in 0..BLOWUP {
+            for (j, row) in coset(&columns, k).iter().enumerate() {
+                hashes[k + BLOWUP * j] = merkle::hash_leaf(row);
+            }
+        }
+        Self {
+            columns,
+            rows: Vec::new(),
+            tree: MerkleTree::from_leaf_hashes(hashes),
+        }
+    }
+
+    /// Commit to polynomials with coefficients `columns` over the coset of
+    /// order `size`
+    fn new(columns: Vec<Vec<Felt>>, size: usize) -> Self {
+        let extended: Vec<Vec<Felt>> = columns
+            .iter()
+            .map(|column| field::coset_evaluate(column, field::coset_shift(), size))
+            .collect();
+        let rows: Vec<Vec<Felt>> = (0..size)
+            .map(|i| extended.iter().map(|column| column[i]).collect())
+            .collect();
+        let tree = MerkleTree::new(rows.iter().map(|row| &row[..]));
+        Self {
+            columns,
+            rows,
+            tree,
+        }
+    }
+
+    fn coset(&self, k: usize) -> Vec<Vec<Felt>> {
+        coset(&self.columns, k)
+    }
+
+    /// Every column at `x`
+    fn evaluate(&self, x: Felt) -> Vec<Felt> {
+        self.columns
+            .iter()
+            .map(|column| field::evaluate(column, x))
+            .collect()
+    }
+
+    /// Open row `index`, at `x`
+    fn open(&self, index: usize, x: Felt) -> Opening {
+        let values = match self.rows.get(index) {
+            Some(row) => encode(row),
+            None => encode(&self.evaluate(x)),
+        };
+        Opening {
+            values,
+            path: self.tree.path(index),
+        }
+    }
+}
+
+/// Rows `k + BLOWUP * j` of the extension of `columns`, the `k`th coset of
+/// the trace domain
+fn coset(columns: &[Vec<Felt>], k: usize) -> Vec<Vec<Felt>> {
+    let n = columns[0].len();
+    let shift = field::coset_shift() * field::root_of_unity(n * BLOWUP).pow_vartime([k as u64]);
+    let mut rows = vec![Vec::with_capacity(columns.len()); n];
+    for column in columns {
+        let values = field::coset_evaluate(column, shift, n);
+        for (row, value) in rows.iter_mut().zip(values) {
+            row.push(value);
+        }
+    }
+    rows
+}
+
+/// Coefficients of the columns of trace `rows`
+fn interpolate(rows: &[Vec<Felt>]) -> Vec<Vec<Felt>> {
+    (0..rows[0].len())
+        .map(|j| {
+            let values: Vec<Felt> = rows.iter().map(|row| row[j]).collect();
+            field::coset_interpolate(&values, Felt::ONE)
+        })
+        .collect()
+}
+
+fn encode(values: &[Felt]) -> Vec<[u8; 32]> {
+    values.iter().map(field::to_bytes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::air::RESULT;
+    use crate::instruction::{AluOp, Instruction};
+    use crate::vm::{Vm, REG_A0, REG_A7, SYS_EXIT};
+
+    fn vm() -> Vm {
+        let program = [
+            Instruction::AluImm {
+                op: AluOp::Add,
+                rd: REG_A0,
+                rs1: 0,
+                imm: 7,
+            },
+            Instruction::AluImm {
+                op: AluOp::Add,
+                rd: REG_A7,
+                rs1: 0,
+                imm: SYS_EXIT as i32,
+            },
+            Instruction::Ecall,
+        ];
+        let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+        Vm::from_program(0x1000, &words).unwrap()
+    }
+
+    fn statement(witness: &Witness) -> Statement {
+        Statement {
+            program_hash: [0; 32],
+            public_input: Vec::new(),
+            public_output: witness.public_output.clone(),
+            exit_code: witness.exit_code,
+            gas_used: witness.gas_used,
+        }
+    }
+
+    #[test]
+    fn test_proof_shape() {
+        let witness = Witness::build(vm()).unwrap();
+        let proof = prove_witness(&statement(&witness), &witness).unwrap();
+
+        assert_eq!(1 << proof.log_trace_len, witness.rows.len());
+        assert_eq!(proof.queries.len(), QUERIES);
+        assert_eq!(proof.fri.roots.len(), proof.log_trace_len as usize);
+        assert_eq!(proof.composition_ood.len(), COMPOSITION_CHUNKS + 1);
+    }
+
+    #[test]
+    fn test_invalid_runs_unprovable() {
+        let mut witness = Witness::build(vm()).unwrap();
+        witness.rows[0][RESULT] += Felt::ONE;
+        assert!(matches!(
+            prove_witness(&statement(&witness), &witness),
+            Err(ZkvmError::Unprovable(_))
+        ));
+
+        let schedule = GasSchedule {
+            alu: 2,
+            ..GasSchedule::default()
+        };
+        assert!(matches!(
+            Witness::build(vm().with_schedule(schedule)),
+            Err(ZkvmError::Unprovable(_))
+        ));
+
+        let mut started = vm();
+        started.step().unwrap();
+        assert!(matches!(
+            Witness::build(started),
+            Err(ZkvmError::Unprovable(_))
+        ));
+    }
+}
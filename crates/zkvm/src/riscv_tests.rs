@@ -0,0 +1,1174 @@
+//! Conformance vectors from the riscv-tests suite
+//! (<https://github.com/riscv-software-src/riscv-tests>)
+//!
+//! The `TEST_*` cases of the `rv32ui` and `rv32um` physical-memory tests are
+//! transcribed by hand below, one row per case, and each runs as its own
+//! small program that exits with the value under test. Register aliasing
+//! variants (`*_EQ_DEST`, `*_ZEROSRC*`, `*_ZERODEST`) are derived from the
+//! rows; the bypass variants only exercise pipeline forwarding and are left
+//! out. The suite's `fence_i` test needs Zifencei, which the interpreter does
+//! not implement. Every case is also checked against the constraints of the
+//! [AIR](crate::air).
+//!
+//! The transcription is not the suite itself. `riscv-tests/build.sh` builds
+//! the upstream sources into ELFs, and [`test_upstream_binaries`] runs them
+//! through [`Program::from_elf`].
+
+use std::fs;
+use std::path::Path;
+
+use crate::abi;
+use crate::air;
+use crate::instruction::{AluOp, BranchCondition, Instruction, LoadOp};
+use crate::memory::Width;
+use crate::vm::{Vm, REG_A0, REG_A7, SYS_EXIT};
+use crate::witness::Witness;
+use crate::Program;
+
+/// Where test programs are loaded
+const CODE: u32 = 0x1000;
+
+/// Where test data (`tdat`) is loaded
+const DATA: u32 = 0x8000;
+
+/// `(result, val1, val2)` rows per operation
+type AluVectors = [(AluOp, &'static [(u32, u32, u32)])];
+
+/// `(taken, val1, val2)` rows per condition
+type BranchVectors = [(BranchCondition, &'static [(bool, u32, u32)])];
+
+/// `tdat` and `(result, offset, base)` rows per load
+type LoadVectors = [(LoadOp, &'static [u8], &'static [(u32, i32, i32)])];
+
+/// `tdat` fill and `(load, value, result, offset, base)` rows per store width
+type StoreVectors = [(Width, u32, &'static [(LoadOp, u32, u32, i32, i32)])];
+
+/// `TEST_RR_OP(n, op, result, val1, val2)`
+const RV32UI_RR: &AluVectors = &[
+    (
+        AluOp::Add,
+        &[
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000002, 0x00000001, 0x00000001),
+            (0x0000000a, 0x00000003, 0x00000007),
+            (0xffff8000, 0x00000000, 0xffff8000),
+            (0x80000000, 0x80000000, 0x00000000),
+            (0x7fff8000, 0x80000000, 0xffff8000),
+            (0x00007fff, 0x00000000, 0x00007fff),
+            (0x7fffffff, 0x7fffffff, 0x00000000),
+            (0x80007ffe, 0x7fffffff, 0x00007fff),
+            (0x80007fff, 0x80000000, 0x00007fff),
+            (0x7fff7fff, 0x7fffffff, 0xffff8000),
+            (0xffffffff, 0x00000000, 0xffffffff),
+            (0x00000000, 0xffffffff, 0x00000001),
+            (0xfffffffe, 0xffffffff, 0xffffffff),
+            (0x80000000, 0x00000001, 0x7fffffff),
+        ],
+    ),
+    (
+        AluOp::Sub,
+        &[
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0xfffffffc, 0x00000003, 0x00000007),
+            (0x00008000, 0x00000000, 0xffff8000),
+            (0x80000000, 0x80000000, 0x00000000),
+            (0x80008000, 0x80000000, 0xffff8000),
+            (0xffff8001, 0x00000000, 0x00007fff),
+            (0x7fffffff, 0x7fffffff, 0x00000000),
+            (0x7fff8000, 0x7fffffff, 0x00007fff),
+            (0x7fff8001, 0x80000000, 0x00007fff),
+            (0x80007fff, 0x7fffffff, 0xffff8000),
+            (0x00000001, 0x00000000, 0xffffffff),
+            (0xfffffffe, 0xffffffff, 0x00000001),
+            (0x00000000, 0xffffffff, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Sll,
+        &[
+            (0x00000001, 0x00000001, 0x00000000),
+            (0x00000002, 0x00000001, 0x00000001),
+            (0x00000080, 0x00000001, 0x00000007),
+            (0x00004000, 0x00000001, 0x0000000e),
+            (0x80000000, 0x00000001, 0x0000001f),
+            (0xffffffff, 0xffffffff, 0x00000000),
+            (0xfffffffe, 0xffffffff, 0x00000001),
+            (0xffffff80, 0xffffffff, 0x00000007),
+            (0xffffc000, 0xffffffff, 0x0000000e),
+            (0x80000000, 0xffffffff, 0x0000001f),
+            (0x21212121, 0x21212121, 0x00000000),
+            (0x42424242, 0x21212121, 0x00000001),
+            (0x90909080, 0x21212121, 0x00000007),
+            (0x48484000, 0x21212121, 0x0000000e),
+            (0x80000000, 0x21212121, 0x0000001f),
+            (0x21212121, 0x21212121, 0xffffffc0),
+            (0x42424242, 0x21212121, 0xffffffc1),
+            (0x90909080, 0x21212121, 0xffffffc7),
+            (0x48484000, 0x21212121, 0xffffffce),
+            (0x80000000, 0x21212121, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Slt,
+        &[
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0x00000001, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000007, 0x00000003),
+            (0x00000000, 0x00000000, 0xffff8000),
+            (0x00000001, 0x80000000, 0x00000000),
+            (0x00000001, 0x80000000, 0xffff8000),
+            (0x00000001, 0x00000000, 0x00007fff),
+            (0x00000000, 0x7fffffff, 0x00000000),
+            (0x00000000, 0x7fffffff, 0x00007fff),
+            (0x00000001, 0x80000000, 0x00007fff),
+            (0x00000000, 0x7fffffff, 0xffff8000),
+            (0x00000000, 0x00000000, 0xffffffff),
+            (0x00000001, 0xffffffff, 0x00000001),
+            (0x00000000, 0xffffffff, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Sltu,
+        &[
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0x00000001, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000007, 0x00000003),
+            (0x00000001, 0x00000000, 0xffff8000),
+            (0x00000000, 0x80000000, 0x00000000),
+            (0x00000001, 0x80000000, 0xffff8000),
+            (0x00000001, 0x00000000, 0x00007fff),
+            (0x00000000, 0x7fffffff, 0x00000000),
+            (0x00000000, 0x7fffffff, 0x00007fff),
+            (0x00000000, 0x80000000, 0x00007fff),
+            (0x00000001, 0x7fffffff, 0xffff8000),
+            (0x00000001, 0x00000000, 0xffffffff),
+            (0x00000000, 0xffffffff, 0x00000001),
+            (0x00000000, 0xffffffff, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Xor,
+        &[
+            (0xf00ff00f, 0xff00ff00, 0x0f0f0f0f),
+            (0xff00ff00, 0x0ff00ff0, 0xf0f0f0f0),
+            (0x0ff00ff0, 0x00ff00ff, 0x0f0f0f0f),
+            (0x00ff00ff, 0xf00ff00f, 0xf0f0f0f0),
+        ],
+    ),
+    (
+        AluOp::Srl,
+        &[
+            (0x80000000, 0x80000000, 0x00000000),
+            (0x40000000, 0x80000000, 0x00000001),
+            (0x01000000, 0x80000000, 0x00000007),
+            (0x00020000, 0x80000000, 0x0000000e),
+            (0x00000001, 0x80000000, 0x0000001f),
+            (0xffffffff, 0xffffffff, 0x00000000),
+            (0x7fffffff, 0xffffffff, 0x00000001),
+            (0x01ffffff, 0xffffffff, 0x00000007),
+            (0x0003ffff, 0xffffffff, 0x0000000e),
+            (0x00000001, 0xffffffff, 0x0000001f),
+            (0x21212121, 0x21212121, 0x00000000),
+            (0x10909090, 0x21212121, 0x00000001),
+            (0x00424242, 0x21212121, 0x00000007),
+            (0x00008484, 0x21212121, 0x0000000e),
+            (0x00000000, 0x21212121, 0x0000001f),
+            (0x21212121, 0x21212121, 0xffffffc0),
+            (0x10909090, 0x21212121, 0xffffffc1),
+            (0x00424242, 0x21212121, 0xffffffc7),
+            (0x00008484, 0x21212121, 0xffffffce),
+            (0x00000000, 0x21212121, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Sra,
+        &[
+            (0x80000000, 0x80000000, 0x00000000),
+            (0xc0000000, 0x80000000, 0x00000001),
+            (0xff000000, 0x80000000, 0x00000007),
+            (0xfffe0000, 0x80000000, 0x0000000e),
+            (0xffffffff, 0x80000000, 0x0000001f),
+            (0xffffffff, 0xffffffff, 0x00000000),
+            (0xffffffff, 0xffffffff, 0x00000001),
+            (0xffffffff, 0xffffffff, 0x00000007),
+            (0xffffffff, 0xffffffff, 0x0000000e),
+            (0xffffffff, 0xffffffff, 0x0000001f),
+            (0x21212121, 0x21212121, 0x00000000),
+            (0x10909090, 0x21212121, 0x00000001),
+            (0x00424242, 0x21212121, 0x00000007),
+            (0x00008484, 0x21212121, 0x0000000e),
+            (0x00000000, 0x21212121, 0x0000001f),
+            (0x21212121, 0x21212121, 0xffffffc0),
+            (0x10909090, 0x21212121, 0xffffffc1),
+            (0x00424242, 0x21212121, 0xffffffc7),
+            (0x00008484, 0x21212121, 0xffffffce),
+            (0x00000000, 0x21212121, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Or,
+        &[
+            (0xff0fff0f, 0xff00ff00, 0x0f0f0f0f),
+            (0xfff0fff0, 0x0ff00ff0, 0xf0f0f0f0),
+            (0x0fff0fff, 0x00ff00ff, 0x0f0f0f0f),
+            (0xf0fff0ff, 0xf00ff00f, 0xf0f0f0f0),
+        ],
+    ),
+    (
+        AluOp::And,
+        &[
+            (0x0f000f00, 0xff00ff00, 0x0f0f0f0f),
+            (0x00f000f0, 0x0ff00ff0, 0xf0f0f0f0),
+            (0x000f000f, 0x00ff00ff, 0x0f0f0f0f),
+            (0xf000f000, 0xf00ff00f, 0xf0f0f0f0),
+        ],
+    ),
+];
+
+/// `TEST_IMM_OP(n, op, result, val1, imm)`
+const RV32UI_IMM: &AluVectors = &[
+    (
+        AluOp::Add,
+        &[
+            (0x00000000, 0x00000000, 0x000),
+            (0x00000002, 0x00000001, 0x001),
+            (0x0000000a, 0x00000003, 0x007),
+            (0xfffff800, 0x00000000, 0x800),
+            (0x80000000, 0x80000000, 0x000),
+            (0x7ffff800, 0x80000000, 0x800),
+            (0x000007ff, 0x00000000, 0x7ff),
+            (0x7fffffff, 0x7fffffff, 0x000),
+            (0x800007fe, 0x7fffffff, 0x7ff),
+            (0x800007ff, 0x80000000, 0x7ff),
+            (0x7ffff7ff, 0x7fffffff, 0x800),
+            (0xffffffff, 0x00000000, 0xfff),
+            (0x00000000, 0xffffffff, 0x001),
+            (0xfffffffe, 0xffffffff, 0xfff),
+            (0x80000000, 0x7fffffff, 0x001),
+        ],
+    ),
+    (
+        AluOp::Slt,
+        &[
+            (0x00000000, 0x00000000, 0x000),
+            (0x00000000, 0x00000001, 0x001),
+            (0x00000001, 0x00000003, 0x007),
+            (0x00000000, 0x00000007, 0x003),
+            (0x00000000, 0x00000000, 0x800),
+            (0x00000001, 0x80000000, 0x000),
+            (0x00000001, 0x80000000, 0x800),
+            (0x00000001, 0x00000000, 0x7ff),
+            (0x00000000, 0x7fffffff, 0x000),
+            (0x00000000, 0x7fffffff, 0x7ff),
+            (0x00000001, 0x80000000, 0x7ff),
+            (0x00000000, 0x7fffffff, 0x800),
+            (0x00000000, 0x00000000, 0xfff),
+            (0x00000001, 0xffffffff, 0x001),
+            (0x00000000, 0xffffffff, 0xfff),
+        ],
+    ),
+    (
+        AluOp::Sltu,
+        &[
+            (0x00000000, 0x00000000, 0x000),
+            (0x00000000, 0x00000001, 0x001),
+            (0x00000001, 0x00000003, 0x007),
+            (0x00000000, 0x00000007, 0x003),
+            (0x00000001, 0x00000000, 0x800),
+            (0x00000000, 0x80000000, 0x000),
+            (0x00000001, 0x80000000, 0x800),
+            (0x00000001, 0x00000000, 0x7ff),
+            (0x00000000, 0x7fffffff, 0x000),
+            (0x00000000, 0x7fffffff, 0x7ff),
+            (0x00000000, 0x80000000, 0x7ff),
+            (0x00000001, 0x7fffffff, 0x800),
+            (0x00000001, 0x00000000, 0xfff),
+            (0x00000000, 0xffffffff, 0x001),
+            (0x00000000, 0xffffffff, 0xfff),
+        ],
+    ),
+    (
+        AluOp::Xor,
+        &[
+            (0xff00f00f, 0x00ff0f00, 0xf0f),
+            (0x0ff00f00, 0x0ff00ff0, 0x0f0),
+            (0x00ff0ff0, 0x00ff08ff, 0x70f),
+            (0xf00ff0ff, 0xf00ff00f, 0x0f0),
+        ],
+    ),
+    (
+        AluOp::Or,
+        &[
+            (0xffffff0f, 0xff00ff00, 0xf0f),
+            (0x0ff00ff0, 0x0ff00ff0, 0x0f0),
+            (0x00ff07ff, 0x00ff00ff, 0x70f),
+            (0xf00ff0ff, 0xf00ff00f, 0x0f0),
+        ],
+    ),
+    (
+        AluOp::And,
+        &[
+            (0xff00ff00, 0xff00ff00, 0xf0f),
+            (0x000000f0, 0x0ff00ff0, 0x0f0),
+            (0x0000000f, 0x00ff00ff, 0x70f),
+            (0x00000000, 0xf00ff00f, 0x0f0),
+        ],
+    ),
+    (
+        AluOp::Sll,
+        &[
+            (0x00000001, 0x00000001, 0),
+            (0x00000002, 0x00000001, 1),
+            (0x00000080, 0x00000001, 7),
+            (0x00004000, 0x00000001, 14),
+            (0x80000000, 0x00000001, 31),
+            (0xffffffff, 0xffffffff, 0),
+            (0xfffffffe, 0xffffffff, 1),
+            (0xffffff80, 0xffffffff, 7),
+            (0xffffc000, 0xffffffff, 14),
+            (0x80000000, 0xffffffff, 31),
+            (0x21212121, 0x21212121, 0),
+            (0x42424242, 0x21212121, 1),
+            (0x90909080, 0x21212121, 7),
+            (0x48484000, 0x21212121, 14),
+            (0x80000000, 0x21212121, 31),
+        ],
+    ),
+    (
+        AluOp::Srl,
+        &[
+            (0x80000000, 0x80000000, 0),
+            (0x40000000, 0x80000000, 1),
+            (0x01000000, 0x80000000, 7),
+            (0x00020000, 0x80000000, 14),
+            (0x00000001, 0x80000000, 31),
+            (0xffffffff, 0xffffffff, 0),
+            (0x7fffffff, 0xffffffff, 1),
+            (0x01ffffff, 0xffffffff, 7),
+            (0x0003ffff, 0xffffffff, 14),
+            (0x00000001, 0xffffffff, 31),
+            (0x21212121, 0x21212121, 0),
+            (0x10909090, 0x21212121, 1),
+            (0x00424242, 0x21212121, 7),
+            (0x00008484, 0x21212121, 14),
+            (0x00000000, 0x21212121, 31),
+        ],
+    ),
+    (
+        AluOp::Sra,
+        &[
+            (0x80000000, 0x80000000, 0),
+            (0xc0000000, 0x80000000, 1),
+            (0xff000000, 0x80000000, 7),
+            (0xfffe0000, 0x80000000, 14),
+            (0xffffffff, 0x80000000, 31),
+            (0xffffffff, 0xffffffff, 0),
+            (0xffffffff, 0xffffffff, 1),
+            (0xffffffff, 0xffffffff, 7),
+            (0xffffffff, 0xffffffff, 14),
+            (0xffffffff, 0xffffffff, 31),
+            (0x21212121, 0x21212121, 0),
+            (0x10909090, 0x21212121, 1),
+            (0x00424242, 0x21212121, 7),
+            (0x00008484, 0x21212121, 14),
+            (0x00000000, 0x21212121, 31),
+        ],
+    ),
+];
+
+/// `TEST_RR_OP(n, op, result, val1, val2)`
+const RV32UM: &AluVectors = &[
+    (
+        AluOp::Mul,
+        &[
+            (0x00001200, 0x00007e00, 0xb6db6db7),
+            (0x00001240, 0x00007fc0, 0xb6db6db7),
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000001, 0x00000001, 0x00000001),
+            (0x00000015, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000000, 0xffff8000),
+            (0x00000000, 0x80000000, 0x00000000),
+            (0x00000000, 0x80000000, 0xffff8000),
+            (0x0000ff7f, 0xaaaaaaab, 0x0002fe7d),
+            (0x0000ff7f, 0x0002fe7d, 0xaaaaaaab),
+            (0x00000000, 0xff000000, 0xff000000),
+            (0x00000001, 0xffffffff, 0xffffffff),
+            (0xffffffff, 0xffffffff, 0x00000001),
+            (0xffffffff, 0x00000001, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Mulh,
+        &[
+            (0xffffdc00, 0x00007e00, 0xb6db6db7),
+            (0xffffdb80, 0x00007fc0, 0xb6db6db7),
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0x00000000, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000000, 0xffff8000),
+            (0x00000000, 0x80000000, 0x00000000),
+            (0x00004000, 0x80000000, 0xffff8000),
+            (0xffff0081, 0xaaaaaaab, 0x0002fe7d),
+            (0xffff0081, 0x0002fe7d, 0xaaaaaaab),
+            (0x00010000, 0xff000000, 0xff000000),
+            (0x00000000, 0xffffffff, 0xffffffff),
+            (0xffffffff, 0xffffffff, 0x00000001),
+            (0xffffffff, 0x00000001, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Mulhsu,
+        &[
+            (0x00005a00, 0x00007e00, 0xb6db6db7),
+            (0x00005b40, 0x00007fc0, 0xb6db6db7),
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0x00000000, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000000, 0xffff8000),
+            (0x00000000, 0x80000000, 0x00000000),
+            (0x80004000, 0x80000000, 0xffff8000),
+            (0xffff0081, 0xaaaaaaab, 0x0002fe7d),
+            (0x0001fefe, 0x0002fe7d, 0xaaaaaaab),
+            (0xff010000, 0xff000000, 0xff000000),
+            (0xffffffff, 0xffffffff, 0xffffffff),
+            (0xffffffff, 0xffffffff, 0x00000001),
+            (0x00000000, 0x00000001, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Mulhu,
+        &[
+            (0x00005a00, 0x00007e00, 0xb6db6db7),
+            (0x00005b40, 0x00007fc0, 0xb6db6db7),
+            (0x00000000, 0x00000000, 0x00000000),
+            (0x00000000, 0x00000001, 0x00000001),
+            (0x00000000, 0x00000003, 0x00000007),
+            (0x00000000, 0x00000000, 0xffff8000),
+            (0x00000000, 0x80000000, 0x00000000),
+            (0x7fffc000, 0x80000000, 0xffff8000),
+            (0x0001fefe, 0xaaaaaaab, 0x0002fe7d),
+            (0x0001fefe, 0x0002fe7d, 0xaaaaaaab),
+            (0xfe010000, 0xff000000, 0xff000000),
+            (0xfffffffe, 0xffffffff, 0xffffffff),
+            (0x00000000, 0xffffffff, 0x00000001),
+            (0x00000000, 0x00000001, 0xffffffff),
+        ],
+    ),
+    (
+        AluOp::Div,
+        &[
+            (0x00000003, 0x00000014, 0x00000006),
+            (0xfffffffd, 0xffffffec, 0x00000006),
+            (0xfffffffd, 0x00000014, 0xfffffffa),
+            (0x00000003, 0xffffffec, 0xfffffffa),
+            (0x80000000, 0x80000000, 0x00000001),
+            (0x80000000, 0x80000000, 0xffffffff),
+            (0xffffffff, 0x80000000, 0x00000000),
+            (0xffffffff, 0x00000001, 0x00000000),
+            (0xffffffff, 0x00000000, 0x00000000),
+        ],
+    ),
+    (
+        AluOp::Divu,
+        &[
+            (0x00000003, 0x00000014, 0x00000006),
+            (0x2aaaaaa7, 0xffffffec, 0x00000006),
+            (0x00000000, 0x00000014, 0xfffffffa),
+            (0x00000000, 0xffffffec, 0xfffffffa),
+            (0x80000000, 0x80000000, 0x00000001),
+            (0x00000000, 0x80000000, 0xffffffff),
+            (0xffffffff, 0x80000000, 0x00000000),
+            (0xffffffff, 0x00000001, 0x00000000),
+            (0xffffffff, 0x00000000, 0x00000000),
+        ],
+    ),
+    (
+        AluOp::Rem,
+        &[
+            (0x00000002, 0x00000014, 0x00000006),
+            (0xfffffffe, 0xffffffec, 0x00000006),
+            (0x00000002, 0x00000014, 0xfffffffa),
+            (0xfffffffe, 0xffffffec, 0xfffffffa),
+            (0x00000000, 0x80000000, 0x00000001),
+            (0x00000000, 0x80000000, 0xffffffff),
+            (0x80000000, 0x80000000, 0x00000000),
+            (0x00000001, 0x00000001, 0x00000000),
+            (0x00000000, 0x00000000, 0x00000000),
+        ],
+    ),
+    (
+        AluOp::Remu,
+        &[
+            (0x00000002, 0x00000014, 0x00000006),
+            (0x00000002, 0xffffffec, 0x00000006),
+            (0x00000014, 0x00000014, 0xfffffffa),
+            (0xffffffec, 0xffffffec, 0xfffffffa),
+            (0x00000000, 0x80000000, 0x00000001),
+            (0x80000000, 0x80000000, 0xffffffff),
+            (0x80000000, 0x80000000, 0x00000000),
+            (0x00000001, 0x00000001, 0x00000000),
+            (0x00000000, 0x00000000, 0x00000000),
+        ],
+    ),
+];
+/// `TEST_BR2_OP_TAKEN` / `TEST_BR2_OP_NOTTAKEN(n, inst, val1, val2)`
+const RV32UI_BRANCH: &BranchVectors = &[
+    (
+        BranchCondition::Eq,
+        &[
+            (true, 0, 0),
+            (true, 1, 1),
+            (true, 0xffffffff, 0xffffffff),
+            (false, 0, 1),
+            (false, 1, 0),
+            (false, 0xffffffff, 1),
+            (false, 1, 0xffffffff),
+        ],
+    ),
+    (
+        BranchCondition::Ne,
+        &[
+            (true, 0, 1),
+            (true, 1, 0),
+            (true, 0xffffffff, 1),
+            (true, 1, 0xffffffff),
+            (false, 0, 0),
+            (false, 1, 1),
+            (false, 0xffffffff, 0xffffffff),
+        ],
+    ),
+    (
+        BranchCondition::Lt,
+        &[
+            (true, 0, 1),
+            (true, 0xffffffff, 1),
+            (true, 0xfffffffe, 0xffffffff),
+            (false, 1, 0),
+            (false, 1, 0xffffffff),
+            (false, 0xffffffff, 0xfffffffe),
+            (false, 1, 0xfffffffe),
+        ],
+    ),
+    (
+        BranchCondition::Ge,
+        &[
+            (true, 0, 0),
+            (true, 1, 1),
+            (true, 0xffffffff, 0xffffffff),
+            (true, 1, 0),
+            (true, 1, 0xffffffff),
+            (true, 0xffffffff, 0xfffffffe),
+            (false, 0, 1),
+            (false, 0xffffffff, 1),
+            (false, 0xfffffffe, 0xffffffff),
+            (false, 0xfffffffe, 1),
+        ],
+    ),
+    (
+        BranchCondition::Ltu,
+        &[
+            (true, 0x00000000, 0x00000001),
+            (true, 0xfffffffe, 0xffffffff),
+            (true, 0x00000000, 0xffffffff),
+            (false, 0x00000001, 0x00000000),
+            (false, 0xffffffff, 0xfffffffe),
+            (false, 0xffffffff, 0x00000000),
+            (false, 0x80000000, 0x7fffffff),
+        ],
+    ),
+    (
+        BranchCondition::Geu,
+        &[
+            (true, 0x00000000, 0x00000000),
+            (true, 0x00000001, 0x00000001),
+            (true, 0xffffffff, 0xffffffff),
+            (true, 0x00000001, 0x00000000),
+            (true, 0xffffffff, 0xfffffffe),
+            (true, 0xffffffff, 0x00000000),
+            (false, 0x00000000, 0x00000001),
+            (false, 0xfffffffe, 0xffffffff),
+            (false, 0x00000000, 0xffffffff),
+            (false, 0x7fffffff, 0x80000000),
+        ],
+    ),
+];
+
+/// `TEST_LD_OP(n, inst, result, offset, base)` over the test's `tdat`, with
+/// `base` relative to `tdat`
+const RV32UI_LOAD: &LoadVectors = &[
+    (
+        LoadOp::Lb,
+        &[0xff, 0x00, 0xf0, 0x0f],
+        &[
+            (0xffffffff, 0, 0),
+            (0x00000000, 1, 0),
+            (0xfffffff0, 2, 0),
+            (0x0000000f, 3, 0),
+            (0xffffffff, -3, 3),
+            (0x00000000, -2, 3),
+            (0xfffffff0, -1, 3),
+            (0x0000000f, 0, 3),
+            (0xffffffff, 32, -32),
+            (0x00000000, 7, -6),
+        ],
+    ),
+    (
+        LoadOp::Lbu,
+        &[0xff, 0x00, 0xf0, 0x0f],
+        &[
+            (0x000000ff, 0, 0),
+            (0x00000000, 1, 0),
+            (0x000000f0, 2, 0),
+            (0x0000000f, 3, 0),
+            (0x000000ff, -3, 3),
+            (0x00000000, -2, 3),
+            (0x000000f0, -1, 3),
+            (0x0000000f, 0, 3),
+            (0x000000ff, 32, -32),
+            (0x00000000, 7, -6),
+        ],
+    ),
+    (
+        LoadOp::Lh,
+        // .half 0x00ff, 0xff00, 0x0ff0, 0xf00f
+        &[0xff, 0x00, 0x00, 0xff, 0xf0, 0x0f, 0x0f, 0xf0],
+        &[
+            (0x000000ff, 0, 0),
+            (0xffffff00, 2, 0),
+            (0x00000ff0, 4, 0),
+            (0xfffff00f, 6, 0),
+            (0x000000ff, -6, 6),
+            (0xffffff00, -4, 6),
+            (0x00000ff0, -2, 6),
+            (0xfffff00f, 0, 6),
+            (0x000000ff, 32, -32),
+            (0xffffff00, 7, -5),
+        ],
+    ),
+    (
+        LoadOp::Lhu,
+        &[0xff, 0x00, 0x00, 0xff, 0xf0, 0x0f, 0x0f, 0xf0],
+        &[
+            (0x000000ff, 0, 0),
+            (0x0000ff00, 2, 0),
+            (0x00000ff0, 4, 0),
+            (0x0000f00f, 6, 0),
+            (0x000000ff, -6, 6),
+            (0x0000ff00, -4, 6),
+            (0x00000ff0, -2, 6),
+            (0x0000f00f, 0, 6),
+            (0x000000ff, 32, -32),
+            (0x0000ff00, 7, -5),
+        ],
+    ),
+    (
+        LoadOp::Lw,
+        // .word 0x00ff00ff, 0xff00ff00, 0x0ff00ff0, 0xf00ff00f
+        &[
+            0xff, 0x00, 0xff, 0x00, 0x00, 0xff, 0x00, 0xff, 0xf0, 0x0f, 0xf0, 0x0f, 0x0f, 0xf0,
+            0x0f, 0xf0,
+        ],
+        &[
+            (0x00ff00ff, 0, 0),
+            (0xff00ff00, 4, 0),
+            (0x0ff00ff0, 8, 0),
+            (0xf00ff00f, 12, 0),
+            (0x00ff00ff, -12, 12),
+            (0xff00ff00, -8, 12),
+            (0x0ff00ff0, -4, 12),
+            (0xf00ff00f, 0, 12),
+            (0x00ff00ff, 32, -32),
+            (0xff00ff00, 7, -3),
+        ],
+    ),
+];
+
+/// `TEST_ST_OP(n, load_inst, store_inst, result, offset, base)` as
+/// `(load, value, result, offset, base)`, over ten `tdat` elements of the
+/// store's width filled with the given pattern
+const RV32UI_STORE: &StoreVectors = &[
+    (
+        Width::Byte,
+        0xef,
+        &[
+            (LoadOp::Lb, 0xffffffaa, 0xffffffaa, 0, 0),
+            (LoadOp::Lb, 0x00000000, 0x00000000, 1, 0),
+            (LoadOp::Lh, 0xffffefa0, 0xffffefa0, 2, 0),
+            (LoadOp::Lb, 0x0000000a, 0x0000000a, 3, 0),
+            (LoadOp::Lb, 0xffffffaa, 0xffffffaa, -3, 7),
+            (LoadOp::Lb, 0x00000000, 0x00000000, -2, 7),
+            (LoadOp::Lb, 0xffffffa0, 0xffffffa0, -1, 7),
+            (LoadOp::Lb, 0x0000000a, 0x0000000a, 0, 7),
+            (LoadOp::Lb, 0x12345678, 0x00000078, 32, -24),
+            (LoadOp::Lb, 0x00003098, 0xffffff98, 7, 2),
+        ],
+    ),
+    (
+        Width::Half,
+        0xbeef,
+        &[
+            (LoadOp::Lh, 0x000000aa, 0x000000aa, 0, 0),
+            (LoadOp::Lh, 0xffffaa00, 0xffffaa00, 2, 0),
+            (LoadOp::Lw, 0xbeef0aa0, 0xbeef0aa0, 4, 0),
+            (LoadOp::Lh, 0xffffa00a, 0xffffa00a, 6, 0),
+            (LoadOp::Lh, 0x000000aa, 0x000000aa, -6, 14),
+            (LoadOp::Lh, 0xffffaa00, 0xffffaa00, -4, 14),
+            (LoadOp::Lh, 0x00000aa0, 0x00000aa0, -2, 14),
+            (LoadOp::Lh, 0xffffa00a, 0xffffa00a, 0, 14),
+            (LoadOp::Lh, 0x12345678, 0x00005678, 32, -16),
+            (LoadOp::Lh, 0x00003098, 0x00003098, 7, 11),
+        ],
+    ),
+    (
+        Width::Word,
+        0xdeadbeef,
+        &[
+            (LoadOp::Lw, 0x00aa00aa, 0x00aa00aa, 0, 0),
+            (LoadOp::Lw, 0xaa00aa00, 0xaa00aa00, 4, 0),
+            (LoadOp::Lw, 0x0aa00aa0, 0x0aa00aa0, 8, 0),
+            (LoadOp::Lw, 0xa00aa00a, 0xa00aa00a, 12, 0),
+            (LoadOp::Lw, 0x00aa00aa, 0x00aa00aa, -12, 28),
+            (LoadOp::Lw, 0xaa00aa00, 0xaa00aa00, -8, 28),
+            (LoadOp::Lw, 0x0aa00aa0, 0x0aa00aa0, -4, 28),
+            (LoadOp::Lw, 0xa00aa00a, 0xa00aa00a, 0, 28),
+            (LoadOp::Lw, 0x12345678, 0x12345678, 32, 0),
+            (LoadOp::Lw, 0x58213098, 0x58213098, 7, 29),
+        ],
+    ),
+];
+
+fn addi(rd: u8, rs1: u8, imm: i32) -> Instruction {
+    Instruction::AluImm {
+        op: AluOp::Add,
+        rd,
+        rs1,
+        imm,
+    }
+}
+
+/// Split `value` into `lui` and `addi` immediates
+fn split(value: u32) -> (u32, i32) {
+    let lo = ((value << 20) as i32) >> 20;
+    (value.wrapping_sub(lo as u32), lo)
+}
+
+/// `li rd, value`
+fn li(rd: u8, value: u32) -> [Instruction; 2] {
+    let (hi, lo) = split(value);
+    [Instruction::Lui { rd, imm: hi }, addi(rd, rd, lo)]
+}
+
+/// Address of the `index`-th instruction of a test program
+fn address(index: usize) -> u32 {
+    CODE + 4 * index as u32
+}
+
+/// Run `program` with `data` at `DATA` and return the final value of `result`
+fn run(program: &[Instruction], result: u8, data: &[u8]) -> u32 {
+    let mut words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+    for instruction in [
+        addi(REG_A0, result, 0),
+        addi(REG_A7, 0, SYS_EXIT as i32),
+        Instruction::Ecall,
+    ] {
+        words.push(instruction.encode().unwrap());
+    }
+
+    let mut vm = Vm::from_program(CODE, &words).unwrap();
+    vm.memory_mut().write_bytes(DATA, data).unwrap();
+    // Every case is also a run the AIR accepts
+    let witness = Witness::build(vm.clone()).unwrap();
+    assert_eq!(air::unsatisfied(&witness), vec![]);
+
+    let exit_code = vm.run(1_000).unwrap().exit_code;
+    assert_eq!(witness.exit_code, exit_code);
+    exit_code
+}
+
+fn check_rr(table: &AluVectors) {
+    for (op, cases) in table {
+        for &(result, val1, val2) in *cases {
+            let mut registers = vec![(14, 1, 2), (1, 1, 2), (2, 1, 2)];
+            if val1 == 0 {
+                registers.push((14, 0, 2));
+            }
+            if val2 == 0 {
+                registers.push((14, 1, 0));
+            }
+            if val1 == val2 {
+                registers.push((1, 1, 1));
+            }
+
+            for (rd, rs1, rs2) in registers {
+                let mut program = li(1, val1).to_vec();
+                program.extend(li(2, val2));
+                program.push(Instruction::Alu {
+                    op: *op,
+                    rd,
+                    rs1,
+                    rs2,
+                });
+                assert_eq!(
+                    run(&program, rd, &[]),
+                    result,
+                    "{:?} x{}, x{}, x{} with {:#x}, {:#x}",
+                    op,
+                    rd,
+                    rs1,
+                    rs2,
+                    val1,
+                    val2
+                );
+            }
+        }
+
+        // TEST_RR_ZERODEST
+        let (_, val1, val2) = cases[0];
+        let mut program = li(1, val1).to_vec();
+        program.extend(li(2, val2));
+        program.push(Instruction::Alu {
+            op: *op,
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        });
+        assert_eq!(run(&program, 0, &[]), 0);
+    }
+}
+
+#[test]
+fn test_rv32ui_register_ops() {
+    check_rr(RV32UI_RR);
+}
+
+#[test]
+fn test_rv32ui_immediate_ops() {
+    for (op, cases) in RV32UI_IMM {
+        for &(result, val1, imm) in *cases {
+            // Shifts take a 5-bit shift amount; the rest a sign-extended 12-bit immediate
+            let imm = match op {
+                AluOp::Sll | AluOp::Srl | AluOp::Sra => imm as i32,
+                _ => ((imm << 20) as i32) >> 20,
+            };
+            let mut registers = vec![(14, 1), (1, 1)];
+            if val1 == 0 {
+                registers.push((14, 0));
+            }
+
+            for (rd, rs1) in registers {
+                let mut program = li(1, val1).to_vec();
+                program.push(Instruction::AluImm {
+                    op: *op,
+                    rd,
+                    rs1,
+                    imm,
+                });
+                assert_eq!(
+                    run(&program, rd, &[]),
+                    result,
+                    "{:?}i x{}, x{} with {:#x}, {}",
+                    op,
+                    rd,
+                    rs1,
+                    val1,
+                    imm
+                );
+            }
+        }
+
+        // TEST_IMM_ZERODEST
+        let program = [
+            li(1, cases[0].1)[0],
+            li(1, cases[0].1)[1],
+            Instruction::AluImm {
+                op: *op,
+                rd: 0,
+                rs1: 1,
+                imm: 1,
+            },
+        ];
+        assert_eq!(run(&program, 0, &[]), 0);
+    }
+}
+
+#[test]
+fn test_rv32um() {
+    check_rr(RV32UM);
+}
+
+#[test]
+fn test_rv32ui_branches() {
+    for (cond, cases) in RV32UI_BRANCH {
+        for &(taken, val1, val2) in *cases {
+            // x14 stays 1 only if the branch skips the instruction clearing it
+            let mut program = li(1, val1).to_vec();
+            program.extend(li(2, val2));
+            program.extend([
+                addi(14, 0, 1),
+                Instruction::Branch {
+                    cond: *cond,
+                    rs1: 1,
+                    rs2: 2,
+                    offset: 8,
+                },
+                addi(14, 0, 0),
+            ]);
+            assert_eq!(
+                run(&program, 14, &[]) == 1,
+                taken,
+                "{:?} with {:#x}, {:#x}",
+                cond,
+                val1,
+                val2
+            );
+        }
+    }
+
+    // A taken backward branch: count x14 down from 3
+    let program = [
+        addi(14, 0, 3),
+        addi(14, 14, -1),
+        Instruction::Branch {
+            cond: BranchCondition::Ne,
+            rs1: 14,
+            rs2: 0,
+            offset: -4,
+        },
+    ];
+    assert_eq!(run(&program, 14, &[]), 0);
+}
+
+#[test]
+fn test_rv32ui_jal() {
+    // Test 2: the link register holds the address after the jump
+    let program = [
+        Instruction::Jal { rd: 4, offset: 12 },
+        addi(4, 0, 0),
+        addi(4, 0, 0),
+    ];
+    assert_eq!(run(&program, 4, &[]), address(1));
+
+    // Test 3: instructions after the jump are not executed
+    let program = [
+        addi(1, 0, 1),
+        Instruction::Jal { rd: 0, offset: 12 },
+        addi(1, 1, 1),
+        addi(1, 1, 1),
+        addi(1, 1, 1),
+        addi(1, 1, 1),
+    ];
+    assert_eq!(run(&program, 1, &[]), 3);
+}
+
+#[test]
+fn test_rv32ui_jalr() {
+    // Test 2: jump through t1, link in t0
+    let mut program = li(6, address(4)).to_vec();
+    program.extend([
+        Instruction::Jalr {
+            rd: 5,
+            rs1: 6,
+            offset: 0,
+        },
+        addi(5, 0, 0),
+    ]);
+    assert_eq!(run(&program, 5, &[]), address(3));
+
+    // Test 3: rd == rs1 reads the target before writing the link
+    let mut program = li(5, address(4)).to_vec();
+    program.extend([
+        Instruction::Jalr {
+            rd: 5,
+            rs1: 5,
+            offset: 0,
+        },
+        addi(5, 0, 0),
+    ]);
+    assert_eq!(run(&program, 5, &[]), address(3));
+
+    // Test 7: `jr t1, -4` lands one instruction before the label
+    let mut program = vec![addi(5, 0, 1)];
+    program.extend(li(6, address(8)));
+    program.extend([
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 6,
+            offset: -4,
+        },
+        addi(5, 5, 1),
+        addi(5, 5, 1),
+        addi(5, 5, 1),
+        addi(5, 5, 1),
+        addi(5, 5, 1),
+        addi(5, 5, 1),
+    ]);
+    assert_eq!(run(&program, 5, &[]), 4);
+
+    // The low bit of the target is cleared
+    let mut program = li(6, address(4) + 1).to_vec();
+    program.extend([
+        Instruction::Jalr {
+            rd: 5,
+            rs1: 6,
+            offset: 0,
+        },
+        addi(5, 0, 0),
+    ]);
+    assert_eq!(run(&program, 5, &[]), address(3));
+}
+
+#[test]
+fn test_rv32ui_loads() {
+    for (op, data, cases) in RV32UI_LOAD {
+        for &(result, offset, base) in *cases {
+            let mut program = li(1, DATA.wrapping_add(base as u32)).to_vec();
+            program.push(Instruction::Load {
+                op: *op,
+                rd: 14,
+                rs1: 1,
+                offset,
+            });
+            assert_eq!(
+                run(&program, 14, data),
+                result,
+                "{:?} {}({})",
+                op,
+                offset,
+                base
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rv32ui_stores() {
+    for (width, fill, cases) in RV32UI_STORE {
+        let data: Vec<u8> = (0..10)
+            .flat_map(|_| fill.to_le_bytes()[..width.bytes() as usize].to_vec())
+            .collect();
+
+        for &(load, value, result, offset, base) in *cases {
+            let mut program = li(1, DATA.wrapping_add(base as u32)).to_vec();
+            program.extend(li(2, value));
+            program.extend([
+                Instruction::Store {
+                    width: *width,
+                    rs1: 1,
+                    rs2: 2,
+                    offset,
+                },
+                Instruction::Load {
+                    op: load,
+                    rd: 14,
+                    rs1: 1,
+                    offset,
+                },
+            ]);
+            assert_eq!(
+                run(&program, 14, &data),
+                result,
+                "{:?} {}({})",
+                width,
+                offset,
+                base
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rv32ui_lui() {
+    // `lui x1, imm; sra x1, x1, shift`
+    for (imm, shift, result) in [
+        (0x00000, 0, 0x00000000),
+        (0xfffff, 1, 0xfffff800),
+        (0x7ffff, 20, 0x000007ff),
+        (0x80000, 20, 0xfffff800),
+    ] {
+        let program = [
+            Instruction::Lui {
+                rd: 1,
+                imm: imm << 12,
+            },
+            Instruction::AluImm {
+                op: AluOp::Sra,
+                rd: 1,
+                rs1: 1,
+                imm: shift,
+            },
+        ];
+        assert_eq!(run(&program, 1, &[]), result);
+    }
+
+    let program = [Instruction::Lui {
+        rd: 0,
+        imm: 0x80000 << 12,
+    }];
+    assert_eq!(run(&program, 0, &[]), 0);
+}
+
+#[test]
+fn test_rv32ui_auipc() {
+    // `lla a0, 1f + delta; jal a1, 1f; 1: sub a0, a0, a1` leaves `delta`
+    for delta in [10000i32, -10000] {
+        let (hi, lo) = split((12 + delta) as u32);
+        let program = [
+            Instruction::Auipc {
+                rd: REG_A0,
+                imm: hi,
+            },
+            addi(REG_A0, REG_A0, lo),
+            Instruction::Jal { rd: 11, offset: 4 },
+            Instruction::Alu {
+                op: AluOp::Sub,
+                rd: REG_A0,
+                rs1: REG_A0,
+                rs2: 11,
+            },
+        ];
+        assert_eq!(run(&program, REG_A0, &[]), delta as u32);
+    }
+}
+
+#[test]
+fn test_rv32ui_simple() {
+    assert_eq!(run(&[], 0, &[]), 0);
+}
+
+/// Run the binaries built by `riscv-tests/build.sh`
+///
+/// A test passes by exiting with 0 and fails with `(case << 1) | 1`.
+#[test]
+#[ignore = "needs the binaries built by riscv-tests/build.sh"]
+fn test_upstream_binaries() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("riscv-tests/bin");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.file_name() != Some("REVISION".as_ref()))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no tests in {}", dir.display());
+
+    let failed: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            let program = Program::from_elf(&fs::read(path).unwrap()).unwrap();
+            let vm = abi::load(&program, &[], &[]).unwrap();
+            let witness = Witness::build(vm.clone()).unwrap();
+            if !air::unsatisfied(&witness).is_empty() {
+                return Some(format!("{}: rejected by the AIR", name));
+            }
+            match vm.clone().run(100_000).map(|trace| trace.exit_code) {
+                Ok(0) => None,
+                Ok(code) => Some(format!("{}: case {} failed", name, code >> 1)),
+                Err(e) => Some(format!("{}: {}", name, e)),
+            }
+        })
+        .collect();
+    assert!(failed.is_empty(), "{:#?}", failed);
+}
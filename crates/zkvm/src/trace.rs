@@ -0,0 +1,169 @@
+//! Execution traces
+//!
+//! Every executed instruction produces one [`TraceRow`] recording the pc, the
+//! instruction word and each register and memory access it made. Rows are the
+//! witness of a proof of execution: replaying them from the initial memory
+//! image must reproduce every read. Precompile calls are recorded separately,
+//! in [`PrecompileTables`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::Width;
+
+/// A register read
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterRead {
+    pub register: u8,
+    pub value: u32,
+}
+
+/// A register write
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegisterWrite {
+    pub register: u8,
+    pub value: u32,
+}
+
+/// Direction of a memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+/// A data memory access (instruction fetches are not recorded)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryOp {
+    pub access: MemoryAccess,
+    pub address: u32,
+    pub width: Width,
+    /// Value read or written, zero-extended from `width`
+    pub value: u32,
+}
+
+/// One executed instruction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRow {
+    pub cycle: u64,
+    pub pc: u32,
+    pub instruction: u32,
+    pub rs1: Option<RegisterRead>,
+    pub rs2: Option<RegisterRead>,
+    /// Destination write; writes to `x0` are discarded and not recorded
+    pub rd: Option<RegisterWrite>,
+    pub memory: Option<MemoryOp>,
+    pub next_pc: u32,
+}
+
+/// A `SYS_POSEIDON` call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoseidonCall {
+    pub cycle: u64,
+    pub inputs: Vec<[u8; 32]>,
+    pub output: [u8; 32],
+}
+
+/// A `SYS_PEDERSEN_COMMIT` call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PedersenCall {
+    pub cycle: u64,
+    pub value: u64,
+    pub blinding: [u8; 32],
+    pub commitment: [u8; 32],
+}
+
+/// A `SYS_MERKLE_VERIFY` call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleCall {
+    pub cycle: u64,
+    pub leaf: [u8; 32],
+    pub root: [u8; 32],
+    pub path: Vec<[u8; 32]>,
+    pub position: u64,
+    pub valid: bool,
+}
+
+/// A `SYS_NOTE_NULLIFIER` call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NullifierCall {
+    pub cycle: u64,
+    pub value: u64,
+    /// The hash of the asset id the guest passed, reduced into the asset base
+    pub asset: [u8; 32],
+    pub owner: [u8; 32],
+    pub randomness: [u8; 32],
+    pub nullifier_key: [u8; 32],
+    pub position: u64,
+    pub commitment: [u8; 32],
+    pub nullifier: [u8; 32],
+}
+
+/// A `SYS_SIGNATURE_VERIFY` call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureCall {
+    pub cycle: u64,
+    pub key: [u8; 32],
+    pub signature: Vec<u8>,
+    /// The 64-byte challenge the guest hashed the message to
+    pub challenge: Vec<u8>,
+    pub valid: bool,
+}
+
+/// Precompile calls, one table per operation
+///
+/// A call streams its words through a run of `ecall` rows in the main trace;
+/// its inputs and outputs are recorded here, keyed by the cycle of the first
+/// of those rows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrecompileTables {
+    pub poseidon: Vec<PoseidonCall>,
+    pub pedersen: Vec<PedersenCall>,
+    pub merkle: Vec<MerkleCall>,
+    pub nullifier: Vec<NullifierCall>,
+    pub signature: Vec<SignatureCall>,
+}
+
+impl PrecompileTables {
+    /// Total number of calls
+    pub fn len(&self) -> usize {
+        self.poseidon.len()
+            + self.pedersen.len()
+            + self.merkle.len()
+            + self.nullifier.len()
+            + self.signature.len()
+    }
+
+    /// Whether no precompile was called
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The trace of a run up to its exit
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    pub rows: Vec<TraceRow>,
+    /// Exit code the guest passed to the exit system call
+    pub exit_code: u32,
+    /// Total gas charged, part of the public statement alongside the exit
+    /// code so it can be tied to fees
+    pub gas_used: u64,
+    pub precompiles: PrecompileTables,
+}
+
+impl ExecutionTrace {
+    /// Number of executed cycles
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether no instruction was executed
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Iterate over the memory accesses in execution order
+    pub fn memory_ops(&self) -> impl Iterator<Item = &MemoryOp> {
+        self.rows.iter().filter_map(|row| row.memory.as_ref())
+    }
+}
@@ -0,0 +1,101 @@
+//! Fiat-Shamir transcript
+//!
+//! Prover and verifier absorb the same messages in the same order and draw
+//! the same challenges from a running BLAKE3 hash. Each challenge is
+//! absorbed back, so successive challenges differ.
+
+use pasta_curves::group::ff::FromUniformBytes;
+
+use crate::field::{self, Felt};
+
+/// A transcript of the messages of one proof
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    hasher: blake3::Hasher,
+}
+
+impl Transcript {
+    /// A transcript for the protocol named `label`
+    pub fn new(label: &str) -> Self {
+        Self {
+            hasher: blake3::Hasher::new_derive_key(label),
+        }
+    }
+
+    pub fn absorb(&mut self, bytes: &[u8]) {
+        self.hasher.update(&(bytes.len() as u64).to_le_bytes());
+        self.hasher.update(bytes);
+    }
+
+    pub fn absorb_u64(&mut self, value: u64) {
+        self.absorb(&value.to_le_bytes());
+    }
+
+    pub fn absorb_felts(&mut self, values: &[Felt]) {
+        for value in values {
+            self.absorb(&field::to_bytes(value));
+        }
+    }
+
+    /// A uniformly random field element
+    pub fn challenge(&mut self) -> Felt {
+        Felt::from_uniform_bytes(&self.squeeze())
+    }
+
+    /// `count` indices below `bound`
+    pub fn indices(&mut self, count: usize, bound: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                let bytes = self.squeeze();
+                let value = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+                (value % bound as u64) as usize
+            })
+            .collect()
+    }
+
+    fn squeeze(&mut self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        self.hasher.finalize_xof().fill(&mut bytes);
+        self.absorb(&bytes);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcripts_agree() {
+        let mut prover = Transcript::new("test");
+        let mut verifier = Transcript::new("test");
+        prover.absorb(b"message");
+        verifier.absorb(b"message");
+
+        let challenge = prover.challenge();
+        assert_eq!(verifier.challenge(), challenge);
+        // Challenges are chained
+        assert_ne!(prover.challenge(), challenge);
+    }
+
+    #[test]
+    fn test_transcripts_bind_messages() {
+        let mut a = Transcript::new("test");
+        let mut b = Transcript::new("test");
+        a.absorb(b"ab");
+        a.absorb(b"c");
+        b.absorb(b"a");
+        b.absorb(b"bc");
+        assert_ne!(a.challenge(), b.challenge());
+
+        let mut other = Transcript::new("other");
+        assert_ne!(Transcript::new("test").challenge(), other.challenge());
+    }
+
+    #[test]
+    fn test_indices_in_bound() {
+        let indices = Transcript::new("test").indices(100, 12);
+        assert!(indices.iter().all(|&i| i < 12));
+        assert!(indices.iter().any(|&i| i != indices[0]));
+    }
+}
@@ -0,0 +1,389 @@
+//! STARK verifier
+//!
+//! [`verify`] replays the prover's transcript, checks the constraints at the
+//! out-of-domain point against the composition polynomial, and checks that
+//! each queried row is committed and folds through FRI. Verification is
+//! independent of the precompile calls a run makes, which the AIR
+//! constrains.
+
+use pasta_curves::group::ff::Field;
+
+use crate::abi::{self, MAX_IO_LEN};
+use crate::air::{self, Air, Challenges, Frame, MAX_TRACE_LEN, MIN_TRACE_LEN, PERIOD};
+use crate::elf::Program;
+use crate::field::{self, Felt};
+use crate::fri::{self, FriVerifier};
+use crate::gas::GasSchedule;
+use crate::merkle::{self, Digest};
+use crate::proof::{
+    self, Folder, Ood, Opening, Proof, Statement, BLOWUP, COMPOSITION_CHUNKS, QUERIES, WIDTHS,
+};
+use crate::witness;
+use crate::{Result, ZkvmError};
+
+/// Check that `proof` shows `statement` for `program`
+pub fn verify(program: &Program, statement: &Statement, proof: &Proof) -> Result<()> {
+    if program.hash() != statement.program_hash {
+        return Err(invalid("the statement is for another program"));
+    }
+    if statement.public_output.len() > MAX_IO_LEN {
+        return Err(invalid("the public output exceeds its region"));
+    }
+    let n = 1usize
+        .checked_shl(proof.log_trace_len)
+        .filter(|n| (MIN_TRACE_LEN..=MAX_TRACE_LEN).contains(n))
+        .ok_or_else(|| invalid("unsupported trace length"))?;
+    let size = n * BLOWUP;
+    let shift = field::coset_shift();
+
+    let vm = abi::load(program, &[], &statement.public_input)?;
+    if !vm.pc().is_multiple_of(4) || vm.pc() >= 1 << 31 {
+        return Err(invalid(
+            "the entry point is not an aligned address below 2^31",
+        ));
+    }
+
+    // Replay the transcript
+    let mut transcript = proof::transcript(statement, proof.log_trace_len);
+    transcript.absorb(&proof.main_root);
+    let challenges = Challenges {
+        alpha: transcript.challenge(),
+        beta: transcript.challenge(),
+    };
+    transcript.absorb(&proof.aux_root);
+    let gamma = transcript.challenge();
+    transcript.absorb(&proof.composition_root);
+    let z = proof::ood_point(&mut transcript, n);
+    let ood = Ood {
+        main: [
+            decode(&proof.main_ood[0], WIDTHS[0])?,
+            decode(&proof.main_ood[1], WIDTHS[0])?,
+        ],
+        aux: [
+            decode(&proof.aux_ood[0], WIDTHS[1])?,
+            decode(&proof.aux_ood[1], WIDTHS[1])?,
+        ],
+        composition: decode(&proof.composition_ood, WIDTHS[2])?,
+    };
+    ood.absorb(&mut transcript);
+    let lambda = transcript.challenge();
+
+    // The constraints at z
+    let air = Air::new(vm.pc(), &GasSchedule::default());
+    let public_sum = air::public_sum(
+        &challenges,
+        &witness::public_memory(&vm),
+        &witness::output_cells(&statement.public_output),
+        statement.exit_code,
+        statement.gas_used,
+    );
+    let z_period = z.pow_vartime([(n / PERIOD) as u64]);
+    let periodic: Vec<Felt> = proof::periodic_columns(&air)
+        .iter()
+        .map(|column| field::evaluate(column, z_period))
+        .collect();
+    let folder = Folder::fold(
+        &air,
+        &Frame {
+            current: &ood.main[0],
+            next: &ood.main[1],
+        },
+        &Frame {
+            current: &ood.aux[0],
+            next: &ood.aux[1],
+        },
+        &periodic,
+        &challenges,
+        public_sum,
+        gamma,
+    );
+    let z_n = z.pow_vartime([n as u64]);
+    let last_row = proof::last_row(n);
+    let mut inverses = [z_n - Felt::ONE, z - Felt::ONE, z - last_row];
+    field::batch_invert(&mut inverses);
+    let z_chunk = z.pow_vartime([proof::chunk_len(n) as u64]);
+    let chunks = ood.composition[..COMPOSITION_CHUNKS]
+        .iter()
+        .rev()
+        .fold(Felt::ZERO, |sum, chunk| sum * z_chunk + chunk);
+    if folder.composition(proof::random(z, n), z - last_row, inverses) != chunks {
+        return Err(invalid(
+            "the constraints do not hold at the out-of-domain point",
+        ));
+    }
+
+    // Queries
+    let fri = FriVerifier::new(&proof.fri, shift, size, n, &mut transcript)?;
+    let indices = transcript.indices(QUERIES, size);
+    if proof.queries.len() != indices.len() {
+        return Err(invalid("wrong number of queries"));
+    }
+    let root = field::root_of_unity(size);
+    let zg = z * field::root_of_unity(n);
+    for (index, query) in indices.into_iter().zip(&proof.queries) {
+        let main = open(&proof.main_root, index, &query.main, WIDTHS[0])?;
+        let aux = open(&proof.aux_root, index, &query.aux, WIDTHS[1])?;
+        let composition = open(
+            &proof.composition_root,
+            index,
+            &query.composition,
+            WIDTHS[2],
+        )?;
+
+        let x = shift * root.pow_vartime([index as u64]);
+        let mut quotients = [x - z, x - zg];
+        field::batch_invert(&mut quotients);
+        let deep = ood.deep(
+            lambda,
+            [&main, &aux, &composition],
+            quotients[0],
+            quotients[1],
+        );
+        fri.check(index, deep, &query.fri)?;
+    }
+    Ok(())
+}
+
+/// Decode `width` field elements
+fn decode(values: &[[u8; 32]], width: usize) -> Result<Vec<Felt>> {
+    if values.len() != width {
+        return Err(invalid("wrong number of values"));
+    }
+    values.iter().map(fri::decode).collect()
+}
+
+/// Decode an opening of row `index` and check it against `root`
+fn open(root: &Digest, index: usize, opening: &Opening, width: usize) -> Result<Vec<Felt>> {
+    let values = decode(&opening.values, width)?;
+    if !merkle::verify(root, index, &values, &opening.path) {
+        return Err(invalid("invalid trace opening"));
+    }
+    Ok(values)
+}
+
+fn invalid(reason: &str) -> ZkvmError {
+    ZkvmError::InvalidProof(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::{PRIVATE_INPUT_START, PUBLIC_INPUT_START, PUBLIC_OUTPUT_START};
+    use crate::air::RESULT;
+    use crate::elf::Segment;
+    use crate::gas::ResourceLimits;
+    use crate::instruction::{AluOp, BranchCondition, Instruction, LoadOp};
+    use crate::memory::Width;
+    use crate::precompile::{poseidon_hash, SYS_POSEIDON};
+    use crate::prover::prove;
+    use crate::vm::{REG_A0, REG_A7, SYS_EXIT};
+    use pasta_curves::group::ff::PrimeField;
+
+    const ENTRY: u32 = 0x1_0000;
+
+    fn addi(rd: u8, rs1: u8, imm: i32) -> Instruction {
+        Instruction::AluImm {
+            op: AluOp::Add,
+            rd,
+            rs1,
+            imm,
+        }
+    }
+
+    /// Commit the Poseidon hash of the two public input elements, then exit
+    /// with the sum of the private input bytes
+    fn program() -> Program {
+        let (t0, t1, t2, t3) = (5, 6, 7, 28);
+        let code = [
+            Instruction::Lui {
+                rd: t0,
+                imm: PUBLIC_INPUT_START,
+            },
+            Instruction::Lui {
+                rd: t3,
+                imm: PUBLIC_OUTPUT_START,
+            },
+            // Stream the header, the 16 input words and the 8 output words
+            addi(REG_A7, 0, SYS_POSEIDON as i32),
+            addi(REG_A0, 0, 2),
+            Instruction::Ecall,
+            addi(t0, t0, 4),
+            addi(t1, 0, 16),
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: REG_A0,
+                rs1: t0,
+                offset: 0,
+            },
+            Instruction::Ecall,
+            addi(t0, t0, 4),
+            addi(t1, t1, -1),
+            Instruction::Branch {
+                cond: BranchCondition::Ne,
+                rs1: t1,
+                rs2: 0,
+                offset: -16,
+            },
+            addi(t0, t3, 4),
+            addi(t1, 0, 8),
+            Instruction::Ecall,
+            Instruction::Store {
+                width: Width::Word,
+                rs1: t0,
+                rs2: REG_A0,
+                offset: 0,
+            },
+            addi(t0, t0, 4),
+            addi(t1, t1, -1),
+            Instruction::Branch {
+                cond: BranchCondition::Ne,
+                rs1: t1,
+                rs2: 0,
+                offset: -16,
+            },
+            addi(t2, 0, 32),
+            Instruction::Store {
+                width: Width::Word,
+                rs1: t3,
+                rs2: t2,
+                offset: 0,
+            },
+            Instruction::Lui {
+                rd: t0,
+                imm: PRIVATE_INPUT_START,
+            },
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: t1,
+                rs1: t0,
+                offset: 0,
+            },
+            addi(t0, t0, 4),
+            addi(REG_A0, 0, 0),
+            // loop: while t1 != 0
+            Instruction::Branch {
+                cond: BranchCondition::Eq,
+                rs1: t1,
+                rs2: 0,
+                offset: 24,
+            },
+            Instruction::Load {
+                op: LoadOp::Lbu,
+                rd: t2,
+                rs1: t0,
+                offset: 0,
+            },
+            Instruction::Alu {
+                op: AluOp::Add,
+                rd: REG_A0,
+                rs1: REG_A0,
+                rs2: t2,
+            },
+            addi(t0, t0, 1),
+            addi(t1, t1, -1),
+            Instruction::Jal { rd: 0, offset: -20 },
+            addi(REG_A7, 0, SYS_EXIT as i32),
+            Instruction::Ecall,
+        ];
+
+        Program {
+            entry: ENTRY,
+            segments: vec![Segment {
+                address: ENTRY,
+                data: code
+                    .iter()
+                    .flat_map(|i| i.encode().unwrap().to_le_bytes())
+                    .collect(),
+                size: 4 * code.len() as u32,
+                executable: true,
+            }],
+        }
+    }
+
+    fn public_input() -> Vec<u8> {
+        let mut input = [0u8; 64];
+        input[0] = 3;
+        input[32] = 5;
+        input.to_vec()
+    }
+
+    fn proven() -> (Statement, Proof) {
+        prove(
+            &program(),
+            &[1, 2, 3, 250],
+            &public_input(),
+            ResourceLimits::default(),
+        )
+        .unwrap()
+    }
+
+    fn rejects(statement: &Statement, proof: &Proof) -> bool {
+        matches!(
+            verify(&program(), statement, proof),
+            Err(ZkvmError::InvalidProof(_))
+        )
+    }
+
+    #[test]
+    fn test_prove_and_verify() {
+        let (statement, proof) = proven();
+        verify(&program(), &statement, &proof).unwrap();
+
+        let run = abi::execute(
+            &program(),
+            &[1, 2, 3, 250],
+            &public_input(),
+            ResourceLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(statement.program_hash, program().hash());
+        assert_eq!(statement.exit_code, 256);
+        assert_eq!(statement.public_output, run.public_output);
+        assert_eq!(statement.public_output.len(), 32);
+        assert_eq!(statement.gas_used, run.trace.gas_used);
+        let hash = poseidon_hash(&[Felt::from(3u64), Felt::from(5u64)]);
+        assert_eq!(statement.public_output, hash.to_repr());
+    }
+
+    #[test]
+    fn test_wrong_statement_rejected() {
+        let (statement, proof) = proven();
+        let wrong: [fn(&mut Statement); 5] = [
+            |statement| statement.exit_code += 1,
+            |statement| statement.gas_used -= 1,
+            |statement| statement.public_output[0] ^= 1,
+            |statement| statement.public_output.push(0),
+            |statement| statement.public_input[32] = 6,
+        ];
+        for change in wrong {
+            let mut statement = statement.clone();
+            change(&mut statement);
+            assert!(rejects(&statement, &proof), "{statement:?}");
+        }
+
+        let mut other = program();
+        other.segments[0].size += 4;
+        assert!(matches!(
+            verify(&other, &statement, &proof),
+            Err(ZkvmError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_tampered_proof_rejected() {
+        let (statement, proof) = proven();
+        let tampered: [fn(&mut Proof); 6] = [
+            |proof| proof.log_trace_len += 1,
+            |proof| proof.main_ood[0][RESULT] = [1; 32],
+            |proof| proof.aux_ood[1][0] = proof.aux_ood[0][0],
+            |proof| proof.composition_ood[0] = proof.composition_ood[1],
+            |proof| proof.queries[0].main.values[0] = proof.queries[0].aux.values[0],
+            |proof| proof.queries.truncate(QUERIES - 1),
+        ];
+        for change in tampered {
+            let mut proof = proof.clone();
+            change(&mut proof);
+            assert!(rejects(&statement, &proof));
+        }
+    }
+}
@@ -0,0 +1,733 @@
+//! RV32IM interpreter
+//!
+//! [`Vm`] executes one instruction per cycle and records a [`TraceRow`] for
+//! each. Execution depends only on the initial memory and pc, so a run can be
+//! replayed exactly by a prover. A guest stops with the exit system call:
+//! `ecall` with `a7 = 93` and the exit code in `a0`. Other call numbers run
+//! a [precompile](crate::precompile), streamed one word per `ecall` in `a0`
+//! and answered in `a0`; the VM holds the call's words until it has them all.
+//!
+//! Each instruction is charged gas from a [`GasSchedule`] before it executes,
+//! within the [`ResourceLimits`] of the VM. Memory is charged per page the
+//! guest writes to, once, whether or not the page was already allocated. An
+//! instruction that faults, including by exceeding a limit, leaves registers,
+//! memory, pc and gas used unchanged.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::gas::{GasSchedule, ResourceLimits};
+use crate::instruction::Instruction;
+use crate::memory::{self, Memory, Width};
+use crate::precompile;
+use crate::trace::{
+    ExecutionTrace, MemoryAccess, MemoryOp, PrecompileTables, RegisterRead, RegisterWrite, TraceRow,
+};
+use crate::{Result, ZkvmError};
+
+/// Register holding the first argument and the exit code (`a0`)
+pub const REG_A0: u8 = 10;
+
+/// Register holding the system call number (`a7`)
+pub const REG_A7: u8 = 17;
+
+/// System call number of `exit`
+pub const SYS_EXIT: u32 = privl1_zkvm_guest::SYS_EXIT;
+
+/// Interpreter state
+#[derive(Clone, Debug)]
+pub struct Vm {
+    pc: u32,
+    registers: [u32; 32],
+    memory: Memory,
+    cycle: u64,
+    exit_code: Option<u32>,
+    precompiles: PrecompileTables,
+    schedule: GasSchedule,
+    limits: ResourceLimits,
+    gas_used: u64,
+    /// Pages the guest has written to, each charged once
+    written_pages: BTreeSet<u32>,
+    /// Precompile call being streamed
+    pending: Option<PendingCall>,
+}
+
+/// A precompile call started but not finished
+#[derive(Clone, Debug)]
+struct PendingCall {
+    number: u32,
+    param: u32,
+    /// Cycle of the header `ecall`
+    cycle: u64,
+    /// Input words received so far
+    inputs: Vec<u32>,
+    /// Input words the call takes
+    input_len: usize,
+    /// Output words not yet returned, once the call has run
+    outputs: Vec<u32>,
+}
+
+impl Vm {
+    /// Start executing at `entry` with the given memory and all registers
+    /// zero, under the default gas schedule and limits
+    pub fn new(memory: Memory, entry: u32) -> Self {
+        Self {
+            pc: entry,
+            registers: [0; 32],
+            memory,
+            cycle: 0,
+            exit_code: None,
+            precompiles: PrecompileTables::default(),
+            schedule: GasSchedule::default(),
+            limits: ResourceLimits::default(),
+            gas_used: 0,
+            written_pages: BTreeSet::new(),
+            pending: None,
+        }
+    }
+
+    /// Charge gas according to `schedule`
+    pub fn with_schedule(mut self, schedule: GasSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Enforce `limits` on the rest of the run
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Load `program` at `address` and start executing its first instruction
+    pub fn from_program(address: u32, program: &[u32]) -> Result<Self> {
+        let bytes: Vec<u8> = program.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let mut memory = Memory::new();
+        memory.write_bytes(address, &bytes)?;
+
+        Ok(Self::new(memory, address))
+    }
+
+    /// Address of the next instruction
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// Value of register `x{index}`
+    pub fn register(&self, index: u8) -> u32 {
+        self.registers[index as usize]
+    }
+
+    /// Set register `x{index}`; writes to `x0` are ignored
+    pub fn set_register(&mut self, index: u8, value: u32) {
+        if index != 0 {
+            self.registers[index as usize] = value;
+        }
+    }
+
+    /// Guest memory
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Guest memory, e.g. to pass input before running
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Number of instructions executed so far
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// Exit code, once the guest has exited
+    pub fn exit_code(&self) -> Option<u32> {
+        self.exit_code
+    }
+
+    /// Gas charged so far
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    /// Limits enforced on the run
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Gas schedule of the run
+    pub fn schedule(&self) -> &GasSchedule {
+        &self.schedule
+    }
+
+    /// Precompile calls made since the last [`Vm::run`]
+    pub fn precompiles(&self) -> &PrecompileTables {
+        &self.precompiles
+    }
+
+    /// Execute one instruction
+    pub fn step(&mut self) -> Result<TraceRow> {
+        if self.exit_code.is_some() {
+            return Err(ZkvmError::Halted);
+        }
+        if self.cycle >= self.limits.max_cycles {
+            return Err(ZkvmError::CycleLimit(self.limits.max_cycles));
+        }
+
+        let pc = self.pc;
+        let word = self.memory.load(pc, Width::Word)?;
+        let instruction =
+            Instruction::decode(word).ok_or(ZkvmError::IllegalInstruction { pc, word })?;
+        let (gas_used, pages) = self.charge(&instruction)?;
+        let mut row = TraceRow {
+            cycle: self.cycle,
+            pc,
+            instruction: word,
+            rs1: None,
+            rs2: None,
+            rd: None,
+            memory: None,
+            next_pc: pc.wrapping_add(4),
+        };
+
+        match instruction {
+            Instruction::Lui { rd, imm } => self.write(&mut row, rd, imm),
+            Instruction::Auipc { rd, imm } => self.write(&mut row, rd, pc.wrapping_add(imm)),
+            Instruction::Jal { rd, offset } => {
+                jump(&mut row, pc.wrapping_add(offset as u32))?;
+                self.write(&mut row, rd, pc.wrapping_add(4));
+            }
+            Instruction::Jalr { rd, rs1, offset } => {
+                let base = self.read(&mut row.rs1, rs1);
+                jump(&mut row, base.wrapping_add(offset as u32) & !1)?;
+                self.write(&mut row, rd, pc.wrapping_add(4));
+            }
+            Instruction::Branch {
+                cond,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let a = self.read(&mut row.rs1, rs1);
+                let b = self.read(&mut row.rs2, rs2);
+                if cond.holds(a, b) {
+                    jump(&mut row, pc.wrapping_add(offset as u32))?;
+                }
+            }
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let address = self.read(&mut row.rs1, rs1).wrapping_add(offset as u32);
+                let value = self.memory.load(address, op.width())?;
+                row.memory = Some(MemoryOp {
+                    access: MemoryAccess::Read,
+                    address,
+                    width: op.width(),
+                    value,
+                });
+                self.write(&mut row, rd, op.extend(value));
+            }
+            Instruction::Store {
+                width,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let address = self.read(&mut row.rs1, rs1).wrapping_add(offset as u32);
+                let value = self.read(&mut row.rs2, rs2);
+                self.memory.store(address, width, value)?;
+                row.memory = Some(MemoryOp {
+                    access: MemoryAccess::Write,
+                    address,
+                    width,
+                    value: truncate(value, width),
+                });
+            }
+            Instruction::AluImm { op, rd, rs1, imm } => {
+                let a = self.read(&mut row.rs1, rs1);
+                self.write(&mut row, rd, op.apply(a, imm as u32));
+            }
+            Instruction::Alu { op, rd, rs1, rs2 } => {
+                let a = self.read(&mut row.rs1, rs1);
+                let b = self.read(&mut row.rs2, rs2);
+                self.write(&mut row, rd, op.apply(a, b));
+            }
+            Instruction::Fence => {}
+            Instruction::Ecall => self.syscall(&mut row)?,
+            Instruction::Ebreak => return Err(ZkvmError::Breakpoint(pc)),
+        }
+
+        self.pc = row.next_pc;
+        self.cycle += 1;
+        self.gas_used = gas_used;
+        self.written_pages.extend(pages);
+        Ok(row)
+    }
+
+    /// Run until the guest exits, failing after `max_cycles` instructions
+    pub fn run(&mut self, max_cycles: u64) -> Result<ExecutionTrace> {
+        let mut rows = Vec::new();
+        loop {
+            if rows.len() as u64 == max_cycles {
+                return Err(ZkvmError::CycleLimit(max_cycles));
+            }
+            rows.push(self.step()?);
+            if let Some(exit_code) = self.exit_code {
+                return Ok(ExecutionTrace {
+                    rows,
+                    exit_code,
+                    gas_used: self.gas_used,
+                    precompiles: std::mem::take(&mut self.precompiles),
+                });
+            }
+        }
+    }
+
+    /// Handle `ecall`; the call number (`a7`) and word (`a0`) are recorded as
+    /// the two register reads, and a precompile's answer as the write to `a0`
+    fn syscall(&mut self, row: &mut TraceRow) -> Result<()> {
+        let number = self.read(&mut row.rs1, REG_A7);
+        let word = self.read(&mut row.rs2, REG_A0);
+        if let Some(pending) = &self.pending {
+            if number != pending.number {
+                return Err(ZkvmError::InvalidSyscallInput {
+                    number,
+                    reason: format!("call {:#x} is unfinished", pending.number),
+                });
+            }
+            return self.stream(row, word);
+        }
+        match number {
+            SYS_EXIT => {
+                self.exit_code = Some(word);
+                Ok(())
+            }
+            _ if precompile::is_precompile(number) => {
+                let input_len = precompile::input_words(number, word)?;
+                self.pending = Some(PendingCall {
+                    number,
+                    param: word,
+                    cycle: self.cycle,
+                    inputs: Vec::with_capacity(input_len),
+                    input_len,
+                    outputs: Vec::new(),
+                });
+                self.write(row, REG_A0, 0);
+                Ok(())
+            }
+            _ => Err(ZkvmError::UnsupportedSyscall(number)),
+        }
+    }
+
+    /// Take the next word of the pending call: an input, running the call on
+    /// the last one, or a request for the next output word
+    fn stream(&mut self, row: &mut TraceRow, word: u32) -> Result<()> {
+        let pending = self.pending.as_mut().expect("a pending call");
+        if pending.inputs.len() < pending.input_len {
+            if pending.inputs.len() + 1 == pending.input_len {
+                let mut inputs = pending.inputs.clone();
+                inputs.push(word);
+                let mut outputs = precompile::call(
+                    pending.number,
+                    pending.param,
+                    &inputs,
+                    pending.cycle,
+                    &mut self.precompiles,
+                )?;
+                outputs.reverse();
+                pending.outputs = outputs;
+            }
+            pending.inputs.push(word);
+            self.write(row, REG_A0, 0);
+        } else {
+            let output = pending.outputs.pop().expect("an output word");
+            if pending.outputs.is_empty() {
+                self.pending = None;
+            }
+            self.write(row, REG_A0, output);
+        }
+        Ok(())
+    }
+
+    /// Gas used once `instruction` has executed and the pages it writes to,
+    /// checking the gas and memory limits
+    fn charge(&self, instruction: &Instruction) -> Result<(u64, Range<u32>)> {
+        let mut cost = self.schedule.instruction(instruction);
+        let write = match *instruction {
+            Instruction::Store {
+                width, rs1, offset, ..
+            } => Some((
+                self.register(rs1).wrapping_add(offset as u32),
+                width.bytes() as usize,
+            )),
+            Instruction::Ecall => {
+                if self.pending.is_none() {
+                    let number = self.register(REG_A7);
+                    cost = cost.saturating_add(self.schedule.syscall(number));
+                }
+                None
+            }
+            _ => None,
+        };
+
+        let (address, len) = write.unwrap_or((0, 0));
+        let pages = self.memory.new_pages(address, len);
+        if pages > 0 && self.memory.page_count() + pages > self.limits.max_pages {
+            return Err(ZkvmError::MemoryLimit {
+                pages: self.memory.page_count() + pages,
+                max: self.limits.max_pages,
+            });
+        }
+
+        let written = memory::pages(address, len);
+        let first_writes = written
+            .clone()
+            .filter(|page| !self.written_pages.contains(page))
+            .count();
+        let required = self
+            .gas_used
+            .saturating_add(cost)
+            .saturating_add(self.schedule.memory(first_writes));
+        if required > self.limits.gas {
+            return Err(ZkvmError::OutOfGas {
+                limit: self.limits.gas,
+                required,
+            });
+        }
+        Ok((required, written))
+    }
+
+    fn read(&self, slot: &mut Option<RegisterRead>, register: u8) -> u32 {
+        let value = self.register(register);
+        *slot = Some(RegisterRead { register, value });
+        value
+    }
+
+    fn write(&mut self, row: &mut TraceRow, register: u8, value: u32) {
+        if register != 0 {
+            self.registers[register as usize] = value;
+            row.rd = Some(RegisterWrite { register, value });
+        }
+    }
+}
+
+fn jump(row: &mut TraceRow, target: u32) -> Result<()> {
+    if !target.is_multiple_of(4) {
+        return Err(ZkvmError::MisalignedJump(target));
+    }
+    row.next_pc = target;
+    Ok(())
+}
+
+fn truncate(value: u32, width: Width) -> u32 {
+    match width {
+        Width::Byte => value & 0xff,
+        Width::Half => value & 0xffff,
+        Width::Word => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{AluOp, BranchCondition, LoadOp};
+
+    const BASE: u32 = 0x1000;
+
+    fn addi(rd: u8, rs1: u8, imm: i32) -> Instruction {
+        Instruction::AluImm {
+            op: AluOp::Add,
+            rd,
+            rs1,
+            imm,
+        }
+    }
+
+    fn exit() -> [Instruction; 2] {
+        [addi(REG_A7, 0, SYS_EXIT as i32), Instruction::Ecall]
+    }
+
+    fn vm(program: &[Instruction]) -> Vm {
+        let words: Vec<u32> = program.iter().map(|i| i.encode().unwrap()).collect();
+        Vm::from_program(BASE, &words).unwrap()
+    }
+
+    /// Sum 1..=10 into a0 with a countdown loop
+    fn sum_program() -> Vec<Instruction> {
+        let mut program = vec![
+            addi(5, 0, 10),
+            addi(REG_A0, 0, 0),
+            Instruction::Alu {
+                op: AluOp::Add,
+                rd: REG_A0,
+                rs1: REG_A0,
+                rs2: 5,
+            },
+            addi(5, 5, -1),
+            Instruction::Branch {
+                cond: BranchCondition::Ne,
+                rs1: 5,
+                rs2: 0,
+                offset: -8,
+            },
+        ];
+        program.extend(exit());
+        program
+    }
+
+    #[test]
+    fn test_loop_runs_to_exit() {
+        let trace = vm(&sum_program()).run(1_000).unwrap();
+
+        assert_eq!(trace.exit_code, 55);
+        assert_eq!(trace.len(), 2 + 3 * 10 + 2);
+        for (cycle, row) in trace.rows.iter().enumerate() {
+            assert_eq!(row.cycle, cycle as u64);
+        }
+        for pair in trace.rows.windows(2) {
+            assert_eq!(pair[0].next_pc, pair[1].pc);
+        }
+
+        // The backward branch is taken nine times, then falls through
+        let branches: Vec<_> = trace
+            .rows
+            .iter()
+            .filter(|row| row.pc == BASE + 16)
+            .collect();
+        assert_eq!(branches.len(), 10);
+        assert!(branches[..9].iter().all(|row| row.next_pc == BASE + 8));
+        assert_eq!(branches[9].next_pc, BASE + 20);
+    }
+
+    #[test]
+    fn test_trace_records_accesses() {
+        let mut program = vec![
+            Instruction::Lui { rd: 6, imm: 0x2000 },
+            addi(7, 0, -2),
+            Instruction::Store {
+                width: Width::Half,
+                rs1: 6,
+                rs2: 7,
+                offset: 2,
+            },
+            Instruction::Load {
+                op: LoadOp::Lh,
+                rd: REG_A0,
+                rs1: 6,
+                offset: 2,
+            },
+            addi(0, REG_A0, 1),
+        ];
+        program.extend(exit());
+        let mut vm = vm(&program);
+        let trace = vm.run(100).unwrap();
+
+        let store = &trace.rows[2];
+        assert_eq!(
+            store.rs1,
+            Some(RegisterRead {
+                register: 6,
+                value: 0x2000
+            })
+        );
+        assert_eq!(
+            store.rs2,
+            Some(RegisterRead {
+                register: 7,
+                value: 0xffff_fffe
+            })
+        );
+        assert_eq!(store.rd, None);
+        assert_eq!(
+            store.memory,
+            Some(MemoryOp {
+                access: MemoryAccess::Write,
+                address: 0x2002,
+                width: Width::Half,
+                value: 0xfffe,
+            })
+        );
+
+        let load = &trace.rows[3];
+        assert_eq!(load.memory.unwrap().access, MemoryAccess::Read);
+        assert_eq!(load.memory.unwrap().value, 0xfffe);
+        assert_eq!(
+            load.rd,
+            Some(RegisterWrite {
+                register: REG_A0,
+                value: 0xffff_fffe
+            })
+        );
+        assert_eq!(trace.memory_ops().count(), 2);
+
+        // Writes to x0 are dropped
+        assert_eq!(trace.rows[4].rd, None);
+        assert_eq!(vm.register(0), 0);
+
+        let ecall = trace.rows.last().unwrap();
+        assert_eq!(
+            ecall.rs1,
+            Some(RegisterRead {
+                register: REG_A7,
+                value: SYS_EXIT
+            })
+        );
+        assert_eq!(trace.exit_code, 0xffff_fffe);
+    }
+
+    #[test]
+    fn test_execution_is_deterministic() {
+        let first = vm(&sum_program()).run(1_000).unwrap();
+        let second = vm(&sum_program()).run(1_000).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_faults_leave_state_unchanged() {
+        // jalr to a 2-byte aligned address
+        let mut jump = vm(&[
+            addi(5, 0, 6),
+            Instruction::Jalr {
+                rd: 1,
+                rs1: 5,
+                offset: 0,
+            },
+        ]);
+        jump.step().unwrap();
+        assert!(matches!(jump.step(), Err(ZkvmError::MisalignedJump(6))));
+        assert_eq!(jump.pc(), BASE + 4);
+        assert_eq!(jump.register(1), 0);
+        assert_eq!(jump.cycle(), 1);
+
+        let mut load = vm(&[Instruction::Load {
+            op: LoadOp::Lw,
+            rd: 1,
+            rs1: 0,
+            offset: 2,
+        }]);
+        assert!(matches!(
+            load.step(),
+            Err(ZkvmError::MisalignedAccess {
+                address: 2,
+                width: 4
+            })
+        ));
+        assert_eq!(load.pc(), BASE);
+
+        let mut illegal = Vm::from_program(BASE, &[0x3000_2573]).unwrap();
+        assert!(matches!(
+            illegal.step(),
+            Err(ZkvmError::IllegalInstruction {
+                pc: BASE,
+                word: 0x3000_2573
+            })
+        ));
+        assert!(matches!(
+            vm(&[Instruction::Ebreak]).step(),
+            Err(ZkvmError::Breakpoint(BASE))
+        ));
+    }
+
+    #[test]
+    fn test_run_limits() {
+        // An infinite loop hits the cycle limit
+        let mut spin = vm(&[Instruction::Jal { rd: 0, offset: 0 }]);
+        assert!(matches!(spin.run(50), Err(ZkvmError::CycleLimit(50))));
+        assert_eq!(spin.cycle(), 50);
+
+        let mut unknown = vm(&[addi(REG_A7, 0, 1), Instruction::Ecall]);
+        assert!(matches!(
+            unknown.run(10),
+            Err(ZkvmError::UnsupportedSyscall(1))
+        ));
+
+        let mut exited = vm(&exit());
+        exited.run(10).unwrap();
+        assert_eq!(exited.exit_code(), Some(0));
+        assert!(matches!(exited.step(), Err(ZkvmError::Halted)));
+
+        // The hard limit applies whatever bound is passed to `run`
+        let mut capped = vm(&[Instruction::Jal { rd: 0, offset: 0 }]).with_limits(ResourceLimits {
+            max_cycles: 10,
+            ..ResourceLimits::default()
+        });
+        assert!(matches!(capped.run(1_000), Err(ZkvmError::CycleLimit(10))));
+    }
+
+    #[test]
+    fn test_gas_metering() {
+        let gas = GasSchedule::default();
+        let trace = vm(&sum_program()).run(1_000).unwrap();
+        assert_eq!(
+            trace.gas_used,
+            3 * gas.alu + 10 * (2 * gas.alu + gas.branch) + gas.system
+        );
+
+        // The first store to a page also pays for the page, even if the page
+        // was already allocated by loading the program
+        let store = |rs1, offset| Instruction::Store {
+            width: Width::Word,
+            rs1,
+            rs2: 0,
+            offset,
+        };
+        let mut stores = vm(&[
+            Instruction::Lui { rd: 6, imm: 0x2000 },
+            store(6, 0),
+            store(6, 4),
+            Instruction::Lui { rd: 7, imm: BASE },
+            store(7, 0x100),
+        ]);
+        for _ in 0..3 {
+            stores.step().unwrap();
+        }
+        assert_eq!(stores.gas_used(), gas.alu + 2 * gas.store + gas.page);
+        stores.step().unwrap();
+        stores.step().unwrap();
+        assert_eq!(
+            stores.gas_used(),
+            2 * gas.alu + 3 * gas.store + 2 * gas.page
+        );
+    }
+
+    #[test]
+    fn test_limits_fault_without_side_effects() {
+        let mut starved = vm(&sum_program()).with_limits(ResourceLimits {
+            gas: 4,
+            ..ResourceLimits::default()
+        });
+        assert!(matches!(
+            starved.run(1_000),
+            Err(ZkvmError::OutOfGas {
+                limit: 4,
+                required: 5
+            })
+        ));
+        assert_eq!(starved.gas_used(), 4);
+        assert_eq!(starved.pc(), BASE + 16);
+
+        let mut full = vm(&[
+            Instruction::Lui { rd: 6, imm: 0x2000 },
+            Instruction::Store {
+                width: Width::Byte,
+                rs1: 6,
+                rs2: 6,
+                offset: 0,
+            },
+        ])
+        .with_limits(ResourceLimits {
+            max_pages: 1,
+            ..ResourceLimits::default()
+        });
+        full.step().unwrap();
+        assert!(matches!(
+            full.step(),
+            Err(ZkvmError::MemoryLimit { pages: 2, max: 1 })
+        ));
+        assert_eq!(full.memory().page_count(), 1);
+        assert_eq!(full.gas_used(), GasSchedule::default().alu);
+        assert_eq!(full.pc(), BASE + 4);
+    }
+}
@@ -0,0 +1,554 @@
+//! Execution witness for the [AIR](crate::air)
+//!
+//! [`Witness::build`] runs a VM to its exit and records a main trace row per
+//! cycle: the instruction, its operands, the helper values of its
+//! constraints, and the previous value and timestamp of every cell it
+//! accesses. The touched cells, with their initial and final values, fill
+//! the memory table columns of the first rows, and the precompile calls the
+//! columns of their [units](calls). The last [`RANDOM_ROWS`] rows of the
+//! main and aux traces are random.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use pasta_curves::group::ff::Field;
+use rand::rngs::OsRng;
+
+use crate::abi::{self, PUBLIC_OUTPUT_START};
+use crate::air::*;
+use crate::field::{self, Felt};
+use crate::gas::GasSchedule;
+use crate::instruction::{b_immediate, j_immediate, s_immediate, AluOp, Instruction, ECALL};
+use crate::memory::{Width, PAGE_SIZE};
+use crate::vm::{Vm, REG_A0, REG_A7};
+use crate::{Result, ZkvmError};
+
+mod calls;
+
+use calls::{Calls, Ecall};
+
+/// A run of the VM laid out as a main trace
+pub(crate) struct Witness {
+    /// Main trace rows, padded to a power of two
+    pub rows: Vec<Vec<Felt>>,
+    pub entry: u32,
+    /// Initial cells the verifier knows, as `(cell, value)`
+    pub public_memory: Vec<(u64, u32)>,
+    /// Final cells of the public output region
+    pub output: Vec<(u64, u32)>,
+    pub exit_code: u32,
+    pub gas_used: u64,
+    pub public_output: Vec<u8>,
+}
+
+impl Witness {
+    /// Run `vm`, which must not have started, to its exit
+    pub fn build(vm: Vm) -> Result<Self> {
+        let mut vm = vm;
+        if vm.cycle() != 0 {
+            return Err(unprovable("the VM has already started"));
+        }
+        if *vm.schedule() != GasSchedule::default() {
+            return Err(unprovable("proofs use the default gas schedule"));
+        }
+        let entry = vm.pc();
+        if !entry.is_multiple_of(4) || entry >= 1 << 31 {
+            return Err(unprovable(
+                "the entry point is not an aligned address below 2^31",
+            ));
+        }
+
+        let public_memory = public_memory(&vm);
+        let mut builder = Builder {
+            cells: BTreeMap::new(),
+            written_pages: BTreeSet::new(),
+            ecalls: Vec::new(),
+            schedule: GasSchedule::default(),
+        };
+        let mut rows = Vec::new();
+        while vm.exit_code().is_none() {
+            if rows.len() + 1 + RANDOM_ROWS >= MAX_TRACE_LEN {
+                return Err(unprovable(&format!(
+                    "runs are limited to {} cycles",
+                    MAX_TRACE_LEN - RANDOM_ROWS - 1
+                )));
+            }
+            rows.push(builder.step(&mut vm)?);
+        }
+
+        let public_output = abi::public_output(vm.memory())?;
+        let output = output_cells(&public_output);
+        for &(cell, value) in &output {
+            if vm.memory().load(cell as u32 * 4, Width::Word)? != value {
+                return Err(unprovable(
+                    "the public output is followed by nonzero bytes in its last word",
+                ));
+            }
+        }
+        // Cells the verifier adds lookups for must be in the table
+        for &(cell, value) in public_memory.iter().chain(&output) {
+            builder.cells.entry(cell).or_insert(Cell {
+                init: value,
+                value,
+                timestamp: 0,
+            });
+        }
+
+        let calls = Calls::new(&builder.ecalls);
+        let trace_len = ((rows.len() + 1)
+            .max(builder.cells.len())
+            .max(calls.stream_len() + 1)
+            .max(calls.permutation_len())
+            .max(calls.scalar_len() + 1)
+            .max(calls.control_len() + 1)
+            + RANDOM_ROWS)
+            .max(MIN_TRACE_LEN)
+            .next_power_of_two();
+        if trace_len > MAX_TRACE_LEN {
+            return Err(unprovable(
+                "the run touches too many cells or makes too many precompile calls",
+            ));
+        }
+        let gas_used = vm.gas_used();
+        for cycle in rows.len()..trace_len {
+            let mut row = Row::new();
+            row.set(CYCLE, cycle as u64);
+            row.set(PADDING, 1);
+            row.set(GAS, gas_used);
+            row.set(ECALLS, builder.ecalls.len() as u64);
+            rows.push(row.0);
+        }
+
+        let public: BTreeSet<u64> = public_memory.iter().map(|(cell, _)| *cell).collect();
+        let outputs: BTreeSet<u64> = output.iter().map(|(cell, _)| *cell).collect();
+        let cells: Vec<(&u64, &Cell)> = builder.cells.iter().collect();
+        for (i, &(&cell, entry)) in cells.iter().enumerate() {
+            let row = &mut rows[i];
+            let private = is_private(cell);
+            set(row, M_ACTIVE, 1);
+            set(row, M_PUBLIC, public.contains(&cell) as u64);
+            set(row, M_PRIVATE, private as u64);
+            set(row, M_OUTPUT, outputs.contains(&cell) as u64);
+            set(row, M_ADDRESS, cell);
+            set(row, M_INIT, entry.init as u64);
+            set(row, M_FINAL, entry.value as u64);
+            set(row, M_FINAL_TS, entry.timestamp);
+            if let Some(&(&next, _)) = cells.get(i + 1) {
+                set_bits(row, M_DIFF_BITS, next - cell - 1, 32);
+            }
+            if private {
+                set_bits(row, M_PRIVATE_BITS, cell - PRIVATE_CELLS, PRIVATE_BITS);
+            }
+        }
+
+        let last = last_row(trace_len);
+        calls.fill(&mut rows[..=last]);
+        for row in &mut rows[last + 1..] {
+            row.fill_with(|| Felt::random(OsRng));
+        }
+
+        Ok(Self {
+            rows,
+            entry,
+            public_memory,
+            output,
+            exit_code: vm.exit_code().expect("the guest exited"),
+            gas_used,
+            public_output,
+        })
+    }
+
+    /// Aux trace rows: each row's lookups and the running sum before it, on
+    /// the rows the constraints hold on, then random rows
+    pub fn aux(&self, air: &Air, challenges: &Challenges) -> Vec<Vec<Felt>> {
+        let last = last_row(self.rows.len());
+        let lookups: Vec<Vec<Lookup>> = self.rows[..=last]
+            .iter()
+            .enumerate()
+            .map(|(i, row)| air.lookups(row, &air.periodic(i), challenges))
+            .collect();
+        let mut inverses: Vec<Felt> = lookups
+            .iter()
+            .flatten()
+            .flat_map(|lookup| lookup.denominators)
+            .collect();
+        field::batch_invert(&mut inverses);
+
+        let mut inverses = inverses.into_iter();
+        let mut sum = Felt::ZERO;
+        let mut aux: Vec<Vec<Felt>> = lookups
+            .iter()
+            .map(|row| {
+                let mut aux = vec![Felt::ZERO; AUX_WIDTH];
+                for (fraction, lookup) in aux.iter_mut().zip(row) {
+                    for numerator in lookup.numerators {
+                        *fraction +=
+                            numerator * inverses.next().expect("an inverse per denominator");
+                    }
+                }
+                aux[RUNNING_SUM] = sum;
+                sum += aux[..LOOKUPS].iter().sum::<Felt>();
+                aux
+            })
+            .collect();
+        aux.resize_with(self.rows.len(), || {
+            (0..AUX_WIDTH).map(|_| Felt::random(OsRng)).collect()
+        });
+        aux
+    }
+}
+
+/// Initial cells the verifier knows: nonzero registers, and nonzero memory
+/// words outside the private input region
+pub(crate) fn public_memory(vm: &Vm) -> Vec<(u64, u32)> {
+    let words = vm
+        .memory()
+        .words()
+        .map(|(address, value)| (word_cell(address), value))
+        .filter(|(cell, _)| !is_private(*cell));
+    let registers = (1..32)
+        .map(|index| (register_cell(index), vm.register(index)))
+        .filter(|(_, value)| *value != 0);
+    words.chain(registers).collect()
+}
+
+/// Cells of the public output region holding `output`: the length word and
+/// the bytes, zero-padded to a whole word
+pub(crate) fn output_cells(output: &[u8]) -> Vec<(u64, u32)> {
+    let mut bytes = (output.len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(output);
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+    bytes
+        .chunks(4)
+        .enumerate()
+        .map(|(i, word)| {
+            let value = u32::from_le_bytes(word.try_into().expect("4-byte chunks"));
+            (word_cell(PUBLIC_OUTPUT_START) + i as u64, value)
+        })
+        .collect()
+}
+
+fn is_private(cell: u64) -> bool {
+    (PRIVATE_CELLS..PRIVATE_CELLS + (1 << PRIVATE_BITS)).contains(&cell)
+}
+
+/// A touched cell
+struct Cell {
+    init: u32,
+    value: u32,
+    /// Timestamp of the last access
+    timestamp: u64,
+}
+
+/// State carried between cycles
+struct Builder {
+    cells: BTreeMap<u64, Cell>,
+    written_pages: BTreeSet<u32>,
+    /// Precompile `ecall`s so far
+    ecalls: Vec<Ecall>,
+    schedule: GasSchedule,
+}
+
+impl Builder {
+    /// Execute one instruction and lay out its row
+    fn step(&mut self, vm: &mut Vm) -> Result<Vec<Felt>> {
+        let cycle = vm.cycle();
+        let pc = vm.pc();
+        let gas = vm.gas_used();
+        let before: [u32; 32] = std::array::from_fn(|i| vm.register(i as u8));
+        let word = vm.memory().load(pc, Width::Word)?;
+        let ecall = word == ECALL;
+
+        // State the instruction reads, before it changes
+        let address = match Instruction::decode(word) {
+            Some(
+                Instruction::Load { rs1, offset, .. } | Instruction::Store { rs1, offset, .. },
+            ) => Some(before[rs1 as usize].wrapping_add(offset as u32)),
+            _ => None,
+        };
+        let old_word = match address {
+            Some(address) => vm.memory().load(address & !3, Width::Word)?,
+            None => 0,
+        };
+        vm.step()?;
+        let instruction = Instruction::decode(word).expect("executed instructions decode");
+        let op = Op::of(&instruction).expect("ebreak faults");
+        let after: [u32; 32] = std::array::from_fn(|i| vm.register(i as u8));
+
+        let mut row = Row::new();
+        row.set(CYCLE, cycle);
+        row.set(PC, pc as u64);
+        row.set(op.column(), 1);
+        row.set(GAS, gas);
+        row.set(ECALLS, self.ecalls.len() as u64);
+        row.set(WORD, word as u64);
+        row.bits(WORD_BITS, word as u64, 32);
+        let imm = immediate(word, op.format());
+        row.set(IMM, imm as u64);
+
+        let field = |start: u32| ((word >> start) & 0x1f) as u8;
+        let (rs1, rs2, rd) = if ecall {
+            (REG_A7, REG_A0, REG_A0)
+        } else {
+            (field(15), field(20), field(7))
+        };
+        let (x, rs2_value) = (before[rs1 as usize], before[rs2 as usize]);
+        row.set(RS1, x as u64);
+        row.bits(X_BITS, x as u64, 32);
+        row.set(RS2, rs2_value as u64);
+        let y = match op {
+            Op::Branch(_) | Op::Store(_) | Op::Alu(_) => rs2_value,
+            Op::AluImm(_) => imm,
+            _ => 0,
+        };
+        row.bits(Y_BITS, y as u64, 32);
+
+        let rd_field = field(7) as u64;
+        row.set(RD_NONZERO, (rd_field != 0) as u64);
+        row.felt(RD_INV, inverse(Felt::from(rd_field)));
+        row.set(RD_OLD, before[rd as usize] as u64);
+        row.set(RD_NEW, after[rd as usize] as u64);
+
+        let mut accesses: Vec<(usize, u64, u32, u32)> = vec![
+            (SLOT_FETCH, word_cell(pc), word, word),
+            (SLOT_RS1, register_cell(rs1), x, x),
+            (SLOT_RS2, register_cell(rs2), rs2_value, rs2_value),
+        ];
+        let result = match op {
+            Op::Lui => imm,
+            Op::Auipc => {
+                row.add(Z_BITS, CARRY, pc as u64 + imm as u64);
+                pc.wrapping_add(imm)
+            }
+            Op::Jal => {
+                row.set(TARGET_CARRY, (pc as u64 + imm as u64) >> 32);
+                pc.wrapping_add(4)
+            }
+            Op::Jalr => {
+                let target = x as u64 + imm as u64;
+                row.set(LOW_BIT, target & 1);
+                row.set(TARGET_CARRY, target >> 32);
+                pc.wrapping_add(4)
+            }
+            Op::Branch(cond) => {
+                let taken = cond.holds(x, y);
+                match cond.funct3() >> 1 {
+                    0 => {
+                        row.set(ZERO, (x == y) as u64);
+                        row.felt(
+                            ZERO_INV,
+                            inverse(Felt::from(x as u64) - Felt::from(y as u64)),
+                        );
+                    }
+                    2 => row.subtract(x, y, (x as i32) < (y as i32)),
+                    _ => row.subtract(x, y, x < y),
+                }
+                if taken {
+                    row.set(TARGET_CARRY, (pc as u64 + imm as u64) >> 32);
+                }
+                0
+            }
+            Op::Load(_) | Op::Store(_) => {
+                let address = address.expect("loads and stores have an address");
+                row.add(Z_BITS, CARRY, x as u64 + imm as u64);
+                row.set(OFFSET + (address % 4) as usize, 1);
+                row.bits(V_BITS, old_word as u64, 32);
+                let new_word = vm.memory().load(address & !3, Width::Word)?;
+                row.set(MEM_NEW, new_word as u64);
+                accesses.push((SLOT_MEMORY, word_cell(address), old_word, new_word));
+                if let Op::Store(_) = op {
+                    let page = address / PAGE_SIZE as u32;
+                    let written = !self.written_pages.insert(page);
+                    row.set(PAGE_OLD, written as u64);
+                    accesses.push((SLOT_PAGE, page_cell(page), written as u32, 1));
+                }
+                match op {
+                    Op::Load(op) => op.extend(vm.memory().load(address, op.width())?),
+                    _ => 0,
+                }
+            }
+            Op::AluImm(op) | Op::Alu(op) => {
+                row.alu(op, x, y);
+                op.apply(x, y)
+            }
+            Op::Fence => 0,
+            Op::Ecall => {
+                let exit = vm.exit_code().is_some();
+                row.set(EXIT, exit as u64);
+                if !exit {
+                    let call_gas = vm.gas_used() - gas - self.schedule.instruction(&instruction);
+                    row.set(CALL_GAS, call_gas);
+                    self.ecalls.push(Ecall {
+                        number: x,
+                        word: rs2_value,
+                        result: after[REG_A0 as usize],
+                        gas: call_gas,
+                    });
+                }
+                after[REG_A0 as usize]
+            }
+        };
+        row.set(RESULT, result as u64);
+
+        // The exit call continues to the next instruction, as far as the
+        // constraints go
+        let next_pc = if vm.exit_code().is_some() {
+            pc.wrapping_add(4)
+        } else {
+            vm.pc()
+        };
+        if !next_pc.is_multiple_of(4) || next_pc >= 1 << 31 {
+            return Err(unprovable("the pc leaves the aligned addresses below 2^31"));
+        }
+        row.bits(NEXT_PC_BITS, next_pc as u64 / 4, PC_BITS);
+
+        accesses.push((
+            SLOT_RD,
+            register_cell(rd),
+            before[rd as usize],
+            after[rd as usize],
+        ));
+        for (slot, cell, old, new) in accesses {
+            let timestamp = timestamp(cycle, slot);
+            let old_ts = self.access(cell, old, new, timestamp);
+            row.set(OLD_TS + slot, old_ts);
+            row.bits(
+                TS_DIFF_BITS + slot * TS_BITS,
+                timestamp - old_ts - 1,
+                TS_BITS,
+            );
+        }
+        Ok(row.0)
+    }
+
+    /// Access `cell`, which holds `old`, leaving `new`; returns the timestamp
+    /// of the previous access
+    fn access(&mut self, cell: u64, old: u32, new: u32, timestamp: u64) -> u64 {
+        let entry = self.cells.entry(cell).or_insert(Cell {
+            init: old,
+            value: old,
+            timestamp: 0,
+        });
+        assert_eq!(entry.value, old, "cell {cell:#x} out of sync with the VM");
+        let previous = entry.timestamp;
+        entry.value = new;
+        entry.timestamp = timestamp;
+        previous
+    }
+}
+
+/// A main trace row being filled in
+struct Row(Vec<Felt>);
+
+impl Row {
+    fn new() -> Self {
+        Self(vec![Felt::ZERO; MAIN_WIDTH])
+    }
+
+    fn set(&mut self, column: usize, value: u64) {
+        set(&mut self.0, column, value);
+    }
+
+    fn felt(&mut self, column: usize, value: Felt) {
+        self.0[column] = value;
+    }
+
+    fn bits(&mut self, column: usize, value: u64, count: usize) {
+        set_bits(&mut self.0, column, value, count);
+    }
+
+    /// Split a sum below 2^33 into 32 bits and a carry
+    fn add(&mut self, bits: usize, carry: usize, sum: u64) {
+        self.bits(bits, sum & 0xffff_ffff, 32);
+        self.set(carry, sum >> 32);
+    }
+
+    /// `z = x - y + 2^32 * borrow`
+    fn subtract(&mut self, x: u32, y: u32, borrow: bool) {
+        self.bits(Z_BITS, x.wrapping_sub(y) as u64, 32);
+        self.set(CARRY, borrow as u64);
+    }
+
+    /// Helper columns of an ALU operation on `x` and `y`
+    fn alu(&mut self, op: AluOp, x: u32, y: u32) {
+        let (xs, ys) = (x as i32 as i64, y as i32 as i64);
+        let product = |row: &mut Self, low: usize, high: usize, value: u64| {
+            row.bits(low, value & 0xffff_ffff, 32);
+            row.bits(high, value >> 32, 32);
+        };
+        match op {
+            AluOp::Add => self.add(Z_BITS, CARRY, x as u64 + y as u64),
+            AluOp::Sub | AluOp::Sltu => self.subtract(x, y, x < y),
+            AluOp::Slt => self.subtract(x, y, xs < ys),
+            AluOp::Xor | AluOp::Or | AluOp::And => {}
+            AluOp::Sll | AluOp::Srl | AluOp::Sra => self.set(SHIFT + (y & 0x1f) as usize, 1),
+            AluOp::Mul => product(self, Z_BITS, W_BITS, x as u64 * y as u64),
+            AluOp::Mulhu => product(self, W_BITS, Z_BITS, x as u64 * y as u64),
+            AluOp::Mulh | AluOp::Mulhsu => {
+                let value = if op == AluOp::Mulh {
+                    xs * ys
+                } else {
+                    xs * y as i64
+                };
+                product(self, W_BITS, Z_BITS, value as u64);
+                self.set(CARRY, (value < 0) as u64);
+            }
+            AluOp::Div | AluOp::Divu | AluOp::Rem | AluOp::Remu => {
+                let signed = matches!(op, AluOp::Div | AluOp::Rem);
+                let (quotient, remainder) = if signed {
+                    (AluOp::Div.apply(x, y), AluOp::Rem.apply(x, y))
+                } else {
+                    (AluOp::Divu.apply(x, y), AluOp::Remu.apply(x, y))
+                };
+                self.bits(W_BITS, quotient as u64, 32);
+                self.bits(V_BITS, remainder as u64, 32);
+                self.set(ZERO, (y == 0) as u64);
+                self.felt(ZERO_INV, inverse(Felt::from(y as u64)));
+                let (divisor, remainder_abs) = if signed {
+                    self.set(OVERFLOW, (x == 1 << 31 && y == u32::MAX) as u64);
+                    self.set(REM_ZERO, (remainder == 0) as u64);
+                    self.felt(REM_ZERO_INV, inverse(Felt::from(remainder as u64)));
+                    (ys.unsigned_abs(), (remainder as i32 as i64).unsigned_abs())
+                } else {
+                    (y as u64, remainder as u64)
+                };
+                let bound = divisor + ((y == 0) as u64) * (1 << 32) - remainder_abs - 1;
+                self.bits(Z_BITS, bound, 32);
+            }
+        }
+    }
+}
+
+fn set(row: &mut [Felt], column: usize, value: u64) {
+    row[column] = Felt::from(value);
+}
+
+fn set_bits(row: &mut [Felt], column: usize, value: u64, count: usize) {
+    debug_assert!(
+        count == 64 || value >> count == 0,
+        "{value} does not fit {count} bits"
+    );
+    for (i, bit) in row[column..column + count].iter_mut().enumerate() {
+        *bit = Felt::from((value >> i) & 1);
+    }
+}
+
+/// The inverse of `value`, or zero
+fn inverse(value: Felt) -> Felt {
+    value.invert().unwrap_or(Felt::ZERO)
+}
+
+/// The immediate of `word` as a two's complement `u32`
+fn immediate(word: u32, format: Option<Format>) -> u32 {
+    match format {
+        Some(Format::I) => ((word as i32) >> 20) as u32,
+        Some(Format::S) => s_immediate(word) as u32,
+        Some(Format::B) => b_immediate(word) as u32,
+        Some(Format::U) => word & 0xffff_f000,
+        Some(Format::J) => j_immediate(word) as u32,
+        None => 0,
+    }
+}
+
+fn unprovable(reason: &str) -> ZkvmError {
+    ZkvmError::Unprovable(reason.into())
+}
@@ -0,0 +1,832 @@
+//! Rows of the precompile units
+//!
+//! The `ecall`s of each call fill its [stream](crate::air::stream) rows.
+//! Running the call again on its words fills the rows of the
+//! [control](crate::air::control) unit, the [permutations](crate::air::permutation)
+//! they hash with and the [scalar multiplications](crate::air::scalar) of
+//! signatures and commitments, and recovers the virtual `y` coordinates of
+//! the points a call decodes.
+
+use pasta_curves::group::ff::{Field, PrimeField};
+use pasta_curves::group::Group;
+use pasta_curves::pallas;
+
+use super::{inverse, set, set_bits};
+use crate::air::control::{
+    self, MERKLE_POSITION, NOTE_ASSET, NOTE_COMMITMENT, NOTE_KEY, NOTE_NULLIFIER, NOTE_OWNER,
+    NOTE_POSITION, NOTE_RANDOMNESS, NOTE_ROWS, NOTE_Y, PEDERSEN_BLINDING, PEDERSEN_OUTPUT,
+    PEDERSEN_Y, SIGNATURE_OUTPUT, SIGNATURE_Y,
+};
+use crate::air::permutation::{self, Constants, WIDTH};
+use crate::air::stream::{CODE_CHECK, CODE_MOD_Q, CODE_OUTPUT, CODE_POINT, CODE_VIRTUAL};
+use crate::air::{scalar, stream, PERIOD};
+use crate::field::{pow2, Felt};
+use crate::precompile::{
+    self, ELEMENT_WORDS, MERKLE_DEPTH, SYS_MERKLE_VERIFY, SYS_NOTE_NULLIFIER, SYS_PEDERSEN_COMMIT,
+    SYS_POSEIDON, SYS_SIGNATURE_VERIFY,
+};
+
+/// Rows of a scalar multiplication per bit
+const WORD_BITS: usize = 32;
+
+/// A precompile `ecall`: the word the guest passes in `a0`, the one it gets
+/// back and the gas it is charged beyond the instruction
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Ecall {
+    pub number: u32,
+    pub word: u32,
+    pub result: u32,
+    pub gas: u64,
+}
+
+/// A precompile call and the words of its stream
+struct Call {
+    number: u32,
+    param: u32,
+    /// `ecall`s before the call
+    ecall: usize,
+    gas: u64,
+    /// The words the guest passes and gets back, per `ecall`
+    ecalls: Vec<(u32, u32)>,
+    /// The header, inputs and outputs, then the virtual elements
+    words: Vec<u32>,
+    /// The header and inputs
+    inputs: usize,
+}
+
+/// The precompile calls of a run
+pub(crate) struct Calls {
+    calls: Vec<Call>,
+    permutation: Constants,
+    bounds: [[u32; ELEMENT_WORDS]; 2],
+}
+
+/// Eight words of a call's stream read as an element of the given code
+struct Element {
+    words: [u32; ELEMENT_WORDS],
+    code: u64,
+}
+
+impl Calls {
+    /// Group the precompile `ecall`s of a run, each call complete, into calls
+    pub fn new(ecalls: &[Ecall]) -> Self {
+        let mut calls: Vec<Call> = Vec::new();
+        let mut ecall = 0;
+        while ecall < ecalls.len() {
+            let header = ecalls[ecall];
+            let inputs = 1 + precompile::input_words(header.number, header.word)
+                .expect("the VM ran the call");
+            let len = inputs + precompile::output_words(header.number);
+            let stream = &ecalls[ecall..ecall + len];
+            let words = stream
+                .iter()
+                .enumerate()
+                .map(|(i, e)| if i < inputs { e.word } else { e.result })
+                .collect();
+            calls.push(Call {
+                number: header.number,
+                param: header.word,
+                ecall,
+                gas: header.gas,
+                ecalls: stream.iter().map(|e| (e.word, e.result)).collect(),
+                words,
+                inputs,
+            });
+            ecall += len;
+        }
+
+        let words = |bytes: [u8; 32]| precompile::split(&bytes).try_into().expect("eight words");
+        let mut calls = Self {
+            calls,
+            permutation: Constants::new(),
+            bounds: [
+                words((-Felt::ONE).to_repr()),
+                words((-pallas::Scalar::ONE).to_repr()),
+            ],
+        };
+        for call in &mut calls.calls {
+            // The `y` coordinates of the key and `R`, the commitment or the
+            // owner key
+            let points: &[u64] = match call.number {
+                SYS_SIGNATURE_VERIFY => &[1, 9],
+                SYS_PEDERSEN_COMMIT => &[PEDERSEN_OUTPUT],
+                SYS_NOTE_NULLIFIER => &[NOTE_OWNER],
+                _ => &[],
+            };
+            for &start in points {
+                let element = Element::new(&call.words, start as usize, CODE_CHECK | CODE_POINT);
+                let (_, y) = element.point();
+                call.words.extend(precompile::split(&y.to_repr()));
+            }
+        }
+        calls
+    }
+
+    /// Rows of the stream
+    pub fn stream_len(&self) -> usize {
+        self.calls.iter().map(|call| call.words.len()).sum()
+    }
+
+    /// Rows of the permutation blocks
+    pub fn permutation_len(&self) -> usize {
+        PERIOD * self.calls.iter().map(Call::permutations).sum::<usize>()
+    }
+
+    /// Rows of the scalar multiplications
+    pub fn scalar_len(&self) -> usize {
+        WORD_BITS * self.calls.iter().map(Call::scalar_words).sum::<usize>()
+    }
+
+    /// Rows of the control unit
+    pub fn control_len(&self) -> usize {
+        self.calls.iter().map(Call::control_rows).sum()
+    }
+
+    /// Fill the unit columns of `rows`, the rows the constraints hold on
+    pub fn fill(&self, rows: &mut [Vec<Felt>]) {
+        let mut cursor = Cursor::default();
+        for (index, call) in self.calls.iter().enumerate() {
+            self.stream(rows, &mut cursor, index, call);
+            match call.number {
+                SYS_POSEIDON => self.poseidon(rows, &mut cursor, index, call),
+                SYS_MERKLE_VERIFY => self.merkle(rows, &mut cursor, index, call),
+                SYS_SIGNATURE_VERIFY => self.signature(rows, &mut cursor, index, call),
+                SYS_PEDERSEN_COMMIT => self.pedersen(rows, &mut cursor, index, call),
+                SYS_NOTE_NULLIFIER => self.note(rows, &mut cursor, index, call),
+                _ => unreachable!("only precompile calls stream"),
+            }
+        }
+
+        // Rows past the units carry their counters; spare blocks permute zero
+        let ecalls = self
+            .calls
+            .last()
+            .map_or(0, |call| call.ecall + call.ecalls.len());
+        let last_call = self.calls.len().saturating_sub(1) as u64;
+        for row in &mut rows[cursor.stream..] {
+            set(row, stream::ECALL, ecalls as u64);
+            set(row, stream::CALL, last_call);
+        }
+        for row in &mut rows[cursor.control..] {
+            set(row, control::CALL, last_call);
+        }
+        let mut start = cursor.block * PERIOD;
+        while start < rows.len() {
+            let end = rows.len().min(start + PERIOD);
+            self.block(&mut rows[start..end], [Felt::ZERO; WIDTH], false);
+            start = end;
+        }
+    }
+
+    /// The stream rows of `call`, the `index`th
+    fn stream(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let (elements, singles) = layout(call.number, call.param);
+        let real = call.ecalls.len();
+        for (i, &word) in call.words.iter().enumerate() {
+            let row = &mut rows[cursor.stream + i];
+            let (passed, result) = call.ecalls.get(i).copied().unwrap_or((word, 0));
+            let output = i >= call.inputs && i < real;
+            set(row, stream::ACTIVE, 1);
+            set(row, stream::HEADER, (i == 0) as u64);
+            set(row, stream::SINGLE, singles.contains(&i) as u64);
+            set(row, stream::REAL, (i < real) as u64);
+            set(row, stream::OUTPUT, output as u64);
+            set(row, stream::ECALL, (call.ecall + i.min(real)) as u64);
+            set(row, stream::CALL, index as u64);
+            set(row, stream::INDEX, i as u64);
+            set(row, stream::NUMBER, call.number as u64);
+            set(row, stream::IN, passed as u64);
+            set(row, stream::OUT, result as u64);
+            set(row, stream::WORD, word as u64);
+            set_bits(row, stream::BITS, word as u64, 32);
+            if i == 0 {
+                set(row, stream::GAS, call.gas);
+            }
+        }
+
+        for &(start, code) in &elements {
+            let element = Element::new(&call.words, start, code);
+            let x = element.x();
+            let bound = &self.bounds[(code & CODE_MOD_Q != 0) as usize];
+            let (mut value, mut borrow) = (Felt::ZERO, false);
+            for k in 0..ELEMENT_WORDS {
+                let row = &mut rows[cursor.stream + start + k];
+                value += Felt::from(x[k] as u64) * pow2(32 * k as u32);
+                set(row, stream::ELEMENT, 1);
+                set(row, stream::POSITION + k, 1);
+                set(row, stream::CHECK, (code & CODE_CHECK != 0) as u64);
+                set(row, stream::MOD_Q, (code & CODE_MOD_Q != 0) as u64);
+                set(row, stream::POINT, (code & CODE_POINT != 0) as u64);
+                set(row, stream::PARITY, element.parity());
+                row[stream::VALUE] = value;
+                if k == ELEMENT_WORDS - 1 {
+                    set(row, stream::SIGN, element.sign() as u64);
+                }
+                if code & CODE_CHECK != 0 {
+                    let needed = x[k] as u64 + borrow as u64;
+                    let borrow_out = needed > bound[k] as u64;
+                    let difference = bound[k] as u64 + ((borrow_out as u64) << 32) - needed;
+                    set(row, stream::BORROW_IN, borrow as u64);
+                    set(row, stream::BORROW, borrow_out as u64);
+                    set_bits(row, stream::CHECK_BITS, difference, 32);
+                    borrow = borrow_out;
+                }
+            }
+        }
+        cursor.stream += call.words.len();
+    }
+
+    /// A Poseidon hash: a control row per permutation
+    fn poseidon(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let count = call.param as usize;
+        let inputs: Vec<Element> = (0..count)
+            .map(|e| Element::new(&call.words, 1 + ELEMENT_WORDS * e, CODE_CHECK))
+            .collect();
+        let mut state = [Felt::ZERO, Felt::ZERO, Felt::from(count as u64) * pow2(64)];
+        let permutations = call.permutations();
+        for j in 0..permutations {
+            let pair = 2 * j + 1 < count;
+            let last = j + 1 == permutations;
+            let row = &mut rows[cursor.control];
+            control_header(row, index, call, control::POSEIDON + j);
+            set(row, control::PAIR, pair as u64);
+            set(row, control::HASH_LAST, last as u64);
+            let a = &inputs[2 * j];
+            slot_a(row, 1 + 2 * ELEMENT_WORDS * j, a);
+            state[0] += a.value();
+            if pair {
+                let b = &inputs[2 * j + 1];
+                slot_b(row, b);
+                state[1] += b.value();
+            }
+            state = self.hash_row(rows, cursor, state);
+            if last {
+                let start = 1 + ELEMENT_WORDS * count;
+                let output = Element::new(&call.words, start, CODE_CHECK | CODE_OUTPUT);
+                debug_assert_eq!(output.value(), state[0]);
+                slot_c(&mut rows[cursor.control], start, &output, true);
+            }
+            cursor.control += 1;
+        }
+    }
+
+    /// A Merkle path check: a control row per level
+    fn merkle(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let leaf = Element::new(&call.words, 1, 0);
+        let root = Element::new(&call.words, 9, CODE_CHECK);
+        let position = call.words[MERKLE_POSITION as usize];
+        let top = pow2(MERKLE_DEPTH as u32 - 1);
+        let mut node = leaf.value();
+        let mut bits = 0u64;
+        for level in 0..MERKLE_DEPTH {
+            let flag = match level {
+                0 => control::PATH_FIRST,
+                _ if level + 1 == MERKLE_DEPTH => control::PATH_LAST,
+                _ => control::PATH,
+            };
+            let start = 17 + ELEMENT_WORDS * level;
+            let sibling = Element::new(&call.words, start, 0);
+            let bit = (position >> level) & 1 == 1;
+            bits |= (bit as u64) << level;
+            let power = pow2(level as u32);
+
+            let row = &mut rows[cursor.control];
+            control_header(row, index, call, flag);
+            slot_a(row, start, &sibling);
+            set(row, control::BIT, bit as u64);
+            row[control::NODE] = node;
+            row[control::POWER] = power;
+            row[control::POWER_INV] = inverse(power - top);
+            set(row, control::POSITION, bits);
+            row[control::ROOT] = root.value();
+            set(row, control::ROOT_VALID, root.valid(&self.bounds) as u64);
+            if level == 0 {
+                slot_b(row, &leaf);
+                slot_c(row, 9, &root, root.valid(&self.bounds));
+                set(
+                    row,
+                    control::WORDS,
+                    call.words[MERKLE_POSITION as usize + 1] as u64,
+                );
+            }
+            let (left, right) = if bit {
+                (sibling.value(), node)
+            } else {
+                (node, sibling.value())
+            };
+            let output = self.hash_row(rows, cursor, [left, right, Felt::from(2u64) * pow2(64)]);
+            node = output[0];
+
+            if level + 1 == MERKLE_DEPTH {
+                let row = &mut rows[cursor.control];
+                let equal = node == root.value();
+                let valid = root.valid(&self.bounds) && equal;
+                set(row, control::EQUAL, equal as u64);
+                row[control::ROOT_INV] = inverse(node - root.value());
+                set(row, control::WORDS, position as u64);
+                set(row, control::WORDS + 1, valid as u64);
+                debug_assert_eq!(call.words.last(), Some(&(valid as u32)));
+            }
+            cursor.control += 1;
+        }
+    }
+
+    /// A signature check: a row decoding the key and one decoding `R` and
+    /// comparing, with the scalar multiplications they look up
+    fn signature(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let key = Element::new(&call.words, 1, CODE_CHECK | CODE_POINT);
+        let nonce = Element::new(&call.words, 9, CODE_CHECK | CODE_POINT);
+        let response = Element::new(&call.words, 17, CODE_CHECK | CODE_MOD_Q);
+        let key_y = Element::new(&call.words, 1 + SIGNATURE_Y as usize, CODE_CHECK);
+        let nonce_y = Element::new(&call.words, 9 + SIGNATURE_Y as usize, CODE_CHECK);
+        let challenge_words = precompile::CHALLENGE_LEN / 4;
+
+        let generator = pallas::Point::generator();
+        let (gx, gy) = precompile::coordinates(&generator);
+        let response_base = self.chain(rows, cursor, index, 17, &call.words[17..25], [gx, gy]);
+        let challenge_key = self.chain(
+            rows,
+            cursor,
+            index,
+            25,
+            &call.words[25..25 + challenge_words],
+            [key.value(), key_y.value()],
+        );
+
+        let key_valid = key.decodes(&self.bounds) && !key.identity(&self.bounds);
+        for (flag, point, y) in [
+            (control::KEY, &key, &key_y),
+            (control::SIGNATURE, &nonce, &nonce_y),
+        ] {
+            let row = &mut rows[cursor.control];
+            control_header(row, index, call, flag);
+            let start = if flag == control::KEY { 1 } else { 9 };
+            slot_a(row, start, point);
+            slot_b(row, y);
+            decode(row, point, y, &self.bounds);
+            row[control::CHALLENGE_KEY..control::CHALLENGE_KEY + 3].copy_from_slice(&challenge_key);
+            if flag == control::KEY {
+                row[control::CHAIN_Q] = key.value();
+                row[control::CHAIN_Q + 1] = key_y.value();
+            }
+            if flag == control::SIGNATURE {
+                set(row, control::KEY_VALID, key_valid as u64);
+                slot_c(row, 17, &response, response.valid(&self.bounds));
+                row[control::RESPONSE_BASE..control::RESPONSE_BASE + 3]
+                    .copy_from_slice(&response_base);
+                compare(row, response.valid(&self.bounds));
+                debug_assert_eq!(
+                    row[control::WORDS + 1],
+                    Felt::from(call.words[SIGNATURE_OUTPUT as usize] as u64)
+                );
+            }
+            cursor.control += 1;
+        }
+    }
+
+    /// A Pedersen commitment: a row decoding the output and comparing it
+    /// with the sum of the scalar multiplications it looks up
+    fn pedersen(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let (output_start, blinding_start) = (PEDERSEN_OUTPUT as usize, PEDERSEN_BLINDING as usize);
+        let output = Element::new(
+            &call.words,
+            output_start,
+            CODE_CHECK | CODE_POINT | CODE_OUTPUT,
+        );
+        let y = Element::new(&call.words, output_start + PEDERSEN_Y as usize, CODE_CHECK);
+        let blinding = Element::new(&call.words, blinding_start, CODE_CHECK | CODE_MOD_Q);
+
+        let (gx, gy) = precompile::coordinates(&pallas::Point::generator());
+        let value_base = self.chain(rows, cursor, index, 1, &call.words[1..3], [gx, gy]);
+        let (hx, hy) = precompile::coordinates(&precompile::blinding_base());
+        let blinding_end = blinding_start + ELEMENT_WORDS;
+        let blinding_base = self.chain(
+            rows,
+            cursor,
+            index,
+            blinding_start,
+            &call.words[blinding_start..blinding_end],
+            [hx, hy],
+        );
+
+        let row = &mut rows[cursor.control];
+        control_header(row, index, call, control::PEDERSEN);
+        slot_a(row, output_start, &output);
+        slot_b(row, &y);
+        decode(row, &output, &y, &self.bounds);
+        slot_c(row, blinding_start, &blinding, true);
+        row[control::RESPONSE_BASE..control::RESPONSE_BASE + 3].copy_from_slice(&value_base);
+        row[control::CHALLENGE_KEY..control::CHALLENGE_KEY + 3].copy_from_slice(&blinding_base);
+        row[control::CHAIN_Q] = hx;
+        row[control::CHAIN_Q + 1] = hy;
+        let sum = add(row, &value_base, &blinding_base);
+        debug_assert_eq!(
+            sum[0] * (Felt::ONE - row[control::IDENTITY]),
+            output.value() * sum[2]
+        );
+        cursor.control += 1;
+    }
+
+    /// A note commitment and nullifier: a control row per permutation
+    fn note(&self, rows: &mut [Vec<Felt>], cursor: &mut Cursor, index: usize, call: &Call) {
+        let element = |start: u64, code: u64| Element::new(&call.words, start as usize, code);
+        let pair = |start: u64| {
+            let [low, high] = [0, 1].map(|k| call.words[start as usize + k] as u64);
+            Felt::from(low) + pow2(32) * Felt::from(high)
+        };
+        let asset = element(NOTE_ASSET, 0);
+        let owner = element(NOTE_OWNER, CODE_CHECK | CODE_POINT);
+        let owner_y = element(NOTE_OWNER + NOTE_Y, CODE_CHECK);
+        let randomness = element(NOTE_RANDOMNESS, CODE_CHECK | CODE_MOD_Q);
+        let key = element(NOTE_KEY, CODE_CHECK | CODE_MOD_Q);
+        let commitment = element(NOTE_COMMITMENT, CODE_CHECK | CODE_OUTPUT);
+        let nullifier = element(NOTE_NULLIFIER, CODE_CHECK | CODE_OUTPUT);
+
+        let capacity = pow2(64);
+        let mut state = [pair(1), asset.value(), Felt::from(5u64) * capacity];
+        for i in 0..NOTE_ROWS {
+            let row = &mut rows[cursor.control];
+            control_header(row, index, call, control::NOTE + i);
+            match i {
+                0 => {
+                    slot_a(row, NOTE_OWNER as usize, &owner);
+                    slot_b(row, &owner_y);
+                    decode(row, &owner, &owner_y, &self.bounds);
+                    slot_c(row, NOTE_ASSET as usize, &asset, true);
+                    set(row, control::WORDS, call.words[1] as u64);
+                    set(row, control::WORDS + 1, call.words[2] as u64);
+                }
+                1 => slot_a(row, NOTE_RANDOMNESS as usize, &randomness),
+                2 => {
+                    slot_a(row, NOTE_KEY as usize, &key);
+                    slot_c(row, NOTE_COMMITMENT as usize, &commitment, true);
+                }
+                3 => {
+                    let position = NOTE_POSITION as usize;
+                    set(row, control::WORDS, call.words[position] as u64);
+                    set(row, control::WORDS + 1, call.words[position + 1] as u64);
+                }
+                _ => slot_a(row, NOTE_NULLIFIER as usize, &nullifier),
+            }
+            let output = self.hash_row(rows, cursor, state);
+            state = match i {
+                0 => [
+                    output[0] + owner.value(),
+                    output[1] + owner_y.value(),
+                    output[2],
+                ],
+                1 => [output[0] + randomness.value(), output[1], output[2]],
+                2 => {
+                    debug_assert_eq!(output[0], commitment.value());
+                    [key.value(), output[0], Felt::from(3u64) * capacity]
+                }
+                3 => [output[0] + pair(NOTE_POSITION), output[1], output[2]],
+                _ => {
+                    debug_assert_eq!(output[0], nullifier.value());
+                    output
+                }
+            };
+            cursor.control += 1;
+        }
+    }
+
+    /// The scalar multiplication of `q` by `words`, the stream words from
+    /// index `low` on, most significant last; returns the product
+    fn chain(
+        &self,
+        rows: &mut [Vec<Felt>],
+        cursor: &mut Cursor,
+        index: usize,
+        low: usize,
+        words: &[u32],
+        q: [Felt; 2],
+    ) -> [Felt; 3] {
+        let high = low + words.len() - 1;
+        let total = words.len() * WORD_BITS;
+        let mut acc = [Felt::ZERO, Felt::ONE, Felt::ZERO];
+        for (r, row) in rows[cursor.scalar..cursor.scalar + total]
+            .iter_mut()
+            .enumerate()
+        {
+            let word_index = high - r / WORD_BITS;
+            let word = words[word_index - low];
+            let shift = WORD_BITS - 1 - r % WORD_BITS;
+            let bit = (word >> shift) & 1 == 1;
+            let step = scalar::step(&acc, q, bit);
+            set(row, scalar::ACTIVE, 1);
+            set(row, scalar::START, (r == 0) as u64);
+            set(row, scalar::FINISH, (r + 1 == total) as u64);
+            set(row, scalar::BIT, bit as u64);
+            set(row, scalar::CALL, index as u64);
+            set(row, scalar::HIGH, high as u64);
+            set(row, scalar::INDEX, word_index as u64);
+            row[scalar::QX] = q[0];
+            row[scalar::QY] = q[1];
+            set(row, scalar::REM, (word >> shift) as u64);
+            row[scalar::ACC..scalar::ACC + 3].copy_from_slice(&acc);
+            row[scalar::SQUARES..scalar::SQUARES + 2].copy_from_slice(&step.squares);
+            row[scalar::DOUBLE..scalar::DOUBLE + 3].copy_from_slice(&step.double);
+            row[scalar::PRODUCTS..scalar::PRODUCTS + 5].copy_from_slice(&step.products);
+            row[scalar::OUT..scalar::OUT + 3].copy_from_slice(&step.out);
+            acc = step.out;
+        }
+        cursor.scalar += total;
+        acc
+    }
+
+    /// Permute `input` on the control row at the cursor and in the next
+    /// block; returns the output
+    fn hash_row(
+        &self,
+        rows: &mut [Vec<Felt>],
+        cursor: &mut Cursor,
+        input: [Felt; WIDTH],
+    ) -> [Felt; WIDTH] {
+        let start = cursor.block * PERIOD;
+        let output = self.block(&mut rows[start..start + PERIOD], input, true);
+        let row = &mut rows[cursor.control];
+        row[control::PERMUTATION_IN..control::PERMUTATION_IN + WIDTH].copy_from_slice(&input);
+        row[control::PERMUTATION_OUT..control::PERMUTATION_OUT + WIDTH].copy_from_slice(&output);
+        cursor.block += 1;
+        output
+    }
+
+    /// Fill the rows of a permutation block, or the part of one that fits;
+    /// returns the output
+    fn block(&self, rows: &mut [Vec<Felt>], input: [Felt; WIDTH], used: bool) -> [Felt; WIDTH] {
+        let mut state = input;
+        for (round, row) in rows.iter_mut().enumerate() {
+            let values = self.permutation.round(round, &state);
+            row[permutation::STATE..permutation::STATE + WIDTH].copy_from_slice(&state);
+            row[permutation::SQUARE..permutation::SQUARE + WIDTH].copy_from_slice(&values.square);
+            row[permutation::SBOX..permutation::SBOX + WIDTH].copy_from_slice(&values.sbox);
+            row[permutation::INPUT..permutation::INPUT + WIDTH].copy_from_slice(&input);
+            set(row, permutation::USED, used as u64);
+            state = values.output;
+        }
+        state
+    }
+}
+
+impl Call {
+    fn permutations(&self) -> usize {
+        match self.number {
+            SYS_POSEIDON => (self.param as usize).div_ceil(2),
+            SYS_MERKLE_VERIFY => MERKLE_DEPTH,
+            SYS_NOTE_NULLIFIER => NOTE_ROWS,
+            _ => 0,
+        }
+    }
+
+    fn control_rows(&self) -> usize {
+        match self.number {
+            SYS_SIGNATURE_VERIFY => 2,
+            SYS_PEDERSEN_COMMIT => 1,
+            _ => self.permutations(),
+        }
+    }
+
+    /// Words of the scalars the call multiplies by
+    fn scalar_words(&self) -> usize {
+        match self.number {
+            SYS_SIGNATURE_VERIFY => ELEMENT_WORDS + precompile::CHALLENGE_LEN / 4,
+            SYS_PEDERSEN_COMMIT => 2 + ELEMENT_WORDS,
+            _ => 0,
+        }
+    }
+}
+
+impl Element {
+    fn new(words: &[u32], start: usize, code: u64) -> Self {
+        Self {
+            words: words[start..start + ELEMENT_WORDS]
+                .try_into()
+                .expect("eight words"),
+            code,
+        }
+    }
+
+    fn sign(&self) -> bool {
+        self.code & CODE_POINT != 0 && self.words[ELEMENT_WORDS - 1] >> 31 == 1
+    }
+
+    /// The words without the sign
+    fn x(&self) -> [u32; ELEMENT_WORDS] {
+        let mut words = self.words;
+        words[ELEMENT_WORDS - 1] &= !((self.sign() as u32) << 31);
+        words
+    }
+
+    /// The value of the words without the sign, reduced modulo `p`
+    fn value(&self) -> Felt {
+        self.x()
+            .iter()
+            .enumerate()
+            .map(|(k, word)| Felt::from(*word as u64) * pow2(32 * k as u32))
+            .sum()
+    }
+
+    fn parity(&self) -> u64 {
+        self.words[0] as u64 & 1
+    }
+
+    /// Whether the value is at most `p - 1`, or `q - 1` for a scalar, if
+    /// checked
+    fn valid(&self, bounds: &[[u32; ELEMENT_WORDS]; 2]) -> bool {
+        if self.code & CODE_CHECK == 0 {
+            return true;
+        }
+        let bound = &bounds[(self.code & CODE_MOD_Q != 0) as usize];
+        self.x().iter().rev().cmp(bound.iter().rev()) != std::cmp::Ordering::Greater
+    }
+
+    /// The point the element encodes, and its affine coordinates, `(0, 0)`
+    /// if it encodes none or the identity
+    fn point(&self) -> (Felt, Felt) {
+        let bytes = precompile::element(&self.words);
+        precompile::point(&bytes)
+            .map(|point| precompile::coordinates(&point))
+            .unwrap_or_default()
+    }
+
+    fn decodes(&self, bounds: &[[u32; ELEMENT_WORDS]; 2]) -> bool {
+        self.valid(bounds) && precompile::point(&precompile::element(&self.words)).is_some()
+    }
+
+    fn identity(&self, bounds: &[[u32; ELEMENT_WORDS]; 2]) -> bool {
+        self.valid(bounds) && self.value() == Felt::ZERO && !self.sign()
+    }
+}
+
+/// Where the next rows of each unit go
+#[derive(Default)]
+struct Cursor {
+    stream: usize,
+    block: usize,
+    scalar: usize,
+    control: usize,
+}
+
+/// The elements of a call's stream, as `(first index, code)`, and the
+/// indices of its single words
+fn layout(number: u32, param: u32) -> (Vec<(usize, u64)>, Vec<usize>) {
+    let element = |e: usize| 1 + ELEMENT_WORDS * e;
+    match number {
+        SYS_POSEIDON => {
+            let count = param as usize;
+            let mut elements: Vec<(usize, u64)> =
+                (0..count).map(|e| (element(e), CODE_CHECK)).collect();
+            elements.push((element(count), CODE_CHECK | CODE_OUTPUT));
+            (elements, Vec::new())
+        }
+        SYS_MERKLE_VERIFY => {
+            let mut elements = vec![(element(0), 0), (element(1), CODE_CHECK)];
+            elements.extend((0..MERKLE_DEPTH).map(|level| (element(2 + level), 0)));
+            let position = MERKLE_POSITION as usize;
+            (elements, vec![position, position + 1, position + 2])
+        }
+        SYS_SIGNATURE_VERIFY => {
+            let point = CODE_CHECK | CODE_POINT;
+            let virtual_y = CODE_CHECK | CODE_VIRTUAL;
+            let y = SIGNATURE_Y as usize;
+            let elements = vec![
+                (element(0), point),
+                (element(1), point),
+                (element(2), CODE_CHECK | CODE_MOD_Q),
+                (element(0) + y, virtual_y),
+                (element(1) + y, virtual_y),
+            ];
+            (elements, (element(2)..=SIGNATURE_OUTPUT as usize).collect())
+        }
+        SYS_PEDERSEN_COMMIT => {
+            let output = PEDERSEN_OUTPUT as usize;
+            let elements = vec![
+                (PEDERSEN_BLINDING as usize, CODE_CHECK | CODE_MOD_Q),
+                (output, CODE_CHECK | CODE_POINT | CODE_OUTPUT),
+                (output + PEDERSEN_Y as usize, CODE_CHECK | CODE_VIRTUAL),
+            ];
+            (elements, (1..output).collect())
+        }
+        SYS_NOTE_NULLIFIER => {
+            let scalar = CODE_CHECK | CODE_MOD_Q;
+            let output = CODE_CHECK | CODE_OUTPUT;
+            let elements = [
+                (NOTE_ASSET, 0),
+                (NOTE_OWNER, CODE_CHECK | CODE_POINT),
+                (NOTE_RANDOMNESS, scalar),
+                (NOTE_KEY, scalar),
+                (NOTE_COMMITMENT, output),
+                (NOTE_NULLIFIER, output),
+                (NOTE_OWNER + NOTE_Y, CODE_CHECK | CODE_VIRTUAL),
+            ]
+            .map(|(start, code)| (start as usize, code))
+            .to_vec();
+            let position = NOTE_POSITION as usize;
+            (elements, vec![1, 2, position, position + 1])
+        }
+        _ => unreachable!("only precompile calls stream"),
+    }
+}
+
+/// The flag, call and parameter of a control row
+fn control_header(row: &mut [Felt], index: usize, call: &Call, flag: usize) {
+    set(row, flag, 1);
+    set(row, control::CALL, index as u64);
+    set(row, control::PARAM, call.param as u64);
+}
+
+fn slot_a(row: &mut [Felt], index: usize, element: &Element) {
+    set(row, control::A, index as u64);
+    row[control::A + 1] = element.value();
+    set(row, control::A + 2, 1);
+    set(row, control::A + 3, element.sign() as u64);
+    set(row, control::A + 4, element.parity());
+}
+
+fn slot_b(row: &mut [Felt], element: &Element) {
+    row[control::B] = element.value();
+    set(row, control::B + 1, 1);
+    set(row, control::B + 2, element.parity());
+}
+
+fn slot_c(row: &mut [Felt], index: usize, element: &Element, valid: bool) {
+    set(row, control::C, index as u64);
+    row[control::C + 1] = element.value();
+    set(row, control::C + 2, valid as u64);
+    set(row, control::C + 3, element.parity());
+}
+
+/// The columns decoding the point in slot `A`, with `y` in slot `B`
+fn decode(row: &mut [Felt], point: &Element, y: &Element, bounds: &[[u32; ELEMENT_WORDS]; 2]) {
+    let x = point.value();
+    let valid = point.valid(bounds);
+    let decodes = point.decodes(bounds);
+    let identity = point.identity(bounds);
+    set(row, control::A + 2, valid as u64);
+    row[control::X_SQUARE] = x.square();
+    row[control::X_INV] = inverse(x);
+    set(row, control::X_ZERO, (x == Felt::ZERO) as u64);
+    set(row, control::IDENTITY, identity as u64);
+    set(row, control::DECODES, decodes as u64);
+    let not_on_curve = valid && !decodes;
+    set(row, control::NOT_ON_CURVE, not_on_curve as u64);
+    if not_on_curve {
+        let five = Felt::from(5u64);
+        let root = (five * (x.square() * x + five))
+            .sqrt()
+            .expect("5 (x^3 + 5) is a square where x^3 + 5 is not");
+        row[control::ROOT_5] = root;
+    }
+    debug_assert!(!decodes || identity || y.value().square() == x.square() * x + Felt::from(5u64));
+}
+
+/// The columns adding `R` to `[c] K` and comparing with `[s] G`, and the
+/// output
+fn compare(row: &mut [Felt], response_valid: bool) {
+    let identity = row[control::IDENTITY];
+    let nonce = [
+        row[control::A + 1],
+        row[control::B] + identity,
+        Felt::ONE - identity,
+    ];
+    let key: [Felt; 3] = std::array::from_fn(|i| row[control::CHALLENGE_KEY + i]);
+    let sum = add(row, &nonce, &key);
+    let base: [Felt; 3] = std::array::from_fn(|i| row[control::RESPONSE_BASE + i]);
+    let differences = [
+        sum[0] * base[2] - base[0] * sum[2],
+        sum[1] * base[2] - base[1] * sum[2],
+    ];
+    let mut equal = true;
+    for (i, (difference, column)) in differences
+        .iter()
+        .zip([control::EQUAL_X, control::EQUAL_Y])
+        .enumerate()
+    {
+        row[control::DIFFERENCES + i] = *difference;
+        row[control::DIFFERENCE_INVS + i] = inverse(*difference);
+        set(row, column, (*difference == Felt::ZERO) as u64);
+        equal &= *difference == Felt::ZERO;
+    }
+    let decoded = row[control::KEY_VALID] * row[control::DECODES];
+    let checked = Felt::from((response_valid && equal) as u64);
+    row[control::PARTS] = decoded;
+    row[control::PARTS + 1] = checked;
+    row[control::WORDS + 1] = decoded * checked;
+}
+
+/// The columns adding the projective `p` and `q`; returns the sum
+fn add(row: &mut [Felt], p: &[Felt; 3], q: &[Felt; 3]) -> [Felt; 3] {
+    let products = [
+        p[0] * q[0],
+        p[1] * q[1],
+        p[2] * q[2],
+        p[0] * q[1] + q[0] * p[1],
+        p[1] * q[2] + q[1] * p[2],
+        p[0] * q[2] + q[0] * p[2],
+    ];
+    row[control::PRODUCTS..control::PRODUCTS + 6].copy_from_slice(&products);
+    let [m, n, t, e, f, g] = products;
+    let sum = scalar::complete_sum(m, n, t, e, f, g);
+    row[control::SUM..control::SUM + 3].copy_from_slice(&sum);
+    sum
+}